@@ -0,0 +1,62 @@
+//! String-heavy interpreter workloads.
+//!
+//! Run with `cargo bench --bench strings`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use loft::parser::{InputStream, Parser};
+use loft::runtime::Interpreter;
+
+fn run(source: &str) {
+    let source = source.to_string();
+    let stmts = Parser::new(InputStream::new("bench", &source))
+        .parse()
+        .expect("benchmark program should parse");
+    Interpreter::new()
+        .eval_program(stmts)
+        .expect("benchmark program should run");
+}
+
+/// Pass a large string through variables and function calls
+const COPY_LOOP: &str = r#"
+let text = "0123456789".repeat(1000);
+fn echo(s: str) -> str {
+    return s;
+}
+let mut total = 0;
+let mut i = 0;
+while (i < 1000) {
+    let copy = echo(text);
+    total = total + copy.length();
+    i = i + 1;
+}
+"#;
+
+/// Build a string piece by piece and slice it as it grows
+const CONCAT_LOOP: &str = r#"
+let mut out = "";
+let mut i = 0;
+while (i < 500) {
+    out = out + "ab";
+    let head = out.substring(0, 2);
+    i = i + 1;
+}
+"#;
+
+/// Evaluate the same string literals over and over
+const LITERAL_LOOP: &str = r#"
+let mut i = 0;
+let mut last = "";
+while (i < 2000) {
+    last = "a fairly long string literal that is evaluated on every iteration";
+    i = i + 1;
+}
+"#;
+
+fn strings(c: &mut Criterion) {
+    c.bench_function("copy_loop", |b| b.iter(|| run(COPY_LOOP)));
+    c.bench_function("concat_loop", |b| b.iter(|| run(CONCAT_LOOP)));
+    c.bench_function("literal_loop", |b| b.iter(|| run(LITERAL_LOOP)));
+}
+
+criterion_group!(benches, strings);
+criterion_main!(benches);
@@ -1,130 +1,329 @@
-use std::collections::HashMap;
+//! Generates the description of the standard library that the language
+//! server, `loft check` and `loft doc` read.
+//!
+//! `src/lsp/stdlib_types.json` describes the modules and types themselves,
+//! plus what isn't written as a `#[loft_builtin]` function: constants,
+//! traits, aliases and methods the interpreter implements. Every function
+//! marked `#[loft_builtin(owner.name(params) -> type)]` under
+//! `src/runtime/builtins` is added to it, documented by its doc comment, and
+//! the result is written to `$OUT_DIR/stdlib_types.json`.
+
+#[path = "loft_builtin_macros/src/signature.rs"]
+mod signature;
+
+use serde_json::{json, Map, Value};
+use signature::Signature;
 use std::fs;
 use std::path::Path;
-use syn::{Attribute, Item, ItemFn, Lit, Meta};
+use syn::{Attribute, FnArg, GenericArgument, Item, ItemFn, Lit, Meta, Pat, PathArguments, Type};
 
-type BuiltinInfo = HashMap<String, HashMap<String, serde_json::Value>>;
+const BUILTINS_DIR: &str = "src/runtime/builtins";
+const BASE_JSON: &str = "src/lsp/stdlib_types.json";
+
+/// A builtin function found in the sources
+struct Builtin {
+    signature: Signature,
+    params: Vec<String>,
+    return_type: String,
+    documentation: String,
+    /// Whether it's called on a value, like `set.insert(1)`, rather than
+    /// on its module or type, like `Set.new()`
+    on_value: bool,
+    /// Where it's defined, for errors
+    location: String,
+}
 
-fn extract_doc_comments(attrs: &[Attribute]) -> String {
-    attrs
+/// The text of the doc comment, as Markdown. Lines of a paragraph are
+/// joined, while code blocks, lists and headings keep their lines.
+fn doc_comment(attrs: &[Attribute]) -> String {
+    let lines: Vec<String> = attrs
         .iter()
-        .filter_map(|attr| {
-            if attr.path().is_ident("doc") {
-                if let Meta::NameValue(meta) = &attr.meta {
-                    if let syn::Expr::Lit(expr_lit) = &meta.value {
-                        if let Lit::Str(lit_str) = &expr_lit.lit {
-                            return Some(lit_str.value());
-                        }
-                    }
-                }
-            }
-            None
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(meta) => match &meta.value {
+                syn::Expr::Lit(syn::ExprLit {
+                    lit: Lit::Str(text),
+                    ..
+                }) => Some(text.value()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .map(|line| {
+            let line = line.strip_prefix(' ').unwrap_or(&line);
+            line.trim_end().to_string()
         })
-        .collect::<Vec<_>>()
-        .join("\n")
-        .trim()
-        .to_string()
+        .collect();
+
+    let mut text = String::new();
+    let mut in_code = false;
+    let mut joinable = false;
+    for line in &lines {
+        let fence = line.trim_start().starts_with("```");
+        let own_line = in_code
+            || fence
+            || line.is_empty()
+            || line.starts_with('#')
+            || line.starts_with("- ")
+            || line.starts_with("* ")
+            || line.starts_with(' ');
+        if joinable && !own_line {
+            text.push(' ');
+        } else if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(line);
+        if fence {
+            in_code = !in_code;
+        }
+        joinable = !own_line;
+    }
+    text.trim().to_string()
 }
 
-fn extract_loft_builtin_attr(attrs: &[Attribute]) -> Option<String> {
-    for attr in attrs {
-        if attr.path().is_ident("loft_builtin") {
-            if let Meta::List(meta_list) = &attr.meta {
-                let tokens = meta_list.tokens.to_string();
-                return Some(tokens);
-            }
+fn builtin_attribute(attrs: &[Attribute]) -> Option<String> {
+    attrs.iter().find_map(|attr| match &attr.meta {
+        Meta::List(list) if attr.path().is_ident("loft_builtin") => Some(list.tokens.to_string()),
+        _ => None,
+    })
+}
+
+fn is_value_ref(ty: &Type) -> bool {
+    matches!(ty, Type::Reference(reference) if type_name(&reference.elem).as_deref() == Some("Value"))
+}
+
+fn is_args_slice(ty: &Type) -> bool {
+    match ty {
+        Type::Reference(reference) => {
+            matches!(&*reference.elem, Type::Slice(slice) if type_name(&slice.elem).as_deref() == Some("Value"))
         }
+        _ => false,
     }
-    None
 }
 
-fn parse_builtin_path(path: &str) -> (String, String) {
-    // Parse paths like "web.request", "math.sin", etc.
-    // Remove any whitespace first
-    let path = path.trim();
-    let parts: Vec<&str> = path.split('.').collect();
-    if parts.len() >= 2 {
-        (parts[0].trim().to_string(), parts[1].trim().to_string())
-    } else {
-        ("unknown".to_string(), path.to_string())
+/// The last segment of a type's path, e.g. `Vec` for `std::vec::Vec<T>`
+fn type_name(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string()),
+        _ => None,
     }
 }
 
-fn extract_function_signature(_func: &ItemFn) -> (Vec<String>, String) {
-    // This is a simplified extraction - in practice you'd want more robust parsing
-    // For now, we'll return basic info
-    let params = vec!["...args".to_string()]; // Placeholder
-    let return_type = "any".to_string(); // Placeholder
-    (params, return_type)
+fn type_argument(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let PathArguments::AngleBracketed(arguments) = &path.path.segments.last()?.arguments else {
+        return None;
+    };
+    arguments.args.iter().find_map(|argument| match argument {
+        GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    })
+}
+
+/// The loft type a Rust parameter or result converts to
+fn loft_type(ty: &Type) -> String {
+    if let Type::Reference(reference) = ty {
+        return loft_type(&reference.elem);
+    }
+    if let Type::Tuple(tuple) = ty {
+        if tuple.elems.is_empty() {
+            return "void".to_string();
+        }
+    }
+    match type_name(ty).unwrap_or_default().as_str() {
+        "f64" | "f32" | "i64" | "i32" | "u64" | "u32" | "u8" | "usize" | "isize" | "Decimal" => {
+            "num".to_string()
+        }
+        "String" | "str" => "str".to_string(),
+        "bool" => "bool".to_string(),
+        "Vec" => match type_argument(ty) {
+            Some(element) => format!("Array<{}>", loft_type(element)),
+            None => "Array".to_string(),
+        },
+        "Option" | "Result" | "RuntimeResult" => {
+            type_argument(ty).map_or_else(|| "any".to_string(), loft_type)
+        }
+        _ => "any".to_string(),
+    }
 }
 
-fn scan_rust_file(path: &Path, builtins: &mut BuiltinInfo) -> std::io::Result<()> {
-    let content = fs::read_to_string(path)?;
-    let syntax = match syn::parse_file(&content) {
-        Ok(s) => s,
-        Err(_) => return Ok(()), // Skip files that can't be parsed
+/// Read the signature of a function with plain Rust parameters
+fn typed_signature(func: &ItemFn) -> (Vec<String>, String) {
+    let params = func
+        .sig
+        .inputs
+        .iter()
+        .filter_map(|input| match input {
+            FnArg::Typed(param) if !is_value_ref(&param.ty) => Some(param),
+            _ => None,
+        })
+        .map(|param| {
+            let name = match &*param.pat {
+                Pat::Ident(ident) => ident.ident.to_string(),
+                _ => "arg".to_string(),
+            };
+            let name = name.strip_prefix('_').unwrap_or(&name).to_string();
+            if type_name(&param.ty).as_deref() == Some("Option") {
+                format!("{}?: {}", name, loft_type(&param.ty))
+            } else {
+                format!("{}: {}", name, loft_type(&param.ty))
+            }
+        })
+        .collect();
+    let return_type = match &func.sig.output {
+        syn::ReturnType::Default => "void".to_string(),
+        syn::ReturnType::Type(_, ty) => loft_type(ty),
     };
+    (params, return_type)
+}
 
-    for item in syntax.items {
-        if let Item::Fn(func) = item {
-            if let Some(path_str) = extract_loft_builtin_attr(&func.attrs) {
-                let doc = extract_doc_comments(&func.attrs);
-                let (module_name, method_name) = parse_builtin_path(&path_str);
-                let (_params, _return_type) = extract_function_signature(&func);
+fn builtin(func: &ItemFn, attribute: &str, location: String) -> Option<Builtin> {
+    let signature =
+        signature::parse(attribute).unwrap_or_else(|message| panic!("{}: {}", location, message));
+    let mut inputs = func.sig.inputs.iter().filter_map(|input| match input {
+        FnArg::Typed(param) => Some(param),
+        FnArg::Receiver(_) => None,
+    });
+    let is_typed = !func.sig.inputs.iter().any(|input| match input {
+        FnArg::Typed(param) => is_args_slice(&param.ty),
+        FnArg::Receiver(_) => false,
+    });
 
-                // Add to builtins structure
-                let module = builtins
-                    .entry(module_name.clone())
-                    .or_default();
+    let (params, return_type, on_value) = if is_typed {
+        let on_value = inputs.next().is_some_and(|param| is_value_ref(&param.ty));
+        let (params, return_type) = typed_signature(func);
+        (params, return_type, on_value)
+    } else {
+        // The receiver of a `fn(this: &Value, args: &[Value])` builtin goes
+        // unused when it isn't called on a value
+        let on_value = inputs.next().is_some_and(|param| {
+            !matches!(&*param.pat, Pat::Ident(ident) if ident.ident.to_string().starts_with('_'))
+        });
+        (Vec::new(), "any".to_string(), on_value)
+    };
 
-                let mut method_info = HashMap::new();
-                method_info.insert("documentation".to_string(), serde_json::Value::String(doc));
+    // Functions taking loft values only say what they take in the attribute
+    if !is_typed && signature.params.is_none() {
+        return None;
+    }
+    Some(Builtin {
+        params: signature.params.clone().unwrap_or(params),
+        return_type: match (&signature.params, &signature.return_type) {
+            (_, Some(return_type)) => return_type.clone(),
+            (Some(_), None) => "void".to_string(),
+            (None, None) => return_type,
+        },
+        signature,
+        documentation: doc_comment(&func.attrs),
+        on_value,
+        location,
+    })
+}
 
-                module.insert(method_name, serde_json::json!(method_info));
+fn scan_items(items: &[Item], location: &str, builtins: &mut Vec<Builtin>) {
+    for item in items {
+        match item {
+            Item::Fn(func) => {
+                if let Some(attribute) = builtin_attribute(&func.attrs) {
+                    let location = format!("{} ({})", location, func.sig.ident);
+                    builtins.extend(builtin(func, &attribute, location));
+                }
             }
+            Item::Mod(module) => {
+                let is_test = module.attrs.iter().any(|attr| attr.path().is_ident("cfg"));
+                if let (Some((_, items)), false) = (&module.content, is_test) {
+                    scan_items(items, location, builtins);
+                }
+            }
+            _ => {}
         }
     }
+}
 
-    Ok(())
+fn scan_directory(dir: &Path, builtins: &mut Vec<Builtin>) {
+    let mut entries: Vec<_> = fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("can't read {}: {}", dir.display(), e))
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            scan_directory(&path, builtins);
+        } else if path.extension().is_some_and(|extension| extension == "rs") {
+            let content = fs::read_to_string(&path).unwrap();
+            let file = syn::parse_file(&content)
+                .unwrap_or_else(|e| panic!("can't parse {}: {}", path.display(), e));
+            scan_items(&file.items, &path.display().to_string(), builtins);
+        }
+    }
 }
 
-fn scan_directory(dir: &Path, builtins: &mut BuiltinInfo) -> std::io::Result<()> {
-    if dir.is_dir() {
-        for entry in fs::read_dir(dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_dir() {
-                scan_directory(&path, builtins)?;
-            } else if path.extension().and_then(|s| s.to_str()) == Some("rs") {
-                let _ = scan_rust_file(&path, builtins);
-            }
+/// Add a builtin to the methods of what it belongs to
+fn place(types: &mut Value, builtin: Builtin) {
+    let owner = builtin.signature.owner.as_str();
+    let is_module = types["builtins"].get(owner).is_some();
+    let is_type = types["types"].get(owner).is_some();
+    let methods = match owner {
+        "str" => &mut types["string_methods"],
+        "Array" => &mut types["array_methods"],
+        _ if is_type && (builtin.on_value || !is_module) => {
+            ensure_methods(&mut types["types"][owner])
         }
+        _ if is_module => ensure_methods(&mut types["builtins"][owner]),
+        _ => panic!(
+            "{}: `{}` isn't a module or type described in {}",
+            builtin.location, owner, BASE_JSON
+        ),
+    };
+    let methods = methods.as_object_mut().unwrap();
+    let name = builtin.signature.name.clone();
+    if methods.contains_key(&name) {
+        panic!(
+            "{}: `{}.{}` is described twice",
+            builtin.location, owner, name
+        );
     }
-    Ok(())
+    methods.insert(
+        name,
+        json!({
+            "params": builtin.params,
+            "return_type": builtin.return_type,
+            "documentation": builtin.documentation,
+        }),
+    );
+}
+
+fn ensure_methods(entry: &mut Value) -> &mut Value {
+    entry
+        .as_object_mut()
+        .unwrap()
+        .entry("methods")
+        .or_insert_with(|| Value::Object(Map::new()))
 }
 
 fn main() {
-    println!("cargo:rerun-if-changed=src/runtime/builtins/");
+    println!("cargo:rerun-if-changed={}", BUILTINS_DIR);
+    println!("cargo:rerun-if-changed={}", BASE_JSON);
+    println!("cargo:rerun-if-changed=loft_builtin_macros/src/signature.rs");
     println!("cargo:rerun-if-changed=build.rs");
 
-    let mut builtins: BuiltinInfo = HashMap::new();
+    let base = fs::read_to_string(BASE_JSON).unwrap();
+    let mut types: Value =
+        serde_json::from_str(&base).unwrap_or_else(|e| panic!("can't parse {}: {}", BASE_JSON, e));
 
-    // Scan the builtins directory
-    let builtins_path = Path::new("src/runtime/builtins");
-    if let Err(e) = scan_directory(builtins_path, &mut builtins) {
-        eprintln!("Warning: Failed to scan builtins directory: {}", e);
+    let mut builtins = Vec::new();
+    scan_directory(Path::new(BUILTINS_DIR), &mut builtins);
+    for builtin in builtins {
+        place(&mut types, builtin);
     }
 
-    // Generate JSON
-    let json = serde_json::to_string_pretty(&builtins).unwrap_or_else(|_| "{}".to_string());
-
-    // Write to output file
     let out_dir = std::env::var("OUT_DIR").unwrap();
-    let dest_path = Path::new(&out_dir).join("builtins_generated.json");
-
-    if let Err(e) = fs::write(&dest_path, json) {
-        eprintln!("Warning: Failed to write builtins.json: {}", e);
-    } else {
-        println!("Generated builtins documentation at {:?}", dest_path);
-    }
+    let json = serde_json::to_string_pretty(&types).unwrap();
+    fs::write(Path::new(&out_dir).join("stdlib_types.json"), json).unwrap();
 }
@@ -0,0 +1,13 @@
+//! Feeds arbitrary bytes to the parser, which must return an error for
+//! anything it can't parse rather than panic or overflow the stack.
+//!
+//! Run it with `cargo fuzz run parse` from the repository root.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use loft::parser::Parser;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = Parser::parse_fuzz(data);
+});
@@ -1,6 +1,8 @@
+mod signature;
+
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{ItemFn, ItemStruct, ItemTrait, ItemImpl, Lit, Meta};
+use syn::{DeriveInput, ItemFn, ItemImpl, ItemStruct, ItemTrait, Lit, Meta};
 
 /// Procedural macro to generate builtin declarations for loft
 /// 
@@ -10,21 +12,52 @@ use syn::{ItemFn, ItemStruct, ItemTrait, ItemImpl, Lit, Meta};
 /// # Usage
 /// 
 /// ## For functions (generates builtin method wrappers):
-/// ```rust
+/// ```ignore
 /// #[loft_builtin(term.read)]
 /// fn read(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
 ///     // Implementation
 /// }
 /// ```
 /// 
+/// The loft signature can follow the name, which is how the language server
+/// and the documentation learn the parameters and return type; the build
+/// script reads it together with the doc comment:
+/// ```ignore
+/// use loft::loft_builtin;
+/// use loft::runtime::{value::Value, RuntimeResult};
+///
+/// /// Move the cursor to a column and row
+/// #[loft_builtin(term.move_to(column: num, row: num))]
+/// fn move_to(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+///     // Implementation
+/// }
+/// ```
+/// The name before the `.` is the module, or the type for methods called on
+/// values, such as `str`, `Array` or `DateTime`. Without `->`, the function
+/// returns `void`.
+///
+/// ## For functions with plain Rust parameters (generates conversion glue):
+/// ```ignore
+/// use loft::loft_builtin;
+///
+/// #[loft_builtin(host.add)]
+/// fn add(a: f64, b: f64) -> f64 {
+///     a + b
+/// }
+/// ```
+/// Arguments are converted with `FromValue` and the result with `IntoReturn`,
+/// from `loft::runtime::convert`. A leading `&Value` parameter receives the
+/// value the method is called on. Without a signature in the attribute, the
+/// one documented is read from the Rust parameters.
+/// 
 /// ## For structs (preserves struct definition):
-/// ```rust
+/// ```ignore
 /// #[loft_builtin(fs::file)]
 /// pub struct File(std::fs::File);
 /// ```
 /// 
 /// ## For impl blocks (implements traits or methods onto builtins):
-/// ```rust
+/// ```ignore
 /// #[loft_builtin(fs::file)]
 /// impl File {
 ///     // Methods will be available on File instances
@@ -32,7 +65,7 @@ use syn::{ItemFn, ItemStruct, ItemTrait, ItemImpl, Lit, Meta};
 /// ```
 /// 
 /// ## For traits (preserves trait definition with documentation):
-/// ```rust
+/// ```ignore
 /// #[loft_builtin(add)]
 /// /// Trait for addition operations
 /// trait Add<T> {
@@ -59,6 +92,108 @@ pub fn loft_builtin(attr: TokenStream, item: TokenStream) -> TokenStream {
     }
 }
 
+/// Derive `loft::runtime::convert::IntoValue` for a struct with named fields,
+/// turning it into a loft struct instance of the same name
+///
+/// ```ignore
+/// use loft::runtime::convert::{FromValue, IntoValue};
+///
+/// #[derive(IntoValue, FromValue)]
+/// struct Point {
+///     x: f64,
+///     y: f64,
+/// }
+/// ```
+#[proc_macro_derive(IntoValue)]
+pub fn derive_into_value(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    let fields = match named_fields(&input) {
+        Ok(fields) => fields,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let name = &input.ident;
+    let loft_name = name.to_string();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let field_names = fields.iter().map(|field| field.to_string());
+
+    let expanded = quote! {
+        impl #impl_generics ::loft::runtime::convert::IntoValue for #name #ty_generics #where_clause {
+            fn into_value(self) -> ::loft::runtime::value::Value {
+                let mut fields = ::std::collections::HashMap::new();
+                #(
+                    fields.insert(
+                        #field_names.to_string(),
+                        ::loft::runtime::convert::IntoValue::into_value(self.#fields),
+                    );
+                )*
+                ::loft::runtime::value::Value::Struct {
+                    name: #loft_name.to_string(),
+                    fields,
+                }
+            }
+        }
+    };
+    TokenStream::from(expanded)
+}
+
+/// Derive `loft::runtime::convert::FromValue` for a struct with named fields,
+/// reading each field from the loft struct instance or map of the same shape.
+/// Missing fields are read as `void`, so only `Option` fields may be left out.
+#[proc_macro_derive(FromValue)]
+pub fn derive_from_value(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    let fields = match named_fields(&input) {
+        Ok(fields) => fields,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let name = &input.ident;
+    let loft_name = name.to_string();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let field_names: Vec<String> = fields.iter().map(|field| field.to_string()).collect();
+
+    let expanded = quote! {
+        impl #impl_generics ::loft::runtime::convert::FromValue for #name #ty_generics #where_clause {
+            fn from_value(
+                value: &::loft::runtime::value::Value,
+            ) -> ::loft::runtime::RuntimeResult<Self> {
+                let field = |name: &str| ::loft::runtime::convert::field(value, #loft_name, name);
+                Ok(Self {
+                    #(
+                        #fields: ::loft::runtime::convert::FromValue::from_value(
+                            &field(#field_names)?,
+                        )
+                        .map_err(|e| {
+                            ::loft::runtime::RuntimeError::new(format!(
+                                "field '{}' of {}: {}",
+                                #field_names, #loft_name, e.message
+                            ))
+                        })?,
+                    )*
+                })
+            }
+        }
+    };
+    TokenStream::from(expanded)
+}
+
+/// The fields of a struct with named fields, or an error pointing at the item
+fn named_fields(input: &DeriveInput) -> syn::Result<Vec<syn::Ident>> {
+    match &input.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(fields),
+            ..
+        }) => Ok(fields
+            .named
+            .iter()
+            .filter_map(|field| field.ident.clone())
+            .collect()),
+        _ => Err(syn::Error::new_spanned(
+            &input.ident,
+            "FromValue and IntoValue can only be derived for structs with named fields",
+        )),
+    }
+}
+
 #[proc_macro_attribute]
 pub fn required(_attr: TokenStream, item: TokenStream) -> TokenStream {
     item
@@ -90,7 +225,38 @@ fn extract_doc_comments(attrs: &[syn::Attribute]) -> String {
         .to_string()
 }
 
-fn handle_function(_path: String, mut func: ItemFn) -> TokenStream {
+/// Whether a parameter is the `&[Value]` arguments of a raw builtin
+fn is_args_slice(ty: &syn::Type) -> bool {
+    matches!(ty, syn::Type::Reference(r) if matches!(&*r.elem, syn::Type::Slice(_)))
+}
+
+fn is_value_ref(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Reference(r) => match &*r.elem {
+            syn::Type::Path(p) => p.path.segments.last().is_some_and(|s| s.ident == "Value"),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+fn handle_function(path: String, mut func: ItemFn) -> TokenStream {
+    let signature = match signature::parse(&path) {
+        Ok(signature) => signature,
+        Err(message) => {
+            return syn::Error::new_spanned(&func.sig.ident, message)
+                .to_compile_error()
+                .into()
+        }
+    };
+    let is_typed = func.sig.inputs.iter().all(|arg| match arg {
+        syn::FnArg::Typed(pat_type) => !is_args_slice(&pat_type.ty),
+        syn::FnArg::Receiver(_) => false,
+    });
+    if is_typed {
+        return handle_typed_function(&signature.name, func);
+    }
+
     // Parse arguments and look for attributes
     let mut check_logic = Vec::new();
     let mut required_args = 0;
@@ -199,6 +365,66 @@ fn handle_function(_path: String, mut func: ItemFn) -> TokenStream {
     TokenStream::from(expanded)
 }
 
+/// Wrap a function with plain Rust parameters in a `BuiltinMethod` that
+/// converts its arguments and result
+/// `loft_name` is what loft code calls the function by, for errors
+fn handle_typed_function(loft_name: &str, func: ItemFn) -> TokenStream {
+    let fn_vis = &func.vis;
+    let fn_attrs = &func.attrs;
+    let fn_name = &func.sig.ident;
+
+    let mut inner_sig = func.sig.clone();
+    inner_sig.ident = syn::Ident::new("inner", fn_name.span());
+    let fn_block = &func.block;
+
+    let mut conversions = Vec::new();
+    let mut call_args = Vec::new();
+    let mut arg_count = 0usize;
+    for (position, arg) in func.sig.inputs.iter().enumerate() {
+        let syn::FnArg::Typed(pat_type) = arg else {
+            continue;
+        };
+        let ty = &pat_type.ty;
+        if position == 0 && is_value_ref(ty) {
+            call_args.push(quote! { this });
+            continue;
+        }
+        let ident = syn::Ident::new(&format!("arg{}", arg_count), fn_name.span());
+        let index = arg_count;
+        conversions.push(quote! {
+            let #ident = ::loft::runtime::convert::argument::<#ty>(#loft_name, args, #index)?;
+        });
+        call_args.push(quote! { #ident });
+        arg_count += 1;
+    }
+
+    let too_many = format!(
+        "{}() takes {} argument{} but {{}} were given",
+        loft_name,
+        arg_count,
+        if arg_count == 1 { "" } else { "s" }
+    );
+
+    let expanded = quote! {
+        #(#fn_attrs)*
+        #fn_vis fn #fn_name(
+            this: &::loft::runtime::value::Value,
+            args: &[::loft::runtime::value::Value],
+        ) -> ::loft::runtime::RuntimeResult<::loft::runtime::value::Value> {
+            #inner_sig #fn_block
+
+            let _ = this;
+            if args.len() > #arg_count {
+                return Err(::loft::runtime::RuntimeError::new(format!(#too_many, args.len())));
+            }
+            #(#conversions)*
+            ::loft::runtime::convert::IntoReturn::into_return(inner(#(#call_args),*))
+        }
+    };
+
+    TokenStream::from(expanded)
+}
+
 fn handle_struct(path: String, structure: ItemStruct) -> TokenStream {
     let struct_vis = &structure.vis;
     let struct_attrs = &structure.attrs;
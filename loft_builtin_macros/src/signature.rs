@@ -0,0 +1,121 @@
+//! Reading the arguments of `#[loft_builtin(...)]` on a function.
+//!
+//! The attribute names what the function is exposed as, and can give its
+//! loft signature: `term.move_to(column: num, row: num) -> void`. The build
+//! script of loft reads the same text to describe the standard library, so
+//! this file is included there too and only uses `std`.
+
+/// A function's `#[loft_builtin(...)]` arguments
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    /// The module or type the function belongs to, e.g. `term` or `str`
+    pub owner: String,
+    pub name: String,
+    /// Parameters as loft writes them, e.g. `lines?: num` or `...values`,
+    /// when the attribute lists them
+    pub params: Option<Vec<String>>,
+    pub return_type: Option<String>,
+}
+
+/// Parse the tokens of the attribute, however they are spaced
+pub fn parse(attr: &str) -> Result<Signature, String> {
+    let text: String = attr.chars().filter(|c| !c.is_whitespace()).collect();
+    let (path, rest) = match text.find('(') {
+        Some(open) => (&text[..open], Some(&text[open..])),
+        None => (text.as_str(), None),
+    };
+    let Some((owner, name)) = path.rsplit_once('.') else {
+        return Err(format!(
+            "expected `owner.name` in #[loft_builtin({})], like `term.println`",
+            attr
+        ));
+    };
+    if owner.is_empty() || !is_identifier(name) {
+        return Err(format!("`{}` is not a valid builtin name", path));
+    }
+
+    let mut signature = Signature {
+        owner: owner.to_string(),
+        name: name.to_string(),
+        params: None,
+        return_type: None,
+    };
+    let Some(rest) = rest else {
+        return Ok(signature);
+    };
+    let close = closing_paren(rest).ok_or_else(|| format!("unclosed `(` in `{}`", attr))?;
+    signature.params = Some(
+        split_top_level(&rest[1..close])
+            .into_iter()
+            .map(spaced)
+            .collect(),
+    );
+    let after = &rest[close + 1..];
+    if !after.is_empty() {
+        let return_type = after
+            .strip_prefix("->")
+            .filter(|return_type| !return_type.is_empty())
+            .ok_or_else(|| format!("expected `-> type` after the parameters in `{}`", attr))?;
+        signature.return_type = Some(spaced(return_type));
+    }
+    Ok(signature)
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Index of the `)` that closes the `(` `text` starts with
+fn closing_paren(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Split at the commas that aren't inside `<>` or `()`
+fn split_top_level(text: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    let mut previous = ' ';
+    for (i, c) in text.char_indices() {
+        match c {
+            '<' | '(' => depth += 1,
+            // Not the `>` of `->`
+            '>' if previous != '-' => depth -= 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&text[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+        previous = c;
+    }
+    if start < text.len() {
+        parts.push(&text[start..]);
+    }
+    parts
+}
+
+/// Put back the spaces loft code is written with, e.g. `Map<str, num>`
+fn spaced(text: &str) -> String {
+    text.replace(':', ": ")
+        .replace(',', ", ")
+        .replace('|', " | ")
+        .replace("->", " -> ")
+}
@@ -1,3 +1,5 @@
+mod storage;
+
 use axum::{
     extract::{Path, Query, State},
     http::{HeaderMap, StatusCode},
@@ -18,11 +20,11 @@ use oauth2::{
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
     fs,
-    sync::{Arc, RwLock},
+    sync::Arc,
     time::{SystemTime, UNIX_EPOCH},
 };
+use storage::{SqliteStorage, Storage, StorageError};
 use tower_http::cors::CorsLayer;
 use tower_http::services::ServeDir;
 use uuid::Uuid;
@@ -54,12 +56,10 @@ struct PackageMetadata {
     authors: Vec<String>,
     license: Option<String>,
     owners: Vec<String>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct Package {
-    metadata: PackageMetadata,
-    tarball: Vec<u8>,
+    /// Yanked versions stay downloadable for lockfiles that pin them, but
+    /// resolvers no longer pick them
+    #[serde(default)]
+    yanked: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -82,9 +82,7 @@ struct ApiToken {
 
 #[derive(Clone)]
 struct AppState {
-    packages: Arc<RwLock<HashMap<String, Vec<Package>>>>,
-    users: Arc<RwLock<HashMap<u64, User>>>,
-    tokens: Arc<RwLock<HashMap<String, ApiToken>>>,
+    storage: Arc<dyn Storage>,
     storage_dir: String,
     oauth_client: LoftOauthClient,
     jwt_secret: String,
@@ -99,7 +97,24 @@ impl AppState {
         public_url: String,
     ) -> Self {
         fs::create_dir_all(&storage_dir).expect("Failed to create storage directory");
+        let storage = SqliteStorage::open(std::path::Path::new(&storage_dir))
+            .unwrap_or_else(|e| panic!("Failed to open the registry database: {}", e));
+        Self::with_storage(
+            Arc::new(storage),
+            storage_dir,
+            client_id,
+            client_secret,
+            public_url,
+        )
+    }
 
+    fn with_storage(
+        storage: Arc<dyn Storage>,
+        storage_dir: String,
+        client_id: String,
+        client_secret: String,
+        public_url: String,
+    ) -> Self {
         let oauth_client = BasicClient::new(ClientId::new(client_id))
             .set_client_secret(ClientSecret::new(client_secret))
             .set_auth_uri(
@@ -113,28 +128,33 @@ impl AppState {
             );
 
         Self {
-            packages: Arc::new(RwLock::new(HashMap::new())),
-            users: Arc::new(RwLock::new(HashMap::new())),
-            tokens: Arc::new(RwLock::new(HashMap::new())),
+            storage,
             storage_dir,
             oauth_client,
             jwt_secret: std::env::var("JWT_SECRET").unwrap_or_else(|_| "secret".to_string()),
             loft_bin: std::env::var("LOFT_BIN").unwrap_or_else(|_| "loft".to_string()),
         }
     }
+}
 
-    fn save_users(&self) {
-        let users = self.users.read().unwrap();
-        let file_path = format!("{}/users.json", self.storage_dir);
-        let json = serde_json::to_string_pretty(&*users).unwrap();
-        fs::write(file_path, json).unwrap();
-    }
+/// Log a storage failure and answer with a 500
+fn storage_error(e: StorageError) -> StatusCode {
+    eprintln!("[storage] ERROR: {}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+}
 
-    fn save_tokens(&self) {
-        let tokens = self.tokens.read().unwrap();
-        let file_path = format!("{}/tokens.json", self.storage_dir);
-        let json = serde_json::to_string_pretty(&*tokens).unwrap();
-        fs::write(file_path, json).unwrap();
+impl From<&PackageMetadata> for PackageInfo {
+    fn from(metadata: &PackageMetadata) -> Self {
+        PackageInfo {
+            name: metadata.name.clone(),
+            version: metadata.version.clone(),
+            description: metadata.description.clone(),
+            repository: metadata.repository.clone(),
+            authors: metadata.authors.clone(),
+            license: metadata.license.clone(),
+            owners: metadata.owners.clone(),
+            yanked: metadata.yanked,
+        }
     }
 }
 
@@ -161,6 +181,17 @@ struct PackageInfo {
     authors: Vec<String>,
     license: Option<String>,
     owners: Vec<String>,
+    yanked: bool,
+}
+
+#[derive(Deserialize)]
+struct YankRequest {
+    yanked: bool,
+}
+
+#[derive(Deserialize)]
+struct SearchQuery {
+    q: String,
 }
 
 #[derive(Serialize)]
@@ -248,19 +279,20 @@ async fn github_callback(
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    {
-        let mut users = state.users.write().unwrap();
-        users.insert(
-            github_user.id,
-            User {
-                github_id: github_user.id,
-                username: github_user.login.clone(),
-                avatar_url: github_user.avatar_url,
-                created_at: Utc::now(),
-            },
-        );
-    }
-    state.save_users();
+    let created_at = state
+        .storage
+        .user(github_user.id)
+        .map_err(storage_error)?
+        .map_or_else(Utc::now, |user| user.created_at);
+    state
+        .storage
+        .save_user(&User {
+            github_id: github_user.id,
+            username: github_user.login.clone(),
+            avatar_url: github_user.avatar_url,
+            created_at,
+        })
+        .map_err(storage_error)?;
 
     dbg!("Got user");
 
@@ -297,9 +329,12 @@ async fn get_me(
     headers: HeaderMap,
 ) -> Result<Json<User>, StatusCode> {
     let user_id = authenticate(&state, &headers)?;
-    let users = state.users.read().unwrap();
-    let user = users.get(&user_id).ok_or(StatusCode::UNAUTHORIZED)?;
-    Ok(Json(user.clone()))
+    let user = state
+        .storage
+        .user(user_id)
+        .map_err(storage_error)?
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+    Ok(Json(user))
 }
 
 // --- Token Handlers ---
@@ -321,11 +356,10 @@ async fn create_token(
         last_used_at: None,
     };
 
-    {
-        let mut tokens = state.tokens.write().unwrap();
-        tokens.insert(token_string.clone(), api_token);
-    }
-    state.save_tokens();
+    state
+        .storage
+        .save_token(&token_string, &api_token)
+        .map_err(storage_error)?;
 
     Ok(Json(TokenResponseStruct {
         token: token_string,
@@ -338,12 +372,7 @@ async fn list_tokens(
     headers: HeaderMap,
 ) -> Result<Json<Vec<ApiToken>>, StatusCode> {
     let user_id = authenticate(&state, &headers)?;
-    let tokens = state.tokens.read().unwrap();
-    let user_tokens: Vec<ApiToken> = tokens
-        .values()
-        .filter(|t| t.user_github_id == user_id)
-        .cloned()
-        .collect();
+    let user_tokens = state.storage.user_tokens(user_id).map_err(storage_error)?;
     Ok(Json(user_tokens))
 }
 
@@ -353,17 +382,12 @@ async fn revoke_token(
     Path(token_id): Path<String>,
 ) -> Result<StatusCode, StatusCode> {
     let user_id = authenticate(&state, &headers)?;
-    let mut tokens = state.tokens.write().unwrap();
+    let revoked = state
+        .storage
+        .revoke_token(&token_id, user_id)
+        .map_err(storage_error)?;
 
-    let token_key = tokens
-        .iter()
-        .find(|(_, t)| t.id == token_id && t.user_github_id == user_id)
-        .map(|(k, _)| k.clone());
-
-    if let Some(key) = token_key {
-        tokens.remove(&key);
-        drop(tokens);
-        state.save_tokens();
+    if revoked {
         Ok(StatusCode::OK)
     } else {
         Err(StatusCode::NOT_FOUND)
@@ -419,9 +443,7 @@ fn authenticate(state: &AppState, headers: &HeaderMap) -> Result<u64, StatusCode
         }
     }
 
-    let tokens = state.tokens.read().unwrap();
-    eprintln!("[auth] Token store has {} entries", tokens.len());
-    if let Some(api_token) = tokens.get(token) {
+    if let Some(api_token) = state.storage.token(token).map_err(storage_error)? {
         eprintln!("[auth] OK: Authenticated via API token '{}' as user_id={}", api_token.name, api_token.user_github_id);
         return Ok(api_token.user_github_id);
     }
@@ -432,81 +454,189 @@ fn authenticate(state: &AppState, headers: &HeaderMap) -> Result<u64, StatusCode
 
 // --- Package Handlers ---
 
-async fn get_registry_info(State(state): State<AppState>) -> Json<RegistryInfo> {
-    let packages = state.packages.read().unwrap();
-    let users = state.users.read().unwrap();
-    Json(RegistryInfo {
+async fn get_registry_info(
+    State(state): State<AppState>,
+) -> Result<Json<RegistryInfo>, StatusCode> {
+    let packages = state.storage.packages().map_err(storage_error)?;
+    let users_count = state.storage.user_count().map_err(storage_error)?;
+    Ok(Json(RegistryInfo {
         name: "loft Package Registry".to_string(),
         version: "0.1.0".to_string(),
         packages_count: packages.len(),
-        users_count: users.len(),
+        users_count,
+    }))
+}
+
+/// The newest version that hasn't been yanked, or the newest one if all were
+fn latest_version(versions: &[PackageMetadata]) -> Option<&PackageMetadata> {
+    versions
+        .iter()
+        .rev()
+        .find(|p| !p.yanked)
+        .or(versions.last())
+}
+
+async fn list_packages(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<PackageInfo>>, StatusCode> {
+    let packages = state.storage.packages().map_err(storage_error)?;
+    let result = packages
+        .values()
+        .filter_map(|versions| latest_version(versions))
+        .map(PackageInfo::from)
+        .collect();
+
+    Ok(Json(result))
+}
+
+/// How well a package matches the lowercase words of a search query, best
+/// first, or `None` if some word is in neither its name, its description nor
+/// its keywords
+fn search_rank(metadata: &PackageMetadata, query: &str, words: &[String]) -> Option<u8> {
+    let name = metadata.name.to_lowercase();
+    let description = metadata
+        .description
+        .as_deref()
+        .unwrap_or_default()
+        .to_lowercase();
+    let keywords: Vec<String> = metadata
+        .manifest
+        .get("keywords")
+        .and_then(|k| k.as_array())
+        .map(|k| {
+            k.iter()
+                .filter_map(|k| k.as_str())
+                .map(str::to_lowercase)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let matches = |word: &String| {
+        name.contains(word.as_str())
+            || description.contains(word.as_str())
+            || keywords.iter().any(|k| k.contains(word.as_str()))
+    };
+    if !words.iter().all(matches) {
+        return None;
+    }
+
+    Some(if name == query {
+        0
+    } else if name.starts_with(query) {
+        1
+    } else if name.contains(query) {
+        2
+    } else if keywords.iter().any(|k| k == query) {
+        3
+    } else {
+        4
     })
 }
 
-async fn list_packages(State(state): State<AppState>) -> Json<Vec<PackageInfo>> {
-    let packages = state.packages.read().unwrap();
-    let mut result = Vec::new();
-
-    for (name, versions) in packages.iter() {
-        if let Some(latest) = versions.last() {
-            result.push(PackageInfo {
-                name: name.clone(),
-                version: latest.metadata.version.clone(),
-                description: latest.metadata.description.clone(),
-                repository: latest.metadata.repository.clone(),
-                authors: latest.metadata.authors.clone(),
-                license: latest.metadata.license.clone(),
-                owners: latest.metadata.owners.clone(),
-            });
-        }
+async fn search_packages(
+    State(state): State<AppState>,
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<Vec<PackageInfo>>, StatusCode> {
+    let query = query.q.trim().to_lowercase();
+    let words: Vec<String> = query.split_whitespace().map(str::to_string).collect();
+    if words.is_empty() {
+        return Ok(Json(Vec::new()));
     }
 
-    Json(result)
+    let packages = state.storage.packages().map_err(storage_error)?;
+    let mut results: Vec<(u8, PackageInfo)> = packages
+        .values()
+        .filter_map(|versions| latest_version(versions))
+        .filter_map(|metadata| {
+            let rank = search_rank(metadata, &query, &words)?;
+            Some((rank, PackageInfo::from(metadata)))
+        })
+        .collect();
+    results.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.name.cmp(&b.1.name)));
+
+    Ok(Json(results.into_iter().map(|(_, info)| info).collect()))
 }
 
 async fn get_package(
     State(state): State<AppState>,
     Path(name): Path<String>,
 ) -> Result<Json<Vec<PackageInfo>>, StatusCode> {
-    let packages = state.packages.read().unwrap();
-
-    match packages.get(&name) {
-        Some(versions) => {
-            let info: Vec<PackageInfo> = versions
-                .iter()
-                .map(|pkg| PackageInfo {
-                    name: pkg.metadata.name.clone(),
-                    version: pkg.metadata.version.clone(),
-                    description: pkg.metadata.description.clone(),
-                    repository: pkg.metadata.repository.clone(),
-                    authors: pkg.metadata.authors.clone(),
-                    license: pkg.metadata.license.clone(),
-                    owners: pkg.metadata.owners.clone(),
-                })
-                .collect();
-            Ok(Json(info))
-        }
-        None => Err(StatusCode::NOT_FOUND),
+    let versions = state.storage.versions(&name).map_err(storage_error)?;
+    if versions.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
     }
+    Ok(Json(versions.iter().map(PackageInfo::from).collect()))
 }
 
 async fn download_package(
     State(state): State<AppState>,
     Path((name, version)): Path<(String, String)>,
-) -> Result<Vec<u8>, StatusCode> {
-    let packages = state.packages.read().unwrap();
-
-    match packages.get(&name) {
-        Some(versions) => {
-            for pkg in versions {
-                if pkg.metadata.version == version {
-                    return Ok(pkg.tarball.clone());
-                }
-            }
-            Err(StatusCode::NOT_FOUND)
-        }
-        None => Err(StatusCode::NOT_FOUND),
+) -> Result<(HeaderMap, Vec<u8>), StatusCode> {
+    let tarball = state
+        .storage
+        .tarball(&name, &version)
+        .map_err(storage_error)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let mut headers = HeaderMap::new();
+    let yanked = state
+        .storage
+        .versions(&name)
+        .map_err(storage_error)?
+        .iter()
+        .any(|p| p.version == version && p.yanked);
+    if yanked {
+        headers.insert("X-Loft-Yanked", "true".parse().unwrap());
     }
+    Ok((headers, tarball))
+}
+
+/// Build the docs of a published version into `<storage>/docs/<name>/<version>`
+/// by running `loft doc` on its extracted tarball
+fn generate_docs(
+    state: &AppState,
+    name: &str,
+    version: &str,
+    tarball: &[u8],
+) -> Result<(), String> {
+    use flate2::read::GzDecoder;
+    use std::io::Cursor;
+    use tar::Archive;
+
+    let temp_extract_dir = format!("/tmp/loft-extract-{}-{}", name, version);
+    let _ = fs::remove_dir_all(&temp_extract_dir); // Clean up if exists
+    fs::create_dir_all(&temp_extract_dir).map_err(|e| e.to_string())?;
+
+    let result = Archive::new(GzDecoder::new(Cursor::new(tarball)))
+        .unpack(&temp_extract_dir)
+        .map_err(|e| format!("Failed to extract tarball: {}", e))
+        .and_then(|_| {
+            let docs_output = format!("{}/docs/{}/{}", state.storage_dir, name, version);
+            fs::create_dir_all(&docs_output).map_err(|e| e.to_string())?;
+
+            // Run loft doc command from extracted directory
+            let output = std::process::Command::new(&state.loft_bin)
+                .arg("doc")
+                .arg("-o")
+                .arg(&docs_output)
+                .current_dir(&temp_extract_dir)
+                .output()
+                .map_err(|e| {
+                    format!(
+                        "Could not run doc generator (LOFT_BIN='{}'): {}",
+                        state.loft_bin, e
+                    )
+                })?;
+            if output.status.success() {
+                Ok(())
+            } else {
+                Err(String::from_utf8_lossy(&output.stderr).to_string())
+            }
+        });
+
+    // Cleanup temp directory
+    let _ = fs::remove_dir_all(&temp_extract_dir);
+    result
 }
 
 async fn publish_package(
@@ -518,17 +648,14 @@ async fn publish_package(
 
     let user_id = authenticate(&state, &headers)?;
 
-    let username = {
-        let users = state.users.read().unwrap();
-        match users.get(&user_id) {
-            Some(u) => {
-                eprintln!("[publish] Authenticated as user '{}' (id={})", u.username, user_id);
-                u.username.clone()
-            }
-            None => {
-                eprintln!("[publish] REJECTED: user_id={} authenticated but not found in user store", user_id);
-                return Err(StatusCode::UNAUTHORIZED);
-            }
+    let username = match state.storage.user(user_id).map_err(storage_error)? {
+        Some(u) => {
+            eprintln!("[publish] Authenticated as user '{}' (id={})", u.username, user_id);
+            u.username
+        }
+        None => {
+            eprintln!("[publish] REJECTED: user_id={} authenticated but not found in user store", user_id);
+            return Err(StatusCode::UNAUTHORIZED);
         }
     };
 
@@ -537,27 +664,26 @@ async fn publish_package(
         return Err(StatusCode::BAD_REQUEST);
     }
 
-    {
-        let packages = state.packages.read().unwrap();
-        if let Some(versions) = packages.get(&payload.name) {
-            // Reject if this exact version already exists
-            if versions.iter().any(|p| p.metadata.version == payload.version) {
-                eprintln!("[publish] REJECTED 409: '{}@{}' already exists", payload.name, payload.version);
-                return Err(StatusCode::CONFLICT);
-            }
+    let versions = state
+        .storage
+        .versions(&payload.name)
+        .map_err(storage_error)?;
+    if let Some(latest) = versions.last() {
+        // Reject if this exact version already exists
+        if versions.iter().any(|p| p.version == payload.version) {
+            eprintln!("[publish] REJECTED 409: '{}@{}' already exists", payload.name, payload.version);
+            return Err(StatusCode::CONFLICT);
+        }
 
-            if let Some(latest) = versions.last() {
-                let owners = &latest.metadata.owners;
-                eprintln!("[publish] Package '{}' already exists. Owners: {:?}. Requesting user: '{}'", payload.name, owners, username);
-                if !owners.contains(&username) {
-                    eprintln!("[publish] REJECTED 403: user '{}' is not in owners list {:?}", username, owners);
-                    return Err(StatusCode::FORBIDDEN);
-                }
-                eprintln!("[publish] Ownership check passed for user '{}'", username);
-            }
-        } else {
-            eprintln!("[publish] Package '{}' is new, no ownership check needed", payload.name);
+        let owners = &latest.owners;
+        eprintln!("[publish] Package '{}' already exists. Owners: {:?}. Requesting user: '{}'", payload.name, owners, username);
+        if !owners.contains(&username) {
+            eprintln!("[publish] REJECTED 403: user '{}' is not in owners list {:?}", username, owners);
+            return Err(StatusCode::FORBIDDEN);
         }
+        eprintln!("[publish] Ownership check passed for user '{}'", username);
+    } else {
+        eprintln!("[publish] Package '{}' is new, no ownership check needed", payload.name);
     }
 
     use base64::{engine::general_purpose, Engine as _};
@@ -568,104 +694,103 @@ async fn publish_package(
             StatusCode::BAD_REQUEST
         })?;
 
-    let package = Package {
-        metadata: PackageMetadata {
-            name: payload.name.clone(),
-            version: payload.version.clone(),
-            description: payload.description.clone(),
-            manifest: payload.manifest,
-            repository: payload.repository,
-            authors: payload.authors.unwrap_or_default(),
-            license: payload.license,
-            owners: vec![username.clone()],
-        },
-        tarball,
+    let metadata = PackageMetadata {
+        name: payload.name.clone(),
+        version: payload.version.clone(),
+        description: payload.description.clone(),
+        manifest: payload.manifest,
+        repository: payload.repository,
+        authors: payload.authors.unwrap_or_default(),
+        license: payload.license,
+        owners: vec![username.clone()],
+        yanked: false,
     };
 
-    let mut packages = state.packages.write().unwrap();
-    packages
-        .entry(payload.name.clone())
-        .or_insert_with(Vec::new)
-        .push(package.clone());
-
-    let package_dir = format!("{}/{}", state.storage_dir, payload.name);
-    fs::create_dir_all(&package_dir).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    match state.storage.publish(&metadata, &tarball) {
+        Ok(()) => {}
+        // Published concurrently since the check above
+        Err(StorageError::Conflict) => return Err(StatusCode::CONFLICT),
+        Err(e) => return Err(storage_error(e)),
+    }
 
-    let package_file = format!("{}/{}.tar.gz", package_dir, payload.version);
-    fs::write(&package_file, &package.tarball).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    match generate_docs(&state, &payload.name, &payload.version, &tarball) {
+        Ok(()) => println!(
+            "v Generated documentation for {}@{}",
+            payload.name, payload.version
+        ),
+        Err(e) => eprintln!(
+            "! Documentation generation failed for {}@{}: {}",
+            payload.name, payload.version, e
+        ),
+    }
 
-    let metadata_file = format!("{}/{}.json", package_dir, payload.version);
-    let metadata_json = serde_json::to_string_pretty(&package.metadata)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    fs::write(&metadata_file, metadata_json).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    eprintln!("[publish] SUCCESS: '{}@{}' published by '{}'", payload.name, payload.version, username);
 
-    // Extract tarball to temporary directory and generate docs
-    let temp_extract_dir = format!("/tmp/loft-extract-{}-{}", payload.name, payload.version);
-    let _ = fs::remove_dir_all(&temp_extract_dir); // Clean up if exists
-    fs::create_dir_all(&temp_extract_dir).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(PackageInfo::from(&metadata)))
+}
 
-    // Extract tarball
-    use flate2::read::GzDecoder;
-    use std::io::Cursor;
-    use tar::Archive;
+/// Mark a version as yanked, or un-yank it. Only owners of the package can.
+fn set_yanked(
+    state: &AppState,
+    headers: &HeaderMap,
+    name: &str,
+    version: &str,
+    yanked: bool,
+) -> Result<Json<PackageInfo>, StatusCode> {
+    let user_id = authenticate(state, headers)?;
+    let username = state
+        .storage
+        .user(user_id)
+        .map_err(storage_error)?
+        .map(|u| u.username)
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let versions = state.storage.versions(name).map_err(storage_error)?;
+    let is_owner = versions
+        .last()
+        .is_some_and(|latest| latest.owners.contains(&username));
+    if versions.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    } else if !is_owner {
+        eprintln!(
+            "[yank] REJECTED 403: user '{}' is not an owner of '{}'",
+            username, name
+        );
+        return Err(StatusCode::FORBIDDEN);
+    }
 
-    let cursor = Cursor::new(&package.tarball);
-    let gz = GzDecoder::new(cursor);
-    let mut archive = Archive::new(gz);
-    archive.unpack(&temp_extract_dir).map_err(|e| {
-        eprintln!("Failed to extract tarball: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-
-    // Generate documentation using loft doc command
-    let docs_output = format!(
-        "{}/docs/{}/{}",
-        state.storage_dir, payload.name, payload.version
+    let metadata = state
+        .storage
+        .set_yanked(name, version, yanked)
+        .map_err(storage_error)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    eprintln!(
+        "[yank] '{}@{}' {} by '{}'",
+        name,
+        version,
+        if yanked { "yanked" } else { "un-yanked" },
+        username
     );
-    fs::create_dir_all(&docs_output).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    // Run loft doc command from extracted directory
-    let doc_result = std::process::Command::new(&state.loft_bin)
-        .arg("doc")
-        .arg("-o")
-        .arg(&docs_output)
-        .current_dir(&temp_extract_dir)
-        .output();
-
-    match doc_result {
-        Ok(output) if output.status.success() => {
-            println!(
-                "v Generated documentation for {}@{}",
-                payload.name, payload.version
-            );
-        }
-        Ok(output) => {
-            eprintln!(
-                "! Documentation generation failed for {}@{}: {}",
-                payload.name,
-                payload.version,
-                String::from_utf8_lossy(&output.stderr)
-            );
-        }
-        Err(e) => {
-            eprintln!("! Could not run doc generator (LOFT_BIN='{}'): {}", state.loft_bin, e);
-        }
-    }
 
-    // Cleanup temp directory
-    let _ = fs::remove_dir_all(&temp_extract_dir);
+    Ok(Json(PackageInfo::from(&metadata)))
+}
 
-    eprintln!("[publish] SUCCESS: '{}@{}' published by '{}'", payload.name, payload.version, username);
+async fn yank_version(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((name, version)): Path<(String, String)>,
+) -> Result<Json<PackageInfo>, StatusCode> {
+    set_yanked(&state, &headers, &name, &version, true)
+}
 
-    Ok(Json(PackageInfo {
-        name: package.metadata.name,
-        version: package.metadata.version,
-        description: package.metadata.description,
-        repository: package.metadata.repository,
-        authors: package.metadata.authors,
-        license: package.metadata.license,
-        owners: package.metadata.owners,
-    }))
+async fn update_version(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((name, version)): Path<(String, String)>,
+    Json(payload): Json<YankRequest>,
+) -> Result<Json<PackageInfo>, StatusCode> {
+    set_yanked(&state, &headers, &name, &version, payload.yanked)
 }
 
 async fn get_doc_content(Path(path): Path<String>) -> Result<String, StatusCode> {
@@ -702,136 +827,48 @@ async fn main() {
     let state = AppState::new(storage_dir.clone(), client_id, client_secret, public_url);
 
     // On startup, build missing docs for all published versions
-    if let Ok(entries) = fs::read_dir(&state.storage_dir) {
-        for entry in entries.flatten() {
-            if entry.path().is_dir() {
-                let package_name = entry.file_name().to_string_lossy().to_string();
-                if let Ok(version_entries) = fs::read_dir(entry.path()) {
-                    for version_entry in version_entries.flatten() {
-                        let path = version_entry.path();
-                        if path.extension().and_then(|s| s.to_str()) == Some("json") {
-                            if let Ok(metadata_content) = fs::read_to_string(&path) {
-                                if let Ok(metadata) = serde_json::from_str::<PackageMetadata>(&metadata_content) {
-                                    let version = metadata.version.clone();
-                                    let tarball_path = path.with_file_name(format!("{}.tar.gz", version));
-                                    let docs_dir = format!("{}/docs/{}/{}", storage_dir, package_name, version);
-                                    let docs_index = format!("{}/index.html", docs_dir);
-                                    if !std::path::Path::new(&docs_index).exists() {
-                                        // Extract tarball and build docs
-                                        let temp_extract_dir = format!("/tmp/loft-extract-{}-{}", package_name, version);
-                                        let _ = fs::remove_dir_all(&temp_extract_dir);
-                                        fs::create_dir_all(&temp_extract_dir).ok();
-                                        if let Ok(tarball) = fs::read(&tarball_path) {
-                                            use flate2::read::GzDecoder;
-                                            use std::io::Cursor;
-                                            use tar::Archive;
-                                            let cursor = Cursor::new(&tarball);
-                                            let gz = GzDecoder::new(cursor);
-                                            let mut archive = Archive::new(gz);
-                                            if archive.unpack(&temp_extract_dir).is_ok() {
-                                                fs::create_dir_all(&docs_dir).ok();
-                                                let doc_result = std::process::Command::new(&state.loft_bin)
-                                                    .arg("doc")
-                                                    .arg("-o")
-                                                    .arg(&docs_dir)
-                                                    .current_dir(&temp_extract_dir)
-                                                    .output();
-                                                match doc_result {
-                                                    Ok(output) if output.status.success() => {
-                                                        println!("✓ Startup: Generated docs for {}@{}", package_name, version);
-                                                    }
-                                                    Ok(output) => {
-                                                        eprintln!("⚠ Startup: Doc gen failed for {}@{}: {}", package_name, version, String::from_utf8_lossy(&output.stderr));
-                                                    }
-                                                    Err(e) => {
-                                                        eprintln!("⚠ Startup: Could not run doc generator (LOFT_BIN='{}'): {}", state.loft_bin, e);
-                                                    }
-                                                }
-                                            }
-                                        }
-                                        let _ = fs::remove_dir_all(&temp_extract_dir);
-                                    }
-                                }
-                            }
-                        }
-                    }
+    match state.storage.packages() {
+        Ok(packages) => {
+            let versions = packages.values().flatten();
+            for metadata in versions {
+                let docs_index = format!(
+                    "{}/docs/{}/{}/index.html",
+                    storage_dir, metadata.name, metadata.version
+                );
+                if std::path::Path::new(&docs_index).exists() {
+                    continue;
                 }
-            }
-        }
-    }
-    if let Ok(entries) = fs::read_dir(&state.storage_dir) {
-        let mut packages = state.packages.write().unwrap();
-
-        for entry in entries.flatten() {
-            if entry.path().is_dir() {
-                let package_name = entry.file_name().to_string_lossy().to_string();
-
-                if let Ok(version_entries) = fs::read_dir(entry.path()) {
-                    for version_entry in version_entries.flatten() {
-                        let path = version_entry.path();
-
-                        if path.extension().and_then(|s| s.to_str()) == Some("json") {
-                            if let Ok(metadata_content) = fs::read_to_string(&path) {
-                                if let Ok(metadata) =
-                                    serde_json::from_str::<PackageMetadata>(&metadata_content)
-                                {
-                                    let version = metadata.version.clone();
-                                    let tarball_path =
-                                        path.with_file_name(format!("{}.tar.gz", version));
-
-                                    if let Ok(tarball) = fs::read(&tarball_path) {
-                                        packages
-                                            .entry(package_name.clone())
-                                            .or_insert_with(Vec::new)
-                                            .push(Package { metadata, tarball });
-                                    }
-                                }
-                            }
-                        }
-                    }
+                let tarball = match state.storage.tarball(&metadata.name, &metadata.version) {
+                    Ok(Some(tarball)) => tarball,
+                    _ => continue,
+                };
+                match generate_docs(&state, &metadata.name, &metadata.version, &tarball) {
+                    Ok(()) => println!(
+                        "✓ Startup: Generated docs for {}@{}",
+                        metadata.name, metadata.version
+                    ),
+                    Err(e) => eprintln!(
+                        "⚠ Startup: Doc gen failed for {}@{}: {}",
+                        metadata.name, metadata.version, e
+                    ),
                 }
             }
+            println!("[startup] Serving {} package(s)", packages.len());
         }
+        Err(e) => eprintln!("[startup] WARNING: Failed to load packages: {}", e),
     }
 
-    let users_file = format!("{}/users.json", state.storage_dir);
-    match fs::read_to_string(&users_file) {
-        Ok(content) => match serde_json::from_str::<HashMap<u64, User>>(&content) {
-            Ok(users) => {
-                println!("[startup] Loaded {} user(s) from {}", users.len(), users_file);
-                *state.users.write().unwrap() = users;
-            }
-            Err(e) => eprintln!("[startup] WARNING: Failed to deserialize {}: {}", users_file, e),
-        },
-        Err(e) => eprintln!("[startup] WARNING: Could not read {}: {}", users_file, e),
-    }
-
-    let tokens_file = format!("{}/tokens.json", state.storage_dir);
-    match fs::read_to_string(&tokens_file) {
-        Ok(content) => match serde_json::from_str::<HashMap<String, ApiToken>>(&content) {
-            Ok(tokens) => {
-                println!("[startup] Loaded {} API token(s) from {}", tokens.len(), tokens_file);
-                *state.tokens.write().unwrap() = tokens;
-            }
-            Err(e) => eprintln!("[startup] WARNING: Failed to deserialize {}: {}", tokens_file, e),
-        },
-        Err(e) => eprintln!("[startup] WARNING: Could not read {}: {}", tokens_file, e),
-    }
-
-
     // Serve latest docs at /d/:name and versioned docs at /d/:name@:version
     async fn serve_latest_docs(Path(name): Path<String>, State(state): State<AppState>) -> Result<axum::response::Response, StatusCode> {
-        let packages = state.packages.read().unwrap();
-        if let Some(versions) = packages.get(&name) {
-            if let Some(latest) = versions.last() {
-                let docs_dir = format!("{}/docs/{}/{}", state.storage_dir, name, latest.metadata.version);
-                let index_path = format!("{}/index.html", docs_dir);
-                if std::path::Path::new(&index_path).exists() {
-                    return Ok(axum::response::Response::builder()
-                        .header("Content-Type", "text/html")
-                        .body(axum::body::Body::from(fs::read(index_path).unwrap()))
-                        .unwrap());
-                }
+        let versions = state.storage.versions(&name).map_err(storage_error)?;
+        if let Some(latest) = latest_version(&versions) {
+            let docs_dir = format!("{}/docs/{}/{}", state.storage_dir, name, latest.version);
+            let index_path = format!("{}/index.html", docs_dir);
+            if std::path::Path::new(&index_path).exists() {
+                return Ok(axum::response::Response::builder()
+                    .header("Content-Type", "text/html")
+                    .body(axum::body::Body::from(fs::read(index_path).unwrap()))
+                    .unwrap());
             }
         }
         Err(StatusCode::NOT_FOUND)
@@ -853,7 +890,12 @@ async fn main() {
         .route("/", get(get_registry_info))
         .route("/install.sh", get(get_install_sh))
         .route("/packages", get(list_packages))
+        .route("/packages/search", get(search_packages))
         .route("/packages/:name", get(get_package))
+        .route(
+            "/packages/:name/:version",
+            delete(yank_version).patch(update_version),
+        )
         .route("/packages/:name/:version/download", get(download_package))
         .route("/packages/publish", post(publish_package))
         .route("/auth/github/login", get(github_login))
@@ -868,7 +910,7 @@ async fn main() {
         .nest_service("/stdlib", ServeDir::new("../stdlib-docs"))
         .nest_service(
             "/pkg-docs",
-            ServeDir::new(&format!("{}/docs", &state.storage_dir)),
+            ServeDir::new(format!("{}/docs", &state.storage_dir)),
         )
         .fallback_service(ServeDir::new("../www/dist"))
         .layer(CorsLayer::permissive())
@@ -880,3 +922,203 @@ async fn main() {
     let listener = tokio::net::TcpListener::bind(&bind_addr).await.unwrap();
     axum::serve(listener, app).await.unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(name: &str, version: &str) -> PackageMetadata {
+        PackageMetadata {
+            name: name.to_string(),
+            version: version.to_string(),
+            description: None,
+            manifest: serde_json::json!({}),
+            repository: None,
+            authors: Vec::new(),
+            license: None,
+            owners: vec!["octocat".to_string()],
+            yanked: false,
+        }
+    }
+
+    /// A registry in memory where `octocat` owns `geo` 1.0.0 and 1.1.0 and
+    /// `hubot` owns nothing. Each user's API token is their name
+    fn registry() -> AppState {
+        let storage = SqliteStorage::in_memory().unwrap();
+        for (github_id, username) in [(1, "octocat"), (2, "hubot")] {
+            let user = User {
+                github_id,
+                username: username.to_string(),
+                avatar_url: None,
+                created_at: Utc::now(),
+            };
+            storage.save_user(&user).unwrap();
+            let token = ApiToken {
+                id: format!("t{}", github_id),
+                name: "ci".to_string(),
+                user_github_id: github_id,
+                token_hash: username.to_string(),
+                created_at: Utc::now(),
+                last_used_at: None,
+            };
+            storage.save_token(username, &token).unwrap();
+        }
+        for version in ["1.0.0", "1.1.0"] {
+            storage
+                .publish(&metadata("geo", version), b"tarball")
+                .unwrap();
+        }
+        AppState::with_storage(
+            Arc::new(storage),
+            "registry-storage".to_string(),
+            "client".to_string(),
+            "secret".to_string(),
+            "http://localhost".to_string(),
+        )
+    }
+
+    fn described(name: &str, description: &str, keywords: &[&str]) -> PackageMetadata {
+        PackageMetadata {
+            description: Some(description.to_string()),
+            manifest: serde_json::json!({ "keywords": keywords }),
+            ..metadata(name, "1.0.0")
+        }
+    }
+
+    fn rank(metadata: &PackageMetadata, query: &str) -> Option<u8> {
+        let words: Vec<String> = query.split_whitespace().map(str::to_string).collect();
+        search_rank(metadata, query, &words)
+    }
+
+    async fn search(state: &AppState, q: &str) -> Vec<String> {
+        let query = Query(SearchQuery { q: q.to_string() });
+        let Json(results) = search_packages(State(state.clone()), query).await.unwrap();
+        results.into_iter().map(|info| info.name).collect()
+    }
+
+    fn bearer(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "Authorization",
+            format!("Bearer {}", token).parse().unwrap(),
+        );
+        headers
+    }
+
+    fn version(name: &str, version: &str) -> Path<(String, String)> {
+        Path((name.to_string(), version.to_string()))
+    }
+
+    fn is_yanked(state: &AppState, version: &str) -> bool {
+        state
+            .storage
+            .versions("geo")
+            .unwrap()
+            .iter()
+            .any(|p| p.version == version && p.yanked)
+    }
+
+    #[tokio::test]
+    async fn test_yank_and_unyank() {
+        let state = registry();
+        let Json(info) = yank_version(
+            State(state.clone()),
+            bearer("octocat"),
+            version("geo", "1.1.0"),
+        )
+        .await
+        .unwrap();
+        assert!(info.yanked);
+        assert!(is_yanked(&state, "1.1.0"));
+        let versions = state.storage.versions("geo").unwrap();
+        assert_eq!(latest_version(&versions).unwrap().version, "1.0.0");
+
+        let Json(info) = update_version(
+            State(state.clone()),
+            bearer("octocat"),
+            version("geo", "1.1.0"),
+            Json(YankRequest { yanked: false }),
+        )
+        .await
+        .unwrap();
+        assert!(!info.yanked);
+        assert!(!is_yanked(&state, "1.1.0"));
+    }
+
+    #[tokio::test]
+    async fn test_only_owners_can_yank() {
+        let state = registry();
+        let yank = |headers: HeaderMap, name: &str, v: &str| {
+            yank_version(State(state.clone()), headers, version(name, v))
+        };
+
+        let rejected = yank(bearer("hubot"), "geo", "1.1.0").await;
+        assert_eq!(rejected.err(), Some(StatusCode::FORBIDDEN));
+        let rejected = update_version(
+            State(state.clone()),
+            bearer("hubot"),
+            version("geo", "1.1.0"),
+            Json(YankRequest { yanked: true }),
+        )
+        .await;
+        assert_eq!(rejected.err(), Some(StatusCode::FORBIDDEN));
+        let rejected = yank(HeaderMap::new(), "geo", "1.1.0").await;
+        assert_eq!(rejected.err(), Some(StatusCode::UNAUTHORIZED));
+        let rejected = yank(bearer("unknown"), "geo", "1.1.0").await;
+        assert_eq!(rejected.err(), Some(StatusCode::UNAUTHORIZED));
+        assert!(!is_yanked(&state, "1.1.0"));
+
+        let missing = yank(bearer("octocat"), "geo", "2.0.0").await;
+        assert_eq!(missing.err(), Some(StatusCode::NOT_FOUND));
+        let missing = yank(bearer("octocat"), "roads", "1.0.0").await;
+        assert_eq!(missing.err(), Some(StatusCode::NOT_FOUND));
+    }
+
+    #[test]
+    fn test_search_rank() {
+        let geo = described("geo", "Points and shapes", &["maps"]);
+        assert_eq!(rank(&geo, "geo"), Some(0));
+        assert_eq!(rank(&geo, "ge"), Some(1));
+        assert_eq!(rank(&geo, "eo"), Some(2));
+        assert_eq!(rank(&geo, "maps"), Some(3));
+        assert_eq!(rank(&geo, "map"), Some(4));
+        assert_eq!(rank(&geo, "shapes"), Some(4));
+        assert_eq!(rank(&geo, "roads"), None);
+
+        // Every word has to match somewhere, but not all in the same place
+        assert_eq!(rank(&geo, "geo shapes"), Some(4));
+        assert_eq!(rank(&geo, "shapes maps"), Some(4));
+        assert_eq!(rank(&geo, "geo roads"), None);
+    }
+
+    #[tokio::test]
+    async fn test_search_packages() {
+        let state = registry();
+        let packages = [
+            described("roads", "Road networks from geo data", &[]),
+            described("maps", "Tiles and markers", &["geo"]),
+            described("geojson", "GeoJSON parsing", &[]),
+            described("ageo", "Ages of things", &[]),
+        ];
+        for package in &packages {
+            state.storage.publish(package, b"tarball").unwrap();
+        }
+
+        assert_eq!(
+            search(&state, "Geo").await,
+            vec!["geo", "geojson", "ageo", "maps", "roads"]
+        );
+        assert_eq!(search(&state, "geo json").await, vec!["geojson"]);
+        assert_eq!(search(&state, "geo tiles").await, vec!["maps"]);
+        assert!(search(&state, "  ").await.is_empty());
+        assert!(search(&state, "trains").await.is_empty());
+
+        // Results show the newest version that hasn't been yanked
+        state.storage.set_yanked("geo", "1.1.0", true).unwrap();
+        let query = Query(SearchQuery {
+            q: "geo".to_string(),
+        });
+        let Json(results) = search_packages(State(state.clone()), query).await.unwrap();
+        assert_eq!(results[0].version, "1.0.0");
+    }
+}
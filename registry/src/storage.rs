@@ -0,0 +1,522 @@
+//! Persistent registry state: packages with their tarballs, users and API tokens.
+//!
+//! Handlers talk to the [`Storage`] trait. [`SqliteStorage`] keeps everything
+//! in one SQLite database, so a publish either lands completely or not at all.
+//! On first start it imports the JSON and tarball files older registries kept
+//! in the storage directory.
+
+use crate::{ApiToken, PackageMetadata, User};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Name of the database file inside the storage directory
+pub const DATABASE_FILE: &str = "registry.db";
+
+#[derive(Debug)]
+pub enum StorageError {
+    Database(rusqlite::Error),
+    Serialization(serde_json::Error),
+    Io(std::io::Error),
+    /// The version being published already exists
+    Conflict,
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Database(e) => write!(f, "database error: {}", e),
+            StorageError::Serialization(e) => write!(f, "serialization error: {}", e),
+            StorageError::Io(e) => write!(f, "I/O error: {}", e),
+            StorageError::Conflict => write!(f, "version already exists"),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(e: rusqlite::Error) -> Self {
+        StorageError::Database(e)
+    }
+}
+
+impl From<serde_json::Error> for StorageError {
+    fn from(e: serde_json::Error) -> Self {
+        StorageError::Serialization(e)
+    }
+}
+
+impl From<std::io::Error> for StorageError {
+    fn from(e: std::io::Error) -> Self {
+        StorageError::Io(e)
+    }
+}
+
+pub type StorageResult<T> = Result<T, StorageError>;
+
+pub trait Storage: Send + Sync {
+    /// Every version of every package, oldest version first
+    fn packages(&self) -> StorageResult<BTreeMap<String, Vec<PackageMetadata>>>;
+    /// Every version of a package, oldest first
+    fn versions(&self, name: &str) -> StorageResult<Vec<PackageMetadata>>;
+    fn tarball(&self, name: &str, version: &str) -> StorageResult<Option<Vec<u8>>>;
+    /// Store a new version, failing with [`StorageError::Conflict`] if it exists
+    fn publish(&self, metadata: &PackageMetadata, tarball: &[u8]) -> StorageResult<()>;
+    /// Mark a version as yanked or not, returning its updated metadata
+    fn set_yanked(
+        &self,
+        name: &str,
+        version: &str,
+        yanked: bool,
+    ) -> StorageResult<Option<PackageMetadata>>;
+
+    fn user(&self, github_id: u64) -> StorageResult<Option<User>>;
+    fn user_count(&self) -> StorageResult<usize>;
+    /// Insert a user or update the one with the same GitHub id
+    fn save_user(&self, user: &User) -> StorageResult<()>;
+
+    /// Look up an API token by the secret the client sends
+    fn token(&self, secret: &str) -> StorageResult<Option<ApiToken>>;
+    fn user_tokens(&self, user_github_id: u64) -> StorageResult<Vec<ApiToken>>;
+    fn save_token(&self, secret: &str, token: &ApiToken) -> StorageResult<()>;
+    /// Delete a user's token by id, returning whether it existed
+    fn revoke_token(&self, id: &str, user_github_id: u64) -> StorageResult<bool>;
+}
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS packages (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    version TEXT NOT NULL,
+    metadata TEXT NOT NULL,
+    tarball BLOB NOT NULL,
+    UNIQUE (name, version)
+);
+CREATE TABLE IF NOT EXISTS users (
+    github_id INTEGER PRIMARY KEY,
+    username TEXT NOT NULL,
+    avatar_url TEXT,
+    created_at TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS tokens (
+    secret TEXT PRIMARY KEY,
+    id TEXT NOT NULL UNIQUE,
+    name TEXT NOT NULL,
+    user_github_id INTEGER NOT NULL,
+    created_at TEXT NOT NULL,
+    last_used_at TEXT
+);
+CREATE TABLE IF NOT EXISTS meta (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+";
+
+pub struct SqliteStorage {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStorage {
+    /// Open the database in `storage_dir`, creating it and importing the
+    /// files of an older registry the first time
+    pub fn open(storage_dir: &Path) -> StorageResult<Self> {
+        let storage = Self::from_connection(Connection::open(storage_dir.join(DATABASE_FILE))?)?;
+        storage.import_legacy_files(storage_dir)?;
+        Ok(storage)
+    }
+
+    /// A database that only lives in memory
+    #[cfg(test)]
+    pub fn in_memory() -> StorageResult<Self> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(conn: Connection) -> StorageResult<Self> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Import `users.json`, `tokens.json` and the `<package>/<version>.json`
+    /// and `.tar.gz` files, once. The files are left in place.
+    fn import_legacy_files(&self, storage_dir: &Path) -> StorageResult<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let imported: Option<String> = conn
+            .query_row(
+                "SELECT value FROM meta WHERE key = 'legacy_import'",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if imported.is_some() {
+            return Ok(());
+        }
+
+        let tx = conn.transaction()?;
+
+        if let Ok(content) = fs::read_to_string(storage_dir.join("users.json")) {
+            let users: BTreeMap<u64, User> = serde_json::from_str(&content)?;
+            for user in users.values() {
+                insert_user(&tx, user)?;
+            }
+            println!("[migrate] Imported {} user(s)", users.len());
+        }
+
+        if let Ok(content) = fs::read_to_string(storage_dir.join("tokens.json")) {
+            let tokens: BTreeMap<String, ApiToken> = serde_json::from_str(&content)?;
+            for (secret, token) in &tokens {
+                insert_token(&tx, secret, token)?;
+            }
+            println!("[migrate] Imported {} API token(s)", tokens.len());
+        }
+
+        let mut versions = Vec::new();
+        for entry in fs::read_dir(storage_dir)?.flatten() {
+            // Generated docs live next to the packages
+            if !entry.path().is_dir() || entry.file_name() == "docs" {
+                continue;
+            }
+            for file in fs::read_dir(entry.path())?.flatten() {
+                let path = file.path();
+                if path.extension().and_then(|s| s.to_str()) != Some("json") {
+                    continue;
+                }
+                let metadata: PackageMetadata = match fs::read_to_string(&path)
+                    .map_err(StorageError::from)
+                    .and_then(|content| Ok(serde_json::from_str(&content)?))
+                {
+                    Ok(metadata) => metadata,
+                    Err(e) => {
+                        eprintln!("[migrate] Skipping {}: {}", path.display(), e);
+                        continue;
+                    }
+                };
+                let tarball_path = path.with_file_name(format!("{}.tar.gz", metadata.version));
+                let Ok(tarball) = fs::read(&tarball_path) else {
+                    eprintln!("[migrate] Skipping {}: no tarball", path.display());
+                    continue;
+                };
+                // The tarball was written when the version was published
+                let published = fs::metadata(&tarball_path).and_then(|m| m.modified()).ok();
+                versions.push((published, metadata, tarball));
+            }
+        }
+        versions.sort_by_key(|(published, _, _)| *published);
+        for (_, metadata, tarball) in &versions {
+            insert_package(&tx, metadata, tarball)?;
+        }
+        if !versions.is_empty() {
+            println!("[migrate] Imported {} package version(s)", versions.len());
+        }
+
+        tx.execute(
+            "INSERT INTO meta (key, value) VALUES ('legacy_import', ?1)",
+            params![Utc::now().to_rfc3339()],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+fn insert_package(
+    conn: &Connection,
+    metadata: &PackageMetadata,
+    tarball: &[u8],
+) -> StorageResult<()> {
+    let result = conn.execute(
+        "INSERT INTO packages (name, version, metadata, tarball) VALUES (?1, ?2, ?3, ?4)",
+        params![
+            metadata.name,
+            metadata.version,
+            serde_json::to_string(metadata)?,
+            tarball
+        ],
+    );
+    match result {
+        Ok(_) => Ok(()),
+        Err(rusqlite::Error::SqliteFailure(e, _))
+            if e.code == rusqlite::ErrorCode::ConstraintViolation =>
+        {
+            Err(StorageError::Conflict)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn insert_user(conn: &Connection, user: &User) -> StorageResult<()> {
+    conn.execute(
+        "INSERT INTO users (github_id, username, avatar_url, created_at) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT (github_id) DO UPDATE SET username = ?2, avatar_url = ?3",
+        params![
+            user.github_id,
+            user.username,
+            user.avatar_url,
+            user.created_at
+        ],
+    )?;
+    Ok(())
+}
+
+fn insert_token(conn: &Connection, secret: &str, token: &ApiToken) -> StorageResult<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO tokens (secret, id, name, user_github_id, created_at, last_used_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            secret,
+            token.id,
+            token.name,
+            token.user_github_id,
+            token.created_at,
+            token.last_used_at
+        ],
+    )?;
+    Ok(())
+}
+
+fn metadata_from_json(json: String) -> rusqlite::Result<PackageMetadata> {
+    serde_json::from_str(&json).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e))
+    })
+}
+
+fn token_from_row(row: &rusqlite::Row) -> rusqlite::Result<ApiToken> {
+    Ok(ApiToken {
+        id: row.get("id")?,
+        name: row.get("name")?,
+        user_github_id: row.get("user_github_id")?,
+        token_hash: row.get("secret")?,
+        created_at: row.get::<_, DateTime<Utc>>("created_at")?,
+        last_used_at: row.get("last_used_at")?,
+    })
+}
+
+impl Storage for SqliteStorage {
+    fn packages(&self) -> StorageResult<BTreeMap<String, Vec<PackageMetadata>>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT metadata FROM packages ORDER BY id")?;
+        let mut packages: BTreeMap<String, Vec<PackageMetadata>> = BTreeMap::new();
+        for metadata in stmt.query_map([], |row| metadata_from_json(row.get(0)?))? {
+            let metadata = metadata?;
+            packages
+                .entry(metadata.name.clone())
+                .or_default()
+                .push(metadata);
+        }
+        Ok(packages)
+    }
+
+    fn versions(&self, name: &str) -> StorageResult<Vec<PackageMetadata>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT metadata FROM packages WHERE name = ?1 ORDER BY id")?;
+        let versions = stmt
+            .query_map([name], |row| metadata_from_json(row.get(0)?))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(versions)
+    }
+
+    fn tarball(&self, name: &str, version: &str) -> StorageResult<Option<Vec<u8>>> {
+        let conn = self.conn.lock().unwrap();
+        Ok(conn
+            .query_row(
+                "SELECT tarball FROM packages WHERE name = ?1 AND version = ?2",
+                params![name, version],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    fn publish(&self, metadata: &PackageMetadata, tarball: &[u8]) -> StorageResult<()> {
+        let conn = self.conn.lock().unwrap();
+        insert_package(&conn, metadata, tarball)
+    }
+
+    fn set_yanked(
+        &self,
+        name: &str,
+        version: &str,
+        yanked: bool,
+    ) -> StorageResult<Option<PackageMetadata>> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let metadata = tx
+            .query_row(
+                "SELECT metadata FROM packages WHERE name = ?1 AND version = ?2",
+                params![name, version],
+                |row| metadata_from_json(row.get(0)?),
+            )
+            .optional()?;
+        let Some(mut metadata) = metadata else {
+            return Ok(None);
+        };
+        metadata.yanked = yanked;
+        tx.execute(
+            "UPDATE packages SET metadata = ?3 WHERE name = ?1 AND version = ?2",
+            params![name, version, serde_json::to_string(&metadata)?],
+        )?;
+        tx.commit()?;
+        Ok(Some(metadata))
+    }
+
+    fn user(&self, github_id: u64) -> StorageResult<Option<User>> {
+        let conn = self.conn.lock().unwrap();
+        Ok(conn
+            .query_row(
+                "SELECT github_id, username, avatar_url, created_at FROM users WHERE github_id = ?1",
+                [github_id],
+                |row| {
+                    Ok(User {
+                        github_id: row.get(0)?,
+                        username: row.get(1)?,
+                        avatar_url: row.get(2)?,
+                        created_at: row.get(3)?,
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    fn user_count(&self) -> StorageResult<usize> {
+        let conn = self.conn.lock().unwrap();
+        Ok(conn.query_row("SELECT COUNT(*) FROM users", [], |row| row.get(0))?)
+    }
+
+    fn save_user(&self, user: &User) -> StorageResult<()> {
+        let conn = self.conn.lock().unwrap();
+        insert_user(&conn, user)
+    }
+
+    fn token(&self, secret: &str) -> StorageResult<Option<ApiToken>> {
+        let conn = self.conn.lock().unwrap();
+        Ok(conn
+            .query_row(
+                "SELECT * FROM tokens WHERE secret = ?1",
+                [secret],
+                token_from_row,
+            )
+            .optional()?)
+    }
+
+    fn user_tokens(&self, user_github_id: u64) -> StorageResult<Vec<ApiToken>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt =
+            conn.prepare("SELECT * FROM tokens WHERE user_github_id = ?1 ORDER BY created_at")?;
+        let tokens = stmt
+            .query_map([user_github_id], token_from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(tokens)
+    }
+
+    fn save_token(&self, secret: &str, token: &ApiToken) -> StorageResult<()> {
+        let conn = self.conn.lock().unwrap();
+        insert_token(&conn, secret, token)
+    }
+
+    fn revoke_token(&self, id: &str, user_github_id: u64) -> StorageResult<bool> {
+        let conn = self.conn.lock().unwrap();
+        let deleted = conn.execute(
+            "DELETE FROM tokens WHERE id = ?1 AND user_github_id = ?2",
+            params![id, user_github_id],
+        )?;
+        Ok(deleted > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(name: &str, version: &str) -> PackageMetadata {
+        PackageMetadata {
+            name: name.to_string(),
+            version: version.to_string(),
+            description: None,
+            manifest: serde_json::json!({}),
+            repository: None,
+            authors: Vec::new(),
+            license: None,
+            owners: vec!["octocat".to_string()],
+            yanked: false,
+        }
+    }
+
+    #[test]
+    fn test_import_legacy_files() {
+        let dir = std::env::temp_dir().join(format!("loft-registry-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join("geo")).unwrap();
+        fs::create_dir_all(dir.join("docs/geo/1.0.0")).unwrap();
+        fs::write(
+            dir.join("users.json"),
+            r#"{ "7": { "github_id": 7, "username": "octocat", "avatar_url": null, "created_at": "2025-01-01T00:00:00Z" } }"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("tokens.json"),
+            r#"{ "s3cret": { "id": "t1", "name": "ci", "user_github_id": 7, "token_hash": "s3cret", "created_at": "2025-01-01T00:00:00Z", "last_used_at": null } }"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("geo/1.0.0.json"),
+            serde_json::to_string(&metadata("geo", "1.0.0")).unwrap(),
+        )
+        .unwrap();
+        fs::write(dir.join("geo/1.0.0.tar.gz"), b"tarball").unwrap();
+
+        let storage = SqliteStorage::open(&dir).unwrap();
+        assert_eq!(storage.user(7).unwrap().unwrap().username, "octocat");
+        assert_eq!(storage.token("s3cret").unwrap().unwrap().id, "t1");
+        assert_eq!(storage.versions("geo").unwrap().len(), 1);
+        assert_eq!(
+            storage.tarball("geo", "1.0.0").unwrap().as_deref(),
+            Some(&b"tarball"[..])
+        );
+
+        // Reopening doesn't import the files again
+        drop(storage);
+        let storage = SqliteStorage::open(&dir).unwrap();
+        assert_eq!(storage.versions("geo").unwrap().len(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_publish_yank_and_tokens() {
+        let storage = SqliteStorage::in_memory().unwrap();
+        storage.publish(&metadata("geo", "1.0.0"), b"one").unwrap();
+        storage.publish(&metadata("geo", "1.1.0"), b"two").unwrap();
+        assert!(matches!(
+            storage.publish(&metadata("geo", "1.1.0"), b"again"),
+            Err(StorageError::Conflict)
+        ));
+
+        let yanked = storage.set_yanked("geo", "1.1.0", true).unwrap().unwrap();
+        assert!(yanked.yanked);
+        assert!(storage.set_yanked("geo", "2.0.0", true).unwrap().is_none());
+        let versions: Vec<(String, bool)> = storage.packages().unwrap()["geo"]
+            .iter()
+            .map(|m| (m.version.clone(), m.yanked))
+            .collect();
+        assert_eq!(
+            versions,
+            vec![("1.0.0".to_string(), false), ("1.1.0".to_string(), true)]
+        );
+
+        let token = ApiToken {
+            id: "t1".to_string(),
+            name: "ci".to_string(),
+            user_github_id: 7,
+            token_hash: "s3cret".to_string(),
+            created_at: Utc::now(),
+            last_used_at: None,
+        };
+        storage.save_token("s3cret", &token).unwrap();
+        assert_eq!(storage.user_tokens(7).unwrap().len(), 1);
+        assert!(!storage.revoke_token("t1", 8).unwrap());
+        assert!(storage.revoke_token("t1", 7).unwrap());
+        assert!(storage.token("s3cret").unwrap().is_none());
+    }
+}
@@ -15,6 +15,7 @@ const MAPPING = {
     'math': 'math',
     'object.rs': 'object',
     'random.rs': 'random',
+    'sqlite.rs': 'sqlite',
     'string': 'string', // string builtin
     'term.rs': 'term',
     'test.rs': 'test',
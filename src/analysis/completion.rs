@@ -0,0 +1,276 @@
+//! Completions for editors that don't go through the language server, like
+//! the web playground: keywords, the document's symbols, builtins, and the
+//! members of whatever is before a `.`.
+
+use super::stdlib::{StdlibMethod, StdlibTypes};
+use super::symbols::{extract_symbols, SymbolInfo, SymbolKind};
+use crate::parser::token_stream::KEYWORDS;
+use crate::parser::{InputStream, Parser};
+use serde::Serialize;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompletionKind {
+    Keyword,
+    Variable,
+    Constant,
+    Function,
+    Method,
+    Field,
+    Struct,
+    Trait,
+    Enum,
+    Variant,
+    Module,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Completion {
+    pub label: String,
+    pub kind: CompletionKind,
+    pub detail: Option<String>,
+    pub documentation: Option<String>,
+}
+
+impl Completion {
+    fn new(label: impl Into<String>, kind: CompletionKind) -> Self {
+        Completion {
+            label: label.into(),
+            kind,
+            detail: None,
+            documentation: None,
+        }
+    }
+
+    fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    fn with_documentation(mut self, documentation: &str) -> Self {
+        if !documentation.is_empty() {
+            self.documentation = Some(documentation.to_string());
+        }
+        self
+    }
+}
+
+/// Completions at the zero-based `line` and `column` (in characters) of
+/// `source`, keeping those that start with the word being typed
+pub fn completions(
+    source: &str,
+    line: usize,
+    column: usize,
+    stdlib_types: &StdlibTypes,
+) -> Vec<Completion> {
+    let text = source.lines().nth(line).unwrap_or("");
+    let before: String = text.chars().take(column).collect();
+    let word_start = before
+        .rfind(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .map_or(0, |i| i + 1);
+    let (before, word) = before.split_at(word_start);
+
+    let source = source.to_string();
+    let (stmts, _) = Parser::new(InputStream::new("completion", &source)).parse_recoverable();
+    let symbols = extract_symbols(&stmts, 0, stdlib_types);
+
+    let object = before
+        .strip_suffix('.')
+        .or_else(|| before.strip_suffix("::"));
+    let mut items = match object {
+        Some(object) => {
+            let object_start = object
+                .rfind(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .map_or(0, |i| i + 1);
+            member_completions(&object[object_start..], &symbols, stdlib_types)
+        }
+        None => scope_completions(&symbols, stdlib_types),
+    };
+    items.retain(|item| item.label.starts_with(word));
+    items
+}
+
+/// Everything that can start an expression or statement
+fn scope_completions(symbols: &[SymbolInfo], stdlib_types: &StdlibTypes) -> Vec<Completion> {
+    let mut items: Vec<Completion> = Vec::new();
+    for symbol in symbols {
+        if items.iter().any(|item| item.label == symbol.name) {
+            continue;
+        }
+        let kind = match &symbol.kind {
+            SymbolKind::Variable { .. } => CompletionKind::Variable,
+            SymbolKind::Function { .. } => CompletionKind::Function,
+            SymbolKind::Struct { .. } => CompletionKind::Struct,
+            SymbolKind::Trait { .. } => CompletionKind::Trait,
+            SymbolKind::Enum { .. } => CompletionKind::Enum,
+            SymbolKind::Constant { .. } => CompletionKind::Constant,
+            SymbolKind::Import { .. } => CompletionKind::Module,
+        };
+        let mut item = Completion::new(&symbol.name, kind);
+        item.detail = symbol.detail.clone();
+        item.documentation = symbol.documentation.clone();
+        items.push(item);
+    }
+
+    let mut builtins: Vec<_> = stdlib_types.builtins.iter().collect();
+    builtins.sort_by_key(|(name, _)| *name);
+    for (name, builtin) in builtins {
+        items.push(
+            Completion::new(name, CompletionKind::Module)
+                .with_detail(&builtin.kind)
+                .with_documentation(&builtin.documentation),
+        );
+    }
+
+    items.extend(
+        KEYWORDS
+            .iter()
+            .map(|keyword| Completion::new(*keyword, CompletionKind::Keyword)),
+    );
+    items
+}
+
+/// Members of `object`: a builtin's methods and constants, the variants of an
+/// enum, or the fields and methods of a variable's type
+fn member_completions(
+    object: &str,
+    symbols: &[SymbolInfo],
+    stdlib_types: &StdlibTypes,
+) -> Vec<Completion> {
+    let mut items = Vec::new();
+
+    if let Some(builtin) = stdlib_types.builtins.get(object) {
+        items.extend(methods(&builtin.methods));
+        let mut constants: Vec<_> = builtin.constants.iter().collect();
+        constants.sort_by_key(|(name, _)| *name);
+        for (name, constant) in constants {
+            items.push(
+                Completion::new(name, CompletionKind::Constant)
+                    .with_detail(&constant.const_type)
+                    .with_documentation(&constant.documentation),
+            );
+        }
+        return items;
+    }
+
+    if let Some(ty) = stdlib_types.types.get(object) {
+        for variant in ty.variants.iter().map(|v| v.name()) {
+            items.push(
+                Completion::new(variant, CompletionKind::Variant)
+                    .with_detail(format!("{}::{}", object, variant)),
+            );
+        }
+        return items;
+    }
+
+    let Some(symbol) = symbols.iter().rev().find(|s| s.name == object) else {
+        return items;
+    };
+    match &symbol.kind {
+        SymbolKind::Enum { variants } => {
+            for (variant, _) in variants {
+                items.push(
+                    Completion::new(variant, CompletionKind::Variant)
+                        .with_detail(format!("{}::{}", object, variant)),
+                );
+            }
+        }
+        SymbolKind::Struct { associated, .. } => {
+            for function in associated {
+                items.push(
+                    Completion::new(function, CompletionKind::Function)
+                        .with_detail(format!("{}.{}", object, function)),
+                );
+            }
+        }
+        SymbolKind::Variable {
+            var_type: Some(type_name),
+            ..
+        } => {
+            if type_name == "str" {
+                items.extend(methods(&stdlib_types.string_methods));
+            } else if type_name.starts_with("Array") {
+                items.extend(methods(&stdlib_types.array_methods));
+            } else if let Some(SymbolKind::Struct {
+                fields, methods, ..
+            }) = symbols
+                .iter()
+                .find(|s| &s.name == type_name)
+                .map(|s| &s.kind)
+            {
+                for (field, field_type) in fields {
+                    items.push(
+                        Completion::new(field, CompletionKind::Field).with_detail(field_type),
+                    );
+                }
+                items.extend(
+                    methods
+                        .iter()
+                        .map(|method| Completion::new(method, CompletionKind::Method)),
+                );
+            } else if let Some(ty) = stdlib_types.types.get(type_name) {
+                let mut fields: Vec<_> = ty.fields.iter().collect();
+                fields.sort_by_key(|(name, _)| *name);
+                for (name, field) in fields {
+                    items.push(
+                        Completion::new(name, CompletionKind::Field)
+                            .with_detail(&field.field_type)
+                            .with_documentation(&field.documentation),
+                    );
+                }
+                items.extend(methods(&ty.methods));
+            }
+        }
+        _ => {}
+    }
+    items
+}
+
+/// Completions for a set of stdlib methods, sorted by name
+fn methods(methods: &HashMap<String, StdlibMethod>) -> Vec<Completion> {
+    let mut methods: Vec<_> = methods.iter().collect();
+    methods.sort_by_key(|(name, _)| *name);
+    methods
+        .into_iter()
+        .map(|(name, method)| {
+            Completion::new(name, CompletionKind::Method)
+                .with_detail(format!(
+                    "{}({}) -> {}",
+                    name,
+                    method.params.join(", "),
+                    method.return_type
+                ))
+                .with_documentation(&method.documentation)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::stdlib;
+
+    fn labels(source: &str, line: usize, column: usize) -> Vec<String> {
+        completions(source, line, column, &stdlib::load())
+            .into_iter()
+            .map(|item| item.label)
+            .collect()
+    }
+
+    #[test]
+    fn test_completions() {
+        let source = "def Point {\n    x: num,\n    y: num\n}\nlet origin = Point { x: 0, y: 0 };\nlet name = \"loft\";\nlet total = 1;\nterm.pr\norigin.\nname.to\nto\nimpl Point {\n    fn new(x: num, y: num) -> Point { return Point { x: x, y: y }; }\n    fn sum(self) -> num { return self.x + self.y; }\n}\nPoint::\nPoint.n";
+
+        assert_eq!(labels(source, 7, 7), ["print", "println", "progress"]);
+        assert_eq!(labels(source, 8, 7), ["x", "y", "sum"]);
+        assert!(labels(source, 9, 7).contains(&"to_upper".to_string()));
+        assert_eq!(labels(source, 15, 7), ["new"]);
+        assert_eq!(labels(source, 16, 7), ["new"]);
+
+        let items = completions(source, 10, 2, &stdlib::load());
+        let total = items.iter().find(|item| item.label == "total").unwrap();
+        assert_eq!(total.kind, CompletionKind::Variable);
+        assert!(items.iter().all(|item| item.label.starts_with("to")));
+    }
+}
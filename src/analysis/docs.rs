@@ -0,0 +1,265 @@
+//! Doc comments attached to declarations.
+
+use super::symbols::SymbolInfo;
+use crate::parser::visit::{self, Visitor};
+use crate::parser::{Stmt, StmtKind};
+use std::collections::HashMap;
+
+/// Map each documented declaration name to the text of its `///` or `/** */` comment.
+/// Blank lines and indentation inside the comment are kept, so it can be read as Markdown.
+pub fn extract_doc_comments(stmts: &[Stmt]) -> HashMap<String, String> {
+    let mut docs = DocComments::default();
+    visit::walk_stmts(&mut docs, stmts);
+    docs.0
+}
+
+/// Collects the doc comments of declarations, by name
+#[derive(Default)]
+struct DocComments(HashMap<String, String>);
+
+impl Visitor for DocComments {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        // An attribute comes between a declaration and its doc comment
+        let declaration = match &stmt.kind {
+            StmtKind::AttrStmt { stmt: inner, .. } => &inner.kind,
+            kind => kind,
+        };
+        let name = match declaration {
+            StmtKind::VarDecl { name, .. }
+            | StmtKind::ConstDecl { name, .. }
+            | StmtKind::FunctionDecl { name, .. }
+            | StmtKind::StructDecl { name, .. }
+            | StmtKind::TraitDecl { name, .. }
+            | StmtKind::EnumDecl { name, .. } => Some(name),
+            _ => None,
+        };
+        if let (Some(name), Some(doc)) = (name, stmt.trivia.doc()) {
+            self.0.insert(name.clone(), doc);
+        }
+        visit::walk_stmt(self, stmt);
+    }
+}
+
+/// Name declared by a line such as `teach async fn name(...)` or `let mut name = ...`
+pub fn extract_name_from_declaration(line: &str) -> Option<String> {
+    let line = line.trim();
+    let line = line.strip_prefix("teach ").unwrap_or(line);
+    let line = line.strip_prefix("async ").unwrap_or(line);
+
+    let keywords = [
+        "fn ", "let mut ", "let ", "const ", "def ", "trait ", "struct ", "enum ",
+    ];
+
+    for keyword in keywords {
+        if let Some(rest) = line.strip_prefix(keyword) {
+            // Extract identifier (alphanumeric + underscore)
+            let name: String = rest
+                .trim_start()
+                .chars()
+                .take_while(|c| c.is_alphanumeric() || *c == '_')
+                .collect();
+            if !name.is_empty() {
+                return Some(name);
+            }
+        }
+    }
+
+    None
+}
+
+/// Process doc comments to convert inline links like [module::Item] to markdown code references
+/// This allows referencing other symbols in documentation similar to Rust
+pub fn process_doc_comment(doc: &str) -> String {
+    let mut result = String::new();
+    let chars: Vec<char> = doc.chars().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        if chars[i] == '[' {
+            // Try to parse a link reference like [Item] or [module::Item]
+            let _start = i;
+            i += 1;
+
+            // Collect the content inside brackets
+            let mut link_content = String::new();
+            let mut is_valid_link = true;
+
+            while i < chars.len() && chars[i] != ']' {
+                let ch = chars[i];
+                // Valid characters: alphanumeric, underscore, and ::
+                if ch.is_alphanumeric() || ch == '_' {
+                    link_content.push(ch);
+                } else if ch == ':' && i + 1 < chars.len() && chars[i + 1] == ':' {
+                    link_content.push_str("::");
+                    i += 1; // Skip the second ':'
+                } else {
+                    // Invalid character, not a link reference
+                    is_valid_link = false;
+                    break;
+                }
+                i += 1;
+            }
+
+            if i < chars.len() && chars[i] == ']' && is_valid_link && !link_content.is_empty() {
+                // Valid link reference found
+                // Convert [Item] or [module::Item] to `Item` or `module::Item`
+                result.push('`');
+                result.push_str(&link_content);
+                result.push('`');
+                i += 1; // Skip the closing ]
+            } else {
+                // Not a valid link, copy the original text
+                result.push('[');
+                if !link_content.is_empty() {
+                    result.push_str(&link_content);
+                }
+                // Handle any characters we consumed but didn't add to link_content
+                if i < chars.len() {
+                    if chars[i] == ']' {
+                        result.push(']');
+                        i += 1;
+                    } else if !is_valid_link {
+                        // We hit an invalid character, include it and continue
+                        result.push(chars[i]);
+                        i += 1;
+                    }
+                    // If we reached end without closing bracket, i is already at end
+                }
+            }
+        } else {
+            result.push(chars[i]);
+            i += 1;
+        }
+    }
+
+    result
+}
+
+/// Attach processed doc comments from `stmts` to the symbols they document
+pub fn associate_doc_comments(stmts: &[Stmt], symbols: &mut [SymbolInfo]) {
+    let doc_map = extract_doc_comments(stmts);
+    for symbol in symbols.iter_mut() {
+        if let Some(doc) = doc_map.get(&symbol.name) {
+            symbol.documentation = Some(process_doc_comment(doc));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{InputStream, Parser};
+
+    #[test]
+    fn test_extract_doc_comments() {
+        let source = r#"
+/// This is a documented function
+/// It adds two numbers
+fn add(a: num, b: num) -> num {
+    return a + b;
+}
+
+/** This is a variable with documentation */
+let x = 42;
+
+impl Point {
+    /// Distance from the origin
+    fn len(self) -> num { return 0; }
+}
+
+/// Runs first
+#[test]
+fn check() {}
+"#
+        .to_string();
+
+        let stmts = Parser::new(InputStream::new("test", &source))
+            .parse()
+            .unwrap();
+        let doc_comments = extract_doc_comments(&stmts);
+        assert!(doc_comments.contains_key("add"));
+        assert_eq!(
+            doc_comments.get("add").unwrap(),
+            "This is a documented function\nIt adds two numbers"
+        );
+        assert!(doc_comments.contains_key("x"));
+        assert_eq!(doc_comments["len"], "Distance from the origin");
+        assert_eq!(doc_comments["check"], "Runs first");
+    }
+
+    #[test]
+    fn test_extract_name_from_declaration() {
+        assert_eq!(
+            extract_name_from_declaration("fn test() {}"),
+            Some("test".to_string())
+        );
+        assert_eq!(
+            extract_name_from_declaration("teach fn exported() {}"),
+            Some("exported".to_string())
+        );
+        assert_eq!(
+            extract_name_from_declaration("let x = 42;"),
+            Some("x".to_string())
+        );
+        assert_eq!(
+            extract_name_from_declaration("const PI = 3.14;"),
+            Some("PI".to_string())
+        );
+        assert_eq!(
+            extract_name_from_declaration("def Point {"),
+            Some("Point".to_string())
+        );
+        assert_eq!(
+            extract_name_from_declaration("let mut count = 0;"),
+            Some("count".to_string())
+        );
+        assert_eq!(
+            extract_name_from_declaration("teach async fn fetch() {}"),
+            Some("fetch".to_string())
+        );
+        assert_eq!(
+            extract_name_from_declaration("enum Shape {"),
+            Some("Shape".to_string())
+        );
+    }
+
+    #[test]
+    fn test_doc_comment_link_processing() {
+        // Test that [Item] and [module::Item] are converted to `Item` and `module::Item`
+        let doc1 = "This function uses [Vec] and [std::collections::HashMap]";
+        let processed1 = process_doc_comment(doc1);
+        assert_eq!(
+            processed1,
+            "This function uses `Vec` and `std::collections::HashMap`"
+        );
+
+        // Test with multiple links
+        let doc2 = "See [Option] and [Result] for error handling. Also check [mod::Item].";
+        let processed2 = process_doc_comment(doc2);
+        assert_eq!(
+            processed2,
+            "See `Option` and `Result` for error handling. Also check `mod::Item`."
+        );
+
+        // Test that invalid patterns are not processed
+        let doc3 = "This [has spaces] and [special-char] should not be converted";
+        let processed3 = process_doc_comment(doc3);
+        assert!(processed3.contains("[has spaces]")); // Should remain unchanged
+
+        // Test empty brackets
+        let doc4 = "Empty [] brackets";
+        let processed4 = process_doc_comment(doc4);
+        assert_eq!(processed4, "Empty [] brackets");
+
+        // Test normal markdown is preserved
+        let doc5 = "**Bold** and *italic* and `code` should be preserved";
+        let processed5 = process_doc_comment(doc5);
+        assert_eq!(processed5, doc5);
+
+        // Test edge case: unclosed bracket doesn't cause infinite loop
+        let doc6 = "This [has no closing bracket and continues";
+        let processed6 = process_doc_comment(doc6);
+        assert!(processed6.starts_with("This ["));
+        assert!(processed6.contains("no closing bracket"));
+    }
+}
@@ -0,0 +1,327 @@
+//! Suggested changes for diagnostics, and applying them to a document.
+//!
+//! Checks attach [`Fix`]es to the diagnostics they report. The language
+//! server offers every fix as a quick fix, and `loft fix` applies the
+//! preferred ones to files with [`fix_source`].
+
+use super::semantic::builtin_names;
+use super::stdlib::StdlibTypes;
+use super::symbols::SymbolInfo;
+use super::{check_parsed, Diagnostic, Fix, Position, Range, TextEdit};
+use crate::lints::{self, LintConfig};
+use crate::parser::{InputStream, Parser};
+
+/// How many times [`fix_source`] checks a file again after fixing it
+const MAX_PASSES: usize = 10;
+
+/// How many single-character insertions, deletions and substitutions turn `a` into `b`
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous + usize::from(ca != cb);
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(previous + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// The names closest to `name` that it's likely a typo of, best first
+fn similar_names<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Vec<&'a str> {
+    // Names of up to four characters are only a typo of something one edit away
+    let length = name.chars().count();
+    let mut best = Vec::new();
+    let mut best_distance = ((length + 1) / 3).max(1);
+    for candidate in candidates {
+        let distance = edit_distance(name, candidate);
+        if distance == 0 || distance >= length || distance > best_distance {
+            continue;
+        }
+        if distance < best_distance {
+            best.clear();
+            best_distance = distance;
+        }
+        if !best.contains(&candidate) {
+            best.push(candidate);
+        }
+    }
+    best
+}
+
+/// The line a new `learn` goes on: after the last import, or before the
+/// first line of code when there are none
+pub fn import_line(source: &str) -> u32 {
+    let lines: Vec<&str> = source.lines().collect();
+    if let Some(last) = lines
+        .iter()
+        .rposition(|line| line.trim_start().starts_with("learn "))
+    {
+        return last as u32 + 1;
+    }
+    lines
+        .iter()
+        .position(|line| {
+            let trimmed = line.trim();
+            !trimmed.is_empty() && !trimmed.starts_with("//") && !trimmed.starts_with("/*")
+        })
+        .unwrap_or(0) as u32
+}
+
+/// Add fixes for undefined identifiers: a name it's likely a typo of, or a
+/// `learn` of the builtin module that exports it
+pub fn suggest(
+    diagnostics: &mut [Diagnostic],
+    symbols: &[SymbolInfo],
+    source: &str,
+    stdlib_types: &StdlibTypes,
+) {
+    for diagnostic in diagnostics {
+        if diagnostic.code.as_deref() != Some("undefined_identifier") {
+            continue;
+        }
+        let range = diagnostic.range;
+        let (Some(start), Some(end)) = (offset(source, range.start), offset(source, range.end))
+        else {
+            continue;
+        };
+        let name = &source[start..end];
+        if name.contains("::") {
+            continue;
+        }
+
+        let mut modules: Vec<&str> = stdlib_types
+            .builtins
+            .iter()
+            .filter(|(_, builtin)| {
+                builtin.methods.contains_key(name) || builtin.constants.contains_key(name)
+            })
+            .map(|(module, _)| module.as_str())
+            .collect();
+        modules.sort();
+        let import_at = Position {
+            line: import_line(source),
+            character: 0,
+        };
+        for module in &modules {
+            let import = format!("learn {{ {} }} from \"{}\";", name, module);
+            diagnostic.fixes.push(Fix {
+                title: format!("Add `{}`", import),
+                edits: vec![TextEdit {
+                    range: Range {
+                        start: import_at,
+                        end: import_at,
+                    },
+                    new_text: import + "\n",
+                }],
+                preferred: modules.len() == 1,
+            });
+        }
+
+        let candidates = symbols
+            .iter()
+            .map(|symbol| symbol.name.as_str())
+            .chain(builtin_names().map(|builtin| builtin as &str));
+        let similar = similar_names(name, candidates);
+        for candidate in &similar {
+            diagnostic.fixes.push(Fix {
+                title: format!("Change to '{}'", candidate),
+                edits: vec![TextEdit {
+                    range,
+                    new_text: candidate.to_string(),
+                }],
+                preferred: modules.is_empty() && similar.len() == 1,
+            });
+        }
+    }
+}
+
+/// Byte offset of `position` in `source`, if it is inside it
+fn offset(source: &str, position: Position) -> Option<usize> {
+    let mut line_start = 0;
+    for _ in 0..position.line {
+        line_start += source[line_start..].find('\n')? + 1;
+    }
+    let line = source[line_start..].split('\n').next().unwrap_or("");
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    line.char_indices()
+        .map(|(i, _)| i)
+        .chain([line.len()])
+        .nth(position.character as usize)
+        .map(|i| line_start + i)
+}
+
+/// Apply `fixes` to `source`. A fix whose edits overlap one applied before
+/// it is skipped, and so is one that only repeats edits already made, like
+/// the same `learn` suggested for every use of a name. Returns the new text
+/// and how many fixes were applied.
+pub fn apply<'a>(source: &str, fixes: impl IntoIterator<Item = &'a Fix>) -> (String, usize) {
+    let mut accepted: Vec<(usize, usize, &str)> = Vec::new();
+    let mut applied = 0;
+    'fixes: for fix in fixes {
+        let mut edits = Vec::new();
+        for edit in &fix.edits {
+            let (Some(start), Some(end)) = (
+                offset(source, edit.range.start),
+                offset(source, edit.range.end),
+            ) else {
+                continue 'fixes;
+            };
+            edits.push((start, end, edit.new_text.as_str()));
+        }
+        edits.retain(|edit| !accepted.contains(edit));
+        if edits.is_empty() {
+            continue;
+        }
+        let overlaps = edits.iter().any(|&(start, end, _)| {
+            accepted
+                .iter()
+                .any(|&(other_start, other_end, _)| start < other_end && other_start < end)
+        });
+        if !overlaps {
+            accepted.extend(edits);
+            applied += 1;
+        }
+    }
+
+    // Later edits first, so earlier offsets stay valid. Insertions at the
+    // same place end up in the order they were given.
+    let mut ordered: Vec<_> = accepted.into_iter().enumerate().collect();
+    ordered.sort_by_key(|&(i, (start, end, _))| std::cmp::Reverse((start, end, i)));
+    let mut result = source.to_string();
+    for (_, (start, end, text)) in ordered {
+        result.replace_range(start..end, text);
+    }
+    (result, applied)
+}
+
+/// Apply every preferred fix to a file, checking it again afterwards since
+/// one fix can uncover another problem, like code the parser had skipped.
+/// Returns the new text and how many fixes were applied.
+pub fn fix_source(
+    name: &str,
+    source: &str,
+    lints: &LintConfig,
+    stdlib_types: &StdlibTypes,
+) -> (String, usize) {
+    let mut source = source.to_string();
+    let mut total = 0;
+    for _ in 0..MAX_PASSES {
+        let (stmts, errors) = Parser::new(InputStream::new(name, &source)).parse_recoverable();
+        let mut diagnostics = check_parsed(&stmts, &errors, &source, stdlib_types);
+        diagnostics.extend(lints::check(&stmts, &source, lints));
+
+        let preferred = diagnostics
+            .iter()
+            .flat_map(|diagnostic| &diagnostic.fixes)
+            .filter(|fix| fix.preferred);
+        let (fixed, applied) = apply(&source, preferred);
+        if applied == 0 {
+            break;
+        }
+        source = fixed;
+        total += applied;
+    }
+    (source, total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::{check_source, stdlib};
+
+    fn fixes(source: &str) -> Vec<(String, bool)> {
+        check_source("test.lf", source, &stdlib::load())
+            .into_iter()
+            .flat_map(|d| d.fixes)
+            .map(|fix| (fix.title, fix.preferred))
+            .collect()
+    }
+
+    #[test]
+    fn test_did_you_mean() {
+        let source = "let count = 1;\nterm.println(cuont);\nterm.println(x);\n";
+        assert_eq!(fixes(source), [("Change to 'count'".to_string(), true)]);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn test_learn_from_the_module_that_exports_a_name() {
+        let source = "// Roots\nlet x = sqrt(4);\nterm.println(x);\n";
+        let diagnostics = check_source("test.lf", source, &stdlib::load());
+        let fixes: Vec<&Fix> = diagnostics.iter().flat_map(|d| &d.fixes).collect();
+        assert_eq!(fixes[0].title, "Add `learn { sqrt } from \"math\";`");
+        assert!(fixes[0].preferred);
+
+        let (fixed, applied) = apply(source, fixes.into_iter().filter(|f| f.preferred));
+        assert_eq!(applied, 1);
+        assert_eq!(
+            fixed,
+            "// Roots\nlearn { sqrt } from \"math\";\nlet x = sqrt(4);\nterm.println(x);\n"
+        );
+    }
+
+    #[test]
+    fn test_fix_source() {
+        let source =
+            "let count = abs(-2)\nterm.println(cuont);\nterm.println(abs(count), pow(2, 3));\n";
+        let (fixed, applied) =
+            fix_source("test.lf", source, &LintConfig::default(), &stdlib::load());
+        assert_eq!(
+            fixed,
+            "learn { abs } from \"math\";\nlearn { pow } from \"math\";\nlet count = abs(-2);\nterm.println(count);\nterm.println(abs(count), pow(2, 3));\n"
+        );
+        assert_eq!(applied, 4);
+    }
+
+    #[test]
+    fn test_match_arms_are_left_alone() {
+        let source = "enum Color { Red, Green }
+let c = Color.Red;
+match c {
+    Color.Red => term.println(\"r\"),
+    Color.Green => { term.println(\"g\"); },
+}
+let name = match c {
+    Color.Red => \"red\",
+    Color.Green => \"green\",
+};
+term.println(name);
+";
+        let (stmts, _) = Parser::new(InputStream::new("test.lf", &source.to_string()))
+            .parse_recoverable();
+        let findings = lints::check(&stmts, source, &LintConfig::default());
+        assert!(findings.is_empty(), "{:?}", findings);
+        assert_eq!(
+            fix_source("test.lf", source, &LintConfig::default(), &stdlib::load()),
+            (source.to_string(), 0)
+        );
+    }
+
+    #[test]
+    fn test_apply_skips_repeated_and_overlapping_edits() {
+        let edit = |line, start, end, text: &str| Fix {
+            title: String::new(),
+            edits: vec![TextEdit {
+                range: Range::on_line(line, start, end),
+                new_text: text.to_string(),
+            }],
+            preferred: true,
+        };
+        let source = "let héllo = 1;\nlet b = 2\n";
+        let fixes = [
+            edit(1, 9, 9, ";"),
+            edit(1, 9, 9, ";"),
+            edit(0, 4, 9, "hi"),
+            edit(0, 6, 7, "x"),
+        ];
+        assert_eq!(
+            apply(source, &fixes),
+            ("let hi = 1;\nlet b = 2;\n".to_string(), 2)
+        );
+    }
+}
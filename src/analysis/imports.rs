@@ -0,0 +1,232 @@
+//! Organizing the `learn` statements at the top of a document: sorting them,
+//! dropping unused ones and merging duplicates.
+
+use super::{Position, Range};
+use crate::parser::{ImportItem, Span, Stmt, StmtKind};
+
+/// One import as it will be written back
+#[derive(Debug, Clone, PartialEq)]
+struct Import {
+    module: String,
+    alias: Option<String>,
+    items: Option<Vec<(String, Option<String>)>>,
+    is_exported: bool,
+    /// Comments on the lines before it
+    comments: Vec<String>,
+    /// Comment after it on its line
+    trailing: Option<String>,
+}
+
+impl Import {
+    fn render(&self) -> String {
+        let mut text = String::new();
+        for comment in &self.comments {
+            text.push_str(comment);
+            text.push('\n');
+        }
+        text.push_str(&self.render_statement());
+        if let Some(trailing) = &self.trailing {
+            text.push(' ');
+            text.push_str(trailing);
+        }
+        text
+    }
+
+    fn render_statement(&self) -> String {
+        let teach = if self.is_exported { "teach " } else { "" };
+        match (&self.items, &self.alias) {
+            (Some(items), _) => {
+                let items: Vec<String> = items
+                    .iter()
+                    .map(|(name, alias)| match alias {
+                        Some(alias) => format!("{} as {}", name, alias),
+                        None => name.clone(),
+                    })
+                    .collect();
+                format!(
+                    "{}learn {{ {} }} from \"{}\";",
+                    teach,
+                    items.join(", "),
+                    self.module
+                )
+            }
+            (None, Some(alias)) => format!("{}learn \"{}\" as {};", teach, self.module, alias),
+            (None, None) => format!("{}learn \"{}\";", teach, self.module),
+        }
+    }
+}
+
+/// Rewrite the block of imports that starts at the first top-level `learn`:
+/// imports are sorted by module, `teach learn` re-exports last, those starting
+/// at a line in `unused` are removed, and selective imports of the same module
+/// are merged. Comments move along with the import they are written before
+/// or after, and those of a removed import go to the top of the block.
+/// Comments before the first import stay where they are, as they are usually
+/// about the whole file. Returns the edit, or `None` when the imports are
+/// already organized.
+pub fn organize_imports(stmts: &[Stmt], source: &str, unused: &[u32]) -> Option<(Range, String)> {
+    // The block ends at the first statement that isn't an import
+    let first = stmts
+        .iter()
+        .position(|s| matches!(s.kind, StmtKind::ImportDecl { .. }))?;
+    let mut block: Vec<&Stmt> = vec![&stmts[first]];
+    for stmt in &stmts[first + 1..] {
+        if !matches!(stmt.kind, StmtKind::ImportDecl { .. }) {
+            break;
+        }
+        block.push(stmt);
+    }
+
+    let comments = |stmt: &Stmt| -> Vec<String> {
+        let leading = if std::ptr::eq(stmt, block[0]) {
+            &[][..]
+        } else {
+            &stmt.trivia.leading[..]
+        };
+        leading.iter().map(|comment| comment.text.clone()).collect()
+    };
+    let mut removed_comments = Vec::new();
+    let mut imports: Vec<Import> = Vec::new();
+    for stmt in &block {
+        let StmtKind::ImportDecl {
+            path,
+            alias,
+            items,
+            is_exported,
+        } = &stmt.kind
+        else {
+            continue;
+        };
+        let trailing = stmt.trivia.trailing.as_ref().map(|c| c.text.clone());
+        if unused.contains(&(stmt.span.line as u32)) {
+            removed_comments.extend(comments(stmt).into_iter().chain(trailing));
+            continue;
+        }
+        let import = Import {
+            module: path.join("::"),
+            alias: alias.clone(),
+            items: items.as_ref().map(|items| {
+                items
+                    .iter()
+                    .map(|ImportItem { name, alias }| (name.clone(), alias.clone()))
+                    .collect()
+            }),
+            is_exported: *is_exported,
+            comments: comments(stmt),
+            trailing,
+        };
+
+        let merge_into = imports.iter_mut().find(|existing| {
+            existing.module == import.module
+                && existing.is_exported == import.is_exported
+                && existing.items.is_some() == import.items.is_some()
+                && (existing.items.is_some() || existing.alias == import.alias)
+        });
+        let Some(existing) = merge_into else {
+            imports.push(import);
+            continue;
+        };
+        existing.comments.extend(import.comments);
+        existing.comments.extend(import.trailing);
+        // Nothing more to merge for the same whole-module import twice
+        if let (Some(existing_items), Some(items)) = (&mut existing.items, import.items) {
+            for item in items {
+                if !existing_items.contains(&item) {
+                    existing_items.push(item);
+                }
+            }
+        }
+    }
+
+    for import in &mut imports {
+        if let Some(items) = &mut import.items {
+            items.sort();
+        }
+    }
+    imports.sort_by(|a, b| {
+        (a.is_exported, &a.module, a.items.is_some(), &a.alias).cmp(&(
+            b.is_exported,
+            &b.module,
+            b.items.is_some(),
+            &b.alias,
+        ))
+    });
+
+    let lines: Vec<&str> = source.lines().collect();
+    let last = block.last()?;
+    let span = Span {
+        end: last
+            .trivia
+            .trailing
+            .as_ref()
+            .map_or(last.span.end, |c| c.span.end),
+        ..block[0].span
+    };
+    let mut range = Range::from_span(span, &lines);
+    let current = source.get(block[0].span.start..span.end)?;
+    let organized = removed_comments
+        .into_iter()
+        .chain(imports.iter().map(Import::render))
+        .collect::<Vec<_>>()
+        .join("\n");
+    if organized == current {
+        return None;
+    }
+
+    // Removing every import takes the line break with it
+    if organized.is_empty() && (range.end.line as usize + 1) < lines.len() {
+        range.end = Position {
+            line: range.end.line + 1,
+            character: 0,
+        };
+    }
+    Some((range, organized))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{InputStream, Parser};
+
+    fn organize(source: &str, unused: &[u32]) -> Option<(Range, String)> {
+        let source = source.to_string();
+        let stmts = Parser::new(InputStream::new("test.lf", &source))
+            .parse()
+            .unwrap();
+        organize_imports(&stmts, &source, unused)
+    }
+
+    #[test]
+    fn test_organize_imports() {
+        let source = "// Tools\nteach learn \"./shapes\";\nlearn { sqrt } from \"math\";\nlearn \"./util\";\nlearn \"./old\";\nlearn { PI, abs as absolute } from \"math\";\nlearn \"./util\";\n\nterm.println(util.x);\n";
+        let (range, text) = organize(source, &[4]).unwrap();
+
+        assert_eq!((range.start.line, range.start.character), (1, 0));
+        assert_eq!((range.end.line, range.end.character), (6, 15));
+        assert_eq!(
+            text,
+            "learn \"./util\";\nlearn { PI, abs as absolute, sqrt } from \"math\";\nteach learn \"./shapes\";"
+        );
+
+        // Already organized
+        assert_eq!(organize(&format!("{}\nlet x = 1;\n", text), &[]), None);
+
+        // Removing the only import removes its line
+        let (range, text) = organize("learn \"./old\";\nlet x = 1;\n", &[0]).unwrap();
+        assert_eq!((range.end.line, range.end.character), (1, 0));
+        assert_eq!(text, "");
+    }
+
+    #[test]
+    fn test_organize_imports_keeps_comments() {
+        let source = "// Header\nlearn { sqrt } from \"math\"; // roots\n// Old helpers\nlearn \"./old\";\n/* Geometry */\nlearn \"./shapes\";\nlearn { abs } from \"math\"; // sign\n\nlet x = 1;\n";
+        let (range, text) = organize(source, &[3]).unwrap();
+
+        assert_eq!((range.start.line, range.start.character), (1, 0));
+        assert_eq!((range.end.line, range.end.character), (6, 34));
+        assert_eq!(
+            text,
+            "// Old helpers\n/* Geometry */\nlearn \"./shapes\";\n// sign\nlearn { abs, sqrt } from \"math\"; // roots"
+        );
+    }
+}
@@ -0,0 +1,293 @@
+//! Static analysis shared by the language server, the doc generator and
+//! `loft check`.
+//!
+//! Everything here works on parsed source without running it: building the
+//! symbol table, inferring the types of simple expressions, attaching doc
+//! comments and reporting semantic diagnostics.
+
+pub mod completion;
+pub mod docs;
+pub mod fixes;
+pub mod imports;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod project;
+pub mod semantic;
+pub mod stdlib;
+pub mod symbols;
+pub mod typecheck;
+
+use crate::parser::input_stream::Error as ParseError;
+use crate::parser::{InputStream, Parser, Span, Stmt};
+use serde::{Deserialize, Serialize};
+use stdlib::StdlibTypes;
+
+/// Zero-based line and column in a document
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Position {
+    pub line: u32,
+    pub character: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Range {
+    /// Range covering columns `start..end` of a single line
+    pub fn on_line(line: usize, start: usize, end: usize) -> Self {
+        Range {
+            start: Position {
+                line: line as u32,
+                character: start as u32,
+            },
+            end: Position {
+                line: line as u32,
+                character: end as u32,
+            },
+        }
+    }
+
+    /// Range covering a parsed node, given the lines of the source it came from
+    pub fn from_span(span: Span, lines: &[&str]) -> Self {
+        let start = Position {
+            line: span.line as u32,
+            character: span.column as u32,
+        };
+        let mut line = span.line;
+        let mut offset = lines.get(line).map_or(0, |text| {
+            text.char_indices()
+                .nth(span.column)
+                .map_or(text.len(), |(i, _)| i)
+        });
+        let mut remaining = span.len();
+
+        while let Some(text) = lines.get(line) {
+            let rest = text.len().saturating_sub(offset);
+            if remaining <= rest {
+                let end = text
+                    .get(..offset + remaining)
+                    .map_or(text.len(), |t| t.chars().count());
+                return Range {
+                    start,
+                    end: Position {
+                        line: line as u32,
+                        character: end as u32,
+                    },
+                };
+            }
+            // Step over the rest of this line and its newline
+            remaining -= rest + 1;
+            line += 1;
+            offset = 0;
+        }
+
+        let last = lines.len().saturating_sub(1);
+        Range {
+            start,
+            end: Position {
+                line: last as u32,
+                character: lines.get(last).map_or(0, |t| t.chars().count()) as u32,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Hint,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub range: Range,
+    pub severity: Severity,
+    /// Stable identifier used by code actions, e.g. `missing_impl`
+    pub code: Option<String>,
+    pub message: String,
+    /// The flagged code can be removed without changing behaviour
+    pub unnecessary: bool,
+    /// Extra information for quick fixes
+    pub data: Option<serde_json::Value>,
+    /// Changes that would resolve the diagnostic, offered as quick fixes
+    pub fixes: Vec<Fix>,
+}
+
+/// A change to a document that resolves a diagnostic
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Fix {
+    /// What the fix does, e.g. "Change to 'count'"
+    pub title: String,
+    pub edits: Vec<TextEdit>,
+    /// Surely what was meant, so `loft fix` applies it on its own. A
+    /// diagnostic has at most one preferred fix.
+    pub preferred: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextEdit {
+    pub range: Range,
+    pub new_text: String,
+}
+
+impl Diagnostic {
+    pub fn new(range: Range, severity: Severity, message: impl Into<String>) -> Self {
+        Diagnostic {
+            range,
+            severity,
+            code: None,
+            message: message.into(),
+            unnecessary: false,
+            data: None,
+            fixes: Vec::new(),
+        }
+    }
+
+    /// Diagnostic for a syntax error reported by the parser
+    pub fn from_parse_error(err: &ParseError) -> Self {
+        let end = err.column + err.len.unwrap_or(1);
+        Diagnostic::new(
+            Range::on_line(err.line, err.column, end),
+            Severity::Error,
+            err.message.clone(),
+        )
+        .with_code("syntax_error")
+    }
+
+    pub fn with_code(mut self, code: &str) -> Self {
+        self.code = Some(code.to_string());
+        self
+    }
+
+    pub fn with_data(mut self, data: serde_json::Value) -> Self {
+        self.data = Some(data);
+        self
+    }
+
+    pub fn with_fix(mut self, fix: Fix) -> Self {
+        self.fixes.push(fix);
+        self
+    }
+
+    pub fn unnecessary(mut self) -> Self {
+        self.unnecessary = true;
+        self
+    }
+}
+
+/// Every `.lf` file under `root`, skipping hidden directories, build output and installed packages
+#[cfg(not(target_arch = "wasm32"))]
+pub fn discover_source_files(root: &std::path::Path) -> Vec<std::path::PathBuf> {
+    crate::runtime::test_harness::discover_files(root, ".lf")
+}
+
+/// Parse a document and report syntax errors followed by semantic diagnostics
+pub fn check_source(name: &str, source: &str, stdlib_types: &StdlibTypes) -> Vec<Diagnostic> {
+    let source = source.to_string();
+    let (stmts, errors) = Parser::new(InputStream::new(name, &source)).parse_recoverable();
+    check_parsed(&stmts, &errors, &source, stdlib_types)
+}
+
+/// [`check_source`] for a document that was already parsed with
+/// [`Parser::parse_recoverable`]
+pub fn check_parsed(
+    stmts: &[Stmt],
+    errors: &[ParseError],
+    source: &str,
+    stdlib_types: &StdlibTypes,
+) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> =
+        errors.iter().map(Diagnostic::from_parse_error).collect();
+    let symbols = symbols::extract_symbols(stmts, 0, stdlib_types);
+    diagnostics.extend(semantic::check_semantic_errors(
+        stmts,
+        &symbols,
+        source,
+        stdlib_types,
+    ));
+    diagnostics
+}
+
+/// Like [`check_source`], also running the strict type checker. `declarations`
+/// holds what the file can see from the rest of the project.
+pub fn check_source_strict(
+    name: &str,
+    source: &str,
+    declarations: &typecheck::Declarations,
+    stdlib_types: &StdlibTypes,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = check_source(name, source, stdlib_types);
+    let source = source.to_string();
+    if let Ok(stmts) = Parser::new(InputStream::new(name, &source)).parse() {
+        diagnostics.extend(typecheck::check_types(
+            &stmts,
+            &source,
+            declarations,
+            stdlib_types,
+        ));
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_source_reports_without_running() {
+        let source = "let unused = 1;\nterm.println(missing);\nfn add(a: num, b: num) -> num { return a + b; }\nadd(1);\nlet x = ;\n";
+        let diagnostics = check_source("test.lf", source, &stdlib::load());
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+
+        assert!(diagnostics
+            .iter()
+            .any(|d| d.severity == Severity::Error && d.range.start.line == 4));
+        assert!(messages.contains(&"Undefined identifier 'missing'"));
+        assert!(messages.contains(&"Function 'add' expects 2 argument(s), but 1 provided"));
+        assert!(messages.contains(&"Unused variable 'unused'"));
+    }
+
+    #[test]
+    fn test_generic_type_inference() {
+        let source = "def Box<T> { value: T }\nfn first<T>(items: Array<T>) -> T { return items[0]; }\nfn wrap<T>(value: T) -> Box<T> { return Box { value: value }; }\nlet names: Array<str> = [\"a\"];\nlet name = first(names);\nlet boxed = Box { value: 1 };\nlet inner = boxed.value;\nlet wrapped = wrap(\"x\");\nlet unknown = first([]);\n".to_string();
+        let stdlib_types = stdlib::load();
+        let stmts = Parser::new(InputStream::new("test.lf", &source))
+            .parse()
+            .unwrap();
+        let symbols = symbols::extract_symbols(&stmts, 0, &stdlib_types);
+        let var_type = |name: &str| match symbols.iter().find(|s| s.name == name).map(|s| &s.kind) {
+            Some(symbols::SymbolKind::Variable { var_type, .. }) => var_type.clone(),
+            other => panic!("Expected variable, got {:?}", other),
+        };
+
+        assert_eq!(var_type("name").as_deref(), Some("str"));
+        assert_eq!(var_type("boxed").as_deref(), Some("Box<num>"));
+        assert_eq!(var_type("inner").as_deref(), Some("num"));
+        assert_eq!(var_type("wrapped").as_deref(), Some("Box<str>"));
+        assert_eq!(var_type("unknown"), None);
+        assert_eq!(
+            symbols::split_generic_type("Map<str, Array<num>>"),
+            ("Map", vec!["str", "Array<num>"])
+        );
+    }
+
+    #[test]
+    fn test_rest_parameter_arity() {
+        let source = "fn log(level: str, ...parts: Array<str>) { return; }\nlet parts = [\"a\", \"b\"];\nlog(\"info\");\nlog(\"info\", \"a\", \"b\");\nlog(...parts);\nlog();\n";
+        let diagnostics = check_source("test.lf", source, &stdlib::load());
+        let errors: Vec<&str> = diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .map(|d| d.message.as_str())
+            .collect();
+
+        assert_eq!(
+            errors,
+            ["Function 'log' expects at least 1 argument(s), but 0 provided"]
+        );
+    }
+}
@@ -0,0 +1,169 @@
+//! Loading every file of a project at once, for `loft check` and `loft doc`.
+//!
+//! Files are read and parsed in parallel. Checking then runs in parallel too,
+//! with every file seeing one shared table of what the project exports.
+
+use super::stdlib::StdlibTypes;
+use super::typecheck::{self, Declarations};
+use super::{check_parsed, Diagnostic};
+use crate::lints::{self, LintConfig};
+use crate::parser::input_stream::Error as ParseError;
+use crate::parser::{InputStream, Parser, Stmt};
+use rayon::prelude::*;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Which diagnostics [`Project::check`] reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckMode {
+    /// Syntax and semantic checks and lints, as `loft check` runs them
+    Default,
+    /// The default checks plus the static type checker
+    Strict,
+    /// Only the static type checker, used before running strict projects
+    TypesOnly,
+}
+
+/// A file of a project, parsed
+#[derive(Debug)]
+pub struct SourceFile {
+    pub path: PathBuf,
+    pub source: String,
+    /// Whatever could be parsed around the syntax errors
+    pub stmts: Vec<Stmt>,
+    pub errors: Vec<ParseError>,
+}
+
+impl SourceFile {
+    fn load(path: &Path) -> io::Result<SourceFile> {
+        let source = std::fs::read_to_string(path)?;
+        let (stmts, errors) =
+            Parser::new(InputStream::new(path.display().to_string(), &source)).parse_recoverable();
+        Ok(SourceFile {
+            path: path.to_path_buf(),
+            source,
+            stmts,
+            errors,
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Project {
+    /// The files that could be read, in the order they were given
+    pub files: Vec<SourceFile>,
+    pub unreadable: Vec<(PathBuf, io::Error)>,
+    /// The functions files export with `teach`, which every file can call
+    pub declarations: Declarations,
+}
+
+impl Project {
+    /// Read and parse `paths` on all cores
+    pub fn load(paths: &[PathBuf]) -> Project {
+        let loaded: Vec<(&PathBuf, io::Result<SourceFile>)> = paths
+            .par_iter()
+            .map(|path| (path, SourceFile::load(path)))
+            .collect();
+
+        let mut project = Project::default();
+        for (path, file) in loaded {
+            match file {
+                Ok(file) => {
+                    project.declarations.collect(&file.stmts, true);
+                    project.files.push(file);
+                }
+                Err(e) => project.unreadable.push((path.clone(), e)),
+            }
+        }
+        project
+    }
+
+    /// The diagnostics of every file, sorted by position, in the order of [`Project::files`]
+    pub fn check(
+        &self,
+        mode: CheckMode,
+        lints: &LintConfig,
+        stdlib_types: &StdlibTypes,
+    ) -> Vec<(&SourceFile, Vec<Diagnostic>)> {
+        self.files
+            .par_iter()
+            .map(|file| (file, self.check_file(file, mode, lints, stdlib_types)))
+            .collect()
+    }
+
+    fn check_file(
+        &self,
+        file: &SourceFile,
+        mode: CheckMode,
+        lints: &LintConfig,
+        stdlib_types: &StdlibTypes,
+    ) -> Vec<Diagnostic> {
+        let mut diagnostics = match mode {
+            CheckMode::TypesOnly => Vec::new(),
+            _ => {
+                let mut diagnostics =
+                    check_parsed(&file.stmts, &file.errors, &file.source, stdlib_types);
+                diagnostics.extend(lints::check(&file.stmts, &file.source, lints));
+                diagnostics
+            }
+        };
+        // Types are only checked in files that parse
+        if mode != CheckMode::Default && file.errors.is_empty() {
+            diagnostics.extend(typecheck::check_types(
+                &file.stmts,
+                &file.source,
+                &self.declarations,
+                stdlib_types,
+            ));
+        }
+        diagnostics.sort_by_key(|d| (d.range.start.line, d.range.start.character));
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::{stdlib, Severity};
+
+    #[test]
+    fn test_project_checks_files_together() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, source: &str| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, source).unwrap();
+            path
+        };
+        let paths = vec![
+            write(
+                "shapes.lf",
+                "teach fn area(w: num, h: num) -> num {\n    return w * h;\n}\n",
+            ),
+            write("main.lf", "let label: str = area(2, 3);\n"),
+            write("broken.lf", "let = 1;\n"),
+            dir.path().join("missing.lf"),
+        ];
+
+        let project = Project::load(&paths);
+        assert_eq!(project.files.len(), 3);
+        assert_eq!(project.unreadable.len(), 1);
+        assert_eq!(project.unreadable[0].0, paths[3]);
+
+        // The type checker sees `area` from the other file
+        let checked = project.check(CheckMode::Strict, &LintConfig::default(), &stdlib::load());
+        assert_eq!(checked.len(), 3);
+        let errors = |index: usize| -> Vec<&str> {
+            checked[index]
+                .1
+                .iter()
+                .filter(|d| d.severity == Severity::Error)
+                .map(|d| d.message.as_str())
+                .collect()
+        };
+        assert!(errors(0).is_empty());
+        assert!(
+            errors(1).contains(&"Type mismatch in variable 'label': expected 'str', found 'num'")
+        );
+        assert_eq!(errors(2), ["Expected identifier but got '='"]);
+    }
+}
@@ -0,0 +1,969 @@
+//! Semantic checks over a parsed document: undefined identifiers, arity
+//! mismatches, missing trait methods, unused bindings and unreachable code.
+
+use super::fixes;
+use super::stdlib::StdlibTypes;
+use super::symbols::{is_rest_param, opt_type_to_string, type_to_string, SymbolInfo, SymbolKind};
+use super::{Diagnostic, Range, Severity};
+use crate::parser::{import_binding_name, Expr, ExprKind, Span, Stmt, StmtKind, TemplatePart};
+use crate::runtime::builtin_registry::BuiltinRegistration;
+
+/// Report `match` expressions that leave enum variants or booleans uncovered
+pub fn check_match_exhaustiveness(
+    stmts: &[Stmt],
+    diagnostics: &mut Vec<Diagnostic>,
+    lines: &[&str],
+) {
+    for report in crate::parser::exhaustiveness::check_program(stmts) {
+        // Only the `match` keyword is highlighted rather than the whole expression
+        let keyword = Span {
+            end: report.span.start + "match".len(),
+            ..report.span
+        };
+        diagnostics.push(
+            Diagnostic::new(
+                Range::from_span(keyword, lines),
+                Severity::Error,
+                report.message(),
+            )
+            .with_code("non_exhaustive_match"),
+        );
+    }
+}
+
+fn check_trait_implementations(
+    stmts: &[Stmt],
+    symbols: &[SymbolInfo],
+    stdlib_types: &StdlibTypes,
+    diagnostics: &mut Vec<Diagnostic>,
+    lines: &[&str],
+) {
+    for stmt in stmts {
+        if let StmtKind::ImplBlock {
+            type_name,
+            trait_name: Some(trait_name),
+            methods,
+        } = &stmt.kind
+        {
+            // Find the trait definition
+            let mut required_methods = Vec::new();
+
+            // Check user-defined traits
+            if let Some(trait_symbol) = symbols.iter().find(|s| s.name == *trait_name) {
+                if let SymbolKind::Trait { methods } = &trait_symbol.kind {
+                    for method in methods {
+                        if !method.has_default_impl {
+                            required_methods.push(method.name.clone());
+                        }
+                    }
+                }
+            }
+            // Check builtin traits
+            else if let Some(trait_def) = stdlib_types.traits.get(trait_name) {
+                for method_name in trait_def.methods.keys() {
+                    required_methods.push(method_name.clone());
+                }
+            } else {
+                // Debug: Log that we couldn't find the trait
+                // eprintln!("Could not find trait definition for {}", trait_name);
+            }
+
+            // Check implemented methods
+            let implemented_methods: std::collections::HashSet<String> = methods.iter()
+                .filter_map(|m| {
+                    if let StmtKind::FunctionDecl { name, params, return_type, .. } = &m.kind {
+                        // Check signature against trait definition
+                        let mut signature_mismatch = None;
+
+                        // Check user-defined traits
+                        if let Some(trait_symbol) = symbols.iter().find(|s| s.name == *trait_name) {
+                            if let SymbolKind::Trait { methods: trait_methods } = &trait_symbol.kind {
+                                if let Some(trait_method) = trait_methods.iter().find(|tm| tm.name == *name) {
+                                    // Check return type
+                                    let impl_return = opt_type_to_string(return_type);
+                                    if impl_return != trait_method.return_type {
+                                        signature_mismatch = Some(format!(
+                                            "Return type mismatch: expected '{}', found '{}'",
+                                            trait_method.return_type, impl_return
+                                        ));
+                                    }
+
+                                    // Check params
+                                    if signature_mismatch.is_none() {
+                                        if params.len() != trait_method.params.len() {
+                                            signature_mismatch = Some(format!(
+                                                "Parameter count mismatch: expected {}, found {}",
+                                                trait_method.params.len(), params.len()
+                                            ));
+                                        } else {
+                                            for (i, (impl_name, impl_type)) in params.iter().enumerate() {
+                                                let (trait_param_name, trait_param_type) = &trait_method.params[i];
+                                                let impl_type_str = type_to_string(impl_type);
+
+                                                // Check param name (optional, but good for consistency)
+                                                if impl_name != trait_param_name {
+                                                    // Maybe warning? For now strict check
+                                                    // signature_mismatch = Some(format!("Parameter name mismatch: expected '{}', found '{}'", trait_param_name, impl_name));
+                                                }
+
+                                                if impl_type_str != *trait_param_type {
+                                                    signature_mismatch = Some(format!(
+                                                        "Parameter type mismatch for '{}': expected '{}', found '{}'",
+                                                        impl_name, trait_param_type, impl_type_str
+                                                    ));
+                                                    break;
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                        // Check builtin traits
+                        else if let Some(trait_def) = stdlib_types.traits.get(trait_name) {
+                            if let Some(method_def) = trait_def.methods.get(name) {
+                                // Check return type
+                                let impl_return = opt_type_to_string(return_type);
+                                if method_def.return_type != "any" && impl_return != method_def.return_type {
+                                    signature_mismatch = Some(format!(
+                                        "Return type mismatch: expected '{}', found '{}'",
+                                        method_def.return_type, impl_return
+                                    ));
+                                }
+
+                                // Check params
+                                if signature_mismatch.is_none() {
+                                    if params.len() != method_def.params.len() {
+                                        signature_mismatch = Some(format!(
+                                            "Parameter count mismatch: expected {}, found {}",
+                                            method_def.params.len(), params.len()
+                                        ));
+                                    } else {
+                                        for (i, (impl_name, impl_type)) in params.iter().enumerate() {
+                                            let trait_param_str = &method_def.params[i];
+
+                                            // Parse builtin param string "name: type" or just "name"
+                                            let (trait_param_name, trait_param_type) = if let Some((n, t)) = trait_param_str.split_once(':') {
+                                                (n.trim(), Some(t.trim()))
+                                            } else {
+                                                (trait_param_str.as_str(), None)
+                                            };
+
+                                            if impl_name != trait_param_name {
+                                                // signature_mismatch = Some(format!("Parameter name mismatch: expected '{}', found '{}'", trait_param_name, impl_name));
+                                            }
+
+                                            if let Some(expected_type) = trait_param_type {
+                                                let impl_type_str = type_to_string(impl_type);
+                                                // Handle 'any' type in builtins
+                                                if expected_type != "any" && impl_type_str != expected_type {
+                                                    signature_mismatch = Some(format!(
+                                                        "Parameter type mismatch for '{}': expected '{}', found '{}'",
+                                                        impl_name, expected_type, impl_type_str
+                                                    ));
+                                                    break;
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+
+                        if let Some(msg) = signature_mismatch {
+                            // Find line number for this method
+                            let mut method_line = 0;
+                            for (i, line) in lines.iter().enumerate() {
+                                // Simple heuristic to find the method definition
+                                if line.contains(&format!("fn {}", name)) {
+                                    method_line = i;
+                                    break;
+                                }
+                            }
+
+                            diagnostics.push(Diagnostic::new(Range::on_line(method_line, 0, lines[method_line].len()), Severity::Error, msg).with_code("signature_mismatch"));
+                        }
+
+                        Some(name.clone())
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+
+            // Find missing methods
+            let missing_methods: Vec<String> = required_methods
+                .into_iter()
+                .filter(|m| !implemented_methods.contains(m))
+                .collect();
+
+            if !missing_methods.is_empty() {
+                // Find the location of the impl block
+                let mut line_num = 0;
+                for (i, line) in lines.iter().enumerate() {
+                    if line.contains(&format!("impl {} for {}", trait_name, type_name)) {
+                        line_num = i;
+                        break;
+                    }
+                }
+
+                diagnostics.push(
+                    Diagnostic::new(
+                        Range::on_line(line_num, 0, lines[line_num].len()),
+                        Severity::Error,
+                        format!(
+                            "Missing implementation for methods: {}",
+                            missing_methods.join(", ")
+                        ),
+                    )
+                    .with_code("missing_impl")
+                    .with_data(serde_json::json!({
+                        "missing_methods": missing_methods,
+                        "trait_name": trait_name,
+                        "type_name": type_name
+                    })),
+                );
+            }
+        }
+
+        // Recurse into blocks
+        if let StmtKind::Block(inner_stmts) = &stmt.kind {
+            check_trait_implementations(inner_stmts, symbols, stdlib_types, diagnostics, lines);
+        }
+    }
+}
+
+/// Run every semantic check over a parsed document
+pub fn check_semantic_errors(
+    stmts: &[Stmt],
+    symbols: &[SymbolInfo],
+    source: &str,
+    stdlib_types: &StdlibTypes,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let lines: Vec<&str> = source.lines().collect();
+
+    // Track which variables and imports are used
+    let mut used_variables = std::collections::HashSet::new();
+    let mut used_imports = std::collections::HashSet::new();
+
+    // Collect all imports with the names they bind
+    let mut imports: Vec<(String, Vec<String>, Span)> = Vec::new();
+    for stmt in stmts {
+        if let StmtKind::ImportDecl {
+            path,
+            alias,
+            items,
+            is_exported: false,
+        } = &stmt.kind
+        {
+            let names = match items {
+                Some(items) => items.iter().map(|i| i.local_name().to_string()).collect(),
+                None => vec![alias.clone().unwrap_or_else(|| import_binding_name(path))],
+            };
+            imports.push((path.join("::"), names, stmt.span));
+        }
+    }
+
+    // Check for unused variables and other semantic errors
+    check_stmt_list_with_imports(
+        stmts,
+        symbols,
+        &mut used_variables,
+        &mut used_imports,
+        &mut diagnostics,
+        &lines,
+    );
+
+    // Check for missing trait implementations
+    check_trait_implementations(stmts, symbols, stdlib_types, &mut diagnostics, &lines);
+
+    // Check match statements for uncovered enum variants and booleans
+    check_match_exhaustiveness(stmts, &mut diagnostics, &lines);
+
+    // Report unused variables
+    // NOTE: Variables starting with '_' are exempt from unused warnings (Rust convention)
+    // This follows common practice but could be made configurable in the future
+    for symbol in symbols {
+        if let SymbolKind::Variable { .. } = &symbol.kind {
+            if !used_variables.contains(&symbol.name) && !symbol.name.starts_with('_') {
+                // Point at the statement that declares this variable
+                if let Some(span) = find_declaration(stmts, &symbol.name) {
+                    diagnostics.push(
+                        Diagnostic::new(
+                            Range::from_span(span, &lines),
+                            Severity::Warning,
+                            format!("Unused variable '{}'", symbol.name),
+                        )
+                        .with_code("unused_variable")
+                        .unnecessary(),
+                    );
+                }
+            }
+        }
+    }
+
+    // Report unused imports
+    // Re-exports (`teach learn`) are used by whoever imports this module
+    for (import_path, names, span) in imports {
+        let used = used_imports.contains(&import_path)
+            || names.iter().any(|name| used_variables.contains(name));
+        if !used {
+            diagnostics.push(
+                Diagnostic::new(
+                    Range::from_span(span, &lines),
+                    Severity::Hint,
+                    format!("Unused import '{}'", import_path),
+                )
+                .with_code("unused_import")
+                .unnecessary(),
+            );
+        }
+    }
+
+    fixes::suggest(&mut diagnostics, symbols, source, stdlib_types);
+    diagnostics
+}
+
+/// Names the runtime defines in every program besides the registered builtins
+const BUILTIN_NAMES: &[&str] = &["console", "Option", "Result"];
+
+/// The modules and globals every file can use without importing them
+pub(super) fn builtin_names() -> impl Iterator<Item = &'static str> {
+    BUILTIN_NAMES.iter().copied().chain(
+        inventory::iter::<BuiltinRegistration>
+            .into_iter()
+            .map(|registration| registration.name),
+    )
+}
+
+fn is_builtin(name: &str) -> bool {
+    builtin_names().any(|builtin| builtin == name)
+}
+
+fn check_stmt_list_with_imports(
+    stmts: &[Stmt],
+    symbols: &[SymbolInfo],
+    used_vars: &mut std::collections::HashSet<String>,
+    used_imports: &mut std::collections::HashSet<String>,
+    diagnostics: &mut Vec<Diagnostic>,
+    lines: &[&str],
+) {
+    let mut found_terminal = false;
+    for stmt in stmts.iter() {
+        if found_terminal {
+            // Code after return/break/continue is unreachable
+            diagnostics.push(
+                Diagnostic::new(
+                    Range::from_span(stmt.span, lines),
+                    Severity::Warning,
+                    "Unreachable code".to_string(),
+                )
+                .with_code("unreachable_code")
+                .unnecessary(),
+            );
+            // Only report first unreachable statement
+            break;
+        }
+
+        check_stmt_with_imports(stmt, symbols, used_vars, used_imports, diagnostics, lines);
+
+        // Check if this statement is a terminal (return, break, continue)
+        if is_terminal_stmt(stmt) {
+            found_terminal = true;
+        }
+    }
+}
+
+fn is_terminal_stmt(stmt: &Stmt) -> bool {
+    matches!(
+        &stmt.kind,
+        StmtKind::Return(_) | StmtKind::Throw(_) | StmtKind::Break | StmtKind::Continue
+    )
+}
+
+fn check_stmt_with_imports(
+    stmt: &Stmt,
+    symbols: &[SymbolInfo],
+    used_vars: &mut std::collections::HashSet<String>,
+    used_imports: &mut std::collections::HashSet<String>,
+    diagnostics: &mut Vec<Diagnostic>,
+    lines: &[&str],
+) {
+    match &stmt.kind {
+        StmtKind::VarDecl {
+            value: Some(expr), ..
+        } => {
+            check_expr_with_imports(expr, symbols, used_vars, used_imports, diagnostics, lines);
+        }
+        StmtKind::FunctionDecl { params, body, .. } => {
+            if let StmtKind::Block(stmts) = &body.kind {
+                // The body can see the parameters
+                let extended_symbols = with_params(
+                    symbols,
+                    params
+                        .iter()
+                        .map(|(name, ty)| (name, Some(type_to_string(ty)))),
+                );
+                check_stmt_list_with_imports(
+                    stmts,
+                    &extended_symbols,
+                    used_vars,
+                    used_imports,
+                    diagnostics,
+                    lines,
+                );
+            }
+        }
+        StmtKind::Expr(expr) => {
+            check_expr_with_imports(expr, symbols, used_vars, used_imports, diagnostics, lines);
+        }
+        StmtKind::Return(Some(expr)) | StmtKind::Throw(expr) | StmtKind::Defer(expr) => {
+            check_expr_with_imports(expr, symbols, used_vars, used_imports, diagnostics, lines);
+        }
+        StmtKind::Assign { value, .. } => {
+            check_expr_with_imports(value, symbols, used_vars, used_imports, diagnostics, lines);
+        }
+        StmtKind::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            check_expr_with_imports(
+                condition,
+                symbols,
+                used_vars,
+                used_imports,
+                diagnostics,
+                lines,
+            );
+            check_stmt_with_imports(
+                then_branch,
+                symbols,
+                used_vars,
+                used_imports,
+                diagnostics,
+                lines,
+            );
+            if let Some(else_stmt) = else_branch {
+                check_stmt_with_imports(
+                    else_stmt,
+                    symbols,
+                    used_vars,
+                    used_imports,
+                    diagnostics,
+                    lines,
+                );
+            }
+        }
+        StmtKind::While { condition, body } => {
+            check_expr_with_imports(
+                condition,
+                symbols,
+                used_vars,
+                used_imports,
+                diagnostics,
+                lines,
+            );
+            check_stmt_with_imports(body, symbols, used_vars, used_imports, diagnostics, lines);
+        }
+        StmtKind::For {
+            var,
+            body,
+            iterable,
+            ..
+        } => {
+            check_expr_with_imports(
+                iterable,
+                symbols,
+                used_vars,
+                used_imports,
+                diagnostics,
+                lines,
+            );
+
+            // Create a temporary scope including the loop variable
+            let mut extended_symbols = symbols.to_vec();
+            extended_symbols.push(SymbolInfo {
+                name: var.clone(),
+                kind: SymbolKind::Variable {
+                    var_type: None,
+                    mutable: false,
+                },
+                detail: Some(format!("loop variable {}", var)),
+                documentation: None,
+                scope_level: 0, // Scope level not strictly used here for existence check
+                range: None,
+                selection_range: None,
+                source_uri: None,
+                is_exported: false,
+            });
+
+            check_stmt_with_imports(
+                body,
+                &extended_symbols,
+                used_vars,
+                used_imports,
+                diagnostics,
+                lines,
+            );
+        }
+        StmtKind::Try {
+            body,
+            catch,
+            finally,
+        } => {
+            check_stmt_with_imports(body, symbols, used_vars, used_imports, diagnostics, lines);
+            if let Some((var, catch_body)) = catch {
+                // The catch block can see the caught error
+                let mut extended_symbols = symbols.to_vec();
+                if let Some(var) = var {
+                    extended_symbols.push(SymbolInfo {
+                        name: var.clone(),
+                        kind: SymbolKind::Variable {
+                            var_type: Some("Error".to_string()),
+                            mutable: false,
+                        },
+                        detail: Some(format!("caught error {}", var)),
+                        documentation: None,
+                        scope_level: 0,
+                        range: None,
+                        selection_range: None,
+                        source_uri: None,
+                        is_exported: false,
+                    });
+                }
+                check_stmt_with_imports(
+                    catch_body,
+                    &extended_symbols,
+                    used_vars,
+                    used_imports,
+                    diagnostics,
+                    lines,
+                );
+            }
+            if let Some(finally) = finally {
+                check_stmt_with_imports(
+                    finally,
+                    symbols,
+                    used_vars,
+                    used_imports,
+                    diagnostics,
+                    lines,
+                );
+            }
+        }
+        StmtKind::Match { expr, arms } => {
+            check_expr_with_imports(expr, symbols, used_vars, used_imports, diagnostics, lines);
+            for (pattern, body) in arms {
+                check_stmt_with_imports(
+                    body,
+                    &with_pattern_bindings(symbols, pattern),
+                    used_vars,
+                    used_imports,
+                    diagnostics,
+                    lines,
+                );
+            }
+        }
+        StmtKind::Block(stmts) => {
+            check_stmt_list_with_imports(
+                stmts,
+                symbols,
+                used_vars,
+                used_imports,
+                diagnostics,
+                lines,
+            );
+        }
+        _ => {}
+    }
+}
+
+fn check_expr_with_imports(
+    expr: &Expr,
+    symbols: &[SymbolInfo],
+    used_vars: &mut std::collections::HashSet<String>,
+    used_imports: &mut std::collections::HashSet<String>,
+    diagnostics: &mut Vec<Diagnostic>,
+    lines: &[&str],
+) {
+    match &expr.kind {
+        ExprKind::Ident(name) => {
+            used_vars.insert(name.clone());
+
+            // Track if this might be from an import
+            // If identifier contains "::", it's using an imported symbol
+            if name.contains("::") {
+                let parts: Vec<&str> = name.split("::").collect();
+                if parts.len() >= 2 {
+                    // Mark the module as used
+                    used_imports.insert(parts[0].to_string());
+                }
+            }
+
+            // Check if identifier is defined
+            if !symbols.iter().any(|s| &s.name == name) {
+                // Check if it's a builtin (term, math, etc.)
+                if !is_builtin(name) {
+                    diagnostics.push(
+                        Diagnostic::new(
+                            Range::from_span(expr.span, lines),
+                            Severity::Error,
+                            format!("Undefined identifier '{}'", name),
+                        )
+                        .with_code("undefined_identifier"),
+                    );
+                }
+            }
+        }
+        ExprKind::Call { func, args } => {
+            check_expr_with_imports(func, symbols, used_vars, used_imports, diagnostics, lines);
+            for arg in args {
+                check_expr_with_imports(arg, symbols, used_vars, used_imports, diagnostics, lines);
+            }
+
+            // Check function arity if func is an identifier. Spread
+            // arguments can be any number of them, so the count is unknown.
+            let spreads = args.iter().any(|a| matches!(a.kind, ExprKind::Spread(_)));
+            if let (ExprKind::Ident(func_name), false) = (&func.kind, spreads) {
+                if let Some(symbol) = symbols.iter().find(|s| s.name == *func_name) {
+                    if let SymbolKind::Function { params, .. } = &symbol.kind {
+                        let variadic = params.last().is_some_and(|(name, _)| is_rest_param(name));
+                        let fixed = params.len() - variadic as usize;
+                        let message = if variadic && args.len() < fixed {
+                            Some(format!(
+                                "Function '{}' expects at least {} argument(s), but {} provided",
+                                func_name,
+                                fixed,
+                                args.len()
+                            ))
+                        } else if !variadic && params.len() != args.len() {
+                            Some(format!(
+                                "Function '{}' expects {} argument(s), but {} provided",
+                                func_name,
+                                params.len(),
+                                args.len()
+                            ))
+                        } else {
+                            None
+                        };
+                        if let Some(message) = message {
+                            diagnostics.push(
+                                Diagnostic::new(
+                                    Range::from_span(expr.span, lines),
+                                    Severity::Error,
+                                    message,
+                                )
+                                .with_code("argument_count"),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        ExprKind::FieldAccess { object, field: _ } => {
+            check_expr_with_imports(object, symbols, used_vars, used_imports, diagnostics, lines);
+            // Mark module as used if it's a builtin
+            if let ExprKind::Ident(obj_name) = &object.kind {
+                if is_builtin(obj_name) {
+                    used_imports.insert(obj_name.clone());
+                }
+            }
+        }
+        ExprKind::BinOp { left, right, .. } => {
+            check_expr_with_imports(left, symbols, used_vars, used_imports, diagnostics, lines);
+            check_expr_with_imports(right, symbols, used_vars, used_imports, diagnostics, lines);
+        }
+        ExprKind::UnaryOp { expr, .. } => {
+            check_expr_with_imports(expr, symbols, used_vars, used_imports, diagnostics, lines);
+        }
+        ExprKind::ArrayLiteral(exprs) => {
+            for e in exprs {
+                check_expr_with_imports(e, symbols, used_vars, used_imports, diagnostics, lines);
+            }
+        }
+        ExprKind::MapLiteral(entries) => {
+            for (k, v) in entries {
+                for e in [k, v] {
+                    check_expr_with_imports(
+                        e,
+                        symbols,
+                        used_vars,
+                        used_imports,
+                        diagnostics,
+                        lines,
+                    );
+                }
+            }
+        }
+        ExprKind::TemplateLiteral { parts } => {
+            for part in parts {
+                if let TemplatePart::Expression(e, _) = part {
+                    check_expr_with_imports(
+                        e,
+                        symbols,
+                        used_vars,
+                        used_imports,
+                        diagnostics,
+                        lines,
+                    );
+                }
+            }
+        }
+        ExprKind::Index { array, index } => {
+            check_expr_with_imports(array, symbols, used_vars, used_imports, diagnostics, lines);
+            check_expr_with_imports(index, symbols, used_vars, used_imports, diagnostics, lines);
+        }
+        ExprKind::Slice { array, start, end } => {
+            check_expr_with_imports(array, symbols, used_vars, used_imports, diagnostics, lines);
+            for bound in [start, end].into_iter().flatten() {
+                check_expr_with_imports(
+                    bound,
+                    symbols,
+                    used_vars,
+                    used_imports,
+                    diagnostics,
+                    lines,
+                );
+            }
+        }
+        ExprKind::Lambda { params, body, .. } => {
+            let extended_symbols = with_params(
+                symbols,
+                params
+                    .iter()
+                    .map(|(name, ty)| (name, ty.as_ref().map(type_to_string))),
+            );
+            check_expr_with_imports(
+                body,
+                &extended_symbols,
+                used_vars,
+                used_imports,
+                diagnostics,
+                lines,
+            );
+        }
+        ExprKind::Await(expr)
+        | ExprKind::Async(expr)
+        | ExprKind::Lazy(expr)
+        | ExprKind::Spread(expr) => {
+            check_expr_with_imports(expr, symbols, used_vars, used_imports, diagnostics, lines);
+        }
+        ExprKind::Block(stmts) => {
+            check_stmt_list_with_imports(
+                stmts,
+                symbols,
+                used_vars,
+                used_imports,
+                diagnostics,
+                lines,
+            );
+        }
+        ExprKind::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            let branches = [Some(condition), Some(then_branch), else_branch.as_ref()];
+            for expr in branches.into_iter().flatten() {
+                check_expr_with_imports(expr, symbols, used_vars, used_imports, diagnostics, lines);
+            }
+        }
+        ExprKind::Match { expr, arms } => {
+            check_expr_with_imports(expr, symbols, used_vars, used_imports, diagnostics, lines);
+            for (pattern, body) in arms {
+                check_expr_with_imports(
+                    body,
+                    &with_pattern_bindings(symbols, pattern),
+                    used_vars,
+                    used_imports,
+                    diagnostics,
+                    lines,
+                );
+            }
+        }
+        ExprKind::StructLiteral { fields, .. } => {
+            // Check each field expression in the struct literal
+            for (_, field_expr) in fields {
+                check_expr_with_imports(
+                    field_expr,
+                    symbols,
+                    used_vars,
+                    used_imports,
+                    diagnostics,
+                    lines,
+                );
+            }
+        }
+        _ => {}
+    }
+}
+
+/// `symbols` plus the parameters of a function or lambda
+fn with_params<'a>(
+    symbols: &[SymbolInfo],
+    params: impl Iterator<Item = (&'a String, Option<String>)>,
+) -> Vec<SymbolInfo> {
+    let mut extended_symbols = symbols.to_vec();
+    extended_symbols.extend(params.map(|(name, var_type)| SymbolInfo {
+        name: name.clone(),
+        kind: SymbolKind::Variable {
+            var_type,
+            mutable: false,
+        },
+        detail: Some(format!("parameter {}", name)),
+        documentation: None,
+        scope_level: 0,
+        range: None,
+        selection_range: None,
+        source_uri: None,
+        is_exported: false,
+    }));
+    extended_symbols
+}
+
+/// `symbols` plus the names a match arm's pattern binds, like `r` in
+/// `Shape.Circle(r)`. Enum paths and literals in the pattern bind nothing.
+fn with_pattern_bindings(symbols: &[SymbolInfo], pattern: &Expr) -> Vec<SymbolInfo> {
+    fn bindings<'a>(pattern: &'a Expr, names: &mut Vec<&'a String>) {
+        match &pattern.kind {
+            ExprKind::Ident(name) if name != "_" => names.push(name),
+            ExprKind::Call { args: items, .. } | ExprKind::ArrayLiteral(items) => {
+                items.iter().for_each(|item| bindings(item, names));
+            }
+            ExprKind::StructLiteral { fields, .. } => {
+                fields.iter().for_each(|(_, value)| bindings(value, names));
+            }
+            _ => {}
+        }
+    }
+
+    let mut names = Vec::new();
+    bindings(pattern, &mut names);
+    with_params(symbols, names.into_iter().map(|name| (name, None)))
+}
+
+/// Span of the first statement declaring the variable `name`
+fn find_declaration(stmts: &[Stmt], name: &str) -> Option<Span> {
+    stmts.iter().find_map(|stmt| match &stmt.kind {
+        StmtKind::VarDecl { name: declared, .. } if declared == name => Some(stmt.span),
+        StmtKind::FunctionDecl { body, .. } => find_declaration(std::slice::from_ref(body), name),
+        StmtKind::If {
+            then_branch,
+            else_branch,
+            ..
+        } => find_declaration(std::slice::from_ref(then_branch), name).or_else(|| {
+            else_branch
+                .as_ref()
+                .and_then(|e| find_declaration(std::slice::from_ref(e), name))
+        }),
+        StmtKind::While { body, .. } | StmtKind::For { body, .. } => {
+            find_declaration(std::slice::from_ref(body), name)
+        }
+        StmtKind::Block(stmts) => find_declaration(stmts, name),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{InputStream, Parser};
+
+    #[test]
+    fn test_non_exhaustive_match_diagnostic() {
+        let source = r#"enum Light { Red, Yellow, Green }
+
+fn describe(light: Light) -> str {
+    // a match inside a comment is ignored
+    return match light {
+        Light.Red => "stop",
+        Light.Green => "go",
+    };
+}"#;
+        let source = source.to_string();
+        let stmts = Parser::new(InputStream::new("test.lf", &source))
+            .parse()
+            .unwrap();
+        let lines: Vec<&str> = source.lines().collect();
+        let mut diagnostics = Vec::new();
+        check_match_exhaustiveness(&stmts, &mut diagnostics, &lines);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].range.start.line, 4);
+        assert_eq!(diagnostics[0].range.start.character, 11);
+        assert!(diagnostics[0].message.contains("Light.Yellow"));
+    }
+
+    #[test]
+    fn test_undefined_identifier_range_uses_span() {
+        // `total` also appears earlier in a string, which used to throw off the line search
+        let source = "let label = \"total\";\nlet x = 1 +\n    total;\n".to_string();
+        let stmts = Parser::new(InputStream::new("test.lf", &source))
+            .parse()
+            .unwrap();
+        let diagnostics =
+            check_semantic_errors(&stmts, &[], &source, &crate::analysis::stdlib::load());
+
+        let undefined = diagnostics
+            .iter()
+            .find(|d| d.message == "Undefined identifier 'total'")
+            .unwrap();
+        assert_eq!(undefined.range, Range::on_line(2, 4, 9));
+    }
+
+    #[test]
+    fn test_match_uses_its_subject_and_checks_its_arms() {
+        let source = r#"enum Shape { Circle(num), Square(num) }
+let c = Shape.Circle(2);
+match c {
+    Shape.Circle(r) => term.println(r),
+    Shape.Square(side) => term.println(sid),
+}
+let s = Shape.Square(1);
+let area = match s {
+    Shape.Circle(r) => r * r,
+    Shape.Square(side) => side * sdie,
+};
+term.println(area);
+"#;
+        let diagnostics =
+            crate::analysis::check_source("test.lf", source, &crate::analysis::stdlib::load());
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+
+        assert!(
+            !messages.iter().any(|m| m.starts_with("Unused variable")),
+            "{:?}",
+            messages
+        );
+        let undefined: Vec<&str> = messages
+            .into_iter()
+            .filter(|m| m.starts_with("Undefined identifier"))
+            .collect();
+        assert_eq!(
+            undefined,
+            ["Undefined identifier 'sid'", "Undefined identifier 'sdie'"]
+        );
+    }
+
+    #[test]
+    fn test_parameters_and_builtins_are_defined() {
+        let source =
+            "fn add(a: num, b: num) -> num {\n    return a + b;\n}\nlet double = (x) => x * 2;\nlet name = Option.Some(\"Ada\");\n"
+                .to_string();
+        let stmts = Parser::new(InputStream::new("test.lf", &source))
+            .parse()
+            .unwrap();
+        let diagnostics =
+            check_semantic_errors(&stmts, &[], &source, &crate::analysis::stdlib::load());
+
+        assert!(
+            !diagnostics
+                .iter()
+                .any(|d| d.message.starts_with("Undefined identifier")),
+            "{:?}",
+            diagnostics
+        );
+    }
+}
@@ -0,0 +1,142 @@
+//! Types describing the standard library, loaded from `stdlib_types.json`.
+//!
+//! The build script writes that file from `src/lsp/stdlib_types.json` and
+//! the signatures and doc comments of the `#[loft_builtin]` functions.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The standard library description bundled with the binary, as JSON
+pub const JSON: &str = include_str!(concat!(env!("OUT_DIR"), "/stdlib_types.json"));
+
+/// Load the standard library description bundled with the binary
+pub fn load() -> StdlibTypes {
+    serde_json::from_str(JSON).expect("Failed to parse stdlib_types.json")
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StdlibTypes {
+    pub builtins: HashMap<String, StdlibBuiltin>,
+    pub string_methods: HashMap<String, StdlibMethod>,
+    pub array_methods: HashMap<String, StdlibMethod>,
+    #[serde(default)]
+    pub types: HashMap<String, StdlibType>,
+    #[serde(default)]
+    pub traits: HashMap<String, StdlibTrait>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StdlibTrait {
+    pub documentation: String,
+    #[serde(default)]
+    pub methods: HashMap<String, StdlibMethod>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StdlibBuiltin {
+    pub kind: String,
+    pub documentation: String,
+    #[serde(default)]
+    pub constants: HashMap<String, StdlibConstant>,
+    #[serde(default)]
+    pub methods: HashMap<String, StdlibMethod>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StdlibConstant {
+    #[serde(rename = "type")]
+    pub const_type: String,
+    pub documentation: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StdlibMethod {
+    pub params: Vec<String>,
+    pub return_type: String,
+    pub documentation: String,
+}
+
+type StdlibMethodMap = HashMap<String, StdlibMethod>;
+type StdlibFieldMap = HashMap<String, StdlibField>;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StdlibType {
+    pub kind: String,
+    pub documentation: String,
+    #[serde(default)]
+    pub fields: StdlibFieldMap,
+    #[serde(default)]
+    pub methods: StdlibMethodMap,
+    #[serde(default)]
+    pub variants: Vec<StdlibVariant>,
+    #[serde(default)]
+    pub implements: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum StdlibVariant {
+    Simple(String),
+    WithFields {
+        name: String,
+        #[serde(default)]
+        fields: Vec<String>,
+    },
+}
+
+impl StdlibVariant {
+    pub fn name(&self) -> &str {
+        match self {
+            StdlibVariant::Simple(s) => s,
+            StdlibVariant::WithFields { name, .. } => name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StdlibField {
+    #[serde(rename = "type")]
+    pub field_type: String,
+    pub documentation: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::builtin_registry::BuiltinRegistration;
+
+    /// Placeholders that don't work yet
+    const UNDESCRIBED: &[&str] = &["time.benchmark"];
+
+    #[test]
+    fn test_registered_builtins_are_described() {
+        let types = load();
+        for registration in inventory::iter::<BuiltinRegistration> {
+            let builtin = (registration.factory)();
+            for method in builtin.methods.keys() {
+                if UNDESCRIBED.contains(&format!("{}.{}", registration.name, method).as_str()) {
+                    continue;
+                }
+                let described = types.builtins.get(registration.name).is_some_and(|b| {
+                    b.methods.contains_key(method) || b.constants.contains_key(method)
+                }) || types.types.values().any(|t| t.methods.contains_key(method))
+                    || types.string_methods.contains_key(method)
+                    || types.array_methods.contains_key(method);
+                assert!(
+                    described,
+                    "{}.{} isn't described",
+                    registration.name, method
+                );
+            }
+        }
+
+        let move_to = &types.builtins["term"].methods["move_to"];
+        assert_eq!(move_to.params, ["column: num", "row: num"]);
+        assert_eq!(move_to.return_type, "void");
+        assert_eq!(types.types["Set"].methods["insert"].return_type, "Set");
+        assert_eq!(
+            types.builtins["Set"].methods["from"].params,
+            ["items: Array"]
+        );
+    }
+}
@@ -0,0 +1,923 @@
+//! Symbol table construction and simple type inference.
+
+use super::stdlib::StdlibTypes;
+use super::Range;
+use crate::parser::visit::{self, Visitor};
+use crate::parser::{import_binding_name, is_method, Expr, ExprKind, Stmt, StmtKind, Type};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+pub struct TraitMethodInfo {
+    pub name: String,
+    pub params: Vec<(String, String)>,
+    pub return_type: String,
+    pub has_default_impl: bool,
+}
+
+/// A named item declared in a document
+#[derive(Debug, Clone)]
+pub struct SymbolInfo {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub detail: Option<String>,
+    pub documentation: Option<String>,
+    pub scope_level: usize,   // Track scope level for better symbol resolution
+    pub range: Option<Range>, // Position in document where symbol is defined
+    pub selection_range: Option<Range>, // Range of just the name (for go-to-def)
+    pub source_uri: Option<String>, // URI of the file where this symbol is defined (for imports)
+    pub is_exported: bool,    // Whether this symbol is exported with `teach`
+}
+
+#[derive(Debug, Clone)]
+pub enum SymbolKind {
+    Variable {
+        var_type: Option<String>,
+        mutable: bool,
+    },
+    Function {
+        type_params: Vec<String>,
+        params: Vec<(String, String)>,
+        return_type: String,
+    },
+    Struct {
+        type_params: Vec<String>,
+        fields: Vec<(String, String)>,
+        methods: Vec<String>,
+        /// Functions called on the type itself, like `Point.new`
+        associated: Vec<String>,
+    },
+    Trait {
+        methods: Vec<TraitMethodInfo>,
+    },
+    Enum {
+        variants: Vec<(String, Option<Vec<String>>)>,
+    },
+    Constant {
+        const_type: String,
+    },
+    /// A name bound by `learn`: a whole module, or one `item` taken from it
+    Import {
+        path: Vec<String>,
+        item: Option<String>,
+    },
+}
+
+/// Build the symbol table for a list of statements, recursing into nested scopes
+pub fn extract_symbols(
+    stmts: &[Stmt],
+    scope_level: usize,
+    stdlib_types: &StdlibTypes,
+) -> Vec<SymbolInfo> {
+    let mut symbols = Vec::new();
+
+    // First pass: extract all symbols
+    for stmt in stmts {
+        match &stmt.kind {
+            StmtKind::ImportDecl {
+                path,
+                alias,
+                items,
+                is_exported,
+            } => {
+                let module = path.join("::");
+                let bindings: Vec<(String, Option<String>, String)> = match items {
+                    Some(items) => items
+                        .iter()
+                        .map(|item| {
+                            (
+                                item.local_name().to_string(),
+                                Some(item.name.clone()),
+                                format!("learn {{ {} }} from \"{}\"", item.name, module),
+                            )
+                        })
+                        .collect(),
+                    None => vec![(
+                        alias.clone().unwrap_or_else(|| import_binding_name(path)),
+                        None,
+                        format!("learn \"{}\"", module),
+                    )],
+                };
+
+                for (name, item, detail) in bindings {
+                    symbols.push(SymbolInfo {
+                        name,
+                        kind: SymbolKind::Import {
+                            path: path.clone(),
+                            item,
+                        },
+                        detail: Some(detail),
+                        documentation: None,
+                        scope_level,
+                        range: None,
+                        selection_range: None,
+                        source_uri: None,
+                        is_exported: *is_exported,
+                    });
+                }
+            }
+            StmtKind::VarDecl {
+                name,
+                var_type,
+                mutable,
+                value,
+            } => {
+                // Infer type from value if not explicitly specified
+                let inferred_type = if var_type.is_none() {
+                    value
+                        .as_ref()
+                        .and_then(|v| infer_type_from_expr(v, &symbols, stdlib_types))
+                } else {
+                    None
+                };
+
+                let final_type = var_type.as_ref().map(type_to_string).or(inferred_type);
+
+                symbols.push(SymbolInfo {
+                    name: name.clone(),
+                    kind: SymbolKind::Variable {
+                        var_type: final_type,
+                        mutable: *mutable,
+                    },
+                    detail: Some(format!(
+                        "let {}{}",
+                        if *mutable { "mut " } else { "" },
+                        name
+                    )),
+                    documentation: None,
+                    scope_level,
+                    range: None, // TODO: extract from AST node position
+                    selection_range: None,
+                    source_uri: None,
+                    is_exported: false, // Variables cannot be exported
+                });
+            }
+            StmtKind::ConstDecl {
+                name, const_type, ..
+            } => {
+                symbols.push(SymbolInfo {
+                    name: name.clone(),
+                    kind: SymbolKind::Constant {
+                        const_type: const_type
+                            .as_ref()
+                            .map(type_to_string)
+                            .unwrap_or_else(|| "unknown".to_string()),
+                    },
+                    detail: Some(format!("const {}", name)),
+                    documentation: None,
+                    scope_level,
+                    range: None, // TODO: extract from AST node position
+                    selection_range: None,
+                    source_uri: None,
+                    is_exported: false, // TODO: Detect if constant is preceded by 'teach' keyword
+                });
+            }
+            StmtKind::FunctionDecl {
+                name,
+                type_params,
+                params,
+                variadic,
+                return_type,
+                body,
+                is_exported,
+                ..
+            } => {
+                let param_list = param_list(params, *variadic);
+
+                symbols.push(SymbolInfo {
+                    name: name.clone(),
+                    kind: SymbolKind::Function {
+                        type_params: type_params.clone(),
+                        params: param_list.clone(),
+                        return_type: opt_type_to_string(return_type),
+                    },
+                    detail: Some(format!(
+                        "fn {}{}({})",
+                        name,
+                        type_params_to_string(type_params),
+                        param_list
+                            .iter()
+                            .map(|(n, t)| format!("{}: {}", n, t))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )),
+                    documentation: None,
+                    scope_level,
+                    range: None, // TODO: extract from AST node position
+                    selection_range: None,
+                    source_uri: None,
+                    is_exported: *is_exported,
+                });
+
+                // Recursively extract symbols from function body
+                if let StmtKind::Block(body_stmts) = &body.kind {
+                    symbols.extend(extract_symbols(body_stmts, scope_level + 1, stdlib_types));
+                }
+            }
+            StmtKind::For { var, body, .. } => {
+                // Add loop variable to symbols
+                symbols.push(SymbolInfo {
+                    name: var.clone(),
+                    kind: SymbolKind::Variable {
+                        var_type: None, // Could potentially infer from iterable
+                        mutable: false, // Loft loop variables are typically immutable
+                    },
+                    detail: Some(format!("loop variable {}", var)),
+                    documentation: None,
+                    scope_level,
+                    range: None,
+                    selection_range: None,
+                    source_uri: None,
+                    is_exported: false,
+                });
+
+                // Recursively extract symbols from loop body
+                if let StmtKind::Block(body_stmts) = &body.kind {
+                    symbols.extend(extract_symbols(body_stmts, scope_level + 1, stdlib_types));
+                }
+            }
+            StmtKind::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                if let Some((Some(var), _)) = catch {
+                    symbols.push(SymbolInfo {
+                        name: var.clone(),
+                        kind: SymbolKind::Variable {
+                            var_type: Some("Error".to_string()),
+                            mutable: false,
+                        },
+                        detail: Some(format!("caught error {}", var)),
+                        documentation: None,
+                        scope_level,
+                        range: None,
+                        selection_range: None,
+                        source_uri: None,
+                        is_exported: false,
+                    });
+                }
+
+                // Recursively extract symbols from the try, catch and finally blocks
+                let blocks = std::iter::once(body)
+                    .chain(catch.iter().map(|(_, catch_body)| catch_body))
+                    .chain(finally);
+                for block in blocks {
+                    if let StmtKind::Block(block_stmts) = &block.kind {
+                        symbols.extend(extract_symbols(block_stmts, scope_level + 1, stdlib_types));
+                    }
+                }
+            }
+            StmtKind::StructDecl {
+                name,
+                type_params,
+                fields,
+            } => {
+                let field_list: Vec<(String, String)> = fields
+                    .iter()
+                    .map(|(n, t)| (n.clone(), type_to_string(t)))
+                    .collect();
+
+                symbols.push(SymbolInfo {
+                    name: name.clone(),
+                    kind: SymbolKind::Struct {
+                        type_params: type_params.clone(),
+                        fields: field_list,
+                        methods: Vec::new(), // Will be populated in second pass
+                        associated: Vec::new(),
+                    },
+                    detail: Some(format!(
+                        "struct {}{}",
+                        name,
+                        type_params_to_string(type_params)
+                    )),
+                    documentation: None,
+                    scope_level,
+                    range: None, // TODO: extract from AST node position
+                    selection_range: None,
+                    source_uri: None,
+                    is_exported: false, // TODO: Detect if struct is preceded by 'teach' keyword
+                });
+            }
+            StmtKind::TraitDecl { name, methods } => {
+                let method_infos: Vec<TraitMethodInfo> = methods
+                    .iter()
+                    .map(|m| match m {
+                        crate::parser::TraitMethod::Signature {
+                            name,
+                            params,
+                            return_type,
+                        } => TraitMethodInfo {
+                            name: name.clone(),
+                            params: params
+                                .iter()
+                                .map(|(n, t)| (n.clone(), type_to_string(t)))
+                                .collect(),
+                            return_type: type_to_string(return_type),
+                            has_default_impl: false,
+                        },
+                        crate::parser::TraitMethod::Default {
+                            name,
+                            params,
+                            return_type,
+                            ..
+                        } => TraitMethodInfo {
+                            name: name.clone(),
+                            params: params
+                                .iter()
+                                .map(|(n, t)| (n.clone(), type_to_string(t)))
+                                .collect(),
+                            return_type: type_to_string(return_type),
+                            has_default_impl: true,
+                        },
+                    })
+                    .collect();
+
+                symbols.push(SymbolInfo {
+                    name: name.clone(),
+                    kind: SymbolKind::Trait {
+                        methods: method_infos,
+                    },
+                    detail: Some(format!("trait {}", name)),
+                    documentation: None,
+                    scope_level,
+                    range: None, // TODO: extract from AST node position
+                    selection_range: None,
+                    source_uri: None,
+                    is_exported: false, // TODO: Detect if trait is preceded by 'teach' keyword
+                });
+            }
+            StmtKind::EnumDecl { name, variants } => {
+                let variant_infos: Vec<(String, Option<Vec<String>>)> = variants
+                    .iter()
+                    .map(|(n, fields)| {
+                        let field_types = fields
+                            .as_ref()
+                            .map(|types| types.iter().map(type_to_string).collect());
+                        (n.clone(), field_types)
+                    })
+                    .collect();
+
+                symbols.push(SymbolInfo {
+                    name: name.clone(),
+                    kind: SymbolKind::Enum {
+                        variants: variant_infos,
+                    },
+                    detail: Some(format!("enum {}", name)),
+                    documentation: None,
+                    scope_level,
+                    range: None, // TODO: extract from AST node position
+                    selection_range: None,
+                    source_uri: None,
+                    is_exported: false, // TODO: Detect if enum is preceded by 'teach' keyword
+                });
+            }
+            StmtKind::Block(stmts) => {
+                // Recursively extract symbols from blocks with increased scope level
+                symbols.extend(extract_symbols(stmts, scope_level + 1, stdlib_types));
+            }
+            _ => {}
+        }
+    }
+
+    // Second pass: extract methods from impl blocks and associate with structs
+    for stmt in stmts {
+        if let StmtKind::ImplBlock {
+            type_name,
+            trait_name,
+            methods,
+        } = &stmt.kind
+        {
+            // Extract method and associated function names from the impl block
+            let (mut method_names, associated_names): (Vec<_>, Vec<_>) = methods
+                .iter()
+                .filter_map(|m| {
+                    if let StmtKind::FunctionDecl { name, params, .. } = &m.kind {
+                        Some((name.clone(), is_method(params)))
+                    } else {
+                        None
+                    }
+                })
+                .partition(|(_, is_method)| *is_method);
+
+            // Trait methods left out of the impl come from their default bodies
+            let trait_methods = symbols.iter().find_map(|s| match &s.kind {
+                SymbolKind::Trait { methods } if Some(&s.name) == trait_name.as_ref() => {
+                    Some(methods)
+                }
+                _ => None,
+            });
+            for method in trait_methods.into_iter().flatten() {
+                if method.has_default_impl && !method_names.iter().any(|(n, _)| *n == method.name) {
+                    method_names.push((method.name.clone(), true));
+                }
+            }
+
+            // Find the struct and add methods to it
+            for symbol in symbols.iter_mut() {
+                if symbol.name == *type_name {
+                    if let SymbolKind::Struct {
+                        methods,
+                        associated,
+                        ..
+                    } = &mut symbol.kind
+                    {
+                        methods.extend(method_names.iter().map(|(name, _)| name.clone()));
+                        associated.extend(associated_names.iter().map(|(name, _)| name.clone()));
+                    }
+                }
+            }
+
+            // Also extract symbols from the methods, injecting 'self'
+            for method in methods {
+                if let StmtKind::FunctionDecl {
+                    name,
+                    type_params,
+                    params,
+                    variadic,
+                    return_type,
+                    body,
+                    is_exported,
+                    ..
+                } = &method.kind
+                {
+                    let param_list = param_list(params, *variadic);
+
+                    // Add 'self' to the method scope
+                    let mut method_symbols = Vec::new();
+                    if is_method(params) {
+                        method_symbols.push(SymbolInfo {
+                            name: "self".to_string(),
+                            kind: SymbolKind::Variable {
+                                var_type: Some(type_name.clone()),
+                                mutable: false,
+                            },
+                            detail: Some(format!("self: {}", type_name)),
+                            documentation: Some("The instance of the struct".to_string()),
+                            scope_level: scope_level + 1,
+                            range: None,
+                            selection_range: None,
+                            source_uri: None,
+                            is_exported: false,
+                        });
+                    }
+
+                    method_symbols.push(SymbolInfo {
+                        name: name.clone(),
+                        kind: SymbolKind::Function {
+                            type_params: type_params.clone(),
+                            params: param_list.clone(),
+                            return_type: opt_type_to_string(return_type),
+                        },
+                        detail: Some(format!(
+                            "fn {}{}({})",
+                            name,
+                            type_params_to_string(type_params),
+                            param_list
+                                .iter()
+                                .map(|(n, t)| format!("{}: {}", n, t))
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        )),
+                        documentation: None,
+                        scope_level: scope_level + 1, // Method scope
+                        range: None,
+                        selection_range: None,
+                        source_uri: None,
+                        is_exported: *is_exported,
+                    });
+
+                    // Recursively extract symbols from function body
+                    if let StmtKind::Block(body_stmts) = &body.kind {
+                        method_symbols.extend(extract_symbols(
+                            body_stmts,
+                            scope_level + 2,
+                            stdlib_types,
+                        ));
+                    }
+
+                    symbols.extend(method_symbols);
+                }
+            }
+        }
+    }
+
+    symbols
+}
+
+/// Best-effort static type of an expression, if it can be told without running it
+pub fn infer_type_from_expr(
+    expr: &Expr,
+    symbols: &[SymbolInfo],
+    stdlib_types: &StdlibTypes,
+) -> Option<String> {
+    match &expr.kind {
+        ExprKind::Number(_) => Some("num".to_string()),
+        ExprKind::String(_) => Some("str".to_string()),
+        ExprKind::Boolean(_) => Some("bool".to_string()),
+        ExprKind::ArrayLiteral(_) => Some("Array".to_string()),
+        ExprKind::MapLiteral(_) => Some("Map".to_string()),
+        ExprKind::StructLiteral { name, fields } => {
+            let Some(SymbolKind::Struct {
+                type_params,
+                fields: declared,
+                ..
+            }) = symbols.iter().find(|s| s.name == *name).map(|s| &s.kind)
+            else {
+                return Some(name.clone());
+            };
+            if type_params.is_empty() {
+                return Some(name.clone());
+            }
+
+            // Box { value: 1 } is a Box<num>
+            let mut bindings = HashMap::new();
+            for (field_name, value) in fields {
+                if let (Some((_, pattern)), Some(actual)) = (
+                    declared.iter().find(|(n, _)| n == field_name),
+                    infer_type_from_expr(value, symbols, stdlib_types),
+                ) {
+                    bind_type_params(pattern, &actual, type_params, &mut bindings);
+                }
+            }
+            let args: Vec<String> = type_params
+                .iter()
+                .map(|p| instantiate(p, type_params, &bindings))
+                .collect();
+            Some(format!("{}<{}>", name, args.join(", ")))
+        }
+        ExprKind::FieldAccess { object, field } => {
+            let object_type = infer_type_from_expr(object, symbols, stdlib_types)?;
+            let (base, _) = split_generic_type(&object_type);
+            let struct_symbol = symbols.iter().find(|s| s.name == base)?;
+            if let SymbolKind::Struct {
+                type_params,
+                fields,
+                ..
+            } = &struct_symbol.kind
+            {
+                return instantiate_fields(type_params, fields, &object_type)
+                    .into_iter()
+                    .find(|(n, _)| n == field)
+                    .map(|(_, t)| t);
+            }
+            None
+        }
+        ExprKind::Call { func, args } => {
+            // Try to infer return type from function call
+            match &func.kind {
+                ExprKind::Ident(func_name) => {
+                    // Look up the function in symbols
+                    if let Some(func_symbol) = symbols.iter().find(|s| s.name == *func_name) {
+                        if let SymbolKind::Function {
+                            type_params,
+                            params,
+                            return_type,
+                        } = &func_symbol.kind
+                        {
+                            if type_params.is_empty() {
+                                return Some(return_type.clone());
+                            }
+
+                            // Work out the type parameters from the arguments
+                            let mut bindings = HashMap::new();
+                            for ((_, pattern), arg) in params.iter().zip(args) {
+                                if let Some(actual) =
+                                    infer_type_from_expr(arg, symbols, stdlib_types)
+                                {
+                                    bind_type_params(pattern, &actual, type_params, &mut bindings);
+                                }
+                            }
+                            let ty = instantiate(return_type, type_params, &bindings);
+                            return (ty != "unknown").then_some(ty);
+                        }
+                    }
+                    None
+                }
+                ExprKind::FieldAccess { object, field } => {
+                    // Check if it's a builtin method call (e.g., web.get())
+                    if let ExprKind::Ident(obj_name) = &object.kind {
+                        // Look up the builtin and its method
+                        if let Some(builtin) = stdlib_types.builtins.get(obj_name) {
+                            if let Some(method) = builtin.methods.get(field) {
+                                return Some(method.return_type.clone());
+                            }
+                        }
+                    }
+                    None
+                }
+                _ => None,
+            }
+        }
+        ExprKind::Lazy(inner) => {
+            // For lazy expressions, wrap the inner type in Promise<T>
+            // This represents a lazy future that will evaluate to the inner type
+            if let Some(inner_type) = infer_type_from_expr(inner, symbols, stdlib_types) {
+                Some(format!("Promise<{}>", inner_type))
+            } else {
+                Some("Promise<unknown>".to_string())
+            }
+        }
+        ExprKind::Async(inner) => {
+            // For async expressions, wrap the inner type in Promise<T>
+            // This represents an eager future that will evaluate to the inner type
+            if let Some(inner_type) = infer_type_from_expr(inner, symbols, stdlib_types) {
+                Some(format!("Promise<{}>", inner_type))
+            } else {
+                Some("Promise<unknown>".to_string())
+            }
+        }
+        ExprKind::Await(inner) => {
+            // For await expressions, unwrap the Promise<T> to get T
+            if let Some(inner_type) = infer_type_from_expr(inner, symbols, stdlib_types) {
+                // If it's a Promise<T>, extract T
+                if inner_type.starts_with("Promise<") && inner_type.ends_with(">") {
+                    let t = &inner_type[8..inner_type.len() - 1];
+                    Some(t.to_string())
+                } else {
+                    // If it's not a Promise type, just return the type as-is
+                    Some(inner_type)
+                }
+            } else {
+                None
+            }
+        }
+        ExprKind::BinOp { op, left, right } => match op.as_str() {
+            "==" | "!=" | "<" | ">" | "<=" | ">=" | "&&" | "||" => Some("bool".to_string()),
+            "+" | "-" | "*" | "/" | "%" => {
+                let left = infer_type_from_expr(left, symbols, stdlib_types)?;
+                let right = infer_type_from_expr(right, symbols, stdlib_types)?;
+                match (left.as_str(), right.as_str()) {
+                    ("num", "num") => Some("num".to_string()),
+                    ("str", _) | (_, "str") if op == "+" => Some("str".to_string()),
+                    _ => None,
+                }
+            }
+            _ => None,
+        },
+        ExprKind::UnaryOp { op, expr } => match op.as_str() {
+            "!" => Some("bool".to_string()),
+            "-" => infer_type_from_expr(expr, symbols, stdlib_types).filter(|t| t == "num"),
+            _ => None,
+        },
+        ExprKind::TemplateLiteral { .. } => Some("str".to_string()),
+        ExprKind::Block(stmts) => match &stmts.last()?.kind {
+            StmtKind::Expr(value) => infer_type_from_expr(value, symbols, stdlib_types),
+            _ => None,
+        },
+        ExprKind::If {
+            then_branch,
+            else_branch,
+            ..
+        } => {
+            // Both branches have to agree, and without an else the value may be unit
+            let then_type = infer_type_from_expr(then_branch, symbols, stdlib_types)?;
+            let else_type = infer_type_from_expr(else_branch.as_ref()?, symbols, stdlib_types)?;
+            (then_type == else_type).then_some(then_type)
+        }
+        ExprKind::Ident(name) => {
+            // Look up the variable in symbols to get its type
+            if let Some(var_symbol) = symbols.iter().find(|s| s.name == *name) {
+                if let SymbolKind::Variable { var_type, .. } = &var_symbol.kind {
+                    return var_type.clone();
+                }
+            }
+            None
+        }
+        _ => None,
+    }
+}
+
+/// Fill in where the top-level declarations of `stmts` are in `source`: the
+/// whole declaration as the range and its name as the selection range
+pub fn locate_declarations(stmts: &[Stmt], source: &str, symbols: &mut [SymbolInfo]) {
+    let lines: Vec<&str> = source.lines().collect();
+    for stmt in stmts {
+        let declared = match &stmt.kind {
+            StmtKind::AttrStmt { stmt: inner, .. } => &inner.kind,
+            kind => kind,
+        };
+        let name = match declared {
+            StmtKind::VarDecl { name, .. }
+            | StmtKind::ConstDecl { name, .. }
+            | StmtKind::FunctionDecl { name, .. }
+            | StmtKind::StructDecl { name, .. }
+            | StmtKind::TraitDecl { name, .. }
+            | StmtKind::EnumDecl { name, .. } => name,
+            _ => continue,
+        };
+        let Some(symbol) = symbols
+            .iter_mut()
+            .find(|s| s.scope_level == 0 && s.name == *name && s.range.is_none())
+        else {
+            continue;
+        };
+
+        let range = Range::from_span(stmt.span, &lines);
+        symbol.range = Some(range);
+        symbol.selection_range = Some(
+            name_column(&lines, stmt.span.line, stmt.span.column, name).map_or(range, |column| {
+                Range::on_line(stmt.span.line, column, column + name.chars().count())
+            }),
+        );
+    }
+}
+
+/// Column of the first whole-word `name` on `line` at or after `column`
+fn name_column(lines: &[&str], line: usize, column: usize, name: &str) -> Option<usize> {
+    let chars: Vec<char> = lines.get(line)?.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let is_ident = |c: Option<&char>| c.is_some_and(|c| c.is_alphanumeric() || *c == '_');
+    (column..chars.len()).find(|&i| {
+        chars[i..].starts_with(&name)
+            && !is_ident(i.checked_sub(1).and_then(|j| chars.get(j)))
+            && !is_ident(chars.get(i + name.len()))
+    })
+}
+
+/// Collect the paths of every `learn` statement, including nested ones
+pub fn collect_imports(stmts: &[Stmt]) -> Vec<Vec<String>> {
+    struct Imports(Vec<Vec<String>>);
+
+    impl Visitor for Imports {
+        fn visit_stmt(&mut self, stmt: &Stmt) {
+            if let StmtKind::ImportDecl { path, .. } = &stmt.kind {
+                self.0.push(path.clone());
+            }
+            visit::walk_stmt(self, stmt);
+        }
+    }
+
+    let mut imports = Imports(Vec::new());
+    visit::walk_stmts(&mut imports, stmts);
+    imports.0
+}
+
+/// Render a type annotation the way it is written in source
+pub fn type_to_string(ty: &Type) -> String {
+    match ty {
+        Type::Named(name) => name.clone(),
+        Type::Generic { base, type_args } => {
+            format!(
+                "{}<{}>",
+                base,
+                type_args
+                    .iter()
+                    .map(type_to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        }
+        Type::Function {
+            params,
+            return_type,
+        } => {
+            format!(
+                "fn({}) -> {}",
+                params
+                    .iter()
+                    .map(type_to_string)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                type_to_string(return_type)
+            )
+        }
+    }
+}
+
+/// Render a declaration's type parameters as `<A, B>`, or nothing if it has none
+pub fn type_params_to_string(type_params: &[String]) -> String {
+    if type_params.is_empty() {
+        String::new()
+    } else {
+        format!("<{}>", type_params.join(", "))
+    }
+}
+
+/// Split a type like `Map<str, Array<num>>` into its base name and top-level type arguments
+/// Parameters with their types rendered, a rest parameter named `...name`
+/// so it shows up the way it was written
+fn param_list(params: &[(String, Type)], variadic: bool) -> Vec<(String, String)> {
+    let rest = params.len().saturating_sub(variadic as usize);
+    params
+        .iter()
+        .enumerate()
+        .map(|(i, (n, t))| {
+            let name = if i == rest {
+                format!("...{}", n)
+            } else {
+                n.clone()
+            };
+            (name, type_to_string(t))
+        })
+        .collect()
+}
+
+/// Whether a parameter of a [`SymbolKind::Function`] is a rest parameter
+pub fn is_rest_param(name: &str) -> bool {
+    name.starts_with("...")
+}
+
+pub fn split_generic_type(ty: &str) -> (&str, Vec<&str>) {
+    let Some(open) = ty.find('<').filter(|_| ty.ends_with('>')) else {
+        return (ty.trim(), Vec::new());
+    };
+
+    let inner = &ty[open + 1..ty.len() - 1];
+    let mut args = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in inner.char_indices() {
+        match c {
+            '<' | '(' => depth += 1,
+            '>' | ')' => depth -= 1,
+            ',' if depth == 0 => {
+                args.push(inner[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    args.push(inner[start..].trim());
+    (ty[..open].trim(), args)
+}
+
+/// Replace whole-word type names in `ty` using `bindings`
+pub fn substitute_type_params(ty: &str, bindings: &HashMap<String, String>) -> String {
+    let mut out = String::new();
+    let mut word = String::new();
+    for c in ty.chars().chain(std::iter::once(' ')) {
+        if c.is_alphanumeric() || c == '_' {
+            word.push(c);
+            continue;
+        }
+        out.push_str(bindings.get(&word).unwrap_or(&word));
+        word.clear();
+        out.push(c);
+    }
+    out.pop();
+    out
+}
+
+/// Match a declared type against the type of an actual value, recording what each
+/// type parameter stands for. The first binding of a parameter wins.
+pub fn bind_type_params(
+    pattern: &str,
+    actual: &str,
+    type_params: &[String],
+    bindings: &mut HashMap<String, String>,
+) {
+    if type_params.iter().any(|p| p == pattern) {
+        if actual != "unknown" {
+            bindings
+                .entry(pattern.to_string())
+                .or_insert_with(|| actual.to_string());
+        }
+        return;
+    }
+
+    let (pattern_base, pattern_args) = split_generic_type(pattern);
+    let (actual_base, actual_args) = split_generic_type(actual);
+    if pattern_base == actual_base && pattern_args.len() == actual_args.len() {
+        for (p, a) in pattern_args.iter().zip(&actual_args) {
+            bind_type_params(p, a, type_params, bindings);
+        }
+    }
+}
+
+/// Fill in the type parameters of a generic declaration; any that could not be
+/// worked out become `unknown`
+fn instantiate(ty: &str, type_params: &[String], bindings: &HashMap<String, String>) -> String {
+    let mut bindings = bindings.clone();
+    for param in type_params {
+        bindings
+            .entry(param.clone())
+            .or_insert_with(|| "unknown".to_string());
+    }
+    substitute_type_params(ty, &bindings)
+}
+
+/// The field types of a struct as seen through an instance type such as `Box<num>`
+pub fn instantiate_fields(
+    type_params: &[String],
+    fields: &[(String, String)],
+    instance_type: &str,
+) -> Vec<(String, String)> {
+    let (_, args) = split_generic_type(instance_type);
+    let bindings: HashMap<String, String> = type_params
+        .iter()
+        .cloned()
+        .zip(args.into_iter().map(str::to_string))
+        .collect();
+    fields
+        .iter()
+        .map(|(n, t)| (n.clone(), instantiate(t, type_params, &bindings)))
+        .collect()
+}
+
+/// Like [`type_to_string`], treating a missing return type as `void`
+pub fn opt_type_to_string(ty: &Option<Type>) -> String {
+    match ty {
+        Some(t) => type_to_string(t),
+        None => "void".to_string(),
+    }
+}
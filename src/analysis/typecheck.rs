@@ -0,0 +1,1289 @@
+//! Opt-in strict type checking (`loft check --strict` or `"strict": true` in
+//! manifest.json).
+//!
+//! The checker is gradual: anything without a known type is `unknown` and is
+//! accepted everywhere, so unannotated code keeps working. Where types are
+//! known it verifies variable declarations, assignments, call arguments,
+//! struct literal fields and return values. Expressions are checked against
+//! the type expected by their context when there is one (array elements
+//! against `Array<T>`, lambda bodies against `fn(..) -> T`) and have their
+//! type synthesized bottom-up otherwise.
+
+use super::stdlib::StdlibTypes;
+use super::symbols::{
+    bind_type_params, instantiate_fields, split_generic_type, substitute_type_params,
+    type_to_string,
+};
+use super::{Diagnostic, Range, Severity};
+use crate::parser::{Expr, ExprKind, FormatSpec, Stmt, StmtKind, TemplatePart, TraitMethod, Type};
+use std::collections::HashMap;
+
+/// Signature of a function or method, with types rendered as strings
+#[derive(Debug, Clone)]
+struct FnSig {
+    type_params: Vec<String>,
+    params: Vec<(String, String)>,
+    /// The last parameter collects the remaining arguments into an array
+    variadic: bool,
+    /// `None` when the return type was not annotated
+    return_type: Option<String>,
+}
+
+impl FnSig {
+    fn new(
+        type_params: &[String],
+        params: &[(String, Type)],
+        variadic: bool,
+        return_type: &Option<Type>,
+    ) -> Self {
+        FnSig {
+            type_params: type_params.to_vec(),
+            params: params
+                .iter()
+                .map(|(n, t)| (n.clone(), type_to_string(t)))
+                .collect(),
+            variadic,
+            return_type: return_type.as_ref().map(type_to_string),
+        }
+    }
+
+    /// The type each argument is checked against, `None` past the last parameter
+    fn param_type(&self, index: usize) -> Option<(&str, String)> {
+        let fixed = self.params.len() - self.variadic as usize;
+        if index < fixed {
+            let (name, ty) = &self.params[index];
+            return Some((name, ty.clone()));
+        }
+        let (name, rest) = self.params.last().filter(|_| self.variadic)?;
+        match split_generic_type(rest) {
+            ("Array", item) if item.len() == 1 => Some((name, item[0].to_string())),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct StructSig {
+    type_params: Vec<String>,
+    fields: Vec<(String, String)>,
+}
+
+/// Functions, structs and methods visible to the checker
+#[derive(Debug, Clone, Default)]
+pub struct Declarations {
+    functions: HashMap<String, FnSig>,
+    structs: HashMap<String, StructSig>,
+    /// Methods keyed by type name, then method name
+    methods: HashMap<String, HashMap<String, FnSig>>,
+    /// Trait methods with a default body, keyed by trait name, then method name
+    trait_defaults: HashMap<String, HashMap<String, FnSig>>,
+    /// Traits implemented by each type
+    trait_impls: HashMap<String, Vec<String>>,
+}
+
+impl Declarations {
+    /// Record the top-level declarations of a file. With `exported_only`, only
+    /// functions marked `teach` are taken, as seen from other files of a project.
+    pub fn collect(&mut self, stmts: &[Stmt], exported_only: bool) {
+        for stmt in stmts {
+            let stmt = match &stmt.kind {
+                StmtKind::AttrStmt { stmt, .. } => stmt,
+                _ => stmt,
+            };
+            match &stmt.kind {
+                StmtKind::FunctionDecl {
+                    name,
+                    type_params,
+                    params,
+                    variadic,
+                    return_type,
+                    is_exported,
+                    ..
+                } if *is_exported || !exported_only => {
+                    self.functions.insert(
+                        name.clone(),
+                        FnSig::new(type_params, params, *variadic, return_type),
+                    );
+                }
+                StmtKind::StructDecl {
+                    name,
+                    type_params,
+                    fields,
+                } => {
+                    self.structs.insert(
+                        name.clone(),
+                        StructSig {
+                            type_params: type_params.clone(),
+                            fields: fields
+                                .iter()
+                                .map(|(n, t)| (n.clone(), type_to_string(t)))
+                                .collect(),
+                        },
+                    );
+                }
+                StmtKind::ImplBlock {
+                    type_name,
+                    trait_name,
+                    methods,
+                } => {
+                    if let Some(trait_name) = trait_name {
+                        self.trait_impls
+                            .entry(type_name.clone())
+                            .or_default()
+                            .push(trait_name.clone());
+                    }
+                    let table = self.methods.entry(type_name.clone()).or_default();
+                    for method in methods {
+                        if let StmtKind::FunctionDecl {
+                            name,
+                            type_params,
+                            params,
+                            variadic,
+                            return_type,
+                            ..
+                        } = &method.kind
+                        {
+                            table.insert(
+                                name.clone(),
+                                FnSig::new(type_params, params, *variadic, return_type),
+                            );
+                        }
+                    }
+                }
+                StmtKind::TraitDecl { name, methods } => {
+                    let table = self.trait_defaults.entry(name.clone()).or_default();
+                    for method in methods {
+                        if let TraitMethod::Default {
+                            name,
+                            params,
+                            return_type,
+                            ..
+                        } = method
+                        {
+                            let return_type = Some(return_type.clone());
+                            table
+                                .insert(name.clone(), FnSig::new(&[], params, false, &return_type));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// The method `method` of `type_name`, from its impl blocks or else the
+    /// default body of a trait it implements
+    fn method(&self, type_name: &str, method: &str) -> Option<&FnSig> {
+        self.methods
+            .get(type_name)
+            .and_then(|methods| methods.get(method))
+            .or_else(|| {
+                self.trait_impls.get(type_name)?.iter().find_map(|t| {
+                    self.trait_defaults
+                        .get(t)
+                        .and_then(|defaults| defaults.get(method))
+                })
+            })
+    }
+}
+
+/// Type check a parsed file against `declarations`, which should already hold
+/// what the file can see from the rest of the project
+pub fn check_types(
+    stmts: &[Stmt],
+    source: &str,
+    declarations: &Declarations,
+    stdlib_types: &StdlibTypes,
+) -> Vec<Diagnostic> {
+    let mut declarations = declarations.clone();
+    declarations.collect(stmts, false);
+
+    let lines: Vec<&str> = source.lines().collect();
+    let mut checker = Checker {
+        declarations: &declarations,
+        stdlib_types,
+        lines: &lines,
+        scopes: vec![HashMap::new()],
+        function: None,
+        diagnostics: Vec::new(),
+    };
+    checker.check_stmts(stmts);
+    checker.diagnostics
+}
+
+/// Whether a value of type `actual` may be used where `expected` is required
+pub fn is_assignable(expected: &str, actual: &str) -> bool {
+    let dynamic = |t: &str| matches!(t, "any" | "unknown" | "Object");
+    if dynamic(expected) || dynamic(actual) || expected == actual {
+        return true;
+    }
+    if expected.starts_with("fn(") || expected == "fn" {
+        return actual.starts_with("fn(") || actual == "fn";
+    }
+
+    let (expected_base, expected_args) = split_generic_type(expected);
+    let (actual_base, actual_args) = split_generic_type(actual);
+    if expected_base != actual_base {
+        return false;
+    }
+    // A bare `Array` says nothing about its elements
+    if expected_args.len() != actual_args.len() {
+        return true;
+    }
+    expected_args
+        .iter()
+        .zip(&actual_args)
+        .all(|(e, a)| is_assignable(e, a))
+}
+
+/// The function whose body is being checked
+struct FunctionContext {
+    name: String,
+    return_type: Option<String>,
+}
+
+struct Checker<'a> {
+    declarations: &'a Declarations,
+    stdlib_types: &'a StdlibTypes,
+    lines: &'a [&'a str],
+    /// Known variable types, innermost scope last; `None` for unknown
+    scopes: Vec<HashMap<String, Option<String>>>,
+    function: Option<FunctionContext>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker<'_> {
+    fn error(&mut self, expr: &Expr, message: String) {
+        self.diagnostics.push(
+            Diagnostic::new(
+                Range::from_span(expr.span, self.lines),
+                Severity::Error,
+                message,
+            )
+            .with_code("type_error"),
+        );
+    }
+
+    fn declare(&mut self, name: &str, ty: Option<String>) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), ty);
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Option<String>> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn in_scope<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        self.scopes.push(HashMap::new());
+        let result = f(self);
+        self.scopes.pop();
+        result
+    }
+
+    fn check_stmts(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts {
+            self.check_stmt(stmt);
+        }
+    }
+
+    fn check_stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::VarDecl {
+                name,
+                var_type,
+                value,
+                ..
+            } => {
+                let declared = var_type.as_ref().map(type_to_string);
+                let ty = match (&declared, value) {
+                    (Some(declared), Some(value)) => {
+                        self.check_expr(value, declared, &format!("variable '{}'", name));
+                        Some(declared.clone())
+                    }
+                    (None, Some(value)) => self.synth(value),
+                    (declared, None) => declared.clone(),
+                };
+                self.declare(name, ty);
+            }
+            StmtKind::ConstDecl {
+                name,
+                const_type,
+                value,
+            } => {
+                let ty = match const_type.as_ref().map(type_to_string) {
+                    Some(declared) => {
+                        self.check_expr(value, &declared, &format!("constant '{}'", name));
+                        Some(declared)
+                    }
+                    None => self.synth(value),
+                };
+                self.declare(name, ty);
+            }
+            StmtKind::Assign { name, value } => match self.lookup(name).cloned().flatten() {
+                Some(declared) => {
+                    self.check_expr(value, &declared, &format!("assignment to '{}'", name))
+                }
+                None => {
+                    self.synth(value);
+                }
+            },
+            StmtKind::FunctionDecl {
+                name,
+                params,
+                return_type,
+                body,
+                ..
+            } => self.check_function(name, params, return_type, body, None),
+            StmtKind::ImplBlock {
+                type_name, methods, ..
+            } => {
+                for method in methods {
+                    if let StmtKind::FunctionDecl {
+                        name,
+                        params,
+                        return_type,
+                        body,
+                        ..
+                    } = &method.kind
+                    {
+                        self.check_function(name, params, return_type, body, Some(type_name));
+                    }
+                }
+            }
+            StmtKind::TraitDecl { methods, .. } => {
+                for method in methods {
+                    if let TraitMethod::Default {
+                        name,
+                        params,
+                        return_type,
+                        body,
+                    } = method
+                    {
+                        let return_type = Some(return_type.clone());
+                        self.check_function(name, params, &return_type, body, None);
+                    }
+                }
+            }
+            StmtKind::AttrStmt { stmt, .. } => self.check_stmt(stmt),
+            StmtKind::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.synth(condition);
+                self.in_scope(|c| c.check_stmt(then_branch));
+                if let Some(else_branch) = else_branch {
+                    self.in_scope(|c| c.check_stmt(else_branch));
+                }
+            }
+            StmtKind::While { condition, body } => {
+                self.synth(condition);
+                self.in_scope(|c| c.check_stmt(body));
+            }
+            StmtKind::For {
+                var,
+                iterable,
+                body,
+            } => {
+                let item = self.synth(iterable).and_then(|ty| {
+                    let (base, args) = split_generic_type(&ty);
+                    match (base, args.as_slice()) {
+                        ("Array" | "Set", [item]) => Some(item.to_string()),
+                        ("str", []) => Some("str".to_string()),
+                        _ => None,
+                    }
+                });
+                self.in_scope(|c| {
+                    c.declare(var, item);
+                    c.check_stmt(body);
+                });
+            }
+            StmtKind::Match { expr, arms } => {
+                self.synth(expr);
+                for (_, arm) in arms {
+                    self.in_scope(|c| c.check_stmt(arm));
+                }
+            }
+            StmtKind::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                self.in_scope(|c| c.check_stmt(body));
+                if let Some((var, catch_body)) = catch {
+                    self.in_scope(|c| {
+                        if let Some(var) = var {
+                            c.declare(var, None);
+                        }
+                        c.check_stmt(catch_body);
+                    });
+                }
+                if let Some(finally) = finally {
+                    self.in_scope(|c| c.check_stmt(finally));
+                }
+            }
+            StmtKind::Return(value) => self.check_return(stmt, value.as_ref()),
+            StmtKind::Expr(expr) | StmtKind::Throw(expr) | StmtKind::Defer(expr) => {
+                self.synth(expr);
+            }
+            StmtKind::Block(stmts) => self.in_scope(|c| c.check_stmts(stmts)),
+            StmtKind::ImportDecl { .. }
+            | StmtKind::StructDecl { .. }
+            | StmtKind::EnumDecl { .. }
+            | StmtKind::Break
+            | StmtKind::Continue => {}
+        }
+    }
+
+    fn check_function(
+        &mut self,
+        name: &str,
+        params: &[(String, Type)],
+        return_type: &Option<Type>,
+        body: &Stmt,
+        self_type: Option<&str>,
+    ) {
+        let outer = self.function.replace(FunctionContext {
+            name: name.to_string(),
+            return_type: return_type.as_ref().map(type_to_string),
+        });
+        self.in_scope(|c| {
+            for (param, ty) in params {
+                let ty = match (type_to_string(ty).as_str(), self_type) {
+                    ("Self", Some(self_type)) => self_type.to_string(),
+                    ("Self", None) => "unknown".to_string(),
+                    (ty, _) => ty.to_string(),
+                };
+                c.declare(param, Some(ty));
+            }
+            c.check_stmt(body);
+        });
+        self.function = outer;
+    }
+
+    fn check_return(&mut self, stmt: &Stmt, value: Option<&Expr>) {
+        let Some(FunctionContext {
+            name,
+            return_type: Some(return_type),
+        }) = &self.function
+        else {
+            if let Some(value) = value {
+                self.synth(value);
+            }
+            return;
+        };
+        let (name, return_type) = (name.clone(), return_type.clone());
+
+        match value {
+            Some(value) if return_type == "void" => {
+                self.synth(value);
+                self.error(
+                    value,
+                    format!("Function '{}' returns void but a value is returned", name),
+                );
+            }
+            Some(value) => {
+                self.check_expr(value, &return_type, &format!("return value of '{}'", name))
+            }
+            None if return_type != "void" => {
+                let stmt = Expr::new(ExprKind::Block(Vec::new()), stmt.span);
+                self.error(
+                    &stmt,
+                    format!(
+                        "Missing return value: function '{}' returns '{}'",
+                        name, return_type
+                    ),
+                );
+            }
+            None => {}
+        }
+    }
+
+    /// Check `expr` against the type its context expects, pushing the
+    /// expectation into literals so errors point at the offending element
+    fn check_expr(&mut self, expr: &Expr, expected: &str, context: &str) {
+        let (base, args) = split_generic_type(expected);
+        match (&expr.kind, base, args.as_slice()) {
+            (ExprKind::ArrayLiteral(items), "Array", [item]) => {
+                let item = item.to_string();
+                for element in items {
+                    match &element.kind {
+                        ExprKind::Spread(inner) => {
+                            self.check_expr(inner, expected, &format!("spread into {}", context))
+                        }
+                        _ => self.check_expr(element, &item, &format!("element of {}", context)),
+                    }
+                }
+            }
+            (ExprKind::MapLiteral(entries), "Map", [key, value]) => {
+                let (key, value) = (key.to_string(), value.to_string());
+                for (k, v) in entries {
+                    self.check_expr(k, &key, &format!("key of {}", context));
+                    self.check_expr(v, &value, &format!("value of {}", context));
+                }
+            }
+            (
+                ExprKind::If {
+                    condition,
+                    then_branch,
+                    else_branch: Some(else_branch),
+                },
+                _,
+                _,
+            ) => {
+                self.synth(condition);
+                self.check_expr(then_branch, expected, context);
+                self.check_expr(else_branch, expected, context);
+            }
+            (ExprKind::Block(stmts), _, _) => match stmts.split_last() {
+                Some((
+                    Stmt {
+                        kind: StmtKind::Expr(value),
+                        ..
+                    },
+                    rest,
+                )) => self.in_scope(|c| {
+                    c.check_stmts(rest);
+                    c.check_expr(value, expected, context);
+                }),
+                _ => {
+                    self.synth(expr);
+                }
+            },
+            (ExprKind::Lambda { .. }, _, _) if expected.starts_with("fn(") => {
+                self.check_lambda(expr, expected, context);
+            }
+            _ => {
+                if let Some(actual) = self.synth(expr) {
+                    if !is_assignable(expected, &actual) {
+                        self.error(
+                            expr,
+                            format!(
+                                "Type mismatch in {}: expected '{}', found '{}'",
+                                context, expected, actual
+                            ),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Check a lambda against an expected `fn(A, B) -> R`, giving its
+    /// unannotated parameters the expected types
+    fn check_lambda(&mut self, expr: &Expr, expected: &str, context: &str) {
+        let ExprKind::Lambda {
+            params,
+            return_type,
+            body,
+        } = &expr.kind
+        else {
+            return;
+        };
+        let (param_types, expected_return) = split_fn_type(expected);
+
+        self.in_scope(|c| {
+            for (i, (name, ty)) in params.iter().enumerate() {
+                let ty = ty
+                    .as_ref()
+                    .map(type_to_string)
+                    .or_else(|| param_types.get(i).cloned());
+                c.declare(name, ty);
+            }
+            match (return_type, expected_return) {
+                (Some(annotated), _) => {
+                    c.check_expr(body, &type_to_string(annotated), context);
+                }
+                (None, Some(expected)) if expected != "void" => {
+                    c.check_expr(body, &expected, &format!("result of {}", context));
+                }
+                _ => {
+                    c.synth(body);
+                }
+            }
+        });
+    }
+
+    /// Check the arguments of a call and work out what it returns
+    fn check_call(&mut self, callee: &str, sig: &FnSig, args: &[Expr]) -> Option<String> {
+        // Infer the type parameters from the arguments first
+        let mut bindings = HashMap::new();
+        if !sig.type_params.is_empty() {
+            for (i, arg) in args.iter().enumerate() {
+                let Some((_, pattern)) = sig.param_type(i) else {
+                    break;
+                };
+                if let Some(actual) = self.synth(arg) {
+                    bind_type_params(&pattern, &actual, &sig.type_params, &mut bindings);
+                }
+            }
+        }
+        let instantiate = |ty: &str| {
+            let mut bindings = bindings.clone();
+            for param in &sig.type_params {
+                bindings
+                    .entry(param.clone())
+                    .or_insert_with(|| "unknown".to_string());
+            }
+            substitute_type_params(ty, &bindings)
+        };
+
+        for (i, arg) in args.iter().enumerate() {
+            // Arguments after a spread no longer line up with the parameters
+            if args[..i]
+                .iter()
+                .any(|a| matches!(a.kind, ExprKind::Spread(_)))
+            {
+                self.synth(arg);
+                continue;
+            }
+            match (&arg.kind, sig.param_type(i)) {
+                (ExprKind::Spread(inner), Some((param, ty))) => self.check_expr(
+                    inner,
+                    &format!("Array<{}>", instantiate(&ty)),
+                    &format!("arguments spread into '{}' of '{}'", param, callee),
+                ),
+                (_, Some((param, ty))) => self.check_expr(
+                    arg,
+                    &instantiate(&ty),
+                    &format!("argument '{}' of '{}'", param, callee),
+                ),
+                (_, None) => {
+                    self.synth(arg);
+                }
+            }
+        }
+
+        sig.return_type
+            .as_deref()
+            .map(instantiate)
+            .filter(|t| t != "unknown")
+    }
+
+    fn check_method_call(&mut self, object: &Expr, method: &str, args: &[Expr]) -> Option<String> {
+        // Calls on builtin modules such as `math.sqrt(x)`
+        if let ExprKind::Ident(module) = &object.kind {
+            if self.lookup(module).is_none() {
+                if let Some(builtin) = self.stdlib_types.builtins.get(module) {
+                    for arg in args {
+                        self.synth(arg);
+                    }
+                    return builtin
+                        .methods
+                        .get(method)
+                        .map(|m| m.return_type.clone())
+                        .and_then(known_stdlib_type);
+                }
+                // Associated functions such as `Point.new(1, 2)`
+                if let Some(sig) = self
+                    .declarations
+                    .methods
+                    .get(module)
+                    .and_then(|methods| methods.get(method))
+                    .filter(|sig| sig.params.first().is_none_or(|(name, _)| name != "self"))
+                {
+                    let mut sig = sig.clone();
+                    if let Some(decl) = self.declarations.structs.get(module) {
+                        sig.type_params.extend(decl.type_params.iter().cloned());
+                    }
+                    let callee = format!("{}.{}", module, method);
+                    return self.check_call(&callee, &sig, args);
+                }
+                // The default body of a trait method, like `Greeter.greet(self)`,
+                // where `Self` is whatever type is passed for it
+                if let Some(sig) = self
+                    .declarations
+                    .trait_defaults
+                    .get(module)
+                    .and_then(|defaults| defaults.get(method))
+                {
+                    let mut sig = sig.clone();
+                    sig.type_params.push("Self".to_string());
+                    let callee = format!("{}.{}", module, method);
+                    return self.check_call(&callee, &sig, args);
+                }
+            }
+        }
+
+        let Some(object_type) = self.synth(object) else {
+            for arg in args {
+                self.synth(arg);
+            }
+            return None;
+        };
+        let (base, type_args) = split_generic_type(&object_type);
+
+        // Methods from impl blocks, seen through the struct's type arguments
+        if let Some(sig) = self.declarations.method(base, method) {
+            let mut sig = sig.clone();
+            if sig.params.first().is_some_and(|(name, _)| name == "self") {
+                sig.params.remove(0);
+            }
+            // `Self`, as in trait default bodies, is the type called on
+            let mut bindings = HashMap::from([("Self".to_string(), object_type.clone())]);
+            if let Some(decl) = self.declarations.structs.get(base) {
+                bindings.extend(
+                    decl.type_params
+                        .iter()
+                        .cloned()
+                        .zip(type_args.iter().map(|t| t.to_string())),
+                );
+                sig.type_params.extend(decl.type_params.iter().cloned());
+            }
+            for (_, ty) in sig.params.iter_mut() {
+                *ty = substitute_type_params(ty, &bindings);
+            }
+            sig.return_type = sig
+                .return_type
+                .map(|t| substitute_type_params(&t, &bindings));
+            let callee = format!("{}.{}", base, method);
+            return self.check_call(&callee, &sig, args);
+        }
+
+        for arg in args {
+            self.synth(arg);
+        }
+        // Every value can be copied, unless its type defines its own `clone`
+        if method == "clone" && args.is_empty() {
+            return Some(object_type);
+        }
+        let stdlib_method = match base {
+            "str" => self.stdlib_types.string_methods.get(method),
+            "Array" => self.stdlib_types.array_methods.get(method),
+            _ => self
+                .stdlib_types
+                .types
+                .get(base)
+                .and_then(|ty| ty.methods.get(method)),
+        };
+        stdlib_method
+            .map(|m| m.return_type.clone())
+            .and_then(known_stdlib_type)
+    }
+
+    /// Work out the type of an expression bottom-up, checking anything nested
+    /// inside it along the way. `None` means the type is unknown.
+    fn synth(&mut self, expr: &Expr) -> Option<String> {
+        match &expr.kind {
+            ExprKind::Number(_) => Some("num".to_string()),
+            ExprKind::String(_) => Some("str".to_string()),
+            ExprKind::Boolean(_) => Some("bool".to_string()),
+            ExprKind::TemplateLiteral { parts } => {
+                for part in parts {
+                    if let TemplatePart::Expression(expr, format) = part {
+                        let ty = self.synth(expr);
+                        // Every specifier but padding is for numbers
+                        if let (Some(format), Some(ty)) = (format, ty) {
+                            let numeric = !matches!(format, FormatSpec::Align { .. });
+                            if numeric && !is_assignable("num", &ty) {
+                                self.error(
+                                    expr,
+                                    format!("Format ':{}' needs a number, found '{}'", format, ty),
+                                );
+                            }
+                        }
+                    }
+                }
+                Some("str".to_string())
+            }
+            ExprKind::Ident(name) | ExprKind::Local { name, .. } => match self.lookup(name) {
+                Some(ty) => ty.clone(),
+                None => self.declarations.functions.get(name).map(|sig| {
+                    format!(
+                        "fn({}) -> {}",
+                        sig.params
+                            .iter()
+                            .map(|(_, t)| t.as_str())
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                        sig.return_type.as_deref().unwrap_or("unknown")
+                    )
+                }),
+            },
+            ExprKind::BinOp { op, left, right } => {
+                let left = self.synth(left);
+                let right = self.synth(right);
+                match op.as_str() {
+                    "==" | "!=" | "<" | ">" | "<=" | ">=" | "&&" | "||" => Some("bool".to_string()),
+                    "+" | "-" | "*" | "/" | "%" => match (left.as_deref(), right.as_deref()) {
+                        (Some("num"), Some("num")) => Some("num".to_string()),
+                        (Some("str"), _) | (_, Some("str")) if op == "+" => Some("str".to_string()),
+                        _ => None,
+                    },
+                    _ => None,
+                }
+            }
+            ExprKind::UnaryOp { op, expr } => {
+                let inner = self.synth(expr);
+                match op.as_str() {
+                    "!" => Some("bool".to_string()),
+                    "-" => inner.filter(|t| t == "num"),
+                    _ => None,
+                }
+            }
+            ExprKind::ArrayLiteral(items) => {
+                let types: Vec<Option<String>> = items
+                    .iter()
+                    .map(|i| match &i.kind {
+                        // A spread adds elements of the spread array's type
+                        ExprKind::Spread(inner) => {
+                            let spread = self.synth(inner)?;
+                            match split_generic_type(&spread) {
+                                ("Array", item) if item.len() == 1 => Some(item[0].to_string()),
+                                _ => None,
+                            }
+                        }
+                        _ => self.synth(i),
+                    })
+                    .collect();
+                match types.first() {
+                    Some(Some(first)) if types.iter().all(|t| t.as_ref() == Some(first)) => {
+                        Some(format!("Array<{}>", first))
+                    }
+                    _ => Some("Array".to_string()),
+                }
+            }
+            ExprKind::MapLiteral(entries) => {
+                for (key, value) in entries {
+                    self.synth(key);
+                    self.synth(value);
+                }
+                Some("Map".to_string())
+            }
+            ExprKind::StructLiteral { name, fields } => self.synth_struct(name, fields),
+            ExprKind::FieldAccess { object, field } => {
+                let object_type = self.synth(object)?;
+                let (base, _) = split_generic_type(&object_type);
+                if let Some(decl) = self.declarations.structs.get(base) {
+                    return instantiate_fields(&decl.type_params, &decl.fields, &object_type)
+                        .into_iter()
+                        .find(|(n, _)| n == field)
+                        .map(|(_, t)| t)
+                        .filter(|t| t != "unknown");
+                }
+                self.stdlib_types
+                    .types
+                    .get(base)
+                    .and_then(|ty| ty.fields.get(field))
+                    .map(|f| f.field_type.clone())
+                    .and_then(known_stdlib_type)
+            }
+            ExprKind::Index { array, index } => {
+                let container = self.synth(array);
+                self.synth(index);
+                let container = container?;
+                let (base, args) = split_generic_type(&container);
+                match (base, args.as_slice()) {
+                    ("Array", [item]) => Some(item.to_string()),
+                    ("Map", [_, value]) => Some(value.to_string()),
+                    ("str", []) => Some("str".to_string()),
+                    _ => None,
+                }
+            }
+            ExprKind::Slice { array, start, end } => {
+                let container = self.synth(array);
+                for bound in [start, end].into_iter().flatten() {
+                    self.check_expr(bound, "num", "slice bound");
+                }
+                let container = container?;
+                let (base, args) = split_generic_type(&container);
+                match (base, args.as_slice()) {
+                    ("Array", [_]) | ("str" | "Bytes", []) => Some(container.clone()),
+                    _ => None,
+                }
+            }
+            ExprKind::Call { func, args } => match &func.kind {
+                ExprKind::Ident(name) if self.lookup(name).is_none() => {
+                    match self.declarations.functions.get(name).cloned() {
+                        Some(sig) => self.check_call(name, &sig, args),
+                        None => {
+                            for arg in args {
+                                self.synth(arg);
+                            }
+                            None
+                        }
+                    }
+                }
+                ExprKind::FieldAccess { object, field } => {
+                    self.check_method_call(object, field, args)
+                }
+                _ => {
+                    let callee = self.synth(func);
+                    for arg in args {
+                        self.synth(arg);
+                    }
+                    callee.and_then(|t| split_fn_type(&t).1)
+                }
+            },
+            ExprKind::Lambda {
+                params,
+                return_type,
+                body,
+            } => self.in_scope(|c| {
+                for (name, ty) in params {
+                    c.declare(name, ty.as_ref().map(type_to_string));
+                }
+                let result = match return_type {
+                    Some(annotated) => {
+                        let annotated = type_to_string(annotated);
+                        c.check_expr(body, &annotated, "lambda result");
+                        annotated
+                    }
+                    None => c.synth(body).unwrap_or_else(|| "unknown".to_string()),
+                };
+                let param_types: Vec<String> = params
+                    .iter()
+                    .map(|(_, ty)| {
+                        ty.as_ref()
+                            .map(type_to_string)
+                            .unwrap_or_else(|| "unknown".to_string())
+                    })
+                    .collect();
+                Some(format!("fn({}) -> {}", param_types.join(", "), result))
+            }),
+            ExprKind::Block(stmts) => self.in_scope(|c| c.synth_block(stmts)),
+            ExprKind::Await(inner) => {
+                let inner = self.synth(inner)?;
+                match split_generic_type(&inner) {
+                    ("Promise", args) if args.len() == 1 => known_stdlib_type(args[0].to_string()),
+                    _ => Some(inner),
+                }
+            }
+            ExprKind::Async(inner) | ExprKind::Lazy(inner) => {
+                let inner = self.synth(inner).unwrap_or_else(|| "unknown".to_string());
+                Some(format!("Promise<{}>", inner))
+            }
+            ExprKind::Match { expr, arms } => {
+                self.synth(expr);
+                let types: Vec<Option<String>> = arms
+                    .iter()
+                    .map(|(_, arm)| self.in_scope(|c| c.synth(arm)))
+                    .collect();
+                match types.first() {
+                    Some(Some(first)) if types.iter().all(|t| t.as_ref() == Some(first)) => {
+                        Some(first.clone())
+                    }
+                    _ => None,
+                }
+            }
+            ExprKind::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.synth(condition);
+                let then_type = self.synth(then_branch);
+                // Without an else the value may be unit
+                let else_type = self.synth(else_branch.as_ref()?);
+                then_type.filter(|t| else_type.as_ref() == Some(t))
+            }
+            ExprKind::Try(inner) => {
+                let inner = self.synth(inner)?;
+                match split_generic_type(&inner) {
+                    ("Result" | "Option", args) if !args.is_empty() => {
+                        known_stdlib_type(args[0].to_string())
+                    }
+                    _ => None,
+                }
+            }
+            ExprKind::Spread(inner) => {
+                self.synth(inner);
+                None
+            }
+        }
+    }
+
+    /// Check a block, giving the type of its value: its last statement if
+    /// that is an expression
+    fn synth_block(&mut self, stmts: &[Stmt]) -> Option<String> {
+        let (last, rest) = stmts.split_last()?;
+        self.check_stmts(rest);
+        match &last.kind {
+            StmtKind::Expr(expr) => self.synth(expr),
+            _ => {
+                self.check_stmt(last);
+                None
+            }
+        }
+    }
+
+    fn synth_struct(&mut self, name: &str, fields: &[(String, Expr)]) -> Option<String> {
+        let Some(decl) = self.declarations.structs.get(name).cloned() else {
+            for (_, value) in fields {
+                self.synth(value);
+            }
+            return Some(name.to_string());
+        };
+
+        let mut bindings = HashMap::new();
+        if !decl.type_params.is_empty() {
+            for (field, value) in fields {
+                if let Some((_, pattern)) = decl.fields.iter().find(|(n, _)| n == field) {
+                    if let Some(actual) = self.synth(value) {
+                        bind_type_params(pattern, &actual, &decl.type_params, &mut bindings);
+                    }
+                }
+            }
+        }
+        for param in &decl.type_params {
+            bindings
+                .entry(param.clone())
+                .or_insert_with(|| "unknown".to_string());
+        }
+
+        for (field, value) in fields {
+            match decl.fields.iter().find(|(n, _)| n == field) {
+                Some((_, ty)) => self.check_expr(
+                    value,
+                    &substitute_type_params(ty, &bindings),
+                    &format!("field '{}' of '{}'", field, name),
+                ),
+                None => {
+                    self.synth(value);
+                }
+            }
+        }
+
+        if decl.type_params.is_empty() {
+            Some(name.to_string())
+        } else {
+            let args: Vec<&str> = decl
+                .type_params
+                .iter()
+                .map(|p| bindings[p].as_str())
+                .collect();
+            Some(format!("{}<{}>", name, args.join(", ")))
+        }
+    }
+}
+
+/// Stdlib signatures use `any` and single-letter placeholders for values the
+/// checker cannot know
+fn known_stdlib_type(ty: String) -> Option<String> {
+    let placeholder = ty.len() == 1 && ty.chars().all(|c| c.is_ascii_uppercase());
+    (!placeholder && ty != "any").then_some(ty)
+}
+
+/// Split `fn(A, B) -> R` into its parameter types and return type
+fn split_fn_type(ty: &str) -> (Vec<String>, Option<String>) {
+    let Some(rest) = ty.strip_prefix("fn(") else {
+        return (Vec::new(), None);
+    };
+
+    let mut depth = 0;
+    let mut close = None;
+    for (i, c) in rest.char_indices() {
+        match c {
+            '(' | '<' => depth += 1,
+            ')' if depth == 0 => {
+                close = Some(i);
+                break;
+            }
+            ')' | '>' => depth -= 1,
+            _ => {}
+        }
+    }
+    let Some(close) = close else {
+        return (Vec::new(), None);
+    };
+
+    // Reuse the generic splitter on the parameter list
+    let wrapped = format!("fn<{}>", &rest[..close]);
+    let (_, params) = split_generic_type(&wrapped);
+    let params = params
+        .into_iter()
+        .filter(|p| !p.is_empty())
+        .map(str::to_string)
+        .collect();
+    let result = rest[close + 1..]
+        .trim()
+        .strip_prefix("->")
+        .map(|r| r.trim().to_string())
+        .filter(|r| r != "unknown");
+    (params, result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{InputStream, Parser};
+
+    fn check(source: &str) -> Vec<String> {
+        let source = source.to_string();
+        let stmts = Parser::new(InputStream::new("test.lf", &source))
+            .parse()
+            .unwrap();
+        check_types(
+            &stmts,
+            &source,
+            &Declarations::default(),
+            &super::super::stdlib::load(),
+        )
+        .into_iter()
+        .map(|d| d.message)
+        .collect()
+    }
+
+    #[test]
+    fn test_reports_mismatched_annotations() {
+        let errors = check(
+            "fn add(a: num, b: num) -> num { return a + b; }\n\
+             let total: num = add(1, \"2\");\n\
+             let label: str = add(1, 2);\n\
+             let mut count = 0;\n\
+             count = \"many\";\n\
+             fn name() -> str { return; }\n\
+             fn log() { return 1; }\n\
+             let values: Array<num> = [1, \"two\"];\n\
+             let shout: fn(str) -> str = (s) => s.length();\n",
+        );
+
+        assert_eq!(
+            errors,
+            vec![
+                "Type mismatch in argument 'b' of 'add': expected 'num', found 'str'",
+                "Type mismatch in variable 'label': expected 'str', found 'num'",
+                "Type mismatch in assignment to 'count': expected 'num', found 'str'",
+                "Missing return value: function 'name' returns 'str'",
+                "Type mismatch in element of variable 'values': expected 'num', found 'str'",
+                "Type mismatch in result of variable 'shout': expected 'str', found 'num'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_gradual_and_generic_code_passes() {
+        let errors = check(
+            "def Box<T> { value: T }\n\
+             fn first<T>(items: Array<T>) -> T { return items[0]; }\n\
+             fn untyped(x: any) { return x; }\n\
+             let n: num = first([1, 2]);\n\
+             let b: Box<str> = Box { value: \"x\" };\n\
+             let s: str = b.value;\n\
+             let anything: num = untyped(\"x\");\n\
+             let parsed: num = json.parse(\"1\");\n\
+             let double: fn(num) -> num = (x) => x * 2;\n",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        let errors = check(
+            "def Box<T> { value: T }\n\
+             let b: Box<num> = Box { value: \"x\" };\n\
+             fn first<T>(items: Array<T>) -> T { return items[0]; }\n\
+             let s: str = first([1, 2]);\n",
+        );
+        assert_eq!(
+            errors,
+            vec![
+                "Type mismatch in variable 'b': expected 'Box<num>', found 'Box<str>'",
+                "Type mismatch in variable 's': expected 'str', found 'num'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rest_parameters_and_spread() {
+        let errors = check(
+            "fn sum(label: str, ...nums: Array<num>) -> num { return 0; }\n\
+             let more: Array<num> = [3, 4];\n\
+             let total: num = sum(\"total\", 1, 2, ...more);\n\
+             let all: Array<num> = [0, ...more];\n\
+             sum(\"bad\", 1, \"two\");\n\
+             let words: Array<str> = [\"a\", ...more];\n",
+        );
+        assert_eq!(
+            errors,
+            vec![
+                "Type mismatch in argument 'nums' of 'sum': expected 'num', found 'str'",
+                "Type mismatch in spread into variable 'words': expected 'Array<str>', found 'Array<num>'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_if_expression_types() {
+        let errors = check(
+            "let x = 5;\n\
+             let size = if (x > 3) { \"big\" } else { \"small\" };\n\
+             let label: num = size;\n\
+             let mixed: str = if (x > 3) { \"big\" } else { 0 };\n\
+             let maybe = if (x > 3) { 1 };\n\
+             let fine: str = maybe;\n",
+        );
+        assert_eq!(
+            errors,
+            vec![
+                "Type mismatch in variable 'label': expected 'num', found 'str'",
+                "Type mismatch in variable 'mixed': expected 'str', found 'num'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_associated_functions() {
+        let errors = check(
+            "def Point { x: num, y: num }\n\
+             impl Point {\n\
+                 fn new(x: num, y: num) -> Point { return Point { x: x, y: y }; }\n\
+             }\n\
+             let p: Point = Point.new(1, 2);\n\
+             let q: Point = Point::new(1, \"2\");\n\
+             let r: num = Point.new(1, 2);\n",
+        );
+        assert_eq!(
+            errors,
+            vec![
+                "Type mismatch in argument 'y' of 'Point.new': expected 'num', found 'str'",
+                "Type mismatch in variable 'r': expected 'num', found 'Point'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_trait_default_methods() {
+        let errors = check(
+            "trait Named {\n\
+                 fn name(self) -> str;\n\
+                 fn greet(self) -> str { return \"Hello, \" + self.name(); }\n\
+             }\n\
+             def Dog { label: str }\n\
+             impl Named for Dog {\n\
+                 fn name(self) -> str { return self.label; }\n\
+             }\n\
+             let dog = Dog { label: \"Rex\" };\n\
+             let greeting: str = dog.greet();\n\
+             let direct: str = Named.greet(dog);\n\
+             let count: num = dog.greet();\n",
+        );
+        assert_eq!(
+            errors,
+            vec!["Type mismatch in variable 'count': expected 'num', found 'str'"]
+        );
+    }
+
+    #[test]
+    fn test_template_format_specifiers() {
+        let errors = check(
+            "let n = 7;\n\
+             let name = \"loft\";\n\
+             let ok = `${n:d2} ${n:hex} ${name:>8}`;\n\
+             let bad = `${name:f2}`;\n",
+        );
+        assert_eq!(errors, vec!["Format ':f2' needs a number, found 'str'"]);
+    }
+
+    #[test]
+    fn test_clone_keeps_type() {
+        let errors = check(
+            "def Point { x: num }\n\
+             let p = Point { x: 1 };\n\
+             let copy: Point = p.clone();\n\
+             let wrong: str = [1, 2].clone();\n",
+        );
+        assert_eq!(
+            errors,
+            vec!["Type mismatch in variable 'wrong': expected 'str', found 'Array<num>'"]
+        );
+    }
+}
@@ -0,0 +1,451 @@
+//! Single-file bundles produced by `loft bundle`.
+//!
+//! A bundle is a plain `.lf` file that starts with [`BUNDLE_HEADER`] and holds
+//! every module reachable from an entrypoint through `learn` imports. Each
+//! module section starts with a `//@module <path>` line, followed by one
+//! `//@learn <import> => <path>` line per resolved import and then the module
+//! source. The first module is the entrypoint.
+//!
+//! ```text
+//! // loft bundle v1
+//! //@module src/main.lf
+//! //@learn ./utils => src/utils.lf
+//! learn "./utils";
+//! ...
+//! //@module src/utils.lf
+//! teach fn double(x: num) -> num { return x * 2; }
+//! ```
+//!
+//! `loft compile` appends a bundle to a copy of the loft executable, followed
+//! by its length and [`EMBED_MAGIC`], so the copy runs the program on its own.
+//! The features it was compiled with are listed on a `//@features io, ffi`
+//! line before the first module.
+//!
+//! A source line that itself starts with `//@`, say inside a multi-line
+//! string, is written after an extra `//@ ` so it isn't read as a marker.
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::analysis::symbols::collect_imports;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::{Component, Path, PathBuf};
+
+/// First line of every bundle file
+pub const BUNDLE_HEADER: &str = "// loft bundle v1";
+
+const FEATURES_MARKER: &str = "//@features ";
+const MODULE_MARKER: &str = "//@module ";
+const LEARN_MARKER: &str = "//@learn ";
+/// Put before source lines that would otherwise look like a marker
+const ESCAPE_MARKER: &str = "//@ ";
+
+/// Last bytes of an executable with an embedded bundle
+pub const EMBED_MAGIC: &[u8; 8] = b"loftexe1";
+
+/// The embedded bundle's length and [`EMBED_MAGIC`]
+const TRAILER_LEN: usize = 16;
+
+/// A single module inlined into a bundle
+#[derive(Debug, Clone, PartialEq)]
+pub struct BundledModule {
+    /// Project-relative path of the module
+    pub path: String,
+    /// Import paths used by this module and the bundled module they resolve to
+    pub imports: Vec<(String, String)>,
+    pub source: String,
+}
+
+/// All modules of a program, entrypoint first
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bundle {
+    pub modules: Vec<BundledModule>,
+    /// Language features the program runs with, such as `io`
+    pub features: Vec<String>,
+}
+
+impl Bundle {
+    /// Check whether source code is a bundle
+    pub fn is_bundle(source: &str) -> bool {
+        source.lines().next().map(str::trim_end) == Some(BUNDLE_HEADER)
+    }
+
+    /// The module that is executed when the bundle runs
+    pub fn entry(&self) -> &BundledModule {
+        &self.modules[0]
+    }
+
+    /// Look up a bundled module by its path
+    pub fn module(&self, path: &str) -> Option<&BundledModule> {
+        self.modules.iter().find(|m| m.path == path)
+    }
+
+    /// Resolve an import made from `importer` to the bundled module it refers to
+    pub fn resolve(&self, importer: &str, import_path: &str) -> Option<&BundledModule> {
+        let importer = self.module(importer)?;
+        let (_, target) = importer
+            .imports
+            .iter()
+            .find(|(import, _)| import == import_path)?;
+        self.module(target)
+    }
+
+    /// Parse a bundle file
+    pub fn parse(source: &str) -> Result<Self, BundleError> {
+        let mut lines = source.lines();
+        if lines.next().map(str::trim_end) != Some(BUNDLE_HEADER) {
+            return Err(BundleError::Malformed(format!(
+                "missing '{}' header",
+                BUNDLE_HEADER
+            )));
+        }
+
+        let mut modules: Vec<BundledModule> = Vec::new();
+        let mut features = Vec::new();
+        for line in lines {
+            if let Some(line) = line.strip_prefix(ESCAPE_MARKER) {
+                let Some(module) = modules.last_mut() else {
+                    return Err(BundleError::Malformed(
+                        "code found before the first module".to_string(),
+                    ));
+                };
+                module.source.push_str(line);
+                module.source.push('\n');
+                continue;
+            }
+
+            if let Some(path) = line.strip_prefix(MODULE_MARKER) {
+                modules.push(BundledModule {
+                    path: path.trim().to_string(),
+                    imports: Vec::new(),
+                    source: String::new(),
+                });
+                continue;
+            }
+
+            let Some(module) = modules.last_mut() else {
+                if let Some(list) = line.strip_prefix(FEATURES_MARKER) {
+                    features.extend(list.split(',').map(|f| f.trim().to_string()));
+                    continue;
+                }
+                if line.trim().is_empty() {
+                    continue;
+                }
+                return Err(BundleError::Malformed(
+                    "code found before the first module".to_string(),
+                ));
+            };
+
+            match line.strip_prefix(LEARN_MARKER) {
+                Some(import) if module.source.is_empty() => {
+                    let (import, target) = import.split_once(" => ").ok_or_else(|| {
+                        BundleError::Malformed(format!("invalid import line '{}'", line))
+                    })?;
+                    module
+                        .imports
+                        .push((import.trim().to_string(), target.trim().to_string()));
+                }
+                _ => {
+                    module.source.push_str(line);
+                    module.source.push('\n');
+                }
+            }
+        }
+
+        if modules.is_empty() {
+            return Err(BundleError::Malformed(
+                "bundle contains no modules".to_string(),
+            ));
+        }
+
+        Ok(Self { modules, features })
+    }
+
+    /// Render the bundle as a single `.lf` file
+    pub fn to_source(&self) -> String {
+        let mut out = String::new();
+        out.push_str(BUNDLE_HEADER);
+        out.push('\n');
+        if !self.features.is_empty() {
+            out.push_str(FEATURES_MARKER);
+            out.push_str(&self.features.join(", "));
+            out.push('\n');
+        }
+
+        for module in &self.modules {
+            out.push_str(MODULE_MARKER);
+            out.push_str(&module.path);
+            out.push('\n');
+            for (import, target) in &module.imports {
+                out.push_str(&format!("{}{} => {}\n", LEARN_MARKER, import, target));
+            }
+            for line in module.source.lines() {
+                if line.starts_with("//@") {
+                    out.push_str(ESCAPE_MARKER);
+                }
+                out.push_str(line);
+                out.push('\n');
+            }
+        }
+
+        out
+    }
+
+    /// Walk the import graph starting at `entrypoint` and bundle every reachable module
+    ///
+    /// Module paths are recorded relative to `root`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn build(root: &Path, entrypoint: &Path) -> Result<Self, BundleError> {
+        let mut modules = Vec::new();
+        let mut pending = vec![entrypoint.to_path_buf()];
+
+        while let Some(file) = pending.pop() {
+            let key = module_key(root, &file);
+            if modules.iter().any(|m: &BundledModule| m.path == key) {
+                continue;
+            }
+
+            let source = std::fs::read_to_string(&file)
+                .map_err(|e| BundleError::IoError(format!("{}: {}", file.display(), e)))?;
+            let display_path = file.display().to_string();
+            let stmts =
+                crate::parser::Parser::new(crate::parser::InputStream::new(&display_path, &source))
+                    .parse()
+                    .map_err(|e| {
+                        BundleError::ParseError(format!("{}: {}", display_path, e.message))
+                    })?;
+
+            let mut imports = Vec::new();
+            let mut targets = Vec::new();
+            for path in collect_imports(&stmts) {
+                let import = path.join("::");
+                if imports.iter().any(|(i, _)| *i == import)
+                    || crate::runtime::is_builtin_module(&path)
+                {
+                    continue;
+                }
+
+                let resolved = crate::runtime::resolve_module_path(Some(&display_path), &path)
+                    .map_err(|_| BundleError::UnresolvedImport {
+                        from: key.clone(),
+                        path: import.clone(),
+                    })?;
+                imports.push((import, module_key(root, &resolved)));
+                targets.push(resolved);
+            }
+
+            // Keep modules in the order they are first imported
+            pending.extend(targets.into_iter().rev());
+            modules.push(BundledModule {
+                path: key,
+                imports,
+                source,
+            });
+        }
+
+        Ok(Self {
+            modules,
+            features: Vec::new(),
+        })
+    }
+
+    /// A copy of the executable `exe` that runs this bundle when started
+    pub fn embed(&self, exe: &[u8]) -> Vec<u8> {
+        let source = self.to_source();
+        let mut out = Vec::with_capacity(exe.len() + source.len() + TRAILER_LEN);
+        out.extend_from_slice(exe);
+        out.extend_from_slice(source.as_bytes());
+        out.extend_from_slice(&(source.len() as u64).to_le_bytes());
+        out.extend_from_slice(EMBED_MAGIC);
+        out
+    }
+
+    /// Read the bundle embedded in an executable by [`Bundle::embed`], without
+    /// loading the rest of the file
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn read_embedded(exe: &Path) -> Result<Option<Self>, BundleError> {
+        use std::io::{Read, Seek, SeekFrom};
+
+        let io_error =
+            |e: std::io::Error| BundleError::IoError(format!("{}: {}", exe.display(), e));
+        let mut file = std::fs::File::open(exe).map_err(io_error)?;
+        let file_len = file.metadata().map_err(io_error)?.len();
+        if file_len < TRAILER_LEN as u64 {
+            return Ok(None);
+        }
+
+        let mut trailer = [0u8; TRAILER_LEN];
+        file.seek(SeekFrom::End(-(TRAILER_LEN as i64)))
+            .and_then(|_| file.read_exact(&mut trailer))
+            .map_err(io_error)?;
+        let (len, magic) = trailer.split_at(8);
+        if magic != EMBED_MAGIC {
+            return Ok(None);
+        }
+        let len = u64::from_le_bytes(len.try_into().expect("trailer holds a u64"));
+        if len > file_len - TRAILER_LEN as u64 {
+            return Err(BundleError::Malformed(
+                "embedded bundle is longer than the executable".to_string(),
+            ));
+        }
+
+        let mut source = vec![0u8; len as usize];
+        file.seek(SeekFrom::End(-((len + TRAILER_LEN as u64) as i64)))
+            .and_then(|_| file.read_exact(&mut source))
+            .map_err(io_error)?;
+        let source = String::from_utf8(source)
+            .map_err(|_| BundleError::Malformed("embedded bundle is not UTF-8".to_string()))?;
+        Self::parse(&source).map(Some)
+    }
+}
+
+/// Normalized, `/`-separated path of a module relative to the project root
+#[cfg(not(target_arch = "wasm32"))]
+fn module_key(root: &Path, file: &Path) -> String {
+    let absolute = |p: &Path| -> PathBuf { p.canonicalize().unwrap_or_else(|_| p.to_path_buf()) };
+    let file = absolute(file);
+    let relative = file
+        .strip_prefix(absolute(root))
+        .map(Path::to_path_buf)
+        .unwrap_or(file);
+
+    relative
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+            Component::RootDir => Some(String::new()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[derive(Debug)]
+pub enum BundleError {
+    IoError(String),
+    ParseError(String),
+    UnresolvedImport { from: String, path: String },
+    Malformed(String),
+}
+
+impl std::fmt::Display for BundleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BundleError::IoError(e) => write!(f, "IO error: {}", e),
+            BundleError::ParseError(e) => write!(f, "Parse error: {}", e),
+            BundleError::UnresolvedImport { from, path } => {
+                write!(f, "Unresolved import '{}' in {}", path, from)
+            }
+            BundleError::Malformed(e) => write!(f, "Malformed bundle: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for BundleError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundle_roundtrip() {
+        let bundle = Bundle {
+            modules: vec![
+                BundledModule {
+                    path: "src/main.lf".to_string(),
+                    imports: vec![("./utils".to_string(), "src/utils.lf".to_string())],
+                    source: "learn \"./utils\";\nterm.println(utils.double(2));\n".to_string(),
+                },
+                BundledModule {
+                    path: "src/utils.lf".to_string(),
+                    imports: vec![],
+                    source: "teach fn double(x: num) -> num { return x * 2; }\n".to_string(),
+                },
+            ],
+            features: vec!["io".to_string(), "ffi".to_string()],
+        };
+
+        let source = bundle.to_source();
+        assert!(Bundle::is_bundle(&source));
+        assert_eq!(Bundle::parse(&source).unwrap(), bundle);
+        assert_eq!(
+            bundle.resolve("src/main.lf", "./utils").unwrap().path,
+            "src/utils.lf"
+        );
+    }
+
+    #[test]
+    fn test_bundle_roundtrip_escapes_markers() {
+        let bundle = Bundle {
+            modules: vec![
+                BundledModule {
+                    path: "main.lf".to_string(),
+                    imports: vec![],
+                    source: "//@learn ./x => x.lf\nlet s = `\n//@module fake.lf\n//@ kept\n`;\n"
+                        .to_string(),
+                },
+                BundledModule {
+                    path: "other.lf".to_string(),
+                    imports: vec![],
+                    source: "// just a comment\n".to_string(),
+                },
+            ],
+            features: vec![],
+        };
+
+        let parsed = Bundle::parse(&bundle.to_source()).unwrap();
+        assert_eq!(parsed, bundle);
+        assert_eq!(parsed.to_source(), bundle.to_source());
+    }
+
+    #[test]
+    fn test_build_follows_imports() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        std::fs::create_dir_all(src.join("lib")).unwrap();
+        std::fs::write(
+            src.join("main.lf"),
+            "learn \"./lib/math\";\nlearn \"./lib/math\";\nterm.println(math.square(3));\n",
+        )
+        .unwrap();
+        std::fs::write(
+            src.join("lib/math.lf"),
+            "learn \"./helpers\";\nteach fn square(x: num) -> num { return helpers.mul(x, x); }\n",
+        )
+        .unwrap();
+        std::fs::write(
+            src.join("lib/helpers.lf"),
+            "teach fn mul(a: num, b: num) -> num { return a * b; }\n",
+        )
+        .unwrap();
+
+        let bundle = Bundle::build(dir.path(), &src.join("main.lf")).unwrap();
+        let paths: Vec<&str> = bundle.modules.iter().map(|m| m.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec!["src/main.lf", "src/lib/math.lf", "src/lib/helpers.lf"]
+        );
+        assert_eq!(
+            bundle.resolve("src/lib/math.lf", "./helpers").unwrap().path,
+            "src/lib/helpers.lf"
+        );
+    }
+
+    #[test]
+    fn test_embedded_bundle() {
+        let bundle = Bundle {
+            modules: vec![BundledModule {
+                path: "main.lf".to_string(),
+                imports: vec![],
+                source: "term.println(\"hi\");\n".to_string(),
+            }],
+            features: vec!["io".to_string()],
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let plain = dir.path().join("loft");
+        let compiled = dir.path().join("hello");
+        std::fs::write(&plain, b"\x7fELF not really").unwrap();
+        std::fs::write(&compiled, bundle.embed(b"\x7fELF not really")).unwrap();
+
+        assert_eq!(Bundle::read_embedded(&plain).unwrap(), None);
+        assert_eq!(Bundle::read_embedded(&compiled).unwrap(), Some(bundle));
+    }
+}
@@ -0,0 +1,116 @@
+//! Stable codes for loft's diagnostics, such as `L0003` for an unused variable.
+//!
+//! Diagnostics are identified inside loft by a name like `unused_variable`,
+//! which is what code actions and the `lints` section of manifest.json use.
+//! Each name has a code that reports show and that `loft explain` takes,
+//! with a longer explanation kept in a markdown file next to this one.
+//! Codes are never reused or renumbered; new ones go at the end.
+
+use crate::runtime::RuntimeError;
+
+#[derive(Debug)]
+pub struct Code {
+    /// e.g. `L0003`
+    pub code: &'static str,
+    /// The name diagnostics carry, e.g. `unused_variable`
+    pub name: &'static str,
+    pub title: &'static str,
+    /// What causes the diagnostic and how to fix it, with examples, in markdown
+    pub explanation: &'static str,
+}
+
+macro_rules! codes {
+    ($($code:literal $name:literal $title:literal,)*) => {
+        /// Every code, in order
+        pub static CODES: &[Code] = &[$(
+            Code {
+                code: $code,
+                name: $name,
+                title: $title,
+                explanation: include_str!(concat!($code, ".md")),
+            },
+        )*];
+    };
+}
+
+codes! {
+    "L0001" "syntax_error" "Syntax error",
+    "L0002" "undefined_identifier" "Undefined identifier",
+    "L0003" "unused_variable" "Unused variable",
+    "L0004" "unreachable_code" "Unreachable code",
+    "L0005" "argument_count" "Wrong number of arguments",
+    "L0006" "unused_import" "Unused import",
+    "L0007" "circular_import" "Circular import",
+    "L0008" "missing_impl" "Missing trait methods",
+    "L0009" "signature_mismatch" "Method signature doesn't match the trait",
+    "L0010" "non_exhaustive_match" "Match doesn't cover every value",
+    "L0011" "type_error" "Type error",
+    "L0012" "shadowed_variable" "Shadowed variable",
+    "L0013" "unused_result" "Unused function result",
+    "L0014" "constant_condition" "Constant condition",
+    "L0015" "float_equality" "Equality on fractional numbers",
+    "L0016" "runtime_error" "Runtime error",
+    "L0017" "uncaught_error" "Uncaught error",
+    "L0018" "missing_semicolon" "Missing semicolon",
+}
+
+/// The code with the diagnostic name `name`
+pub fn by_name(name: &str) -> Option<&'static Code> {
+    CODES.iter().find(|code| code.name == name)
+}
+
+/// Look up a code like `L0005`, in any case, or a diagnostic name
+pub fn find(code_or_name: &str) -> Option<&'static Code> {
+    CODES
+        .iter()
+        .find(|code| code.code.eq_ignore_ascii_case(code_or_name))
+        .or_else(|| by_name(code_or_name))
+}
+
+/// The code of an error that stopped a program: the one it was given, an
+/// uncaught `throw`, or any other runtime error. Leaving with `exit()` has none.
+pub fn of_runtime_error(error: &RuntimeError) -> Option<&'static Code> {
+    if error.exit_code().is_some() {
+        return None;
+    }
+    if let Some(code) = error.rejected_code() {
+        return Some(code);
+    }
+    let name = if error.thrown().is_some() {
+        "uncaught_error"
+    } else {
+        "runtime_error"
+    };
+    by_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_are_numbered_in_order() {
+        for (index, code) in CODES.iter().enumerate() {
+            assert_eq!(code.code, format!("L{:04}", index + 1));
+            assert!(code.explanation.contains("```loft"), "{}", code.code);
+        }
+        assert_eq!(find("l0005").unwrap().name, "argument_count");
+        assert_eq!(find("unused_import").unwrap().code, "L0006");
+        assert!(find("L9999").is_none());
+
+        let thrown = RuntimeError {
+            raised: Some(Box::new(crate::runtime::Raised::Thrown(
+                crate::runtime::value::Value::Unit,
+            ))),
+            ..RuntimeError::new("boom")
+        };
+        assert_eq!(of_runtime_error(&thrown).unwrap().code, "L0017");
+        assert_eq!(
+            of_runtime_error(&RuntimeError::new("Division by zero"))
+                .unwrap()
+                .code,
+            "L0016"
+        );
+        assert!(of_runtime_error(&RuntimeError::exit(1)).is_none());
+    }
+}
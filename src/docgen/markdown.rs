@@ -0,0 +1,260 @@
+//! Markdown rendering for doc comments, shared by package and stdlib docs.
+//!
+//! Covers what doc comments use: paragraphs, headings, lists, fenced code
+//! blocks, inline code, emphasis, links and `[Symbol]` cross-references.
+//! Cross-references are resolved by the caller; one that doesn't resolve is
+//! shown as code, the way the language server shows it.
+
+/// Render `markdown` as HTML. `resolve` maps a cross-referenced symbol such as
+/// `Point` or `term::println` to the page it is documented on.
+pub fn to_html(markdown: &str, resolve: &dyn Fn(&str) -> Option<String>) -> String {
+    let mut html = String::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut list: Option<&str> = None;
+    let mut lines = markdown.lines();
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim();
+
+        if let Some(lang) = trimmed.strip_prefix("```") {
+            end_paragraph(&mut html, &mut paragraph, resolve);
+            end_list(&mut html, &mut list);
+            let code: Vec<&str> = lines
+                .by_ref()
+                .take_while(|line| !line.trim_start().starts_with("```"))
+                .collect();
+            let lang = lang.split(',').next().unwrap_or("").trim();
+            let code: Vec<&str> = if lang == "loft" {
+                code.into_iter()
+                    .filter(|line| hidden_line(line).is_none())
+                    .collect()
+            } else {
+                code
+            };
+            let class = if lang.is_empty() {
+                String::new()
+            } else {
+                format!(" class=\"language-{}\"", escape_html(lang))
+            };
+            html.push_str(&format!(
+                "<pre><code{}>{}</code></pre>\n",
+                class,
+                escape_html(&code.join("\n"))
+            ));
+        } else if trimmed.is_empty() {
+            end_paragraph(&mut html, &mut paragraph, resolve);
+            end_list(&mut html, &mut list);
+        } else if let Some((level, text)) = heading(trimmed) {
+            end_paragraph(&mut html, &mut paragraph, resolve);
+            end_list(&mut html, &mut list);
+            // Doc pages already use h1 to h3, so headings in docs start at h4
+            let tag = format!("h{}", (level + 3).min(6));
+            html.push_str(&format!("<{0}>{1}</{0}>\n", tag, inline(text, resolve)));
+        } else if let Some((tag, text)) = list_item(trimmed) {
+            end_paragraph(&mut html, &mut paragraph, resolve);
+            if list != Some(tag) {
+                end_list(&mut html, &mut list);
+                html.push_str(&format!("<{}>\n", tag));
+                list = Some(tag);
+            }
+            html.push_str(&format!("<li>{}</li>\n", inline(text, resolve)));
+        } else {
+            end_list(&mut html, &mut list);
+            paragraph.push(trimmed);
+        }
+    }
+
+    end_paragraph(&mut html, &mut paragraph, resolve);
+    end_list(&mut html, &mut list);
+    html
+}
+
+/// The code of a line hidden from rendered ```loft blocks: `# let x = setup();`
+/// runs in doc tests without showing up in the docs
+pub fn hidden_line(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    if line == "#" {
+        Some("")
+    } else {
+        line.strip_prefix("# ")
+    }
+}
+
+fn end_paragraph(
+    html: &mut String,
+    paragraph: &mut Vec<&str>,
+    resolve: &dyn Fn(&str) -> Option<String>,
+) {
+    if !paragraph.is_empty() {
+        html.push_str(&format!(
+            "<p>{}</p>\n",
+            inline(&paragraph.join(" "), resolve)
+        ));
+        paragraph.clear();
+    }
+}
+
+fn end_list(html: &mut String, list: &mut Option<&str>) {
+    if let Some(tag) = list.take() {
+        html.push_str(&format!("</{}>\n", tag));
+    }
+}
+
+/// `## Title` as its level and text
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let text = line[level..].strip_prefix(' ')?;
+    (1..=6).contains(&level).then_some((level, text.trim()))
+}
+
+/// `- item` or `1. item` as the list tag and the item text
+fn list_item(line: &str) -> Option<(&'static str, &str)> {
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(text) = line.strip_prefix(bullet) {
+            return Some(("ul", text));
+        }
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 {
+        if let Some(text) = line[digits..].strip_prefix(". ") {
+            return Some(("ol", text));
+        }
+    }
+    None
+}
+
+/// Render inline code, emphasis, links and cross-references
+fn inline(text: &str, resolve: &dyn Fn(&str) -> Option<String>) -> String {
+    let mut html = String::new();
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        // `code`
+        if c == '`' {
+            if let Some(end) = rest[1..].find('`') {
+                html.push_str(&format!("<code>{}</code>", escape_html(&rest[1..end + 1])));
+                rest = &rest[end + 2..];
+                continue;
+            }
+        }
+
+        // **strong** and *emphasis* or _emphasis_, but not the underscores of snake_case
+        let in_word = text[..text.len() - rest.len()].ends_with(|c: char| c.is_alphanumeric());
+        let emphasis = [("**", "strong"), ("__", "strong"), ("*", "em"), ("_", "em")]
+            .into_iter()
+            .filter(|(marker, _)| !(in_word && marker.starts_with('_')))
+            .find_map(|(marker, tag)| {
+                let after = rest.strip_prefix(marker)?;
+                if after.starts_with(char::is_whitespace) {
+                    return None;
+                }
+                let end = after.find(marker).filter(|&end| end > 0)?;
+                Some((tag, &after[..end], &after[end + marker.len()..]))
+            });
+        if let Some((tag, content, after)) = emphasis {
+            html.push_str(&format!("<{0}>{1}</{0}>", tag, inline(content, resolve)));
+            rest = after;
+            continue;
+        }
+
+        // [text](url), [Symbol] and [`Symbol`]
+        if c == '[' {
+            if let Some(end) = rest.find(']') {
+                let label = &rest[1..end];
+                let after = &rest[end + 1..];
+                if let Some(url_end) = after.strip_prefix('(').and_then(|a| a.find(')')) {
+                    let url = &after[1..url_end + 1];
+                    html.push_str(&link(url, &inline(label, resolve)));
+                    rest = &after[url_end + 2..];
+                    continue;
+                }
+
+                let symbol = label.trim_matches('`');
+                if is_symbol(symbol) {
+                    let code = format!("<code>{}</code>", escape_html(symbol));
+                    match resolve(symbol) {
+                        Some(url) => html.push_str(&link(&url, &code)),
+                        None => html.push_str(&code),
+                    }
+                    rest = after;
+                    continue;
+                }
+            }
+        }
+
+        html.push_str(&escape_html(&rest[..c.len_utf8()]));
+        rest = &rest[c.len_utf8()..];
+    }
+
+    html
+}
+
+/// A name such as `Point`, `term.println` or `math::sqrt`
+fn is_symbol(text: &str) -> bool {
+    !text.is_empty()
+        && text
+            .split("::")
+            .flat_map(|part| part.split('.'))
+            .all(|part| {
+                part.starts_with(|c: char| c.is_alphabetic() || c == '_')
+                    && part.chars().all(|c| c.is_alphanumeric() || c == '_')
+            })
+}
+
+fn link(url: &str, label: &str) -> String {
+    // Scripts can't be smuggled in through a doc comment
+    if url.trim_start().to_lowercase().starts_with("javascript:") {
+        return label.to_string();
+    }
+    format!("<a href=\"{}\">{}</a>", escape_html(url), label)
+}
+
+pub fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(symbol: &str) -> Option<String> {
+        (symbol == "Point").then(|| "#Point".to_string())
+    }
+
+    #[test]
+    fn test_blocks() {
+        let markdown = "Adds two numbers.\nReturns their sum.\n\n## Example\n\n```loft\nlet x = add(1, 2);\nif (x > 2) {\n    term.println(x);\n}\n```\n\n- first\n- second\n\n1. one\n2. two";
+        assert_eq!(
+            to_html(markdown, &resolve),
+            "<p>Adds two numbers. Returns their sum.</p>\n\
+             <h5>Example</h5>\n\
+             <pre><code class=\"language-loft\">let x = add(1, 2);\nif (x &gt; 2) {\n    term.println(x);\n}</code></pre>\n\
+             <ul>\n<li>first</li>\n<li>second</li>\n</ul>\n\
+             <ol>\n<li>one</li>\n<li>two</li>\n</ol>\n"
+        );
+    }
+
+    #[test]
+    fn test_inline() {
+        assert_eq!(
+            to_html(
+                "Takes a [Point] or [`Line`], see [the book](https://loft.fargone.sh) and `a < b`.",
+                &resolve
+            ),
+            "<p>Takes a <a href=\"#Point\"><code>Point</code></a> or <code>Line</code>, \
+             see <a href=\"https://loft.fargone.sh\">the book</a> and <code>a &lt; b</code>.</p>\n"
+        );
+        assert_eq!(
+            to_html("**bold**, *em*, snake_case_name, [not a link] and 2 * 3 * 4", &resolve),
+            "<p><strong>bold</strong>, <em>em</em>, snake_case_name, [not a link] and 2 * 3 * 4</p>\n"
+        );
+        assert_eq!(
+            to_html("[click](javascript:alert(1))", &resolve),
+            "<p>click)</p>\n"
+        );
+    }
+}
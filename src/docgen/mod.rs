@@ -1,18 +1,36 @@
+pub mod markdown;
+pub mod source;
 pub mod stdlib;
 pub mod terminal;
 
-use crate::parser::{InputStream, Parser, Stmt, Type};
+use crate::analysis::symbols::{opt_type_to_string, type_params_to_string, type_to_string};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::lockfile::Lockfile;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::manifest::Manifest;
+use crate::parser::{is_method, InputStream, Parser, Stmt, StmtKind};
 use regex;
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+/// Pages of the stdlib docs for the primitive types
+const STD_PAGES: [(&str, &str); 5] = [
+    ("str", "/d/std/string.html"),
+    ("num", "/d/std/num.html"),
+    ("bool", "/d/std/bool.html"),
+    ("void", "/d/std/void.html"),
+    ("Array", "/d/std/array.html"),
+];
+
 #[derive(Debug, Clone)]
 pub struct DocItem {
     pub name: String,
     pub kind: DocItemKind,
     pub documentation: Option<String>,
     pub signature: Option<String>,
+    /// File and 1-based line the item is declared on
+    pub source: Option<(PathBuf, usize)>,
 }
 
 #[derive(Debug, Clone)]
@@ -39,12 +57,53 @@ pub enum DocItemKind {
     },
 }
 
+/// A registry dependency whose docs are hosted at `/pkg-docs/<name>/<version>/`
+#[derive(Debug, Clone)]
+pub struct DependencyDocs {
+    pub name: String,
+    pub version: String,
+    /// Names of the items documented on the dependency's page
+    pub items: Vec<String>,
+    pub impl_methods: HashMap<String, Vec<String>>,
+    pub associated_functions: HashMap<String, Vec<String>>,
+}
+
+impl DependencyDocs {
+    /// Link to `anchor` on the dependency's docs page
+    fn link(&self, anchor: &str) -> String {
+        format!(
+            "/pkg-docs/{}/{}/index.html#{}",
+            self.name, self.version, anchor
+        )
+    }
+
+    /// Where `symbol`, e.g. `Point` or `Point.area`, is documented in this package
+    fn resolve(&self, symbol: &str) -> Option<String> {
+        if self.items.iter().any(|item| item == symbol) {
+            return Some(self.link(symbol));
+        }
+        let (owner, method) = symbol.split_once('.').or(symbol.split_once("::"))?;
+        [&self.impl_methods, &self.associated_functions]
+            .iter()
+            .any(|table| {
+                table
+                    .get(owner)
+                    .is_some_and(|methods| methods.iter().any(|m| m == method))
+            })
+            .then(|| self.link(method))
+    }
+}
+
 pub struct DocGenerator {
     pub items: Vec<DocItem>,
     pub source_files: HashMap<PathBuf, String>,
     pub impl_relations: Vec<(String, String)>,
     /// Maps type_name -> list of method names defined in impl blocks for that type.
     pub impl_methods: HashMap<String, Vec<String>>,
+    /// Maps type_name -> functions of its impl blocks that take no `self`, like `new`
+    pub associated_functions: HashMap<String, Vec<String>>,
+    /// Dependencies whose items are linked to their registry-hosted docs
+    pub dependencies: Vec<DependencyDocs>,
 }
 
 impl Default for DocGenerator {
@@ -60,6 +119,8 @@ impl DocGenerator {
             source_files: HashMap::new(),
             impl_relations: Vec::new(),
             impl_methods: HashMap::new(),
+            associated_functions: HashMap::new(),
+            dependencies: Vec::new(),
         }
     }
 
@@ -79,18 +140,82 @@ impl DocGenerator {
             .parse()
             .map_err(|e| format!("Failed to parse {}: {}", path.display(), e.message))?;
 
-        self.extract_items(&stmts, &content);
+        self.extract_items(&stmts, path);
         Ok(())
     }
 
-    /// Extract documentation items from parsed statements
-    fn extract_items(&mut self, stmts: &[Stmt], source: &str) {
-        let doc_comments = Self::extract_doc_comments(source);
+    /// Extract documentation items from a file that was already parsed,
+    /// e.g. by [`Project::load`](crate::analysis::project::Project::load)
+    pub fn add_parsed(&mut self, path: &Path, source: &str, stmts: &[Stmt]) {
+        self.source_files
+            .insert(path.to_path_buf(), source.to_string());
+        self.extract_items(stmts, path);
+    }
+
+    /// Link references to the items of a dependency to its docs for `version`,
+    /// reading the items from the dependency's entrypoint
+    pub fn add_dependency<P: AsRef<Path>>(
+        &mut self,
+        name: &str,
+        version: &str,
+        entrypoint: P,
+    ) -> Result<(), String> {
+        let mut dep_gen = DocGenerator::new();
+        dep_gen.parse_file(entrypoint)?;
+        self.dependencies.push(DependencyDocs {
+            name: name.to_string(),
+            version: version.to_string(),
+            items: dep_gen.items.into_iter().map(|item| item.name).collect(),
+            impl_methods: dep_gen.impl_methods,
+            associated_functions: dep_gen.associated_functions,
+        });
+        Ok(())
+    }
 
+    /// Add every dependency of the project in `root` that is installed in
+    /// `.lflibs`, at the version pinned in loft.lock or else the installed one
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn add_dependencies(&mut self, manifest: &Manifest, root: &Path) {
+        let lockfile = Lockfile::load_or_default(root).unwrap_or_default();
+        let lflibs = root.join(".lflibs");
+
+        let mut names: Vec<&String> = manifest.dependencies.keys().collect();
+        names.sort();
+        for name in names {
+            let dir = match lockfile.get(name) {
+                Some(locked) => lflibs.join(format!("{}@{}", name, locked.version)),
+                None => match fs::read_dir(&lflibs).ok().and_then(|entries| {
+                    entries.flatten().map(|entry| entry.path()).find(|path| {
+                        path.file_name()
+                            .and_then(|n| n.to_str())
+                            .is_some_and(|n| n.starts_with(&format!("{}@", name)))
+                    })
+                }) {
+                    Some(dir) => dir,
+                    None => continue,
+                },
+            };
+            let Ok(dep_manifest) = Manifest::load(dir.join("manifest.json")) else {
+                continue;
+            };
+            let version = lockfile
+                .get(name)
+                .map_or(dep_manifest.version.clone(), |locked| {
+                    locked.version.clone()
+                });
+            self.add_dependency(name, &version, dir.join(&dep_manifest.entrypoint))
+                .ok();
+        }
+    }
+
+    /// Extract documentation items from parsed statements
+    fn extract_items(&mut self, stmts: &[Stmt], path: &Path) {
         for stmt in stmts {
-            match stmt {
-                Stmt::FunctionDecl {
+            let location = Some((path.to_path_buf(), stmt.span.line + 1));
+            match &stmt.kind {
+                StmtKind::FunctionDecl {
                     name,
+                    type_params,
                     params,
                     return_type,
                     is_async,
@@ -99,43 +224,50 @@ impl DocGenerator {
                 } => {
                     let params_vec: Vec<(String, String)> = params
                         .iter()
-                        .map(|(n, t)| (n.clone(), Self::type_to_string(t)))
+                        .map(|(n, t)| (n.clone(), type_to_string(t)))
                         .collect();
 
                     let signature = format!(
-                        "{}{}fn {}({}) -> {}",
+                        "{}{}fn {}{}({}) -> {}",
                         if *is_exported { "teach " } else { "" },
                         if *is_async { "async " } else { "" },
                         name,
+                        type_params_to_string(type_params),
                         params_vec
                             .iter()
                             .map(|(n, t)| format!("{}: {}", n, t))
                             .collect::<Vec<_>>()
                             .join(", "),
-                        Self::opt_type_to_string(return_type)
+                        opt_type_to_string(return_type)
                     );
 
                     self.items.push(DocItem {
                         name: name.clone(),
                         kind: DocItemKind::Function {
                             params: params_vec,
-                            return_type: Self::opt_type_to_string(return_type),
+                            return_type: opt_type_to_string(return_type),
                             is_async: *is_async,
                             is_exported: *is_exported,
                         },
-                        documentation: doc_comments.get(name).cloned(),
+                        documentation: stmt.trivia.doc(),
                         signature: Some(signature),
+                        source: location,
                     });
                 }
-                Stmt::StructDecl { name, fields } => {
+                StmtKind::StructDecl {
+                    name,
+                    type_params,
+                    fields,
+                } => {
                     let fields_vec: Vec<(String, String)> = fields
                         .iter()
-                        .map(|(n, t)| (n.clone(), Self::type_to_string(t)))
+                        .map(|(n, t)| (n.clone(), type_to_string(t)))
                         .collect();
 
                     let signature = format!(
-                        "def {} {{\n{}\n}}",
+                        "def {}{} {{\n{}\n}}",
                         name,
+                        type_params_to_string(type_params),
                         fields_vec
                             .iter()
                             .map(|(n, t)| format!("    {}: {}", n, t))
@@ -149,11 +281,12 @@ impl DocGenerator {
                             fields: fields_vec,
                             implemented_traits: Vec::new(),
                         },
-                        documentation: doc_comments.get(name).cloned(),
+                        documentation: stmt.trivia.doc(),
                         signature: Some(signature),
+                        source: location,
                     });
                 }
-                Stmt::TraitDecl { name, methods } => {
+                StmtKind::TraitDecl { name, methods } => {
                     let method_names: Vec<String> = methods
                         .iter()
                         .map(|m| match m {
@@ -168,16 +301,17 @@ impl DocGenerator {
                             methods: method_names,
                             implementors: Vec::new(),
                         },
-                        documentation: doc_comments.get(name).cloned(),
+                        documentation: stmt.trivia.doc(),
                         signature: Some(format!("trait {}", name)),
+                        source: location,
                     });
                 }
-                Stmt::ConstDecl {
+                StmtKind::ConstDecl {
                     name, const_type, ..
                 } => {
                     let type_str = const_type
                         .as_ref()
-                        .map(Self::type_to_string)
+                        .map(type_to_string)
                         .unwrap_or_else(|| "unknown".to_string());
 
                     self.items.push(DocItem {
@@ -185,14 +319,15 @@ impl DocGenerator {
                         kind: DocItemKind::Constant {
                             const_type: type_str.clone(),
                         },
-                        documentation: doc_comments.get(name).cloned(),
+                        documentation: stmt.trivia.doc(),
                         signature: Some(format!("const {}: {}", name, type_str)),
+                        source: location,
                     });
                 }
-                Stmt::VarDecl { name, var_type, .. } => {
+                StmtKind::VarDecl { name, var_type, .. } => {
                     let type_str = var_type
                         .as_ref()
-                        .map(Self::type_to_string)
+                        .map(type_to_string)
                         .unwrap_or_else(|| "unknown".to_string());
 
                     self.items.push(DocItem {
@@ -200,11 +335,12 @@ impl DocGenerator {
                         kind: DocItemKind::Variable {
                             var_type: type_str.clone(),
                         },
-                        documentation: doc_comments.get(name).cloned(),
+                        documentation: stmt.trivia.doc(),
                         signature: Some(format!("let {}: {}", name, type_str)),
+                        source: location,
                     });
                 }
-                Stmt::ImplBlock {
+                StmtKind::ImplBlock {
                     type_name,
                     trait_name,
                     methods,
@@ -215,193 +351,29 @@ impl DocGenerator {
                     }
                     // Track method names per type for sidebar sub-items
                     for method in methods {
-                        if let Stmt::FunctionDecl { name, .. } = method {
-                            self.impl_methods
+                        if let StmtKind::FunctionDecl { name, params, .. } = &method.kind {
+                            let table = if is_method(params) {
+                                &mut self.impl_methods
+                            } else {
+                                &mut self.associated_functions
+                            };
+                            table
                                 .entry(type_name.clone())
                                 .or_default()
                                 .push(name.clone());
                         }
                     }
                     // Recursively process methods in impl blocks
-                    self.extract_items(methods, source);
+                    self.extract_items(methods, path);
                 }
-                Stmt::AttrStmt { stmt, .. } => {
-                    self.extract_items(std::slice::from_ref(stmt), source);
+                StmtKind::AttrStmt { stmt, .. } => {
+                    self.extract_items(std::slice::from_ref(stmt), path);
                 }
                 _ => {}
             }
         }
     }
 
-    /// Extract doc comments from source code
-    fn extract_doc_comments(source: &str) -> HashMap<String, String> {
-        let lines: Vec<&str> = source.lines().collect();
-        let mut doc_map: HashMap<String, String> = HashMap::new();
-
-        let mut i = 0;
-        while i < lines.len() {
-            let line = lines[i].trim();
-
-            // Check for /// style doc comment
-            if line.starts_with("///") {
-                let mut doc_lines = vec![];
-                while i < lines.len() && lines[i].trim().starts_with("///") {
-                    let doc_text = lines[i].trim().strip_prefix("///").unwrap().trim();
-                    if !doc_text.is_empty() {
-                        doc_lines.push(doc_text);
-                    }
-                    i += 1;
-                }
-
-                // Find next declaration
-                while i < lines.len() && lines[i].trim().is_empty() {
-                    i += 1;
-                }
-
-                if i < lines.len() {
-                    if let Some(name) = Self::extract_name_from_declaration(lines[i]) {
-                        doc_map.insert(name, doc_lines.join("\n"));
-                    }
-                }
-            } else if line.starts_with("/**") {
-                // Block doc comment
-                let mut doc_lines = vec![];
-
-                if line.contains("*/") {
-                    // Single-line block comment
-                    let doc_text = line
-                        .strip_prefix("/**")
-                        .and_then(|s| s.strip_suffix("*/"))
-                        .map(|s| s.trim())
-                        .unwrap_or("");
-                    doc_lines.push(doc_text);
-                    i += 1;
-                } else {
-                    // Multi-line block comment
-                    let first_line = line.strip_prefix("/**").unwrap().trim();
-                    if !first_line.is_empty() && !first_line.starts_with("*") {
-                        doc_lines.push(first_line);
-                    }
-                    i += 1;
-
-                    while i < lines.len() {
-                        let comment_line = lines[i].trim();
-                        if comment_line.ends_with("*/") {
-                            let text = comment_line.strip_suffix("*/").unwrap().trim();
-                            if text.starts_with("*") {
-                                let text = text.strip_prefix("*").unwrap().trim();
-                                if !text.is_empty() {
-                                    doc_lines.push(text);
-                                }
-                            } else if !text.is_empty() {
-                                doc_lines.push(text);
-                            }
-                            i += 1;
-                            break;
-                        } else {
-                            if comment_line.starts_with("*") {
-                                let text = comment_line.strip_prefix("*").unwrap().trim();
-                                if !text.is_empty() {
-                                    doc_lines.push(text);
-                                }
-                            }
-                            i += 1;
-                        }
-                    }
-                }
-
-                // Find next declaration
-                while i < lines.len() && lines[i].trim().is_empty() {
-                    i += 1;
-                }
-
-                if i < lines.len() {
-                    if let Some(name) = Self::extract_name_from_declaration(lines[i]) {
-                        doc_map.insert(name, doc_lines.join("\n"));
-                    }
-                }
-            }
-
-            i += 1;
-        }
-
-        doc_map
-    }
-
-    /// Extract the name from a declaration line
-    fn extract_name_from_declaration(line: &str) -> Option<String> {
-        let line = line.trim();
-
-        let patterns = [
-            ("teach fn ", true),
-            ("async fn ", true),
-            ("fn ", true),
-            ("teach async fn ", true),
-            ("let mut ", true),
-            ("let ", true),
-            ("const ", true),
-            ("def ", true),
-            ("trait ", true),
-            ("struct ", true),
-        ];
-
-        for (pattern, extract_next) in patterns {
-            if line.starts_with(pattern)
-                && extract_next {
-                    let rest = line.strip_prefix(pattern).unwrap();
-                    // Extract identifier (alphanumeric + underscore)
-                    let name: String = rest
-                        .chars()
-                        .take_while(|c| c.is_alphanumeric() || *c == '_')
-                        .collect();
-                    if !name.is_empty() {
-                        return Some(name);
-                    }
-                }
-        }
-
-        None
-    }
-
-    /// Convert a Type to a string representation
-    fn type_to_string(ty: &Type) -> String {
-        match ty {
-            Type::Named(name) => name.clone(),
-            Type::Generic { base, type_args } => {
-                format!(
-                    "{}<{}>",
-                    base,
-                    type_args
-                        .iter()
-                        .map(Self::type_to_string)
-                        .collect::<Vec<_>>()
-                        .join(", ")
-                )
-            }
-            Type::Function {
-                params,
-                return_type,
-            } => {
-                format!(
-                    "({}) -> {}",
-                    params
-                        .iter()
-                        .map(Self::type_to_string)
-                        .collect::<Vec<_>>()
-                        .join(", "),
-                    Self::type_to_string(return_type)
-                )
-            }
-        }
-    }
-
-    fn opt_type_to_string(ty: &Option<Type>) -> String {
-        match ty {
-            Some(t) => Self::type_to_string(t),
-            None => "void".to_string(),
-        }
-    }
-
     /// Generate HTML documentation
     pub fn generate_html(&mut self, output_dir: &Path, package_name: &str) -> Result<(), String> {
         // Post-process implementations
@@ -442,6 +414,17 @@ impl DocGenerator {
         let css_path = output_dir.join("style.css");
         fs::write(&css_path, css).map_err(|e| format!("Failed to write style.css: {}", e))?;
 
+        // Generate source listings for the [src] links
+        for (file, content) in &self.source_files {
+            let page_path = output_dir.join(source::page_path(file));
+            if let Some(parent) = page_path.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create output directory: {}", e))?;
+            }
+            fs::write(&page_path, source::source_page(file, content, package_name))
+                .map_err(|e| format!("Failed to write {}: {}", page_path.display(), e))?;
+        }
+
         Ok(())
     }
 
@@ -508,18 +491,29 @@ impl DocGenerator {
             html.push_str("            <ul>\n");
             for item in &structs {
                 let impl_methods = self.impl_methods.get(&item.name);
+                let associated_functions = self.associated_functions.get(&item.name);
                 let impl_traits: &[String] = if let DocItemKind::Struct { implemented_traits, .. } = &item.kind {
                     implemented_traits.as_slice()
                 } else {
                     &[]
                 };
-                let has_subitems = impl_methods.is_some_and(|m| !m.is_empty()) || !impl_traits.is_empty();
+                let has_subitems = impl_methods.is_some_and(|m| !m.is_empty())
+                    || associated_functions.is_some_and(|f| !f.is_empty())
+                    || !impl_traits.is_empty();
                 if has_subitems {
                     html.push_str(&format!(
                         "                <li><a href=\"#{}\">{}</a>\n",
                         item.name, item.name
                     ));
                     html.push_str("                    <ul class=\"nav-subitems\">\n");
+                    if let Some(functions) = associated_functions {
+                        for function_name in functions {
+                            html.push_str(&format!(
+                                "                        <li><a href=\"#{}\">fn {}.{}</a></li>\n",
+                                function_name, item.name, function_name
+                            ));
+                        }
+                    }
                     if let Some(methods) = impl_methods {
                         for method_name in methods {
                             html.push_str(&format!(
@@ -617,11 +611,20 @@ impl DocGenerator {
             _ => false,
         };
 
+        let src_link = match &item.source {
+            Some((file, line)) => format!(
+                " <a class=\"src-link\" href=\"{}#L{}\">[src]</a>",
+                source::page_path(file),
+                line
+            ),
+            None => String::new(),
+        };
         html.push_str(&format!(
-            "            <h3 id=\"{}\">{}{}</h3>\n",
+            "            <h3 id=\"{}\">{}{}{}</h3>\n",
             item.name,
             item.name,
-            if is_exported { " (pub)" } else { "" }
+            if is_exported { " (pub)" } else { "" },
+            src_link
         ));
 
         // Generate signature with links
@@ -662,11 +665,7 @@ impl DocGenerator {
         }
 
         if let Some(doc) = &item.documentation {
-            for line in doc.lines() {
-                html.push_str("            <p>");
-                html.push_str(&Self::escape_html(line));
-                html.push_str("</p>\n");
-            }
+            html.push_str(&markdown::to_html(doc, &|symbol| self.doc_link(symbol)));
         }
 
         // Add details based on kind
@@ -707,6 +706,25 @@ impl DocGenerator {
                     }
                     html.push_str("            </ul>\n");
                 }
+
+                for (heading, table) in [
+                    ("Associated functions", &self.associated_functions),
+                    ("Methods", &self.impl_methods),
+                ] {
+                    let Some(names) = table.get(&item.name).filter(|n| !n.is_empty()) else {
+                        continue;
+                    };
+                    html.push_str(&format!("            <h4>{}</h4>\n", heading));
+                    html.push_str("            <ul class=\"methods\">\n");
+                    for name in names {
+                        html.push_str(&format!(
+                            "                <li><a href=\"#{}\"><code>{}</code></a></li>\n",
+                            name,
+                            Self::escape_html(name)
+                        ));
+                    }
+                    html.push_str("            </ul>\n");
+                }
             }
             DocItemKind::Trait {
                 methods,
@@ -744,52 +762,56 @@ impl DocGenerator {
         html
     }
 
-    fn type_to_html_string(&self, type_str: &str) -> String {
-        // Basic heuristic: if the type string contains a known item name, link it.
-        // This is a simplification. Ideally we'd parse the type string.
-        let mut html = Self::escape_html(type_str);
-        let mut replacements = Vec::new();
-
-        // Primitives from stdlib
-        let primitives = [
-            ("str", "/d/std/string.html"),
-            ("num", "/d/std/num.html"),
-            ("bool", "/d/std/bool.html"),
-            ("void", "/d/std/void.html"),
-            ("Array", "/d/std/array.html"),
-        ];
-
-        for (i, (prim, link)) in primitives.iter().enumerate() {
-            let pattern = format!(r"\b{}\b", regex::escape(prim));
-            if let Ok(re) = regex::Regex::new(&pattern) {
-                if re.is_match(&html) {
-                    let placeholder = format!("__PRIM_{}__", i);
-                    html = re.replace_all(&html, placeholder.as_str()).to_string();
-                    replacements.push((placeholder, format!("<a href=\"{}\">{}</a>", link, prim)));
-                }
+    /// Where a reference to `symbol`, in a signature or a `[Symbol]` in a doc
+    /// comment, points: an item of this package, a method of one of its types,
+    /// a stdlib primitive, or an item of a dependency such as `geo::Point`
+    fn doc_link(&self, symbol: &str) -> Option<String> {
+        if self.items.iter().any(|item| item.name == symbol) {
+            return Some(format!("#{}", symbol));
+        }
+        if let Some((_, link)) = STD_PAGES.iter().find(|(prim, _)| *prim == symbol) {
+            return Some(link.to_string());
+        }
+
+        // A name qualified with the package it comes from
+        if let Some((package, rest)) = symbol.split_once("::").or(symbol.split_once('.')) {
+            if let Some(dep) = self.dependencies.iter().find(|dep| dep.name == package) {
+                return dep.resolve(rest);
             }
         }
 
-        // Sort items by name length descending to avoid partial replacements
-        let mut item_names: Vec<String> = self.items.iter().map(|i| i.name.clone()).collect();
-        item_names.sort_by_key(|b| std::cmp::Reverse(b.len()));
-
-        for (i, name) in item_names.iter().enumerate() {
-            // Only replace whole words
-            let pattern = format!(r"\b{}\b", regex::escape(name));
-            if let Ok(re) = regex::Regex::new(&pattern) {
-                if re.is_match(&html) {
-                    let placeholder = format!("__ITEM_{}__", i);
-                    html = re.replace_all(&html, placeholder.as_str()).to_string();
-                    replacements.push((placeholder, format!("<a href=\"#{}\">{}</a>", name, name)));
-                }
+        if let Some((owner, method)) = symbol.split_once('.').or(symbol.split_once("::")) {
+            if [&self.impl_methods, &self.associated_functions]
+                .iter()
+                .any(|table| {
+                    table
+                        .get(owner)
+                        .is_some_and(|methods| methods.iter().any(|m| m == method))
+                })
+            {
+                return Some(format!("#{}", method));
             }
         }
+        self.dependencies.iter().find_map(|dep| dep.resolve(symbol))
+    }
 
-        // Apply replacements
-        for (placeholder, replacement) in replacements {
-            html = html.replace(&placeholder, &replacement);
+    /// A type as HTML, with every name that resolves linked to its docs
+    fn type_to_html_string(&self, type_str: &str) -> String {
+        let names =
+            regex::Regex::new(r"[A-Za-z_][A-Za-z0-9_]*(?:(?:::|\.)[A-Za-z_][A-Za-z0-9_]*)*")
+                .unwrap();
+        let mut html = String::new();
+        let mut last = 0;
+        for name in names.find_iter(type_str) {
+            html.push_str(&Self::escape_html(&type_str[last..name.start()]));
+            let text = Self::escape_html(name.as_str());
+            match self.doc_link(name.as_str()) {
+                Some(link) => html.push_str(&format!("<a href=\"{}\">{}</a>", link, text)),
+                None => html.push_str(&text),
+            }
+            last = name.end();
         }
+        html.push_str(&Self::escape_html(&type_str[last..]));
         html
     }
 
@@ -1055,6 +1077,76 @@ strong {
     transition: background-color 0.15s, border-color 0.15s;
 }
 
+.src-link {
+    float: right;
+    font-size: 13px;
+    font-weight: 400;
+    color: #6b7280;
+    text-decoration: none;
+}
+
+.src-link:hover {
+    color: var(--color-bio-green);
+}
+
+.source {
+    background-color: #fff;
+    border: 1px solid var(--color-border);
+    border-radius: 6px;
+    padding: 12px 0;
+    overflow-x: auto;
+}
+
+.source code {
+    display: block;
+    background: none;
+    padding: 0;
+    color: var(--color-bio-black);
+    white-space: pre;
+}
+
+.source .line {
+    display: block;
+    padding-right: 16px;
+}
+
+.source .line:target {
+    background-color: #fdf6d8;
+}
+
+.source .line-number {
+    display: inline-block;
+    width: 48px;
+    margin-right: 16px;
+    padding-right: 8px;
+    text-align: right;
+    color: #9ca3af;
+    text-decoration: none;
+    user-select: none;
+}
+
+.source .keyword {
+    color: var(--color-bio-green);
+    font-weight: 600;
+}
+
+.source .type {
+    color: var(--color-bio-gold);
+}
+
+.source .string {
+    color: var(--color-bio-green-light);
+}
+
+.source .number {
+    color: #b45309;
+}
+
+.source .comment {
+    color: #6b7280;
+    font-style: italic;
+}
+
 .trait-pill:hover {
     background-color: #e8f5e0;
     border-color: var(--color-bio-green);
@@ -1082,28 +1174,6 @@ mod tests {
     use std::io::Write;
     use tempfile::NamedTempFile;
 
-    #[test]
-    fn test_extract_doc_comments() {
-        let source = r#"
-/// This is a documented function
-/// It adds two numbers
-fn add(a: num, b: num) -> num {
-    return a + b;
-}
-
-/** This is a variable with documentation */
-let x = 42;
-"#;
-
-        let doc_comments = DocGenerator::extract_doc_comments(source);
-        assert!(doc_comments.contains_key("add"));
-        assert_eq!(
-            doc_comments.get("add").unwrap(),
-            "This is a documented function\nIt adds two numbers"
-        );
-        assert!(doc_comments.contains_key("x"));
-    }
-
     #[test]
     fn test_parse_simple_function() {
         let source = r#"
@@ -1126,26 +1196,118 @@ fn add(a: num, b: num) -> num {
     }
 
     #[test]
-    fn test_extract_name_from_declaration() {
-        assert_eq!(
-            DocGenerator::extract_name_from_declaration("fn test() {}"),
-            Some("test".to_string())
-        );
-        assert_eq!(
-            DocGenerator::extract_name_from_declaration("teach fn exported() {}"),
-            Some("exported".to_string())
-        );
-        assert_eq!(
-            DocGenerator::extract_name_from_declaration("let x = 42;"),
-            Some("x".to_string())
-        );
+    fn test_doc_comments_render_as_markdown() {
+        let source = r#"
+def Point { x: num, y: num }
+
+/// Distance from the origin to a [Point].
+///
+/// ```loft
+/// let d = length(Point { x: 3, y: 4 });
+/// ```
+fn length(p: Point) -> num {
+    return p.x;
+}
+"#;
+
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file.write_all(source.as_bytes()).unwrap();
+
+        let mut doc_gen = DocGenerator::new();
+        doc_gen.parse_file(temp_file.path()).unwrap();
+        let html = doc_gen.generate_item_html(&doc_gen.items[1]);
+        assert!(html.contains(
+            "<p>Distance from the origin to a <a href=\"#Point\"><code>Point</code></a>.</p>"
+        ));
+        assert!(html.contains(
+            "<pre><code class=\"language-loft\">let d = length(Point { x: 3, y: 4 });</code></pre>"
+        ));
+    }
+
+    #[test]
+    fn test_associated_functions_listed_apart_from_methods() {
+        let source = r#"
+def Point { x: num, y: num }
+
+impl Point {
+    fn new(x: num, y: num) -> Point {
+        return Point { x: x, y: y };
+    }
+
+    fn sum(self) -> num {
+        return self.x + self.y;
+    }
+}
+"#;
+
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file.write_all(source.as_bytes()).unwrap();
+
+        let mut doc_gen = DocGenerator::new();
+        doc_gen.parse_file(temp_file.path()).unwrap();
+        assert_eq!(doc_gen.associated_functions["Point"], ["new"]);
+        assert_eq!(doc_gen.impl_methods["Point"], ["sum"]);
+        assert_eq!(doc_gen.doc_link("Point.new").as_deref(), Some("#new"));
+
+        let html = doc_gen.generate_item_html(&doc_gen.items[0]);
+        let associated = html.find("<h4>Associated functions</h4>").unwrap();
+        let methods = html.find("<h4>Methods</h4>").unwrap();
+        assert!(associated < methods);
+        assert!(html.contains("<li><a href=\"#new\"><code>new</code></a></li>"));
+    }
+
+    #[test]
+    fn test_source_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("main.lf");
+        fs::write(&file, "// Shapes\n\ndef Point { x: num, y: num }\n").unwrap();
+
+        let mut doc_gen = DocGenerator::new();
+        doc_gen.parse_file(&file).unwrap();
+        let out = dir.path().join("docs");
+        doc_gen.generate_html(&out, "shapes").unwrap();
+
+        let page = source::page_path(&file);
+        let index = fs::read_to_string(out.join("index.html")).unwrap();
+        assert!(index.contains(&format!("href=\"{}#L3\">[src]</a>", page)));
+        let listing = fs::read_to_string(out.join(&page)).unwrap();
+        assert!(listing.contains("id=\"L3\""));
+        assert!(listing
+            .contains("<span class=\"keyword\">def</span> <span class=\"type\">Point</span>"));
+    }
+
+    #[test]
+    fn test_links_to_dependency_docs() {
+        let dir = tempfile::tempdir().unwrap();
+        let dep_dir = dir.path().join(".lflibs").join("geo@1.2.0");
+        fs::create_dir_all(&dep_dir).unwrap();
+        fs::write(
+            dep_dir.join("manifest.json"),
+            r#"{ "name": "geo", "version": "1.2.0", "entrypoint": "main.lf" }"#,
+        )
+        .unwrap();
+        fs::write(
+            dep_dir.join("main.lf"),
+            "def Point { x: num, y: num }\nimpl Point {\n    fn area(self) -> num { return 0; }\n}\n",
+        )
+        .unwrap();
+        let manifest: Manifest = serde_json::from_str(
+            r#"{ "name": "app", "version": "0.1.0", "entrypoint": "main.lf", "dependencies": { "geo": "^1.0.0" } }"#,
+        )
+        .unwrap();
+
+        let mut doc_gen = DocGenerator::new();
+        doc_gen.add_dependencies(&manifest, dir.path());
+        assert_eq!(doc_gen.dependencies.len(), 1);
         assert_eq!(
-            DocGenerator::extract_name_from_declaration("const PI = 3.14;"),
-            Some("PI".to_string())
+            doc_gen.type_to_html_string("Array<Point>"),
+            "<a href=\"/d/std/array.html\">Array</a>&lt;\
+             <a href=\"/pkg-docs/geo/1.2.0/index.html#Point\">Point</a>&gt;"
         );
         assert_eq!(
-            DocGenerator::extract_name_from_declaration("def Point {"),
-            Some("Point".to_string())
+            doc_gen.doc_link("geo::Point.area").as_deref(),
+            Some("/pkg-docs/geo/1.2.0/index.html#area")
         );
+        assert_eq!(doc_gen.doc_link("Line"), None);
     }
 }
@@ -0,0 +1,166 @@
+//! Syntax-highlighted source listings linked from the `[src]` link of each item.
+//!
+//! Every line of a listing has an `L<n>` anchor, so `main.lf.html#L12` opens
+//! the file at line 12.
+
+use super::markdown::escape_html;
+use crate::parser::token_stream::KEYWORDS;
+use std::path::{Component, Path};
+
+/// Directory of the generated docs holding the source listings
+pub const SOURCE_DIR: &str = "src";
+
+/// Path of the listing for `file`, relative to the docs root,
+/// e.g. `src/lib/geometry.lf.html`
+pub fn page_path(file: &Path) -> String {
+    let mut parts = vec![SOURCE_DIR.to_string()];
+    parts.extend(file.components().filter_map(|c| match c {
+        Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+        _ => None,
+    }));
+    format!("{}.html", parts.join("/"))
+}
+
+/// Highlight loft source as HTML, one entry per line. Comments and strings
+/// that span several lines are closed at the end of each line and reopened
+/// on the next, so every line is valid HTML on its own.
+pub fn highlight(source: &str) -> Vec<String> {
+    let mut lines = vec![String::new()];
+    let mut push = |class: Option<&str>, text: &str| {
+        for (i, part) in text.split('\n').enumerate() {
+            if i > 0 {
+                lines.push(String::new());
+            }
+            if part.is_empty() {
+                continue;
+            }
+            let line = lines.last_mut().unwrap();
+            match class {
+                Some(class) => line.push_str(&format!(
+                    "<span class=\"{}\">{}</span>",
+                    class,
+                    escape_html(part)
+                )),
+                None => line.push_str(&escape_html(part)),
+            }
+        }
+    };
+
+    let mut rest = source;
+    while let Some(c) = rest.chars().next() {
+        let (class, len) = if rest.starts_with("//") {
+            (Some("comment"), rest.find('\n').unwrap_or(rest.len()))
+        } else if let Some(body) = rest.strip_prefix("/*") {
+            let end = body.find("*/").map_or(rest.len(), |end| end + 4);
+            (Some("comment"), end)
+        } else if c == '"' || c == '`' {
+            (Some("string"), string_len(rest, c))
+        } else if c.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.'))
+                .unwrap_or(rest.len());
+            (Some("number"), len)
+        } else if c.is_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            let word = &rest[..len];
+            let class = if KEYWORDS.contains(&word) {
+                Some("keyword")
+            } else if word.starts_with(char::is_uppercase) {
+                Some("type")
+            } else {
+                None
+            };
+            (class, len)
+        } else {
+            (None, c.len_utf8())
+        };
+        push(class, &rest[..len]);
+        rest = &rest[len..];
+    }
+
+    lines
+}
+
+/// Length of the string literal at the start of `text`, up to and including
+/// the closing `quote`
+fn string_len(text: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == quote {
+            return i + c.len_utf8();
+        }
+    }
+    text.len()
+}
+
+/// The listing page for `file`
+pub fn source_page(file: &Path, source: &str, package_name: &str) -> String {
+    let page = page_path(file);
+    let root = "../".repeat(page.matches('/').count());
+    let name = escape_html(&file.display().to_string());
+
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n");
+    html.push_str("    <meta charset=\"UTF-8\">\n");
+    html.push_str(
+        "    <meta name=\"viewport\" content=\"width=device-width, initial-scale=1.0\">\n",
+    );
+    html.push_str(&format!(
+        "    <title>{} - {} - loft Documentation</title>\n",
+        name, package_name
+    ));
+    html.push_str(&format!(
+        "    <link rel=\"stylesheet\" href=\"{}style.css\">\n",
+        root
+    ));
+    html.push_str("</head>\n<body>\n");
+    html.push_str("    <div class=\"content\">\n");
+    html.push_str(&format!(
+        "        <div class=\"breadcrumb\"><a href=\"{}index.html\">{}</a> / <span>{}</span></div>\n",
+        root, package_name, name
+    ));
+    html.push_str(&format!("        <h1>{}</h1>\n", name));
+    html.push_str("        <pre class=\"source\"><code>");
+    for (i, line) in highlight(source).iter().enumerate() {
+        html.push_str(&format!(
+            "<span class=\"line\" id=\"L{0}\"><a class=\"line-number\" href=\"#L{0}\">{0}</a>{1}</span>\n",
+            i + 1,
+            line
+        ));
+    }
+    html.push_str("</code></pre>\n");
+    html.push_str("    </div>\n");
+    html.push_str("</body>\n");
+    html.push_str("</html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight() {
+        let lines = highlight("// Sum\nfn add(a: num) -> Point {\n    let s = \"a\\\"<b\n\";\n}");
+        assert_eq!(
+            lines,
+            vec![
+                "<span class=\"comment\">// Sum</span>",
+                "<span class=\"keyword\">fn</span> add(a: num) -&gt; <span class=\"type\">Point</span> {",
+                "    <span class=\"keyword\">let</span> s = <span class=\"string\">&quot;a\\&quot;&lt;b</span>",
+                "<span class=\"string\">&quot;</span>;",
+                "}",
+            ]
+        );
+        assert_eq!(
+            page_path(Path::new("./src/main.lf")),
+            "src/src/main.lf.html"
+        );
+    }
+}
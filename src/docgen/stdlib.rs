@@ -1,3 +1,4 @@
+use super::markdown;
 use regex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -295,8 +296,8 @@ impl StdlibDocGenerator {
         html.push_str("</span></div>\n");
         html.push_str(&format!("        <h1>{}</h1>\n", name));
         html.push_str(&format!(
-            "        <p class=\"description\">{}</p>\n",
-            Self::escape_html(&builtin.documentation)
+            "        <div class=\"description\">{}</div>\n",
+            self.doc_html(&builtin.documentation)
         ));
 
         // Constants
@@ -314,10 +315,7 @@ impl StdlibDocGenerator {
                     const_name,
                     self.link_type(&constant.const_type)
                 ));
-                html.push_str(&format!(
-                    "            <p>{}</p>\n",
-                    Self::escape_html(&constant.documentation)
-                ));
+                html.push_str(&self.doc_html(&constant.documentation));
                 html.push_str("        </div>\n");
             }
         }
@@ -342,10 +340,7 @@ impl StdlibDocGenerator {
                     "            <p><strong>Returns:</strong> <code>{}</code></p>\n",
                     self.link_type(&method.return_type)
                 ));
-                html.push_str(&format!(
-                    "            <p>{}</p>\n",
-                    Self::escape_html(&method.documentation)
-                ));
+                html.push_str(&self.doc_html(&method.documentation));
                 html.push_str("        </div>\n");
             }
         }
@@ -403,8 +398,8 @@ impl StdlibDocGenerator {
         html.push_str("</span></div>\n");
         html.push_str(&format!("        <h1>{}</h1>\n", title));
         html.push_str(&format!(
-            "        <p class=\"description\">{}</p>\n",
-            Self::escape_html(description)
+            "        <div class=\"description\">{}</div>\n",
+            self.doc_html(description)
         ));
 
         // Instance methods
@@ -427,10 +422,7 @@ impl StdlibDocGenerator {
                 "            <p><strong>Returns:</strong> <code>{}</code></p>\n",
                 self.link_type(&method.return_type)
             ));
-            html.push_str(&format!(
-                "            <p>{}</p>\n",
-                Self::escape_html(&method.documentation)
-            ));
+            html.push_str(&self.doc_html(&method.documentation));
             if let Some(example) = self.get_usage_example(title, method_name) {
                 html.push_str("            <h4>Example</h4>\n");
                 html.push_str(&format!(
@@ -464,10 +456,7 @@ impl StdlibDocGenerator {
                         "            <p><strong>Returns:</strong> <code>{}</code></p>\n",
                         self.link_type(&method.return_type)
                     ));
-                    html.push_str(&format!(
-                        "            <p>{}</p>\n",
-                        Self::escape_html(&method.documentation)
-                    ));
+                    html.push_str(&self.doc_html(&method.documentation));
                     html.push_str("        </div>\n");
                 }
             }
@@ -529,8 +518,8 @@ impl StdlibDocGenerator {
         html.push_str("</span></div>\n");
         html.push_str(&format!("        <h1>{}</h1>\n", title));
         html.push_str(&format!(
-            "        <p class=\"description\">{}</p>\n",
-            Self::escape_html(description)
+            "        <div class=\"description\">{}</div>\n",
+            self.doc_html(description)
         ));
 
         html.push_str("        <h2 id=\"methods\">Methods</h2>\n");
@@ -550,10 +539,7 @@ impl StdlibDocGenerator {
                 "            <p><strong>Returns:</strong> <code>{}</code></p>\n",
                 self.link_type(&method.return_type)
             ));
-            html.push_str(&format!(
-                "            <p>{}</p>\n",
-                Self::escape_html(&method.documentation)
-            ));
+            html.push_str(&self.doc_html(&method.documentation));
 
             // Add usage examples for common methods
             if let Some(example) = self.get_usage_example(title, method_name) {
@@ -618,8 +604,8 @@ impl StdlibDocGenerator {
         html.push_str("</span></div>\n");
         html.push_str(&format!("        <h1>Type: {}</h1>\n", name));
         html.push_str(&format!(
-            "        <p class=\"description\">{}</p>\n",
-            Self::escape_html(&type_def.documentation)
+            "        <div class=\"description\">{}</div>\n",
+            self.doc_html(&type_def.documentation)
         ));
 
         if !type_def.fields.is_empty() {
@@ -635,10 +621,7 @@ impl StdlibDocGenerator {
                     field_name,
                     self.link_type(&field.field_type)
                 ));
-                html.push_str(&format!(
-                    "            <p>{}</p>\n",
-                    Self::escape_html(&field.documentation)
-                ));
+                html.push_str(&self.doc_html(&field.documentation));
                 html.push_str("        </div>\n");
             }
         }
@@ -662,10 +645,7 @@ impl StdlibDocGenerator {
                     "            <p><strong>Returns:</strong> <code>{}</code></p>\n",
                     self.link_type(&method.return_type)
                 ));
-                html.push_str(&format!(
-                    "            <p>{}</p>\n",
-                    Self::escape_html(&method.documentation)
-                ));
+                html.push_str(&self.doc_html(&method.documentation));
                 html.push_str("        </div>\n");
             }
         }
@@ -717,8 +697,8 @@ impl StdlibDocGenerator {
         html.push_str("</span></div>\n");
         html.push_str(&format!("        <h1>Trait: {}</h1>\n", name));
         html.push_str(&format!(
-            "        <p class=\"description\">{}</p>\n",
-            Self::escape_html(&trait_def.documentation)
+            "        <div class=\"description\">{}</div>\n",
+            self.doc_html(&trait_def.documentation)
         ));
 
         html.push_str("        <h2 id=\"required-methods\">Required Methods</h2>\n");
@@ -738,10 +718,7 @@ impl StdlibDocGenerator {
                 "            <p><strong>Returns:</strong> <code>{}</code></p>\n",
                 self.link_type(&method.return_type)
             ));
-            html.push_str(&format!(
-                "            <p>{}</p>\n",
-                Self::escape_html(&method.documentation)
-            ));
+            html.push_str(&self.doc_html(&method.documentation));
             html.push_str("        </div>\n");
         }
 
@@ -901,6 +878,32 @@ impl StdlibDocGenerator {
         }
     }
 
+    /// Doc text rendered as Markdown, with `[Symbol]` linking to stdlib pages
+    fn doc_html(&self, doc: &str) -> String {
+        markdown::to_html(doc, &|symbol| self.doc_link(symbol))
+    }
+
+    /// The page documenting a stdlib symbol such as `str`, `term`, `term.println` or `Option`
+    fn doc_link(&self, symbol: &str) -> Option<String> {
+        let (owner, member) = match symbol.split_once('.').or(symbol.split_once("::")) {
+            Some((owner, member)) => (owner, Some(member)),
+            None => (symbol, None),
+        };
+        let page = match owner {
+            "str" | "string" => "string.html".to_string(),
+            "num" | "bool" | "void" => format!("{}.html", owner),
+            "Array" | "array" => "array.html".to_string(),
+            _ if self.stdlib.builtins.contains_key(owner) => format!("{}.html", owner),
+            _ if self.stdlib.types.contains_key(owner) => format!("type-{}.html", owner),
+            _ if self.stdlib.traits.contains_key(owner) => format!("trait-{}.html", owner),
+            _ => return None,
+        };
+        Some(match member {
+            Some(member) => format!("{}#{}", page, member),
+            None => page,
+        })
+    }
+
     fn link_type(&self, type_str: &str) -> String {
         let mut html = Self::escape_html(type_str);
         let mut replacements = Vec::new();
@@ -1,6 +1,6 @@
 use crate::docgen::stdlib::{MethodDef, StdlibTypes};
 use crate::docgen::{DocItem, DocItemKind};
-use owo_colors::OwoColorize;
+use crate::output::Colorize;
 use std::collections::HashMap;
 
 pub fn display_stdlib_doc(topic: &str, stdlib: &StdlibTypes) -> bool {
@@ -0,0 +1,263 @@
+//! A small facade for embedding loft in Rust programs.
+//!
+//! [`Engine`] wraps the parser and the interpreter so a host can run code,
+//! call the functions it defines and exchange values with it:
+//!
+//! ```
+//! use loft::runtime::value::Value;
+//! use loft::Engine;
+//!
+//! let mut engine = Engine::new();
+//! engine.eval("fn double(x: num) -> num { return x * 2; }").unwrap();
+//! let result = engine.call_function("double", vec![Value::Number(21.into())]).unwrap();
+//! assert_eq!(result, Value::Number(42.into()));
+//! ```
+//!
+//! Builtins that touch the system ask for permission through
+//! [`permission_context`](crate::runtime::permission_context), which is set
+//! up per thread with [`Engine::set_permissions`].
+
+use crate::parser::{self, InputStream, Parser};
+use crate::runtime::builtin::BuiltinStruct;
+use crate::runtime::limits::ExecutionLimits;
+use crate::runtime::permission_context;
+use crate::runtime::permissions::PermissionManager;
+use crate::runtime::value::Value;
+use crate::runtime::{Interpreter, RuntimeError};
+use miette::Diagnostic;
+use std::fmt::Display;
+
+/// Path code passed to [`Engine::eval`] runs as
+const EVAL_PATH: &str = "<eval>";
+
+/// Why code run by an [`Engine`] failed
+#[derive(Debug)]
+pub enum EngineError {
+    Io(std::io::Error),
+    Parse(parser::input_stream::Error),
+    Runtime(RuntimeError),
+}
+
+impl EngineError {
+    /// The status the program passed to `exit()`, if that is how it ended
+    pub fn exit_code(&self) -> Option<i32> {
+        match self {
+            EngineError::Runtime(e) => e.exit_code(),
+            _ => None,
+        }
+    }
+
+    fn diagnostic(&self) -> Option<&dyn Diagnostic> {
+        match self {
+            EngineError::Io(_) => None,
+            EngineError::Parse(e) => Some(e),
+            EngineError::Runtime(e) => Some(e),
+        }
+    }
+}
+
+impl Display for EngineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EngineError::Io(e) => write!(f, "IO error: {}", e),
+            EngineError::Parse(e) => write!(f, "{}", e),
+            EngineError::Runtime(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for EngineError {}
+
+impl Diagnostic for EngineError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.diagnostic()?.code()
+    }
+
+    fn severity(&self) -> Option<miette::Severity> {
+        Some(miette::Severity::Error)
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.diagnostic()?.help()
+    }
+
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        self.diagnostic()?.source_code()
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        self.diagnostic()?.labels()
+    }
+}
+
+impl From<std::io::Error> for EngineError {
+    fn from(e: std::io::Error) -> Self {
+        EngineError::Io(e)
+    }
+}
+
+impl From<parser::input_stream::Error> for EngineError {
+    fn from(e: parser::input_stream::Error) -> Self {
+        EngineError::Parse(e)
+    }
+}
+
+impl From<RuntimeError> for EngineError {
+    fn from(e: RuntimeError) -> Self {
+        EngineError::Runtime(e)
+    }
+}
+
+/// A loft program that keeps its state between calls: functions, structs and
+/// variables defined by one `eval` are visible to the next
+pub struct Engine {
+    interpreter: Interpreter,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Engine {
+            interpreter: Interpreter::new(),
+        }
+    }
+
+    /// An engine around an interpreter set up elsewhere, e.g. a sandboxed one
+    /// from [`Interpreter::builder`]
+    pub fn from_interpreter(interpreter: Interpreter) -> Self {
+        Engine { interpreter }
+    }
+
+    /// Enable feature-gated builtins, e.g. `ffi`. Drops whatever was defined so far.
+    pub fn with_features(mut self, features: Vec<String>) -> Self {
+        self.interpreter = self.interpreter.with_features(features);
+        self
+    }
+
+    /// Stop code with an error once it runs longer than `limits` allow
+    pub fn with_limits(mut self, limits: ExecutionLimits) -> Self {
+        self.interpreter.set_limits(limits);
+        self
+    }
+
+    /// Decide what builtins run on the current thread may access
+    pub fn set_permissions(&self, permissions: PermissionManager) {
+        permission_context::init_permissions(permissions);
+    }
+
+    /// Run code, returning the value of its last statement. As with a
+    /// program, a `main` function without parameters is called at the end.
+    pub fn eval(&mut self, source: &str) -> Result<Value, EngineError> {
+        self.eval_named(EVAL_PATH, source)
+    }
+
+    /// Run code as if it was read from `path`, which errors point at and
+    /// relative imports are resolved from
+    pub fn eval_named(&mut self, path: &str, source: &str) -> Result<Value, EngineError> {
+        let source = source.to_string();
+        let stmts = Parser::new(InputStream::new(path, &source)).parse()?;
+        self.interpreter.set_source(path, source.clone());
+        Ok(self.interpreter.eval_program(stmts)?)
+    }
+
+    /// Read and run a file
+    pub fn eval_file(&mut self, path: &str) -> Result<Value, EngineError> {
+        let source = crate::runtime::vfs::read_to_string(path)?;
+        self.eval_named(path, &source)
+    }
+
+    /// Call a function defined by code run earlier, or a builtin function
+    pub fn call_function(&mut self, name: &str, args: Vec<Value>) -> Result<Value, EngineError> {
+        let function = self
+            .interpreter
+            .env
+            .get(name)
+            .cloned()
+            .ok_or_else(|| RuntimeError::new(format!("Undefined function '{}'", name)))?;
+        Ok(self.interpreter.call_value(function, args)?)
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<&Value> {
+        self.interpreter.env.get(name)
+    }
+
+    /// Define or replace a global variable
+    pub fn set_global(&mut self, name: impl Into<String>, value: Value) {
+        self.interpreter.env.set(name.into(), value);
+    }
+
+    /// Make a builtin available under its name, like `term` or `math`,
+    /// replacing any builtin of the same name
+    pub fn register_builtin_module(&mut self, module: BuiltinStruct) {
+        self.interpreter
+            .env
+            .set(module.name.clone(), Value::Builtin(module));
+    }
+
+    /// The builtin registered under `name`, to change or extend before
+    /// registering it again
+    pub fn builtin_module(&self, name: &str) -> Option<BuiltinStruct> {
+        match self.get_global(name) {
+            Some(Value::Builtin(module)) => Some(module.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn interpreter(&self) -> &Interpreter {
+        &self.interpreter
+    }
+
+    /// The interpreter underneath, for what the engine doesn't cover
+    pub fn interpreter_mut(&mut self) -> &mut Interpreter {
+        &mut self.interpreter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::RuntimeResult;
+
+    fn shout(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+        match args.first() {
+            Some(Value::String(s)) => Ok(Value::String(s.to_uppercase().into())),
+            _ => Err(RuntimeError::new("shout() expects a string")),
+        }
+    }
+
+    #[test]
+    fn test_engine() {
+        let mut engine = Engine::new();
+        let mut host = BuiltinStruct::new("host");
+        host.add_method("shout", shout);
+        engine.register_builtin_module(host);
+        engine.set_global("name", Value::String("loft".into()));
+
+        engine
+            .eval("fn greet(greeting: str) -> str {\n    return `${greeting}, ${host.shout(name)}`;\n}")
+            .unwrap();
+        assert_eq!(
+            engine
+                .call_function("greet", vec![Value::String("hi".into())])
+                .unwrap(),
+            Value::String("hi, LOFT".into())
+        );
+
+        engine.eval("let total = 1 + 2;").unwrap();
+        assert_eq!(engine.get_global("total"), Some(&Value::Number(3.into())));
+
+        assert!(matches!(
+            engine.eval("let = 1;"),
+            Err(EngineError::Parse(_))
+        ));
+        assert!(matches!(
+            engine.call_function("missing", vec![]),
+            Err(EngineError::Runtime(_))
+        ));
+    }
+}
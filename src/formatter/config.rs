@@ -0,0 +1,158 @@
+//! Formatter settings, read from `.loftfmt.json` or the `format` section of `manifest.json`.
+//!
+//! ```json
+//! {
+//!     "indent_width": 2,
+//!     "use_tabs": false,
+//!     "max_width": 100,
+//!     "trailing_commas": "always",
+//!     "brace_style": "next_line"
+//! }
+//! ```
+//!
+//! Every key is optional and falls back to its default.
+
+use serde::{Deserialize, Serialize};
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+/// Name of the formatter configuration file
+pub const CONFIG_FILE: &str = ".loftfmt.json";
+
+/// What to do with the comma after the last item of a multi-line list
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrailingCommas {
+    /// Keep the comma if the source has one
+    #[default]
+    Preserve,
+    Always,
+    Never,
+}
+
+/// Where the opening brace of a block goes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BraceStyle {
+    /// `fn main() {`
+    #[default]
+    SameLine,
+    /// The brace on a line of its own below the declaration or statement
+    NextLine,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FormatConfig {
+    /// Spaces per indentation level
+    pub indent_width: usize,
+    /// Indent with tabs instead of spaces
+    pub use_tabs: bool,
+    /// Maximum line width
+    pub max_width: usize,
+    pub trailing_commas: TrailingCommas,
+    pub brace_style: BraceStyle,
+}
+
+impl Default for FormatConfig {
+    fn default() -> Self {
+        Self {
+            indent_width: 4,
+            use_tabs: false,
+            max_width: 100,
+            trailing_commas: TrailingCommas::default(),
+            brace_style: BraceStyle::default(),
+        }
+    }
+}
+
+impl FormatConfig {
+    /// Parse settings from JSON
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| e.to_string())
+    }
+
+    /// Indentation for `level` levels of nesting
+    pub fn indent(&self, level: usize) -> String {
+        if self.use_tabs {
+            "\t".repeat(level)
+        } else {
+            " ".repeat(level * self.indent_width)
+        }
+    }
+
+    /// Settings for files in `dir`: the nearest `.loftfmt.json`, or the `format`
+    /// section of the project's manifest.json. The search stops at the project root.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn find(dir: &Path) -> Result<Option<Self>, String> {
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+        for dir in dir.ancestors() {
+            let config_path = dir.join(CONFIG_FILE);
+            if config_path.is_file() {
+                let content = std::fs::read_to_string(&config_path)
+                    .map_err(|e| format!("{}: {}", config_path.display(), e))?;
+                return Self::from_json(&content)
+                    .map(Some)
+                    .map_err(|e| format!("{}: {}", config_path.display(), e));
+            }
+
+            let manifest_path = dir.join("manifest.json");
+            if manifest_path.is_file() {
+                let content = std::fs::read_to_string(&manifest_path)
+                    .map_err(|e| format!("{}: {}", manifest_path.display(), e))?;
+                let manifest: serde_json::Value = serde_json::from_str(&content)
+                    .map_err(|e| format!("{}: {}", manifest_path.display(), e))?;
+                return match manifest.get("format") {
+                    Some(section) => serde_json::from_value(section.clone())
+                        .map(Some)
+                        .map_err(|e| format!("{}: format: {}", manifest_path.display(), e)),
+                    None => Ok(None),
+                };
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_config_file_and_manifest_section() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("src");
+        std::fs::create_dir(&nested).unwrap();
+        std::fs::write(
+            dir.path().join("manifest.json"),
+            r#"{ "name": "app", "version": "0.1.0", "entrypoint": "src/main.lf", "format": { "use_tabs": true } }"#,
+        )
+        .unwrap();
+
+        let config = FormatConfig::find(&nested).unwrap().unwrap();
+        assert!(config.use_tabs);
+        assert_eq!(config.indent_width, 4);
+        assert_eq!(config.indent(2), "\t\t");
+
+        // A .loftfmt.json takes precedence over the manifest
+        std::fs::write(
+            nested.join(CONFIG_FILE),
+            r#"{ "indent_width": 2, "brace_style": "next_line" }"#,
+        )
+        .unwrap();
+        let config = FormatConfig::find(&nested).unwrap().unwrap();
+        assert!(!config.use_tabs);
+        assert_eq!(config.brace_style, BraceStyle::NextLine);
+        assert_eq!(config.indent(2), "    ");
+
+        std::fs::write(nested.join(CONFIG_FILE), r#"{ "indent": 2 }"#).unwrap();
+        assert!(FormatConfig::find(&nested)
+            .unwrap_err()
+            .contains("unknown field"));
+    }
+}
@@ -0,0 +1,182 @@
+//! Unified diffs between a file and its formatted version, for `loft format --diff`.
+
+/// Unchanged lines shown around each change
+const CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    /// Line `.0` of the old text is line `.1` of the new one
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// The shortest edit script turning `old` into `new`, found with Myers' algorithm
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max = (n + m) as usize;
+    let offset = max as isize;
+    let mut v = vec![0isize; 2 * max + 2];
+    // Furthest reaching paths before each round, to walk back through
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    let prefers_down = |v: &[isize], k: isize, d: isize| {
+        let index = (k + offset) as usize;
+        k == -d || (k != d && v[index - 1] < v[index + 1])
+    };
+
+    'search: for d in 0..=max as isize {
+        trace.push(v.clone());
+        for k in (-d..=d).step_by(2) {
+            let mut x = if prefers_down(&v, k, d) {
+                v[(k + 1 + offset) as usize]
+            } else {
+                v[(k - 1 + offset) as usize] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[(k + offset) as usize] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let prev_k = if prefers_down(v, k, d) { k + 1 } else { k - 1 };
+        let prev_x = v[(prev_k + offset) as usize];
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            edits.push(Edit::Equal(x as usize, y as usize));
+        }
+        if d > 0 {
+            if x == prev_x {
+                y -= 1;
+                edits.push(Edit::Insert(y as usize));
+            } else {
+                x -= 1;
+                edits.push(Edit::Delete(x as usize));
+            }
+        }
+    }
+    edits.reverse();
+    edits
+}
+
+/// `start,count` of a hunk header, leaving out a count of one like `diff -u` does
+fn hunk_range(start: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+/// A unified diff from `old` to `new`, or an empty string if they have the
+/// same lines. `path` names the file in the `---`/`+++` header.
+pub fn unified_diff(old: &str, new: &str, path: &str) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    if old_lines == new_lines {
+        return String::new();
+    }
+    let edits = diff_lines(&old_lines, &new_lines);
+
+    // Where each edit starts in the old and the new text
+    let mut positions = Vec::with_capacity(edits.len());
+    let (mut old_at, mut new_at) = (0, 0);
+    for edit in &edits {
+        positions.push((old_at, new_at));
+        match edit {
+            Edit::Equal(..) => {
+                old_at += 1;
+                new_at += 1;
+            }
+            Edit::Delete(_) => old_at += 1,
+            Edit::Insert(_) => new_at += 1,
+        }
+    }
+
+    let path = path.trim_start_matches('/');
+    let mut out = format!("--- a/{}\n+++ b/{}\n", path, path);
+    let mut i = 0;
+    while i < edits.len() {
+        if matches!(edits[i], Edit::Equal(..)) {
+            i += 1;
+            continue;
+        }
+
+        // Changes closer than twice the context share a hunk
+        let start = i.saturating_sub(CONTEXT);
+        let mut last_change = i;
+        for (j, edit) in edits.iter().enumerate().skip(i + 1) {
+            if !matches!(edit, Edit::Equal(..)) {
+                last_change = j;
+            } else if j - last_change > 2 * CONTEXT {
+                break;
+            }
+        }
+        let end = (last_change + CONTEXT + 1).min(edits.len());
+
+        let hunk = &edits[start..end];
+        let old_count = hunk
+            .iter()
+            .filter(|edit| !matches!(edit, Edit::Insert(_)))
+            .count();
+        let new_count = hunk
+            .iter()
+            .filter(|edit| !matches!(edit, Edit::Delete(_)))
+            .count();
+        let (old_start, new_start) = positions[start];
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_start, old_count),
+            hunk_range(new_start, new_count)
+        ));
+        for edit in hunk {
+            let (marker, line) = match *edit {
+                Edit::Equal(x, _) => (' ', old_lines[x]),
+                Edit::Delete(x) => ('-', old_lines[x]),
+                Edit::Insert(y) => ('+', new_lines[y]),
+            };
+            out.push(marker);
+            out.push_str(line);
+            out.push('\n');
+        }
+        i = end;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unified_diff() {
+        assert_eq!(unified_diff("let x = 1;\n", "let x = 1;\n", "same.lf"), "");
+
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm\nn\n";
+        assert_eq!(
+            unified_diff(old, new, "main.lf"),
+            "--- a/main.lf\n+++ b/main.lf\n\
+             @@ -1,5 +1,5 @@\n a\n-b\n+B\n c\n d\n e\n\
+             @@ -11,3 +11,4 @@\n k\n l\n m\n+n\n"
+        );
+
+        assert_eq!(
+            unified_diff("", "fn main() {}\n", "new.lf"),
+            "--- a/new.lf\n+++ b/new.lf\n@@ -0,0 +1 @@\n+fn main() {}\n"
+        );
+    }
+}
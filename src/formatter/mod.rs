@@ -1,6 +1,9 @@
-use crate::parser::{Expr, Stmt, TemplatePart, TraitMethod, Type};
+use crate::parser::{Expr, ExprKind, Stmt, StmtKind, TemplatePart, TraitMethod, Type};
 
+pub mod config;
+pub mod diff;
 mod token_formatter;
+pub use config::FormatConfig;
 pub use token_formatter::TokenFormatter;
 
 /// Formatter for loft source code.
@@ -42,11 +45,34 @@ impl Formatter {
 
     fn format_stmt(&self, stmt: &Stmt, level: usize) -> String {
         let indent = self.indent(level);
-        match stmt {
-            Stmt::ImportDecl { path } => {
-                format!("{}learn \"{}\";", indent, path.join("/"))
+        match &stmt.kind {
+            StmtKind::ImportDecl {
+                path,
+                alias,
+                items,
+                is_exported,
+            } => {
+                let export = if *is_exported { "teach " } else { "" };
+                let module = format!("\"{}\"", path.join("/"));
+                match (items, alias) {
+                    (Some(items), _) => {
+                        let items = items
+                            .iter()
+                            .map(|item| match &item.alias {
+                                Some(alias) => format!("{} as {}", item.name, alias),
+                                None => item.name.clone(),
+                            })
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        format!("{}{}learn {{ {} }} from {};", indent, export, items, module)
+                    }
+                    (None, Some(alias)) => {
+                        format!("{}{}learn {} as {};", indent, export, module, alias)
+                    }
+                    (None, None) => format!("{}{}learn {};", indent, export, module),
+                }
             }
-            Stmt::VarDecl {
+            StmtKind::VarDecl {
                 name,
                 var_type,
                 mutable,
@@ -66,7 +92,7 @@ impl Formatter {
                     indent, mut_kw, name, type_annotation, val
                 )
             }
-            Stmt::ConstDecl {
+            StmtKind::ConstDecl {
                 name,
                 const_type,
                 value,
@@ -83,10 +109,11 @@ impl Formatter {
                     self.format_expr(value)
                 )
             }
-            Stmt::FunctionDecl {
+            StmtKind::FunctionDecl {
                 name,
                 type_params,
                 params,
+                variadic,
                 return_type,
                 body,
                 is_async,
@@ -99,9 +126,14 @@ impl Formatter {
                 } else {
                     String::new()
                 };
+                let rest = params.len().saturating_sub(*variadic as usize);
                 let params_str = params
                     .iter()
-                    .map(|(n, t)| format!("{}: {}", n, self.format_type(t)))
+                    .enumerate()
+                    .map(|(i, (n, t))| {
+                        let spread = if i == rest { "..." } else { "" };
+                        format!("{}{}: {}", spread, n, self.format_type(t))
+                    })
                     .collect::<Vec<_>>()
                     .join(", ");
                 let return_str = match return_type {
@@ -122,7 +154,16 @@ impl Formatter {
                     body_str.trim_start()
                 )
             }
-            Stmt::StructDecl { name, fields } => {
+            StmtKind::StructDecl {
+                name,
+                type_params,
+                fields,
+            } => {
+                let name = if type_params.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}<{}>", name, type_params.join(", "))
+                };
                 if fields.is_empty() {
                     format!("{}def {} {{}}", indent, name)
                 } else {
@@ -136,7 +177,7 @@ impl Formatter {
                     format!("{}def {} {{\n{}\n{}}}", indent, name, fields_str, indent)
                 }
             }
-            Stmt::ImplBlock {
+            StmtKind::ImplBlock {
                 type_name,
                 trait_name,
                 methods,
@@ -155,7 +196,7 @@ impl Formatter {
                     indent, trait_part, type_name, methods_str, indent
                 )
             }
-            Stmt::TraitDecl { name, methods } => {
+            StmtKind::TraitDecl { name, methods } => {
                 let methods_str = methods
                     .iter()
                     .map(|m| self.format_trait_method(m, level + 1))
@@ -163,7 +204,7 @@ impl Formatter {
                     .join("\n\n");
                 format!("{}trait {} {{\n{}\n{}}}", indent, name, methods_str, indent)
             }
-            Stmt::EnumDecl { name, variants } => {
+            StmtKind::EnumDecl { name, variants } => {
                 let variants_str = variants
                     .iter()
                     .map(|(n, types)| {
@@ -182,10 +223,10 @@ impl Formatter {
                     .join("\n");
                 format!("{}enum {} {{\n{}\n{}}}", indent, name, variants_str, indent)
             }
-            Stmt::Assign { name, value } => {
+            StmtKind::Assign { name, value } => {
                 format!("{}{} = {};", indent, name, self.format_expr(value))
             }
-            Stmt::AttrStmt { attr, stmt } => {
+            StmtKind::AttrStmt { attr, stmt } => {
                 let attr_args = if attr.args.is_empty() {
                     String::new()
                 } else {
@@ -201,7 +242,7 @@ impl Formatter {
                 let attr_str = format!("{}#[{}{}]", indent, attr.name, attr_args);
                 format!("{}\n{}", attr_str, self.format_stmt(stmt, level))
             }
-            Stmt::If {
+            StmtKind::If {
                 condition,
                 then_branch,
                 else_branch,
@@ -219,7 +260,7 @@ impl Formatter {
                     else_str
                 )
             }
-            Stmt::While { condition, body } => {
+            StmtKind::While { condition, body } => {
                 let body_str = self.format_stmt(body, level);
                 format!(
                     "{}while ({}) {}",
@@ -228,7 +269,7 @@ impl Formatter {
                     body_str.trim_start()
                 )
             }
-            Stmt::For {
+            StmtKind::For {
                 var,
                 iterable,
                 body,
@@ -242,7 +283,7 @@ impl Formatter {
                     body_str.trim_start()
                 )
             }
-            Stmt::Match { expr, arms } => {
+            StmtKind::Match { expr, arms } => {
                 let arms_str = arms
                     .iter()
                     .map(|(pattern, stmt)| {
@@ -265,19 +306,50 @@ impl Formatter {
                     indent
                 )
             }
-            Stmt::Return(expr) => {
+            StmtKind::Return(expr) => {
                 if let Some(e) = expr {
                     format!("{}return {};", indent, self.format_expr(e))
                 } else {
                     format!("{}return;", indent)
                 }
             }
-            Stmt::Break => format!("{}break;", indent),
-            Stmt::Continue => format!("{}continue;", indent),
-            Stmt::Expr(expr) => {
+            StmtKind::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                let mut out = format!(
+                    "{}try {}",
+                    indent,
+                    self.format_stmt(body, level).trim_start()
+                );
+                if let Some((var, catch_body)) = catch {
+                    let binding = var
+                        .as_ref()
+                        .map(|v| format!("({}) ", v))
+                        .unwrap_or_default();
+                    out.push_str(&format!(
+                        " catch {}{}",
+                        binding,
+                        self.format_stmt(catch_body, level).trim_start()
+                    ));
+                }
+                if let Some(finally) = finally {
+                    out.push_str(&format!(
+                        " finally {}",
+                        self.format_stmt(finally, level).trim_start()
+                    ));
+                }
+                out
+            }
+            StmtKind::Throw(expr) => format!("{}throw {};", indent, self.format_expr(expr)),
+            StmtKind::Defer(expr) => format!("{}defer {};", indent, self.format_expr(expr)),
+            StmtKind::Break => format!("{}break;", indent),
+            StmtKind::Continue => format!("{}continue;", indent),
+            StmtKind::Expr(expr) => {
                 format!("{}{};", indent, self.format_expr(expr))
             }
-            Stmt::Block(stmts) => {
+            StmtKind::Block(stmts) => {
                 if stmts.is_empty() {
                     format!("{}{{}}", indent)
                 } else {
@@ -293,12 +365,12 @@ impl Formatter {
     }
 
     fn format_expr(&self, expr: &Expr) -> String {
-        match expr {
-            Expr::Number(n) => n.to_string(),
-            Expr::Ident(s) => s.clone(),
-            Expr::String(s) => format!("\"{}\"", s.replace('"', "\\\"")),
-            Expr::Boolean(b) => b.to_string(),
-            Expr::BinOp { op, left, right } => {
+        match &expr.kind {
+            ExprKind::Number(n) => n.to_string(),
+            ExprKind::Ident(s) | ExprKind::Local { name: s, .. } => s.clone(),
+            ExprKind::String(s) => format!("\"{}\"", s.replace('"', "\\\"")),
+            ExprKind::Boolean(b) => b.to_string(),
+            ExprKind::BinOp { op, left, right } => {
                 format!(
                     "{} {} {}",
                     self.format_expr(left),
@@ -306,10 +378,10 @@ impl Formatter {
                     self.format_expr(right)
                 )
             }
-            Expr::UnaryOp { op, expr } => {
+            ExprKind::UnaryOp { op, expr } => {
                 format!("{}{}", op, self.format_expr(expr))
             }
-            Expr::Call { func, args } => {
+            ExprKind::Call { func, args } => {
                 let args_str = args
                     .iter()
                     .map(|a| self.format_expr(a))
@@ -317,10 +389,10 @@ impl Formatter {
                     .join(", ");
                 format!("{}({})", self.format_expr(func), args_str)
             }
-            Expr::FieldAccess { object, field } => {
+            ExprKind::FieldAccess { object, field } => {
                 format!("{}.{}", self.format_expr(object), field)
             }
-            Expr::ArrayLiteral(items) => {
+            ExprKind::ArrayLiteral(items) => {
                 let items_str = items
                     .iter()
                     .map(|i| self.format_expr(i))
@@ -328,7 +400,15 @@ impl Formatter {
                     .join(", ");
                 format!("[{}]", items_str)
             }
-            Expr::StructLiteral { name, fields } => {
+            ExprKind::MapLiteral(entries) => {
+                let entries_str = entries
+                    .iter()
+                    .map(|(k, v)| format!("{}: {}", self.format_expr(k), self.format_expr(v)))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("#{{ {} }}", entries_str)
+            }
+            ExprKind::StructLiteral { name, fields } => {
                 let fields_str = fields
                     .iter()
                     .map(|(n, e)| format!("{}: {}", n, self.format_expr(e)))
@@ -336,10 +416,21 @@ impl Formatter {
                     .join(", ");
                 format!("{} {{ {} }}", name, fields_str)
             }
-            Expr::Index { array, index } => {
+            ExprKind::Index { array, index } => {
                 format!("{}[{}]", self.format_expr(array), self.format_expr(index))
             }
-            Expr::Lambda {
+            ExprKind::Slice { array, start, end } => {
+                let bound = |b: &Option<Box<Expr>>| {
+                    b.as_ref().map(|b| self.format_expr(b)).unwrap_or_default()
+                };
+                format!(
+                    "{}[{}..{}]",
+                    self.format_expr(array),
+                    bound(start),
+                    bound(end)
+                )
+            }
+            ExprKind::Lambda {
                 params,
                 return_type,
                 body,
@@ -365,7 +456,7 @@ impl Formatter {
                     .unwrap_or_default();
                 format!("{}{} => {}", params_str, return_str, self.format_expr(body))
             }
-            Expr::Block(stmts) => {
+            ExprKind::Block(stmts) => {
                 if stmts.is_empty() {
                     "{}".to_string()
                 } else {
@@ -377,21 +468,26 @@ impl Formatter {
                     format!("{{\n{}\n}}", stmts_str)
                 }
             }
-            Expr::Await(expr) => format!("await {}", self.format_expr(expr)),
-            Expr::Async(expr) => format!("async {}", self.format_expr(expr)),
-            Expr::Lazy(expr) => format!("lazy {}", self.format_expr(expr)),
-            Expr::TemplateLiteral { parts } => {
+            ExprKind::Await(expr) => format!("await {}", self.format_expr(expr)),
+            ExprKind::Async(expr) => format!("async {}", self.format_expr(expr)),
+            ExprKind::Lazy(expr) => format!("lazy {}", self.format_expr(expr)),
+            ExprKind::TemplateLiteral { parts } => {
                 let parts_str = parts
                     .iter()
                     .map(|p| match p {
                         TemplatePart::Text(t) => t.clone(),
-                        TemplatePart::Expression(e) => format!("${{{}}}", self.format_expr(e)),
+                        TemplatePart::Expression(e, None) => {
+                            format!("${{{}}}", self.format_expr(e))
+                        }
+                        TemplatePart::Expression(e, Some(spec)) => {
+                            format!("${{{}:{}}}", self.format_expr(e), spec)
+                        }
                     })
                     .collect::<Vec<_>>()
                     .join("");
                 format!("`{}`", parts_str)
             }
-            Expr::Match { expr, arms } => {
+            ExprKind::Match { expr, arms } => {
                 let arms_str = arms
                     .iter()
                     .map(|(pattern, body)| {
@@ -405,7 +501,24 @@ impl Formatter {
                     .join("\n");
                 format!("match {} {{\n{}\n}}", self.format_expr(expr), arms_str)
             }
-            Expr::Try(expr) => format!("{}?", self.format_expr(expr)),
+            ExprKind::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                let else_str = else_branch
+                    .as_ref()
+                    .map(|e| format!(" else {}", self.format_expr(e)))
+                    .unwrap_or_default();
+                format!(
+                    "if ({}) {}{}",
+                    self.format_expr(condition),
+                    self.format_expr(then_branch),
+                    else_str
+                )
+            }
+            ExprKind::Try(expr) => format!("{}?", self.format_expr(expr)),
+            ExprKind::Spread(expr) => format!("...{}", self.format_expr(expr)),
         }
     }
 
@@ -1,6 +1,6 @@
+use super::config::{BraceStyle, FormatConfig, TrailingCommas};
 use crate::parser::input_stream::InputStream;
-use crate::parser::token_stream::Token;
-use rust_decimal::Decimal;
+use crate::parser::token_stream::{parse_number_literal, read_number_literal, Token};
 
 /// Token-based formatter that preserves comments and handles parse errors.
 ///
@@ -9,7 +9,7 @@ use rust_decimal::Decimal;
 /// - Format code even when there are parse errors
 /// - Maintain more control over whitespace and formatting
 pub struct TokenFormatter {
-    indent_size: usize,
+    config: FormatConfig,
 }
 
 impl Default for TokenFormatter {
@@ -20,13 +20,51 @@ impl Default for TokenFormatter {
 
 impl TokenFormatter {
     pub fn new() -> Self {
-        Self { indent_size: 4 }
+        Self::with_config(FormatConfig::default())
+    }
+
+    /// A formatter using the given indentation, trailing comma and brace settings
+    pub fn with_config(config: FormatConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &FormatConfig {
+        &self.config
     }
 
     /// Format source code using token-based approach
     pub fn format(&self, source: &str) -> Result<String, String> {
         let tokens = self.tokenize_with_comments(source)?;
-        Ok(self.format_tokens(&tokens))
+        Ok(self.format_tokens(&tokens, 0))
+    }
+
+    /// Format lines `start..=end` of `source`, indented for the blocks they are nested in.
+    /// Returns the text that replaces those lines, without a trailing newline.
+    pub fn format_lines(&self, source: &str, start: usize, end: usize) -> Result<String, String> {
+        let lines: Vec<&str> = source.lines().collect();
+        if start > end || end >= lines.len() {
+            return Err(format!(
+                "Lines {}..{} are outside the document",
+                start + 1,
+                end + 1
+            ));
+        }
+
+        let selected = lines[start..=end].join("\n");
+        if selected.trim().is_empty() {
+            return Ok(selected);
+        }
+
+        // Blocks opened before the selection and not yet closed
+        let before = self.tokenize_with_comments(&lines[..start].join("\n"))?;
+        let depth = before.iter().fold(0usize, |depth, tw| match &tw.token {
+            Token::Punct(p) if p == "{" => depth + 1,
+            Token::Punct(p) if p == "}" => depth.saturating_sub(1),
+            _ => depth,
+        });
+
+        let tokens = self.tokenize_with_comments(&selected)?;
+        Ok(self.format_tokens(&tokens, depth).trim_end().to_string())
     }
 
     /// Tokenize source code while preserving all comments
@@ -34,6 +72,24 @@ impl TokenFormatter {
         let mut tokens = Vec::new();
         let source_string = source.to_string();
         let mut input = InputStream::new("formatter", &source_string);
+        // The input yields bytes, so text that may hold non-ASCII characters is
+        // copied from the source instead of collected from the stream
+        let slice = |input: &InputStream, start| {
+            let span = input.span_from(start);
+            &source[span.start..span.end]
+        };
+
+        // A `#!` line is kept as written, like a comment
+        if source.starts_with("#!") {
+            let pos = input.save_position();
+            while input.peek().is_some_and(|c| c != '\n') {
+                input.next();
+            }
+            tokens.push(TokenWithWhitespace {
+                token: Token::Comment(slice(&input, pos).to_string()),
+                leading_whitespace: String::new(),
+            });
+        }
 
         while !input.eof() {
             // Track position before skipping whitespace
@@ -67,19 +123,15 @@ impl TokenFormatter {
                             input.next(); // consume third '/'
                         }
 
-                        let mut comment_text = String::from("//");
-                        if is_doc {
-                            comment_text.push('/');
-                        }
-
                         while !input.eof() && input.peek().unwrap() != '\n' {
-                            comment_text.push(input.next().unwrap());
+                            input.next();
                         }
+                        let comment_text = slice(&input, pos);
 
                         let token = if is_doc {
                             Token::DocComment(comment_text[3..].trim().to_string())
                         } else {
-                            Token::Comment(comment_text)
+                            Token::Comment(comment_text.to_string())
                         };
 
                         tokens.push(TokenWithWhitespace {
@@ -97,21 +149,16 @@ impl TokenFormatter {
                             input.next(); // consume third '*'
                         }
 
-                        let mut comment_text = String::from("/*");
-                        if is_doc {
-                            comment_text.push('*');
-                        }
-
                         let mut found_end = false;
                         while !input.eof() {
                             let c = input.next().unwrap();
-                            comment_text.push(c);
                             if c == '*' && !input.eof() && input.peek().unwrap() == '/' {
-                                comment_text.push(input.next().unwrap());
+                                input.next();
                                 found_end = true;
                                 break;
                             }
                         }
+                        let comment_text = slice(&input, pos);
 
                         if !found_end {
                             return Err("Unterminated block comment".to_string());
@@ -122,7 +169,7 @@ impl TokenFormatter {
                                 comment_text[3..comment_text.len() - 2].trim().to_string(),
                             )
                         } else {
-                            Token::Comment(comment_text)
+                            Token::Comment(comment_text.to_string())
                         };
 
                         tokens.push(TokenWithWhitespace {
@@ -138,7 +185,7 @@ impl TokenFormatter {
             }
 
             // Parse regular token
-            if let Some(token) = self.read_token(&mut input)? {
+            if let Some(token) = self.read_token(&mut input, source)? {
                 tokens.push(TokenWithWhitespace {
                     token,
                     leading_whitespace: whitespace,
@@ -150,7 +197,7 @@ impl TokenFormatter {
     }
 
     /// Read a single token from the input
-    fn read_token(&self, input: &mut InputStream) -> Result<Option<Token>, String> {
+    fn read_token(&self, input: &mut InputStream, source: &str) -> Result<Option<Token>, String> {
         if input.eof() {
             return Ok(None);
         }
@@ -158,35 +205,55 @@ impl TokenFormatter {
         let c = input.peek().unwrap();
 
         // Numbers
-        if c.is_numeric() {
+        if c.is_ascii_digit() {
             return Ok(Some(self.read_number(input)?));
         }
 
-        // Strings
+        // Strings keep their escapes as written
         if c == '"' {
+            let start = input.save_position();
             input.next();
-            let mut s = String::new();
             let mut escaped = false;
-            while !input.eof() {
-                let ch = input.next().unwrap();
-                if escaped {
-                    s.push(ch);
-                    escaped = false;
-                } else if ch == '\\' {
-                    escaped = true;
-                } else if ch == '"' {
-                    break;
-                } else {
-                    s.push(ch);
+            loop {
+                match input.next() {
+                    None => return Err("Unterminated string".to_string()),
+                    Some(_) if escaped => escaped = false,
+                    Some('\\') => escaped = true,
+                    Some('"') => break,
+                    Some(_) => {}
                 }
             }
-            return Ok(Some(Token::String(s)));
+            let span = input.span_from(start);
+            return Ok(Some(Token::String(
+                source[span.start + 1..span.end - 1].to_string(),
+            )));
         }
 
-        // Template literals
+        // Template literals are kept as written, interpolations included
         if c == '`' {
+            let start = input.save_position();
             input.next();
-            return Ok(Some(Token::TemplateStart));
+            let mut depth = 0;
+            loop {
+                match input.next() {
+                    None => return Err("Unterminated template literal".to_string()),
+                    Some('\\') => {
+                        input.next();
+                    }
+                    Some('`') if depth == 0 => break,
+                    Some('$') if depth == 0 && input.peek() == Some('{') => {
+                        input.next();
+                        depth += 1;
+                    }
+                    Some('{') if depth > 0 => depth += 1,
+                    Some('}') if depth > 0 => depth -= 1,
+                    Some(_) => {}
+                }
+            }
+            let span = input.span_from(start);
+            return Ok(Some(Token::TemplateString(
+                source[span.start..span.end].to_string(),
+            )));
         }
 
         // Identifiers and keywords
@@ -219,20 +286,12 @@ impl TokenFormatter {
         Err(format!("Unexpected character: {}", c))
     }
 
+    /// Numbers are kept as written, like strings, so `0xFF` and `1_000` survive
+    /// formatting. As text they are spaced like identifiers.
     fn read_number(&self, input: &mut InputStream) -> Result<Token, String> {
-        let mut num = String::new();
-        while !input.eof() {
-            let c = input.peek().unwrap();
-            if c.is_numeric() || c == '.' {
-                num.push(input.next().unwrap());
-            } else {
-                break;
-            }
-        }
-
-        Decimal::from_str_exact(&num)
-            .map(Token::Number)
-            .map_err(|e| format!("Invalid number: {}", e))
+        let num = read_number_literal(input);
+        parse_number_literal(&num).map_err(|e| format!("Invalid number: {}", e))?;
+        Ok(Token::Ident(num))
     }
 
     fn read_ident(&self, input: &mut InputStream) -> Token {
@@ -280,18 +339,37 @@ impl TokenFormatter {
                 | "false"
                 | "learn"
                 | "teach"
+                | "try"
+                | "catch"
+                | "finally"
+                | "throw"
+                | "defer"
         )
     }
 
     fn is_op_char(&self, c: char) -> bool {
         matches!(
             c,
-            '+' | '-' | '*' | '/' | '%' | '=' | '!' | '<' | '>' | '&' | '|' | '^' | '~' | '.' | '@'
+            '+' | '-'
+                | '*'
+                | '/'
+                | '%'
+                | '='
+                | '!'
+                | '<'
+                | '>'
+                | '&'
+                | '|'
+                | '^'
+                | '~'
+                | '.'
+                | '@'
+                | '?'
         )
     }
 
     fn is_punct_char(&self, c: char) -> bool {
-        matches!(c, ',' | ';' | ':' | '(' | ')' | '{' | '}' | '[' | ']')
+        matches!(c, ',' | ';' | ':' | '(' | ')' | '{' | '}' | '[' | ']' | '#')
     }
 
     fn is_multi_char_op(&self, s: &str) -> bool {
@@ -313,23 +391,46 @@ impl TokenFormatter {
     }
 
     /// Format tokens with proper spacing and indentation
-    fn format_tokens(&self, tokens: &[TokenWithWhitespace]) -> String {
+    fn format_tokens(&self, tokens: &[TokenWithWhitespace], mut indent_level: usize) -> String {
         let mut output = String::new();
-        let mut indent_level = 0;
         let mut at_line_start = true;
         let mut prev_token: Option<&Token> = None;
         let mut scope_stack: Vec<char> = Vec::new();
+        // Whether each open `{` holds a comma separated list, such as struct fields
+        let mut brace_lists: Vec<bool> = Vec::new();
+        let mut line_first: Option<&Token> = None;
+        let mut in_import_items = false;
 
         for (i, tw) in tokens.iter().enumerate() {
             let token = &tw.token;
 
+            // Handle extra newlines from source
+            let newlines = tw.leading_whitespace.chars().filter(|&c| c == '\n').count();
+            if newlines >= 2 && !output.is_empty() {
+                if !at_line_start {
+                    output.push('\n');
+                }
+                output.push('\n');
+                at_line_start = true;
+            }
+
             // Handle comments specially
             match token {
                 Token::Comment(text) | Token::DocComment(text) => {
+                    // A comment written after code stays on that line
+                    if newlines == 0
+                        && at_line_start
+                        && prev_token.is_some()
+                        && output.ends_with('\n')
+                    {
+                        output.pop();
+                        at_line_start = false;
+                    }
+
                     if !at_line_start {
                         output.push_str("  ");
                     } else {
-                        output.push_str(&" ".repeat(indent_level * self.indent_size));
+                        output.push_str(&self.config.indent(indent_level));
                     }
 
                     if matches!(token, Token::Comment(_)) {
@@ -347,14 +448,24 @@ impl TokenFormatter {
                 _ => {}
             }
 
-            // Handle extra newlines from source
-            let newlines = tw.leading_whitespace.chars().filter(|&c| c == '\n').count();
-            if newlines >= 2 {
-                if !at_line_start {
-                    output.push('\n');
+            // Keep the item list of `learn { a, b as c } from "m"` on one line
+            let opens_import_items = matches!(
+                (prev_token, token),
+                (Some(Token::Keyword(k)), Token::Punct(p)) if k == "learn" && p == "{"
+            );
+            if in_import_items || opens_import_items {
+                match token {
+                    Token::Punct(p) if p == "{" => in_import_items = true,
+                    Token::Punct(p) if p == "}" => in_import_items = false,
+                    _ => {}
                 }
-                output.push('\n');
-                at_line_start = true;
+                if !matches!(token, Token::Punct(p) if p == ",") {
+                    output.push(' ');
+                }
+                output.push_str(&self.token_to_string(token));
+                at_line_start = false;
+                prev_token = Some(token);
+                continue;
             }
 
             // Handle indentation decreases
@@ -368,6 +479,11 @@ impl TokenFormatter {
                     Token::Punct(p) if p == "}" => {
                         if let Some(&'{') = scope_stack.last() {
                             scope_stack.pop();
+                            if brace_lists.pop() == Some(true)
+                                && self.apply_trailing_comma(&mut output, prev_token)
+                            {
+                                at_line_start = false;
+                            }
                         }
                     }
                     Token::Punct(p) if p == "]" => {
@@ -391,8 +507,9 @@ impl TokenFormatter {
                     }
 
                 if at_line_start {
-                    output.push_str(&" ".repeat(indent_level * self.indent_size));
+                    output.push_str(&self.config.indent(indent_level));
                     at_line_start = false;
+                    line_first = Some(token);
                 }
             }
 
@@ -400,17 +517,33 @@ impl TokenFormatter {
             let token_str = self.token_to_string(token);
 
             // Add spacing before token
-            if !at_line_start && self.needs_space_before(token, prev_token) {
+            let brace_on_next_line = self.config.brace_style == BraceStyle::NextLine
+                && !at_line_start
+                && matches!(token, Token::Punct(p) if p == "{")
+                && !matches!(scope_stack.last(), Some('(' | '['))
+                && matches!(line_first, Some(Token::Keyword(k)) if self.opens_block(k));
+            if brace_on_next_line {
+                output.push('\n');
+                output.push_str(&self.config.indent(indent_level));
+                line_first = Some(token);
+            } else if !at_line_start
+                && self.needs_space_before(token, prev_token)
+                && !Self::is_negation(prev_token, i.checked_sub(2).map(|j| &tokens[j].token))
+            {
                 output.push(' ');
             } else if at_line_start {
-                output.push_str(&" ".repeat(indent_level * self.indent_size));
+                output.push_str(&self.config.indent(indent_level));
+                line_first = Some(token);
             }
 
             output.push_str(&token_str);
 
             // Update scope stack for openers
             match token {
-                Token::Punct(p) if p == "{" => scope_stack.push('{'),
+                Token::Punct(p) if p == "{" => {
+                    scope_stack.push('{');
+                    brace_lists.push(false);
+                }
                 Token::Punct(p) if p == "(" => scope_stack.push('('),
                 Token::Punct(p) if p == "[" => scope_stack.push('['),
                 _ => {}
@@ -427,9 +560,21 @@ impl TokenFormatter {
                     output.push('\n');
                     at_line_start = true;
                 }
+                // An attribute goes on its own line above what it applies to
+                Token::Punct(p)
+                    if p == "]"
+                        && matches!(line_first, Some(Token::Punct(h)) if h == "#")
+                        && !matches!(scope_stack.last(), Some('(' | '[')) =>
+                {
+                    output.push('\n');
+                    at_line_start = true;
+                }
                 Token::Punct(p) if p == "," => {
                     // Force newline if inside braces (struct fields)
                     if let Some(&'{') = scope_stack.last() {
+                        if let Some(is_list) = brace_lists.last_mut() {
+                            *is_list = true;
+                        }
                         output.push('\n');
                         at_line_start = true;
                     }
@@ -440,7 +585,10 @@ impl TokenFormatter {
                     let should_newline = !matches!(
                         next_token,
                         Some(Token::Punct(p)) if p == ";" || p == "," || p == ")"
-                    );
+                    ) && !matches!(
+                        next_token,
+                        Some(Token::Keyword(k)) if k == "else" || k == "catch" || k == "finally"
+                    ) && !matches!(next_token, Some(Token::Op(_)));
 
                     if should_newline {
                         output.push('\n');
@@ -457,7 +605,283 @@ impl TokenFormatter {
             prev_token = Some(token);
         }
 
-        output.trim_end().to_string() + "\n"
+        self.wrap_long_lines(output.trim_end()) + "\n"
+    }
+
+    /// Break lines longer than `max_width` at method chains, argument lists and array literals
+    fn wrap_long_lines(&self, output: &str) -> String {
+        let mut lines = Vec::new();
+        for (line, standalone) in output.lines().zip(self.standalone_lines(output)) {
+            if !standalone || self.width(line) <= self.config.max_width {
+                lines.push(line.to_string());
+                continue;
+            }
+
+            let code = line.trim_start();
+            let indent = &line[..line.len() - code.len()];
+            let tokens: Vec<Token> = match self.tokenize_with_comments(code) {
+                Ok(tokens) => tokens.into_iter().map(|tw| tw.token).collect(),
+                Err(_) => Vec::new(),
+            };
+            let wrappable = !tokens.is_empty()
+                && !tokens
+                    .iter()
+                    .any(|t| matches!(t, Token::Comment(_) | Token::DocComment(_)))
+                && !matches!(&tokens[0], Token::Keyword(k) if k == "learn" || k == "teach");
+            if wrappable {
+                self.wrap_line(&tokens, indent, &mut lines);
+            } else {
+                lines.push(line.to_string());
+            }
+        }
+        lines.join("\n")
+    }
+
+    /// Lay out the tokens of one line, splitting it until every part fits
+    fn wrap_line(&self, tokens: &[Token], indent: &str, lines: &mut Vec<String>) {
+        let line = format!("{}{}", indent, self.render(tokens));
+        if self.width(&line) <= self.config.max_width {
+            lines.push(line);
+            return;
+        }
+        let inner = format!("{}{}", indent, self.config.indent(1));
+
+        // A chain of method calls goes one call per line
+        let calls = self.chain_calls(tokens);
+        if calls.len() >= 2 {
+            self.wrap_line(&tokens[..calls[0]], indent, lines);
+            for (i, &start) in calls.iter().enumerate() {
+                let end = calls.get(i + 1).copied().unwrap_or(tokens.len());
+                self.wrap_line(&tokens[start..end], &inner, lines);
+            }
+            return;
+        }
+
+        // Otherwise the widest list in brackets goes one item per line
+        let Some((open, close)) = self.widest_group(tokens) else {
+            lines.push(line);
+            return;
+        };
+        self.wrap_line(&tokens[..=open], indent, lines);
+
+        let mut items = self.split_items(&tokens[open + 1..close]);
+        let had_trailing_comma = items.last().is_some_and(|item| item.is_empty());
+        if had_trailing_comma {
+            items.pop();
+        }
+        let trailing_comma = match self.config.trailing_commas {
+            TrailingCommas::Preserve => had_trailing_comma,
+            TrailingCommas::Always => true,
+            TrailingCommas::Never => false,
+        };
+        let comma = Token::Punct(",".to_string());
+        for (i, item) in items.iter().enumerate() {
+            let mut item = item.to_vec();
+            if i + 1 < items.len() || trailing_comma {
+                item.push(comma.clone());
+            }
+            self.wrap_line(&item, &inner, lines);
+        }
+
+        self.wrap_line(&tokens[close..], indent, lines);
+    }
+
+    /// Tokens joined with the spacing used by `format_tokens`
+    fn render(&self, tokens: &[Token]) -> String {
+        let mut out = String::new();
+        let (mut before, mut prev) = (None, None);
+        for token in tokens {
+            if self.needs_space_before(token, prev) && !Self::is_negation(prev, before) {
+                out.push(' ');
+            }
+            out.push_str(&self.token_to_string(token));
+            (before, prev) = (prev, Some(token));
+        }
+        out
+    }
+
+    /// Whether `prev` is a `-` negating what follows, as in `x * -y`, rather
+    /// than subtracting, judging by the token `before` it
+    fn is_negation(prev: Option<&Token>, before: Option<&Token>) -> bool {
+        if !matches!(prev, Some(Token::Op(o)) if o == "-") {
+            return false;
+        }
+        match before {
+            None => true,
+            Some(Token::Op(o)) => o != "?",
+            Some(Token::Punct(p)) => p != ")" && p != "]" && p != "}",
+            Some(Token::Keyword(k)) => k != "true" && k != "false",
+            _ => false,
+        }
+    }
+
+    /// Display width of a line, counting a tab as one level of indentation
+    fn width(&self, line: &str) -> usize {
+        line.chars()
+            .map(|c| {
+                if c == '\t' {
+                    self.config.indent_width
+                } else {
+                    1
+                }
+            })
+            .sum()
+    }
+
+    /// Positions of the `.` of each method call outside of any brackets, as in `a.b().c()`
+    fn chain_calls(&self, tokens: &[Token]) -> Vec<usize> {
+        let mut depth = 0isize;
+        let mut calls = Vec::new();
+        for (i, token) in tokens.iter().enumerate() {
+            match token {
+                Token::Punct(p) if p == "(" || p == "[" || p == "{" => depth += 1,
+                Token::Punct(p) if p == ")" || p == "]" || p == "}" => depth -= 1,
+                Token::Op(o) if o == "." && depth == 0 => {
+                    let is_call = matches!(tokens.get(i + 1), Some(Token::Ident(_)))
+                        && matches!(tokens.get(i + 2), Some(Token::Punct(p)) if p == "(");
+                    if is_call {
+                        calls.push(i);
+                    }
+                }
+                _ => {}
+            }
+        }
+        calls
+    }
+
+    /// The non-empty `(...)` or `[...]` outside of any brackets with the longest contents
+    fn widest_group(&self, tokens: &[Token]) -> Option<(usize, usize)> {
+        let mut depth = 0isize;
+        let mut open = None;
+        let mut widest: Option<(usize, usize, usize)> = None;
+        for (i, token) in tokens.iter().enumerate() {
+            match token {
+                Token::Punct(p) if p == "(" || p == "[" || p == "{" => {
+                    if depth == 0 && p != "{" {
+                        open = Some(i);
+                    }
+                    depth += 1;
+                }
+                Token::Punct(p) if p == ")" || p == "]" || p == "}" => {
+                    depth -= 1;
+                    if depth == 0 {
+                        if let Some(start) = open.take().filter(|&start| i > start + 1) {
+                            let width = self.render(&tokens[start + 1..i]).len();
+                            if widest.is_none_or(|(_, _, w)| width > w) {
+                                widest = Some((start, i, width));
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        widest.map(|(open, close, _)| (open, close))
+    }
+
+    /// Split the contents of a list at its top-level commas
+    fn split_items<'t>(&self, tokens: &'t [Token]) -> Vec<&'t [Token]> {
+        let mut depth = 0isize;
+        let mut start = 0;
+        let mut items = Vec::new();
+        for (i, token) in tokens.iter().enumerate() {
+            match token {
+                Token::Punct(p) if p == "(" || p == "[" || p == "{" => depth += 1,
+                Token::Punct(p) if p == ")" || p == "]" || p == "}" => depth -= 1,
+                Token::Punct(p) if p == "," && depth == 0 => {
+                    items.push(&tokens[start..i]);
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        items.push(&tokens[start..]);
+        items
+    }
+
+    /// For each line of `output`, whether it starts and ends outside of string
+    /// literals and block comments, so it can be tokenized on its own
+    fn standalone_lines(&self, output: &str) -> Vec<bool> {
+        let mut lines = Vec::new();
+        // The quote or `*` of the literal or comment being read
+        let mut open: Option<char> = None;
+        let mut opened_before_line = false;
+        let mut chars = output.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (open, c) {
+                (_, '\n') => {
+                    lines.push(!opened_before_line && open.is_none());
+                    opened_before_line = open.is_some();
+                }
+                (Some('"' | '`'), '\\') if chars.peek() != Some(&'\n') => {
+                    chars.next();
+                }
+                (Some('*'), '*') if chars.peek() == Some(&'/') => {
+                    chars.next();
+                    open = None;
+                }
+                (Some(quote), c) if quote == c && quote != '*' => open = None,
+                (None, '"' | '`') => open = Some(c),
+                (None, '/') if chars.peek() == Some(&'/') => {
+                    while chars.peek().is_some_and(|&c| c != '\n') {
+                        chars.next();
+                    }
+                }
+                (None, '/') if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    open = Some('*');
+                }
+                _ => {}
+            }
+        }
+        lines.push(!opened_before_line && open.is_none());
+        lines
+    }
+
+    /// Keywords that start a line whose `{` can move to the next line
+    fn opens_block(&self, keyword: &str) -> bool {
+        matches!(
+            keyword,
+            "fn" | "def"
+                | "enum"
+                | "impl"
+                | "trait"
+                | "if"
+                | "else"
+                | "while"
+                | "for"
+                | "match"
+                | "try"
+                | "catch"
+                | "finally"
+                | "teach"
+                | "async"
+        )
+    }
+
+    /// Add or remove the comma after the last item of a list that is being closed.
+    /// Returns whether the output changed.
+    fn apply_trailing_comma(&self, output: &mut String, prev: Option<&Token>) -> bool {
+        let after_comma = matches!(prev, Some(Token::Punct(p)) if p == ",");
+        let after_item = match prev {
+            None | Some(Token::Comment(_) | Token::DocComment(_)) => false,
+            Some(Token::Punct(p)) => p == "}" || p == ")" || p == "]",
+            Some(_) => true,
+        };
+
+        match self.config.trailing_commas {
+            TrailingCommas::Always if after_item => {
+                output.truncate(output.trim_end().len());
+                output.push(',');
+                true
+            }
+            TrailingCommas::Never if after_comma => {
+                output.truncate(output.trim_end().len());
+                output.pop();
+                true
+            }
+            _ => false,
+        }
     }
 
     fn token_to_string(&self, token: &Token) -> String {
@@ -474,6 +898,7 @@ impl TokenFormatter {
             Token::TemplateString(s) => s.clone(),
             Token::TemplateExprStart => "${".to_string(),
             Token::TemplateExprEnd => "}".to_string(),
+            Token::TemplateFormat(spec) => format!(":{}", spec),
             Token::TemplateEnd => "`".to_string(),
         }
     }
@@ -485,6 +910,8 @@ impl TokenFormatter {
         };
 
         match (prev, token) {
+            // No space inside `#[attr]` and `#{ map }`
+            (Token::Punct(p), _) if p == "#" => false,
             // No space after opening brackets
             (Token::Punct(p), _) if p == "(" || p == "[" || p == "{" => false,
             // No space before closing brackets or punctuation (except colons)
@@ -497,11 +924,19 @@ impl TokenFormatter {
             (Token::Punct(p), _) if p == ":" => true,
             // Space after commas
             (Token::Punct(p), _) if p == "," => true,
+            // `} else {`, `} catch (e) {` and `} finally {`
+            (Token::Punct(p), Token::Keyword(_)) if p == "}" => true,
             // No space before colons
             (_, Token::Punct(p)) if p == ":" => false,
-            // No space around dots
-            (Token::Op(o), _) if o == "." => false,
-            (_, Token::Op(o)) if o == "." => false,
+            // No space around dots and paths like `Point::new`
+            (Token::Op(o), _) if o == "." || o == "::" => false,
+            (_, Token::Op(o)) if o == "." || o == "::" => false,
+            // Space around `from` and `as` in imports
+            (Token::Punct(p), Token::Ident(i)) if p == "}" && i == "from" => true,
+            (Token::Ident(i), Token::String(_)) if i == "from" => true,
+            (Token::String(_), Token::Ident(i)) if i == "as" => true,
+            // No space before the `?` operator
+            (_, Token::Op(o)) if o == "?" => false,
             // Space around operators
             (_, Token::Op(_)) => true,
             (Token::Op(_), _) => true,
@@ -540,6 +975,13 @@ mod tests {
         assert!(formatted.contains("// inline comment"));
     }
 
+    #[test]
+    fn test_format_keeps_shebang() {
+        let formatter = TokenFormatter::new();
+        let formatted = formatter.format("#!/usr/bin/env loft\nlet x=42;").unwrap();
+        assert_eq!(formatted, "#!/usr/bin/env loft\nlet x = 42;\n");
+    }
+
     #[test]
     fn test_format_with_doc_comments() {
         let input = "/// Documentation\nfn test()->void{}";
@@ -548,4 +990,117 @@ mod tests {
 
         assert!(formatted.contains("/// Documentation"));
     }
+
+    #[test]
+    fn test_format_with_config() {
+        let input = "enum Color { Red, Green, Blue, }\nfn main() { let p = Point { x: 1, y: 2 }; }";
+        let formatter = TokenFormatter::with_config(FormatConfig {
+            use_tabs: true,
+            trailing_commas: TrailingCommas::Never,
+            ..FormatConfig::default()
+        });
+        assert_eq!(
+            formatter.format(input).unwrap(),
+            "enum Color {\n\tRed,\n\tGreen,\n\tBlue\n}\nfn main() {\n\tlet p = Point {\n\t\tx: 1,\n\t\ty: 2\n\t};\n}\n"
+        );
+
+        let formatter = TokenFormatter::with_config(FormatConfig {
+            indent_width: 2,
+            trailing_commas: TrailingCommas::Always,
+            brace_style: BraceStyle::NextLine,
+            ..FormatConfig::default()
+        });
+        let formatted = formatter.format(input).unwrap();
+        assert_eq!(
+            formatted,
+            "enum Color\n{\n  Red,\n  Green,\n  Blue,\n}\nfn main()\n{\n  let p = Point {\n    x: 1,\n    y: 2,\n  };\n}\n"
+        );
+        assert_eq!(formatter.format(&formatted).unwrap(), formatted);
+    }
+
+    #[test]
+    fn test_wrap_long_lines() {
+        let input = "let total = compute(first_argument, second_argument, third_argument);\n\
+                     let names = people.filter(is_adult).map(get_name).join(\", \");\n\
+                     let primes = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29];\n";
+        let formatter = TokenFormatter::with_config(FormatConfig {
+            max_width: 40,
+            trailing_commas: TrailingCommas::Always,
+            ..FormatConfig::default()
+        });
+        let formatted = formatter.format(input).unwrap();
+        assert_eq!(
+            formatted,
+            "let total = compute(\n    first_argument,\n    second_argument,\n    third_argument,\n);\n\
+             let names = people\n    .filter(is_adult)\n    .map(get_name)\n    .join(\", \");\n\
+             let primes = [\n    2,\n    3,\n    5,\n    7,\n    11,\n    13,\n    17,\n    19,\n    23,\n    29,\n];\n"
+        );
+        assert_eq!(formatter.format(&formatted).unwrap(), formatted);
+
+        // Strings, comments and short lines are left alone
+        let input = "let s = \"π \\\"quoted\\\" text that is longer than forty\";  // note\n";
+        assert_eq!(formatter.format(input).unwrap(), input);
+    }
+
+    #[test]
+    fn test_format_lines_inside_blocks() {
+        let source = "fn main() {\n    if (ready) {\nlet x=1;\n        }\n}\n";
+        let formatter = TokenFormatter::new();
+
+        assert_eq!(
+            formatter.format_lines(source, 2, 3).unwrap(),
+            "        let x = 1;\n    }"
+        );
+        assert_eq!(formatter.format_lines(source, 4, 4).unwrap(), "}");
+        assert!(formatter.format_lines(source, 3, 9).is_err());
+    }
+
+    #[test]
+    fn test_format_import_forms() {
+        let input = "learn {helper,other as o} from \"./utils\";\nteach learn \"./utils\"   as u;";
+        let formatter = TokenFormatter::new();
+        let formatted = formatter.format(input).unwrap();
+
+        assert_eq!(
+            formatted,
+            "learn { helper, other as o } from \"./utils\";\nteach learn \"./utils\" as u;\n"
+        );
+    }
+
+    #[test]
+    fn test_format_keeps_number_literals() {
+        let formatter = TokenFormatter::new();
+        let formatted = formatter
+            .format("let mask=0xFF&0b1010;\nlet big=1_000_000.50;\nlet mode=0o755;")
+            .unwrap();
+
+        assert_eq!(
+            formatted,
+            "let mask = 0xFF & 0b1010;\nlet big = 1_000_000.50;\nlet mode = 0o755;\n"
+        );
+        assert!(formatter.format("let x = 0xFG;").is_err());
+    }
+
+    #[test]
+    fn test_format_negation_and_slices() {
+        let formatter = TokenFormatter::new();
+        let formatted = formatter
+            .format("let last=items[ -1 ]-items[0];\nlet tail=items[1..-1];\nreturn -x*2;")
+            .unwrap();
+
+        assert_eq!(
+            formatted,
+            "let last = items[-1] - items[0];\nlet tail = items[1..-1];\nreturn -x * 2;\n"
+        );
+    }
+
+    #[test]
+    fn test_format_paths() {
+        let formatter = TokenFormatter::new();
+        let formatted = formatter
+            .format("learn std :: math;\nlet p = Point :: new(x, y);")
+            .unwrap();
+
+        assert_eq!(formatted, "learn std::math;\nlet p = Point::new(x, y);\n");
+    }
 }
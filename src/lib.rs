@@ -1,10 +1,26 @@
+// Lets code generated by the loft_builtin_macros refer to `::loft` from inside this crate too
+extern crate self as loft;
+
+pub mod analysis;
+pub mod bundle;
+pub mod codes;
 pub mod docgen;
+pub mod engine;
 pub mod formatter;
+pub mod lints;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod lsp;
+pub mod lockfile;
 pub mod manifest;
+pub mod output;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod package;
 pub mod parser;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod registry_client;
 pub mod runtime;
 
 // Re-export the loft_builtin macro for convenience
 pub use loft_builtin_macros::loft_builtin;
+
+pub use engine::{Engine, EngineError};
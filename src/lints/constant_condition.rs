@@ -0,0 +1,69 @@
+use super::{Finding, Lint};
+use crate::parser::visit::{self, Visitor};
+use crate::parser::{Expr, ExprKind, Stmt, StmtKind};
+
+/// An `if` or `while` whose condition is made of literals only, so it always
+/// takes the same branch. `while (true)` is the usual endless loop and is
+/// left alone.
+#[derive(Default)]
+pub struct ConstantCondition {
+    found: Vec<Finding>,
+}
+
+/// Whether `expr` has the same value every time it runs
+fn is_constant(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Number(_) | ExprKind::String(_) | ExprKind::Boolean(_) => true,
+        ExprKind::BinOp { left, right, .. } => is_constant(left) && is_constant(right),
+        ExprKind::UnaryOp { expr, .. } => is_constant(expr),
+        _ => false,
+    }
+}
+
+impl ConstantCondition {
+    fn check(&mut self, condition: &Expr, what: &str) {
+        if is_constant(condition) {
+            self.found.push(Finding {
+                span: condition.span,
+                message: format!("The condition of this {} never changes", what),
+                fix: None,
+            });
+        }
+    }
+}
+
+impl Visitor for ConstantCondition {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::If { condition, .. } => self.check(condition, "if"),
+            StmtKind::While { condition, .. }
+                if !matches!(condition.kind, ExprKind::Boolean(true)) =>
+            {
+                self.check(condition, "loop")
+            }
+            _ => {}
+        }
+        visit::walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let ExprKind::If { condition, .. } = &expr.kind {
+            self.check(condition, "if");
+        }
+        visit::walk_expr(self, expr);
+    }
+}
+
+impl Lint for ConstantCondition {
+    fn name(&self) -> &'static str {
+        "constant_condition"
+    }
+
+    fn description(&self) -> &'static str {
+        "An if or while condition is made of literals only, so it always goes the same way"
+    }
+
+    fn findings(&mut self) -> Vec<Finding> {
+        std::mem::take(&mut self.found)
+    }
+}
@@ -0,0 +1,52 @@
+use super::{Finding, Lint};
+use crate::parser::visit::{self, Visitor};
+use crate::parser::{Expr, ExprKind};
+
+/// `==` or `!=` on a fractional number. Numbers are exact decimals, but
+/// division rounds, so `1 / 3 * 3 == 1` is false and values that should be
+/// equal often differ in the last digit.
+#[derive(Default)]
+pub struct FloatEquality {
+    found: Vec<Finding>,
+}
+
+/// Whether `expr` is a number literal with a fractional part, or a division
+fn is_fractional(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Number(n) => !n.is_integer(),
+        ExprKind::BinOp { op, .. } => op == "/",
+        _ => false,
+    }
+}
+
+impl Visitor for FloatEquality {
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let ExprKind::BinOp { op, left, right } = &expr.kind {
+            if (op == "==" || op == "!=") && (is_fractional(left) || is_fractional(right)) {
+                self.found.push(Finding {
+                    span: expr.span,
+                    message: format!(
+                        "Fractional numbers compared with '{}' can differ by rounding, compare their difference against a small tolerance instead",
+                        op
+                    ),
+                    fix: None,
+                });
+            }
+        }
+        visit::walk_expr(self, expr);
+    }
+}
+
+impl Lint for FloatEquality {
+    fn name(&self) -> &'static str {
+        "float_equality"
+    }
+
+    fn description(&self) -> &'static str {
+        "Fractional numbers are compared with == or !="
+    }
+
+    fn findings(&mut self) -> Vec<Finding> {
+        std::mem::take(&mut self.found)
+    }
+}
@@ -0,0 +1,106 @@
+use super::{Edit, Finding, Lint, Suggestion};
+use crate::parser::visit::{self, Visitor};
+use crate::parser::{Expr, ExprKind, Stmt, StmtKind};
+
+/// A simple statement that doesn't end with `;`. The parser doesn't need
+/// one, which hides mistakes like `let total = price tax`, read as two
+/// statements. The last expression of a block is its value and is left alone,
+/// as are expressions that end with a block, like `match`, and match arms.
+#[derive(Default)]
+pub struct MissingSemicolon {
+    found: Vec<Finding>,
+}
+
+/// Whether the parser stopped at the end of `stmt` without taking a `;`
+fn unterminated(stmt: &Stmt) -> bool {
+    let ends_with = |expr: &Expr| stmt.span.end == expr.span.end;
+    match &stmt.kind {
+        StmtKind::VarDecl {
+            value: Some(value), ..
+        }
+        | StmtKind::ConstDecl { value, .. }
+        | StmtKind::Assign { value, .. }
+        | StmtKind::Return(Some(value))
+        | StmtKind::Throw(value)
+        | StmtKind::Defer(value) => ends_with(value),
+        StmtKind::Expr(expr) => {
+            !matches!(
+                expr.kind,
+                ExprKind::Block(_) | ExprKind::If { .. } | ExprKind::Match { .. }
+            ) && ends_with(expr)
+        }
+        StmtKind::Return(None) => stmt.span.len() == "return".len(),
+        StmtKind::Break => stmt.span.len() == "break".len(),
+        StmtKind::Continue => stmt.span.len() == "continue".len(),
+        _ => false,
+    }
+}
+
+impl MissingSemicolon {
+    /// Check the statements of a block, except a value it ends with
+    fn visit_block(&mut self, stmts: &[Stmt]) {
+        let Some((last, rest)) = stmts.split_last() else {
+            return;
+        };
+        rest.iter().for_each(|stmt| self.visit_stmt(stmt));
+        if let StmtKind::Expr(_) = last.kind {
+            visit::walk_stmt(self, last);
+        } else {
+            self.visit_stmt(last);
+        }
+    }
+}
+
+impl Visitor for MissingSemicolon {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Block(stmts) => return self.visit_block(stmts),
+            // An arm's body ends with `,` rather than `;`
+            StmtKind::Match { expr, arms } => {
+                self.visit_expr(expr);
+                for (pattern, body) in arms {
+                    self.visit_pattern(pattern);
+                    match body.kind {
+                        StmtKind::Block(_) => self.visit_stmt(body),
+                        _ => visit::walk_stmt(self, body),
+                    }
+                }
+                return;
+            }
+            _ => {}
+        }
+        if unterminated(stmt) {
+            self.found.push(Finding {
+                span: stmt.span,
+                message: "Missing ';' at the end of the statement".to_string(),
+                fix: Some(Suggestion {
+                    title: "Add ';'".to_string(),
+                    edit: Edit::Append(";".to_string()),
+                    preferred: true,
+                }),
+            });
+        }
+        visit::walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Block(stmts) => self.visit_block(stmts),
+            _ => visit::walk_expr(self, expr),
+        }
+    }
+}
+
+impl Lint for MissingSemicolon {
+    fn name(&self) -> &'static str {
+        "missing_semicolon"
+    }
+
+    fn description(&self) -> &'static str {
+        "A statement doesn't end with ';', so it can run into the next one"
+    }
+
+    fn findings(&mut self) -> Vec<Finding> {
+        std::mem::take(&mut self.found)
+    }
+}
@@ -0,0 +1,323 @@
+//! Lints: rules that flag code which runs but is likely a mistake.
+//!
+//! Each rule is a [`Visitor`] over the syntax tree that records what it
+//! finds. Rules warn by default, and a project can change that per rule in
+//! the `lints` section of its manifest.json:
+//!
+//! ```json
+//! { "lints": { "shadowed_variable": "allow", "float_equality": "deny" } }
+//! ```
+//!
+//! `allow` turns a rule off, `warn` reports a warning and `deny` an error.
+//! Findings are reported by `loft check` and the language server, under the
+//! code that [`crate::codes`] gives the name of the rule.
+
+mod constant_condition;
+mod float_equality;
+mod missing_semicolon;
+mod shadowed_variable;
+mod unused_result;
+
+use crate::analysis::{Diagnostic, Fix, Range, Severity, TextEdit};
+use crate::parser::visit::{self, Visitor};
+use crate::parser::{Span, Stmt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How a rule's findings are reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+}
+
+/// Levels set in the manifest, by rule name
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct LintConfig(pub HashMap<String, LintLevel>);
+
+impl LintConfig {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The level of `lint`, which is its default unless the manifest says otherwise
+    pub fn level(&self, lint: &dyn Lint) -> LintLevel {
+        self.0
+            .get(lint.name())
+            .copied()
+            .unwrap_or(lint.default_level())
+    }
+}
+
+/// Something a rule found, at the code it's about
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub span: Span,
+    pub message: String,
+    pub fix: Option<Suggestion>,
+}
+
+/// A change to the code a finding is about, offered as a quick fix
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    pub title: String,
+    pub edit: Edit,
+    /// Surely what was meant, see [`Fix::preferred`]
+    pub preferred: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Edit {
+    /// Put this text in place of the code
+    Replace(String),
+    /// Add this text right after the code
+    Append(String),
+}
+
+/// A rule, which walks a whole program and collects its findings
+pub trait Lint: Visitor {
+    /// Name used in the manifest and as the diagnostic code, e.g. `shadowed_variable`
+    fn name(&self) -> &'static str;
+
+    /// What the rule looks for, in one sentence
+    fn description(&self) -> &'static str;
+
+    fn default_level(&self) -> LintLevel {
+        LintLevel::Warn
+    }
+
+    /// Take what the rule found since it was created
+    fn findings(&mut self) -> Vec<Finding>;
+}
+
+/// A fresh instance of every rule
+pub fn all() -> Vec<Box<dyn Lint>> {
+    vec![
+        Box::new(shadowed_variable::ShadowedVariable::default()),
+        Box::new(unused_result::UnusedResult::default()),
+        Box::new(constant_condition::ConstantCondition::default()),
+        Box::new(float_equality::FloatEquality::default()),
+        Box::new(missing_semicolon::MissingSemicolon::default()),
+    ]
+}
+
+/// Whether a rule called `name` exists
+pub fn is_known(name: &str) -> bool {
+    all().iter().any(|lint| lint.name() == name)
+}
+
+/// Run every rule that isn't allowed over a parsed program
+pub fn check(stmts: &[Stmt], source: &str, config: &LintConfig) -> Vec<Diagnostic> {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut diagnostics = Vec::new();
+
+    for mut lint in all() {
+        let severity = match config.level(lint.as_ref()) {
+            LintLevel::Allow => continue,
+            LintLevel::Warn => Severity::Warning,
+            LintLevel::Deny => Severity::Error,
+        };
+        visit::walk_stmts(lint.as_mut(), stmts);
+        diagnostics.extend(lint.findings().into_iter().map(|finding| {
+            let range = Range::from_span(finding.span, &lines);
+            let diagnostic =
+                Diagnostic::new(range, severity, finding.message).with_code(lint.name());
+            let Some(suggestion) = finding.fix else {
+                return diagnostic;
+            };
+            let (range, new_text) = match suggestion.edit {
+                Edit::Replace(text) => (range, text),
+                Edit::Append(text) => (
+                    Range {
+                        start: range.end,
+                        end: range.end,
+                    },
+                    text,
+                ),
+            };
+            diagnostic.with_fix(Fix {
+                title: suggestion.title,
+                edits: vec![TextEdit { range, new_text }],
+                preferred: suggestion.preferred,
+            })
+        }));
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{InputStream, Parser};
+
+    /// The code and line of every finding in `source`
+    fn lint(source: &str, config: &LintConfig) -> Vec<(String, u32, Severity)> {
+        let source = source.to_string();
+        let stmts = Parser::new(InputStream::new("test.lf", &source))
+            .parse()
+            .unwrap();
+        check(&stmts, &source, config)
+            .into_iter()
+            .map(|d| (d.code.unwrap(), d.range.start.line, d.severity))
+            .collect()
+    }
+
+    fn names(source: &str) -> Vec<(String, u32)> {
+        lint(source, &LintConfig::default())
+            .into_iter()
+            .map(|(code, line, _)| (code, line))
+            .collect()
+    }
+
+    #[test]
+    fn test_shadowed_variable() {
+        let source = "let count = 0;
+fn add(step: num) -> num {
+    let count = step;
+    if (step > 1) {
+        let step = 1;
+    }
+    return count;
+}
+fn other() {
+    let total = 1;
+    let total = 2;
+    let f = (count) => count;
+    try { other(); } catch (e) { let e = 1; }
+}
+";
+        // Declaring a name again in the same scope is left alone
+        assert_eq!(
+            names(source),
+            [
+                ("shadowed_variable".to_string(), 2),
+                ("shadowed_variable".to_string(), 4),
+                ("shadowed_variable".to_string(), 11),
+                ("shadowed_variable".to_string(), 12),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unused_result() {
+        let source = "fn double(n: num) -> num { return n * 2; }
+fn log(n: num) { term.println(n); }
+double(2);
+log(2);
+let x = double(2);
+impl Point { fn len(self) -> num { return 1; } }
+async fn load() { }
+load();
+time.sleep(100);
+await time.sleep(100);
+term.println(1);
+";
+        assert_eq!(
+            names(source),
+            [
+                ("unused_result".to_string(), 2),
+                ("unused_result".to_string(), 7),
+                ("unused_result".to_string(), 8),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_constant_condition() {
+        let source = "if (true) { }
+while (true) { break; }
+while (1 < 2) { break; }
+let x = 1;
+if (x > 1 && false) { }
+let y = if (\"a\" == \"a\") { 1 } else { 2 };
+if (x > 1) { }
+";
+        assert_eq!(
+            names(source),
+            [
+                ("constant_condition".to_string(), 0),
+                ("constant_condition".to_string(), 2),
+                ("constant_condition".to_string(), 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_float_equality() {
+        let source = "let x = 0.1 + 0.2;
+if (x == 0.3) { }
+if (x / 3 != 1) { }
+if (x == 1) { }
+let same = x >= 0.3;
+";
+        assert_eq!(
+            names(source),
+            [
+                ("float_equality".to_string(), 1),
+                ("float_equality".to_string(), 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_missing_semicolon() {
+        let source = "let total = 1 term.println(total);
+let double = (x) => { x * 2 };
+fn f() {
+    if (total > 1) { continue }
+    break
+}
+let p = Point {
+    x: 1,
+}
+";
+        assert_eq!(
+            names(source),
+            [
+                ("missing_semicolon".to_string(), 0),
+                ("missing_semicolon".to_string(), 3),
+                ("missing_semicolon".to_string(), 4),
+                ("missing_semicolon".to_string(), 6),
+            ]
+        );
+
+        let diagnostics = check(
+            &Parser::new(InputStream::new("test.lf", &source.to_string()))
+                .parse()
+                .unwrap(),
+            source,
+            &LintConfig::default(),
+        );
+        let fixes = diagnostics.iter().flat_map(|d| &d.fixes);
+        assert_eq!(
+            crate::analysis::fixes::apply(source, fixes).0,
+            "let total = 1; term.println(total);
+let double = (x) => { x * 2 };
+fn f() {
+    if (total > 1) { continue; }
+    break;
+}
+let p = Point {
+    x: 1,
+};
+"
+        );
+    }
+
+    #[test]
+    fn test_levels_from_config() {
+        let source = "let x = 0.5;\nif (x == 0.5) { }\nif (false) { }\n";
+        let config: LintConfig =
+            serde_json::from_str(r#"{ "float_equality": "deny", "constant_condition": "allow" }"#)
+                .unwrap();
+        assert_eq!(
+            lint(source, &config),
+            [("float_equality".to_string(), 1, Severity::Error)]
+        );
+        assert!(is_known("unused_result"));
+        assert!(!is_known("unused_results"));
+    }
+}
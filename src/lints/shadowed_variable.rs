@@ -0,0 +1,119 @@
+use super::{Finding, Lint};
+use crate::parser::visit::{self, Visitor};
+use crate::parser::{Expr, ExprKind, Span, Stmt, StmtKind};
+
+/// A variable or parameter named like one of an enclosing scope, which hides
+/// the outer one so that assignments no longer reach it
+pub struct ShadowedVariable {
+    /// Names declared in each enclosing scope, innermost last
+    scopes: Vec<Vec<String>>,
+    found: Vec<Finding>,
+}
+
+impl Default for ShadowedVariable {
+    fn default() -> Self {
+        Self {
+            scopes: vec![Vec::new()],
+            found: Vec::new(),
+        }
+    }
+}
+
+impl ShadowedVariable {
+    fn declare(&mut self, name: &str, span: Span) {
+        let outer = &self.scopes[..self.scopes.len() - 1];
+        if outer.iter().flatten().any(|declared| declared == name) {
+            self.found.push(Finding {
+                span,
+                message: format!("'{}' shadows a variable of an outer scope", name),
+                fix: None,
+            });
+        }
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push(name.to_string());
+        }
+    }
+
+    /// Walk code in a scope of its own, where `names` are declared first
+    fn scoped<'a>(
+        &mut self,
+        names: impl IntoIterator<Item = &'a String>,
+        span: Span,
+        walk: impl FnOnce(&mut Self),
+    ) {
+        self.scopes.push(Vec::new());
+        for name in names {
+            self.declare(name, span);
+        }
+        walk(self);
+        self.scopes.pop();
+    }
+}
+
+impl Visitor for ShadowedVariable {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::VarDecl { name, value, .. } => {
+                value.iter().for_each(|v| self.visit_expr(v));
+                self.declare(name, stmt.span);
+            }
+            StmtKind::ConstDecl { name, value, .. } => {
+                self.visit_expr(value);
+                self.declare(name, stmt.span);
+            }
+            StmtKind::FunctionDecl { params, body, .. } => {
+                let names = params.iter().map(|(name, _)| name);
+                self.scoped(names, stmt.span, |this| this.visit_stmt(body));
+            }
+            StmtKind::For {
+                var,
+                iterable,
+                body,
+            } => {
+                self.visit_expr(iterable);
+                self.scoped([var], stmt.span, |this| this.visit_stmt(body));
+            }
+            StmtKind::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                self.visit_stmt(body);
+                if let Some((name, catch_body)) = catch {
+                    self.scoped(name, catch_body.span, |this| this.visit_stmt(catch_body));
+                }
+                finally.iter().for_each(|f| self.visit_stmt(f));
+            }
+            StmtKind::Block(_) => self.scoped([], stmt.span, |this| visit::walk_stmt(this, stmt)),
+            _ => visit::walk_stmt(self, stmt),
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Lambda { params, body, .. } => {
+                let names = params.iter().map(|(name, _)| name);
+                self.scoped(names, expr.span, |this| this.visit_expr(body));
+            }
+            ExprKind::Block(_) => self.scoped([], expr.span, |this| visit::walk_expr(this, expr)),
+            _ => visit::walk_expr(self, expr),
+        }
+    }
+
+    /// Names bound by patterns only live for their arm
+    fn visit_pattern(&mut self, _pattern: &Expr) {}
+}
+
+impl Lint for ShadowedVariable {
+    fn name(&self) -> &'static str {
+        "shadowed_variable"
+    }
+
+    fn description(&self) -> &'static str {
+        "A variable or parameter hides one of the same name from an outer scope"
+    }
+
+    fn findings(&mut self) -> Vec<Finding> {
+        std::mem::take(&mut self.found)
+    }
+}
@@ -0,0 +1,116 @@
+use super::{Finding, Lint};
+use crate::analysis::stdlib;
+use crate::parser::visit::{self, Visitor};
+use crate::parser::{ExprKind, Span, Stmt, StmtKind, Type};
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+/// A call to a function declared to return a value, made as a statement of
+/// its own so that the value is thrown away. A promise thrown away this way,
+/// like that of `time.sleep(100);`, is never waited for.
+#[derive(Default)]
+pub struct UnusedResult {
+    /// Functions declared with a return type other than void
+    returning: HashSet<String>,
+    /// Functions declared `async`, which return a promise
+    asynchronous: HashSet<String>,
+    /// Calls whose result is dropped, which may come before the declaration
+    discarded: Vec<(String, Span)>,
+}
+
+impl Visitor for UnusedResult {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::FunctionDecl {
+                name,
+                is_async: true,
+                ..
+            } => {
+                self.asynchronous.insert(name.clone());
+            }
+            StmtKind::FunctionDecl {
+                name,
+                return_type: Some(return_type),
+                ..
+            } if *return_type != Type::Named("void".to_string()) => {
+                self.returning.insert(name.clone());
+            }
+            // Methods are called on a value rather than by name
+            StmtKind::ImplBlock { methods, .. } => {
+                for method in methods {
+                    match &method.kind {
+                        StmtKind::FunctionDecl { body, .. } => self.visit_stmt(body),
+                        _ => self.visit_stmt(method),
+                    }
+                }
+                return;
+            }
+            StmtKind::Expr(expr) => {
+                if let ExprKind::Call { func, .. } = &expr.kind {
+                    match &func.kind {
+                        ExprKind::Ident(name) => {
+                            self.discarded.push((name.clone(), stmt.span));
+                        }
+                        ExprKind::FieldAccess { object, field } => {
+                            if let ExprKind::Ident(builtin) = &object.kind {
+                                let name = format!("{}.{}", builtin, field);
+                                self.discarded.push((name, stmt.span));
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+        visit::walk_stmt(self, stmt);
+    }
+}
+
+/// Whether `name`, like `time.sleep`, is a builtin function returning a promise
+fn returns_promise(name: &str) -> bool {
+    static PROMISES: OnceLock<HashSet<String>> = OnceLock::new();
+    PROMISES
+        .get_or_init(|| {
+            let mut promises = HashSet::new();
+            for (builtin, description) in stdlib::load().builtins {
+                for (method, signature) in description.methods {
+                    if signature.return_type.starts_with("Promise") {
+                        promises.insert(format!("{}.{}", builtin, method));
+                    }
+                }
+            }
+            promises
+        })
+        .contains(name)
+}
+
+impl Lint for UnusedResult {
+    fn name(&self) -> &'static str {
+        "unused_result"
+    }
+
+    fn description(&self) -> &'static str {
+        "The value or promise returned by a function call is thrown away"
+    }
+
+    fn findings(&mut self) -> Vec<Finding> {
+        std::mem::take(&mut self.discarded)
+            .into_iter()
+            .filter_map(|(name, span)| {
+                let message = if self.asynchronous.contains(&name) || returns_promise(&name) {
+                    format!("The promise returned by '{}' is never awaited", name)
+                } else if self.returning.contains(&name) {
+                    format!("The result of '{}' is never used", name)
+                } else {
+                    return None;
+                };
+                Some(Finding {
+                    span,
+                    message,
+                    fix: None,
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::manifest::ManifestError;
+#[cfg(not(target_arch = "wasm32"))]
+use std::{fs, path::Path};
+
+/// Name of the lockfile written next to manifest.json
+pub const LOCKFILE_NAME: &str = "loft.lock";
+
+/// Current lockfile format version
+const LOCKFILE_VERSION: u32 = 1;
+
+/// An exactly resolved registry dependency
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockedPackage {
+    pub name: String,
+    pub version: String,
+    /// Registry the package was downloaded from
+    pub source: String,
+    /// Checksum of the downloaded tarball, e.g. `sha256:<hex>`
+    pub checksum: String,
+}
+
+/// The contents of loft.lock
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Lockfile {
+    pub version: u32,
+    #[serde(default)]
+    pub packages: Vec<LockedPackage>,
+}
+
+impl Default for Lockfile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Lockfile {
+    pub fn new() -> Self {
+        Self {
+            version: LOCKFILE_VERSION,
+            packages: Vec::new(),
+        }
+    }
+
+    /// Load a lockfile from a file path
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ManifestError> {
+        let content =
+            fs::read_to_string(path.as_ref()).map_err(|e| ManifestError::IoError(e.to_string()))?;
+
+        let lockfile: Lockfile =
+            serde_json::from_str(&content).map_err(|e| ManifestError::ParseError(e.to_string()))?;
+
+        if lockfile.version > LOCKFILE_VERSION {
+            return Err(ManifestError::ParseError(format!(
+                "{} has format version {}, but this loft only understands version {}",
+                LOCKFILE_NAME, lockfile.version, LOCKFILE_VERSION
+            )));
+        }
+
+        Ok(lockfile)
+    }
+
+    /// Load the lockfile in `dir`, or an empty one if it does not exist yet
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_or_default<P: AsRef<Path>>(dir: P) -> Result<Self, ManifestError> {
+        let path = dir.as_ref().join(LOCKFILE_NAME);
+        if path.exists() {
+            Self::load(path)
+        } else {
+            Ok(Self::new())
+        }
+    }
+
+    /// Write the lockfile to a file path
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ManifestError> {
+        let mut content = serde_json::to_string_pretty(self)
+            .map_err(|e| ManifestError::ParseError(e.to_string()))?;
+        content.push('\n');
+        fs::write(path.as_ref(), content).map_err(|e| ManifestError::IoError(e.to_string()))
+    }
+
+    /// Look up the locked entry for a package
+    pub fn get(&self, name: &str) -> Option<&LockedPackage> {
+        self.packages.iter().find(|p| p.name == name)
+    }
+
+    /// Insert or replace the locked entry for a package, keeping entries sorted by name
+    pub fn insert(&mut self, package: LockedPackage) {
+        self.packages.retain(|p| p.name != package.name);
+        self.packages.push(package);
+        self.packages.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+
+    /// Remove the locked entry for a package
+    pub fn remove(&mut self, name: &str) -> Option<LockedPackage> {
+        let index = self.packages.iter().position(|p| p.name == name)?;
+        Some(self.packages.remove(index))
+    }
+
+    /// Drop entries for packages that are no longer dependencies
+    pub fn retain_dependencies(&mut self, dependencies: &HashMap<String, String>) {
+        self.packages.retain(|p| dependencies.contains_key(&p.name));
+    }
+}
+
+/// Compute the checksum recorded for a package tarball
+#[cfg(not(target_arch = "wasm32"))]
+pub fn checksum(data: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+
+    let digest = Sha256::digest(data);
+    let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("sha256:{}", hex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locked(name: &str, version: &str) -> LockedPackage {
+        LockedPackage {
+            name: name.to_string(),
+            version: version.to_string(),
+            source: "https://loft.fargone.sh".to_string(),
+            checksum: checksum(name.as_bytes()),
+        }
+    }
+
+    #[test]
+    fn test_insert_replaces_and_sorts() {
+        let mut lockfile = Lockfile::new();
+        lockfile.insert(locked("zeta", "1.0.0"));
+        lockfile.insert(locked("alpha", "0.1.0"));
+        lockfile.insert(locked("zeta", "1.1.0"));
+
+        let names: Vec<&str> = lockfile.packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["alpha", "zeta"]);
+        assert_eq!(lockfile.get("zeta").unwrap().version, "1.1.0");
+    }
+
+    #[test]
+    fn test_retain_dependencies() {
+        let mut lockfile = Lockfile::new();
+        lockfile.insert(locked("kept", "1.0.0"));
+        lockfile.insert(locked("dropped", "1.0.0"));
+
+        let mut deps = HashMap::new();
+        deps.insert("kept".to_string(), "^1.0.0".to_string());
+        lockfile.retain_dependencies(&deps);
+        assert!(lockfile.get("kept").is_some());
+        assert!(lockfile.get("dropped").is_none());
+    }
+
+    #[test]
+    fn test_checksum_is_sha256() {
+        assert_eq!(
+            checksum(b""),
+            "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(LOCKFILE_NAME);
+
+        let mut lockfile = Lockfile::new();
+        lockfile.insert(locked("utils", "2.0.1"));
+        lockfile.save(&path).unwrap();
+
+        assert_eq!(Lockfile::load(&path).unwrap(), lockfile);
+        assert_eq!(Lockfile::load_or_default(dir.path()).unwrap(), lockfile);
+    }
+}
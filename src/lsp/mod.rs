@@ -1,4 +1,3 @@
-use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
@@ -8,143 +7,71 @@ use tower_lsp::jsonrpc::Result;
 use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer, LspService, Server};
 
-use crate::formatter::TokenFormatter;
+use crate::analysis::docs::associate_doc_comments;
+use crate::analysis::imports::organize_imports;
+use crate::analysis::semantic::check_semantic_errors;
+use crate::analysis::stdlib::{self, StdlibTypes, StdlibVariant};
+use crate::analysis::symbols::{
+    collect_imports, extract_symbols, instantiate_fields, locate_declarations, split_generic_type,
+    type_params_to_string, SymbolInfo, SymbolKind, TraitMethodInfo,
+};
+use crate::analysis::{self, Severity};
+use crate::codes;
+use crate::formatter::{FormatConfig, TokenFormatter};
+use crate::lints::{self, LintConfig};
 use crate::manifest::Manifest;
-use crate::parser::{Expr, InputStream, Parser, Stmt, TemplatePart, Type};
-
-// Stdlib types data structures
-#[derive(Debug, Clone, Deserialize, Serialize)]
-struct StdlibTypes {
-    builtins: HashMap<String, StdlibBuiltin>,
-    string_methods: HashMap<String, StdlibMethod>,
-    array_methods: HashMap<String, StdlibMethod>,
-    #[serde(default)]
-    types: HashMap<String, StdlibType>,
-    #[serde(default)]
-    traits: HashMap<String, StdlibTrait>,
-}
-
-#[derive(Debug, Clone, Deserialize, Serialize)]
-struct StdlibTrait {
-    documentation: String,
-    #[serde(default)]
-    methods: HashMap<String, StdlibMethod>,
-}
-
-#[derive(Debug, Clone, Deserialize, Serialize)]
-struct StdlibBuiltin {
-    kind: String,
-    documentation: String,
-    #[serde(default)]
-    constants: HashMap<String, StdlibConstant>,
-    #[serde(default)]
-    methods: HashMap<String, StdlibMethod>,
-}
-
-#[derive(Debug, Clone, Deserialize, Serialize)]
-struct StdlibConstant {
-    #[serde(rename = "type")]
-    const_type: String,
-    documentation: String,
-}
-
-#[derive(Debug, Clone, Deserialize, Serialize)]
-struct StdlibMethod {
-    params: Vec<String>,
-    return_type: String,
-    documentation: String,
-}
-
-type StdlibMethodMap = HashMap<String, StdlibMethod>;
-type StdlibFieldMap = HashMap<String, StdlibField>;
-
-#[derive(Debug, Clone, Deserialize, Serialize)]
-struct StdlibType {
-    kind: String,
-    documentation: String,
-    #[serde(default)]
-    fields: StdlibFieldMap,
-    #[serde(default)]
-    methods: StdlibMethodMap,
-    #[serde(default)]
-    variants: Vec<StdlibVariant>,
-    #[serde(default)]
-    implements: Vec<String>,
-}
-
-#[derive(Debug, Clone, Deserialize, Serialize)]
-#[serde(untagged)]
-enum StdlibVariant {
-    Simple(String),
-    WithFields {
-        name: String,
-        #[serde(default)]
-        fields: Vec<String>,
-    },
-}
+use crate::parser::{InputStream, Parser, StmtKind};
 
-impl StdlibVariant {
-    fn name(&self) -> &str {
-        match self {
-            StdlibVariant::Simple(s) => s,
-            StdlibVariant::WithFields { name, .. } => name,
+impl From<analysis::Position> for Position {
+    fn from(position: analysis::Position) -> Self {
+        Position {
+            line: position.line,
+            character: position.character,
         }
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
-struct StdlibField {
-    #[serde(rename = "type")]
-    field_type: String,
-    documentation: String,
+impl From<analysis::Range> for Range {
+    fn from(range: analysis::Range) -> Self {
+        Range {
+            start: range.start.into(),
+            end: range.end.into(),
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
-struct TraitMethodInfo {
-    name: String,
-    params: Vec<(String, String)>,
-    return_type: String,
-    has_default_impl: bool,
+impl From<analysis::Diagnostic> for Diagnostic {
+    fn from(diagnostic: analysis::Diagnostic) -> Self {
+        // Fixes come back with the diagnostic when the editor asks for code actions
+        let mut data = diagnostic.data;
+        if !diagnostic.fixes.is_empty() {
+            let data = data.get_or_insert_with(|| serde_json::json!({}));
+            data["fixes"] = serde_json::to_value(&diagnostic.fixes).unwrap_or_default();
+        }
+        Diagnostic {
+            range: diagnostic.range.into(),
+            severity: Some(match diagnostic.severity {
+                Severity::Error => DiagnosticSeverity::ERROR,
+                Severity::Warning => DiagnosticSeverity::WARNING,
+                Severity::Hint => DiagnosticSeverity::HINT,
+            }),
+            // Editors show the stable code, which `loft explain` takes
+            code: diagnostic.code.map(|name| {
+                let code = codes::by_name(&name).map_or(name, |code| code.code.to_string());
+                NumberOrString::String(code)
+            }),
+            source: Some("loft".to_string()),
+            message: diagnostic.message,
+            tags: diagnostic
+                .unnecessary
+                .then(|| vec![DiagnosticTag::UNNECESSARY]),
+            data,
+            ..Default::default()
+        }
+    }
 }
 
-// Symbol information for LSP features
-#[derive(Debug, Clone)]
-struct SymbolInfo {
-    name: String,
-    kind: SymbolKind,
-    detail: Option<String>,
-    documentation: Option<String>,
-    scope_level: usize,   // Track scope level for better symbol resolution
-    range: Option<Range>, // Position in document where symbol is defined
-    selection_range: Option<Range>, // Range of just the name (for go-to-def)
-    source_uri: Option<String>, // URI of the file where this symbol is defined (for imports)
-    is_exported: bool,    // Whether this symbol is exported with `teach`
-}
 
-#[derive(Debug, Clone)]
-enum SymbolKind {
-    Variable {
-        var_type: Option<String>,
-        mutable: bool,
-    },
-    Function {
-        params: Vec<(String, String)>,
-        return_type: String,
-    },
-    Struct {
-        fields: Vec<(String, String)>,
-        methods: Vec<String>,
-    },
-    Trait {
-        methods: Vec<TraitMethodInfo>,
-    },
-    Enum {
-        variants: Vec<(String, Option<Vec<String>>)>,
-    },
-    Constant {
-        const_type: String,
-    },
-}
 
 #[derive(Debug, Clone)]
 struct DocumentData {
@@ -152,12 +79,22 @@ struct DocumentData {
     #[allow(dead_code)]
     version: i32,
     symbols: Vec<SymbolInfo>,
-    imports: Vec<Vec<String>>,         // Track imported module paths
     imported_symbols: Vec<SymbolInfo>, // Symbols imported from other modules
+    module_exports: HashMap<String, Vec<SymbolInfo>>, // Exports of modules bound to a name
     #[allow(dead_code)]
     uri: String, // The URI of this document
 }
 
+impl DocumentData {
+    /// Find a symbol imported into this document, directly or through a module binding
+    fn find_imported(&self, name: &str) -> Option<&SymbolInfo> {
+        self.imported_symbols
+            .iter()
+            .chain(self.module_exports.values().flatten())
+            .find(|s| s.name == name)
+    }
+}
+
 pub struct LoftLanguageServer {
     client: Client,
     documents: Arc<RwLock<HashMap<String, DocumentData>>>,
@@ -165,14 +102,16 @@ pub struct LoftLanguageServer {
     // Cache of file URI to physical path mappings
     #[allow(dead_code)]
     uri_to_path: Arc<RwLock<HashMap<String, PathBuf>>>,
+    /// Top-level declarations of every `.lf` file in the indexed projects, by URI
+    workspace_index: Arc<RwLock<HashMap<String, Vec<SymbolInfo>>>>,
+    /// Project roots that are indexed or being indexed
+    indexed_roots: Arc<RwLock<Vec<PathBuf>>>,
 }
 
 impl LoftLanguageServer {
     pub fn new(client: Client) -> Self {
         // Load stdlib types from embedded JSON
-        let stdlib_json = include_str!("stdlib_types.json");
-        let stdlib_types = serde_json::from_str::<StdlibTypes>(stdlib_json)
-            .expect("Failed to parse stdlib_types.json");
+        let stdlib_types = stdlib::load();
 
         // Log loaded traits for debugging
         // Note: We can't log here easily as we don't have the client yet, but we can verify it loaded
@@ -182,7 +121,38 @@ impl LoftLanguageServer {
             documents: Arc::new(RwLock::new(HashMap::new())),
             stdlib_types: Arc::new(stdlib_types),
             uri_to_path: Arc::new(RwLock::new(HashMap::new())),
+            workspace_index: Arc::new(RwLock::new(HashMap::new())),
+            indexed_roots: Arc::new(RwLock::new(Vec::new())),
+        }
+    }
+
+    /// Index every `.lf` file under `root` in the background, unless it is
+    /// already indexed
+    async fn index_project(&self, root: PathBuf) {
+        {
+            let mut roots = self.indexed_roots.write().await;
+            if roots.iter().any(|indexed| root.starts_with(indexed)) {
+                return;
+            }
+            roots.push(root.clone());
         }
+
+        self.client
+            .log_message(
+                MessageType::LOG,
+                format!("Indexing workspace symbols in {}", root.display()),
+            )
+            .await;
+
+        let index = self.workspace_index.clone();
+        let stdlib_types = self.stdlib_types.clone();
+        tokio::task::spawn_blocking(move || {
+            for file in analysis::discover_source_files(&root) {
+                if let Some((uri, symbols)) = index_file(&file, &stdlib_types) {
+                    index.blocking_write().insert(uri, symbols);
+                }
+            }
+        });
     }
 
     /// Convert URI to file system path
@@ -206,6 +176,19 @@ impl LoftLanguageServer {
         }
     }
 
+    /// The lint levels set by the manifest of the project `uri` belongs to
+    async fn lint_config(&self, uri: &Uri) -> LintConfig {
+        let Some(path) = Self::uri_to_file_path(uri) else {
+            return LintConfig::default();
+        };
+        match self.find_manifest(&path).await {
+            Some(manifest_path) => Manifest::load(&manifest_path)
+                .map(|m| m.lints)
+                .unwrap_or_default(),
+            None => LintConfig::default(),
+        }
+    }
+
     /// Resolve an import path to a file URI
     async fn resolve_import_to_uri(&self, import_path: &[String], from_uri: &Uri) -> Option<Uri> {
         // Get the file path for the importing document
@@ -265,8 +248,8 @@ impl LoftLanguageServer {
 
         match parser.parse() {
             Ok(stmts) => {
-                let mut symbols = Self::extract_symbols(&stmts, 0, &self.stdlib_types);
-                Self::associate_doc_comments(&content, &mut symbols);
+                let mut symbols = extract_symbols(&stmts, 0, &self.stdlib_types);
+                associate_doc_comments(&stmts, &mut symbols);
 
                 // Mark symbols with their source URI
                 for symbol in &mut symbols {
@@ -280,28 +263,225 @@ impl LoftLanguageServer {
         }
     }
 
+    /// Load a module's exported symbols, resolving any `teach learn` re-exports
+    async fn load_module_exports(&self, uri: &Uri) -> Vec<SymbolInfo> {
+        let mut exports = Vec::new();
+
+        for symbol in self.load_exported_symbols(uri).await {
+            let SymbolKind::Import { path, item } = &symbol.kind else {
+                exports.push(symbol);
+                continue;
+            };
+            let Some(reexport_uri) = self.resolve_import_to_uri(path, uri).await else {
+                continue;
+            };
+            let reexported = self.load_exported_symbols(&reexport_uri).await;
+            match item {
+                // `teach learn { a as b } from "m"` re-exports one item under its local name
+                Some(item) => {
+                    let matching = reexported.into_iter().filter(|s| &s.name == item);
+                    exports.extend(matching.map(|s| SymbolInfo {
+                        name: symbol.name.clone(),
+                        ..s
+                    }));
+                }
+                // `teach learn "m"` re-exports everything the module exports
+                None => exports.extend(reexported),
+            }
+        }
+
+        exports
+    }
+
     /// Resolve all imports for a document and load their exported symbols
-    async fn resolve_document_imports(&self, uri: &Uri) -> Vec<SymbolInfo> {
+    ///
+    /// Returns the items imported directly into scope, and the exports of each
+    /// module bound to a name by `learn "m"` or `learn "m" as alias`
+    async fn resolve_document_imports(
+        &self,
+        uri: &Uri,
+    ) -> (Vec<SymbolInfo>, HashMap<String, Vec<SymbolInfo>>) {
         let uri_string = uri.to_string();
-        let imports = {
+        let imports: Vec<(String, Vec<String>, Option<String>)> = {
             let docs = self.documents.read().await;
-            if let Some(doc) = docs.get(&uri_string) {
-                doc.imports.clone()
-            } else {
-                Vec::new()
+            match docs.get(&uri_string) {
+                Some(doc) => doc
+                    .symbols
+                    .iter()
+                    .filter_map(|s| match &s.kind {
+                        SymbolKind::Import { path, item } if s.scope_level == 0 => {
+                            Some((s.name.clone(), path.clone(), item.clone()))
+                        }
+                        _ => None,
+                    })
+                    .collect(),
+                None => Vec::new(),
             }
         };
 
         let mut imported_symbols = Vec::new();
+        let mut module_exports = HashMap::new();
+
+        for (name, import_path, item) in imports {
+            let Some(import_uri) = self.resolve_import_to_uri(&import_path, uri).await else {
+                continue;
+            };
+            let exports = self.load_module_exports(&import_uri).await;
+            match item {
+                Some(item) => {
+                    let matching = exports.into_iter().filter(|s| s.name == item);
+                    imported_symbols.extend(matching.map(|s| SymbolInfo {
+                        name: name.clone(),
+                        ..s
+                    }));
+                }
+                None => {
+                    module_exports.insert(name, exports);
+                }
+            }
+        }
+
+        (imported_symbols, module_exports)
+    }
+
+    /// A formatter for a document, configured by the project's `.loftfmt.json` or manifest,
+    /// or by the editor's indentation settings when the project has no configuration
+    async fn formatter_for(&self, uri: &Uri, options: &FormattingOptions) -> TokenFormatter {
+        let found = Self::uri_to_file_path(uri)
+            .and_then(|path| path.parent().map(FormatConfig::find))
+            .unwrap_or(Ok(None));
+        let config = match found {
+            Ok(Some(config)) => config,
+            Ok(None) => FormatConfig {
+                indent_width: options.tab_size as usize,
+                use_tabs: !options.insert_spaces,
+                ..FormatConfig::default()
+            },
+            Err(err) => {
+                self.client
+                    .log_message(
+                        MessageType::ERROR,
+                        format!("Invalid formatter configuration: {}", err),
+                    )
+                    .await;
+                FormatConfig::default()
+            }
+        };
+        TokenFormatter::with_config(config)
+    }
+
+    /// An edit that formats lines `start..=end` of a document, if they change
+    async fn format_lines(
+        &self,
+        uri: &Uri,
+        options: &FormattingOptions,
+        start: usize,
+        end: usize,
+    ) -> Option<Vec<TextEdit>> {
+        let content = {
+            let docs = self.documents.read().await;
+            docs.get(&uri.to_string())?.content.clone()
+        };
+        let lines: Vec<&str> = content.lines().collect();
+
+        let formatter = self.formatter_for(uri, options).await;
+        let formatted = match formatter.format_lines(&content, start, end) {
+            Ok(formatted) => formatted,
+            Err(err) => {
+                self.client
+                    .log_message(
+                        MessageType::ERROR,
+                        format!("Range formatting failed: {}", err),
+                    )
+                    .await;
+                return None;
+            }
+        };
+        if formatted == lines[start..=end].join("\n") {
+            return None;
+        }
+
+        Some(vec![TextEdit {
+            range: Range {
+                start: Position {
+                    line: start as u32,
+                    character: 0,
+                },
+                end: Position {
+                    line: end as u32,
+                    character: lines[end].encode_utf16().count() as u32,
+                },
+            },
+            new_text: formatted,
+        }])
+    }
+
+    /// The import paths of a module, from the open document or from disk
+    async fn module_imports(&self, uri: &Uri) -> Vec<Vec<String>> {
+        let open_content = {
+            let docs = self.documents.read().await;
+            docs.get(&uri.to_string()).map(|doc| doc.content.clone())
+        };
+        let content = match open_content {
+            Some(content) => content,
+            None => match Self::uri_to_file_path(uri).map(std::fs::read_to_string) {
+                Some(Ok(content)) => content,
+                _ => return Vec::new(),
+            },
+        };
+
+        let mut parser = Parser::new(InputStream::new(uri.as_str(), &content));
+        let (stmts, _) = parser.parse_recoverable();
+        collect_imports(&stmts)
+    }
+
+    /// Follow the imports of `start` looking for a chain of modules that leads back to `uri`
+    async fn find_import_cycle(&self, uri: &Uri, start: Uri) -> Option<Vec<Uri>> {
+        let mut visited = std::collections::HashSet::new();
+        // Each entry is the chain of modules from `uri` to the module to visit next
+        let mut pending = vec![vec![uri.clone(), start]];
+
+        while let Some(chain) = pending.pop() {
+            let current = chain.last()?.clone();
+            if current == *uri {
+                return Some(chain);
+            }
+            if !visited.insert(current.to_string()) {
+                continue;
+            }
 
-        for import_path in imports {
-            if let Some(import_uri) = self.resolve_import_to_uri(&import_path, uri).await {
-                let symbols = self.load_exported_symbols(&import_uri).await;
-                imported_symbols.extend(symbols);
+            for import_path in self.module_imports(&current).await {
+                if let Some(next) = self.resolve_import_to_uri(&import_path, &current).await {
+                    let mut next_chain = chain.clone();
+                    next_chain.push(next);
+                    pending.push(next_chain);
+                }
             }
         }
 
-        imported_symbols
+        None
+    }
+
+    /// The edit that organizes a document's imports, if they aren't already.
+    /// Nothing is offered while the document doesn't parse.
+    fn organize_imports_edit(&self, doc_data: &DocumentData) -> Option<TextEdit> {
+        let input_stream = InputStream::new(&doc_data.uri, &doc_data.content);
+        let stmts = Parser::new(input_stream).parse().ok()?;
+        let unused: Vec<u32> = check_semantic_errors(
+            &stmts,
+            &doc_data.symbols,
+            &doc_data.content,
+            &self.stdlib_types,
+        )
+        .into_iter()
+        .filter(|d| d.code.as_deref() == Some("unused_import"))
+        .map(|d| d.range.start.line)
+        .collect();
+        let (range, new_text) = organize_imports(&stmts, &doc_data.content, &unused)?;
+        Some(TextEdit {
+            range: Range::from(range),
+            new_text,
+        })
     }
 
     async fn parse_and_report_diagnostics(&self, uri: &Uri, content: &str) {
@@ -316,52 +496,59 @@ impl LoftLanguageServer {
         let (stmts, errors) = parser.parse_recoverable();
 
         // Extract symbols and imports from whatever statements we got
-        let mut symbols = Self::extract_symbols(&stmts, 0, &self.stdlib_types);
-        let imports = Self::extract_imports(&stmts);
+        let mut symbols = extract_symbols(&stmts, 0, &self.stdlib_types);
+        locate_declarations(&stmts, &content_string, &mut symbols);
 
-        // Extract doc comments from source and associate with symbols
-        Self::associate_doc_comments(&content_string, &mut symbols);
+        // Associate the doc comments the parser kept with symbols
+        associate_doc_comments(&stmts, &mut symbols);
 
         // Add semantic diagnostics (type checking, unused variables, etc.)
         let semantic_diagnostics =
-            Self::check_semantic_errors(&stmts, &symbols, &content_string, &self.stdlib_types);
-        diagnostics.extend(semantic_diagnostics);
+            check_semantic_errors(&stmts, &symbols, &content_string, &self.stdlib_types);
+        diagnostics.extend(semantic_diagnostics.into_iter().map(Diagnostic::from));
+
+        let lints = self.lint_config(uri).await;
+        diagnostics.extend(
+            lints::check(&stmts, &content_string, &lints)
+                .into_iter()
+                .map(Diagnostic::from),
+        );
 
         // Add parse errors
-        for err in errors {
-            // Convert parse error to LSP diagnostic with proper position
-            let error_msg = err.message.clone();
-            let line = err.line as u32;
-            let column = err.column as u32;
-
-            // Calculate end position (use length if available, otherwise just the start position + 1)
-            let end_character = if let Some(len) = err.len {
-                column + len as u32
-            } else {
-                column + 1
-            };
+        diagnostics.extend(
+            errors
+                .iter()
+                .map(|err| Diagnostic::from(analysis::Diagnostic::from_parse_error(err))),
+        );
 
-            let diagnostic = Diagnostic {
-                range: Range {
-                    start: Position {
-                        line,
-                        character: column,
-                    },
-                    end: Position {
-                        line,
-                        character: end_character,
-                    },
-                },
-                severity: Some(DiagnosticSeverity::ERROR),
-                code: None,
-                code_description: None,
-                source: Some("loft".to_string()),
-                message: error_msg,
-                related_information: None,
-                tags: None,
-                data: None,
+        // Imports that lead back to this document would recurse forever at runtime
+        let lines: Vec<&str> = content_string.lines().collect();
+        let import_ranges: Vec<(Vec<String>, analysis::Range)> = stmts
+            .iter()
+            .filter_map(|stmt| match &stmt.kind {
+                StmtKind::ImportDecl { path, .. } => {
+                    Some((path.clone(), analysis::Range::from_span(stmt.span, &lines)))
+                }
+                _ => None,
+            })
+            .collect();
+        for (import_path, range) in import_ranges {
+            let Some(import_uri) = self.resolve_import_to_uri(&import_path, uri).await else {
+                continue;
             };
-            diagnostics.push(diagnostic);
+            if let Some(cycle) = self.find_import_cycle(uri, import_uri).await {
+                let names: Vec<&str> = cycle
+                    .iter()
+                    .map(|module| module.as_str().rsplit('/').next().unwrap_or_default())
+                    .collect();
+                let diagnostic = analysis::Diagnostic::new(
+                    range,
+                    Severity::Error,
+                    format!("Circular import: {}", names.join(" -> ")),
+                )
+                .with_code("circular_import");
+                diagnostics.push(Diagnostic::from(diagnostic));
+            }
         }
 
         // Update document data with symbols and imports
@@ -372,11 +559,9 @@ impl LoftLanguageServer {
             if let Some(doc) = docs.get_mut(&uri.to_string()) {
                 if !symbols.is_empty() {
                     doc.symbols = symbols;
-                    doc.imports = imports.clone();
                 } else if diagnostics.is_empty() {
                     // If there are no errors but symbols is empty (empty file?), update it
                     doc.symbols = symbols;
-                    doc.imports = imports.clone();
                 }
                 // If there are errors and symbols is empty, we assume parsing failed and we keep the old symbols
             }
@@ -384,7 +569,7 @@ impl LoftLanguageServer {
 
         // Resolve imports and load exported symbols from imported modules
         // Also check each import to provide diagnostics for unresolved imports
-        let imported_symbols = self.resolve_document_imports(uri).await;
+        let (imported_symbols, module_exports) = self.resolve_document_imports(uri).await;
 
         // Note: Import diagnostics would require better tracking of import statement locations
         // For now, we just resolve and load the symbols
@@ -393,6 +578,7 @@ impl LoftLanguageServer {
             let mut docs = self.documents.write().await;
             if let Some(doc) = docs.get_mut(&uri.to_string()) {
                 doc.imported_symbols = imported_symbols;
+                doc.module_exports = module_exports;
             }
         }
 
@@ -402,1347 +588,54 @@ impl LoftLanguageServer {
             .await;
     }
 
-    fn associate_doc_comments(source: &str, symbols: &mut [SymbolInfo]) {
-        // Extract doc comments from source and associate with symbols by name
-        let lines: Vec<&str> = source.lines().collect();
-        let mut doc_map: std::collections::HashMap<String, String> =
-            std::collections::HashMap::new();
-
-        let mut i = 0;
-        while i < lines.len() {
-            let line = lines[i].trim();
-
-            // Check for doc comment
-            if line.starts_with("///") {
-                // Collect consecutive doc comment lines
-                let mut doc_lines = vec![];
-                while i < lines.len() && lines[i].trim().starts_with("///") {
-                    let doc_text = lines[i].trim().strip_prefix("///").unwrap().trim();
-                    if !doc_text.is_empty() {
-                        doc_lines.push(doc_text);
-                    }
-                    i += 1;
-                }
-
-                // The next non-empty line should be a declaration
-                while i < lines.len() && lines[i].trim().is_empty() {
-                    i += 1;
-                }
-
-                if i < lines.len() {
-                    let decl_line = lines[i];
-                    // Try to extract the name from the declaration
-                    if let Some(name) = Self::extract_name_from_declaration(decl_line) {
-                        doc_map.insert(name, doc_lines.join("\n"));
-                    }
-                }
-            } else if line.starts_with("/**") && line.contains("*/") {
-                // Single-line block doc comment
-                let doc_text = line
-                    .strip_prefix("/**")
-                    .and_then(|s| s.strip_suffix("*/"))
-                    .map(|s| s.trim())
-                    .unwrap_or("");
-
-                i += 1;
-                while i < lines.len() && lines[i].trim().is_empty() {
-                    i += 1;
-                }
-
-                if i < lines.len() {
-                    let decl_line = lines[i];
-                    if let Some(name) = Self::extract_name_from_declaration(decl_line) {
-                        doc_map.insert(name, doc_text.to_string());
-                    }
-                }
-            } else if line.starts_with("/**") {
-                // Multi-line block doc comment
-                let mut doc_lines = vec![];
-                let first_line = line.strip_prefix("/**").unwrap().trim();
-                if !first_line.is_empty() && !first_line.starts_with("*") {
-                    doc_lines.push(first_line);
-                }
-                i += 1;
-
-                while i < lines.len() {
-                    let comment_line = lines[i].trim();
-                    if comment_line.ends_with("*/") {
-                        let text = comment_line.strip_suffix("*/").unwrap().trim();
-                        if text.starts_with("*") {
-                            let text = text.strip_prefix("*").unwrap().trim();
-                            if !text.is_empty() {
-                                doc_lines.push(text);
-                            }
-                        } else if !text.is_empty() {
-                            doc_lines.push(text);
-                        }
-                        i += 1;
-                        break;
-                    } else {
-                        if comment_line.starts_with("*") {
-                            let text = comment_line.strip_prefix("*").unwrap().trim();
-                            if !text.is_empty() {
-                                doc_lines.push(text);
-                            }
-                        }
-                        i += 1;
-                    }
-                }
-
-                // Find next declaration
-                while i < lines.len() && lines[i].trim().is_empty() {
-                    i += 1;
-                }
-
-                if i < lines.len() {
-                    let decl_line = lines[i];
-                    if let Some(name) = Self::extract_name_from_declaration(decl_line) {
-                        doc_map.insert(name, doc_lines.join("\n"));
-                    }
-                }
-            }
-
-            i += 1;
-        }
-
-        // Associate doc comments with symbols and process markdown
-        for symbol in symbols.iter_mut() {
-            if let Some(doc) = doc_map.get(&symbol.name) {
-                symbol.documentation = Some(Self::process_doc_comment(doc));
-            }
-        }
-    }
-
-    /// Process doc comments to convert inline links like [module::Item] to markdown code references
-    /// This allows referencing other symbols in documentation similar to Rust
-    fn process_doc_comment(doc: &str) -> String {
-        let mut result = String::new();
-        let chars: Vec<char> = doc.chars().collect();
-        let mut i = 0;
-
-        while i < chars.len() {
-            if chars[i] == '[' {
-                // Try to parse a link reference like [Item] or [module::Item]
-                let _start = i;
-                i += 1;
-
-                // Collect the content inside brackets
-                let mut link_content = String::new();
-                let mut is_valid_link = true;
-
-                while i < chars.len() && chars[i] != ']' {
-                    let ch = chars[i];
-                    // Valid characters: alphanumeric, underscore, and ::
-                    if ch.is_alphanumeric() || ch == '_' {
-                        link_content.push(ch);
-                    } else if ch == ':' && i + 1 < chars.len() && chars[i + 1] == ':' {
-                        link_content.push_str("::");
-                        i += 1; // Skip the second ':'
-                    } else {
-                        // Invalid character, not a link reference
-                        is_valid_link = false;
-                        break;
-                    }
-                    i += 1;
-                }
-
-                if i < chars.len() && chars[i] == ']' && is_valid_link && !link_content.is_empty() {
-                    // Valid link reference found
-                    // Convert [Item] or [module::Item] to `Item` or `module::Item`
-                    result.push('`');
-                    result.push_str(&link_content);
-                    result.push('`');
-                    i += 1; // Skip the closing ]
-                } else {
-                    // Not a valid link, copy the original text
-                    result.push('[');
-                    if !link_content.is_empty() {
-                        result.push_str(&link_content);
-                    }
-                    // Handle any characters we consumed but didn't add to link_content
-                    if i < chars.len() {
-                        if chars[i] == ']' {
-                            result.push(']');
-                            i += 1;
-                        } else if !is_valid_link {
-                            // We hit an invalid character, include it and continue
-                            result.push(chars[i]);
-                            i += 1;
-                        }
-                        // If we reached end without closing bracket, i is already at end
-                    }
-                }
-            } else {
-                result.push(chars[i]);
-                i += 1;
-            }
-        }
-
-        result
-    }
-
-    fn extract_name_from_declaration(line: &str) -> Option<String> {
-        let line = line.trim();
-
-        // Try to match: let/const NAME, fn NAME, def NAME, trait NAME, etc.
-        let patterns = [
-            ("let ", true),
-            ("let mut ", true),
-            ("const ", true),
-            ("fn ", true),
-            ("def ", true),
-            ("trait ", true),
-            ("struct ", true),
-            ("enum ", true),
-            ("teach fn ", true),
-            ("async fn ", true),
-        ];
-
-        for (prefix, _) in &patterns {
-            if let Some(rest) = line.strip_prefix(prefix) {
-                // Extract the name (stop at whitespace, colon, or parenthesis)
-                if let Some(name) = rest
-                    .split(|c: char| c.is_whitespace() || c == ':' || c == '(' || c == '{')
-                    .next()
-                {
-                    if !name.is_empty() {
-                        return Some(name.to_string());
+    #[allow(dead_code)]
+    fn find_containing_function(symbols: &[SymbolInfo], line_num: usize) -> Option<&SymbolInfo> {
+        // Find the function that contains this line
+        for symbol in symbols {
+            if let SymbolKind::Function { .. } = &symbol.kind {
+                if let Some(range) = &symbol.range {
+                    if (range.start.line as usize) <= line_num
+                        && (range.end.line as usize) >= line_num
+                    {
+                        return Some(symbol);
                     }
                 }
             }
         }
-
         None
     }
 
-    fn check_trait_implementations(
-        stmts: &[Stmt],
-        symbols: &[SymbolInfo],
-        stdlib_types: &StdlibTypes,
-        diagnostics: &mut Vec<Diagnostic>,
-        lines: &[&str],
+    fn find_references_in_document(
+        content: &str,
+        word: &str,
+        uri: &str,
+        locations: &mut Vec<Location>,
     ) {
-        for stmt in stmts {
-            if let Stmt::ImplBlock {
-                type_name,
-                trait_name: Some(trait_name),
-                methods,
-            } = stmt
-            {
-                // Find the trait definition
-                let mut required_methods = Vec::new();
-
-                // Check user-defined traits
-                if let Some(trait_symbol) = symbols.iter().find(|s| s.name == *trait_name) {
-                    if let SymbolKind::Trait { methods } = &trait_symbol.kind {
-                        for method in methods {
-                            if !method.has_default_impl {
-                                required_methods.push(method.name.clone());
-                            }
-                        }
-                    }
-                }
-                // Check builtin traits
-                else if let Some(trait_def) = stdlib_types.traits.get(trait_name) {
-                    for method_name in trait_def.methods.keys() {
-                        required_methods.push(method_name.clone());
-                    }
-                } else {
-                    // Debug: Log that we couldn't find the trait
-                    // eprintln!("Could not find trait definition for {}", trait_name);
-                }
-
-                // Check implemented methods
-                let implemented_methods: std::collections::HashSet<String> = methods.iter()
-                    .filter_map(|m| {
-                        if let Stmt::FunctionDecl { name, params, return_type, .. } = m {
-                            // Check signature against trait definition
-                            let mut signature_mismatch = None;
-                            
-                            // Check user-defined traits
-                            if let Some(trait_symbol) = symbols.iter().find(|s| s.name == *trait_name) {
-                                if let SymbolKind::Trait { methods: trait_methods } = &trait_symbol.kind {
-                                    if let Some(trait_method) = trait_methods.iter().find(|tm| tm.name == *name) {
-                                        // Check return type
-                                        let impl_return = Self::opt_type_to_string(return_type);
-                                        if impl_return != trait_method.return_type {
-                                            signature_mismatch = Some(format!(
-                                                "Return type mismatch: expected '{}', found '{}'",
-                                                trait_method.return_type, impl_return
-                                            ));
-                                        }
-                                        
-                                        // Check params
-                                        if signature_mismatch.is_none() {
-                                            if params.len() != trait_method.params.len() {
-                                                signature_mismatch = Some(format!(
-                                                    "Parameter count mismatch: expected {}, found {}",
-                                                    trait_method.params.len(), params.len()
-                                                ));
-                                            } else {
-                                                for (i, (impl_name, impl_type)) in params.iter().enumerate() {
-                                                    let (trait_param_name, trait_param_type) = &trait_method.params[i];
-                                                    let impl_type_str = Self::type_to_string(impl_type);
-                                                    
-                                                    // Check param name (optional, but good for consistency)
-                                                    if impl_name != trait_param_name {
-                                                        // Maybe warning? For now strict check
-                                                        // signature_mismatch = Some(format!("Parameter name mismatch: expected '{}', found '{}'", trait_param_name, impl_name));
-                                                    }
-                                                    
-                                                    if impl_type_str != *trait_param_type {
-                                                        signature_mismatch = Some(format!(
-                                                            "Parameter type mismatch for '{}': expected '{}', found '{}'",
-                                                            impl_name, trait_param_type, impl_type_str
-                                                        ));
-                                                        break;
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                            // Check builtin traits
-                            else if let Some(trait_def) = stdlib_types.traits.get(trait_name) {
-                                if let Some(method_def) = trait_def.methods.get(name) {
-                                    // Check return type
-                                    let impl_return = Self::opt_type_to_string(return_type);
-                                    if method_def.return_type != "any" && impl_return != method_def.return_type {
-                                        signature_mismatch = Some(format!(
-                                            "Return type mismatch: expected '{}', found '{}'",
-                                            method_def.return_type, impl_return
-                                        ));
-                                    }
-                                    
-                                    // Check params
-                                    if signature_mismatch.is_none() {
-                                        if params.len() != method_def.params.len() {
-                                            signature_mismatch = Some(format!(
-                                                "Parameter count mismatch: expected {}, found {}",
-                                                method_def.params.len(), params.len()
-                                            ));
-                                        } else {
-                                            for (i, (impl_name, impl_type)) in params.iter().enumerate() {
-                                                let trait_param_str = &method_def.params[i];
-                                                
-                                                // Parse builtin param string "name: type" or just "name"
-                                                let (trait_param_name, trait_param_type) = if let Some((n, t)) = trait_param_str.split_once(':') {
-                                                    (n.trim(), Some(t.trim()))
-                                                } else {
-                                                    (trait_param_str.as_str(), None)
-                                                };
-                                                
-                                                if impl_name != trait_param_name {
-                                                    // signature_mismatch = Some(format!("Parameter name mismatch: expected '{}', found '{}'", trait_param_name, impl_name));
-                                                }
-                                                
-                                                if let Some(expected_type) = trait_param_type {
-                                                    let impl_type_str = Self::type_to_string(impl_type);
-                                                    // Handle 'any' type in builtins
-                                                    if expected_type != "any" && impl_type_str != expected_type {
-                                                        signature_mismatch = Some(format!(
-                                                            "Parameter type mismatch for '{}': expected '{}', found '{}'",
-                                                            impl_name, expected_type, impl_type_str
-                                                        ));
-                                                        break;
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                            
-                            if let Some(msg) = signature_mismatch {
-                                // Find line number for this method
-                                let mut method_line = 0;
-                                for (i, line) in lines.iter().enumerate() {
-                                    // Simple heuristic to find the method definition
-                                    if line.contains(&format!("fn {}", name)) {
-                                        method_line = i;
-                                        break;
-                                    }
-                                }
-                                
-                                diagnostics.push(Diagnostic {
-                                    range: Range {
-                                        start: Position { line: method_line as u32, character: 0 },
-                                        end: Position { line: method_line as u32, character: lines[method_line].len() as u32 },
-                                    },
-                                    severity: Some(DiagnosticSeverity::ERROR),
-                                    code: Some(NumberOrString::String("signature_mismatch".to_string())),
-                                    source: Some("loft".to_string()),
-                                    message: msg,
-                                    ..Default::default()
-                                });
-                            }
+        // A word in a comment is prose rather than a reference, except in
+        // doc comments, which can name items as [item]
+        let source = content.to_string();
+        let mut parser = Parser::new(InputStream::new(uri, &source));
+        parser.parse_recoverable();
+        let comments: Vec<_> = parser
+            .comments()
+            .iter()
+            .filter(|comment| !comment.is_doc())
+            .map(|comment| comment.span.start..comment.span.end)
+            .collect();
 
-                            Some(name.clone())
-                        } else {
-                            None
-                        }
-                    })
-                    .collect();
-
-                // Find missing methods
-                let missing_methods: Vec<String> = required_methods
-                    .into_iter()
-                    .filter(|m| !implemented_methods.contains(m))
-                    .collect();
-
-                if !missing_methods.is_empty() {
-                    // Find the location of the impl block
-                    let mut line_num = 0;
-                    for (i, line) in lines.iter().enumerate() {
-                        if line.contains(&format!("impl {} for {}", trait_name, type_name)) {
-                            line_num = i;
-                            break;
-                        }
-                    }
-
-                    diagnostics.push(Diagnostic {
-                        range: Range {
-                            start: Position {
-                                line: line_num as u32,
-                                character: 0,
-                            },
-                            end: Position {
-                                line: line_num as u32,
-                                character: lines[line_num].len() as u32,
-                            },
-                        },
-                        severity: Some(DiagnosticSeverity::ERROR),
-                        code: Some(NumberOrString::String("missing_impl".to_string())),
-                        source: Some("loft".to_string()),
-                        message: format!(
-                            "Missing implementation for methods: {}",
-                            missing_methods.join(", ")
-                        ),
-                        data: Some(serde_json::json!({
-                            "missing_methods": missing_methods,
-                            "trait_name": trait_name,
-                            "type_name": type_name
-                        })),
-                        ..Default::default()
-                    });
-                }
-            }
-
-            // Recurse into blocks
-            if let Stmt::Block(inner_stmts) = stmt {
-                Self::check_trait_implementations(
-                    inner_stmts,
-                    symbols,
-                    stdlib_types,
-                    diagnostics,
-                    lines,
-                );
-            }
-        }
-    }
-
-    fn check_semantic_errors(
-        stmts: &[Stmt],
-        symbols: &[SymbolInfo],
-        source: &str,
-        stdlib_types: &StdlibTypes,
-    ) -> Vec<Diagnostic> {
-        let mut diagnostics = Vec::new();
-        let lines: Vec<&str> = source.lines().collect();
-
-        // Track which variables and imports are used
-        let mut used_variables = std::collections::HashSet::new();
-        let mut used_imports = std::collections::HashSet::new();
-
-        // Collect all imports
-        let mut imports = Vec::new();
-        for stmt in stmts {
-            if let Stmt::ImportDecl { path } = stmt {
-                imports.push(path.join("::"));
-            }
-        }
-
-        // Check for unused variables and other semantic errors
-        Self::check_stmt_list_with_imports(
-            stmts,
-            symbols,
-            &mut used_variables,
-            &mut used_imports,
-            &mut diagnostics,
-            &lines,
-        );
-
-        // Check for missing trait implementations
-        Self::check_trait_implementations(stmts, symbols, stdlib_types, &mut diagnostics, &lines);
-
-        // Report unused variables
-        // NOTE: Variables starting with '_' are exempt from unused warnings (Rust convention)
-        // This follows common practice but could be made configurable in the future
-        for symbol in symbols {
-            if let SymbolKind::Variable { .. } = &symbol.kind {
-                if !used_variables.contains(&symbol.name) && !symbol.name.starts_with('_') {
-                    // Find the line where this variable is defined
-                    if let Some(line_num) = Self::find_symbol_line(&symbol.name, &lines) {
-                        diagnostics.push(Diagnostic {
-                            range: Range {
-                                start: Position {
-                                    line: line_num as u32,
-                                    character: 0,
-                                },
-                                end: Position {
-                                    line: line_num as u32,
-                                    character: lines[line_num].len() as u32,
-                                },
-                            },
-                            severity: Some(DiagnosticSeverity::WARNING),
-                            code: None,
-                            code_description: None,
-                            source: Some("loft".to_string()),
-                            message: format!("Unused variable '{}'", symbol.name),
-                            related_information: None,
-                            tags: Some(vec![DiagnosticTag::UNNECESSARY]),
-                            data: None,
-                        });
-                    }
-                }
-            }
-        }
-
-        // Report unused imports
-        for import_path in imports {
-            if !used_imports.contains(&import_path) {
-                // Find the line with this import
-                for (line_num, line) in lines.iter().enumerate() {
-                    if line.contains("learn") && line.contains(&import_path.replace("::", "/")) {
-                        diagnostics.push(Diagnostic {
-                            range: Range {
-                                start: Position {
-                                    line: line_num as u32,
-                                    character: 0,
-                                },
-                                end: Position {
-                                    line: line_num as u32,
-                                    character: lines[line_num].len() as u32,
-                                },
-                            },
-                            severity: Some(DiagnosticSeverity::HINT),
-                            code: None,
-                            code_description: None,
-                            source: Some("loft".to_string()),
-                            message: format!("Unused import '{}'", import_path),
-                            related_information: None,
-                            tags: Some(vec![DiagnosticTag::UNNECESSARY]),
-                            data: None,
-                        });
-                        break;
-                    }
-                }
-            }
-        }
-
-        diagnostics
-    }
-
-    fn check_stmt_list_with_imports(
-        stmts: &[Stmt],
-        symbols: &[SymbolInfo],
-        used_vars: &mut std::collections::HashSet<String>,
-        used_imports: &mut std::collections::HashSet<String>,
-        diagnostics: &mut Vec<Diagnostic>,
-        lines: &[&str],
-    ) {
-        let mut found_terminal = false;
-        for stmt in stmts.iter() {
-            if found_terminal {
-                // Code after return/break/continue is unreachable
-                if let Some(line_num) = Self::get_stmt_line(stmt, lines) {
-                    diagnostics.push(Diagnostic {
-                        range: Range {
-                            start: Position {
-                                line: line_num as u32,
-                                character: 0,
-                            },
-                            end: Position {
-                                line: line_num as u32,
-                                character: lines[line_num].len() as u32,
-                            },
-                        },
-                        severity: Some(DiagnosticSeverity::WARNING),
-                        code: None,
-                        code_description: None,
-                        source: Some("loft".to_string()),
-                        message: "Unreachable code".to_string(),
-                        related_information: None,
-                        tags: Some(vec![DiagnosticTag::UNNECESSARY]),
-                        data: None,
-                    });
-                }
-                // Only report first unreachable statement
-                break;
-            }
-
-            Self::check_stmt_with_imports(
-                stmt,
-                symbols,
-                used_vars,
-                used_imports,
-                diagnostics,
-                lines,
-            );
-
-            // Check if this statement is a terminal (return, break, continue)
-            if Self::is_terminal_stmt(stmt) {
-                found_terminal = true;
-            }
-        }
-    }
-
-    #[allow(dead_code)]
-    fn check_stmt_list(
-        stmts: &[Stmt],
-        symbols: &[SymbolInfo],
-        used_vars: &mut std::collections::HashSet<String>,
-        diagnostics: &mut Vec<Diagnostic>,
-        lines: &[&str],
-    ) {
-        let mut found_terminal = false;
-        for stmt in stmts.iter() {
-            if found_terminal {
-                // Code after return/break/continue is unreachable
-                if let Some(line_num) = Self::get_stmt_line(stmt, lines) {
-                    diagnostics.push(Diagnostic {
-                        range: Range {
-                            start: Position {
-                                line: line_num as u32,
-                                character: 0,
-                            },
-                            end: Position {
-                                line: line_num as u32,
-                                character: lines[line_num].len() as u32,
-                            },
-                        },
-                        severity: Some(DiagnosticSeverity::WARNING),
-                        code: None,
-                        code_description: None,
-                        source: Some("loft".to_string()),
-                        message: "Unreachable code".to_string(),
-                        related_information: None,
-                        tags: Some(vec![DiagnosticTag::UNNECESSARY]),
-                        data: None,
-                    });
-                }
-                // Only report first unreachable statement
-                break;
-            }
-
-            Self::check_stmt(stmt, symbols, used_vars, diagnostics, lines);
-
-            // Check if this statement is a terminal (return, break, continue)
-            if Self::is_terminal_stmt(stmt) {
-                found_terminal = true;
-            }
-        }
-    }
-
-    fn is_terminal_stmt(stmt: &Stmt) -> bool {
-        matches!(stmt, Stmt::Return(_) | Stmt::Break | Stmt::Continue)
-    }
-
-    fn get_stmt_line(stmt: &Stmt, lines: &[&str]) -> Option<usize> {
-        // Try to find the statement in the source
-        // This is a simple heuristic - could be improved with position tracking
-        match stmt {
-            Stmt::Return(_) => {
-                for (idx, line) in lines.iter().enumerate() {
-                    if line.trim().starts_with("return") {
-                        return Some(idx);
-                    }
-                }
-            }
-            Stmt::Break => {
-                for (idx, line) in lines.iter().enumerate() {
-                    if line.trim().starts_with("break") {
-                        return Some(idx);
-                    }
-                }
-            }
-            Stmt::Continue => {
-                for (idx, line) in lines.iter().enumerate() {
-                    if line.trim().starts_with("continue") {
-                        return Some(idx);
-                    }
-                }
-            }
-            _ => {}
-        }
-        None
-    }
-
-    fn check_stmt_with_imports(
-        stmt: &Stmt,
-        symbols: &[SymbolInfo],
-        used_vars: &mut std::collections::HashSet<String>,
-        used_imports: &mut std::collections::HashSet<String>,
-        diagnostics: &mut Vec<Diagnostic>,
-        lines: &[&str],
-    ) {
-        match stmt {
-            Stmt::VarDecl { value: Some(expr), .. } => {
-                Self::check_expr_with_imports(
-                    expr,
-                    symbols,
-                    used_vars,
-                    used_imports,
-                    diagnostics,
-                    lines,
-                );
-            }
-            Stmt::FunctionDecl { body, .. } => {
-                if let Stmt::Block(stmts) = body.as_ref() {
-                    Self::check_stmt_list_with_imports(
-                        stmts,
-                        symbols,
-                        used_vars,
-                        used_imports,
-                        diagnostics,
-                        lines,
-                    );
-                }
-            }
-            Stmt::Expr(expr) => {
-                Self::check_expr_with_imports(
-                    expr,
-                    symbols,
-                    used_vars,
-                    used_imports,
-                    diagnostics,
-                    lines,
-                );
-            }
-            Stmt::Return(Some(expr)) => {
-                Self::check_expr_with_imports(
-                    expr,
-                    symbols,
-                    used_vars,
-                    used_imports,
-                    diagnostics,
-                    lines,
-                );
-            }
-            Stmt::Assign { value, .. } => {
-                Self::check_expr_with_imports(
-                    value,
-                    symbols,
-                    used_vars,
-                    used_imports,
-                    diagnostics,
-                    lines,
-                );
-            }
-            Stmt::If {
-                condition,
-                then_branch,
-                else_branch,
-            } => {
-                Self::check_expr_with_imports(
-                    condition,
-                    symbols,
-                    used_vars,
-                    used_imports,
-                    diagnostics,
-                    lines,
-                );
-                Self::check_stmt_with_imports(
-                    then_branch,
-                    symbols,
-                    used_vars,
-                    used_imports,
-                    diagnostics,
-                    lines,
-                );
-                if let Some(else_stmt) = else_branch {
-                    Self::check_stmt_with_imports(
-                        else_stmt,
-                        symbols,
-                        used_vars,
-                        used_imports,
-                        diagnostics,
-                        lines,
-                    );
-                }
-            }
-            Stmt::While { condition, body } => {
-                Self::check_expr_with_imports(
-                    condition,
-                    symbols,
-                    used_vars,
-                    used_imports,
-                    diagnostics,
-                    lines,
-                );
-                Self::check_stmt_with_imports(
-                    body,
-                    symbols,
-                    used_vars,
-                    used_imports,
-                    diagnostics,
-                    lines,
-                );
-            }
-            Stmt::For {
-                var,
-                body,
-                iterable,
-                ..
-            } => {
-                Self::check_expr_with_imports(
-                    iterable,
-                    symbols,
-                    used_vars,
-                    used_imports,
-                    diagnostics,
-                    lines,
-                );
-
-                // Create a temporary scope including the loop variable
-                let mut extended_symbols = symbols.to_vec();
-                extended_symbols.push(SymbolInfo {
-                    name: var.clone(),
-                    kind: SymbolKind::Variable {
-                        var_type: None,
-                        mutable: false,
-                    },
-                    detail: Some(format!("loop variable {}", var)),
-                    documentation: None,
-                    scope_level: 0, // Scope level not strictly used here for existence check
-                    range: None,
-                    selection_range: None,
-                    source_uri: None,
-                    is_exported: false,
-                });
-
-                Self::check_stmt_with_imports(
-                    body,
-                    &extended_symbols,
-                    used_vars,
-                    used_imports,
-                    diagnostics,
-                    lines,
-                );
-            }
-            Stmt::Block(stmts) => {
-                Self::check_stmt_list_with_imports(
-                    stmts,
-                    symbols,
-                    used_vars,
-                    used_imports,
-                    diagnostics,
-                    lines,
-                );
-            }
-            _ => {}
-        }
-    }
-
-    #[allow(dead_code)]
-    fn check_stmt(
-        stmt: &Stmt,
-        symbols: &[SymbolInfo],
-        used_vars: &mut std::collections::HashSet<String>,
-        diagnostics: &mut Vec<Diagnostic>,
-        lines: &[&str],
-    ) {
-        match stmt {
-            Stmt::VarDecl { value: Some(expr), .. } => {
-                Self::check_expr(expr, symbols, used_vars, diagnostics, lines);
-            }
-            Stmt::FunctionDecl { body, .. } => {
-                if let Stmt::Block(stmts) = body.as_ref() {
-                    Self::check_stmt_list(stmts, symbols, used_vars, diagnostics, lines);
-                }
-            }
-            Stmt::Expr(expr) => {
-                Self::check_expr(expr, symbols, used_vars, diagnostics, lines);
-            }
-            Stmt::Return(Some(expr)) => {
-                Self::check_expr(expr, symbols, used_vars, diagnostics, lines);
-            }
-            Stmt::Assign { value, .. } => {
-                Self::check_expr(value, symbols, used_vars, diagnostics, lines);
-            }
-            Stmt::If {
-                condition,
-                then_branch,
-                else_branch,
-            } => {
-                Self::check_expr(condition, symbols, used_vars, diagnostics, lines);
-                Self::check_stmt(then_branch, symbols, used_vars, diagnostics, lines);
-                if let Some(else_stmt) = else_branch {
-                    Self::check_stmt(else_stmt, symbols, used_vars, diagnostics, lines);
-                }
-            }
-            Stmt::While { condition, body } => {
-                Self::check_expr(condition, symbols, used_vars, diagnostics, lines);
-                Self::check_stmt(body, symbols, used_vars, diagnostics, lines);
-            }
-            Stmt::For { var, body, iterable } => {
-                Self::check_expr(iterable, symbols, used_vars, diagnostics, lines);
-
-                // Create a temporary scope including the loop variable
-                let mut extended_symbols = symbols.to_vec();
-                extended_symbols.push(SymbolInfo {
-                    name: var.clone(),
-                    kind: SymbolKind::Variable {
-                        var_type: None,
-                        mutable: false,
-                    },
-                    detail: Some(format!("loop variable {}", var)),
-                    documentation: None,
-                    scope_level: 0,
-                    range: None,
-                    selection_range: None,
-                    source_uri: None,
-                    is_exported: false,
-                });
-
-                Self::check_stmt(body, &extended_symbols, used_vars, diagnostics, lines);
-            }
-            Stmt::Block(stmts) => {
-                Self::check_stmt_list(stmts, symbols, used_vars, diagnostics, lines);
-            }
-            _ => {}
-        }
-    }
-
-    fn check_expr_with_imports(
-        expr: &Expr,
-        symbols: &[SymbolInfo],
-        used_vars: &mut std::collections::HashSet<String>,
-        used_imports: &mut std::collections::HashSet<String>,
-        diagnostics: &mut Vec<Diagnostic>,
-        lines: &[&str],
-    ) {
-        match expr {
-            Expr::Ident(name) => {
-                used_vars.insert(name.clone());
-
-                // Track if this might be from an import
-                // If identifier contains "::", it's using an imported symbol
-                if name.contains("::") {
-                    let parts: Vec<&str> = name.split("::").collect();
-                    if parts.len() >= 2 {
-                        // Mark the module as used
-                        used_imports.insert(parts[0].to_string());
-                    }
-                }
-
-                // Check if identifier is defined
-                if !symbols.iter().any(|s| &s.name == name) {
-                    // Check if it's a builtin (term, math, etc.)
-                    let builtin_modules = [
-                        "term", "math", "time", "web", "fs", "console", "json", "encoding",
-                        "random",
-                    ];
-                    if !builtin_modules.contains(&name.as_str()) {
-                        if let Some(line_num) = Self::find_identifier_line(name, lines) {
-                            let line = lines[line_num];
-                            let start_col = line.find(name).unwrap_or(0);
-                            let end_col = start_col + name.len();
-
-                            diagnostics.push(Diagnostic {
-                                range: Range {
-                                    start: Position {
-                                        line: line_num as u32,
-                                        character: start_col as u32,
-                                    },
-                                    end: Position {
-                                        line: line_num as u32,
-                                        character: end_col as u32,
-                                    },
-                                },
-                                severity: Some(DiagnosticSeverity::ERROR),
-                                code: None,
-                                code_description: None,
-                                source: Some("loft".to_string()),
-                                message: format!("Undefined identifier '{}'", name),
-                                related_information: None,
-                                tags: None,
-                                data: None,
-                            });
-                        }
-                    }
-                }
-            }
-            Expr::Call { func, args } => {
-                Self::check_expr_with_imports(
-                    func,
-                    symbols,
-                    used_vars,
-                    used_imports,
-                    diagnostics,
-                    lines,
-                );
-                for arg in args {
-                    Self::check_expr_with_imports(
-                        arg,
-                        symbols,
-                        used_vars,
-                        used_imports,
-                        diagnostics,
-                        lines,
-                    );
-                }
-
-                // Check function arity if func is an identifier
-                if let Expr::Ident(func_name) = func.as_ref() {
-                    if let Some(symbol) = symbols.iter().find(|s| s.name == *func_name) {
-                        if let SymbolKind::Function { params, .. } = &symbol.kind {
-                            if params.len() != args.len() {
-                                if let Some(line_num) =
-                                    Self::find_identifier_line(func_name, lines)
-                                {
-                                    diagnostics.push(Diagnostic {
-                                        range: Range {
-                                            start: Position {
-                                                line: line_num as u32,
-                                                character: 0,
-                                            },
-                                            end: Position {
-                                                line: line_num as u32,
-                                                character: lines[line_num].len() as u32,
-                                            },
-                                        },
-                                        severity: Some(DiagnosticSeverity::ERROR),
-                                        code: None,
-                                        code_description: None,
-                                        source: Some("loft".to_string()),
-                                        message: format!(
-                                            "Function '{}' expects {} argument(s), but {} provided",
-                                            func_name,
-                                            params.len(),
-                                            args.len()
-                                        ),
-                                        related_information: None,
-                                        tags: None,
-                                        data: None,
-                                    });
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-            Expr::FieldAccess { object, field: _ } => {
-                Self::check_expr_with_imports(
-                    object,
-                    symbols,
-                    used_vars,
-                    used_imports,
-                    diagnostics,
-                    lines,
-                );
-                // Mark module as used if it's a builtin
-                if let Expr::Ident(obj_name) = object.as_ref() {
-                    let builtin_modules = [
-                        "term", "math", "time", "web", "fs", "console", "json", "encoding",
-                        "random",
-                    ];
-                    if builtin_modules.contains(&obj_name.as_str()) {
-                        used_imports.insert(obj_name.clone());
-                    }
-                }
-            }
-            Expr::BinOp { left, right, .. } => {
-                Self::check_expr_with_imports(
-                    left,
-                    symbols,
-                    used_vars,
-                    used_imports,
-                    diagnostics,
-                    lines,
-                );
-                Self::check_expr_with_imports(
-                    right,
-                    symbols,
-                    used_vars,
-                    used_imports,
-                    diagnostics,
-                    lines,
-                );
-            }
-            Expr::UnaryOp { expr, .. } => {
-                Self::check_expr_with_imports(
-                    expr,
-                    symbols,
-                    used_vars,
-                    used_imports,
-                    diagnostics,
-                    lines,
-                );
-            }
-            Expr::ArrayLiteral(exprs) => {
-                for e in exprs {
-                    Self::check_expr_with_imports(
-                        e,
-                        symbols,
-                        used_vars,
-                        used_imports,
-                        diagnostics,
-                        lines,
-                    );
-                }
-            }
-            Expr::TemplateLiteral { parts } => {
-                for part in parts {
-                    if let TemplatePart::Expression(e) = part {
-                        Self::check_expr_with_imports(
-                            e,
-                            symbols,
-                            used_vars,
-                            used_imports,
-                            diagnostics,
-                            lines,
-                        );
-                    }
-                }
-            }
-            Expr::Index { array, index } => {
-                Self::check_expr_with_imports(
-                    array,
-                    symbols,
-                    used_vars,
-                    used_imports,
-                    diagnostics,
-                    lines,
-                );
-                Self::check_expr_with_imports(
-                    index,
-                    symbols,
-                    used_vars,
-                    used_imports,
-                    diagnostics,
-                    lines,
-                );
-            }
-            Expr::Lambda { body, .. } => {
-                Self::check_expr_with_imports(
-                    body,
-                    symbols,
-                    used_vars,
-                    used_imports,
-                    diagnostics,
-                    lines,
-                );
-            }
-            Expr::Await(expr) | Expr::Async(expr) | Expr::Lazy(expr) => {
-                Self::check_expr_with_imports(
-                    expr,
-                    symbols,
-                    used_vars,
-                    used_imports,
-                    diagnostics,
-                    lines,
-                );
-            }
-            Expr::Block(stmts) => {
-                Self::check_stmt_list_with_imports(
-                    stmts,
-                    symbols,
-                    used_vars,
-                    used_imports,
-                    diagnostics,
-                    lines,
-                );
-            }
-            Expr::StructLiteral { fields, .. } => {
-                // Check each field expression in the struct literal
-                for (_, field_expr) in fields {
-                    Self::check_expr_with_imports(
-                        field_expr,
-                        symbols,
-                        used_vars,
-                        used_imports,
-                        diagnostics,
-                        lines,
-                    );
-                }
-            }
-            _ => {}
-        }
-    }
-
-    #[allow(dead_code)]
-    fn check_expr(
-        expr: &Expr,
-        symbols: &[SymbolInfo],
-        used_vars: &mut std::collections::HashSet<String>,
-        diagnostics: &mut Vec<Diagnostic>,
-        lines: &[&str],
-    ) {
-        match expr {
-            Expr::Ident(name) => {
-                used_vars.insert(name.clone());
-
-                // Check if identifier is defined
-                if !symbols.iter().any(|s| &s.name == name) {
-                    // Check if it's a builtin (term, math, etc.)
-                    // TODO: This list should be centralized and synced with runtime builtins
-                    let builtin_modules = [
-                        "term", "math", "time", "web", "fs", "console", "json", "encoding",
-                        "random",
-                    ];
-                    if !builtin_modules.contains(&name.as_str()) {
-                        if let Some(line_num) = Self::find_identifier_line(name, lines) {
-                            // Find the position of the identifier in the line for more precise range
-                            let line = lines[line_num];
-                            let start_col = line.find(name).unwrap_or(0);
-                            let end_col = start_col + name.len();
-
-                            diagnostics.push(Diagnostic {
-                                range: Range {
-                                    start: Position {
-                                        line: line_num as u32,
-                                        character: start_col as u32,
-                                    },
-                                    end: Position {
-                                        line: line_num as u32,
-                                        character: end_col as u32,
-                                    },
-                                },
-                                severity: Some(DiagnosticSeverity::ERROR),
-                                code: None,
-                                code_description: None,
-                                source: Some("loft".to_string()),
-                                message: format!("Undefined identifier '{}'", name),
-                                related_information: None,
-                                tags: None,
-                                data: None,
-                            });
-                        }
-                    }
-                }
-            }
-            Expr::Call { func, args } => {
-                Self::check_expr(func, symbols, used_vars, diagnostics, lines);
-                for arg in args {
-                    Self::check_expr(arg, symbols, used_vars, diagnostics, lines);
-                }
-
-                // Check function arity if func is an identifier
-                if let Expr::Ident(func_name) = func.as_ref() {
-                    if let Some(symbol) = symbols.iter().find(|s| s.name == *func_name) {
-                        if let SymbolKind::Function { params, .. } = &symbol.kind {
-                            if params.len() != args.len() {
-                                if let Some(line_num) =
-                                    Self::find_identifier_line(func_name, lines)
-                                {
-                                    diagnostics.push(Diagnostic {
-                                        range: Range {
-                                            start: Position {
-                                                line: line_num as u32,
-                                                character: 0,
-                                            },
-                                            end: Position {
-                                                line: line_num as u32,
-                                                character: lines[line_num].len() as u32,
-                                            },
-                                        },
-                                        severity: Some(DiagnosticSeverity::ERROR),
-                                        code: None,
-                                        code_description: None,
-                                        source: Some("loft".to_string()),
-                                        message: format!(
-                                            "Function '{}' expects {} argument(s), but {} provided",
-                                            func_name,
-                                            params.len(),
-                                            args.len()
-                                        ),
-                                        related_information: None,
-                                        tags: None,
-                                        data: None,
-                                    });
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-            Expr::FieldAccess { object, .. } => {
-                Self::check_expr(object, symbols, used_vars, diagnostics, lines);
-            }
-            Expr::BinOp { left, right, .. } => {
-                Self::check_expr(left, symbols, used_vars, diagnostics, lines);
-                Self::check_expr(right, symbols, used_vars, diagnostics, lines);
-            }
-            Expr::UnaryOp { expr, .. } => {
-                Self::check_expr(expr, symbols, used_vars, diagnostics, lines);
-            }
-            Expr::ArrayLiteral(elements) => {
-                for elem in elements {
-                    Self::check_expr(elem, symbols, used_vars, diagnostics, lines);
-                }
-            }
-            Expr::Index { array, index } => {
-                Self::check_expr(array, symbols, used_vars, diagnostics, lines);
-                Self::check_expr(index, symbols, used_vars, diagnostics, lines);
-            }
-            Expr::Block(stmts) => {
-                Self::check_stmt_list(stmts, symbols, used_vars, diagnostics, lines);
-            }
-            Expr::Await(expr) | Expr::Async(expr) | Expr::Lazy(expr) => {
-                Self::check_expr(expr, symbols, used_vars, diagnostics, lines);
-            }
-            Expr::Lambda { body, .. } => {
-                Self::check_expr(body, symbols, used_vars, diagnostics, lines);
-            }
-            Expr::StructLiteral { fields, .. } => {
-                // Check each field expression in the struct literal
-                for (_, field_expr) in fields {
-                    Self::check_expr(field_expr, symbols, used_vars, diagnostics, lines);
-                }
-            }
-            Expr::TemplateLiteral { parts } => {
-                for part in parts {
-                    if let TemplatePart::Expression(e) = part {
-                        Self::check_expr(e, symbols, used_vars, diagnostics, lines);
-                    }
-                }
-            }
-            _ => {}
-        }
-    }
-
-    #[allow(dead_code)]
-    fn find_containing_function(symbols: &[SymbolInfo], line_num: usize) -> Option<&SymbolInfo> {
-        // Find the function that contains this line
-        for symbol in symbols {
-            if let SymbolKind::Function { .. } = &symbol.kind {
-                if let Some(range) = &symbol.range {
-                    if (range.start.line as usize) <= line_num
-                        && (range.end.line as usize) >= line_num
-                    {
-                        return Some(symbol);
-                    }
-                }
-            }
-        }
-        None
-    }
-
-    fn find_references_in_document(
-        content: &str,
-        word: &str,
-        uri: &str,
-        locations: &mut Vec<Location>,
-    ) {
-        let lines: Vec<&str> = content.lines().collect();
-
-        for (line_num, line_content) in lines.iter().enumerate() {
+        let mut line_start = 0;
+        for (line_num, line_content) in content.split_inclusive('\n').enumerate() {
+            let offset = line_start;
+            line_start += line_content.len();
+            let line_content = line_content.strip_suffix('\n').unwrap_or(line_content);
+            let line_content = line_content.strip_suffix('\r').unwrap_or(line_content);
             let mut col_num = 0;
             while col_num < line_content.len() {
                 let found_word = get_word_at_position(line_content, col_num);
-                if !found_word.is_empty() && found_word == word {
+                let in_comment = comments
+                    .iter()
+                    .any(|comment| comment.contains(&(offset + col_num)));
+                if !found_word.is_empty() && found_word == word && !in_comment {
                     let start_pos = Position {
                         line: line_num as u32,
                         character: col_num as u32,
@@ -1754,617 +647,82 @@ impl LoftLanguageServer {
 
                     // Parse URI safely
                     if let Ok(parsed_uri) = Uri::from_str(uri) {
-                        locations.push(Location {
-                            uri: parsed_uri,
-                            range: Range {
-                                start: start_pos,
-                                end: end_pos,
-                            },
-                        });
-                    }
-
-                    col_num += found_word.len();
-                } else if !found_word.is_empty() {
-                    col_num += found_word.len();
-                } else {
-                    col_num += 1;
-                }
-            }
-        }
-    }
-
-    fn find_symbol_line(symbol_name: &str, lines: &[&str]) -> Option<usize> {
-        for (idx, line) in lines.iter().enumerate() {
-            // More precise matching - look for word boundaries
-            let trimmed = line.trim();
-            // Skip comments
-            if trimmed.starts_with("//") || trimmed.starts_with("/*") {
-                continue;
-            }
-            // Check for variable declaration with word boundaries
-            if trimmed.contains(&format!("let {} ", symbol_name))
-                || trimmed.contains(&format!("let {}=", symbol_name))
-                || trimmed.contains(&format!("let {}:", symbol_name))
-                || trimmed.contains(&format!("let mut {} ", symbol_name))
-                || trimmed.contains(&format!("let mut {}=", symbol_name))
-                || trimmed.contains(&format!("let mut {}:", symbol_name))
-            {
-                return Some(idx);
-            }
-        }
-        None
-    }
-
-    fn find_identifier_line(identifier: &str, lines: &[&str]) -> Option<usize> {
-        for (idx, line) in lines.iter().enumerate() {
-            // Skip comments
-            let trimmed = line.trim();
-            if trimmed.starts_with("//") || trimmed.starts_with("/*") {
-                continue;
-            }
-            // Simple word boundary check - could be improved with proper tokenization
-            if line.contains(identifier) {
-                return Some(idx);
-            }
-        }
-        None
-    }
-
-    fn suggest_import_for_symbol(symbol_name: &str) -> Option<String> {
-        // Map common symbols to their standard library modules
-        let symbol_to_module: HashMap<&str, &str> = [
-            // term module
-            ("println", "term"),
-            ("print", "term"),
-            ("read_line", "term"),
-            ("clear", "term"),
-            // math module
-            ("sqrt", "math"),
-            ("pow", "math"),
-            ("abs", "math"),
-            ("floor", "math"),
-            ("ceil", "math"),
-            ("round", "math"),
-            ("sin", "math"),
-            ("cos", "math"),
-            ("tan", "math"),
-            ("PI", "math"),
-            ("E", "math"),
-            // time module
-            ("now", "time"),
-            ("sleep", "time"),
-            // fs module
-            ("read", "fs"),
-            ("write", "fs"),
-            ("exists", "fs"),
-            ("remove", "fs"),
-            // json module
-            ("parse", "json"),
-            ("stringify", "json"),
-            // random module
-            ("random", "random"),
-            ("seed", "random"),
-            ("range", "random"),
-            ("choice", "random"),
-            ("shuffle", "random"),
-        ]
-        .iter()
-        .cloned()
-        .collect();
-
-        symbol_to_module.get(symbol_name).map(|s| s.to_string())
-    }
-
-    fn find_import_insertion_line(content: &str) -> u32 {
-        let lines: Vec<&str> = content.lines().collect();
-
-        // Find the last import statement
-        let mut last_import_line = 0u32;
-        for (idx, line) in lines.iter().enumerate() {
-            let trimmed = line.trim();
-            if trimmed.starts_with("learn ") {
-                last_import_line = (idx + 1) as u32; // Insert after this line
-            }
-        }
-
-        // If there are no imports, insert at the top (after any initial comments)
-        if last_import_line == 0 {
-            for (idx, line) in lines.iter().enumerate() {
-                let trimmed = line.trim();
-                // Skip doc comments and regular comments at the start
-                if !trimmed.starts_with("//") && !trimmed.starts_with("/*") && !trimmed.is_empty() {
-                    return idx as u32;
-                }
-            }
-        }
-
-        last_import_line
-    }
-
-    fn extract_imports(stmts: &[Stmt]) -> Vec<Vec<String>> {
-        let mut imports = Vec::new();
-
-        for stmt in stmts {
-            match stmt {
-                Stmt::ImportDecl { path } => {
-                    imports.push(path.clone());
-                }
-                Stmt::Block(stmts) => {
-                    imports.extend(Self::extract_imports(stmts));
-                }
-                _ => {}
-            }
-        }
-
-        imports
-    }
-
-    fn extract_symbols(
-        stmts: &[Stmt],
-        scope_level: usize,
-        stdlib_types: &StdlibTypes,
-    ) -> Vec<SymbolInfo> {
-        let mut symbols = Vec::new();
-
-        // First pass: extract all symbols
-        for stmt in stmts {
-            match stmt {
-                Stmt::VarDecl {
-                    name,
-                    var_type,
-                    mutable,
-                    value,
-                } => {
-                    // Infer type from value if not explicitly specified
-                    let inferred_type = if var_type.is_none() {
-                        value
-                            .as_ref()
-                            .and_then(|v| Self::infer_type_from_expr(v, &symbols, stdlib_types))
-                    } else {
-                        None
-                    };
-
-                    let final_type = var_type
-                        .as_ref()
-                        .map(Self::type_to_string)
-                        .or(inferred_type);
-
-                    symbols.push(SymbolInfo {
-                        name: name.clone(),
-                        kind: SymbolKind::Variable {
-                            var_type: final_type,
-                            mutable: *mutable,
-                        },
-                        detail: Some(format!(
-                            "let {}{}",
-                            if *mutable { "mut " } else { "" },
-                            name
-                        )),
-                        documentation: None,
-                        scope_level,
-                        range: None, // TODO: extract from AST node position
-                        selection_range: None,
-                        source_uri: None,
-                        is_exported: false, // Variables cannot be exported
-                    });
-                }
-                Stmt::ConstDecl {
-                    name, const_type, ..
-                } => {
-                    symbols.push(SymbolInfo {
-                        name: name.clone(),
-                        kind: SymbolKind::Constant {
-                            const_type: const_type
-                                .as_ref()
-                                .map(Self::type_to_string)
-                                .unwrap_or_else(|| "unknown".to_string()),
-                        },
-                        detail: Some(format!("const {}", name)),
-                        documentation: None,
-                        scope_level,
-                        range: None, // TODO: extract from AST node position
-                        selection_range: None,
-                        source_uri: None,
-                        is_exported: false, // TODO: Detect if constant is preceded by 'teach' keyword
-                    });
-                }
-                Stmt::FunctionDecl {
-                    name,
-                    params,
-                    return_type,
-                    body,
-                    is_exported,
-                    ..
-                } => {
-                    let param_list: Vec<(String, String)> = params
-                        .iter()
-                        .map(|(n, t)| (n.clone(), Self::type_to_string(t)))
-                        .collect();
-
-                    symbols.push(SymbolInfo {
-                        name: name.clone(),
-                        kind: SymbolKind::Function {
-                            params: param_list.clone(),
-                            return_type: Self::opt_type_to_string(return_type),
-                        },
-                        detail: Some(format!(
-                            "fn {}({})",
-                            name,
-                            param_list
-                                .iter()
-                                .map(|(n, t)| format!("{}: {}", n, t))
-                                .collect::<Vec<_>>()
-                                .join(", ")
-                        )),
-                        documentation: None,
-                        scope_level,
-                        range: None, // TODO: extract from AST node position
-                        selection_range: None,
-                        source_uri: None,
-                        is_exported: *is_exported,
-                    });
-
-                    // Recursively extract symbols from function body
-                    if let Stmt::Block(body_stmts) = body.as_ref() {
-                        symbols.extend(Self::extract_symbols(
-                            body_stmts,
-                            scope_level + 1,
-                            stdlib_types,
-                        ));
-                    }
-                }
-                Stmt::For { var, body, .. } => {
-                    // Add loop variable to symbols
-                    symbols.push(SymbolInfo {
-                        name: var.clone(),
-                        kind: SymbolKind::Variable {
-                            var_type: None, // Could potentially infer from iterable
-                            mutable: false, // Loft loop variables are typically immutable
-                        },
-                        detail: Some(format!("loop variable {}", var)),
-                        documentation: None,
-                        scope_level,
-                        range: None,
-                        selection_range: None,
-                        source_uri: None,
-                        is_exported: false,
-                    });
-
-                    // Recursively extract symbols from loop body
-                    if let Stmt::Block(body_stmts) = body.as_ref() {
-                        symbols.extend(Self::extract_symbols(
-                            body_stmts,
-                            scope_level + 1,
-                            stdlib_types,
-                        ));
-                    }
-                }
-                Stmt::StructDecl { name, fields } => {
-                    let field_list: Vec<(String, String)> = fields
-                        .iter()
-                        .map(|(n, t)| (n.clone(), Self::type_to_string(t)))
-                        .collect();
-
-                    symbols.push(SymbolInfo {
-                        name: name.clone(),
-                        kind: SymbolKind::Struct {
-                            fields: field_list,
-                            methods: Vec::new(), // Will be populated in second pass
-                        },
-                        detail: Some(format!("struct {}", name)),
-                        documentation: None,
-                        scope_level,
-                        range: None, // TODO: extract from AST node position
-                        selection_range: None,
-                        source_uri: None,
-                        is_exported: false, // TODO: Detect if struct is preceded by 'teach' keyword
-                    });
-                }
-                Stmt::TraitDecl { name, methods } => {
-                    let method_infos: Vec<TraitMethodInfo> = methods
-                        .iter()
-                        .map(|m| match m {
-                            crate::parser::TraitMethod::Signature {
-                                name,
-                                params,
-                                return_type,
-                            } => TraitMethodInfo {
-                                name: name.clone(),
-                                params: params
-                                    .iter()
-                                    .map(|(n, t)| (n.clone(), Self::type_to_string(t)))
-                                    .collect(),
-                                return_type: Self::type_to_string(return_type),
-                                has_default_impl: false,
-                            },
-                            crate::parser::TraitMethod::Default {
-                                name,
-                                params,
-                                return_type,
-                                ..
-                            } => TraitMethodInfo {
-                                name: name.clone(),
-                                params: params
-                                    .iter()
-                                    .map(|(n, t)| (n.clone(), Self::type_to_string(t)))
-                                    .collect(),
-                                return_type: Self::type_to_string(return_type),
-                                has_default_impl: true,
-                            },
-                        })
-                        .collect();
-
-                    symbols.push(SymbolInfo {
-                        name: name.clone(),
-                        kind: SymbolKind::Trait {
-                            methods: method_infos,
-                        },
-                        detail: Some(format!("trait {}", name)),
-                        documentation: None,
-                        scope_level,
-                        range: None, // TODO: extract from AST node position
-                        selection_range: None,
-                        source_uri: None,
-                        is_exported: false, // TODO: Detect if trait is preceded by 'teach' keyword
-                    });
-                }
-                Stmt::EnumDecl { name, variants } => {
-                    let variant_infos: Vec<(String, Option<Vec<String>>)> = variants
-                        .iter()
-                        .map(|(n, fields)| {
-                            let field_types = fields.as_ref().map(|types| {
-                                types.iter().map(Self::type_to_string).collect()
-                            });
-                            (n.clone(), field_types)
-                        })
-                        .collect();
-
-                    symbols.push(SymbolInfo {
-                        name: name.clone(),
-                        kind: SymbolKind::Enum {
-                            variants: variant_infos,
-                        },
-                        detail: Some(format!("enum {}", name)),
-                        documentation: None,
-                        scope_level,
-                        range: None, // TODO: extract from AST node position
-                        selection_range: None,
-                        source_uri: None,
-                        is_exported: false, // TODO: Detect if enum is preceded by 'teach' keyword
-                    });
-                }
-                Stmt::Block(stmts) => {
-                    // Recursively extract symbols from blocks with increased scope level
-                    symbols.extend(Self::extract_symbols(stmts, scope_level + 1, stdlib_types));
-                }
-                _ => {}
-            }
-        }
-
-        // Second pass: extract methods from impl blocks and associate with structs
-        for stmt in stmts {
-            if let Stmt::ImplBlock {
-                type_name, methods, ..
-            } = stmt
-            {
-                // Extract method names from the impl block
-                let method_names: Vec<String> = methods
-                    .iter()
-                    .filter_map(|m| {
-                        if let Stmt::FunctionDecl { name, .. } = m {
-                            Some(name.clone())
-                        } else {
-                            None
-                        }
-                    })
-                    .collect();
-
-                // Find the struct and add methods to it
-                for symbol in symbols.iter_mut() {
-                    if symbol.name == *type_name {
-                        if let SymbolKind::Struct { methods, .. } = &mut symbol.kind {
-                            methods.extend(method_names.clone());
-                        }
-                    }
-                }
-
-                // Also extract symbols from the methods, injecting 'self'
-                for method in methods {
-                    if let Stmt::FunctionDecl {
-                        name,
-                        params,
-                        return_type,
-                        body,
-                        is_exported,
-                        ..
-                    } = method
-                    {
-                        let param_list: Vec<(String, String)> = params
-                            .iter()
-                            .map(|(n, t)| (n.clone(), Self::type_to_string(t)))
-                            .collect();
-
-                        // Add 'self' to the method scope
-                        let mut method_symbols = Vec::new();
-                        method_symbols.push(SymbolInfo {
-                            name: "self".to_string(),
-                            kind: SymbolKind::Variable {
-                                var_type: Some(type_name.clone()),
-                                mutable: false,
-                            },
-                            detail: Some(format!("self: {}", type_name)),
-                            documentation: Some("The instance of the struct".to_string()),
-                            scope_level: scope_level + 1,
-                            range: None,
-                            selection_range: None,
-                            source_uri: None,
-                            is_exported: false,
-                        });
-
-                        method_symbols.push(SymbolInfo {
-                            name: name.clone(),
-                            kind: SymbolKind::Function {
-                                params: param_list.clone(),
-                                return_type: Self::opt_type_to_string(return_type),
-                            },
-                            detail: Some(format!(
-                                "fn {}({})",
-                                name,
-                                param_list
-                                    .iter()
-                                    .map(|(n, t)| format!("{}: {}", n, t))
-                                    .collect::<Vec<_>>()
-                                    .join(", ")
-                            )),
-                            documentation: None,
-                            scope_level: scope_level + 1, // Method scope
-                            range: None,
-                            selection_range: None,
-                            source_uri: None,
-                            is_exported: *is_exported,
-                        });
-
-                        // Recursively extract symbols from function body
-                        if let Stmt::Block(body_stmts) = body.as_ref() {
-                            method_symbols.extend(Self::extract_symbols(
-                                body_stmts,
-                                scope_level + 2,
-                                stdlib_types,
-                            ));
-                        }
-
-                        symbols.extend(method_symbols);
-                    }
-                }
-            }
-        }
-
-        symbols
-    }
-
-    fn infer_type_from_expr(
-        expr: &Expr,
-        symbols: &[SymbolInfo],
-        stdlib_types: &StdlibTypes,
-    ) -> Option<String> {
-        match expr {
-            Expr::Number(_) => Some("num".to_string()),
-            Expr::String(_) => Some("str".to_string()),
-            Expr::Boolean(_) => Some("bool".to_string()),
-            Expr::ArrayLiteral(_) => Some("Array".to_string()),
-            Expr::StructLiteral { name, .. } => Some(name.clone()),
-            Expr::Call { func, .. } => {
-                // Try to infer return type from function call
-                match func.as_ref() {
-                    Expr::Ident(func_name) => {
-                        // Look up the function in symbols
-                        if let Some(func_symbol) = symbols.iter().find(|s| s.name == *func_name) {
-                            if let SymbolKind::Function { return_type, .. } = &func_symbol.kind {
-                                return Some(return_type.clone());
-                            }
-                        }
-                        None
-                    }
-                    Expr::FieldAccess { object, field } => {
-                        // Check if it's a builtin method call (e.g., web.get())
-                        if let Expr::Ident(obj_name) = object.as_ref() {
-                            // Look up the builtin and its method
-                            if let Some(builtin) = stdlib_types.builtins.get(obj_name) {
-                                if let Some(method) = builtin.methods.get(field) {
-                                    return Some(method.return_type.clone());
-                                }
-                            }
-                        }
-                        None
-                    }
-                    _ => None,
-                }
-            }
-            Expr::Lazy(inner) => {
-                // For lazy expressions, wrap the inner type in Promise<T>
-                // This represents a lazy future that will evaluate to the inner type
-                if let Some(inner_type) = Self::infer_type_from_expr(inner, symbols, stdlib_types) {
-                    Some(format!("Promise<{}>", inner_type))
-                } else {
-                    Some("Promise<unknown>".to_string())
-                }
-            }
-            Expr::Async(inner) => {
-                // For async expressions, wrap the inner type in Promise<T>
-                // This represents an eager future that will evaluate to the inner type
-                if let Some(inner_type) = Self::infer_type_from_expr(inner, symbols, stdlib_types) {
-                    Some(format!("Promise<{}>", inner_type))
-                } else {
-                    Some("Promise<unknown>".to_string())
-                }
-            }
-            Expr::Await(inner) => {
-                // For await expressions, unwrap the Promise<T> to get T
-                if let Some(inner_type) = Self::infer_type_from_expr(inner, symbols, stdlib_types) {
-                    // If it's a Promise<T>, extract T
-                    if inner_type.starts_with("Promise<") && inner_type.ends_with(">") {
-                        let t = &inner_type[8..inner_type.len() - 1];
-                        Some(t.to_string())
-                    } else {
-                        // If it's not a Promise type, just return the type as-is
-                        Some(inner_type)
-                    }
-                } else {
-                    None
-                }
-            }
-            Expr::Ident(name) => {
-                // Look up the variable in symbols to get its type
-                if let Some(var_symbol) = symbols.iter().find(|s| s.name == *name) {
-                    if let SymbolKind::Variable { var_type, .. } = &var_symbol.kind {
-                        return var_type.clone();
+                        locations.push(Location {
+                            uri: parsed_uri,
+                            range: Range {
+                                start: start_pos,
+                                end: end_pos,
+                            },
+                        });
                     }
+
                 }
-                None
+                // Past the word and the character that ends it, as at the
+                // end of a word the one before counts as under the cursor
+                col_num += found_word.len() + 1;
             }
-            _ => None,
         }
     }
 
-    fn type_to_string(ty: &Type) -> String {
-        match ty {
-            Type::Named(name) => name.clone(),
-            Type::Generic { base, type_args } => {
-                format!(
-                    "{}<{}>",
-                    base,
-                    type_args
-                        .iter()
-                        .map(Self::type_to_string)
-                        .collect::<Vec<_>>()
-                        .join(", ")
-                )
-            }
-            Type::Function {
-                params,
-                return_type,
-            } => {
-                format!(
-                    "fn({}) -> {}",
-                    params
+    /// A stub for `method_name` of `trait_name`, to add to an impl block
+    fn method_stub(&self, doc_data: &DocumentData, trait_name: &str, method_name: &str) -> String {
+        // User-defined traits first, then builtin ones
+        let signature = doc_data
+            .symbols
+            .iter()
+            .find(|s| s.name == trait_name)
+            .and_then(|symbol| match &symbol.kind {
+                SymbolKind::Trait { methods } => {
+                    methods.iter().find(|m| m.name == method_name).cloned()
+                }
+                _ => None,
+            })
+            .or_else(|| {
+                let method = self
+                    .stdlib_types
+                    .traits
+                    .get(trait_name)?
+                    .methods
+                    .get(method_name)?;
+                Some(TraitMethodInfo {
+                    name: method_name.to_string(),
+                    params: method
+                        .params
                         .iter()
-                        .map(Self::type_to_string)
-                        .collect::<Vec<_>>()
-                        .join(", "),
-                    Self::type_to_string(return_type)
-                )
-            }
-        }
-    }
+                        .map(|p| match p.split_once(':') {
+                            Some((n, t)) => (n.trim().to_string(), t.trim().to_string()),
+                            None => (p.clone(), "any".to_string()),
+                        })
+                        .collect(),
+                    return_type: method.return_type.clone(),
+                    has_default_impl: false,
+                })
+            });
 
-    fn opt_type_to_string(ty: &Option<Type>) -> String {
-        match ty {
-            Some(t) => Self::type_to_string(t),
-            None => "void".to_string(),
-        }
+        let (params, return_type) = match signature {
+            Some(sig) => {
+                let params = sig
+                    .params
+                    .iter()
+                    .map(|(n, t)| {
+                        if n == "self" {
+                            "self".to_string()
+                        } else {
+                            format!("{}: {}", n, t)
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                (params, sig.return_type)
+            }
+            None => ("self".to_string(), "void".to_string()),
+        };
+        format!(
+            "\n    fn {}({}) -> {} {{\n        throw \"{} is not implemented\";\n    }}\n",
+            method_name, params, return_type, method_name
+        )
     }
 
-    // Get the scope level at a given line position
     // Counts the number of open braces before the position
     fn get_scope_at_position(content: &str, line_number: usize) -> usize {
         let lines: Vec<&str> = content.lines().collect();
@@ -2397,16 +755,15 @@ impl LoftLanguageServer {
         }
     }
 
-    // Check if we're in a member access context (e.g., "object.")
+    // Check if we're in a member access context (e.g., "object." or "Type::")
     // Returns Some(object_name) if we are, None otherwise
     fn get_member_access_context(text: &str) -> Option<String> {
         let trimmed = text.trim_end();
-        if !trimmed.ends_with('.') {
-            return None;
-        }
+        let before_dot = trimmed
+            .strip_suffix("::")
+            .or_else(|| trimmed.strip_suffix('.'))?;
 
         // Extract the identifier before the dot
-        let before_dot = &trimmed[..trimmed.len() - 1];
         let chars: Vec<char> = before_dot.chars().collect();
 
         // Find the start of the identifier (working backwards)
@@ -2450,8 +807,8 @@ impl LoftLanguageServer {
     fn get_keyword_completions(&self) -> Vec<CompletionItem> {
         let keywords = [
             "fn", "let", "const", "if", "else", "while", "for", "return", "struct", "trait",
-            "impl", "learn", "teach", "async", "await", "try", "catch", "true", "false", "null",
-            "void", "num", "str", "bool", "Option", "Result",
+            "impl", "learn", "teach", "async", "await", "try", "catch", "finally", "throw",
+            "defer", "true", "false", "null", "void", "num", "str", "bool", "Option", "Result",
         ];
 
         keywords
@@ -2466,12 +823,14 @@ impl LoftLanguageServer {
             .collect()
     }
 
-    fn find_closing_brace(content: &str, start_line: usize) -> Option<usize> {
+    /// Position of the `}` closing the first block opened on or after `start_line`
+    fn find_closing_brace(content: &str, start_line: usize) -> Option<Position> {
         let lines: Vec<&str> = content.lines().collect();
         let mut open_braces = 0;
         let mut found_start = false;
 
         for (i, line) in lines.iter().enumerate().skip(start_line) {
+            let mut character = 0;
             for ch in line.chars() {
                 if ch == '{' {
                     open_braces += 1;
@@ -2479,9 +838,10 @@ impl LoftLanguageServer {
                 } else if ch == '}' {
                     open_braces -= 1;
                     if found_start && open_braces == 0 {
-                        return Some(i);
+                        return Some(Position::new(i as u32, character));
                     }
                 }
+                character += ch.len_utf16() as u32;
             }
         }
         None
@@ -2501,6 +861,18 @@ impl LoftLanguageServer {
 
         let mut items = Vec::new();
 
+        // A module bound by `learn "m" as name` offers its exports
+        {
+            let docs = self.documents.read().await;
+            if let Some(exports) = docs
+                .get(uri)
+                .and_then(|doc| doc.module_exports.get(object_name))
+            {
+                items.extend(exports.iter().map(Self::imported_symbol_completion));
+                return Ok(Some(CompletionResponse::Array(items)));
+            }
+        }
+
         // First, check if it's a builtin (term, math, time, fs, etc.)
         if let Some(builtin) = self.stdlib_types.builtins.get(object_name) {
             self.client
@@ -2613,6 +985,21 @@ impl LoftLanguageServer {
                     }
                     return Ok(Some(CompletionResponse::Array(items)));
                 }
+
+                // A struct name offers its associated functions, like Point.new
+                if let SymbolKind::Struct { associated, .. } = &symbol.kind {
+                    for function_name in associated {
+                        items.push(CompletionItem {
+                            label: function_name.clone(),
+                            kind: Some(CompletionItemKind::FUNCTION),
+                            detail: Some(format!("{}.{}(...)", object_name, function_name)),
+                            insert_text: Some(format!("{}($0)", function_name)),
+                            insert_text_format: Some(InsertTextFormat::SNIPPET),
+                            ..Default::default()
+                        });
+                    }
+                    return Ok(Some(CompletionResponse::Array(items)));
+                }
             }
 
             // Find the variable/symbol and its type
@@ -2795,13 +1182,23 @@ impl LoftLanguageServer {
                     }
                     // Handle user-defined structs
                     else {
-                        // Look for the struct definition in the document symbols
+                        // Look for the struct definition in the document symbols,
+                        // seeing a generic struct through its type arguments
+                        let (struct_name, _) = split_generic_type(type_name);
                         if let Some(struct_symbol) =
-                            doc_data.symbols.iter().find(|s| s.name == *type_name)
+                            doc_data.symbols.iter().find(|s| s.name == struct_name)
                         {
-                            if let SymbolKind::Struct { fields, methods } = &struct_symbol.kind {
+                            if let SymbolKind::Struct {
+                                type_params,
+                                fields,
+                                methods,
+                                ..
+                            } = &struct_symbol.kind
+                            {
                                 // Add fields
-                                for (field_name, field_type) in fields {
+                                for (field_name, field_type) in
+                                    instantiate_fields(type_params, fields, type_name)
+                                {
                                     items.push(CompletionItem {
                                         label: field_name.clone(),
                                         kind: Some(CompletionItemKind::FIELD),
@@ -2834,6 +1231,54 @@ impl LoftLanguageServer {
         Ok(Some(CompletionResponse::Array(items)))
     }
 
+    /// Completion item for a symbol imported from another module
+    fn imported_symbol_completion(symbol: &SymbolInfo) -> CompletionItem {
+        let (kind, insert_text) = match &symbol.kind {
+            SymbolKind::Variable { .. } | SymbolKind::Constant { .. } => {
+                (CompletionItemKind::VARIABLE, symbol.name.clone())
+            }
+            SymbolKind::Enum { .. } => (CompletionItemKind::ENUM, symbol.name.clone()),
+            SymbolKind::Function { params, .. } => {
+                let params_snippet = params
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (n, _))| format!("${{{}:{}}}", i + 1, n))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                (
+                    CompletionItemKind::FUNCTION,
+                    format!("{}({})$0", symbol.name, params_snippet),
+                )
+            }
+            SymbolKind::Struct { .. } => (CompletionItemKind::STRUCT, symbol.name.clone()),
+            SymbolKind::Import { .. } => (CompletionItemKind::MODULE, symbol.name.clone()),
+            SymbolKind::Trait { .. } => (CompletionItemKind::INTERFACE, symbol.name.clone()),
+        };
+
+        let mut detail = symbol.detail.clone().unwrap_or_default();
+        if let Some(source_uri) = &symbol.source_uri {
+            detail.push_str(&format!(
+                " (from {})",
+                source_uri.split('/').next_back().unwrap_or(source_uri)
+            ));
+        }
+
+        CompletionItem {
+            label: symbol.name.clone(),
+            kind: Some(kind),
+            detail: Some(detail),
+            documentation: symbol.documentation.as_ref().map(|d| {
+                Documentation::MarkupContent(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: d.clone(),
+                })
+            }),
+            insert_text: Some(insert_text),
+            insert_text_format: Some(InsertTextFormat::SNIPPET),
+            ..Default::default()
+        }
+    }
+
     fn format_symbol_hover(symbol: &SymbolInfo) -> String {
         let mut text = String::new();
 
@@ -2867,6 +1312,7 @@ impl LoftLanguageServer {
                 text.push_str("_(constant)_");
             }
             SymbolKind::Function {
+                type_params,
                 params,
                 return_type,
             } => {
@@ -2874,6 +1320,7 @@ impl LoftLanguageServer {
                 text.push_str("```loft\n");
                 text.push_str("fn ");
                 text.push_str(&symbol.name);
+                text.push_str(&type_params_to_string(type_params));
                 text.push('(');
                 text.push_str(
                     &params
@@ -2886,11 +1333,17 @@ impl LoftLanguageServer {
                 text.push_str("\n```\n\n");
                 text.push_str("_(function)_");
             }
-            SymbolKind::Struct { fields, methods } => {
+            SymbolKind::Struct {
+                type_params,
+                fields,
+                methods,
+                associated,
+            } => {
                 // Code block with struct definition
                 text.push_str("```loft\n");
                 text.push_str("struct ");
                 text.push_str(&symbol.name);
+                text.push_str(&type_params_to_string(type_params));
                 text.push_str(" {\n");
                 for (field_name, field_type) in fields {
                     text.push_str(&format!("    {}: {},\n", field_name, field_type));
@@ -2907,6 +1360,13 @@ impl LoftLanguageServer {
                         if methods.len() == 1 { "" } else { "s" }
                     ));
                 }
+                if !associated.is_empty() {
+                    text.push_str(&format!(
+                        "\n\n**{}** associated function{}",
+                        associated.len(),
+                        if associated.len() == 1 { "" } else { "s" }
+                    ));
+                }
             }
             SymbolKind::Enum { variants } => {
                 // Code block with enum definition
@@ -2946,6 +1406,16 @@ impl LoftLanguageServer {
                     }
                 }
             }
+            SymbolKind::Import { item, .. } => {
+                text.push_str("```loft\n");
+                text.push_str(symbol.detail.as_deref().unwrap_or(&symbol.name));
+                text.push_str("\n```\n\n");
+                text.push_str(if item.is_some() {
+                    "_(imported item)_"
+                } else {
+                    "_(module)_"
+                });
+            }
         }
 
         // Add documentation with separator if present
@@ -3065,6 +1535,11 @@ fn get_hover_text(word: &str) -> Option<String> {
         "async" => Some("**async** _keyword_\n\nMarks a function as asynchronous or creates an eager async expression.\n\n```loft\nasync fn fetch() -> str { }\nlet promise = async compute();\n```".to_string()),
         "await" => Some("**await** _keyword_\n\nAwaits an async expression.\n\n```loft\nlet result = await promise;\n```".to_string()),
         "lazy" => Some("**lazy** _keyword_\n\nCreates a lazily-evaluated async expression.\n\n```loft\nlet future = lazy expensive_computation();\n```".to_string()),
+        "try" => Some("**try** _keyword_\n\nRuns a block, sending any error it raises to `catch`.\n\n```loft\ntry { risky(); } catch (e) { term.println(e.message); } finally { cleanup(); }\n```".to_string()),
+        "catch" => Some("**catch** _keyword_\n\nHandles an error raised in a try block. The error has `message`, `stack` and the thrown `value`.\n\n```loft\ntry { risky(); } catch (e) { term.println(e.message); }\n```".to_string()),
+        "finally" => Some("**finally** _keyword_\n\nA block that always runs after try and catch, even when they return or fail.\n\n```loft\ntry { work(); } finally { cleanup(); }\n```".to_string()),
+        "throw" => Some("**throw** _keyword_\n\nRaises an error that a surrounding `catch` can handle.\n\n```loft\nthrow \"something went wrong\";\n```".to_string()),
+        "defer" => Some("**defer** _keyword_\n\nRuns an expression when the enclosing function exits, even if it fails. Deferred expressions run most recent first.\n\n```loft\nlet file = fs.open(\"data.txt\");\ndefer file.close();\n```".to_string()),
         
         // Types
         "num" => Some("**num** _type_\n\nNumeric type (integer or decimal).".to_string()),
@@ -3103,7 +1578,16 @@ impl LoftLanguageServer {
 }
 
 impl LanguageServer for LoftLanguageServer {
-    async fn initialize(&self, _: InitializeParams) -> Result<InitializeResult> {
+    async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+        #[allow(deprecated)]
+        let folders: Vec<Uri> = match params.workspace_folders {
+            Some(folders) => folders.into_iter().map(|folder| folder.uri).collect(),
+            None => params.root_uri.into_iter().collect(),
+        };
+        for folder in folders.iter().filter_map(Self::uri_to_file_path) {
+            self.index_project(folder).await;
+        }
+
         Ok(InitializeResult {
             server_info: Some(ServerInfo {
                 name: "loft-lsp".to_string(),
@@ -3116,7 +1600,7 @@ impl LanguageServer for LoftLanguageServer {
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
                 completion_provider: Some(CompletionOptions {
                     resolve_provider: Some(false),
-                    trigger_characters: Some(vec![".".to_string()]),
+                    trigger_characters: Some(vec![".".to_string(), ":".to_string()]),
                     all_commit_characters: None,
                     work_done_progress_options: WorkDoneProgressOptions::default(),
                     completion_item: None,
@@ -3140,6 +1624,10 @@ impl LanguageServer for LoftLanguageServer {
                 // Enable document formatting
                 document_formatting_provider: Some(OneOf::Left(true)),
                 document_range_formatting_provider: Some(OneOf::Left(true)),
+                document_on_type_formatting_provider: Some(DocumentOnTypeFormattingOptions {
+                    first_trigger_character: "}".to_string(),
+                    more_trigger_character: None,
+                }),
                 // Enable semantic tokens
                 semantic_tokens_provider: Some(
                     SemanticTokensServerCapabilities::SemanticTokensOptions(
@@ -3189,7 +1677,16 @@ impl LanguageServer for LoftLanguageServer {
                     ),
                 ),
                 // Enable code actions
-                code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+                code_action_provider: Some(CodeActionProviderCapability::Options(
+                    CodeActionOptions {
+                        code_action_kinds: Some(vec![
+                            CodeActionKind::QUICKFIX,
+                            CodeActionKind::REFACTOR,
+                            CodeActionKind::SOURCE_ORGANIZE_IMPORTS,
+                        ]),
+                        ..Default::default()
+                    },
+                )),
                 // Enable inlay hints
                 inlay_hint_provider: Some(OneOf::Left(true)),
                 // Enable folding ranges
@@ -3216,6 +1713,44 @@ impl LanguageServer for LoftLanguageServer {
         self.client
             .log_message(MessageType::INFO, "loft LSP server initialized!")
             .await;
+
+        // Keep the workspace index up to date with files changed outside the editor
+        let watch = DidChangeWatchedFilesRegistrationOptions {
+            watchers: vec![FileSystemWatcher {
+                glob_pattern: GlobPattern::String("**/*.lf".to_string()),
+                kind: None,
+            }],
+        };
+        let registration = Registration {
+            id: "loft-watch-sources".to_string(),
+            method: "workspace/didChangeWatchedFiles".to_string(),
+            register_options: serde_json::to_value(watch).ok(),
+        };
+        if let Err(e) = self.client.register_capability(vec![registration]).await {
+            self.client
+                .log_message(
+                    MessageType::WARNING,
+                    format!("Could not watch source files: {}", e),
+                )
+                .await;
+        }
+    }
+
+    async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
+        for change in params.changes {
+            let uri = change.uri.to_string();
+            let indexed = match Self::uri_to_file_path(&change.uri) {
+                Some(path) if change.typ != FileChangeType::DELETED => {
+                    index_file(&path, &self.stdlib_types)
+                }
+                _ => None,
+            };
+            let mut index = self.workspace_index.write().await;
+            match indexed {
+                Some((_, symbols)) => index.insert(uri, symbols),
+                None => index.remove(&uri),
+            };
+        }
     }
 
     async fn shutdown(&self) -> Result<()> {
@@ -3243,8 +1778,8 @@ impl LanguageServer for LoftLanguageServer {
                     content: content.clone(),
                     version,
                     symbols: Vec::new(),
-                    imports: Vec::new(),
                     imported_symbols: Vec::new(),
+                    module_exports: HashMap::new(),
                     uri: uri.clone(),
                 },
             );
@@ -3260,6 +1795,15 @@ impl LanguageServer for LoftLanguageServer {
         // Parse and report diagnostics
         self.parse_and_report_diagnostics(&params.text_document.uri, &content)
             .await;
+
+        // Files opened outside the workspace folders bring their project into the index
+        let manifest = match Self::uri_to_file_path(&params.text_document.uri) {
+            Some(path) => self.find_manifest(&path).await,
+            None => None,
+        };
+        if let Some(root) = manifest.as_deref().and_then(Path::parent) {
+            self.index_project(root.to_path_buf()).await;
+        }
     }
 
     async fn did_change(&self, params: DidChangeTextDocumentParams) {
@@ -3285,8 +1829,8 @@ impl LanguageServer for LoftLanguageServer {
                         content: content.clone(),
                         version,
                         symbols: Vec::new(),
-                        imports: Vec::new(),
                         imported_symbols: Vec::new(),
+                        module_exports: HashMap::new(),
                         uri: uri.clone(),
                     },
                 );
@@ -3735,7 +2279,7 @@ impl LanguageServer for LoftLanguageServer {
         }
 
         // Check if it's an imported symbol
-        if let Some(imported_symbol) = doc_data.imported_symbols.iter().find(|s| s.name == word) {
+        if let Some(imported_symbol) = doc_data.find_imported(&word) {
             self.client
                 .log_message(
                     MessageType::LOG,
@@ -3808,8 +2352,7 @@ impl LanguageServer for LoftLanguageServer {
 
                     // Add standard library modules
                     // These are available globally but can be imported for clarity or aliasing
-                    let std_modules = ["term", "math", "time", "fs", "os", "http"];
-                    for mod_name in std_modules {
+                    for mod_name in crate::runtime::builtin_registry::module_names() {
                         import_items.push(CompletionItem {
                             label: mod_name.to_string(),
                             kind: Some(CompletionItemKind::MODULE),
@@ -4262,7 +2805,7 @@ impl LanguageServer for LoftLanguageServer {
                 detail: Some("Try-catch error handling".to_string()),
                 documentation: Some(Documentation::MarkupContent(MarkupContent {
                     kind: MarkupKind::Markdown,
-                    value: "Handle errors with try-catch.\n\n```loft\ntry {\n    // code\n} catch (error) {\n    // handle error\n}\n```".to_string(),
+                    value: "Handle errors with try-catch.\n\n```loft\ntry {\n    // code\n} catch (error) {\n    term.println(error.message);\n}\n```".to_string(),
                 })),
                 insert_text: Some("try {\n    ${1:// code}\n} catch (${2:error}) {\n    ${3:// handle error}\n}$0".to_string()),
                 insert_text_format: Some(InsertTextFormat::SNIPPET),
@@ -4485,6 +3028,7 @@ impl LanguageServer for LoftLanguageServer {
                         )
                     }
                     SymbolKind::Struct { .. } => (CompletionItemKind::STRUCT, symbol.name.clone()),
+                    SymbolKind::Import { .. } => (CompletionItemKind::MODULE, symbol.name.clone()),
                     SymbolKind::Trait { .. } => {
                         (CompletionItemKind::INTERFACE, symbol.name.clone())
                     }
@@ -4507,55 +3051,12 @@ impl LanguageServer for LoftLanguageServer {
             }
 
             // Add imported symbols to completions
-            for symbol in &doc_data.imported_symbols {
-                let (kind, insert_text) = match &symbol.kind {
-                    SymbolKind::Variable { .. } | SymbolKind::Constant { .. } => {
-                        (CompletionItemKind::VARIABLE, symbol.name.clone())
-                    }
-                    SymbolKind::Enum { .. } => {
-                        (CompletionItemKind::ENUM, symbol.name.clone())
-                    }
-                    SymbolKind::Function { params, .. } => {
-                        let params_snippet = params
-                            .iter()
-                            .enumerate()
-                            .map(|(i, (n, _))| format!("${{{}:{}}}", i + 1, n))
-                            .collect::<Vec<_>>()
-                            .join(", ");
-                        (
-                            CompletionItemKind::FUNCTION,
-                            format!("{}({})$0", symbol.name, params_snippet),
-                        )
-                    }
-                    SymbolKind::Struct { .. } => (CompletionItemKind::STRUCT, symbol.name.clone()),
-                    SymbolKind::Trait { .. } => {
-                        (CompletionItemKind::INTERFACE, symbol.name.clone())
-                    }
-                };
-
-                let mut detail = symbol.detail.clone().unwrap_or_default();
-                if let Some(source_uri) = &symbol.source_uri {
-                    detail.push_str(&format!(
-                        " (from {})",
-                        source_uri.split('/').next_back().unwrap_or(source_uri)
-                    ));
-                }
-
-                items.push(CompletionItem {
-                    label: symbol.name.clone(),
-                    kind: Some(kind),
-                    detail: Some(detail),
-                    documentation: symbol.documentation.as_ref().map(|d| {
-                        Documentation::MarkupContent(MarkupContent {
-                            kind: MarkupKind::Markdown,
-                            value: d.clone(),
-                        })
-                    }),
-                    insert_text: Some(insert_text),
-                    insert_text_format: Some(InsertTextFormat::SNIPPET),
-                    ..Default::default()
-                });
-            }
+            items.extend(
+                doc_data
+                    .imported_symbols
+                    .iter()
+                    .map(Self::imported_symbol_completion),
+            );
         }
         drop(docs);
 
@@ -4650,7 +3151,7 @@ impl LanguageServer for LoftLanguageServer {
                         .text_document
                         .uri
                         .clone(),
-                    range: *range,
+                    range: (*range).into(),
                 };
 
                 self.client
@@ -4668,12 +3169,12 @@ impl LanguageServer for LoftLanguageServer {
         }
 
         // Check imported symbols
-        if let Some(imported_symbol) = doc_data.imported_symbols.iter().find(|s| s.name == word) {
+        if let Some(imported_symbol) = doc_data.find_imported(&word) {
             if let Some(source_uri) = &imported_symbol.source_uri {
                 // Try to parse the URI and get the location
                 if let Ok(source_url) = Uri::from_str(source_uri) {
                     // If the symbol has a range, use it; otherwise use the beginning of the file
-                    let range = imported_symbol.range.unwrap_or(Range {
+                    let range = imported_symbol.range.map(Range::from).unwrap_or(Range {
                         start: Position {
                             line: 0,
                             character: 0,
@@ -4770,7 +3271,7 @@ impl LanguageServer for LoftLanguageServer {
             // Check if this document imports from the current document or references the symbol
             let should_search = if is_exported {
                 // If symbol is exported, check if this document imports it
-                doc.imported_symbols.iter().any(|s| s.name == word)
+                doc.find_imported(&word).is_some()
             } else {
                 // Search anyway - symbol might be referenced in other files
                 true
@@ -4798,7 +3299,7 @@ impl LanguageServer for LoftLanguageServer {
                     if let Some(range) = &symbol.range {
                         let def_loc = Location {
                             uri: Uri::from_str(&uri).unwrap(),
-                            range: *range,
+                            range: (*range).into(),
                         };
 
                         if !locations
@@ -4863,10 +3364,11 @@ impl LanguageServer for LoftLanguageServer {
                     SymbolKind::Function { .. } => tower_lsp::lsp_types::SymbolKind::FUNCTION,
                     SymbolKind::Struct { .. } => tower_lsp::lsp_types::SymbolKind::STRUCT,
                     SymbolKind::Trait { .. } => tower_lsp::lsp_types::SymbolKind::INTERFACE,
+                    SymbolKind::Import { .. } => tower_lsp::lsp_types::SymbolKind::MODULE,
                 };
 
                 // For now, use a default range if we don't have position info
-                let range = symbol.range.unwrap_or(Range {
+                let range = symbol.range.map(Range::from).unwrap_or(Range {
                     start: Position {
                         line: 0,
                         character: 0,
@@ -4877,7 +3379,7 @@ impl LanguageServer for LoftLanguageServer {
                     },
                 });
 
-                let selection_range = symbol.selection_range.unwrap_or(range);
+                let selection_range = symbol.selection_range.map(Range::from).unwrap_or(range);
 
                 #[allow(deprecated)]
                 document_symbols.push(DocumentSymbol {
@@ -4997,6 +3499,7 @@ impl LanguageServer for LoftLanguageServer {
                 if let SymbolKind::Function {
                     params,
                     return_type,
+                    ..
                 } = &symbol.kind
                 {
                     // Build signature information
@@ -5235,25 +3738,24 @@ impl LanguageServer for LoftLanguageServer {
         drop(docs);
 
         // Format the document using TokenFormatter
-        let formatter = TokenFormatter::new();
+        let formatter = self
+            .formatter_for(&params.text_document.uri, &params.options)
+            .await;
         match formatter.format(&doc_data.content) {
             Ok(formatted) => {
                 // Only return edits if content changed
                 if formatted != doc_data.content {
-                    // Calculate the range of the entire document
-                    let lines: Vec<&str> = doc_data.content.lines().collect();
-                    let line_count = lines.len() as u32;
-                    let last_line = lines.last().unwrap_or(&"");
-                    let last_line_len = last_line.len() as u32;
-
+                    // Replace the entire document, including its final newline
+                    let content = &doc_data.content;
+                    let last_line = content.rsplit('\n').next().unwrap_or("");
                     let range = Range {
                         start: Position {
                             line: 0,
                             character: 0,
                         },
                         end: Position {
-                            line: line_count.saturating_sub(1),
-                            character: last_line_len,
+                            line: content.matches('\n').count() as u32,
+                            character: last_line.encode_utf16().count() as u32,
                         },
                     };
 
@@ -5288,87 +3790,51 @@ impl LanguageServer for LoftLanguageServer {
         &self,
         params: DocumentRangeFormattingParams,
     ) -> Result<Option<Vec<TextEdit>>> {
-        let uri = params.text_document.uri.to_string();
-
         self.client
             .log_message(
                 MessageType::LOG,
-                format!("Range formatting request for {}", uri),
+                format!(
+                    "Range formatting request for {}",
+                    params.text_document.uri.as_str()
+                ),
             )
             .await;
 
-        // Get document content
-        let docs = self.documents.read().await;
-        let doc_data = match docs.get(&uri) {
-            Some(data) => data.clone(),
-            None => return Ok(None),
-        };
-        drop(docs);
+        // A selection that ends at the start of a line doesn't include that line
+        let start_line = params.range.start.line;
+        let mut end_line = params.range.end.line;
+        if params.range.end.character == 0 && end_line > start_line {
+            end_line -= 1;
+        }
 
-        // Extract the range to format
-        let lines: Vec<&str> = doc_data.content.lines().collect();
-        let start_line = params.range.start.line as usize;
-        let end_line = params.range.end.line as usize;
+        Ok(self
+            .format_lines(
+                &params.text_document.uri,
+                &params.options,
+                start_line as usize,
+                end_line as usize,
+            )
+            .await)
+    }
 
-        // Validate range
-        if start_line >= lines.len() || end_line >= lines.len() || start_line > end_line {
-            self.client
-                .log_message(
-                    MessageType::ERROR,
-                    "Invalid range for formatting".to_string(),
-                )
-                .await;
+    async fn on_type_formatting(
+        &self,
+        params: DocumentOnTypeFormattingParams,
+    ) -> Result<Option<Vec<TextEdit>>> {
+        // Re-indent the line of a `}` that was just typed
+        let position = params.text_document_position.position;
+        if params.ch != "}" {
             return Ok(None);
         }
 
-        // Extract the selected range
-        let selected_text = lines[start_line..=end_line].join("\n");
-
-        // Format the selected text
-        let formatter = TokenFormatter::new();
-        match formatter.format(&selected_text) {
-            Ok(formatted) => {
-                // Only return edits if content changed
-                if formatted != selected_text {
-                    let range = Range {
-                        start: Position {
-                            line: start_line as u32,
-                            character: 0,
-                        },
-                        end: Position {
-                            line: end_line as u32,
-                            character: lines[end_line].len() as u32,
-                        },
-                    };
-
-                    self.client
-                        .log_message(
-                            MessageType::LOG,
-                            "Range formatting completed successfully".to_string(),
-                        )
-                        .await;
-
-                    Ok(Some(vec![TextEdit {
-                        range,
-                        new_text: formatted,
-                    }]))
-                } else {
-                    self.client
-                        .log_message(MessageType::LOG, "No formatting changes needed".to_string())
-                        .await;
-                    Ok(None)
-                }
-            }
-            Err(err) => {
-                self.client
-                    .log_message(
-                        MessageType::ERROR,
-                        format!("Range formatting failed: {}", err),
-                    )
-                    .await;
-                Ok(None)
-            }
-        }
+        Ok(self
+            .format_lines(
+                &params.text_document_position.text_document.uri,
+                &params.options,
+                position.line as usize,
+                position.line as usize,
+            )
+            .await)
     }
 
     async fn semantic_tokens_full(
@@ -5404,12 +3870,12 @@ impl LanguageServer for LoftLanguageServer {
         let operator_token = 21u32;
         let string_token = 18u32;
         let number_token = 19u32;
-
+        let namespace_token = 0u32;
         // Keywords to highlight
         let keywords = &[
             "learn", "teach", "fn", "let", "mut", "const", "if", "else", "while", "for", "in",
             "return", "break", "continue", "def", "impl", "trait", "enum", "match", "async",
-            "await", "lazy",
+            "await", "lazy", "try", "catch", "finally", "throw", "defer",
         ];
 
         // Operator characters
@@ -5499,9 +3965,12 @@ impl LanguageServer for LoftLanguageServer {
                 }
 
                 // Numbers
-                if chars[col].is_numeric() {
+                if chars[col].is_ascii_digit() {
                     let start_col = col;
-                    while col < chars.len() && (chars[col].is_numeric() || chars[col] == '.') {
+                    // Letters and underscores cover `0xFF` and `1_000`
+                    while col < chars.len()
+                        && (chars[col].is_alphanumeric() || chars[col] == '_' || chars[col] == '.')
+                    {
                         col += 1;
                     }
 
@@ -5576,6 +4045,7 @@ impl LanguageServer for LoftLanguageServer {
                                     SymbolKind::Struct { .. } | SymbolKind::Trait { .. } | SymbolKind::Enum { .. } => {
                                         type_token
                                     }
+                                    SymbolKind::Import { .. } => namespace_token,
                                 }
                             } else {
                                 variable_token
@@ -5679,204 +4149,108 @@ impl LanguageServer for LoftLanguageServer {
             Ok(Some(SemanticTokensRangeResult::Tokens(tokens)))
         } else {
             Ok(None)
-        }
-    }
-
-    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
-        let uri = params.text_document.uri.to_string();
-
-        self.client
-            .log_message(MessageType::LOG, format!("Code action request for {}", uri))
-            .await;
-
-        // Get document content
-        let docs = self.documents.read().await;
-        let doc_data = match docs.get(&uri) {
-            Some(data) => data.clone(),
-            None => return Ok(None),
-        };
-        drop(docs);
-
-        let mut code_actions = Vec::new();
-
-        // Check diagnostics for common errors and provide quick fixes
-        for diagnostic in &params.context.diagnostics {
-            // Quick fix for missing trait implementation
-            if diagnostic
-                .message
-                .contains("Missing implementation for methods")
-            {
-                if let Some(data) = &diagnostic.data {
-                    if let Ok(info) = serde_json::from_value::<serde_json::Value>(data.clone()) {
-                        if let Some(missing_methods) = info["missing_methods"].as_array() {
-                            let trait_name = info["trait_name"].as_str().unwrap_or("");
-
-                            // Generate method stubs
-                            let mut stubs = String::new();
-
-                            for method_val in missing_methods {
-                                let method_name = method_val.as_str().unwrap_or("");
-                                if method_name.is_empty() {
-                                    continue;
-                                }
-
-                                // Find method signature
-                                let mut signature = None;
-
-                                // Check user-defined traits
-                                if let Some(trait_symbol) =
-                                    doc_data.symbols.iter().find(|s| s.name == trait_name)
-                                {
-                                    if let SymbolKind::Trait { methods } = &trait_symbol.kind {
-                                        if let Some(m) =
-                                            methods.iter().find(|m| m.name == method_name)
-                                        {
-                                            signature = Some(m.clone());
-                                        }
-                                    }
-                                }
-
-                                // Check builtin traits
-                                if signature.is_none() {
-                                    if let Some(trait_def) =
-                                        self.stdlib_types.traits.get(trait_name)
-                                    {
-                                        if let Some(method_def) = trait_def.methods.get(method_name)
-                                        {
-                                            let params: Vec<(String, String)> = method_def
-                                                .params
-                                                .iter()
-                                                .map(|p| {
-                                                    if let Some((n, t)) = p.split_once(':') {
-                                                        (n.trim().to_string(), t.trim().to_string())
-                                                    } else {
-                                                        (p.clone(), "any".to_string())
-                                                    }
-                                                })
-                                                .collect();
-
-                                            signature = Some(TraitMethodInfo {
-                                                name: method_name.to_string(),
-                                                params,
-                                                return_type: method_def.return_type.clone(),
-                                                has_default_impl: false,
-                                            });
-                                        }
-                                    }
-                                }
-
-                                if let Some(sig) = signature {
-                                    stubs.push_str(&format!("\n    fn {}(", sig.name));
-
-                                    let params_str = sig
-                                        .params
-                                        .iter()
-                                        .map(|(n, t)| {
-                                            if n == "self" {
-                                                "self".to_string()
-                                            } else {
-                                                format!("{}: {}", n, t)
-                                            }
-                                        })
-                                        .collect::<Vec<_>>()
-                                        .join(", ");
-                                    stubs.push_str(&params_str);
+        }
+    }
 
-                                    stubs.push_str(&format!(") -> {} {{\n        // TODO: Implement {}\n        panic(\"Not implemented\");\n    }}\n", sig.return_type, sig.name));
-                                } else {
-                                    stubs.push_str(&format!("\n    fn {}(self) -> void {{\n        // TODO: Implement {}\n    }}\n", method_name, method_name));
-                                }
-                            }
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        let uri = params.text_document.uri.to_string();
 
-                            let start_line = diagnostic.range.start.line as usize;
-                            if let Some(end_line) =
-                                Self::find_closing_brace(&doc_data.content, start_line)
-                            {
-                                let mut changes = HashMap::new();
-                                changes.insert(
-                                    params.text_document.uri.clone(),
-                                    vec![TextEdit {
-                                        range: Range {
-                                            start: Position {
-                                                line: end_line as u32,
-                                                character: 0,
-                                            },
-                                            end: Position {
-                                                line: end_line as u32,
-                                                character: 0,
-                                            },
-                                        },
-                                        new_text: stubs,
-                                    }],
-                                );
-
-                                code_actions.push(CodeActionOrCommand::CodeAction(CodeAction {
-                                    title: format!("Implement missing members for {}", trait_name),
-                                    kind: Some(CodeActionKind::QUICKFIX),
-                                    diagnostics: Some(vec![diagnostic.clone()]),
-                                    edit: Some(WorkspaceEdit {
-                                        changes: Some(changes),
-                                        document_changes: None,
-                                        change_annotations: None,
-                                    }),
-                                    command: None,
-                                    is_preferred: Some(true),
-                                    disabled: None,
-                                    data: None,
-                                }));
-                            }
-                        }
-                    }
-                }
-            }
+        self.client
+            .log_message(MessageType::LOG, format!("Code action request for {}", uri))
+            .await;
 
-            // Quick fix for undefined identifier - suggest adding import
-            if diagnostic.message.contains("Undefined identifier") {
-                // Extract the undefined symbol name from the error message
-                if let Some(symbol_name) = extract_symbol_from_error(&diagnostic.message) {
-                    // Try to find which module might provide this symbol
-                    // For now, suggest a standard library import as an example
-                    let import_path = Self::suggest_import_for_symbol(&symbol_name);
+        // Get document content
+        let docs = self.documents.read().await;
+        let doc_data = match docs.get(&uri) {
+            Some(data) => data.clone(),
+            None => return Ok(None),
+        };
+        drop(docs);
 
-                    if let Some(import) = import_path {
-                        // Find where to insert the import (after existing imports or at the top)
-                        let insert_line = Self::find_import_insertion_line(&doc_data.content);
+        let mut code_actions = Vec::new();
 
-                        let mut changes = HashMap::new();
-                        changes.insert(
-                            params.text_document.uri.clone(),
-                            vec![TextEdit {
-                                range: Range {
-                                    start: Position {
-                                        line: insert_line,
-                                        character: 0,
-                                    },
-                                    end: Position {
-                                        line: insert_line,
-                                        character: 0,
-                                    },
-                                },
-                                new_text: format!("learn \"{}\";\n", import),
-                            }],
-                        );
+        // Quick fixes for the diagnostics in range
+        for diagnostic in &params.context.diagnostics {
+            let uri = &params.text_document.uri;
+            let insert = |position: Position, new_text: String| {
+                let mut changes = HashMap::new();
+                changes.insert(
+                    uri.clone(),
+                    vec![TextEdit {
+                        range: Range {
+                            start: position,
+                            end: position,
+                        },
+                        new_text,
+                    }],
+                );
+                Some(WorkspaceEdit {
+                    changes: Some(changes),
+                    document_changes: None,
+                    change_annotations: None,
+                })
+            };
 
-                        code_actions.push(CodeActionOrCommand::CodeAction(CodeAction {
-                            title: format!("Add import for '{}'", symbol_name),
-                            kind: Some(CodeActionKind::QUICKFIX),
-                            diagnostics: Some(vec![diagnostic.clone()]),
-                            edit: Some(WorkspaceEdit {
-                                changes: Some(changes),
-                                document_changes: None,
-                                change_annotations: None,
-                            }),
-                            command: None,
-                            is_preferred: Some(true),
-                            disabled: None,
-                            data: None,
-                        }));
-                    }
+            // Add stubs for the methods an impl block is missing
+            if diagnostic.code == Some(NumberOrString::String("L0008".to_string())) {
+                let data = diagnostic.data.as_ref();
+                let trait_name = data.and_then(|d| d["trait_name"].as_str());
+                let missing_methods = data.and_then(|d| d["missing_methods"].as_array());
+                let closing_brace = Self::find_closing_brace(
+                    &doc_data.content,
+                    diagnostic.range.start.line as usize,
+                );
+                if let (Some(trait_name), Some(missing_methods), Some(closing_brace)) =
+                    (trait_name, missing_methods, closing_brace)
+                {
+                    let stubs: String = missing_methods
+                        .iter()
+                        .filter_map(|m| m.as_str())
+                        .map(|m| self.method_stub(&doc_data, trait_name, m))
+                        .collect();
+                    code_actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                        title: format!("Implement missing methods of {}", trait_name),
+                        kind: Some(CodeActionKind::QUICKFIX),
+                        diagnostics: Some(vec![diagnostic.clone()]),
+                        edit: insert(closing_brace, stubs),
+                        command: None,
+                        is_preferred: Some(true),
+                        disabled: None,
+                        data: None,
+                    }));
                 }
             }
+
+            // The fixes analysis attached, like a `learn` for an undefined name
+            let fixes: Vec<analysis::Fix> = diagnostic
+                .data
+                .as_ref()
+                .and_then(|data| serde_json::from_value(data.get("fixes")?.clone()).ok())
+                .unwrap_or_default();
+            for fix in fixes {
+                let edits = fix
+                    .edits
+                    .into_iter()
+                    .map(|edit| TextEdit {
+                        range: edit.range.into(),
+                        new_text: edit.new_text,
+                    })
+                    .collect();
+                code_actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                    title: fix.title,
+                    kind: Some(CodeActionKind::QUICKFIX),
+                    diagnostics: Some(vec![diagnostic.clone()]),
+                    edit: Some(WorkspaceEdit {
+                        changes: Some(HashMap::from([(uri.clone(), edits)])),
+                        document_changes: None,
+                        change_annotations: None,
+                    }),
+                    command: None,
+                    is_preferred: Some(fix.preferred),
+                    disabled: None,
+                    data: None,
+                }));
+            }
         }
 
         // Refactoring actions based on selection
@@ -5958,6 +4332,35 @@ impl LanguageServer for LoftLanguageServer {
             }
         }
 
+        // Sort, merge and prune the imports, on request or when the editor runs it on save
+        let wants_source = params.context.only.as_ref().is_none_or(|only| {
+            only.iter().any(|kind| {
+                CodeActionKind::SOURCE_ORGANIZE_IMPORTS
+                    .as_str()
+                    .starts_with(kind.as_str())
+            })
+        });
+        if wants_source {
+            if let Some(edit) = self.organize_imports_edit(&doc_data) {
+                let mut changes = HashMap::new();
+                changes.insert(params.text_document.uri.clone(), vec![edit]);
+                code_actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                    title: "Organize imports".to_string(),
+                    kind: Some(CodeActionKind::SOURCE_ORGANIZE_IMPORTS),
+                    diagnostics: None,
+                    edit: Some(WorkspaceEdit {
+                        changes: Some(changes),
+                        document_changes: None,
+                        change_annotations: None,
+                    }),
+                    command: None,
+                    is_preferred: None,
+                    disabled: None,
+                    data: None,
+                }));
+            }
+        }
+
         self.client
             .log_message(
                 MessageType::LOG,
@@ -6126,16 +4529,27 @@ impl LanguageServer for LoftLanguageServer {
         let query = params.query.to_lowercase();
         let mut symbols = Vec::new();
 
-        // Search through all open documents
+        // Open documents are searched as edited, the rest of the project as indexed
         let docs = self.documents.read().await;
-        for (uri_str, doc_data) in docs.iter() {
-            // Filter symbols by query
-            for symbol in &doc_data.symbols {
+        let index = self.workspace_index.read().await;
+        let files = docs
+            .iter()
+            .map(|(uri, doc_data)| (uri, &doc_data.symbols))
+            .chain(index.iter().filter(|(uri, _)| !docs.contains_key(*uri)));
+        for (uri_str, file_symbols) in files {
+            let Ok(uri) = Uri::from_str(uri_str) else {
+                continue;
+            };
+            for symbol in file_symbols {
                 // Fuzzy match: check if query chars appear in order in symbol name
                 if fuzzy_match(&symbol.name.to_lowercase(), &query) {
                     let location = Location {
-                        uri: Uri::from_str(uri_str).unwrap(),
-                        range: symbol.range.unwrap_or_else(Range::default),
+                        uri: uri.clone(),
+                        range: symbol
+                            .selection_range
+                            .or(symbol.range)
+                            .map(Range::from)
+                            .unwrap_or_default(),
                     };
 
                     let lsp_kind = match &symbol.kind {
@@ -6145,6 +4559,7 @@ impl LanguageServer for LoftLanguageServer {
                         SymbolKind::Struct { .. } => tower_lsp::lsp_types::SymbolKind::STRUCT,
                         SymbolKind::Trait { .. } => tower_lsp::lsp_types::SymbolKind::INTERFACE,
                         SymbolKind::Enum { .. } => tower_lsp::lsp_types::SymbolKind::ENUM,
+                        SymbolKind::Import { .. } => tower_lsp::lsp_types::SymbolKind::MODULE,
                     };
 
                     #[allow(deprecated)]
@@ -6258,7 +4673,7 @@ impl LanguageServer for LoftLanguageServer {
                     let ref_count = self.count_references(&symbol.name, &doc_data.content);
 
                     lenses.push(CodeLens {
-                        range: *range,
+                        range: (*range).into(),
                         command: Some(Command {
                             title: format!(
                                 "{} reference{}",
@@ -6310,8 +4725,8 @@ impl LanguageServer for LoftLanguageServer {
                             tags: None,
                             detail: symbol.detail.clone(),
                             uri: Uri::from_str(&uri).unwrap(),
-                            range: *range,
-                            selection_range: symbol.selection_range.unwrap_or(*range),
+                            range: (*range).into(),
+                            selection_range: symbol.selection_range.unwrap_or(*range).into(),
                             data: None,
                         };
                         return Ok(Some(vec![item]));
@@ -6453,7 +4868,7 @@ impl LanguageServer for LoftLanguageServer {
                                         tags: None,
                                         detail: symbol.detail.clone(),
                                         uri: Uri::from_str(&uri).unwrap(),
-                                        range: symbol.range.unwrap_or(Range {
+                                        range: symbol.range.map(Range::from).unwrap_or(Range {
                                             start: Position { line: line_num as u32, character: 0 },
                                             end: Position { line: line_num as u32, character: line.len() as u32 },
                                         }),
@@ -6492,18 +4907,18 @@ impl LanguageServer for LoftLanguageServer {
 }
 
 // Helper to extract symbol name from error messages
-fn extract_symbol_from_error(message: &str) -> Option<String> {
-    // Try to extract symbol name from common error patterns
-    // e.g., "undefined symbol 'foo'" -> "foo"
-    // e.g., "'bar' not found" -> "bar"
-
-    if let Some(start) = message.find('\'') {
-        if let Some(end) = message[start + 1..].find('\'') {
-            return Some(message[start + 1..start + 1 + end].to_string());
-        }
-    }
-
-    None
+/// The top-level declarations of a file for the workspace index, with the file's URI
+fn index_file(path: &Path, stdlib_types: &StdlibTypes) -> Option<(String, Vec<SymbolInfo>)> {
+    let source = std::fs::read_to_string(path).ok()?;
+    let uri = Uri::from_file_path(path).ok()?;
+    let mut parser = Parser::new(InputStream::new(uri.as_str(), &source));
+    let (stmts, _) = parser.parse_recoverable();
+    let mut symbols: Vec<SymbolInfo> = extract_symbols(&stmts, 0, stdlib_types)
+        .into_iter()
+        .filter(|s| s.scope_level == 0 && !matches!(s.kind, SymbolKind::Import { .. }))
+        .collect();
+    locate_declarations(&stmts, &source, &mut symbols);
+    Some((uri.to_string(), symbols))
 }
 
 // Fuzzy match helper for workspace symbol search
@@ -6545,8 +4960,7 @@ mod tests {
 
     // Helper to load stdlib_types for tests
     fn load_stdlib_types() -> StdlibTypes {
-        let stdlib_json = include_str!("stdlib_types.json");
-        serde_json::from_str::<StdlibTypes>(stdlib_json).expect("Failed to parse stdlib_types.json")
+        stdlib::load()
     }
 
     #[test]
@@ -6582,7 +4996,7 @@ trait Drawable {
         let stdlib_types = load_stdlib_types();
 
         // Extract symbols
-        let symbols = LoftLanguageServer::extract_symbols(&stmts, 0, &stdlib_types);
+        let symbols = extract_symbols(&stmts, 0, &stdlib_types);
 
         // Should have 5 symbols: 2 vars, 1 func, 1 struct, 1 trait
         assert_eq!(symbols.len(), 5);
@@ -6603,6 +5017,7 @@ trait Drawable {
         if let SymbolKind::Function {
             params,
             return_type,
+            ..
         } = &add_symbol.kind
         {
             assert_eq!(params.len(), 2);
@@ -6660,6 +5075,7 @@ trait Drawable {
         let func_symbol = SymbolInfo {
             name: "add".to_string(),
             kind: SymbolKind::Function {
+                type_params: vec![],
                 params: vec![
                     ("a".to_string(), "num".to_string()),
                     ("b".to_string(), "num".to_string()),
@@ -6712,8 +5128,8 @@ def Point {
         let stmts = parser.parse().unwrap();
 
         let stdlib_types = load_stdlib_types();
-        let mut symbols = LoftLanguageServer::extract_symbols(&stmts, 0, &stdlib_types);
-        LoftLanguageServer::associate_doc_comments(source, &mut symbols);
+        let mut symbols = extract_symbols(&stmts, 0, &stdlib_types);
+        associate_doc_comments(&stmts, &mut symbols);
 
         // Check function has doc comment
         let add_symbol = symbols.iter().find(|s| s.name == "add").unwrap();
@@ -6736,46 +5152,6 @@ def Point {
         assert!(doc.contains("multiple lines"));
     }
 
-    #[test]
-    fn test_doc_comment_link_processing() {
-        // Test that [Item] and [module::Item] are converted to `Item` and `module::Item`
-        let doc1 = "This function uses [Vec] and [std::collections::HashMap]";
-        let processed1 = LoftLanguageServer::process_doc_comment(doc1);
-        assert_eq!(
-            processed1,
-            "This function uses `Vec` and `std::collections::HashMap`"
-        );
-
-        // Test with multiple links
-        let doc2 = "See [Option] and [Result] for error handling. Also check [mod::Item].";
-        let processed2 = LoftLanguageServer::process_doc_comment(doc2);
-        assert_eq!(
-            processed2,
-            "See `Option` and `Result` for error handling. Also check `mod::Item`."
-        );
-
-        // Test that invalid patterns are not processed
-        let doc3 = "This [has spaces] and [special-char] should not be converted";
-        let processed3 = LoftLanguageServer::process_doc_comment(doc3);
-        assert!(processed3.contains("[has spaces]")); // Should remain unchanged
-
-        // Test empty brackets
-        let doc4 = "Empty [] brackets";
-        let processed4 = LoftLanguageServer::process_doc_comment(doc4);
-        assert_eq!(processed4, "Empty [] brackets");
-
-        // Test normal markdown is preserved
-        let doc5 = "**Bold** and *italic* and `code` should be preserved";
-        let processed5 = LoftLanguageServer::process_doc_comment(doc5);
-        assert_eq!(processed5, doc5);
-
-        // Test edge case: unclosed bracket doesn't cause infinite loop
-        let doc6 = "This [has no closing bracket and continues";
-        let processed6 = LoftLanguageServer::process_doc_comment(doc6);
-        assert!(processed6.starts_with("This ["));
-        assert!(processed6.contains("no closing bracket"));
-    }
-
     #[test]
     fn test_type_inference() {
         let source = r#"
@@ -6791,7 +5167,7 @@ let arr_var = [1, 2, 3];
         let stmts = parser.parse().unwrap();
 
         let stdlib_types = load_stdlib_types();
-        let symbols = LoftLanguageServer::extract_symbols(&stmts, 0, &stdlib_types);
+        let symbols = extract_symbols(&stmts, 0, &stdlib_types);
 
         // Check type inference
         let num_symbol = symbols.iter().find(|s| s.name == "num_var").unwrap();
@@ -6824,6 +5200,10 @@ def Point {
 }
 
 impl Point {
+    fn origin() -> Point {
+        return Point { x: 0, y: 0 };
+    }
+
     fn distance(self: Point) -> num {
         return 0;
     }
@@ -6839,15 +5219,22 @@ impl Point {
         let stmts = parser.parse().unwrap();
 
         let stdlib_types = load_stdlib_types();
-        let symbols = LoftLanguageServer::extract_symbols(&stmts, 0, &stdlib_types);
+        let symbols = extract_symbols(&stmts, 0, &stdlib_types);
 
         // Find the Point struct
         let point_symbol = symbols.iter().find(|s| s.name == "Point").unwrap();
-        if let SymbolKind::Struct { fields, methods } = &point_symbol.kind {
+        if let SymbolKind::Struct {
+            fields,
+            methods,
+            associated,
+            ..
+        } = &point_symbol.kind
+        {
             assert_eq!(fields.len(), 2);
             assert_eq!(methods.len(), 2);
             assert!(methods.contains(&"distance".to_string()));
             assert!(methods.contains(&"translate".to_string()));
+            assert_eq!(associated, &vec!["origin".to_string()]);
         } else {
             panic!("Expected struct symbol with methods");
         }
@@ -6871,6 +5258,11 @@ impl Point {
             Some("math".to_string())
         );
 
+        assert_eq!(
+            LoftLanguageServer::get_member_access_context("let p = Point::"),
+            Some("Point".to_string())
+        );
+
         // Should return None when not in member access context
         assert_eq!(LoftLanguageServer::get_member_access_context("myvar"), None);
 
@@ -6878,6 +5270,11 @@ impl Point {
             LoftLanguageServer::get_member_access_context("let x = "),
             None
         );
+
+        assert_eq!(
+            LoftLanguageServer::get_member_access_context("let x:"),
+            None
+        );
     }
 
     #[test]
@@ -6899,7 +5296,7 @@ let async_result = await async_future;
         let stmts = parser.parse().unwrap();
 
         let stdlib_types = load_stdlib_types();
-        let symbols = LoftLanguageServer::extract_symbols(&stmts, 0, &stdlib_types);
+        let symbols = extract_symbols(&stmts, 0, &stdlib_types);
 
         // Check that lazy_future has inferred type from function return type
         let lazy_future_symbol = symbols.iter().find(|s| s.name == "lazy_future").unwrap();
@@ -6951,7 +5348,7 @@ let e = fs.read("file.txt");
         let stmts = parser.parse().unwrap();
 
         let stdlib_types = load_stdlib_types();
-        let symbols = LoftLanguageServer::extract_symbols(&stmts, 0, &stdlib_types);
+        let symbols = extract_symbols(&stmts, 0, &stdlib_types);
 
         // Check that 'a' has type Promise<Response>
         let a_symbol = symbols.iter().find(|s| s.name == "a").unwrap();
@@ -7040,7 +5437,7 @@ let e = fs.read("file.txt");
         );
         assert_eq!(
             response_type.fields.get("body").unwrap().field_type,
-            "Promise<Buffer>"
+            "Promise<Bytes>"
         );
 
         // Check method return types
@@ -7120,7 +5517,7 @@ let response = await web.get("https://google.com");
         let input_stream = InputStream::new(&uri, &content);
         let mut parser = Parser::new(input_stream);
         let stmts = parser.parse().unwrap();
-        let symbols = LoftLanguageServer::extract_symbols(&stmts, 0, &server.stdlib_types);
+        let symbols = extract_symbols(&stmts, 0, &server.stdlib_types);
 
         let mut docs = server.documents.write().await;
         docs.insert(
@@ -7129,8 +5526,8 @@ let response = await web.get("https://google.com");
                 content: content.clone(),
                 version: 1,
                 symbols,
-                imports: vec![],
                 imported_symbols: Vec::new(),
+                module_exports: HashMap::new(),
                 uri: uri.clone(),
             },
         );
@@ -7202,7 +5599,7 @@ let req = web.request("https://api.example.com");
         let input_stream = InputStream::new(&uri, &content);
         let mut parser = Parser::new(input_stream);
         let stmts = parser.parse().unwrap();
-        let symbols = LoftLanguageServer::extract_symbols(&stmts, 0, &server.stdlib_types);
+        let symbols = extract_symbols(&stmts, 0, &server.stdlib_types);
 
         let mut docs = server.documents.write().await;
         docs.insert(
@@ -7211,8 +5608,8 @@ let req = web.request("https://api.example.com");
                 content: content.clone(),
                 version: 1,
                 symbols,
-                imports: vec![],
                 imported_symbols: Vec::new(),
+                module_exports: HashMap::new(),
                 uri: uri.clone(),
             },
         );
@@ -7287,7 +5684,7 @@ let b = await a;
         let input_stream = InputStream::new(&uri, &content);
         let mut parser = Parser::new(input_stream);
         let stmts = parser.parse().unwrap();
-        let symbols = LoftLanguageServer::extract_symbols(&stmts, 0, &server.stdlib_types);
+        let symbols = extract_symbols(&stmts, 0, &server.stdlib_types);
 
         // Verify types are correctly inferred
         let a_symbol = symbols.iter().find(|s| s.name == "a").unwrap();
@@ -7307,8 +5704,8 @@ let b = await a;
                 content: content.clone(),
                 version: 1,
                 symbols,
-                imports: vec![],
                 imported_symbols: Vec::new(),
+                module_exports: HashMap::new(),
                 uri: uri.clone(),
             },
         );
@@ -7505,7 +5902,7 @@ fn outer_function() -> void {
         let stmts = parser.parse().unwrap();
 
         let stdlib_types = load_stdlib_types();
-        let symbols = LoftLanguageServer::extract_symbols(&stmts, 0, &stdlib_types);
+        let symbols = extract_symbols(&stmts, 0, &stdlib_types);
 
         // Find symbols
         let global = symbols.iter().find(|s| s.name == "global_var");
@@ -7590,12 +5987,12 @@ let name = "Alice";"#;
                         detail: None,
                         documentation: None,
                         scope_level: 0,
-                        range: Some(Range {
-                            start: Position {
+                        range: Some(analysis::Range {
+                            start: analysis::Position {
                                 line: 0,
                                 character: 4,
                             },
-                            end: Position {
+                            end: analysis::Position {
                                 line: 0,
                                 character: 5,
                             },
@@ -7613,12 +6010,12 @@ let name = "Alice";"#;
                         detail: None,
                         documentation: None,
                         scope_level: 0,
-                        range: Some(Range {
-                            start: Position {
+                        range: Some(analysis::Range {
+                            start: analysis::Position {
                                 line: 1,
                                 character: 4,
                             },
-                            end: Position {
+                            end: analysis::Position {
                                 line: 1,
                                 character: 5,
                             },
@@ -7636,12 +6033,12 @@ let name = "Alice";"#;
                         detail: None,
                         documentation: None,
                         scope_level: 0,
-                        range: Some(Range {
-                            start: Position {
+                        range: Some(analysis::Range {
+                            start: analysis::Position {
                                 line: 2,
                                 character: 4,
                             },
-                            end: Position {
+                            end: analysis::Position {
                                 line: 2,
                                 character: 8,
                             },
@@ -7651,8 +6048,8 @@ let name = "Alice";"#;
                         is_exported: false,
                     },
                 ],
-                imports: vec![],
                 imported_symbols: vec![],
+                module_exports: HashMap::new(),
                 uri: uri.clone(),
             },
         );
@@ -7717,8 +6114,8 @@ def Point {
                 content: source.to_string(),
                 version: 1,
                 symbols: vec![],
-                imports: vec![],
                 imported_symbols: vec![],
+                module_exports: HashMap::new(),
                 uri: uri.clone(),
             },
         );
@@ -7761,13 +6158,14 @@ def Point {
                     SymbolInfo {
                         name: "add".to_string(),
                         kind: SymbolKind::Function {
+                            type_params: vec![],
                             params: vec![],
                             return_type: "void".to_string(),
                         },
                         detail: None,
                         documentation: None,
                         scope_level: 0,
-                        range: Some(Range::default()),
+                        range: Some(analysis::Range::default()),
                         selection_range: None,
                         source_uri: None,
                         is_exported: false,
@@ -7781,14 +6179,14 @@ def Point {
                         detail: None,
                         documentation: None,
                         scope_level: 0,
-                        range: Some(Range::default()),
+                        range: Some(analysis::Range::default()),
                         selection_range: None,
                         source_uri: None,
                         is_exported: false,
                     },
                 ],
-                imports: vec![],
                 imported_symbols: vec![],
+                module_exports: HashMap::new(),
                 uri: uri.clone(),
             },
         );
@@ -7825,6 +6223,69 @@ def Point {
         assert_eq!(symbols[0].name, "value");
     }
 
+    #[tokio::test]
+    async fn test_workspace_symbols_from_unopened_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(
+            dir.path().join("src/shapes.lf"),
+            "// Shapes\ndef Circle {\n    radius: num\n}\n\nteach fn circle_area(c: Circle) -> num {\n    let r = c.radius;\n    return r * r * 3;\n}\n",
+        )
+        .unwrap();
+
+        let (service, _) = LspService::new(LoftLanguageServer::new);
+        let server = service.inner();
+        let folder = Uri::from_file_path(dir.path()).unwrap();
+        server
+            .initialize(InitializeParams {
+                workspace_folders: Some(vec![WorkspaceFolder {
+                    uri: folder,
+                    name: "shapes".to_string(),
+                }]),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        // Indexing runs in the background
+        for _ in 0..100 {
+            if !server.workspace_index.read().await.is_empty() {
+                break;
+            }
+            tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+        }
+
+        let symbols = server
+            .symbol(WorkspaceSymbolParams {
+                query: "circ".to_string(),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            })
+            .await
+            .unwrap()
+            .unwrap();
+        let mut found: Vec<(String, Range)> = symbols
+            .into_iter()
+            .map(|s| (s.name, s.location.range))
+            .collect();
+        found.sort_by(|a, b| a.0.cmp(&b.0));
+
+        // Local variables aren't indexed, and each symbol points at its name
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "Circle".to_string(),
+                    Range::new(Position::new(1, 4), Position::new(1, 10))
+                ),
+                (
+                    "circle_area".to_string(),
+                    Range::new(Position::new(5, 9), Position::new(5, 20))
+                ),
+            ]
+        );
+    }
+
     #[tokio::test]
     async fn test_document_links() {
         // Test document link detection for URLs
@@ -7844,8 +6305,8 @@ let url = "https://github.com/tascord/loft";
                 content: source.to_string(),
                 version: 1,
                 symbols: vec![],
-                imports: vec![],
                 imported_symbols: vec![],
+                module_exports: HashMap::new(),
                 uri: uri.clone(),
             },
         );
@@ -7893,6 +6354,7 @@ let result2 = add(3, 4);"#;
                 symbols: vec![SymbolInfo {
                     name: "add".to_string(),
                     kind: SymbolKind::Function {
+                        type_params: vec![],
                         params: vec![
                             ("a".to_string(), "num".to_string()),
                             ("b".to_string(), "num".to_string()),
@@ -7902,12 +6364,12 @@ let result2 = add(3, 4);"#;
                     detail: None,
                     documentation: None,
                     scope_level: 0,
-                    range: Some(Range {
-                        start: Position {
+                    range: Some(analysis::Range {
+                        start: analysis::Position {
                             line: 0,
                             character: 0,
                         },
-                        end: Position {
+                        end: analysis::Position {
                             line: 2,
                             character: 1,
                         },
@@ -7916,8 +6378,8 @@ let result2 = add(3, 4);"#;
                     source_uri: None,
                     is_exported: false,
                 }],
-                imports: vec![],
                 imported_symbols: vec![],
+                module_exports: HashMap::new(),
                 uri: uri.clone(),
             },
         );
@@ -7988,6 +6450,7 @@ fn calculate() -> num {
                     SymbolInfo {
                         name: "add".to_string(),
                         kind: SymbolKind::Function {
+                            type_params: vec![],
                             params: vec![
                                 ("a".to_string(), "num".to_string()),
                                 ("b".to_string(), "num".to_string()),
@@ -7997,22 +6460,22 @@ fn calculate() -> num {
                         detail: Some("fn(num, num) -> num".to_string()),
                         documentation: None,
                         scope_level: 0,
-                        range: Some(Range {
-                            start: Position {
+                        range: Some(analysis::Range {
+                            start: analysis::Position {
                                 line: 0,
                                 character: 0,
                             },
-                            end: Position {
+                            end: analysis::Position {
                                 line: 2,
                                 character: 1,
                             },
                         }),
-                        selection_range: Some(Range {
-                            start: Position {
+                        selection_range: Some(analysis::Range {
+                            start: analysis::Position {
                                 line: 0,
                                 character: 3,
                             },
-                            end: Position {
+                            end: analysis::Position {
                                 line: 0,
                                 character: 6,
                             },
@@ -8023,28 +6486,29 @@ fn calculate() -> num {
                     SymbolInfo {
                         name: "calculate".to_string(),
                         kind: SymbolKind::Function {
+                            type_params: vec![],
                             params: vec![],
                             return_type: "num".to_string(),
                         },
                         detail: Some("fn() -> num".to_string()),
                         documentation: None,
                         scope_level: 0,
-                        range: Some(Range {
-                            start: Position {
+                        range: Some(analysis::Range {
+                            start: analysis::Position {
                                 line: 4,
                                 character: 0,
                             },
-                            end: Position {
+                            end: analysis::Position {
                                 line: 6,
                                 character: 1,
                             },
                         }),
-                        selection_range: Some(Range {
-                            start: Position {
+                        selection_range: Some(analysis::Range {
+                            start: analysis::Position {
                                 line: 4,
                                 character: 3,
                             },
-                            end: Position {
+                            end: analysis::Position {
                                 line: 4,
                                 character: 12,
                             },
@@ -8053,8 +6517,8 @@ fn calculate() -> num {
                         is_exported: false,
                     },
                 ],
-                imports: vec![],
                 imported_symbols: vec![],
+                module_exports: HashMap::new(),
                 uri: uri.clone(),
             },
         );
@@ -8144,7 +6608,8 @@ let x=5;
 let y=10;
 let result=add(x,y);
 term.println(result);
-}"#;
+}
+"#;
 
         // Add document
         let mut docs = server.documents.write().await;
@@ -8154,8 +6619,8 @@ term.println(result);
                 content: unformatted.to_string(),
                 version: 1,
                 symbols: vec![],
-                imports: vec![],
                 imported_symbols: vec![],
+                module_exports: HashMap::new(),
                 uri: uri.clone(),
             },
         );
@@ -8189,6 +6654,9 @@ term.println(result);
         assert!(formatted.contains("fn add"));
         assert!(formatted.contains("->"));
         assert!(formatted.contains("num"));
+
+        // The edit covers the whole document, so applying it twice doesn't add lines
+        assert_eq!(edits[0].range.end, Position::new(10, 0));
     }
 
     #[tokio::test]
@@ -8220,8 +6688,8 @@ fn well_formatted() -> void {
                 content: source.to_string(),
                 version: 1,
                 symbols: vec![],
-                imports: vec![],
                 imported_symbols: vec![],
+                module_exports: HashMap::new(),
                 uri: uri.clone(),
             },
         );
@@ -8264,6 +6732,63 @@ fn well_formatted() -> void {
         assert!(formatted.contains("let x = 5"));
     }
 
+    #[tokio::test]
+    async fn test_on_type_formatting() {
+        let (service, _) = LspService::new(LoftLanguageServer::new);
+        let server = service.inner();
+
+        let uri = "file:///test.loft".to_string();
+        let source = "fn main() {\n    if (ready) {\n        go();\n            }\n}";
+
+        server.documents.write().await.insert(
+            uri.clone(),
+            DocumentData {
+                content: source.to_string(),
+                version: 1,
+                symbols: vec![],
+                imported_symbols: vec![],
+                module_exports: HashMap::new(),
+                uri: uri.clone(),
+            },
+        );
+
+        let on_type = |line: u32| DocumentOnTypeFormattingParams {
+            text_document_position: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: Uri::from_str(&uri).unwrap(),
+                },
+                position: Position {
+                    line,
+                    character: 13,
+                },
+            },
+            ch: "}".to_string(),
+            options: FormattingOptions {
+                tab_size: 4,
+                insert_spaces: true,
+                ..Default::default()
+            },
+        };
+
+        // Typing `}` re-indents only that line
+        let edits = server
+            .on_type_formatting(on_type(3))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].new_text, "    }");
+        assert_eq!(edits[0].range.start.line, 3);
+        assert_eq!(edits[0].range.end.line, 3);
+
+        // An already indented line needs no edit
+        assert!(server
+            .on_type_formatting(on_type(4))
+            .await
+            .unwrap()
+            .is_none());
+    }
+
     #[tokio::test]
     async fn test_unused_variable_diagnostic() {
         let (service, _) = LspService::new(LoftLanguageServer::new);
@@ -8369,6 +6894,138 @@ fn main() -> void {
         assert!(docs.contains_key(&uri));
     }
 
+    #[tokio::test]
+    async fn test_quick_fixes() {
+        let (service, _) = LspService::new(LoftLanguageServer::new);
+        let server = service.inner();
+
+        let uri = Uri::from_str("file:///test.lf").unwrap();
+        let source = "trait Shape {\n    fn area(self) -> num;\n}\ndef Square {\n    side: num\n}\nimpl Shape for Square {}\nlet root = sqrt(4);\n"
+            .to_string();
+        server
+            .did_open(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem {
+                    uri: uri.clone(),
+                    language_id: "loft".to_string(),
+                    version: 1,
+                    text: source.clone(),
+                },
+            })
+            .await;
+
+        let stmts = Parser::new(InputStream::new("test.lf", &source))
+            .parse()
+            .unwrap();
+        let symbols = extract_symbols(&stmts, 0, &server.stdlib_types);
+        let diagnostics: Vec<Diagnostic> =
+            check_semantic_errors(&stmts, &symbols, &source, &server.stdlib_types)
+                .into_iter()
+                .map(Diagnostic::from)
+                .collect();
+
+        let actions = server
+            .code_action(CodeActionParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                range: Range::default(),
+                context: CodeActionContext {
+                    diagnostics,
+                    ..Default::default()
+                },
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            })
+            .await
+            .unwrap()
+            .unwrap();
+        let edits: Vec<(String, TextEdit)> = actions
+            .into_iter()
+            .filter_map(|action| match action {
+                CodeActionOrCommand::CodeAction(action) => {
+                    let mut changes = action.edit?.changes?;
+                    Some((action.title, changes.remove(&uri)?.remove(0)))
+                }
+                _ => None,
+            })
+            .collect();
+
+        let (_, stubs) = edits
+            .iter()
+            .find(|(title, _)| title == "Implement missing methods of Shape")
+            .unwrap();
+        assert_eq!(stubs.range.start, Position::new(6, 23));
+        assert_eq!(
+            stubs.new_text,
+            "\n    fn area(self) -> num {\n        throw \"area is not implemented\";\n    }\n"
+        );
+
+        let (_, import) = edits
+            .iter()
+            .find(|(title, _)| title == "Add `learn { sqrt } from \"math\";`")
+            .unwrap();
+        assert_eq!(import.range.start, Position::new(0, 0));
+        assert_eq!(import.new_text, "learn { sqrt } from \"math\";\n");
+    }
+
+    #[tokio::test]
+    async fn test_organize_imports() {
+        let (service, _) = LspService::new(LoftLanguageServer::new);
+        let server = service.inner();
+
+        let uri = Uri::from_str("file:///test.lf").unwrap();
+        let source = "learn { sqrt } from \"math\";\nlearn { floor } from \"math\";\nlearn { abs } from \"math\";\nlet r = sqrt(4) + abs(1);\nterm.println(r);\n";
+        server
+            .did_open(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem {
+                    uri: uri.clone(),
+                    language_id: "loft".to_string(),
+                    version: 1,
+                    text: source.to_string(),
+                },
+            })
+            .await;
+
+        let actions = server
+            .code_action(CodeActionParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                range: Range::default(),
+                context: CodeActionContext {
+                    only: Some(vec![CodeActionKind::SOURCE]),
+                    ..Default::default()
+                },
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            })
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(actions.len(), 1);
+        let CodeActionOrCommand::CodeAction(action) = &actions[0] else {
+            panic!("expected a code action");
+        };
+        assert_eq!(action.kind, Some(CodeActionKind::SOURCE_ORGANIZE_IMPORTS));
+        let edit = &action.edit.as_ref().unwrap().changes.as_ref().unwrap()[&uri][0];
+        assert_eq!(edit.range.start, Position::new(0, 0));
+        assert_eq!(edit.range.end, Position::new(2, 26));
+        assert_eq!(edit.new_text, "learn { abs, sqrt } from \"math\";");
+    }
+
+    #[test]
+    fn test_references_skip_comments() {
+        let source = "/// Uses [count]\nfn show() { term.println(count); }\n// count is global\nlet count = 1; /* count */\n";
+        let mut locations = Vec::new();
+        LoftLanguageServer::find_references_in_document(
+            source,
+            "count",
+            "file:///main.lf",
+            &mut locations,
+        );
+        let found: Vec<(u32, u32)> = locations
+            .iter()
+            .map(|location| (location.range.start.line, location.range.start.character))
+            .collect();
+        assert_eq!(found, [(0, 10), (1, 25), (3, 4)]);
+    }
+
     #[tokio::test]
     async fn test_cross_file_references() {
         let (service, _) = LspService::new(LoftLanguageServer::new);
@@ -1,10 +1,19 @@
 #[cfg(not(target_arch = "wasm32"))]
 use loft::lsp::run_server;
 
+/// Stack for the threads documents are parsed and analyzed on, which walk
+/// code nested up to the parser's limit
 #[cfg(not(target_arch = "wasm32"))]
-#[tokio::main]
-async fn main() {
-    run_server().await;
+const STACK_SIZE: usize = 8 * 1024 * 1024;
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .thread_stack_size(STACK_SIZE)
+        .build()
+        .expect("Failed to start the runtime")
+        .block_on(run_server());
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -1,11 +1,22 @@
 #[cfg(not(target_arch = "wasm32"))]
 use clap::{Parser as ClapParser, Subcommand};
+#[cfg(not(target_arch = "wasm32"))]
+use loft::analysis::project::CheckMode;
+#[cfg(not(target_arch = "wasm32"))]
+use loft::analysis::symbols::{extract_symbols, infer_type_from_expr, SymbolInfo, SymbolKind};
+use loft::output::{Colorize, Rgb};
+#[cfg(not(target_arch = "wasm32"))]
+use loft::parser::{Expr, StmtKind};
 use loft::parser::{InputStream, Parser};
+#[cfg(not(target_arch = "wasm32"))]
+use loft::registry_client::{self, PackageVersion, PublishRequest, RegistryClient, RegistryError};
 use loft::runtime::{
-    permission_context, permissions::PermissionManager, value::Value, Interpreter,
+    exit, permission_context,
+    permissions::{PermissionManager, PermissionScope},
+    value::Value,
+    Interpreter,
 };
 use miette::GraphicalReportHandler;
-use owo_colors::{OwoColorize, Rgb};
 #[cfg(not(target_arch = "wasm32"))]
 use rustyline::error::ReadlineError;
 
@@ -27,32 +38,85 @@ struct Cli {
     #[arg(short = 'c', long = "command", value_name = "CODE")]
     code: Option<String>,
 
-    /// File to execute (use '.' to run from manifest.json entrypoint)
+    /// File to execute (use '.' to run from manifest.json entrypoint, or '-' to read the program from stdin)
     file: Option<String>,
 
     /// Allow all permissions (file system, network, command execution)
     #[arg(long = "allow-all")]
     allow_all: bool,
 
-    /// Allow read access to the file system
-    #[arg(long = "allow-read")]
-    allow_read: bool,
-
-    /// Allow write access to the file system
-    #[arg(long = "allow-write")]
-    allow_write: bool,
-
-    /// Allow network access
-    #[arg(long = "allow-net")]
-    allow_net: bool,
-
-    /// Allow command execution
-    #[arg(long = "allow-run")]
-    allow_run: bool,
+    /// Allow read access to the file system, optionally limited to a comma-separated list of paths, e.g. --allow-read=./data
+    #[arg(
+        long = "allow-read",
+        value_name = "PATHS",
+        num_args = 0..,
+        value_delimiter = ',',
+        require_equals = true
+    )]
+    allow_read: Option<Vec<String>>,
+
+    /// Allow write access to the file system, optionally limited to a comma-separated list of paths
+    #[arg(
+        long = "allow-write",
+        value_name = "PATHS",
+        num_args = 0..,
+        value_delimiter = ',',
+        require_equals = true
+    )]
+    allow_write: Option<Vec<String>>,
+
+    /// Allow network access, optionally limited to a comma-separated list of hosts, e.g. --allow-net=api.example.com:443
+    #[arg(
+        long = "allow-net",
+        value_name = "HOSTS",
+        num_args = 0..,
+        value_delimiter = ',',
+        require_equals = true
+    )]
+    allow_net: Option<Vec<String>>,
+
+    /// Allow command execution, optionally limited to a comma-separated list of commands
+    #[arg(
+        long = "allow-run",
+        value_name = "COMMANDS",
+        num_args = 0..,
+        value_delimiter = ',',
+        require_equals = true
+    )]
+    allow_run: Option<Vec<String>>,
 
     /// Enable specific language features (e.g., io, ffi)
-    #[arg(short = 'F', long = "feature", value_name = "FEATURE")]
+    #[arg(short = 'F', long = "feature", value_name = "FEATURE", global = true)]
     features: Vec<String>,
+
+    /// Print call counts and timing per function after the program exits, or write them to FILE as collapsed stacks for flamegraph tools
+    #[arg(
+        long = "profile",
+        value_name = "FILE",
+        num_args = 0..=1,
+        require_equals = true
+    )]
+    profile: Option<Option<String>>,
+
+    /// Re-run the file whenever it or one of the modules it imports changes
+    #[arg(short = 'w', long = "watch")]
+    watch: bool,
+
+    /// Print without colors, as when NO_COLOR is set
+    #[arg(long = "no-color", global = true)]
+    no_color: bool,
+
+    /// Print only what the program prints, leaving out loft's status messages; the default when output isn't a terminal
+    #[arg(short = 'q', long = "quiet", global = true)]
+    quiet: bool,
+
+    /// Exit with the value the program ends with: a number is the exit code, true is 0 and false is 1
+    #[arg(long = "exit-with-result")]
+    exit_with_result: bool,
+
+    /// Arguments passed to the script as env.args, e.g. loft main.lf -- input.txt
+    #[arg(last = true, value_name = "ARGS")]
+    script_args: Vec<String>,
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -78,6 +142,18 @@ enum Commands {
         #[arg(short, long)]
         version: Option<String>,
     },
+    /// [ REMOVE ] Remove a dependency from the current project
+    Remove {
+        /// Name of the dependency
+        name: String,
+    },
+    /// [ INSTALL ] Install every dependency listed in manifest.json
+    Install,
+    /// [ SEARCH ] Search the registry for packages
+    Search {
+        /// Words to look for in package names, descriptions and keywords
+        query: Vec<String>,
+    },
     /// [ UPDATE ] Update dependencies according to version constraints
     Update {
         /// Specific package to update (updates all if not specified)
@@ -88,6 +164,9 @@ enum Commands {
         /// Output directory for generated documentation (defaults to ./docs)
         #[arg(short, long, default_value = "docs")]
         output: String,
+        /// Run in every member of the workspace
+        #[arg(long)]
+        workspace: bool,
     },
     /// [ STDLIB ] Generate standard library documentation
     StdlibDoc {
@@ -108,6 +187,92 @@ enum Commands {
         /// Check formatting without modifying files
         #[arg(short, long)]
         check: bool,
+        /// Print a unified diff of the changes instead of writing them
+        #[arg(long, conflicts_with = "check")]
+        diff: bool,
+        /// Format source read from stdin and write it to stdout
+        #[arg(long, conflicts_with_all = ["path", "check", "workspace"])]
+        stdin: bool,
+        /// Run in every member of the workspace
+        #[arg(long, conflicts_with = "path")]
+        workspace: bool,
+    },
+    /// [ TEST ] Run *_test.lf files and #[test] functions
+    Test {
+        /// File or directory to search for tests (defaults to current directory)
+        path: Option<String>,
+        /// Only run tests whose name contains this string
+        #[arg(short, long)]
+        filter: Option<String>,
+        /// Run the ```loft code blocks in doc comments instead of test files
+        #[arg(long)]
+        doc: bool,
+        /// Run in every member of the workspace
+        #[arg(long, conflicts_with = "path")]
+        workspace: bool,
+    },
+    /// [ BENCH ] Time *_bench.lf files and #[bench] functions
+    Bench {
+        /// File or directory to search for benches (defaults to current directory)
+        path: Option<String>,
+        /// Only run benches whose name contains this string
+        #[arg(short, long)]
+        filter: Option<String>,
+        /// Runs of each bench before measuring
+        #[arg(long, default_value_t = 3)]
+        warmup: usize,
+        /// Measured runs of each bench
+        #[arg(short = 'n', long, default_value_t = 10)]
+        iterations: usize,
+        /// Save the results as JSON, to compare later runs against
+        #[arg(long, value_name = "FILE")]
+        save: Option<String>,
+        /// Show the change from results saved with --save
+        #[arg(long, value_name = "FILE")]
+        compare: Option<String>,
+    },
+    /// [ CHECK ] Report errors and warnings without running any code
+    Check {
+        /// File or directory to check (defaults to current directory)
+        path: Option<String>,
+        /// Also run the static type checker (implied by "strict": true in manifest.json)
+        #[arg(long)]
+        strict: bool,
+        /// Run in every member of the workspace
+        #[arg(long, conflicts_with = "path")]
+        workspace: bool,
+    },
+    /// [ FIX ] Apply the suggested fixes for errors and warnings
+    Fix {
+        /// File or directory to fix (defaults to current directory)
+        path: Option<String>,
+        /// Print a unified diff of the changes instead of writing them
+        #[arg(long)]
+        diff: bool,
+    },
+    /// [ EXPLAIN ] Explain a diagnostic code, e.g. `loft explain L0005`
+    Explain {
+        /// The code or name of the diagnostic (lists every code if omitted)
+        code: Option<String>,
+    },
+    /// [ BUNDLE ] Bundle the project and its imports into a single .lf file
+    Bundle {
+        /// Output file (defaults to target/<name>.lf)
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    /// [ COMPILE ] Build a standalone executable that runs a file and its imports
+    Compile {
+        /// The file to compile
+        file: String,
+        /// Output executable (defaults to the file's name without its extension)
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    /// [ PERMISSIONS ] Show or revoke the permissions saved with "always" or "never"
+    Permissions {
+        #[command(subcommand)]
+        action: PermissionsAction,
     },
     /// [ LOGIN ] Log in to the loft registry
     Login {
@@ -115,7 +280,38 @@ enum Commands {
         token: Option<String>,
     },
     /// [ PUBLISH ] Publish the current project to the registry
-    Publish,
+    Publish {
+        /// Build the package and check it against the registry without uploading
+        #[arg(long)]
+        dry_run: bool,
+        /// Skip checking the package's source before publishing
+        #[arg(long)]
+        no_verify: bool,
+    },
+    /// [ YANK ] Stop new projects from resolving a published version of the current project
+    Yank {
+        /// The version to yank
+        version: String,
+        /// Make a yanked version available again
+        #[arg(long)]
+        undo: bool,
+    },
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Subcommand)]
+enum PermissionsAction {
+    /// List every saved decision with the script that asked for it
+    List,
+    /// Forget a saved decision so the next run asks again, e.g. `loft permissions revoke net example.com`
+    Revoke {
+        /// Kind of access: read, write, net or run
+        kind: String,
+        /// The path, host or command the decision is for
+        target: String,
+    },
+    /// Forget every saved decision
+    Clear,
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -135,15 +331,25 @@ fn main() {}
 
 #[cfg(not(target_arch = "wasm32"))]
 fn main() {
+    // Executables built by `loft compile` run their program and take no loft flags
+    if let Some(bundle) = compiled_bundle() {
+        run_compiled(bundle);
+        return;
+    }
+
     let cli = Cli::parse();
+    if cli.no_color {
+        loft::output::disable_colors();
+    }
+    loft::output::set_quiet(cli.quiet || !loft::output::stdout_is_terminal());
 
     // Initialize permission manager based on CLI flags
-    let mut permissions = PermissionManager::with_flags(
+    let mut permissions = PermissionManager::with_scopes(
         cli.allow_all,
-        cli.allow_read,
-        cli.allow_write,
-        cli.allow_net,
-        cli.allow_run,
+        PermissionScope::from_flag(cli.allow_read),
+        PermissionScope::from_flag(cli.allow_write),
+        PermissionScope::from_flag(cli.allow_net),
+        PermissionScope::from_flag(cli.allow_run),
     );
 
     // Load cached permissions
@@ -152,15 +358,29 @@ fn main() {
     // Initialize permissions for this thread
     permission_context::init_permissions(permissions);
 
+    loft::runtime::builtins::env::set_script_args(cli.script_args);
+
     // Priority: -c flag > file argument > subcommand > REPL
     if let Some(code) = cli.code {
-        run_inline_code(&code, cli.features);
+        run_inline_code(&code, cli.features, cli.exit_with_result);
     } else if let Some(file_path) = cli.file {
         // Check if file_path is "." - run from manifest.json entrypoint
-        if file_path == "." {
-            run_from_manifest(cli.features);
+        if file_path == "-" {
+            if cli.watch {
+                eprintln!(
+                    "{} {}: --watch needs a file, not a program read from stdin",
+                    "!".bright_red(),
+                    "Error".bright_red().bold()
+                );
+                std::process::exit(1);
+            }
+            run_stdin(cli.features, cli.profile, cli.exit_with_result);
+        } else if cli.watch {
+            run_watch(&file_path);
+        } else if file_path == "." {
+            run_from_manifest(cli.features, cli.profile, cli.exit_with_result);
         } else {
-            run_file(&file_path, cli.features);
+            run_file(&file_path, cli.features, cli.profile, cli.exit_with_result);
         }
     } else if let Some(command) = cli.command {
         match command {
@@ -172,13 +392,60 @@ fn main() {
                 path,
                 version,
             } => run_add(&name, path.as_deref(), version.as_deref()),
+            Commands::Remove { name } => run_remove(&name),
+            Commands::Install => run_install(),
+            Commands::Search { query } => run_search(&query.join(" ")),
             Commands::Update { package } => run_update(package.as_deref()),
-            Commands::Doc { output } => run_doc(&output),
+            Commands::Doc {
+                workspace: true, ..
+            }
+            | Commands::Format {
+                workspace: true, ..
+            }
+            | Commands::Test {
+                workspace: true, ..
+            }
+            | Commands::Check {
+                workspace: true, ..
+            } => run_workspace(),
+            Commands::Doc { output, .. } => run_doc(&output),
             Commands::StdlibDoc { output } => run_stdlib_doc(&output),
             Commands::Docs { topic } => run_docs(topic),
-            Commands::Format { path, check } => run_format(path.as_deref(), check),
+            Commands::Format {
+                stdin: true, diff, ..
+            } => run_format_stdin(diff),
+            Commands::Format {
+                path, check, diff, ..
+            } => run_format(path.as_deref(), check, diff),
+            Commands::Test {
+                path, filter, doc, ..
+            } => run_test(path.as_deref(), filter.as_deref(), doc, cli.features),
+            Commands::Bench {
+                path,
+                filter,
+                warmup,
+                iterations,
+                save,
+                compare,
+            } => run_bench(
+                path.as_deref(),
+                filter.as_deref(),
+                loft::runtime::bench_harness::BenchOptions { warmup, iterations },
+                save.as_deref(),
+                compare.as_deref(),
+                cli.features,
+            ),
+            Commands::Check { path, strict, .. } => run_check(path.as_deref(), strict),
+            Commands::Fix { path, diff } => run_fix(path.as_deref(), diff),
+            Commands::Explain { code } => run_explain(code.as_deref()),
+            Commands::Bundle { output } => run_bundle(output.as_deref()),
+            Commands::Compile { file, output } => {
+                run_compile(&file, output.as_deref(), cli.features)
+            }
+            Commands::Permissions { action } => run_permissions(action),
             Commands::Login { token } => run_login(token.as_deref()),
-            Commands::Publish => run_publish(),
+            Commands::Publish { dry_run, no_verify } => run_publish(dry_run, !no_verify),
+            Commands::Yank { version, undo } => run_yank(&version, undo),
         }
     } else {
         run_repl(cli.features);
@@ -186,27 +453,32 @@ fn main() {
 }
 
 #[cfg(not(target_arch = "wasm32"))]
-fn run_inline_code(code: &str, features: Vec<String>) {
-    let code_string = code.to_string();
-    let stream = InputStream::new("command-line", &code_string);
-    let mut parser = Parser::new(stream);
-
-    match parser.parse() {
-        Ok(stmts) => {
-            let mut interpreter =
-                Interpreter::with_source("command-line", &code_string).with_features(features);
-            match interpreter.eval_program(stmts) {
-                Ok(result) => {
-                    if result != Value::Unit {
-                        println!("{:?}", result);
-                    }
-                }
-                Err(e) => {
-                    print_error(&e);
-                    std::process::exit(1);
-                }
+fn run_inline_code(code: &str, features: Vec<String>, exit_with_result: bool) {
+    let mut engine = loft::Engine::new().with_features(features);
+    let result = engine.eval_named("command-line", code);
+    loft::runtime::builtins::term::restore();
+    match result {
+        Ok(result) if exit_with_result => exit_with(&result),
+        Ok(result) => {
+            if result != Value::Unit && !loft::output::is_quiet() {
+                println!("{:?}", result);
             }
         }
+        Err(e) => match e.exit_code() {
+            Some(code) => exit::exit_process(code),
+            None => {
+                print_error(&e);
+                std::process::exit(1);
+            }
+        },
+    }
+}
+
+/// Exit with the status the program's final value stands for, as
+/// `--exit-with-result` asks
+fn exit_with(value: &Value) -> ! {
+    match exit::result_code(value) {
+        Ok(code) => exit::exit_process(code),
         Err(e) => {
             print_error(&e);
             std::process::exit(1);
@@ -337,6 +609,11 @@ fn run_repl(features: Vec<String>) {
     }));
 
     let mut interpreter = Interpreter::new().with_features(features);
+    // Names bound before the user typed anything, hidden from `:env`
+    let builtins: std::collections::HashSet<String> =
+        interpreter.env.capture_all().into_keys().collect();
+    // Every input that ran successfully, written out by `:save`
+    let mut session: Vec<String> = Vec::new();
 
     loop {
         let time = Local::now().format("%H:%M:%S").to_string();
@@ -389,6 +666,11 @@ fn run_repl(features: Vec<String>) {
                 // Add to history
                 rl.add_history_entry(&input).ok();
 
+                if let Some(command) = trimmed.strip_prefix(':') {
+                    run_repl_command(command, &mut interpreter, &mut session, &builtins);
+                    continue;
+                }
+
                 // Process input - potentially add semicolon
                 let processed_input = if should_append_semicolon(&input) {
                     format!("{};", input)
@@ -409,8 +691,13 @@ fn run_repl(features: Vec<String>) {
                             continue;
                         }
 
-                        match interpreter.eval_program(stmts) {
+                        interpreter.set_source("repl", &processed_input);
+                        let result = interpreter.eval_program(stmts);
+                        // The prompt needs the terminal back even if the input left raw mode on
+                        loft::runtime::builtins::term::restore();
+                        match result {
                             Ok(result) => {
+                                session.push(processed_input.trim_end().to_string());
                                 if !matches!(result, Value::Unit) {
                                     println!(
                                         " {} {:?}",
@@ -419,9 +706,10 @@ fn run_repl(features: Vec<String>) {
                                     );
                                 }
                             }
-                            Err(e) => {
-                                print_error(&e);
-                            }
+                            Err(e) => match e.exit_code() {
+                                Some(code) => exit::exit_process(code),
+                                None => print_error(&e),
+                            },
                         }
                     }
                     Err(e) => {
@@ -452,20 +740,181 @@ fn run_repl(features: Vec<String>) {
     }
 }
 
+/// Run a `:command` typed at the REPL prompt
+#[cfg(not(target_arch = "wasm32"))]
+fn run_repl_command(
+    command: &str,
+    interpreter: &mut Interpreter,
+    session: &mut Vec<String>,
+    builtins: &std::collections::HashSet<String>,
+) {
+    let (name, arg) = command
+        .split_once(char::is_whitespace)
+        .map(|(name, arg)| (name, arg.trim()))
+        .unwrap_or((command, ""));
+
+    match (name, arg) {
+        ("load", path) if !path.is_empty() => {
+            let source = match std::fs::read_to_string(path) {
+                Ok(source) => source,
+                Err(e) => return repl_error(&format!("Failed to read '{}': {}", path, e)),
+            };
+            let stmts = match Parser::new(InputStream::new(path, &source)).parse() {
+                Ok(stmts) => stmts,
+                Err(e) => return print_error(&e),
+            };
+
+            interpreter.set_source(path, &source);
+            match interpreter.eval_program(stmts) {
+                Ok(_) => {
+                    session.push(source.trim_end().to_string());
+                    repl_note(&format!("Loaded {}", path));
+                }
+                Err(e) => print_error(&e),
+            }
+        }
+        ("save", path) if !path.is_empty() => {
+            let mut contents = session.join("\n");
+            contents.push('\n');
+            match std::fs::write(path, contents) {
+                Ok(()) => repl_note(&format!("Saved {} entries to {}", session.len(), path)),
+                Err(e) => repl_error(&format!("Failed to write '{}': {}", path, e)),
+            }
+        }
+        ("env", "") => {
+            let bindings = repl_bindings(interpreter, builtins);
+            if bindings.is_empty() {
+                return repl_note("No bindings yet");
+            }
+
+            for (name, ty) in bindings {
+                println!(
+                    "  {}: {}",
+                    name.truecolor(LUMINOUS.0, LUMINOUS.1, LUMINOUS.2),
+                    ty.truecolor(ACID.0, ACID.1, ACID.2)
+                );
+            }
+        }
+        ("type", code) if !code.is_empty() => {
+            let code = code.trim_end_matches(';').to_string() + ";";
+            let stmts = match Parser::new(InputStream::new("repl", &code)).parse() {
+                Ok(stmts) => stmts,
+                Err(e) => return print_error(&e),
+            };
+            match stmts.as_slice() {
+                [stmt] => match &stmt.kind {
+                    StmtKind::Expr(expr) => {
+                        let ty = infer_repl_type(expr, interpreter, session);
+                        println!(
+                            " {} {}",
+                            "╼".truecolor(ACID.0, ACID.1, ACID.2),
+                            ty.truecolor(ACID.0, ACID.1, ACID.2)
+                        );
+                    }
+                    _ => repl_error("':type' expects an expression"),
+                },
+                _ => repl_error("':type' expects a single expression"),
+            }
+        }
+        ("load" | "save", _) => repl_error(&format!("Usage: :{} <file>", name)),
+        ("type", _) => repl_error("Usage: :type <expression>"),
+        _ => repl_error(&format!(
+            "Unknown command ':{}', type 'help' for help",
+            name
+        )),
+    }
+}
+
+/// Names bound at the REPL and the types of their values, sorted by name
+#[cfg(not(target_arch = "wasm32"))]
+fn repl_bindings(
+    interpreter: &Interpreter,
+    builtins: &std::collections::HashSet<String>,
+) -> Vec<(String, String)> {
+    let mut bindings: Vec<(String, String)> = interpreter
+        .env
+        .capture_all()
+        .into_iter()
+        .filter(|(name, _)| !builtins.contains(name))
+        .map(|(name, value)| (name, interpreter.type_of(&value)))
+        .collect();
+    bindings.sort();
+    bindings
+}
+
+/// Static type of a REPL expression, falling back to the type of bound values
+///
+/// The expression is never evaluated, so calls and other side effects do not run.
+#[cfg(not(target_arch = "wasm32"))]
+fn infer_repl_type(expr: &Expr, interpreter: &Interpreter, session: &[String]) -> String {
+    let stdlib_types = loft::analysis::stdlib::load();
+
+    // Values already bound are known exactly; declarations in the session add function signatures
+    let mut symbols: Vec<SymbolInfo> = interpreter
+        .env
+        .capture_all()
+        .into_iter()
+        .filter(|(_, value)| !matches!(value, Value::Function { .. }))
+        .map(|(name, value)| SymbolInfo {
+            detail: None,
+            kind: SymbolKind::Variable {
+                var_type: Some(interpreter.type_of(&value)),
+                mutable: true,
+            },
+            name,
+            documentation: None,
+            scope_level: 0,
+            range: None,
+            selection_range: None,
+            source_uri: None,
+            is_exported: false,
+        })
+        .collect();
+    for source in session {
+        if let Ok(stmts) = Parser::new(InputStream::new("repl", source)).parse() {
+            symbols.extend(extract_symbols(&stmts, 0, &stdlib_types));
+        }
+    }
+
+    infer_type_from_expr(expr, &symbols, &stdlib_types).unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn repl_note(message: &str) {
+    println!(
+        "{} {}",
+        "=>".truecolor(LUMINOUS.0, LUMINOUS.1, LUMINOUS.2),
+        message.truecolor(ACID.0, ACID.1, ACID.2)
+    );
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn repl_error(message: &str) {
+    println!(
+        "{} {}",
+        "!".truecolor(LUMINOUS.0, LUMINOUS.1, LUMINOUS.2),
+        message.truecolor(LUMINOUS.0, LUMINOUS.1, LUMINOUS.2).bold()
+    );
+}
+
 fn print_error<E: miette::Diagnostic>(e: &E) {
     let mut out = String::new();
-    let _ = GraphicalReportHandler::new_themed(miette::GraphicalTheme::unicode())
-        .render_report(&mut out, e);
+    let theme = if loft::output::colors_enabled() {
+        miette::GraphicalTheme::unicode()
+    } else {
+        miette::GraphicalTheme::unicode_nocolor()
+    };
+    let _ = GraphicalReportHandler::new_themed(theme).render_report(&mut out, e);
 
-    println!(
+    eprintln!(
         "{}",
         "┌─── [ ERROR ] ─────────────────────────────────────────"
             .truecolor(ACID.0, ACID.1, ACID.2)
     );
     for line in out.trim_end().lines() {
-        println!("{} {}", "║".truecolor(ACID.0, ACID.1, ACID.2), line);
+        eprintln!("{} {}", "║".truecolor(ACID.0, ACID.1, ACID.2), line);
     }
-    println!(
+    eprintln!(
         "{}",
         "└───────────────────────────────────────────────────────"
             .truecolor(ACID.0, ACID.1, ACID.2)
@@ -496,6 +945,22 @@ fn print_help() {
         "  {}     - Exit the REPL",
         "exit".truecolor(LUMINOUS.0, LUMINOUS.1, LUMINOUS.2)
     );
+    println!(
+        "  {}  - Run a file in the current session",
+        ":load <file>".truecolor(LUMINOUS.0, LUMINOUS.1, LUMINOUS.2)
+    );
+    println!(
+        "  {}  - Write the session's inputs to a file",
+        ":save <file>".truecolor(LUMINOUS.0, LUMINOUS.1, LUMINOUS.2)
+    );
+    println!(
+        "  {}          - List bindings and their types",
+        ":env".truecolor(LUMINOUS.0, LUMINOUS.1, LUMINOUS.2)
+    );
+    println!(
+        "  {} - Show the type of an expression without running it",
+        ":type <expr>".truecolor(LUMINOUS.0, LUMINOUS.1, LUMINOUS.2)
+    );
     println!();
     println!("{}", "Examples:".truecolor(ACID.0, ACID.1, ACID.2).bold());
     println!("  {}", "2 + 3 * 4".truecolor(FOREST.0, FOREST.1, FOREST.2));
@@ -582,32 +1047,46 @@ fn run_demo() {
     }
 }
 
-fn run_file(path: &str, features: Vec<String>) {
+fn run_file(
+    path: &str,
+    features: Vec<String>,
+    profile: Option<Option<String>>,
+    exit_with_result: bool,
+) {
     use std::fs;
 
+    // Permissions saved while the file runs remember which script asked
+    let script = fs::canonicalize(path)
+        .map(|p| p.display().to_string())
+        .unwrap_or_else(|_| path.to_string());
+    permission_context::with_permissions(|pm| pm.set_script(script));
+
     match fs::read_to_string(path) {
         Ok(code) => {
-            let stream = InputStream::new(path, &code);
-            let mut parser = Parser::new(stream);
-
-            match parser.parse() {
-                Ok(stmts) => {
-                    let mut interpreter =
-                        Interpreter::with_source(path, &code).with_features(features);
-                    if let Err(e) = interpreter.eval_program(stmts) {
-                        println!();
-                        print_error(&e);
+            // Bundles run their entrypoint module and resolve imports from the bundle
+            let (path, code, bundle) = if loft::bundle::Bundle::is_bundle(&code) {
+                match loft::bundle::Bundle::parse(&code) {
+                    Ok(bundle) => {
+                        let entry = bundle.entry();
+                        (
+                            entry.path.clone(),
+                            entry.source.clone(),
+                            Some(std::sync::Arc::new(bundle)),
+                        )
+                    }
+                    Err(e) => {
+                        eprintln!("{}: {}", "Error".bright_red().bold(), e);
                         std::process::exit(1);
                     }
                 }
-                Err(e) => {
-                    print_error(&e);
-                    std::process::exit(1);
-                }
-            }
+            } else {
+                (path.to_string(), code, None)
+            };
+
+            run_program(&path, &code, bundle, features, profile, exit_with_result);
         }
         Err(e) => {
-            println!(
+            eprintln!(
                 "{} {}: {}",
                 "!".bright_red(),
                 "Error reading file".bright_red().bold(),
@@ -618,7 +1097,138 @@ fn run_file(path: &str, features: Vec<String>) {
     }
 }
 
-fn run_from_manifest(features: Vec<String>) {
+/// Run a program piped in on stdin, as with `echo 'term.println(1)' | loft -`
+fn run_stdin(features: Vec<String>, profile: Option<Option<String>>, exit_with_result: bool) {
+    use std::io::Read;
+
+    let mut code = String::new();
+    if let Err(e) = std::io::stdin().read_to_string(&mut code) {
+        eprintln!(
+            "{} {}: {}",
+            "!".bright_red(),
+            "Error reading stdin".bright_red().bold(),
+            e
+        );
+        std::process::exit(1);
+    }
+    run_program("<stdin>", &code, None, features, profile, exit_with_result);
+}
+
+/// Parse and run a program, exiting with an error when it fails or with the
+/// code it passed to `exit()`
+fn run_program(
+    path: &str,
+    code: &str,
+    bundle: Option<std::sync::Arc<loft::bundle::Bundle>>,
+    features: Vec<String>,
+    profile: Option<Option<String>>,
+    exit_with_result: bool,
+) {
+    let code = code.to_string();
+    let stream = InputStream::new(path, &code);
+    let mut parser = Parser::new(stream);
+
+    match parser.parse() {
+        Ok(stmts) => {
+            let mut interpreter = Interpreter::with_source(path, &code).with_features(features);
+            if let Some(bundle) = bundle {
+                interpreter = interpreter.with_bundle(bundle);
+            }
+            if profile.is_some() {
+                interpreter = interpreter.with_profiler();
+            }
+            let result = interpreter.eval_program(stmts);
+            loft::runtime::builtins::term::restore();
+            if let (Some(output), Some(profiler)) = (&profile, interpreter.profiler()) {
+                report_profile(profiler, output.as_deref());
+            }
+            match result {
+                Ok(value) if exit_with_result => exit_with(&value),
+                Ok(_) => {}
+                Err(e) => match e.exit_code() {
+                    Some(code) => exit::exit_process(code),
+                    None => {
+                        eprintln!();
+                        print_error(&e);
+                        std::process::exit(1);
+                    }
+                },
+            }
+        }
+        Err(e) => {
+            print_error(&e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// The program embedded in this executable by `loft compile`, if any
+#[cfg(not(target_arch = "wasm32"))]
+fn compiled_bundle() -> Option<loft::bundle::Bundle> {
+    let exe = std::env::current_exe().ok()?;
+    match loft::bundle::Bundle::read_embedded(&exe) {
+        Ok(bundle) => bundle,
+        Err(e) => {
+            println!("{}: {}", "Error".bright_red().bold(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Run the program of a compiled executable, passing every argument to it
+#[cfg(not(target_arch = "wasm32"))]
+fn run_compiled(bundle: loft::bundle::Bundle) {
+    let mut permissions = PermissionManager::new();
+    let _ = permissions.load_cache();
+    if let Ok(exe) = std::env::current_exe() {
+        permissions.set_script(exe.display().to_string());
+    }
+    permission_context::init_permissions(permissions);
+    loft::runtime::builtins::env::set_script_args(std::env::args().skip(1).collect());
+
+    let entry = bundle.entry();
+    let (path, code) = (entry.path.clone(), entry.source.clone());
+    let features = bundle.features.clone();
+    run_program(
+        &path,
+        &code,
+        Some(std::sync::Arc::new(bundle)),
+        features,
+        None,
+        false,
+    );
+}
+
+/// Print the profile as a table, or write it to `output` as collapsed stacks
+fn report_profile(profiler: &loft::runtime::profiler::Profiler, output: Option<&str>) {
+    match output {
+        Some(path) => match std::fs::write(path, profiler.collapsed_stacks()) {
+            Ok(()) => eprintln!(
+                "{} {}: {}",
+                ">".bright_cyan(),
+                "Profile written".bright_cyan().bold(),
+                path.bright_white()
+            ),
+            Err(e) => eprintln!(
+                "{} {}: Failed to write {}: {}",
+                "!".bright_red(),
+                "Error".bright_red().bold(),
+                path,
+                e
+            ),
+        },
+        None => {
+            eprintln!();
+            eprint!("{}", profiler.report());
+        }
+    }
+}
+
+fn run_from_manifest(
+    features: Vec<String>,
+    profile: Option<Option<String>>,
+    exit_with_result: bool,
+) {
     use loft::manifest::Manifest;
     use std::path::Path;
 
@@ -628,7 +1238,7 @@ fn run_from_manifest(features: Vec<String>) {
             let entrypoint_path = Path::new(&manifest.entrypoint);
 
             if !entrypoint_path.exists() {
-                println!(
+                eprintln!(
                     "{} {}: Entrypoint file '{}' not found",
                     "!".bright_red(),
                     "Error".bright_red().bold(),
@@ -637,33 +1247,51 @@ fn run_from_manifest(features: Vec<String>) {
                 std::process::exit(1);
             }
 
-            println!(
-                "{} {}: {} ({})",
+            loft::output::status(format!(
+                "{} {}: {} ({})\n",
                 ">".bright_cyan(),
                 "Running project".bright_cyan().bold(),
                 manifest.name.bright_white(),
                 manifest.entrypoint.bright_white()
-            );
-            println!();
+            ));
+
+            // Make sure the exact versions pinned in loft.lock are installed
+            install_locked_packages(&manifest);
+
+            // Strict projects must type check before they run
+            if manifest.strict {
+                let files = loft::analysis::discover_source_files(Path::new("."));
+                let (errors, _) = check_files(&files, CheckMode::TypesOnly, &manifest.lints);
+                if errors > 0 {
+                    eprintln!(
+                        "{} {}: {} error(s) found by the strict type checker",
+                        "!".bright_red(),
+                        "Error".bright_red().bold(),
+                        errors
+                    );
+                    std::process::exit(1);
+                }
+            }
 
             // Run the entrypoint file
-            run_file(&manifest.entrypoint, features);
+            run_file(&manifest.entrypoint, features, profile, exit_with_result);
         }
         Err(e) => {
             match e {
                 loft::manifest::ManifestError::NotFound => {
-                    println!(
+                    eprintln!(
                         "{} {}: No manifest.json found in current directory or parent directories",
                         "!".bright_red(),
                         "Error".bright_red().bold()
                     );
-                    println!(
+                    eprintln!(
                         "Run {} to create a new project or specify a file to run",
                         "loft new <project-name>".bright_cyan()
                     );
                 }
+                loft::manifest::ManifestError::Invalid(e) => print_error(e.as_ref()),
                 _ => {
-                    println!(
+                    eprintln!(
                         "{} {}: Failed to load manifest.json: {}",
                         "!".bright_red(),
                         "Error".bright_red().bold(),
@@ -676,27 +1304,200 @@ fn run_from_manifest(features: Vec<String>) {
     }
 }
 
-fn run_new(name: &str) {
-    use std::fs;
-    use std::path::Path;
+/// The files a watched run depends on: the entrypoint and every module it imports
+#[cfg(not(target_arch = "wasm32"))]
+fn watched_files(target: &str) -> Vec<std::path::PathBuf> {
+    use std::path::PathBuf;
 
-    let (project_dir, project_name) = if name == "." {
-        // Create project in current directory
-        let current_dir = std::env::current_dir().unwrap_or_else(|e| {
-            println!(
-                "{} {}: Failed to get current directory: {}",
+    let mut files = Vec::new();
+    let entrypoint = if target == "." {
+        files.extend(PathBuf::from("manifest.json").canonicalize());
+        match loft::manifest::Manifest::find_and_load(".") {
+            Ok(manifest) => manifest.entrypoint,
+            Err(_) => return files,
+        }
+    } else {
+        target.to_string()
+    };
+
+    let mut pending = vec![PathBuf::from(entrypoint)];
+    while let Some(file) = pending.pop() {
+        let Ok(path) = file.canonicalize() else {
+            continue;
+        };
+        if files.contains(&path) {
+            continue;
+        }
+        files.push(path.clone());
+
+        // Modules that fail to read or parse are still watched so fixing them triggers a run
+        let display_path = file.display().to_string();
+        let Ok(source) = std::fs::read_to_string(&file) else {
+            continue;
+        };
+        let (stmts, _) = Parser::new(InputStream::new(&display_path, &source)).parse_recoverable();
+        for import in loft::analysis::symbols::collect_imports(&stmts) {
+            if let Ok(module) = loft::runtime::resolve_module_path(Some(&display_path), &import) {
+                pending.push(module);
+            }
+        }
+    }
+
+    files
+}
+
+/// Run a file in a child process, restarting it whenever a file it depends on changes
+#[cfg(not(target_arch = "wasm32"))]
+fn run_watch(target: &str) {
+    use notify::{EventKind, RecursiveMode, Watcher};
+    use std::collections::HashSet;
+    use std::sync::mpsc;
+    use std::time::{Duration, Instant};
+
+    // The child gets the same arguments without the watch flag
+    let mut args = Vec::new();
+    let mut passthrough = false;
+    for arg in std::env::args().skip(1) {
+        passthrough |= arg == "--";
+        if passthrough || (arg != "--watch" && arg != "-w") {
+            args.push(arg);
+        }
+    }
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => {
+            eprintln!(
+                "{} {}: {}",
                 "!".bright_red(),
                 "Error".bright_red().bold(),
                 e
             );
             std::process::exit(1);
-        });
+        }
+    };
 
-        let project_name = current_dir
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("loft-project")
-            .to_string();
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = match notify::recommended_watcher(tx) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            eprintln!(
+                "{} {}: Failed to start file watcher: {}",
+                "!".bright_red(),
+                "Error".bright_red().bold(),
+                e
+            );
+            std::process::exit(1);
+        }
+    };
+    let mut watched_dirs = HashSet::new();
+
+    loop {
+        // Imports may have changed, so the set of files is rebuilt for every run
+        let files = watched_files(target);
+        // Editors often save by replacing the file, so watch the directories that hold them
+        for dir in files.iter().filter_map(|file| file.parent()) {
+            if watched_dirs.insert(dir.to_path_buf()) {
+                let _ = watcher.watch(dir, RecursiveMode::NonRecursive);
+            }
+        }
+
+        let started = Instant::now();
+        let mut child = match std::process::Command::new(&exe).args(&args).spawn() {
+            Ok(child) => Some(child),
+            Err(e) => {
+                eprintln!(
+                    "{} {}: {}",
+                    "!".bright_red(),
+                    "Error".bright_red().bold(),
+                    e
+                );
+                std::process::exit(1);
+            }
+        };
+
+        let changed = loop {
+            match rx.recv_timeout(Duration::from_millis(100)) {
+                Ok(Ok(event)) => {
+                    let relevant = matches!(
+                        event.kind,
+                        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+                    );
+                    if let Some(path) = event.paths.into_iter().find(|path| {
+                        relevant && files.contains(&path.canonicalize().unwrap_or(path.clone()))
+                    }) {
+                        break path;
+                    }
+                }
+                Ok(Err(_)) | Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => return,
+            }
+
+            // Report once when the run finishes on its own
+            if let Some(status) = child.as_mut().and_then(|c| c.try_wait().ok().flatten()) {
+                let elapsed = format!("{:.2}s", started.elapsed().as_secs_f64());
+                let outcome = if status.success() {
+                    format!("finished in {}", elapsed)
+                        .bright_green()
+                        .to_string()
+                } else {
+                    format!(
+                        "failed with exit code {} after {}",
+                        status.code().unwrap_or(1),
+                        elapsed
+                    )
+                    .bright_red()
+                    .to_string()
+                };
+                loft::output::status(format!(
+                    "{} {} {} watching {} file(s)",
+                    "[watch]".bright_cyan(),
+                    outcome,
+                    "·".dimmed(),
+                    files.len()
+                ));
+                child = None;
+            }
+        };
+
+        if let Some(mut child) = child {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+
+        // Saving often fires a burst of events, so let it settle before re-running
+        std::thread::sleep(Duration::from_millis(50));
+        while rx.try_recv().is_ok() {}
+
+        let cwd = std::env::current_dir().unwrap_or_default();
+        loft::output::status(format!(
+            "{} {} changed, restarting",
+            "[watch]".bright_cyan(),
+            changed.strip_prefix(&cwd).unwrap_or(&changed).display()
+        ));
+    }
+}
+
+fn run_new(name: &str) {
+    use std::fs;
+    use std::path::Path;
+
+    let (project_dir, project_name) = if name == "." {
+        // Create project in current directory
+        let current_dir = std::env::current_dir().unwrap_or_else(|e| {
+            println!(
+                "{} {}: Failed to get current directory: {}",
+                "!".bright_red(),
+                "Error".bright_red().bold(),
+                e
+            );
+            std::process::exit(1);
+        });
+
+        let project_name = current_dir
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("loft-project")
+            .to_string();
 
         (current_dir, project_name)
     } else {
@@ -923,17 +1724,7 @@ fn run_add(dep_name: &str, dep_path: Option<&str>, version_constraint: Option<&s
             .insert(dep_name.to_string(), dependency_path.clone());
 
         // Write updated manifest
-        let manifest_json = serde_json::json!({
-            "name": manifest.name,
-            "version": manifest.version,
-            "entrypoint": manifest.entrypoint,
-            "dependencies": manifest.dependencies
-        });
-
-        match fs::write(
-            &manifest_path,
-            serde_json::to_string_pretty(&manifest_json).unwrap(),
-        ) {
+        match write_manifest_dependencies(&manifest_path, &manifest.dependencies) {
             Ok(_) => {
                 println!(
                     "{} {} dependency '{}' with path '{}'",
@@ -961,49 +1752,29 @@ fn run_add(dep_name: &str, dep_path: Option<&str>, version_constraint: Option<&s
             dep_name.bright_white()
         );
 
-        let registry_url = normalize_registry_url(
-            std::env::var("LOFT_REGISTRY")
-                .unwrap_or_else(|_| "https://loft.fargone.sh".to_string()),
-        );
-
         // Get package info
-        let client = reqwest::blocking::Client::new();
-        let package_url = format!("{}/packages/{}", registry_url, dep_name);
-
-        let response = match client.get(&package_url).send() {
-            Ok(resp) => resp,
-            Err(e) => {
+        let client = RegistryClient::from_env();
+        let packages = match client.get_package(dep_name) {
+            Ok(p) => p,
+            Err(RegistryError::NotFound) => {
                 println!(
-                    "{}: Failed to connect to registry: {}",
+                    "{}: Package '{}' not found in registry",
                     "Error".bright_red().bold(),
-                    e
+                    dep_name
+                );
+                println!(
+                    "Use {} to add a local dependency",
+                    "--path <path>".bright_cyan()
                 );
-                println!("Make sure the registry is running at {}", registry_url);
                 std::process::exit(1);
             }
-        };
-
-        if !response.status().is_success() {
-            println!(
-                "{}: Package '{}' not found in registry",
-                "Error".bright_red().bold(),
-                dep_name
-            );
-            println!(
-                "Use {} to add a local dependency",
-                "--path <path>".bright_cyan()
-            );
-            std::process::exit(1);
-        }
-
-        let packages: Vec<serde_json::Value> = match response.json() {
-            Ok(p) => p,
+            Err(e @ RegistryError::Network(_)) => {
+                println!("{}: {}", "Error".bright_red().bold(), e);
+                println!("Make sure the registry is running at {}", client.url());
+                std::process::exit(1);
+            }
             Err(e) => {
-                println!(
-                    "{}: Failed to parse registry response: {}",
-                    "Error".bright_red().bold(),
-                    e
-                );
+                println!("{}: {}", "Error".bright_red().bold(), e);
                 std::process::exit(1);
             }
         };
@@ -1019,16 +1790,22 @@ fn run_add(dep_name: &str, dep_path: Option<&str>, version_constraint: Option<&s
 
         // Determine version constraint
         let constraint_str = version_constraint.unwrap_or("^0.0.0"); // Default to any version
+        let locked_version = locked_version(&current_dir, dep_name);
 
         // Find best matching version
         let selected_version = if let Some(exact_version) =
             version_constraint.filter(|v| !v.starts_with('^') && !v.starts_with('~'))
         {
             // Exact version specified
-            if packages
-                .iter()
-                .any(|p| p["version"].as_str() == Some(exact_version))
-            {
+            let found = packages.iter().find(|p| p.version == exact_version);
+            if let Some(package) = found.filter(|p| !is_selectable(p, locked_version.as_deref())) {
+                println!(
+                    "{}: Version '{}' has been yanked",
+                    "Error".bright_red().bold(),
+                    package.version
+                );
+                std::process::exit(1);
+            } else if found.is_some() {
                 exact_version.to_string()
             } else {
                 println!(
@@ -1050,12 +1827,14 @@ fn run_add(dep_name: &str, dep_path: Option<&str>, version_constraint: Option<&s
 
             // Find the best matching version
             let mut best_match: Option<String> = None;
-            for pkg in &packages {
-                if let Some(ver_str) = pkg["version"].as_str() {
-                    if let Ok(ver) = semver::Version::parse(ver_str) {
-                        if version_req.matches(&ver) {
-                            best_match = Some(ver_str.to_string());
-                        }
+            let selectable: Vec<&PackageVersion> = packages
+                .iter()
+                .filter(|p| is_selectable(p, locked_version.as_deref()))
+                .collect();
+            for pkg in &selectable {
+                if let Ok(ver) = semver::Version::parse(&pkg.version) {
+                    if version_req.matches(&ver) {
+                        best_match = Some(pkg.version.clone());
                     }
                 }
             }
@@ -1064,10 +1843,17 @@ fn run_add(dep_name: &str, dep_path: Option<&str>, version_constraint: Option<&s
                 Some(v) => v,
                 None => {
                     // No matching version, use latest
-                    packages.last().unwrap()["version"]
-                        .as_str()
-                        .unwrap()
-                        .to_string()
+                    match selectable.last() {
+                        Some(latest) => latest.version.clone(),
+                        None => {
+                            println!(
+                                "{}: Every version of '{}' has been yanked",
+                                "Error".bright_red().bold(),
+                                dep_name
+                            );
+                            std::process::exit(1);
+                        }
+                    }
                 }
             }
         };
@@ -1101,33 +1887,7 @@ fn run_add(dep_name: &str, dep_path: Option<&str>, version_constraint: Option<&s
         io::stdout().flush().ok();
 
         // Download tarball
-        let download_url = format!(
-            "{}/packages/{}/{}/download",
-            registry_url, dep_name, version
-        );
-        let tarball_response = match client.get(&download_url).send() {
-            Ok(resp) => resp,
-            Err(e) => {
-                println!();
-                println!(
-                    "{}: Failed to download package: {}",
-                    "Error".bright_red().bold(),
-                    e
-                );
-                std::process::exit(1);
-            }
-        };
-
-        if !tarball_response.status().is_success() {
-            println!();
-            println!(
-                "{}: Failed to download package tarball",
-                "Error".bright_red().bold()
-            );
-            std::process::exit(1);
-        }
-
-        let tarball_data = match tarball_response.bytes() {
+        let tarball_data = match client.download(dep_name, version) {
             Ok(data) => {
                 let full_bar = format!(
                     "{}{}{}",
@@ -1144,7 +1904,7 @@ fn run_add(dep_name: &str, dep_path: Option<&str>, version_constraint: Option<&s
             Err(e) => {
                 println!();
                 println!(
-                    "{}: Failed to read package data: {}",
+                    "{}: Failed to download package: {}",
                     "Error".bright_red().bold(),
                     e
                 );
@@ -1202,17 +1962,7 @@ fn run_add(dep_name: &str, dep_path: Option<&str>, version_constraint: Option<&s
             .insert(dep_name.to_string(), constraint_str.to_string());
 
         // Write updated manifest
-        let manifest_json = serde_json::json!({
-            "name": manifest.name,
-            "version": manifest.version,
-            "entrypoint": manifest.entrypoint,
-            "dependencies": manifest.dependencies
-        });
-
-        match fs::write(
-            &manifest_path,
-            serde_json::to_string_pretty(&manifest_json).unwrap(),
-        ) {
+        match write_manifest_dependencies(&manifest_path, &manifest.dependencies) {
             Ok(_) => {
                 println!();
                 println!(
@@ -1224,6 +1974,14 @@ fn run_add(dep_name: &str, dep_path: Option<&str>, version_constraint: Option<&s
                     constraint_str.dimmed()
                 );
                 println!("  {} {}", "Location".dimmed(), package_dir.display());
+                record_locked_package(
+                    &current_dir,
+                    &manifest.dependencies,
+                    dep_name,
+                    version,
+                    client.url(),
+                    &tarball_data,
+                );
             }
             Err(e) => {
                 println!(
@@ -1272,10 +2030,7 @@ fn run_update(specific_package: Option<&str>) {
         }
     };
 
-    let registry_url = normalize_registry_url(
-        std::env::var("LOFT_REGISTRY").unwrap_or_else(|_| "https://loft.fargone.sh".to_string()),
-    );
-    let client = reqwest::blocking::Client::new();
+    let client = RegistryClient::from_env();
     let lflibs_dir = current_dir.join(".lflibs");
 
     // Filter dependencies to update
@@ -1321,35 +2076,19 @@ fn run_update(specific_package: Option<&str>) {
 
     for (dep_name, constraint_str) in deps_to_update {
         // Get package info from registry
-        let package_url = format!("{}/packages/{}", registry_url, dep_name);
-
-        let response = match client.get(&package_url).send() {
-            Ok(resp) => resp,
-            Err(e) => {
+        let packages = match client.get_package(&dep_name) {
+            Ok(p) => p,
+            Err(RegistryError::NotFound) => {
                 println!(
-                    "{}: Failed to fetch '{}': {}",
+                    "{}: Package '{}' not found in registry",
                     "Warning".bright_yellow().bold(),
-                    dep_name,
-                    e
+                    dep_name
                 );
                 continue;
             }
-        };
-
-        if !response.status().is_success() {
-            println!(
-                "{}: Package '{}' not found in registry",
-                "Warning".bright_yellow().bold(),
-                dep_name
-            );
-            continue;
-        }
-
-        let packages: Vec<serde_json::Value> = match response.json() {
-            Ok(p) => p,
             Err(e) => {
                 println!(
-                    "{}: Failed to parse registry response for '{}': {}",
+                    "{}: Failed to fetch '{}': {}",
                     "Warning".bright_yellow().bold(),
                     dep_name,
                     e
@@ -1382,29 +2121,17 @@ fn run_update(specific_package: Option<&str>) {
         };
 
         // Find the best matching version
-        let mut best_match: Option<(String, semver::Version)> = None;
-        for pkg in &packages {
-            if let Some(ver_str) = pkg["version"].as_str() {
-                if let Ok(ver) = semver::Version::parse(ver_str) {
-                    if version_req.matches(&ver)
-                        && (best_match.is_none() || best_match.as_ref().unwrap().1 < ver) {
-                            best_match = Some((ver_str.to_string(), ver));
-                        }
-                }
-            }
-        }
-
-        let (selected_version, _) = match best_match {
-            Some(v) => v,
-            None => {
-                println!(
-                    "{}: No matching version for '{}' with constraint '{}'",
-                    "Warning".bright_yellow().bold(),
-                    dep_name,
-                    constraint_str
-                );
-                continue;
-            }
+        let locked_version = locked_version(&current_dir, &dep_name);
+        let Some(selected_version) =
+            select_version(&packages, &version_req, locked_version.as_deref())
+        else {
+            println!(
+                "{}: No matching version for '{}' with constraint '{}'",
+                "Warning".bright_yellow().bold(),
+                dep_name,
+                constraint_str
+            );
+            continue;
         };
 
         // Check if this version is already installed
@@ -1428,35 +2155,11 @@ fn run_update(specific_package: Option<&str>) {
             constraint_str.dimmed()
         );
 
-        let download_url = format!(
-            "{}/packages/{}/{}/download",
-            registry_url, dep_name, selected_version
-        );
-        let tarball_response = match client.get(&download_url).send() {
-            Ok(resp) => resp,
-            Err(e) => {
-                println!(
-                    "    {}: Failed to download: {}",
-                    "Error".bright_red().bold(),
-                    e
-                );
-                continue;
-            }
-        };
-
-        if !tarball_response.status().is_success() {
-            println!(
-                "    {}: Failed to download tarball",
-                "Error".bright_red().bold()
-            );
-            continue;
-        }
-
-        let tarball_data = match tarball_response.bytes() {
+        let tarball_data = match client.download(&dep_name, &selected_version) {
             Ok(data) => data,
             Err(e) => {
                 println!(
-                    "    {}: Failed to read package data: {}",
+                    "    {}: Failed to download: {}",
                     "Error".bright_red().bold(),
                     e
                 );
@@ -1506,6 +2209,14 @@ fn run_update(specific_package: Option<&str>) {
             "v".bright_green(),
             selected_version.bright_white()
         );
+        record_locked_package(
+            &current_dir,
+            &manifest.dependencies,
+            &dep_name,
+            &selected_version,
+            client.url(),
+            &tarball_data,
+        );
         updated_count += 1;
     }
 
@@ -1526,86 +2237,523 @@ fn run_update(specific_package: Option<&str>) {
     }
 }
 
-fn run_doc(output_dir: &str) {
-    use loft::docgen::DocGenerator;
+fn run_remove(dep_name: &str) {
+    use loft::lockfile::{Lockfile, LOCKFILE_NAME};
     use loft::manifest::Manifest;
+    use std::fs;
     use std::path::Path;
 
-    println!(
-        "{} {}",
-        "DOC".bright_cyan(),
-        "Generating documentation...".bright_cyan().bold()
-    );
-    println!();
+    let current_dir = std::env::current_dir().unwrap_or_else(|_| Path::new(".").to_path_buf());
+    let manifest_path = current_dir.join("manifest.json");
 
-    // Load manifest
-    let manifest = match Manifest::find_and_load(".") {
-        Ok(m) => m,
+    // Edit the JSON directly so fields the command doesn't know about are kept
+    let mut manifest_json: serde_json::Value = match fs::read_to_string(&manifest_path)
+        .map_err(|e| e.to_string())
+        .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+    {
+        Ok(json) => json,
         Err(e) => {
-            println!("{}: {}", "Error".bright_red().bold(), e);
-            println!("Make sure you're in a loft project directory with a manifest.json file.");
+            println!(
+                "{}: Failed to load manifest.json: {}",
+                "Error".bright_red().bold(),
+                e
+            );
             std::process::exit(1);
         }
     };
 
-    println!("Package: {}", manifest.name.bright_white().bold());
-    println!("Version: {}", manifest.version.bright_white());
-    println!();
-
-    // Initialize doc generator
-    let mut doc_gen = DocGenerator::new();
+    let removed = manifest_json
+        .get_mut("dependencies")
+        .and_then(|deps| deps.as_object_mut())
+        .and_then(|deps| deps.remove(dep_name));
+    if removed.is_none() {
+        println!(
+            "{}: '{}' is not a dependency of this project",
+            "Error".bright_red().bold(),
+            dep_name
+        );
+        std::process::exit(1);
+    }
 
-    // Parse the entrypoint file
-    let entrypoint_path = Path::new(&manifest.entrypoint);
-    if !entrypoint_path.exists() {
+    if let Err(e) = fs::write(
+        &manifest_path,
+        serde_json::to_string_pretty(&manifest_json).unwrap(),
+    ) {
         println!(
-            "{}: Entrypoint file '{}' not found",
+            "{}: Failed to write manifest.json: {}",
             "Error".bright_red().bold(),
-            manifest.entrypoint
+            e
         );
         std::process::exit(1);
     }
 
-    println!("Parsing: {}", manifest.entrypoint.bright_white());
-    match doc_gen.parse_file(&manifest.entrypoint) {
-        Ok(_) => {}
-        Err(e) => {
-            println!("{}: {}", "Parse error".bright_red().bold(), e);
-            std::process::exit(1);
+    // Installed versions, and the link `loft install` makes for local packages
+    let lflibs_dir = current_dir.join(".lflibs");
+    let mut dependents = Vec::new();
+    if let Ok(entries) = fs::read_dir(&lflibs_dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let dir_name = entry.file_name().to_string_lossy().to_string();
+            if dir_name == dep_name || dir_name.starts_with(&format!("{}@", dep_name)) {
+                let result = if path.is_symlink() {
+                    fs::remove_file(&path)
+                } else {
+                    fs::remove_dir_all(&path)
+                };
+                if let Err(e) = result {
+                    println!(
+                        "{}: Failed to remove {}: {}",
+                        "Warning".bright_yellow().bold(),
+                        path.display(),
+                        e
+                    );
+                }
+            } else if let Ok(package) = Manifest::load(path.join("manifest.json")) {
+                if package.dependencies.contains_key(dep_name) {
+                    dependents.push(dir_name);
+                }
+            }
         }
     }
 
-    // Find and parse all .lf files in src/
-    let src_dir = Path::new("src");
-    if src_dir.exists() && src_dir.is_dir() {
-        if let Ok(entries) = std::fs::read_dir(src_dir) {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("lf") {
-                    // Skip if it's already the entrypoint
-                    if path != entrypoint_path {
-                        println!("Parsing: {}", path.display().to_string().bright_white());
-                        if let Err(e) = doc_gen.parse_file(&path) {
-                            println!("{}: {}", "Warning".bright_yellow().bold(), e);
-                        }
-                    }
-                }
+    if let Ok(mut lockfile) = Lockfile::load_or_default(&current_dir) {
+        if lockfile.remove(dep_name).is_some() {
+            if let Err(e) = lockfile.save(current_dir.join(LOCKFILE_NAME)) {
+                println!(
+                    "{}: Failed to update {}: {}",
+                    "Warning".bright_yellow().bold(),
+                    LOCKFILE_NAME,
+                    e
+                );
             }
         }
     }
 
-    println!();
-    println!("Generating HTML...");
+    println!(
+        "{} {} dependency '{}'",
+        "[pkg]".bright_green(),
+        "Removed".bright_green().bold(),
+        dep_name.bright_white()
+    );
 
-    // Generate HTML documentation
-    let output_path = Path::new(output_dir);
-    match doc_gen.generate_html(output_path, &manifest.name) {
-        Ok(_) => {
-            println!();
-            println!(
-                "{} {}",
-                "*".bright_green(),
-                "Documentation generated successfully!"
+    dependents.sort();
+    for dependent in dependents {
+        println!(
+            "{}: Installed package '{}' still depends on '{}'",
+            "Warning".bright_yellow().bold(),
+            dependent,
+            dep_name
+        );
+    }
+}
+
+fn run_install() {
+    use loft::bundle::Bundle;
+    use loft::lockfile::Lockfile;
+    use loft::manifest::Manifest;
+    use std::fs;
+    use std::path::Path;
+
+    let current_dir = std::env::current_dir().unwrap_or_else(|_| Path::new(".").to_path_buf());
+    let manifest_path = current_dir.join("manifest.json");
+
+    if !manifest_path.exists() {
+        println!(
+            "{} {}: No manifest.json found in current directory",
+            "!".bright_red(),
+            "Error".bright_red().bold()
+        );
+        println!(
+            "Run {} to create a new project",
+            "loft new <project-name>".bright_cyan()
+        );
+        std::process::exit(1);
+    }
+
+    let manifest = match Manifest::load(&manifest_path) {
+        Ok(m) => m,
+        Err(e) => {
+            println!(
+                "{}: Failed to load manifest.json: {}",
+                "Error".bright_red().bold(),
+                e
+            );
+            std::process::exit(1);
+        }
+    };
+
+    // Packages pinned in loft.lock are installed at exactly that version
+    install_locked_packages(&manifest);
+    let lockfile = Lockfile::load_or_default(&current_dir).unwrap_or_default();
+
+    let client = RegistryClient::from_env();
+    let lflibs_dir = current_dir.join(".lflibs");
+    let workspace = loft::manifest::Workspace::find(&current_dir).ok().flatten();
+    let mut failed = 0;
+
+    let mut dependencies: Vec<(&String, &String)> = manifest.dependencies.iter().collect();
+    dependencies.sort();
+
+    for (dep_name, constraint_str) in dependencies {
+        let is_local = constraint_str.starts_with("./")
+            || constraint_str.starts_with("../")
+            || constraint_str.starts_with('/');
+
+        if is_local {
+            match link_local_package(&lflibs_dir, dep_name, Path::new(constraint_str)) {
+                Ok(true) => println!(
+                    "  {} {} (linked to {})",
+                    "v".bright_green(),
+                    dep_name.bright_white(),
+                    constraint_str.dimmed()
+                ),
+                Ok(false) => println!(
+                    "  {} {} ({})",
+                    "v".bright_green(),
+                    dep_name.bright_white(),
+                    constraint_str.dimmed()
+                ),
+                Err(e) => {
+                    println!("  {}: {}: {}", "Error".bright_red().bold(), dep_name, e);
+                    failed += 1;
+                }
+            }
+            continue;
+        }
+
+        // Other projects of the workspace are imported from source
+        if let Some((dir, _)) = workspace.as_ref().and_then(|w| w.member(dep_name)) {
+            println!(
+                "  {} {} (workspace member {})",
+                "v".bright_green(),
+                dep_name.bright_white(),
+                dir.display().to_string().dimmed()
+            );
+            continue;
+        }
+
+        if let Some(locked) = lockfile.get(dep_name) {
+            println!(
+                "  {} {} v{} (locked)",
+                "v".bright_green(),
+                dep_name.bright_white(),
+                locked.version.bright_white()
+            );
+            continue;
+        }
+
+        let version_req = match semver::VersionReq::parse(constraint_str) {
+            Ok(req) => req,
+            Err(_) => {
+                println!(
+                    "  {}: Invalid version constraint '{}' for '{}'",
+                    "Error".bright_red().bold(),
+                    constraint_str,
+                    dep_name
+                );
+                failed += 1;
+                continue;
+            }
+        };
+
+        let version = match client
+            .get_package(dep_name)
+            .map(|packages| select_version(&packages, &version_req, None))
+        {
+            Ok(Some(version)) => version,
+            Ok(None) => {
+                println!(
+                    "  {}: No matching version for '{}' with constraint '{}'",
+                    "Error".bright_red().bold(),
+                    dep_name,
+                    constraint_str
+                );
+                failed += 1;
+                continue;
+            }
+            Err(e) => {
+                println!(
+                    "  {}: Failed to fetch '{}': {}",
+                    "Error".bright_red().bold(),
+                    dep_name,
+                    e
+                );
+                failed += 1;
+                continue;
+            }
+        };
+
+        let package_dir = lflibs_dir.join(format!("{}@{}", dep_name, version));
+        let result = client
+            .download(dep_name, &version)
+            .map_err(|e| e.to_string())
+            .and_then(|tarball_data| {
+                fs::create_dir_all(&package_dir).map_err(|e| e.to_string())?;
+                let tar_gz = flate2::read::GzDecoder::new(&tarball_data[..]);
+                if let Err(e) = tar::Archive::new(tar_gz).unpack(&package_dir) {
+                    fs::remove_dir_all(&package_dir).ok();
+                    return Err(format!("Failed to extract package: {}", e));
+                }
+                Ok(tarball_data)
+            });
+
+        match result {
+            Ok(tarball_data) => {
+                println!(
+                    "  {} {} v{} (constraint: {})",
+                    "+".bright_cyan(),
+                    dep_name.bright_white(),
+                    version.bright_white(),
+                    constraint_str.dimmed()
+                );
+                record_locked_package(
+                    &current_dir,
+                    &manifest.dependencies,
+                    dep_name,
+                    &version,
+                    client.url(),
+                    &tarball_data,
+                );
+            }
+            Err(e) => {
+                println!("  {}: {}: {}", "Error".bright_red().bold(), dep_name, e);
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        println!();
+        println!(
+            "{}: {} dependency(ies) could not be installed",
+            "Error".bright_red().bold(),
+            failed
+        );
+        std::process::exit(1);
+    }
+
+    // Everything the entrypoint imports, directly or not, must now resolve
+    if let Err(e) = Bundle::build(&current_dir, &current_dir.join(&manifest.entrypoint)) {
+        println!();
+        println!("{}: {}", "Error".bright_red().bold(), e);
+        std::process::exit(1);
+    }
+
+    println!();
+    println!(
+        "{} {} {} dependency(ies)",
+        "*".bright_green(),
+        "Installed".bright_green().bold(),
+        manifest.dependencies.len()
+    );
+}
+
+/// Make a local package directory importable by linking it into `.lflibs/<name>`.
+/// Returns false for a dependency on a single file, which needs no link.
+fn link_local_package(
+    lflibs_dir: &std::path::Path,
+    name: &str,
+    path: &std::path::Path,
+) -> std::io::Result<bool> {
+    use std::fs;
+    use std::io::{Error, ErrorKind};
+
+    if !path.exists() {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            format!("'{}' does not exist", path.display()),
+        ));
+    }
+    if !path.join("manifest.json").is_file() {
+        return Ok(false);
+    }
+
+    fs::create_dir_all(lflibs_dir)?;
+    let link = lflibs_dir.join(name);
+    if let Ok(metadata) = fs::symlink_metadata(&link) {
+        if metadata.is_dir() {
+            fs::remove_dir_all(&link)?;
+        } else {
+            fs::remove_file(&link)?;
+        }
+    }
+
+    let target = path.canonicalize()?;
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(target, link)?;
+    #[cfg(windows)]
+    std::os::windows::fs::symlink_dir(target, link)?;
+    Ok(true)
+}
+
+/// Run the current command without `--workspace` in each workspace member
+fn run_workspace() {
+    use loft::manifest::{Manifest, Workspace};
+
+    let current_dir = std::env::current_dir().unwrap_or_else(|_| ".".into());
+    let workspace = match Workspace::find(&current_dir) {
+        Ok(Some(workspace)) => workspace,
+        Ok(None) => {
+            println!(
+                "{}: No manifest.json with a \"workspace\" section found",
+                "Error".bright_red().bold()
+            );
+            std::process::exit(1);
+        }
+        Err(e) => {
+            println!(
+                "{}: Failed to load the workspace: {}",
+                "Error".bright_red().bold(),
+                e
+            );
+            std::process::exit(1);
+        }
+    };
+
+    let exe = std::env::current_exe().unwrap_or_else(|_| "loft".into());
+    let args: Vec<String> = std::env::args()
+        .skip(1)
+        .filter(|arg| arg != "--workspace")
+        .collect();
+
+    let mut failed = Vec::new();
+    for dir in workspace.member_dirs() {
+        let relative = dir.strip_prefix(&workspace.root).unwrap_or(&dir);
+        let name = Manifest::load(dir.join("manifest.json"))
+            .map(|m| m.name)
+            .unwrap_or_else(|_| relative.display().to_string());
+        println!(
+            "{} {} ({})",
+            ">".truecolor(LUMINOUS.0, LUMINOUS.1, LUMINOUS.2),
+            name.truecolor(ACID.0, ACID.1, ACID.2).bold(),
+            relative.display()
+        );
+
+        let status = std::process::Command::new(&exe)
+            .args(&args)
+            .current_dir(&dir)
+            .status();
+        if !status.map(|s| s.success()).unwrap_or(false) {
+            failed.push(name);
+        }
+        println!();
+    }
+
+    if !failed.is_empty() {
+        println!(
+            "{}: Failed in {}",
+            "Error".bright_red().bold(),
+            failed.join(", ")
+        );
+        std::process::exit(1);
+    }
+}
+
+fn run_doc(output_dir: &str) {
+    use loft::analysis::project::Project;
+    use loft::docgen::DocGenerator;
+    use loft::manifest::Manifest;
+    use std::path::Path;
+
+    println!(
+        "{} {}",
+        "DOC".bright_cyan(),
+        "Generating documentation...".bright_cyan().bold()
+    );
+    println!();
+
+    // Load manifest
+    let manifest = match Manifest::find_and_load(".") {
+        Ok(m) => m,
+        Err(e) => {
+            println!("{}: {}", "Error".bright_red().bold(), e);
+            println!("Make sure you're in a loft project directory with a manifest.json file.");
+            std::process::exit(1);
+        }
+    };
+
+    println!("Package: {}", manifest.name.bright_white().bold());
+    println!("Version: {}", manifest.version.bright_white());
+    println!();
+
+    // Initialize doc generator
+    let mut doc_gen = DocGenerator::new();
+
+    // Parse the entrypoint file
+    let entrypoint_path = Path::new(&manifest.entrypoint);
+    if !entrypoint_path.exists() {
+        println!(
+            "{}: Entrypoint file '{}' not found",
+            "Error".bright_red().bold(),
+            manifest.entrypoint
+        );
+        std::process::exit(1);
+    }
+
+    // Parse the entrypoint and all .lf files in src/ together
+    let mut paths = vec![entrypoint_path.to_path_buf()];
+    let src_dir = Path::new("src");
+    if src_dir.exists() && src_dir.is_dir() {
+        if let Ok(entries) = std::fs::read_dir(src_dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.is_file()
+                    && path.extension().and_then(|s| s.to_str()) == Some("lf")
+                    && path != entrypoint_path
+                {
+                    paths.push(path);
+                }
+            }
+        }
+    }
+    let project = Project::load(&paths);
+
+    for (path, e) in &project.unreadable {
+        let message = format!("Failed to read file {}: {}", path.display(), e);
+        if path == entrypoint_path {
+            println!("{}: {}", "Parse error".bright_red().bold(), message);
+            std::process::exit(1);
+        }
+        println!("{}: {}", "Warning".bright_yellow().bold(), message);
+    }
+    for file in &project.files {
+        println!(
+            "Parsing: {}",
+            file.path.display().to_string().bright_white()
+        );
+        if let Some(e) = file.errors.first() {
+            let message = format!("Failed to parse {}: {}", file.path.display(), e.message);
+            if file.path == entrypoint_path {
+                println!("{}: {}", "Parse error".bright_red().bold(), message);
+                std::process::exit(1);
+            }
+            println!("{}: {}", "Warning".bright_yellow().bold(), message);
+            continue;
+        }
+        doc_gen.add_parsed(&file.path, &file.source, &file.stmts);
+    }
+
+    // Link to the registry docs of dependencies
+    doc_gen.add_dependencies(&manifest, Path::new("."));
+    for dep in &doc_gen.dependencies {
+        println!(
+            "Linking: {}",
+            format!("{}@{}", dep.name, dep.version).bright_white()
+        );
+    }
+
+    println!();
+    println!("Generating HTML...");
+
+    // Generate HTML documentation
+    let output_path = Path::new(output_dir);
+    match doc_gen.generate_html(output_path, &manifest.name) {
+        Ok(_) => {
+            println!();
+            println!(
+                "{} {}",
+                "*".bright_green(),
+                "Documentation generated successfully!"
                     .bright_green()
                     .bold()
             );
@@ -1645,7 +2793,7 @@ fn run_stdlib_doc(output_dir: &str) {
     println!();
 
     // Load stdlib_types.json
-    let stdlib_json = include_str!("lsp/stdlib_types.json");
+    let stdlib_json = loft::analysis::stdlib::JSON;
 
     let doc_gen = match StdlibDocGenerator::new(stdlib_json) {
         Ok(gen) => gen,
@@ -1689,23 +2837,440 @@ fn run_stdlib_doc(output_dir: &str) {
     }
 }
 
-/// Downgrade https → http for loopback addresses so local dev servers
-/// (which don't have TLS) work without extra flags.
-fn normalize_registry_url(url: String) -> String {
-    if url.starts_with("https://127.0.0.1")
-        || url.starts_with("https://localhost")
-        || url.starts_with("https://[::1]")
-    {
-        url.replacen("https://", "http://", 1)
-    } else {
-        url
+fn run_search(query: &str) {
+    let packages = match RegistryClient::from_env().search(query) {
+        Ok(p) => p,
+        Err(e) => {
+            println!("{}: {}", "Error".bright_red().bold(), e);
+            std::process::exit(1);
+        }
+    };
+
+    if packages.is_empty() {
+        println!("No packages found matching '{}'", query);
+        return;
     }
-}
 
-fn run_login(token: Option<&str>) {
-    use std::fs;
-    use std::io::{self, Write};
-    use std::path::PathBuf;
+    let width = packages
+        .iter()
+        .map(|p| p.name.len() + p.version.len() + 1)
+        .max()
+        .unwrap_or(0);
+    for package in &packages {
+        let padding = " ".repeat(width - package.name.len() - package.version.len() - 1);
+        println!(
+            "{}@{}{}  {}",
+            package.name.bright_white().bold(),
+            package.version.bright_cyan(),
+            padding,
+            package.description.as_deref().unwrap_or_default()
+        );
+    }
+}
+
+/// Report a manifest.json that couldn't be loaded and exit
+fn exit_with_manifest_error(e: loft::manifest::ManifestError) -> ! {
+    match e {
+        loft::manifest::ManifestError::NotFound => println!(
+            "{}: No manifest.json found in current or parent directories",
+            "Error".bright_red().bold()
+        ),
+        loft::manifest::ManifestError::Invalid(e) => print_error(e.as_ref()),
+        e => println!(
+            "{}: Failed to load manifest.json: {}",
+            "Error".bright_red().bold(),
+            e
+        ),
+    }
+    std::process::exit(1);
+}
+
+/// Replace the dependencies in manifest.json, keeping every other field as written
+fn write_manifest_dependencies(
+    manifest_path: &std::path::Path,
+    dependencies: &std::collections::HashMap<String, String>,
+) -> Result<(), String> {
+    let content = std::fs::read_to_string(manifest_path).map_err(|e| e.to_string())?;
+    let mut manifest: serde_json::Value =
+        serde_json::from_str(&content).map_err(|e| e.to_string())?;
+    let dependencies: std::collections::BTreeMap<_, _> = dependencies.iter().collect();
+    manifest["dependencies"] = serde_json::json!(dependencies);
+    std::fs::write(
+        manifest_path,
+        serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?,
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// The version of a package pinned in the project's loft.lock
+fn locked_version(project_dir: &std::path::Path, name: &str) -> Option<String> {
+    loft::lockfile::Lockfile::load_or_default(project_dir)
+        .ok()?
+        .get(name)
+        .map(|locked| locked.version.clone())
+}
+
+/// Whether the resolver may pick a version listed by the registry: yanked
+/// versions are skipped unless loft.lock pins exactly that version
+fn is_selectable(package: &PackageVersion, locked_version: Option<&str>) -> bool {
+    !package.yanked || locked_version == Some(package.version.as_str())
+}
+
+/// The newest selectable version matching `version_req`
+fn select_version(
+    packages: &[PackageVersion],
+    version_req: &semver::VersionReq,
+    locked_version: Option<&str>,
+) -> Option<String> {
+    packages
+        .iter()
+        .filter(|pkg| is_selectable(pkg, locked_version))
+        .filter_map(|pkg| Some((semver::Version::parse(&pkg.version).ok()?, pkg)))
+        .filter(|(ver, _)| version_req.matches(ver))
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, pkg)| pkg.version.clone())
+}
+
+/// Record the exact version and tarball checksum of an installed package in loft.lock
+fn record_locked_package(
+    project_dir: &std::path::Path,
+    dependencies: &std::collections::HashMap<String, String>,
+    name: &str,
+    version: &str,
+    registry_url: &str,
+    tarball_data: &[u8],
+) {
+    use loft::lockfile::{checksum, LockedPackage, Lockfile, LOCKFILE_NAME};
+
+    let result = Lockfile::load_or_default(project_dir).and_then(|mut lockfile| {
+        lockfile.insert(LockedPackage {
+            name: name.to_string(),
+            version: version.to_string(),
+            source: registry_url.to_string(),
+            checksum: checksum(tarball_data),
+        });
+        lockfile.retain_dependencies(dependencies);
+        lockfile.save(project_dir.join(LOCKFILE_NAME))
+    });
+
+    if let Err(e) = result {
+        println!(
+            "{}: Failed to update {}: {}",
+            "Warning".bright_yellow().bold(),
+            LOCKFILE_NAME,
+            e
+        );
+    }
+}
+
+/// Install any package pinned in loft.lock that is missing from .lflibs
+///
+/// Packages are downloaded at exactly the locked version and rejected if the
+/// tarball checksum does not match the one recorded in the lockfile.
+fn install_locked_packages(manifest: &loft::manifest::Manifest) {
+    use loft::lockfile::{checksum, Lockfile, LOCKFILE_NAME};
+    use std::fs;
+    use std::path::Path;
+
+    let current_dir = std::env::current_dir().unwrap_or_else(|_| Path::new(".").to_path_buf());
+    let lockfile = match Lockfile::load_or_default(&current_dir) {
+        Ok(lockfile) => lockfile,
+        Err(e) => {
+            println!(
+                "{}: Failed to load {}: {}",
+                "Error".bright_red().bold(),
+                LOCKFILE_NAME,
+                e
+            );
+            std::process::exit(1);
+        }
+    };
+
+    let lflibs_dir = current_dir.join(".lflibs");
+
+    for locked in &lockfile.packages {
+        if !manifest.dependencies.contains_key(&locked.name) {
+            continue;
+        }
+
+        let package_dir = lflibs_dir.join(format!("{}@{}", locked.name, locked.version));
+        if package_dir.exists() {
+            continue;
+        }
+
+        loft::output::status(format!(
+            "{} {} {} v{} (locked)",
+            ">>".bright_cyan(),
+            "Installing".bright_cyan().bold(),
+            locked.name.bright_white(),
+            locked.version.bright_white()
+        ));
+
+        let client = RegistryClient::new(locked.source.clone());
+        let tarball_data = match client.download(&locked.name, &locked.version) {
+            Ok(data) => data,
+            Err(e) => {
+                println!(
+                    "{}: Failed to download '{}': {}",
+                    "Error".bright_red().bold(),
+                    locked.name,
+                    e
+                );
+                std::process::exit(1);
+            }
+        };
+
+        let actual = checksum(&tarball_data);
+        if actual != locked.checksum {
+            println!(
+                "{}: Checksum mismatch for {} v{}",
+                "Error".bright_red().bold(),
+                locked.name,
+                locked.version
+            );
+            println!("  {} {}", "expected".dimmed(), locked.checksum);
+            println!("  {} {}", "actual".dimmed(), actual);
+            std::process::exit(1);
+        }
+
+        if let Err(e) = fs::create_dir_all(&package_dir) {
+            println!(
+                "{}: Failed to create package directory: {}",
+                "Error".bright_red().bold(),
+                e
+            );
+            std::process::exit(1);
+        }
+
+        let tar_gz = flate2::read::GzDecoder::new(&tarball_data[..]);
+        let mut archive = tar::Archive::new(tar_gz);
+        if let Err(e) = archive.unpack(&package_dir) {
+            println!(
+                "{}: Failed to extract package: {}",
+                "Error".bright_red().bold(),
+                e
+            );
+            fs::remove_dir_all(&package_dir).ok();
+            std::process::exit(1);
+        }
+    }
+}
+
+fn run_bundle(output: Option<&str>) {
+    use loft::bundle::Bundle;
+    use loft::manifest::Manifest;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    let manifest = match Manifest::find_and_load(".") {
+        Ok(m) => m,
+        Err(e) => {
+            println!("{}: {}", "Error".bright_red().bold(), e);
+            println!("Make sure you're in a loft project directory with a manifest.json file.");
+            std::process::exit(1);
+        }
+    };
+
+    let root = std::env::current_dir().unwrap_or_else(|_| Path::new(".").to_path_buf());
+    let bundle = match Bundle::build(&root, Path::new(&manifest.entrypoint)) {
+        Ok(bundle) => bundle,
+        Err(e) => {
+            println!(
+                "{}: Failed to bundle project: {}",
+                "Error".bright_red().bold(),
+                e
+            );
+            std::process::exit(1);
+        }
+    };
+
+    let output_path = output
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new("target").join(format!("{}.lf", manifest.name)));
+    if let Some(parent) = output_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).ok();
+    }
+
+    if let Err(e) = fs::write(&output_path, bundle.to_source()) {
+        println!(
+            "{}: Failed to write {}: {}",
+            "Error".bright_red().bold(),
+            output_path.display(),
+            e
+        );
+        std::process::exit(1);
+    }
+
+    for module in &bundle.modules {
+        println!("  {} {}", "+".bright_cyan(), module.path.dimmed());
+    }
+    println!(
+        "{} {} {} ({} module(s)) -> {}",
+        "OK".bright_green(),
+        "Bundled".bright_green().bold(),
+        manifest.name.bright_white(),
+        bundle.modules.len(),
+        output_path.display().to_string().bright_white()
+    );
+}
+
+fn run_compile(file: &str, output: Option<&str>, features: Vec<String>) {
+    use loft::bundle::Bundle;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    let file_path = Path::new(file);
+    let root = file_path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let mut bundle = match Bundle::build(root, file_path) {
+        Ok(bundle) => bundle,
+        Err(e) => {
+            println!(
+                "{}: Failed to compile {}: {}",
+                "Error".bright_red().bold(),
+                file,
+                e
+            );
+            std::process::exit(1);
+        }
+    };
+
+    bundle.features = features;
+
+    let exe = match std::env::current_exe().and_then(fs::read) {
+        Ok(exe) => exe,
+        Err(e) => {
+            println!(
+                "{}: Failed to read the loft executable: {}",
+                "Error".bright_red().bold(),
+                e
+            );
+            std::process::exit(1);
+        }
+    };
+
+    let output_path = output.map(PathBuf::from).unwrap_or_else(|| {
+        let stem = file_path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "main".to_string());
+        PathBuf::from(format!("{}{}", stem, std::env::consts::EXE_SUFFIX))
+    });
+    if let Some(parent) = output_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).ok();
+    }
+
+    if let Err(e) = fs::write(&output_path, bundle.embed(&exe)) {
+        println!(
+            "{}: Failed to write {}: {}",
+            "Error".bright_red().bold(),
+            output_path.display(),
+            e
+        );
+        std::process::exit(1);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&output_path, fs::Permissions::from_mode(0o755)).ok();
+    }
+
+    for module in &bundle.modules {
+        println!("  {} {}", "+".bright_cyan(), module.path.dimmed());
+    }
+    println!(
+        "{} {} {} ({} module(s)) -> {}",
+        "OK".bright_green(),
+        "Compiled".bright_green().bold(),
+        file.bright_white(),
+        bundle.modules.len(),
+        output_path.display().to_string().bright_white()
+    );
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn run_permissions(action: PermissionsAction) {
+    use loft::runtime::permissions::PermissionType;
+
+    let mut permissions = PermissionManager::new();
+    if let Err(e) = permissions.load_cache() {
+        println!(
+            "{}: Failed to read the permission cache: {}",
+            "Error".bright_red().bold(),
+            e
+        );
+        std::process::exit(1);
+    }
+
+    let result = match action {
+        PermissionsAction::List => {
+            let cached = permissions.cached();
+            if cached.is_empty() {
+                println!("{}", "No saved permissions".dimmed());
+                return;
+            }
+            let mut current_script = None;
+            for entry in &cached {
+                if current_script != Some(&entry.script) {
+                    current_script = Some(&entry.script);
+                    let script = entry.script.as_deref().unwrap_or("(unknown script)");
+                    println!("{}", script.bright_white().bold());
+                }
+                let decision = if entry.allowed {
+                    "allow".bright_green().to_string()
+                } else {
+                    "deny ".bright_red().to_string()
+                };
+                println!(
+                    "  {} {:<5} {} {}",
+                    decision,
+                    entry.permission.kind(),
+                    entry.permission.target(),
+                    entry.decided_at.as_deref().unwrap_or("").dimmed()
+                );
+            }
+            Ok(())
+        }
+        PermissionsAction::Revoke { kind, target } => {
+            let Some(permission) = PermissionType::parse(&kind, &target) else {
+                println!(
+                    "{}: Unknown kind of permission '{}', expected read, write, net or run",
+                    "Error".bright_red().bold(),
+                    kind
+                );
+                std::process::exit(1);
+            };
+            permissions.revoke(&permission).map(|revoked| {
+                if revoked {
+                    println!("{} Revoked {}", "OK".bright_green(), permission);
+                } else {
+                    println!(
+                        "{}",
+                        format!("No saved decision for {}", permission).dimmed()
+                    );
+                }
+            })
+        }
+        PermissionsAction::Clear => permissions.clear_cache().map(|()| {
+            println!("{} Cleared every saved permission", "OK".bright_green());
+        }),
+    };
+
+    if let Err(e) = result {
+        println!(
+            "{}: Failed to write the permission cache: {}",
+            "Error".bright_red().bold(),
+            e
+        );
+        std::process::exit(1);
+    }
+}
+
+fn run_login(token: Option<&str>) {
+    use std::fs;
+    use std::io::{self, Write};
 
     let token = match token {
         Some(t) => t.to_string(),
@@ -1723,9 +3288,9 @@ fn run_login(token: Option<&str>) {
         std::process::exit(1);
     }
 
-    let home_dir = match std::env::var("HOME") {
-        Ok(h) => PathBuf::from(h),
-        Err(_) => {
+    let token_file = match registry_client::token_path() {
+        Some(path) => path,
+        None => {
             println!(
                 "{}: Could not find home directory",
                 "Error".bright_red().bold()
@@ -1734,12 +3299,10 @@ fn run_login(token: Option<&str>) {
         }
     };
 
-    let config_dir = home_dir.join(".loft");
-    if !config_dir.exists() {
-        fs::create_dir_all(&config_dir).unwrap();
+    if let Some(config_dir) = token_file.parent() {
+        fs::create_dir_all(config_dir).unwrap();
     }
 
-    let token_file = config_dir.join("token");
     match fs::write(&token_file, token) {
         Ok(_) => {
             println!(
@@ -1763,33 +3326,22 @@ fn run_login(token: Option<&str>) {
     }
 }
 
-fn run_publish() {
+fn run_publish(dry_run: bool, verify: bool) {
     use base64::{engine::general_purpose, Engine as _};
-    use flate2::write::GzEncoder;
-    use flate2::Compression;
     use loft::manifest::Manifest;
-    use std::fs;
-    use std::path::PathBuf;
-    use tar::Builder;
+    use loft::package::{build_tarball, format_size, package_files, IgnoreList};
 
     // 1. Load manifest
     let manifest = match Manifest::find_and_load(".") {
         Ok(m) => m,
-        Err(_) => {
-            println!(
-                "{}: No manifest.json found in current or parent directories",
-                "Error".bright_red().bold()
-            );
-            std::process::exit(1);
-        }
+        Err(e) => exit_with_manifest_error(e),
     };
 
     // 2. Load token
-    let home_dir = std::env::var("HOME").expect("Could not find home directory");
-    let token_file = PathBuf::from(home_dir).join(".loft").join("token");
-    let token = match fs::read_to_string(&token_file) {
-        Ok(t) => t.trim().to_string(),
-        Err(_) => {
+    let token = match registry_client::load_token() {
+        Some(t) => t,
+        None if dry_run => String::new(),
+        None => {
             println!(
                 "{}: Not logged in. Run 'loft login' first.",
                 "Error".bright_red().bold()
@@ -1799,292 +3351,1022 @@ fn run_publish() {
     };
 
     println!(
-        "pkg Publishing {}@{}...",
+        "pkg {} {}@{}...",
+        if dry_run { "Packaging" } else { "Publishing" },
         manifest.name.truecolor(ACID.0, ACID.1, ACID.2),
         manifest
             .version
             .truecolor(LUMINOUS.0, LUMINOUS.1, LUMINOUS.2)
     );
-    println!("Building  [█████████████████████████] 100% | Done");
 
-    // 3. Create tarball
-    let mut tar_data = Vec::new();
-    {
-        let enc = GzEncoder::new(&mut tar_data, Compression::default());
-        let mut tar = Builder::new(enc);
-
-        // Add all files in current directory (excluding .lflibs and target)
-        let current_dir = std::env::current_dir().unwrap();
-        for entry in fs::read_dir(current_dir).unwrap().flatten() {
-            let path = entry.path();
-            let name = path.file_name().unwrap().to_str().unwrap();
-            if name == ".lflibs" || name == "target" || name == ".git" {
-                continue;
-            }
-            if path.is_dir() {
-                tar.append_dir_all(name, &path).unwrap();
-            } else {
-                tar.append_path_with_name(&path, name).unwrap();
+    // 3. Create tarball from every file not ignored
+    let current_dir = std::env::current_dir().unwrap();
+    let files = IgnoreList::for_project(&current_dir, &manifest.exclude)
+        .and_then(|ignore| package_files(&current_dir, &ignore));
+    let (files, tar_data) = match files.and_then(|files| {
+        let tarball = build_tarball(&current_dir, &files)?;
+        Ok((files, tarball))
+    }) {
+        Ok((files, tarball)) => {
+            let width = files.iter().map(|f| f.path.len()).max().unwrap_or(0);
+            for file in &files {
+                println!(
+                    "  {:width$}  {}",
+                    file.path,
+                    format_size(file.size).dimmed(),
+                    width = width
+                );
             }
+            println!(
+                "Packaged {} file(s), {} compressed",
+                files.len(),
+                format_size(tarball.len() as u64)
+            );
+            (files, tarball)
         }
-        tar.finish().unwrap();
-    }
-
-    let tarball_b64 = general_purpose::STANDARD.encode(tar_data);
-
-    // 4. Send to registry
-    let registry_url = normalize_registry_url(
-        std::env::var("LOFT_REGISTRY").unwrap_or_else(|_| "https://loft.fargone.sh".to_string()),
-    );
-    let client = reqwest::blocking::Client::new();
-
-    #[derive(serde::Serialize)]
-    struct PublishRequest {
-        name: String,
-        version: String,
-        description: Option<String>,
-        manifest: serde_json::Value,
-        tarball: String,
-    }
-
-    let payload = PublishRequest {
-        name: manifest.name.clone(),
-        version: manifest.version.clone(),
-        description: None, // Could be extracted from manifest if added there
-        manifest: serde_json::to_value(&manifest).unwrap(),
-        tarball: tarball_b64,
-    };
-
-    let response = client
-        .post(format!("{}/packages/publish", registry_url))
-        .header("Authorization", format!("Bearer {}", token))
-        .json(&payload)
-        .send();
-
-    match response {
-        Ok(res) if res.status().is_success() => {
+        Err(e) => {
             println!(
-                "{} {}",
-                "DONE".bright_green(),
-                "Successfully published!".bright_green().bold()
+                "{}: Failed to build the package: {}",
+                "Error".bright_red().bold(),
+                e
             );
+            std::process::exit(1);
         }
-        Ok(res) if res.status() == 409 => {
+    };
+
+    if verify {
+        let errors = verify_package(&current_dir, &manifest, &files);
+        if errors > 0 {
             println!(
-                "{}: {}@{} already exists in the registry. Bump the version and try again.",
+                "{}: Verification failed with {} error(s). Fix them or publish with --no-verify.",
                 "Error".bright_red().bold(),
-                manifest.name.bright_white(),
-                manifest.version.bright_white()
+                errors
             );
             std::process::exit(1);
         }
-        Ok(res) if res.status() == 403 => {
-            println!(
-                "{}: You are not an owner of '{}'.",
-                "Error".bright_red().bold(),
-                manifest.name.bright_white()
-            );
-            std::process::exit(1);
+    }
+
+    let client = RegistryClient::from_env().with_token(token);
+    if dry_run {
+        // Check the version is free without uploading anything
+        let taken = match client.get_package(&manifest.name) {
+            Ok(versions) => versions.iter().any(|v| v.version == manifest.version),
+            Err(RegistryError::NotFound) => false,
+            Err(e) => {
+                println!("{}: {}", "Error".bright_red().bold(), e);
+                std::process::exit(1);
+            }
+        };
+        if taken {
+            println!(
+                "{}: {}@{} already exists in the registry. Bump the version and try again.",
+                "Error".bright_red().bold(),
+                manifest.name.bright_white(),
+                manifest.version.bright_white()
+            );
+            std::process::exit(1);
+        }
+        println!(
+            "{} {}",
+            "DONE".bright_green(),
+            "Dry run passed, nothing was uploaded".bright_green().bold()
+        );
+        return;
+    }
+
+    let tarball_b64 = general_purpose::STANDARD.encode(tar_data);
+
+    // 4. Send to registry
+    let payload = PublishRequest {
+        name: manifest.name.clone(),
+        version: manifest.version.clone(),
+        description: manifest.description.clone(),
+        repository: manifest.repository.clone(),
+        authors: manifest.authors.clone(),
+        license: manifest.license.clone(),
+        manifest: serde_json::to_value(&manifest).unwrap(),
+        tarball: tarball_b64,
+    };
+
+    match client.publish(&payload) {
+        Ok(()) => {
+            println!(
+                "{} {}",
+                "DONE".bright_green(),
+                "Successfully published!".bright_green().bold()
+            );
+        }
+        Err(RegistryError::Conflict) => {
+            println!(
+                "{}: {}@{} already exists in the registry. Bump the version and try again.",
+                "Error".bright_red().bold(),
+                manifest.name.bright_white(),
+                manifest.version.bright_white()
+            );
+            std::process::exit(1);
+        }
+        Err(RegistryError::Forbidden) => {
+            println!(
+                "{}: You are not an owner of '{}'.",
+                "Error".bright_red().bold(),
+                manifest.name.bright_white()
+            );
+            std::process::exit(1);
+        }
+        Err(e) => {
+            println!("{}: {}", "Error".bright_red().bold(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Check the package the way `loft check` would before it's published: every
+/// packaged `.lf` file must be free of errors, and the entrypoint and every
+/// module it imports must be in the package. Returns the number of errors.
+fn verify_package(
+    root: &std::path::Path,
+    manifest: &loft::manifest::Manifest,
+    files: &[loft::package::PackageFile],
+) -> usize {
+    use loft::bundle::Bundle;
+
+    println!(
+        "Verifying {}...",
+        manifest.name.truecolor(ACID.0, ACID.1, ACID.2)
+    );
+
+    let sources: Vec<std::path::PathBuf> = files
+        .iter()
+        .filter(|f| f.path.ends_with(".lf"))
+        .map(|f| std::path::PathBuf::from(&f.path))
+        .collect();
+    let mode = if manifest.strict {
+        CheckMode::Strict
+    } else {
+        CheckMode::Default
+    };
+    let (mut errors, _) = check_files(&sources, mode, &manifest.lints);
+
+    let entrypoint = root.join(&manifest.entrypoint);
+    if !entrypoint.is_file() {
+        println!(
+            "{}: Entrypoint '{}' does not exist",
+            "Error".bright_red().bold(),
+            manifest.entrypoint
+        );
+        return errors + 1;
+    }
+
+    // Dependencies live in .lflibs, or outside the project for linked local
+    // packages, and are installed by whoever uses the package
+    match Bundle::build(root, &entrypoint) {
+        Ok(bundle) => {
+            for module in &bundle.modules {
+                let packaged = files.iter().any(|f| f.path == module.path);
+                let dependency =
+                    module.path.starts_with(".lflibs/") || module.path.starts_with('/');
+                if !packaged && !dependency {
+                    println!(
+                        "{}: '{}' is imported by the package but excluded from it",
+                        "Error".bright_red().bold(),
+                        module.path
+                    );
+                    errors += 1;
+                }
+            }
+        }
+        Err(e) => {
+            println!("{}: {}", "Error".bright_red().bold(), e);
+            errors += 1;
+        }
+    }
+
+    errors
+}
+
+fn run_yank(version: &str, undo: bool) {
+    use loft::manifest::Manifest;
+
+    let manifest = match Manifest::find_and_load(".") {
+        Ok(m) => m,
+        Err(e) => exit_with_manifest_error(e),
+    };
+
+    let token = match registry_client::load_token() {
+        Some(t) => t,
+        None => {
+            println!(
+                "{}: Not logged in. Run 'loft login' first.",
+                "Error".bright_red().bold()
+            );
+            std::process::exit(1);
+        }
+    };
+
+    let client = RegistryClient::from_env().with_token(token);
+    match client.set_yanked(&manifest.name, version, !undo) {
+        Ok(()) => {
+            let action = if undo { "Un-yanked" } else { "Yanked" };
+            println!(
+                "{} {} {}@{}",
+                "DONE".bright_green(),
+                action.bright_green().bold(),
+                manifest.name.bright_white(),
+                version.bright_white()
+            );
+        }
+        Err(RegistryError::NotFound) => {
+            println!(
+                "{}: {}@{} is not in the registry",
+                "Error".bright_red().bold(),
+                manifest.name.bright_white(),
+                version.bright_white()
+            );
+            std::process::exit(1);
+        }
+        Err(RegistryError::Forbidden) => {
+            println!(
+                "{}: You are not an owner of '{}'.",
+                "Error".bright_red().bold(),
+                manifest.name.bright_white()
+            );
+            std::process::exit(1);
+        }
+        Err(e) => {
+            println!("{}: {}", "Error".bright_red().bold(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Format every .lf file under `path`, or with `diff` print what would change
+fn run_format(path: Option<&str>, check: bool, diff: bool) {
+    use loft::formatter::diff::unified_diff;
+    use loft::formatter::{FormatConfig, TokenFormatter};
+    use std::fs;
+    use std::path::Path;
+
+    let target_path = path.unwrap_or(".");
+
+    // Collect all .lf files to format
+    let mut files_to_format = Vec::new();
+    let path_obj = Path::new(target_path);
+
+    if path_obj.is_file() {
+        if path_obj.extension().and_then(|s| s.to_str()) == Some("lf") {
+            files_to_format.push(path_obj.to_path_buf());
+        } else {
+            println!(
+                "{}: '{}' is not a .lf file",
+                "Error".bright_red().bold(),
+                target_path
+            );
+            std::process::exit(1);
+        }
+    } else if path_obj.is_dir() {
+        // Find all .lf files in the directory (non-recursive for simplicity)
+        match fs::read_dir(path_obj) {
+            Ok(entries) => {
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("lf") {
+                        files_to_format.push(path);
+                    }
+                }
+            }
+            Err(e) => {
+                println!(
+                    "{}: Failed to read directory: {}",
+                    "Error".bright_red().bold(),
+                    e
+                );
+                std::process::exit(1);
+            }
+        }
+
+        // Also check src/ directory if it exists
+        let src_dir = path_obj.join("src");
+        if src_dir.exists() && src_dir.is_dir() {
+            if let Ok(entries) = fs::read_dir(&src_dir) {
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("lf") {
+                        files_to_format.push(path);
+                    }
+                }
+            }
+        }
+    } else {
+        println!(
+            "{}: Path '{}' does not exist",
+            "Error".bright_red().bold(),
+            target_path
+        );
+        std::process::exit(1);
+    }
+
+    if files_to_format.is_empty() {
+        println!(
+            "{}: No .lf files found to format",
+            "Warning".bright_yellow().bold()
+        );
+        return;
+    }
+
+    let mut formatted_count = 0;
+    let mut unchanged_count = 0;
+    let mut error_count = 0;
+
+    for file_path in &files_to_format {
+        let display_path = file_path.display().to_string();
+
+        // Read the file
+        let original_content = match fs::read_to_string(file_path) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!(
+                    "{}: Failed to read '{}': {}",
+                    "Error".bright_red().bold(),
+                    display_path,
+                    e
+                );
+                error_count += 1;
+                continue;
+            }
+        };
+
+        // Use the nearest .loftfmt.json or manifest `format` section
+        let config = match FormatConfig::find(file_path.parent().unwrap_or(Path::new("."))) {
+            Ok(config) => config.unwrap_or_default(),
+            Err(e) => {
+                eprintln!(
+                    "{}: Invalid formatter configuration: {}",
+                    "Error".bright_red().bold(),
+                    e
+                );
+                error_count += 1;
+                continue;
+            }
+        };
+        let formatter = TokenFormatter::with_config(config);
+
+        // Format using token-based formatter (preserves comments and handles errors)
+        let formatted_content = match formatter.format(&original_content) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!(
+                    "{}: Failed to format '{}': {}",
+                    "Error".bright_red().bold(),
+                    display_path,
+                    e
+                );
+                error_count += 1;
+                continue;
+            }
+        };
+
+        // Compare and write if changed
+        if formatted_content.trim() == original_content.trim() {
+            unchanged_count += 1;
+            if !check && !diff {
+                println!("  {} {}", "v".dimmed(), display_path.dimmed());
+            }
+        } else if diff {
+            print!(
+                "{}",
+                unified_diff(&original_content, &formatted_content, &display_path)
+            );
+            formatted_count += 1;
+        } else if check {
+            println!("  {} {}", "!".bright_red(), display_path.bright_white());
+            formatted_count += 1;
+        } else {
+            match fs::write(file_path, formatted_content) {
+                Ok(_) => {
+                    println!("  {} {}", "v".bright_green(), display_path.bright_white());
+                    formatted_count += 1;
+                }
+                Err(e) => {
+                    eprintln!(
+                        "{}: Failed to write '{}': {}",
+                        "Error".bright_red().bold(),
+                        display_path,
+                        e
+                    );
+                    error_count += 1;
+                }
+            }
+        }
+    }
+
+    // Only the diff goes to stdout, so it can be piped into other tools
+    if diff {
+        if formatted_count > 0 || error_count > 0 {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    println!();
+
+    if check {
+        if formatted_count > 0 {
+            println!(
+                "{}: {} file(s) need formatting",
+                "Check failed".bright_red().bold(),
+                formatted_count
+            );
+            std::process::exit(1);
+        } else {
+            println!(
+                "{}: All files are properly formatted",
+                "Check passed".bright_green().bold()
+            );
+        }
+    } else {
+        println!(
+            "{} {}",
+            "*".bright_cyan(),
+            "Formatting complete!".bright_cyan().bold()
+        );
+        if formatted_count > 0 {
+            println!(
+                "  {} {} file(s) formatted",
+                "v".bright_green(),
+                formatted_count
+            );
+        }
+        if unchanged_count > 0 {
+            println!(
+                "  {} {} file(s) already formatted",
+                "•".dimmed(),
+                unchanged_count
+            );
+        }
+        if error_count > 0 {
+            println!("  {} {} file(s) had errors", "!".bright_red(), error_count);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Format source read from stdin, writing the result (or a diff) to stdout
+fn run_format_stdin(diff: bool) {
+    use loft::formatter::diff::unified_diff;
+    use loft::formatter::{FormatConfig, TokenFormatter};
+    use std::io::Read;
+
+    let mut source = String::new();
+    if let Err(e) = std::io::stdin().read_to_string(&mut source) {
+        eprintln!(
+            "{}: Failed to read stdin: {}",
+            "Error".bright_red().bold(),
+            e
+        );
+        std::process::exit(1);
+    }
+
+    let config = match FormatConfig::find(std::path::Path::new(".")) {
+        Ok(config) => config.unwrap_or_default(),
+        Err(e) => {
+            eprintln!(
+                "{}: Invalid formatter configuration: {}",
+                "Error".bright_red().bold(),
+                e
+            );
+            std::process::exit(1);
+        }
+    };
+    let formatted = match TokenFormatter::with_config(config).format(&source) {
+        Ok(formatted) => formatted,
+        Err(e) => {
+            eprintln!(
+                "{}: Failed to format stdin: {}",
+                "Error".bright_red().bold(),
+                e
+            );
+            std::process::exit(1);
+        }
+    };
+
+    if diff {
+        let changes = unified_diff(&source, &formatted, "<stdin>");
+        print!("{}", changes);
+        if !changes.is_empty() {
+            std::process::exit(1);
+        }
+    } else {
+        print!("{}", formatted);
+    }
+}
+
+fn run_test(path: Option<&str>, filter: Option<&str>, doc: bool, features: Vec<String>) {
+    use loft::analysis::discover_source_files;
+    use loft::runtime::test_harness::{
+        discover_test_files, run_doc_test_file, run_test_file, TestOutcome,
+    };
+    use std::path::Path;
+
+    let target_path = Path::new(path.unwrap_or("."));
+    if !target_path.exists() {
+        println!(
+            "{}: Path '{}' does not exist",
+            "Error".bright_red().bold(),
+            target_path.display()
+        );
+        std::process::exit(1);
+    }
+
+    let files = if doc {
+        discover_source_files(target_path)
+    } else {
+        discover_test_files(target_path)
+    };
+    if files.is_empty() {
+        println!(
+            "{}: No {} files found in '{}'",
+            "Warning".bright_yellow().bold(),
+            if doc { ".lf" } else { "*_test.lf" },
+            target_path.display()
+        );
+        return;
+    }
+
+    println!(
+        "{} {} {}",
+        ">".truecolor(LUMINOUS.0, LUMINOUS.1, LUMINOUS.2),
+        "Running"
+            .truecolor(LUMINOUS.0, LUMINOUS.1, LUMINOUS.2)
+            .bold(),
+        if doc {
+            format!("doc tests in {} file(s)", files.len())
+        } else {
+            format!("{} test file(s)", files.len())
+        }
+    );
+    println!();
+
+    let start = std::time::Instant::now();
+    let mut passed = 0;
+    let mut failures = Vec::new();
+
+    for file in &files {
+        let report = if doc {
+            run_doc_test_file(file, &features, filter)
+        } else {
+            run_test_file(file, &features, filter)
+        };
+        if report.cases.is_empty() {
+            continue;
+        }
+
+        println!("{}", report.path.truecolor(ACID.0, ACID.1, ACID.2).bold());
+        for case in report.cases {
+            let millis = format!("({}ms)", case.duration.as_millis());
+            match case.outcome {
+                TestOutcome::Passed => {
+                    println!(
+                        "  {} {} {}",
+                        "ok".bright_green(),
+                        case.name,
+                        millis.dimmed()
+                    );
+                    passed += 1;
+                }
+                TestOutcome::Failed(err) => {
+                    println!(
+                        "  {} {} {}",
+                        "FAIL".bright_red().bold(),
+                        case.name,
+                        millis.dimmed()
+                    );
+                    failures.push((report.path.clone(), case.name, err));
+                }
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        println!();
+        println!("{}", "Failures:".bright_red().bold());
+        for (file, name, err) in &failures {
+            println!();
+            println!(
+                "{} {}",
+                "---".bright_red(),
+                format!("{} :: {}", file, name).bold()
+            );
+            print_error(err);
+        }
+    }
+
+    println!();
+    let summary = format!(
+        "{} passed, {} failed in {:.2}s",
+        passed,
+        failures.len(),
+        start.elapsed().as_secs_f64()
+    );
+    if failures.is_empty() {
+        println!("{} {}", "Test result:".bright_green().bold(), summary);
+    } else {
+        println!("{} {}", "Test result:".bright_red().bold(), summary);
+        std::process::exit(1);
+    }
+}
+
+fn run_bench(
+    path: Option<&str>,
+    filter: Option<&str>,
+    options: loft::runtime::bench_harness::BenchOptions,
+    save: Option<&str>,
+    compare: Option<&str>,
+    features: Vec<String>,
+) {
+    use loft::runtime::bench_harness::{
+        discover_bench_files, format_duration, run_bench_file, BenchOutcome, BenchRecord,
+    };
+    use std::path::Path;
+
+    let target_path = Path::new(path.unwrap_or("."));
+    if !target_path.exists() {
+        println!(
+            "{}: Path '{}' does not exist",
+            "Error".bright_red().bold(),
+            target_path.display()
+        );
+        std::process::exit(1);
+    }
+
+    let baseline: Vec<BenchRecord> = match compare {
+        Some(file) => match std::fs::read_to_string(file)
+            .map_err(|e| e.to_string())
+            .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+        {
+            Ok(baseline) => baseline,
+            Err(e) => {
+                println!(
+                    "{}: Failed to read saved results from {}: {}",
+                    "Error".bright_red().bold(),
+                    file,
+                    e
+                );
+                std::process::exit(1);
+            }
+        },
+        None => Vec::new(),
+    };
+
+    let files = discover_bench_files(target_path);
+    if files.is_empty() {
+        println!(
+            "{}: No *_bench.lf files found in '{}'",
+            "Warning".bright_yellow().bold(),
+            target_path.display()
+        );
+        return;
+    }
+
+    println!(
+        "{} {} {} bench file(s), {} warmup and {} measured run(s) each",
+        ">".truecolor(LUMINOUS.0, LUMINOUS.1, LUMINOUS.2),
+        "Running"
+            .truecolor(LUMINOUS.0, LUMINOUS.1, LUMINOUS.2)
+            .bold(),
+        files.len(),
+        options.warmup,
+        options.iterations.max(1)
+    );
+    println!();
+
+    let mut records = Vec::new();
+    let mut failures = Vec::new();
+
+    for file in &files {
+        let report = run_bench_file(file, &features, filter, options);
+        if report.cases.is_empty() {
+            continue;
+        }
+
+        println!("{}", report.path.truecolor(ACID.0, ACID.1, ACID.2).bold());
+        for case in report.cases {
+            match case.outcome {
+                BenchOutcome::Measured(stats) => {
+                    let record = BenchRecord::new(&report.path, &case.name, &stats);
+                    // Slower is red and faster is green, unless the change is within noise
+                    let change = match record.compare(&baseline) {
+                        Some(change) => {
+                            let percent = format!("{:+.1}%", change.percent);
+                            if !change.significant {
+                                format!(" {}", format!("{}, within noise", percent).dimmed())
+                            } else if change.percent > 0.0 {
+                                format!(" {}", percent.bright_red().bold())
+                            } else {
+                                format!(" {}", percent.bright_green().bold())
+                            }
+                        }
+                        None => String::new(),
+                    };
+                    println!(
+                        "  {} {} {} {}{}",
+                        "bench".bright_cyan(),
+                        case.name,
+                        format!(
+                            "{} ± {}",
+                            format_duration(stats.mean),
+                            format_duration(stats.stddev)
+                        )
+                        .bold(),
+                        format!(
+                            "(median {}, min {}, max {})",
+                            format_duration(stats.median),
+                            format_duration(stats.min),
+                            format_duration(stats.max)
+                        )
+                        .dimmed(),
+                        change
+                    );
+                    records.push(record);
+                }
+                BenchOutcome::Failed(err) => {
+                    println!("  {} {}", "FAIL".bright_red().bold(), case.name);
+                    failures.push((report.path.clone(), case.name, err));
+                }
+            }
         }
-        Ok(res) => {
-            let status = res.status();
-            let body = res.text().unwrap_or_default();
+    }
+
+    if !failures.is_empty() {
+        println!();
+        println!("{}", "Failures:".bright_red().bold());
+        for (file, name, err) in &failures {
+            println!();
             println!(
-                "{}: Registry returned {} - {}",
-                "Error".bright_red().bold(),
-                status,
-                body
+                "{} {}",
+                "---".bright_red(),
+                format!("{} :: {}", file, name).bold()
             );
-            std::process::exit(1);
+            print_error(err);
         }
-        Err(e) => {
+    }
+
+    if let Some(file) = save {
+        let json = serde_json::to_string_pretty(&records).expect("bench results serialize");
+        if let Err(e) = std::fs::write(file, json + "\n") {
             println!(
-                "{}: Failed to connect to registry: {}",
+                "{}: Failed to save results to {}: {}",
                 "Error".bright_red().bold(),
+                file,
                 e
             );
             std::process::exit(1);
         }
+        println!();
+        println!(
+            "{} {}: {}",
+            ">".bright_cyan(),
+            "Results saved".bright_cyan().bold(),
+            file.bright_white()
+        );
+    }
+
+    if !failures.is_empty() {
+        std::process::exit(1);
     }
 }
 
-fn run_format(path: Option<&str>, check: bool) {
-    use loft::formatter::TokenFormatter;
-    use std::fs;
+fn run_check(path: Option<&str>, strict: bool) {
+    use loft::analysis;
+    use loft::manifest::Manifest;
     use std::path::Path;
 
-    let target_path = path.unwrap_or(".");
-    let formatter = TokenFormatter::new();
-
-    // Collect all .lf files to format
-    let mut files_to_format = Vec::new();
-    let path_obj = Path::new(target_path);
-
-    if path_obj.is_file() {
-        if path_obj.extension().and_then(|s| s.to_str()) == Some("lf") {
-            files_to_format.push(path_obj.to_path_buf());
-        } else {
-            println!(
-                "{}: '{}' is not a .lf file",
-                "Error".bright_red().bold(),
-                target_path
-            );
-            std::process::exit(1);
-        }
-    } else if path_obj.is_dir() {
-        // Find all .lf files in the directory (non-recursive for simplicity)
-        match fs::read_dir(path_obj) {
-            Ok(entries) => {
-                for entry in entries.flatten() {
-                    let path = entry.path();
-                    if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("lf") {
-                        files_to_format.push(path);
-                    }
-                }
-            }
-            Err(e) => {
-                println!(
-                    "{}: Failed to read directory: {}",
-                    "Error".bright_red().bold(),
-                    e
-                );
-                std::process::exit(1);
-            }
-        }
-
-        // Also check src/ directory if it exists
-        let src_dir = path_obj.join("src");
-        if src_dir.exists() && src_dir.is_dir() {
-            if let Ok(entries) = fs::read_dir(&src_dir) {
-                for entry in entries.flatten() {
-                    let path = entry.path();
-                    if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("lf") {
-                        files_to_format.push(path);
-                    }
-                }
-            }
-        }
-    } else {
+    let target_path = Path::new(path.unwrap_or("."));
+    if !target_path.exists() {
         println!(
             "{}: Path '{}' does not exist",
             "Error".bright_red().bold(),
-            target_path
+            target_path.display()
         );
         std::process::exit(1);
     }
 
-    if files_to_format.is_empty() {
+    let files = analysis::discover_source_files(target_path);
+    if files.is_empty() {
         println!(
-            "{}: No .lf files found to format",
-            "Warning".bright_yellow().bold()
+            "{}: No .lf files found in '{}'",
+            "Warning".bright_yellow().bold(),
+            target_path.display()
         );
         return;
     }
 
-    let mut formatted_count = 0;
-    let mut unchanged_count = 0;
-    let mut error_count = 0;
+    // A project can opt every check into strict mode from its manifest
+    let manifest_dir = if target_path.is_dir() {
+        target_path
+    } else {
+        target_path.parent().unwrap_or(Path::new("."))
+    };
+    let manifest = Manifest::find_and_load(manifest_dir).ok();
+    let strict = strict || manifest.as_ref().is_some_and(|m| m.strict);
+    let lints = manifest.map(|m| m.lints).unwrap_or_default();
 
-    for file_path in &files_to_format {
-        let display_path = file_path.display().to_string();
+    let mode = if strict {
+        CheckMode::Strict
+    } else {
+        CheckMode::Default
+    };
+    let (errors, warnings) = check_files(&files, mode, &lints);
 
-        // Read the file
-        let original_content = match fs::read_to_string(file_path) {
-            Ok(content) => content,
-            Err(e) => {
-                println!(
-                    "{}: Failed to read '{}': {}",
-                    "Error".bright_red().bold(),
-                    display_path,
-                    e
-                );
-                error_count += 1;
-                continue;
-            }
-        };
+    let summary = format!(
+        "{} file(s) checked, {} error(s), {} warning(s)",
+        files.len(),
+        errors,
+        warnings
+    );
+    if errors == 0 {
+        println!("{} {}", "Check result:".bright_green().bold(), summary);
+    } else {
+        println!("{} {}", "Check result:".bright_red().bold(), summary);
+        std::process::exit(1);
+    }
+}
 
-        // Format using token-based formatter (preserves comments and handles errors)
-        let formatted_content = match formatter.format(&original_content) {
-            Ok(content) => content,
+/// Print the diagnostics for `files`, returning the number of errors and warnings
+fn check_files(
+    files: &[std::path::PathBuf],
+    mode: CheckMode,
+    lints: &loft::lints::LintConfig,
+) -> (usize, usize) {
+    use loft::analysis::{self, project::Project, Severity};
+    use loft::codes;
+
+    let stdlib_types = analysis::stdlib::load();
+    let mut errors = 0;
+    let mut warnings = 0;
+
+    let project = Project::load(files);
+    for (file, e) in &project.unreadable {
+        println!(
+            "{}: Failed to read '{}': {}",
+            "Error".bright_red().bold(),
+            file.display(),
+            e
+        );
+        errors += 1;
+    }
+
+    for (file, diagnostics) in project.check(mode, lints, &stdlib_types) {
+        let display_path = file.path.display().to_string();
+        for diagnostic in diagnostics {
+            let label = match diagnostic.severity {
+                Severity::Error => {
+                    errors += 1;
+                    "error".bright_red().bold().to_string()
+                }
+                Severity::Warning => {
+                    warnings += 1;
+                    "warning".bright_yellow().bold().to_string()
+                }
+                Severity::Hint => "hint".dimmed().to_string(),
+            };
+            let code = diagnostic
+                .code
+                .as_deref()
+                .and_then(codes::by_name)
+                .map(|code| format!("[{}]", code.code).dimmed().to_string())
+                .unwrap_or_default();
+            println!(
+                "{}:{}:{}: {}{}: {}",
+                display_path.truecolor(ACID.0, ACID.1, ACID.2),
+                diagnostic.range.start.line + 1,
+                diagnostic.range.start.character + 1,
+                label,
+                code,
+                diagnostic.message
+            );
+        }
+    }
+
+    (errors, warnings)
+}
+
+fn run_fix(path: Option<&str>, diff: bool) {
+    use loft::analysis::{self, fixes};
+    use loft::formatter::diff::unified_diff;
+    use loft::manifest::Manifest;
+    use std::path::Path;
+
+    let target_path = Path::new(path.unwrap_or("."));
+    if !target_path.exists() {
+        println!(
+            "{}: Path '{}' does not exist",
+            "Error".bright_red().bold(),
+            target_path.display()
+        );
+        std::process::exit(1);
+    }
+
+    let files = analysis::discover_source_files(target_path);
+    let manifest_dir = if target_path.is_dir() {
+        target_path
+    } else {
+        target_path.parent().unwrap_or(Path::new("."))
+    };
+    let lints = Manifest::find_and_load(manifest_dir)
+        .map(|m| m.lints)
+        .unwrap_or_default();
+    let stdlib_types = analysis::stdlib::load();
+
+    let mut fixed_files = 0;
+    let mut fixed_problems = 0;
+    for file in &files {
+        let display_path = file.display().to_string();
+        let source = match std::fs::read_to_string(file) {
+            Ok(source) => source,
             Err(e) => {
-                println!(
-                    "{}: Failed to format '{}': {}",
+                eprintln!(
+                    "{}: Failed to read '{}': {}",
                     "Error".bright_red().bold(),
                     display_path,
                     e
                 );
-                error_count += 1;
                 continue;
             }
         };
-
-        // Compare and write if changed
-        if formatted_content.trim() == original_content.trim() {
-            if !check {
-                println!("  {} {}", "v".dimmed(), display_path.dimmed());
-            }
-            unchanged_count += 1;
-        } else if check {
-            println!("  {} {}", "!".bright_red(), display_path.bright_white());
-            formatted_count += 1;
-        } else {
-            match fs::write(file_path, formatted_content) {
-                Ok(_) => {
-                    println!("  {} {}", "v".bright_green(), display_path.bright_white());
-                    formatted_count += 1;
-                }
-                Err(e) => {
-                    println!(
-                        "{}: Failed to write '{}': {}",
-                        "Error".bright_red().bold(),
-                        display_path,
-                        e
-                    );
-                    error_count += 1;
-                }
-            }
+        let (fixed, applied) = fixes::fix_source(&display_path, &source, &lints, &stdlib_types);
+        if applied == 0 {
+            continue;
         }
-    }
-
-    println!();
-
-    if check {
-        if formatted_count > 0 {
-            println!(
-                "{}: {} file(s) need formatting",
-                "Check failed".bright_red().bold(),
-                formatted_count
+        if diff {
+            print!("{}", unified_diff(&source, &fixed, &display_path));
+        } else if let Err(e) = std::fs::write(file, &fixed) {
+            eprintln!(
+                "{}: Failed to write '{}': {}",
+                "Error".bright_red().bold(),
+                display_path,
+                e
             );
-            std::process::exit(1);
+            continue;
         } else {
             println!(
-                "{}: All files are properly formatted",
-                "Check passed".bright_green().bold()
+                "  {} {} ({} fix(es))",
+                "v".bright_green(),
+                display_path.bright_white(),
+                applied
             );
         }
-    } else {
+        fixed_files += 1;
+        fixed_problems += applied;
+    }
+
+    if !diff {
         println!(
-            "{} {}",
-            "*".bright_cyan(),
-            "Formatting complete!".bright_cyan().bold()
+            "{} {} fix(es) applied in {} file(s), run {} for what's left",
+            "Fix result:".bright_green().bold(),
+            fixed_problems,
+            fixed_files,
+            "loft check".bright_cyan()
         );
-        if formatted_count > 0 {
-            println!(
-                "  {} {} file(s) formatted",
-                "v".bright_green(),
-                formatted_count
-            );
-        }
-        if unchanged_count > 0 {
-            println!(
-                "  {} {} file(s) already formatted",
-                "•".dimmed(),
-                unchanged_count
-            );
+    }
+}
+
+fn run_explain(code: Option<&str>) {
+    use loft::codes::{self, CODES};
+
+    let Some(code) = code else {
+        for code in CODES {
+            println!("  {}  {}", code.code.bright_cyan(), code.title);
         }
-        if error_count > 0 {
-            println!("  {} {} file(s) had errors", "!".bright_red(), error_count);
-            std::process::exit(1);
+        println!(
+            "\nUse {} to read about one of them.",
+            "loft explain <code>".bright_cyan()
+        );
+        return;
+    };
+    let Some(code) = codes::find(code) else {
+        eprintln!(
+            "{}: '{}' is not a diagnostic code, run {} to list them",
+            "Error".bright_red().bold(),
+            code,
+            "loft explain".bright_cyan()
+        );
+        std::process::exit(1);
+    };
+
+    println!(
+        "{} {}\n",
+        format!("{}:", code.code).bright_cyan().bold(),
+        code.title.bold()
+    );
+    // Examples are printed indented, without their fences
+    let mut in_example = false;
+    for line in code.explanation.lines() {
+        if line.starts_with("```") {
+            in_example = !in_example;
+        } else if in_example {
+            println!("    {}", line.truecolor(ACID.0, ACID.1, ACID.2));
+        } else {
+            println!("{}", line);
         }
     }
 }
@@ -2098,7 +4380,7 @@ fn run_docs(topic: Option<String>) {
     use std::path::Path;
 
     // Load stdlib_types.json
-    let stdlib_json = include_str!("lsp/stdlib_types.json");
+    let stdlib_json = loft::analysis::stdlib::JSON;
     let stdlib: StdlibTypes = match serde_json::from_str(stdlib_json) {
         Ok(s) => s,
         Err(e) => {
@@ -2211,3 +4493,93 @@ fn run_docs(topic: Option<String>) {
         topic
     );
 }
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    /// A REPL session as it starts, with the names bound before any input
+    fn repl() -> (Interpreter, Vec<String>, HashSet<String>) {
+        let interpreter = Interpreter::new();
+        let builtins = interpreter.env.capture_all().into_keys().collect();
+        (interpreter, Vec::new(), builtins)
+    }
+
+    fn type_of(code: &str, interpreter: &Interpreter, session: &[String]) -> String {
+        let code = format!("{};", code);
+        let stmts = Parser::new(InputStream::new("repl", &code))
+            .parse()
+            .unwrap();
+        match &stmts[0].kind {
+            StmtKind::Expr(expr) => infer_repl_type(expr, interpreter, session),
+            other => panic!("expected an expression, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_repl_load_and_save() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("script.lf");
+        let source = "let width = 3;\nfn area(side: num) -> num {\n    return side * side;\n}";
+        std::fs::write(&script, format!("{}\n", source)).unwrap();
+        let (mut interpreter, mut session, builtins) = repl();
+
+        run_repl_command(
+            &format!("load {}", script.display()),
+            &mut interpreter,
+            &mut session,
+            &builtins,
+        );
+        assert_eq!(session, vec![source.to_string()]);
+        assert!(interpreter.env.get("width").is_some());
+
+        // A file that can't be read leaves the session as it was
+        let missing = dir.path().join("missing.lf");
+        run_repl_command(
+            &format!("load {}", missing.display()),
+            &mut interpreter,
+            &mut session,
+            &builtins,
+        );
+        assert_eq!(session.len(), 1);
+
+        session.push("let height = 4;".to_string());
+        let saved = dir.path().join("saved.lf");
+        run_repl_command(
+            &format!("save {}", saved.display()),
+            &mut interpreter,
+            &mut session,
+            &builtins,
+        );
+        assert_eq!(
+            std::fs::read_to_string(saved).unwrap(),
+            format!("{}\nlet height = 4;\n", source)
+        );
+    }
+
+    #[test]
+    fn test_repl_env_and_type() {
+        let (mut interpreter, mut session, builtins) = repl();
+        assert!(repl_bindings(&interpreter, &builtins).is_empty());
+
+        let source = "let width = 3;\nlet name = \"box\";\nfn area(side: num) -> num { return side * side; }"
+            .to_string();
+        let stmts = Parser::new(InputStream::new("repl", &source))
+            .parse()
+            .unwrap();
+        interpreter.eval_program(stmts).unwrap();
+        session.push(source);
+
+        let names: Vec<String> = repl_bindings(&interpreter, &builtins)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, vec!["area", "name", "width"]);
+
+        assert_eq!(type_of("width", &interpreter, &session), "num");
+        assert_eq!(type_of("name", &interpreter, &session), "str");
+        assert_eq!(type_of("area(2)", &interpreter, &session), "num");
+        assert_eq!(type_of("missing", &interpreter, &session), "unknown");
+    }
+}
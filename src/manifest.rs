@@ -1,18 +1,87 @@
+use crate::analysis::fixes::edit_distance;
+use crate::lints::LintConfig;
+use miette::{Diagnostic, LabeledSpan, NamedSource};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::collections::HashMap;
+use std::fmt::Display;
 
 #[cfg(not(target_arch = "wasm32"))]
-use std::{fs, path::Path};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Manifest {
     pub name: String,
     pub version: String,
     pub entrypoint: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// SPDX license expression, e.g. `MIT OR Apache-2.0`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub authors: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repository: Option<String>,
+    /// Words `loft search` matches the package by
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+    /// Files `loft publish` leaves out, in addition to those in `.loftignore`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
     #[serde(default)]
     pub dependencies: HashMap<String, String>,
+    /// Type check the whole project with the strict checker before running it
+    #[serde(default)]
+    pub strict: bool,
+    /// Makes this the root of a workspace of several projects
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<Workspace>,
+    /// How each lint reports what it finds, by rule name
+    #[serde(default, skip_serializing_if = "LintConfig::is_empty")]
+    pub lints: LintConfig,
 }
 
+/// The `workspace` section of the manifest.json at the root of a monorepo
+///
+/// ```json
+/// { "workspace": { "members": ["app", "packages/*"] } }
+/// ```
+///
+/// The root manifest needs no other fields. Members import each other by
+/// package name without publishing.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Workspace {
+    /// Member project directories relative to the root. `dir/*` takes
+    /// every project directly inside `dir`.
+    pub members: Vec<String>,
+    /// Directory of the root manifest.json
+    #[serde(skip)]
+    #[cfg(not(target_arch = "wasm32"))]
+    pub root: PathBuf,
+}
+
+/// Keys a manifest.json may have
+const KNOWN_KEYS: &[&str] = &[
+    "name",
+    "version",
+    "entrypoint",
+    "description",
+    "license",
+    "authors",
+    "repository",
+    "keywords",
+    "exclude",
+    "dependencies",
+    "strict",
+    "workspace",
+    "format",
+    "lints",
+];
+
 impl Manifest {
     /// Load a manifest from a file path
     #[cfg(not(target_arch = "wasm32"))]
@@ -20,10 +89,49 @@ impl Manifest {
         let content =
             fs::read_to_string(path.as_ref()).map_err(|e| ManifestError::IoError(e.to_string()))?;
 
-        let manifest: Manifest =
-            serde_json::from_str(&content).map_err(|e| ManifestError::ParseError(e.to_string()))?;
+        Self::parse(&path.as_ref().display().to_string(), &content)
+    }
+
+    /// Parse the contents of the manifest.json at `path`, checking it against
+    /// the manifest schema. Problems point at the offending part of the file.
+    pub fn parse(path: &str, source: &str) -> Result<Self, ManifestError> {
+        let value: Value = serde_json::from_str(source).map_err(|e| {
+            let offset = source
+                .split_inclusive('\n')
+                .take(e.line().saturating_sub(1))
+                .map(str::len)
+                .sum::<usize>()
+                + e.column().saturating_sub(1);
+            let message = e.to_string();
+            let message = message.split(" at line ").next().unwrap_or(&message);
+            InvalidManifest::error(
+                path,
+                source,
+                (offset.min(source.len()), 0),
+                "",
+                message,
+                None,
+            )
+        })?;
 
-        Ok(manifest)
+        if let Err(problem) = validate(&value) {
+            let span = if problem.path.is_empty() {
+                let start = source.len() - source.trim_start().len();
+                Some((start, 1))
+            } else {
+                json_span(source, &problem.path, problem.at_key)
+            };
+            return Err(InvalidManifest::error(
+                path,
+                source,
+                span.unwrap_or((0, 0)),
+                &problem.path.join("."),
+                &problem.message,
+                problem.help,
+            ));
+        }
+
+        serde_json::from_value(value).map_err(|e| ManifestError::ParseError(e.to_string()))
     }
 
     /// Find and load manifest.json in the current directory or parent directories
@@ -60,6 +168,21 @@ impl Manifest {
         // Check .lflibs folder first (installed dependencies)
         if let Ok(current_dir) = std::env::current_dir() {
             let lflibs_path = current_dir.join(".lflibs");
+
+            // Prefer the exact version pinned in loft.lock
+            let locked = crate::lockfile::Lockfile::load_or_default(&current_dir)
+                .ok()
+                .and_then(|lockfile| lockfile.get(project_name).cloned());
+            if let Some(locked) = locked {
+                let path = lflibs_path.join(format!("{}@{}", locked.name, locked.version));
+                if let Ok(dep_manifest) = Self::load(path.join("manifest.json")) {
+                    return Ok(path
+                        .join(&dep_manifest.entrypoint)
+                        .to_string_lossy()
+                        .to_string());
+                }
+            }
+
             if lflibs_path.exists() {
                 // Look for versioned directory (e.g., package-name@1.0.0)
                 if let Ok(entries) = fs::read_dir(&lflibs_path) {
@@ -87,6 +210,16 @@ impl Manifest {
             }
         }
 
+        // Projects of the same workspace import each other from source
+        if let Ok(Some(workspace)) = std::env::current_dir()
+            .map_err(|e| ManifestError::IoError(e.to_string()))
+            .and_then(|dir| Workspace::find(&dir))
+        {
+            if let Some((dir, member)) = workspace.member(project_name) {
+                return Ok(dir.join(&member.entrypoint).to_string_lossy().to_string());
+            }
+        }
+
         // Check dependencies
         if let Some(dep_path) = self.dependencies.get(project_name) {
             // In the future, this would resolve through a package manager
@@ -98,6 +231,449 @@ impl Manifest {
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+impl Workspace {
+    /// The workspace `dir` belongs to: the nearest manifest.json in `dir` or
+    /// its parents with a `workspace` section
+    pub fn find(dir: &Path) -> Result<Option<Self>, ManifestError> {
+        let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+        for dir in dir.ancestors() {
+            let manifest_path = dir.join("manifest.json");
+            if !manifest_path.is_file() {
+                continue;
+            }
+            let content = fs::read_to_string(&manifest_path)
+                .map_err(|e| ManifestError::IoError(e.to_string()))?;
+            let manifest: serde_json::Value = serde_json::from_str(&content)
+                .map_err(|e| ManifestError::ParseError(e.to_string()))?;
+            if let Some(section) = manifest.get("workspace") {
+                let mut workspace: Workspace = serde_json::from_value(section.clone())
+                    .map_err(|e| ManifestError::ParseError(format!("workspace: {}", e)))?;
+                workspace.root = dir.to_path_buf();
+                return Ok(Some(workspace));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Directories of the member projects, in the order they are listed
+    pub fn member_dirs(&self) -> Vec<PathBuf> {
+        let mut dirs = Vec::new();
+        for member in &self.members {
+            match member.strip_suffix("/*") {
+                Some(parent) => {
+                    let mut children: Vec<PathBuf> = fs::read_dir(self.root.join(parent))
+                        .into_iter()
+                        .flatten()
+                        .flatten()
+                        .map(|entry| entry.path())
+                        .filter(|path| path.join("manifest.json").is_file())
+                        .collect();
+                    children.sort();
+                    dirs.extend(children);
+                }
+                None => dirs.push(self.root.join(member)),
+            }
+        }
+        dirs
+    }
+
+    /// The directory and manifest of the member project called `name`
+    pub fn member(&self, name: &str) -> Option<(PathBuf, Manifest)> {
+        self.member_dirs().into_iter().find_map(|dir| {
+            let manifest = Manifest::load(dir.join("manifest.json")).ok()?;
+            (manifest.name == name).then_some((dir, manifest))
+        })
+    }
+}
+
+/// Why a manifest doesn't match the schema
+struct Problem {
+    /// JSON path of the offending value, e.g. `["dependencies", "http"]`
+    path: Vec<String>,
+    /// Point at the key rather than the value
+    at_key: bool,
+    message: String,
+    help: Option<String>,
+}
+
+impl Problem {
+    fn new(path: &[&str], message: impl Display) -> Self {
+        Self {
+            path: path.iter().map(|s| s.to_string()).collect(),
+            at_key: false,
+            message: message.to_string(),
+            help: None,
+        }
+    }
+
+    fn help(mut self, help: impl Display) -> Self {
+        self.help = Some(help.to_string());
+        self
+    }
+}
+
+fn validate(manifest: &Value) -> Result<(), Problem> {
+    let Some(fields) = manifest.as_object() else {
+        return Err(Problem::new(
+            &[],
+            "manifest.json must contain a JSON object",
+        ));
+    };
+
+    for key in fields.keys() {
+        if !KNOWN_KEYS.contains(&key.as_str()) {
+            let mut problem = Problem::new(&[key], format!("unknown field '{}'", key));
+            problem.at_key = true;
+            if let Some(known) = KNOWN_KEYS.iter().find(|known| similar(key, known)) {
+                problem = problem.help(format!("did you mean '{}'?", known));
+            }
+            return Err(problem);
+        }
+    }
+
+    for key in ["name", "version", "entrypoint"] {
+        if !fields.contains_key(key) {
+            return Err(Problem::new(
+                &[],
+                format!("missing required field '{}'", key),
+            ));
+        }
+    }
+
+    for (key, value) in fields {
+        let key = key.as_str();
+        match key {
+            "name" => {
+                let name = expect_str(value, &[key])?;
+                let valid = name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+                if name.is_empty() || !valid {
+                    return Err(
+                        Problem::new(&[key], format!("invalid package name '{}'", name))
+                            .help("package names may only contain letters, digits, '-' and '_'"),
+                    );
+                }
+            }
+            "version" => {
+                let version = expect_str(value, &[key])?;
+                if let Err(e) = semver::Version::parse(version) {
+                    return Err(Problem::new(
+                        &[key],
+                        format!("invalid version '{}': {}", version, e),
+                    )
+                    .help("versions follow semantic versioning, e.g. \"1.0.0\""));
+                }
+            }
+            "entrypoint" => {
+                let entrypoint = expect_str(value, &[key])?;
+                if entrypoint.is_empty() {
+                    return Err(Problem::new(&[key], "entrypoint must not be empty")
+                        .help("the entrypoint is the file `loft .` runs, e.g. \"src/main.lf\""));
+                }
+            }
+            "description" | "license" | "repository" => {
+                expect_str(value, &[key])?;
+            }
+            "authors" | "keywords" | "exclude" => {
+                let Some(items) = value.as_array() else {
+                    return Err(Problem::new(
+                        &[key],
+                        format!("'{}' must be a list of strings", key),
+                    ));
+                };
+                for (i, item) in items.iter().enumerate() {
+                    expect_str(item, &[key, &i.to_string()])?;
+                }
+            }
+            "strict" if !value.is_boolean() => {
+                return Err(Problem::new(&[key], "'strict' must be true or false"));
+            }
+            "dependencies" => {
+                let Some(dependencies) = value.as_object() else {
+                    return Err(Problem::new(
+                        &[key],
+                        "'dependencies' must map package names to versions or paths",
+                    ));
+                };
+                for (name, constraint) in dependencies {
+                    let constraint = expect_str(constraint, &[key, name])?;
+                    let is_local = constraint.starts_with("./")
+                        || constraint.starts_with("../")
+                        || constraint.starts_with('/');
+                    if !is_local && semver::VersionReq::parse(constraint).is_err() {
+                        return Err(Problem::new(
+                            &[key, name],
+                            format!(
+                                "invalid version requirement '{}' for dependency '{}'",
+                                constraint, name
+                            ),
+                        )
+                        .help("use a version requirement such as \"^1.2.0\", or a path starting with ./, ../ or /"));
+                    }
+                }
+            }
+            "workspace" => {
+                let members = value.get("members").and_then(Value::as_array);
+                let Some(members) = members.filter(|_| value.is_object()) else {
+                    return Err(
+                        Problem::new(&[key], "'workspace' must have a list of members")
+                            .help("e.g. \"workspace\": { \"members\": [\"app\", \"packages/*\"] }"),
+                    );
+                };
+                for (i, member) in members.iter().enumerate() {
+                    expect_str(member, &[key, "members", &i.to_string()])?;
+                }
+            }
+            "lints" => {
+                let Some(lints) = value.as_object() else {
+                    return Err(
+                        Problem::new(&[key], "'lints' must map lint names to levels")
+                            .help("e.g. \"lints\": { \"shadowed_variable\": \"allow\" }"),
+                    );
+                };
+                for (name, level) in lints {
+                    if !crate::lints::is_known(name) {
+                        let mut problem =
+                            Problem::new(&[key, name], format!("unknown lint '{}'", name));
+                        problem.at_key = true;
+                        let names = crate::lints::all();
+                        if let Some(known) = names.iter().find(|lint| similar(name, lint.name())) {
+                            problem = problem.help(format!("did you mean '{}'?", known.name()));
+                        }
+                        return Err(problem);
+                    }
+                    if !matches!(level.as_str(), Some("allow" | "warn" | "deny")) {
+                        return Err(Problem::new(
+                            &[key, name],
+                            format!(
+                                "the level of '{}' must be \"allow\", \"warn\" or \"deny\"",
+                                name
+                            ),
+                        ));
+                    }
+                }
+            }
+            "format" => {
+                if let Err(e) = crate::formatter::FormatConfig::from_json(&value.to_string()) {
+                    let message = e.split(" at line ").next().unwrap_or(&e).to_string();
+                    return Err(Problem::new(
+                        &[key],
+                        format!("invalid format settings: {}", message),
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn expect_str<'a>(value: &'a Value, path: &[&str]) -> Result<&'a str, Problem> {
+    value
+        .as_str()
+        .ok_or_else(|| Problem::new(path, format!("'{}' must be a string", path.join("."))))
+}
+
+/// Whether `a` is a likely typo of `b`: at most two edits apart
+fn similar(a: &str, b: &str) -> bool {
+    edit_distance(&a.to_lowercase(), b) <= 2
+}
+
+/// Byte offset and length of the value at `path` in `source`, or of its key
+/// when `at_key` is set. `source` must be valid JSON.
+fn json_span(source: &str, path: &[String], at_key: bool) -> Option<(usize, usize)> {
+    let bytes = source.as_bytes();
+    let mut pos = skip_whitespace(bytes, 0);
+    let mut key_span = None;
+
+    for segment in path {
+        match bytes.get(pos)? {
+            b'{' => {
+                pos += 1;
+                loop {
+                    pos = skip_whitespace(bytes, pos);
+                    let key_end = skip_string(bytes, pos)?;
+                    let key: String = serde_json::from_str(&source[pos..key_end]).ok()?;
+                    let key_start = pos;
+                    // Skip the colon
+                    pos = skip_whitespace(bytes, skip_whitespace(bytes, key_end) + 1);
+                    if &key == segment {
+                        key_span = Some((key_start, key_end - key_start));
+                        break;
+                    }
+                    pos = skip_whitespace(bytes, skip_value(bytes, pos)?);
+                    if bytes.get(pos) != Some(&b',') {
+                        return None;
+                    }
+                    pos += 1;
+                }
+            }
+            b'[' => {
+                let index: usize = segment.parse().ok()?;
+                pos = skip_whitespace(bytes, pos + 1);
+                for _ in 0..index {
+                    pos = skip_whitespace(bytes, skip_value(bytes, pos)?);
+                    if bytes.get(pos) != Some(&b',') {
+                        return None;
+                    }
+                    pos = skip_whitespace(bytes, pos + 1);
+                }
+                key_span = None;
+            }
+            _ => return None,
+        }
+    }
+
+    if at_key && key_span.is_some() {
+        return key_span;
+    }
+    Some((pos, skip_value(bytes, pos)? - pos))
+}
+
+fn skip_whitespace(bytes: &[u8], mut pos: usize) -> usize {
+    while bytes.get(pos).is_some_and(u8::is_ascii_whitespace) {
+        pos += 1;
+    }
+    pos
+}
+
+/// End of the string starting at `pos`
+fn skip_string(bytes: &[u8], pos: usize) -> Option<usize> {
+    if bytes.get(pos) != Some(&b'"') {
+        return None;
+    }
+    let mut i = pos + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return Some(i + 1),
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// End of the value starting at `pos`
+fn skip_value(bytes: &[u8], pos: usize) -> Option<usize> {
+    match bytes.get(pos)? {
+        b'"' => skip_string(bytes, pos),
+        b'{' | b'[' => {
+            let mut depth = 0;
+            let mut i = pos;
+            while i < bytes.len() {
+                match bytes[i] {
+                    b'"' => {
+                        i = skip_string(bytes, i)?;
+                        continue;
+                    }
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some(i + 1);
+                        }
+                    }
+                    _ => {}
+                }
+                i += 1;
+            }
+            None
+        }
+        _ => {
+            let mut i = pos;
+            while i < bytes.len()
+                && !matches!(bytes[i], b',' | b'}' | b']')
+                && !bytes[i].is_ascii_whitespace()
+            {
+                i += 1;
+            }
+            Some(i)
+        }
+    }
+}
+
+/// A manifest.json that isn't valid JSON or doesn't match the schema
+#[derive(Debug)]
+pub struct InvalidManifest {
+    pub message: String,
+    pub help: Option<String>,
+    /// Where in the manifest the problem is, e.g. `dependencies.http`
+    pub json_path: String,
+    pub line: usize,
+    pub column: usize,
+    span: (usize, usize),
+    source: NamedSource<String>,
+}
+
+impl InvalidManifest {
+    fn error(
+        path: &str,
+        source: &str,
+        span: (usize, usize),
+        json_path: &str,
+        message: &str,
+        help: Option<String>,
+    ) -> ManifestError {
+        let before = &source[..span.0];
+        ManifestError::Invalid(Box::new(Self {
+            message: message.to_string(),
+            help,
+            json_path: json_path.to_string(),
+            line: before.matches('\n').count() + 1,
+            column: before.rsplit('\n').next().unwrap_or("").chars().count() + 1,
+            span,
+            source: NamedSource::new(path, source.to_string()),
+        }))
+    }
+}
+
+impl Display for InvalidManifest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            self.source.name(),
+            self.line,
+            self.column,
+            self.message
+        )
+    }
+}
+
+impl std::error::Error for InvalidManifest {}
+
+impl Diagnostic for InvalidManifest {
+    fn severity(&self) -> Option<miette::Severity> {
+        Some(miette::Severity::Error)
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.help
+            .as_ref()
+            .map(|help| Box::new(help) as Box<dyn Display>)
+    }
+
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        Some(&self.source)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        let label = if self.json_path.is_empty() {
+            self.message.clone()
+        } else {
+            self.json_path.clone()
+        };
+        Some(Box::new(std::iter::once(LabeledSpan::new(
+            Some(label),
+            self.span.0,
+            self.span.1,
+        ))))
+    }
+}
+
 #[derive(Debug)]
 pub enum ManifestError {
     IoError(String),
@@ -105,6 +681,8 @@ pub enum ManifestError {
     NotFound,
     InvalidPath(String),
     UnresolvedImport(String),
+    /// The manifest isn't valid JSON or doesn't match the schema
+    Invalid(Box<InvalidManifest>),
 }
 
 impl std::fmt::Display for ManifestError {
@@ -115,6 +693,7 @@ impl std::fmt::Display for ManifestError {
             ManifestError::NotFound => write!(f, "manifest.json not found"),
             ManifestError::InvalidPath(e) => write!(f, "Invalid path: {}", e),
             ManifestError::UnresolvedImport(path) => write!(f, "Unresolved import: {}", path),
+            ManifestError::Invalid(e) => write!(f, "{}", e),
         }
     }
 }
@@ -145,7 +724,16 @@ mod tests {
             name: "myproject".to_string(),
             version: "1.0.0".to_string(),
             entrypoint: "src/main.lf".to_string(),
+            description: None,
+            license: None,
+            authors: Vec::new(),
+            repository: None,
+            keywords: Vec::new(),
+            exclude: Vec::new(),
             dependencies: HashMap::new(),
+            strict: false,
+            workspace: None,
+            lints: LintConfig::default(),
         };
 
         let result = manifest
@@ -163,10 +751,135 @@ mod tests {
             name: "myproject".to_string(),
             version: "1.0.0".to_string(),
             entrypoint: "src/main.lf".to_string(),
+            description: None,
+            license: None,
+            authors: Vec::new(),
+            repository: None,
+            keywords: Vec::new(),
+            exclude: Vec::new(),
             dependencies,
+            strict: false,
+            workspace: None,
+            lints: LintConfig::default(),
         };
 
         let result = manifest.resolve_import(&["utils".to_string()]).unwrap();
         assert_eq!(result, "./deps/utils");
     }
+
+    #[test]
+    fn test_workspace_members() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let project = |path: &str, name: &str| {
+            std::fs::create_dir_all(root.join(path)).unwrap();
+            std::fs::write(
+                root.join(path).join("manifest.json"),
+                format!(
+                    r#"{{ "name": "{}", "version": "0.1.0", "entrypoint": "lib.lf" }}"#,
+                    name
+                ),
+            )
+            .unwrap();
+        };
+        std::fs::write(
+            root.join("manifest.json"),
+            r#"{ "workspace": { "members": ["app", "libs/*"] } }"#,
+        )
+        .unwrap();
+        project("app", "app");
+        project("libs/shapes", "shapes");
+        project("libs/geo", "geo");
+        std::fs::create_dir_all(root.join("libs/notes")).unwrap();
+
+        let workspace = Workspace::find(&root.join("app")).unwrap().unwrap();
+        let members: Vec<String> = workspace
+            .member_dirs()
+            .iter()
+            .map(|dir| {
+                dir.strip_prefix(&workspace.root)
+                    .unwrap()
+                    .display()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(members, ["app", "libs/geo", "libs/shapes"]);
+
+        let (dir, geo) = workspace.member("geo").unwrap();
+        assert_eq!(dir, workspace.root.join("libs/geo"));
+        assert_eq!(geo.entrypoint, "lib.lf");
+        assert!(workspace.member("missing").is_none());
+    }
+
+    #[test]
+    fn test_validate() {
+        let manifest = Manifest::parse(
+            "manifest.json",
+            r#"{
+    "name": "geo",
+    "version": "1.2.0",
+    "entrypoint": "src/lib.lf",
+    "description": "Shapes and areas",
+    "authors": ["Ada"],
+    "dependencies": { "math": "^1.0", "utils": "./utils" }
+}"#,
+        )
+        .unwrap();
+        assert_eq!(manifest.description.as_deref(), Some("Shapes and areas"));
+        assert_eq!(manifest.authors, ["Ada"]);
+
+        let invalid = |source: &str| match Manifest::parse("manifest.json", source) {
+            Err(ManifestError::Invalid(e)) => e,
+            other => panic!(
+                "expected an invalid manifest, got {:?}",
+                other.map(|m| m.name)
+            ),
+        };
+
+        let e = invalid(
+            "{\n  \"name\": \"geo\",\n  \"version\": \"1.0.0\",\n  \"entrypoint\": \"a.lf\",\n  \"dependncies\": {}\n}",
+        );
+        assert_eq!(e.message, "unknown field 'dependncies'");
+        assert_eq!(e.help.as_deref(), Some("did you mean 'dependencies'?"));
+        assert_eq!((e.line, e.column), (5, 3));
+
+        let e = invalid(
+            "{\n  \"name\": \"geo\",\n  \"version\": \"1.0.0\",\n  \"entrypoint\": \"a.lf\",\n  \"dependencies\": {\n    \"math\": \"^^1\"\n  }\n}",
+        );
+        assert_eq!(e.json_path, "dependencies.math");
+        assert_eq!((e.line, e.column), (6, 13));
+        assert_eq!(
+            e.to_string(),
+            "manifest.json:6:13: invalid version requirement '^^1' for dependency 'math'"
+        );
+
+        let e = invalid(r#"{ "name": "geo", "version": "1.0", "entrypoint": "a.lf" }"#);
+        assert!(e.message.starts_with("invalid version '1.0'"));
+        assert_eq!(e.column, 29);
+
+        let e = invalid(
+            r#"{ "name": "geo", "version": "1.0.0", "entrypoint": "a.lf", "authors": ["Ada", 7] }"#,
+        );
+        assert_eq!(e.message, "'authors.1' must be a string");
+        assert_eq!(e.column, 79);
+
+        let e = invalid(r#"{ "name": "geo", "entrypoint": "a.lf" }"#);
+        assert_eq!(e.message, "missing required field 'version'");
+
+        let e = invalid("{ \"name\": \"geo\",\n}");
+        assert_eq!(e.line, 2);
+
+        let e = invalid(
+            r#"{ "name": "geo", "version": "1.0.0", "entrypoint": "a.lf", "lints": { "shadowed_variables": "allow" } }"#,
+        );
+        assert_eq!(e.message, "unknown lint 'shadowed_variables'");
+        assert_eq!(e.help.as_deref(), Some("did you mean 'shadowed_variable'?"));
+        let e = invalid(
+            r#"{ "name": "geo", "version": "1.0.0", "entrypoint": "a.lf", "lints": { "float_equality": "error" } }"#,
+        );
+        assert_eq!(
+            e.message,
+            "the level of 'float_equality' must be \"allow\", \"warn\" or \"deny\""
+        );
+    }
 }
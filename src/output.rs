@@ -0,0 +1,210 @@
+//! Colors in loft's own output, such as run headers, errors and prompts
+//!
+//! Output is styled through [`Colorize`], whose methods mirror the ones from
+//! `owo_colors` but leave the styling out when colors are turned off. They are
+//! off with `--no-color`, when `NO_COLOR` is set, when `CLICOLOR` is `0` or
+//! when stdout isn't a terminal, unless `CLICOLOR_FORCE` asks for them anyway.
+//! Scripts styling text with `term.style()` follow the same choice.
+//!
+//! Status messages about a run, such as which project is starting, go to
+//! stderr through [`status`] so that stdout holds only what the program
+//! prints, and are left out entirely in quiet mode.
+
+use owo_colors::Style;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+pub use owo_colors::Rgb;
+
+/// Set by `--no-color`, which wins over the environment
+static DISABLED: AtomicBool = AtomicBool::new(false);
+static DETECTED: OnceLock<bool> = OnceLock::new();
+/// Set by `--quiet`, or when stdout isn't a terminal
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Leave status messages out, or print them again
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Print a status message to stderr, unless in quiet mode
+pub fn status(message: impl fmt::Display) {
+    if !is_quiet() {
+        eprintln!("{}", message);
+    }
+}
+
+/// Turn colors off for the rest of the process
+pub fn disable_colors() {
+    DISABLED.store(true, Ordering::Relaxed);
+}
+
+/// Whether output should be colored
+pub fn colors_enabled() -> bool {
+    if DISABLED.load(Ordering::Relaxed) {
+        return false;
+    }
+    *DETECTED.get_or_init(|| {
+        let var = |name: &str| std::env::var(name).ok();
+        detect(
+            var("NO_COLOR").as_deref(),
+            var("CLICOLOR").as_deref(),
+            var("CLICOLOR_FORCE").as_deref(),
+            stdout_is_terminal(),
+        )
+    })
+}
+
+/// Whether stdout is a terminal rather than a pipe or a file
+pub fn stdout_is_terminal() -> bool {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        atty::is(atty::Stream::Stdout)
+    }
+
+    // The playground shows output as plain text
+    #[cfg(target_arch = "wasm32")]
+    {
+        false
+    }
+}
+
+/// The color choice for the given environment, following no-color.org and
+/// the `CLICOLOR` convention, where an empty variable counts as unset
+fn detect(
+    no_color: Option<&str>,
+    clicolor: Option<&str>,
+    clicolor_force: Option<&str>,
+    terminal: bool,
+) -> bool {
+    fn set(value: Option<&str>) -> Option<&str> {
+        value.filter(|v| !v.is_empty())
+    }
+    if set(no_color).is_some() {
+        return false;
+    }
+    if set(clicolor_force).is_some_and(|v| v != "0") {
+        return true;
+    }
+    set(clicolor) != Some("0") && terminal
+}
+
+/// A value to be displayed with a style, if colors are enabled
+#[derive(Clone, Copy)]
+pub struct Styled<T> {
+    value: T,
+    style: Style,
+}
+
+impl<T: fmt::Display> fmt::Display for Styled<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if colors_enabled() {
+            fmt::Display::fmt(&self.style.style(&self.value), f)
+        } else {
+            fmt::Display::fmt(&self.value, f)
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Styled<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if colors_enabled() {
+            fmt::Debug::fmt(&self.style.style(&self.value), f)
+        } else {
+            fmt::Debug::fmt(&self.value, f)
+        }
+    }
+}
+
+macro_rules! style_methods {
+    ($($name:ident),* $(,)?) => {
+        /// Styling for any value, shown only when colors are enabled
+        pub trait Colorize {
+            $(
+                fn $name(&self) -> Styled<&Self> {
+                    Styled {
+                        value: self,
+                        style: Style::new().$name(),
+                    }
+                }
+            )*
+
+            fn truecolor(&self, r: u8, g: u8, b: u8) -> Styled<&Self> {
+                Styled {
+                    value: self,
+                    style: Style::new().truecolor(r, g, b),
+                }
+            }
+        }
+
+        impl<T> Styled<T> {
+            $(
+                pub fn $name(self) -> Styled<T> {
+                    Styled {
+                        value: self.value,
+                        style: self.style.$name(),
+                    }
+                }
+            )*
+
+            pub fn truecolor(self, r: u8, g: u8, b: u8) -> Styled<T> {
+                Styled {
+                    value: self.value,
+                    style: self.style.truecolor(r, g, b),
+                }
+            }
+        }
+    };
+}
+
+style_methods!(
+    bold,
+    dimmed,
+    italic,
+    underline,
+    red,
+    green,
+    yellow,
+    blue,
+    cyan,
+    bright_black,
+    bright_red,
+    bright_green,
+    bright_yellow,
+    bright_blue,
+    bright_magenta,
+    bright_cyan,
+    bright_white,
+);
+
+impl<T: ?Sized> Colorize for T {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_detection() {
+        assert!(detect(None, None, None, true));
+        assert!(!detect(None, None, None, false));
+        assert!(!detect(Some("1"), None, Some("1"), true));
+        assert!(detect(Some(""), None, None, true));
+        assert!(!detect(None, Some("0"), None, true));
+        assert!(detect(None, Some("1"), None, true));
+        assert!(detect(None, None, Some("1"), false));
+        assert!(!detect(None, None, Some("0"), false));
+    }
+
+    #[test]
+    fn test_styles_follow_the_choice() {
+        let styled = "ok".bright_green().bold();
+        disable_colors();
+        assert_eq!(format!("{}", styled), "ok");
+        assert_eq!(format!("{:?}", "ok".truecolor(0, 255, 65)), "\"ok\"");
+    }
+}
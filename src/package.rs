@@ -0,0 +1,242 @@
+//! The files `loft publish` packs into a package tarball.
+//!
+//! Everything in the project directory is included except version control,
+//! build output, installed dependencies and generated docs, plus whatever a
+//! `.loftignore` file or the `exclude` list in manifest.json names. Patterns
+//! work like `.gitignore`:
+//!
+//! ```text
+//! # Any file or directory named *.log, at any depth
+//! *.log
+//! # Only scratch.lf at the project root
+//! /scratch.lf
+//! # Directories only
+//! fixtures/
+//! assets/**/*.psd
+//! ```
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Name of the ignore file read from the project root
+pub const IGNORE_FILE: &str = ".loftignore";
+
+/// Never packaged, whatever the ignore list says
+const ALWAYS_IGNORED: &[&str] = &[".git", ".lflibs", "target", "/docs", "node_modules"];
+
+#[derive(Debug, Clone)]
+struct Pattern {
+    glob: String,
+    /// Match the whole path from the root instead of any file name
+    anchored: bool,
+    dir_only: bool,
+}
+
+/// Patterns for files to leave out of a package
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreList {
+    patterns: Vec<Pattern>,
+}
+
+impl IgnoreList {
+    /// The built-in patterns, the project's `.loftignore` and the manifest's `exclude` list
+    pub fn for_project(root: &Path, exclude: &[String]) -> io::Result<Self> {
+        let mut list = Self::default();
+        for pattern in ALWAYS_IGNORED {
+            list.add(pattern);
+        }
+        match fs::read_to_string(root.join(IGNORE_FILE)) {
+            Ok(content) => {
+                for line in content.lines() {
+                    list.add(line);
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        for pattern in exclude {
+            list.add(pattern);
+        }
+        Ok(list)
+    }
+
+    /// Add one pattern. Blank lines and `#` comments are skipped.
+    pub fn add(&mut self, line: &str) {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return;
+        }
+        let dir_only = line.ends_with('/');
+        let line = line.trim_end_matches('/');
+        let anchored = line.contains('/');
+        self.patterns.push(Pattern {
+            glob: line.trim_start_matches('/').to_string(),
+            anchored,
+            dir_only,
+        });
+    }
+
+    /// Whether `path`, relative to the project root and `/`-separated, is ignored
+    pub fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        self.patterns.iter().any(|pattern| {
+            (is_dir || !pattern.dir_only)
+                && if pattern.anchored {
+                    glob_match(&pattern.glob, path)
+                } else {
+                    glob_match(&pattern.glob, name)
+                }
+        })
+    }
+}
+
+/// Match `text` against a glob where `*` and `?` stay within one path
+/// component and `**` matches any number of them
+fn glob_match(glob: &str, text: &str) -> bool {
+    fn matches(glob: &[u8], text: &[u8]) -> bool {
+        match glob.split_first() {
+            None => text.is_empty(),
+            Some((b'*', rest)) if rest.first() == Some(&b'*') => {
+                let rest = &rest[1..];
+                let rest = rest.strip_prefix(b"/").unwrap_or(rest);
+                (0..=text.len()).any(|i| matches(rest, &text[i..]))
+            }
+            Some((b'*', rest)) => (0..=text.len())
+                .take_while(|&i| i == 0 || text[i - 1] != b'/')
+                .any(|i| matches(rest, &text[i..])),
+            Some((b'?', rest)) => {
+                text.first().is_some_and(|&c| c != b'/') && matches(rest, &text[1..])
+            }
+            Some((c, rest)) => text.first() == Some(c) && matches(rest, &text[1..]),
+        }
+    }
+    matches(glob.as_bytes(), text.as_bytes())
+}
+
+/// A file to package, with its path relative to the project root
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackageFile {
+    pub path: String,
+    pub size: u64,
+}
+
+/// Every file under `root` not in `ignore`, sorted by path
+pub fn package_files(root: &Path, ignore: &IgnoreList) -> io::Result<Vec<PackageFile>> {
+    fn walk(
+        root: &Path,
+        dir: &Path,
+        ignore: &IgnoreList,
+        files: &mut Vec<PackageFile>,
+    ) -> io::Result<()> {
+        let mut entries: Vec<PathBuf> = fs::read_dir(dir)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<io::Result<_>>()?;
+        entries.sort();
+        for path in entries {
+            let relative = path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let metadata = fs::metadata(&path)?;
+            if ignore.is_ignored(&relative, metadata.is_dir()) {
+                continue;
+            }
+            if metadata.is_dir() {
+                walk(root, &path, ignore, files)?;
+            } else {
+                files.push(PackageFile {
+                    path: relative,
+                    size: metadata.len(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = Vec::new();
+    walk(root, root, ignore, &mut files)?;
+    Ok(files)
+}
+
+/// Pack `files` from `root` into a `.tar.gz`
+pub fn build_tarball(root: &Path, files: &[PackageFile]) -> io::Result<Vec<u8>> {
+    let mut tar = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    for file in files {
+        tar.append_path_with_name(root.join(&file.path), &file.path)?;
+    }
+    tar.into_inner()?.finish()
+}
+
+/// A byte count for people, e.g. `1.5 KB`
+pub fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..=1023 => format!("{} B", bytes),
+        1024..=1_048_575 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.log", "debug.log"));
+        assert!(!glob_match("*.log", "logs/debug.log"));
+        assert!(glob_match("assets/**/*.psd", "assets/a/b/logo.psd"));
+        assert!(glob_match("assets/**/*.psd", "assets/logo.psd"));
+        assert!(glob_match("test_?.lf", "test_1.lf"));
+        assert!(!glob_match("test_?.lf", "test_12.lf"));
+    }
+
+    #[test]
+    fn test_package_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for file in [
+            "manifest.json",
+            "src/main.lf",
+            "src/scratch.lf",
+            "scratch.lf",
+            "debug.log",
+            "docs/index.html",
+            "src/docs/guide.md",
+            "fixtures/data.json",
+            ".lflibs/http@1.0.0/lib.lf",
+            "node_modules/x/index.js",
+        ] {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "x").unwrap();
+        }
+        fs::write(
+            root.join(IGNORE_FILE),
+            "# local files\n*.log\n/scratch.lf\n",
+        )
+        .unwrap();
+
+        let ignore = IgnoreList::for_project(root, &["fixtures/".to_string()]).unwrap();
+        let files: Vec<String> = package_files(root, &ignore)
+            .unwrap()
+            .into_iter()
+            .map(|f| f.path)
+            .collect();
+        assert_eq!(
+            files,
+            [
+                ".loftignore",
+                "manifest.json",
+                "src/docs/guide.md",
+                "src/main.lf",
+                "src/scratch.lf"
+            ]
+        );
+    }
+}
@@ -0,0 +1,351 @@
+//! Static exhaustiveness checking for `match` over enums and booleans.
+//!
+//! Each match is checked with the usual pattern-matrix algorithm: arms are
+//! specialized per constructor of the matched type and every value shape no
+//! arm can reach is reported as a witness such as `Shape.Rect(_, _)`.
+//! Matches over numbers or strings are only checked inside enum payloads,
+//! and enums declared outside the program are assumed to be fully covered.
+
+use super::visit::{self, Visitor};
+use super::{Expr, ExprKind, Span, Stmt, StmtKind};
+use std::collections::HashMap;
+
+/// A `match` that does not cover every possible value
+#[derive(Debug, Clone, PartialEq)]
+pub struct NonExhaustiveMatch {
+    /// Name of the matched type, e.g. `Shape` or `bool`
+    pub subject: String,
+    /// Patterns that no arm matches
+    pub missing: Vec<String>,
+    /// Location of the `match` in the source
+    pub span: Span,
+}
+
+impl NonExhaustiveMatch {
+    pub fn message(&self) -> String {
+        format!(
+            "Non-exhaustive match on {}: missing {}",
+            self.subject,
+            self.missing.join(", ")
+        )
+    }
+}
+
+/// Enum name -> (variant name, payload arity)
+type EnumTable = HashMap<String, Vec<(String, usize)>>;
+
+#[derive(Debug, Clone)]
+enum Pat {
+    /// Wildcard or binding
+    Wild,
+    Bool(bool),
+    Variant {
+        enum_name: String,
+        variant: String,
+        args: Vec<Pat>,
+    },
+    /// Number or string literal
+    Literal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Ctor<'a> {
+    Bool(bool),
+    Variant(&'a str, &'a str, usize),
+}
+
+impl Ctor<'_> {
+    fn arity(&self) -> usize {
+        match self {
+            Ctor::Bool(_) => 0,
+            Ctor::Variant(_, _, arity) => *arity,
+        }
+    }
+
+    fn render(&self, args: &[String]) -> String {
+        match self {
+            Ctor::Bool(b) => b.to_string(),
+            Ctor::Variant(enum_name, variant, _) if args.is_empty() => {
+                format!("{}.{}", enum_name, variant)
+            }
+            Ctor::Variant(enum_name, variant, _) => {
+                format!("{}.{}({})", enum_name, variant, args.join(", "))
+            }
+        }
+    }
+}
+
+/// Check every `match` in a program
+pub fn check_program(stmts: &[Stmt]) -> Vec<NonExhaustiveMatch> {
+    let mut checker = Checker {
+        enums: builtin_enums(),
+        results: Vec::new(),
+    };
+    stmts.iter().for_each(|s| checker.collect_enums(s));
+    visit::walk_stmts(&mut checker, stmts);
+    checker.results
+}
+
+fn builtin_enums() -> EnumTable {
+    let mut enums = EnumTable::new();
+    enums.insert(
+        "Option".to_string(),
+        vec![("Some".to_string(), 1), ("None".to_string(), 0)],
+    );
+    enums.insert(
+        "Result".to_string(),
+        vec![("Ok".to_string(), 1), ("Err".to_string(), 1)],
+    );
+    enums
+}
+
+struct Checker {
+    enums: EnumTable,
+    results: Vec<NonExhaustiveMatch>,
+}
+
+impl Visitor for Checker {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        if let StmtKind::Match { arms, .. } = &stmt.kind {
+            self.check_match(stmt.span, arms.iter().map(|(pattern, _)| pattern));
+        }
+        visit::walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let ExprKind::Match { arms, .. } = &expr.kind {
+            self.check_match(expr.span, arms.iter().map(|(pattern, _)| pattern));
+        }
+        visit::walk_expr(self, expr);
+    }
+}
+
+impl Checker {
+    fn collect_enums(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::EnumDecl { name, variants } => {
+                let variants = variants
+                    .iter()
+                    .map(|(v, types)| (v.clone(), types.as_ref().map_or(0, Vec::len)))
+                    .collect();
+                self.enums.insert(name.clone(), variants);
+            }
+            StmtKind::AttrStmt { stmt, .. } => self.collect_enums(stmt),
+            StmtKind::Block(stmts) => stmts.iter().for_each(|s| self.collect_enums(s)),
+            _ => {}
+        }
+    }
+
+    fn check_match<'a>(&mut self, span: Span, patterns: impl Iterator<Item = &'a Expr>) {
+        let rows: Vec<Vec<Pat>> = patterns.map(|p| vec![self.lower(p)]).collect();
+
+        // Only matches over enums and booleans are checked
+        let subject = rows.iter().find_map(|row| match &row[0] {
+            Pat::Bool(_) => Some("bool".to_string()),
+            Pat::Variant { enum_name, .. } => Some(enum_name.clone()),
+            _ => None,
+        });
+        let Some(subject) = subject else {
+            return;
+        };
+
+        let missing: Vec<String> = self
+            .missing(&rows, 1)
+            .into_iter()
+            .map(|mut witness| witness.remove(0))
+            .collect();
+        if !missing.is_empty() {
+            self.results.push(NonExhaustiveMatch {
+                subject,
+                missing,
+                span,
+            });
+        }
+    }
+
+    /// Convert a pattern expression into a pattern
+    fn lower(&self, pattern: &Expr) -> Pat {
+        match &pattern.kind {
+            ExprKind::Ident(_) => Pat::Wild,
+            ExprKind::Boolean(b) => Pat::Bool(*b),
+            ExprKind::FieldAccess { object, field } => match &object.kind {
+                ExprKind::Ident(enum_name) => Pat::Variant {
+                    enum_name: enum_name.clone(),
+                    variant: field.clone(),
+                    args: Vec::new(),
+                },
+                _ => Pat::Literal,
+            },
+            ExprKind::Call { func, args } => match &func.kind {
+                ExprKind::FieldAccess { object, field } => match &object.kind {
+                    ExprKind::Ident(enum_name) => Pat::Variant {
+                        enum_name: enum_name.clone(),
+                        variant: field.clone(),
+                        args: args.iter().map(|a| self.lower(a)).collect(),
+                    },
+                    _ => Pat::Literal,
+                },
+                _ => Pat::Literal,
+            },
+            _ => Pat::Literal,
+        }
+    }
+
+    /// The constructors of the type matched by the first column, if it is finite and known
+    fn column_ctors(&self, rows: &[Vec<Pat>]) -> Option<Vec<Ctor<'_>>> {
+        for row in rows {
+            match &row[0] {
+                Pat::Bool(_) => return Some(vec![Ctor::Bool(true), Ctor::Bool(false)]),
+                Pat::Variant { enum_name, .. } => {
+                    let (name, variants) = self.enums.get_key_value(enum_name)?;
+                    return Some(
+                        variants
+                            .iter()
+                            .map(|(v, arity)| Ctor::Variant(name, v, *arity))
+                            .collect(),
+                    );
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Value shapes of `width` columns that no row matches, rendered as patterns
+    fn missing(&self, rows: &[Vec<Pat>], width: usize) -> Vec<Vec<String>> {
+        if width == 0 {
+            return if rows.is_empty() {
+                vec![vec![]]
+            } else {
+                vec![]
+            };
+        }
+
+        // An enum from another module cannot be checked, so assume it is covered
+        let unknown_enum = rows.iter().any(|row| {
+            matches!(&row[0], Pat::Variant { enum_name, .. } if !self.enums.contains_key(enum_name))
+        });
+        if unknown_enum {
+            return vec![];
+        }
+
+        let Some(ctors) = self.column_ctors(rows) else {
+            // Infinite domain: only wildcard rows can cover the rest
+            let defaults: Vec<Vec<Pat>> = rows
+                .iter()
+                .filter(|row| matches!(row[0], Pat::Wild))
+                .map(|row| row[1..].to_vec())
+                .collect();
+            return self
+                .missing(&defaults, width - 1)
+                .into_iter()
+                .map(|rest| prepend("_".to_string(), rest))
+                .collect();
+        };
+
+        let mut witnesses = Vec::new();
+        for ctor in ctors {
+            let arity = ctor.arity();
+            let specialized: Vec<Vec<Pat>> = rows
+                .iter()
+                .filter_map(|row| specialize(row, ctor))
+                .collect();
+
+            for witness in self.missing(&specialized, width - 1 + arity) {
+                let (args, rest) = witness.split_at(arity);
+                witnesses.push(prepend(ctor.render(args), rest.to_vec()));
+            }
+        }
+        witnesses
+    }
+}
+
+/// Rows matching `ctor` in the first column, with its payload spliced in
+fn specialize(row: &[Pat], ctor: Ctor) -> Option<Vec<Pat>> {
+    let head: Vec<Pat> = match (&row[0], ctor) {
+        (Pat::Wild, _) => vec![Pat::Wild; ctor.arity()],
+        (Pat::Bool(b), Ctor::Bool(c)) if *b == c => vec![],
+        (Pat::Variant { variant, args, .. }, Ctor::Variant(_, v, arity)) if variant == v => {
+            // A payload pattern with the wrong number of fields never matches
+            if args.len() != arity {
+                return None;
+            }
+            args.clone()
+        }
+        _ => return None,
+    };
+    Some(head.into_iter().chain(row[1..].iter().cloned()).collect())
+}
+
+fn prepend(first: String, mut rest: Vec<String>) -> Vec<String> {
+    rest.insert(0, first);
+    rest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{InputStream, Parser};
+
+    fn check(source: &str) -> Vec<NonExhaustiveMatch> {
+        let source = source.to_string();
+        let stmts = Parser::new(InputStream::new("test", &source))
+            .parse()
+            .unwrap();
+        check_program(&stmts)
+    }
+
+    #[test]
+    fn test_reports_missing_variants() {
+        let results = check(
+            r#"
+            enum Shape { Circle(num), Rect(num, num), Empty }
+            let s = Shape.Empty;
+            let area = match s {
+                Shape.Circle(r) => r * r,
+            };
+            "#,
+        );
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].subject, "Shape");
+        assert_eq!(results[0].missing, vec!["Shape.Rect(_, _)", "Shape.Empty"]);
+    }
+
+    #[test]
+    fn test_exhaustive_matches_pass() {
+        let results = check(
+            r#"
+            enum Color { Red, Green }
+            let c = Color.Red;
+            match c {
+                Color.Red => term.println("red"),
+                _ => term.println("other"),
+            }
+            let flag = match true {
+                true => 1,
+                false => 0,
+            };
+            let n = match 3 {
+                1 => "one",
+                2 => "two",
+            };
+            "#,
+        );
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn test_nested_payload_patterns() {
+        let results = check(
+            r#"
+            let v = Option.Some(true);
+            let x = match v {
+                Option.Some(true) => 1,
+                Option.None => 0,
+            };
+            "#,
+        );
+        assert_eq!(results[0].missing, vec!["Option.Some(false)"]);
+        assert_eq!(results[0].subject, "Option");
+    }
+}
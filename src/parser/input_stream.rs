@@ -1,7 +1,10 @@
+use std::borrow::Cow;
 use std::fmt::{Debug, Display};
 
 use miette::{Diagnostic, LabeledSpan, NamedSource};
 
+use super::span::Span;
+
 #[derive(Debug, Clone, Copy)]
 pub struct Position {
     position: usize,
@@ -11,7 +14,7 @@ pub struct Position {
 
 pub struct InputStream<'a> {
     path: String,
-    input: &'a [u8],
+    input: &'a str,
     position: usize,
     line: usize,
     column: usize,
@@ -33,7 +36,7 @@ impl<'a> InputStream<'a> {
     pub fn new(path: impl Display, st: &'a String) -> Self {
         Self {
             path: path.to_string(),
-            input: st.as_bytes(),
+            input: st,
             position: 0,
             line: 0,
             column: 0,
@@ -45,9 +48,8 @@ impl Iterator for InputStream<'_> {
     type Item = char;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(c) = self.input.get(self.position) {
-            let ch = *c as char;
-            self.position += 1;
+        if let Some(ch) = self.peek() {
+            self.position += ch.len_utf8();
 
             // Update line and column tracking
             if ch == '\n' {
@@ -66,7 +68,12 @@ impl Iterator for InputStream<'_> {
 
 impl InputStream<'_> {
     pub fn peek(&self) -> Option<char> {
-        self.input.get(self.position).map(|v| *v as char)
+        self.input[self.position..].chars().next()
+    }
+
+    /// The character `n` places after the next one, without consuming anything
+    pub fn peek_nth(&self, n: usize) -> Option<char> {
+        self.input[self.position..].chars().nth(n)
     }
 
     pub fn eof(&self) -> bool {
@@ -81,6 +88,21 @@ impl InputStream<'_> {
         }
     }
 
+    /// Span from a saved position up to the current one
+    pub fn span_from(&self, start: Position) -> Span {
+        Span::new(start.position, self.position, start.line, start.column)
+    }
+
+    /// The text between two byte offsets, or nothing if they don't make a range
+    pub fn slice(&self, start: usize, end: usize) -> Cow<'_, str> {
+        Cow::Borrowed(self.input.get(start..end).unwrap_or_default())
+    }
+
+    /// Empty span at the current position
+    pub fn here(&self) -> Span {
+        self.span_from(self.save_position())
+    }
+
     pub fn restore_position(&mut self, pos: Position) {
         self.position = pos.position;
         self.line = pos.line;
@@ -88,7 +110,7 @@ impl InputStream<'_> {
     }
 
     pub fn croak(&self, msg: impl Display, len: Option<usize>) -> Error {
-        let source_text = String::from_utf8_lossy(self.input).to_string();
+        let source_text = self.input.to_string();
         Error {
             path: self.path.clone(),
             position: self.position,
@@ -132,6 +154,10 @@ impl Display for Error {
 
 impl std::error::Error for Error {}
 impl Diagnostic for Error {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        Some(Box::new("L0001"))
+    }
+
     fn severity(&self) -> Option<miette::Severity> {
         Some(miette::Severity::Error)
     }
@@ -1,22 +1,58 @@
+pub mod exhaustiveness;
 pub mod input_stream;
+pub mod span;
 pub mod token_stream;
+pub mod trivia;
+pub mod visit;
 
 #[cfg(test)]
 mod tests;
 
 use input_stream::{Error, Result};
 use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::Arc;
 use token_stream::{Token, TokenStream};
 
 // Re-export commonly used items
 pub use input_stream::InputStream;
+pub use span::Span;
 pub use token_stream::Token as TokenType;
+pub use trivia::{Comment, Trivia};
 
-#[derive(Clone, Debug, PartialEq)]
-pub enum Expr {
+/// An expression together with where it was written
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+/// Expressions built outside the parser have no location
+impl From<ExprKind> for Expr {
+    fn from(kind: ExprKind) -> Self {
+        Self::new(kind, Span::default())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub enum ExprKind {
     Number(Decimal),
     Ident(String),
-    String(String),
+    /// A local variable of a function, found `depth` scopes up at index `slot`
+    /// by [`resolve`](crate::runtime::resolve). The parser never produces these.
+    Local {
+        name: String,
+        depth: usize,
+        slot: usize,
+    },
+    String(Arc<str>),
     Boolean(bool),
     BinOp {
         op: String,
@@ -36,6 +72,7 @@ pub enum Expr {
         field: String,
     },
     ArrayLiteral(Vec<Expr>),
+    MapLiteral(Vec<(Expr, Expr)>), // #{ key: value, ... }
     StructLiteral {
         name: String,
         fields: Vec<(String, Expr)>,
@@ -44,6 +81,12 @@ pub enum Expr {
         array: Box<Expr>,
         index: Box<Expr>,
     },
+    /// `array[start..end]`, where either bound may be left out
+    Slice {
+        array: Box<Expr>,
+        start: Option<Box<Expr>>,
+        end: Option<Box<Expr>>,
+    },
     Lambda {
         params: Vec<(String, Option<Type>)>,
         return_type: Option<Type>,
@@ -61,15 +104,99 @@ pub enum Expr {
         arms: Vec<(Expr, Expr)>, // pattern => expression
     },
     Try(Box<Expr>), // Error propagation with ?
+    If {
+        condition: Box<Expr>,
+        then_branch: Box<Expr>,
+        /// A block, or another `If` for `else if`
+        else_branch: Option<Box<Expr>>,
+    },
+    Spread(Box<Expr>), // ...array, in call arguments and array literals
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub enum TemplatePart {
     Text(String),
-    Expression(Expr),
+    /// An interpolation, with the format specifier of `${value:spec}`
+    Expression(Expr, Option<FormatSpec>),
+}
+
+/// How an interpolated value is written out, e.g. `${n:d2}` or `${n:hex}`
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum FormatSpec {
+    /// `d` or `dN`: an integer, zero-padded to at least N digits
+    Integer { width: usize },
+    /// `fN`: a number with exactly N decimal places
+    Fixed { decimals: u32 },
+    /// `hex`, `HEX`, `oct` or `bin`: an integer in another base
+    Radix { radix: u32, upper: bool },
+    /// `<N`, `>N` or `^N`: any value padded with spaces to N characters
+    Align { align: Align, width: usize },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+impl FormatSpec {
+    pub fn parse(spec: &str) -> Option<FormatSpec> {
+        let radix = |radix, upper| Some(FormatSpec::Radix { radix, upper });
+        match spec {
+            "d" => Some(FormatSpec::Integer { width: 0 }),
+            "hex" => radix(16, false),
+            "HEX" => radix(16, true),
+            "oct" => radix(8, false),
+            "bin" => radix(2, false),
+            _ => {
+                let mut chars = spec.chars();
+                let kind = chars.next()?;
+                let digits = chars.as_str();
+                if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                    return None;
+                }
+                let width = digits.parse().ok()?;
+                let align = |align| Some(FormatSpec::Align { align, width });
+                match kind {
+                    'd' => Some(FormatSpec::Integer { width }),
+                    'f' => Some(FormatSpec::Fixed {
+                        decimals: digits.parse().ok()?,
+                    }),
+                    '<' => align(Align::Left),
+                    '>' => align(Align::Right),
+                    '^' => align(Align::Center),
+                    _ => None,
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for FormatSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FormatSpec::Integer { width: 0 } => write!(f, "d"),
+            FormatSpec::Integer { width } => write!(f, "d{}", width),
+            FormatSpec::Fixed { decimals } => write!(f, "f{}", decimals),
+            FormatSpec::Radix { radix: 16, upper } => {
+                write!(f, "{}", if *upper { "HEX" } else { "hex" })
+            }
+            FormatSpec::Radix { radix: 8, .. } => write!(f, "oct"),
+            FormatSpec::Radix { .. } => write!(f, "bin"),
+            FormatSpec::Align { align, width } => {
+                let align = match align {
+                    Align::Left => '<',
+                    Align::Right => '>',
+                    Align::Center => '^',
+                };
+                write!(f, "{}{}", align, width)
+            }
+        }
+    }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub enum Type {
     Named(String),
     Generic {
@@ -82,16 +209,45 @@ pub enum Type {
     },
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct Attribute {
     pub name: String,
     pub args: Vec<Expr>,
 }
 
-#[derive(Clone, Debug, PartialEq)]
-pub enum Stmt {
+/// A statement together with where it was written and the comments around it
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+    #[serde(skip_serializing_if = "Trivia::is_empty")]
+    pub trivia: Trivia,
+}
+
+impl Stmt {
+    pub fn new(kind: StmtKind, span: Span) -> Self {
+        Self {
+            kind,
+            span,
+            trivia: Trivia::default(),
+        }
+    }
+}
+
+/// Statements built outside the parser have no location
+impl From<StmtKind> for Stmt {
+    fn from(kind: StmtKind) -> Self {
+        Self::new(kind, Span::default())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub enum StmtKind {
     ImportDecl {
-        path: Vec<String>, // e.g., ["project", "module", "value"]
+        path: Vec<String>,              // e.g., ["project", "module", "value"]
+        alias: Option<String>,          // learn "utils" as u
+        items: Option<Vec<ImportItem>>, // learn { a, b } from "utils"
+        is_exported: bool,              // teach learn ... re-exports what it imports
     },
     VarDecl {
         name: String,
@@ -108,6 +264,8 @@ pub enum Stmt {
         name: String,
         type_params: Vec<String>,
         params: Vec<(String, Type)>,
+        /// The last parameter collects the remaining arguments into an array
+        variadic: bool,
         return_type: Option<Type>,
         body: Box<Stmt>,
         is_async: bool,
@@ -119,6 +277,7 @@ pub enum Stmt {
     },
     StructDecl {
         name: String,
+        type_params: Vec<String>,
         fields: Vec<(String, Type)>,
     },
     ImplBlock {
@@ -157,13 +316,55 @@ pub enum Stmt {
         arms: Vec<(Expr, Stmt)>,
     },
     Return(Option<Expr>),
+    Try {
+        body: Box<Stmt>,
+        catch: Option<(Option<String>, Box<Stmt>)>, // catch (error) { ... }
+        finally: Option<Box<Stmt>>,
+    },
+    Throw(Expr),
+    Defer(Expr), // runs when the enclosing function exits
     Break,
     Continue,
     Expr(Expr),
     Block(Vec<Stmt>),
 }
 
-#[derive(Clone, Debug, PartialEq)]
+/// A name taken from a module with `learn { name as alias } from "module"`
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ImportItem {
+    pub name: String,
+    pub alias: Option<String>,
+}
+
+impl ImportItem {
+    /// The name the item is bound to in the importing module
+    pub fn local_name(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// The name a module imported as a whole is bound to when no alias is given:
+/// the file stem of a single path ("./math_utils" binds `math_utils`),
+/// otherwise the last component ("project::module" binds `module`)
+pub fn import_binding_name(path: &[String]) -> String {
+    match path {
+        [single] => std::path::Path::new(single)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or(single)
+            .to_string(),
+        _ => path.last().cloned().unwrap_or_default(),
+    }
+}
+
+/// Whether a function of an impl block is a method, called on a value, rather
+/// than an associated function called on the type like `Point.new(1, 2)`.
+/// Methods take `self` first.
+pub fn is_method(params: &[(String, Type)]) -> bool {
+    params.first().is_some_and(|(name, _)| name == "self")
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub enum TraitMethod {
     Signature {
         name: String,
@@ -180,20 +381,77 @@ pub enum TraitMethod {
 
 pub struct Parser<'a> {
     tokens: TokenStream<'a>,
+    /// String literals seen so far, so repeated literals share one allocation
+    strings: HashSet<Arc<str>>,
+    /// Span of the most recently consumed token
+    last_span: Span,
+    /// How many of the comments read so far belong to a statement
+    claimed: usize,
+    /// How many levels of statements and expressions enclose the one being parsed
+    depth: usize,
+    /// How many links of chains enclose the expression being parsed
+    links: usize,
 }
 
+/// How deeply statements and expressions may nest. Everything that walks the
+/// tree recurses into it, so deeper code stops with an error here instead of
+/// overflowing the stack later on.
+pub const MAX_NESTING: usize = 100;
+
+/// How many links of chains such as `a + b + c` or `a.b().c` an expression may
+/// have on top of its nesting. Each link is another level of the tree too, but
+/// costs far less to walk than a nested block, and the interpreter evaluates
+/// chains without recursing.
+pub const MAX_CHAIN_LINKS: usize = 1000;
+
 impl<'a> Parser<'a> {
     pub fn new(input: InputStream<'a>) -> Self {
         Self {
             tokens: TokenStream::new(input),
+            strings: HashSet::new(),
+            last_span: Span::default(),
+            claimed: 0,
+            depth: 0,
+            links: 0,
+        }
+    }
+
+    /// Every comment read so far, in order. Once the whole input is parsed
+    /// that's all of them, including those also kept in [`Stmt::trivia`].
+    pub fn comments(&self) -> &[Comment] {
+        &self.tokens.comments
+    }
+
+    /// Comments no statement took, like those after the last one in the file
+    pub fn unclaimed_comments(&self) -> &[Comment] {
+        &self.tokens.comments[self.claimed..]
+    }
+
+    /// Parse bytes of any kind, as a fuzzer hands them over. Invalid UTF-8 is
+    /// replaced, and both the strict parser and the recovering one the LSP
+    /// uses get to run. However malformed the input, this returns an error
+    /// rather than panicking or overflowing the stack.
+    pub fn parse_fuzz(bytes: &[u8]) -> Result<Vec<Stmt>> {
+        let source = String::from_utf8_lossy(bytes).into_owned();
+        Parser::new(InputStream::new("<fuzz>", &source)).parse_recoverable();
+        Parser::new(InputStream::new("<fuzz>", &source)).parse()
+    }
+
+    /// Intern a string literal
+    fn intern(&mut self, s: String) -> Arc<str> {
+        if let Some(existing) = self.strings.get(s.as_str()) {
+            return Arc::clone(existing);
         }
+        let shared: Arc<str> = s.into();
+        self.strings.insert(Arc::clone(&shared));
+        shared
     }
 
     // Generic parsing utilities
     fn peek(&mut self) -> Result<Option<Token>> {
         // If there's a token in the buffer, return it without consuming
         if !self.tokens.buffer.is_empty() {
-            return Ok(Some(self.tokens.buffer[0].clone()));
+            return Ok(Some(self.tokens.buffer[0].0.clone()));
         }
 
         // Otherwise, parse the next token and put it in the buffer
@@ -201,19 +459,84 @@ impl<'a> Parser<'a> {
         if let Some(ref token) = token_opt {
             self.tokens.buffer.insert(0, token.clone());
         }
-        Ok(token_opt)
+        Ok(token_opt.map(|(token, _)| token))
     }
 
     fn next(&mut self) -> Result<Option<Token>> {
-        if !self.tokens.buffer.is_empty() {
-            return Ok(Some(self.tokens.buffer.remove(0)));
-        }
+        let next = if !self.tokens.buffer.is_empty() {
+            Some(self.tokens.buffer.remove(0))
+        } else {
+            self.tokens.read_next_token().transpose()?
+        };
+
+        Ok(next.map(|(token, span)| {
+            self.last_span = span;
+            token
+        }))
+    }
 
-        match self.tokens.read_next_token() {
-            Some(Ok(token)) => Ok(Some(token)),
-            Some(Err(e)) => Err(e),
-            None => Ok(None),
+    /// Put the last consumed token back in front of the stream
+    fn push_back(&mut self, token: Token) {
+        self.tokens.push_back(token, self.last_span);
+    }
+
+    /// Fail if going `levels` deeper would nest past [`MAX_NESTING`]
+    fn check_depth(&self, levels: usize) -> Result<()> {
+        if self.depth + levels > MAX_NESTING {
+            return Err(self.tokens.croak(
+                format!(
+                    "Code is nested too deeply, the limit is {} levels",
+                    MAX_NESTING
+                ),
+                None,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Run a parser one level deeper. Links of chains parsed inside are
+    /// dropped again when it returns.
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.check_depth(1)?;
+        let (depth, links) = (self.depth, self.links);
+        self.depth += 1;
+        let result = parse(self);
+        (self.depth, self.links) = (depth, links);
+        result
+    }
+
+    /// Add a link to a chain being parsed, failing past [`MAX_CHAIN_LINKS`]
+    fn chain_link(&mut self) -> Result<()> {
+        if self.links >= MAX_CHAIN_LINKS {
+            return Err(self.tokens.croak(
+                format!("Chain is too long, the limit is {} links", MAX_CHAIN_LINKS),
+                None,
+            ));
         }
+        self.links += 1;
+        Ok(())
+    }
+
+    /// Span of the next token, or an empty span at the end of input
+    fn peek_span(&mut self) -> Result<Span> {
+        self.peek()?;
+        Ok(match self.tokens.buffer.first() {
+            Some((_, span)) => *span,
+            None => self.tokens.input.here(),
+        })
+    }
+
+    /// Span from `start` to the end of the last consumed token
+    fn span_from(&self, start: Span) -> Span {
+        start.to(self.last_span)
+    }
+
+    fn stmt(&self, kind: StmtKind, start: Span) -> Stmt {
+        Stmt::new(kind, self.span_from(start))
+    }
+
+    fn expr(&self, kind: ExprKind, start: Span) -> Expr {
+        Expr::new(kind, self.span_from(start))
     }
 
     fn expect_punct(&mut self, punct: &str) -> Result<()> {
@@ -300,27 +623,99 @@ impl<'a> Parser<'a> {
         while let Ok(Some(token)) = self.peek() {
             if self.is_punct(&token, ";") {
                 let _ = self.next();
-                return;
+                break;
             }
 
             // Check for keywords that start statements
             if let Token::Keyword(k) = &token {
                 match k.as_str() {
                     "fn" | "let" | "const" | "if" | "while" | "for" | "return" | "teach"
-                    | "learn" | "def" | "impl" | "trait" => return,
+                    | "learn" | "def" | "impl" | "trait" | "try" | "throw" | "defer" => break,
                     _ => {}
                 }
             }
 
             let _ = self.next();
         }
+
+        // Comments inside the statement that failed belong to no statement
+        let skipped = self.tokens.comments[self.claimed..]
+            .iter()
+            .take_while(|comment| comment.span.start < self.last_span.end)
+            .count();
+        self.claimed += skipped;
     }
 
     fn parse_statement(&mut self) -> Result<Stmt> {
+        self.nested(|parser| parser.spanned_stmt(Self::parse_statement_kind))
+    }
+
+    /// Run a statement parser and attach the span of everything it consumed,
+    /// along with the comments around it
+    fn spanned_stmt(&mut self, parse: impl FnOnce(&mut Self) -> Result<StmtKind>) -> Result<Stmt> {
+        let start = self.peek_span()?;
+        let leading = self.claim_comments(start.start);
+        let gap_end = leading
+            .first()
+            .map_or(start.start, |comment| comment.span.start)
+            .max(self.last_span.end);
+        let blank_line_before = self.last_span.end > 0
+            && trivia::has_blank_line(&self.tokens.input.slice(self.last_span.end, gap_end));
+
+        let kind = parse(self)?;
+        let mut stmt = self.stmt(kind, start);
+
+        // Read past the end of the line, so a comment there has been seen
+        if self.tokens.buffer.is_empty() {
+            self.tokens.skip_whitespace_and_comments()?;
+        }
+        let inner = self.claim_comments(stmt.span.end);
+        let trailing = self.tokens.comments.get(self.claimed).filter(|comment| {
+            comment.span.start >= stmt.span.end
+                && self
+                    .tokens
+                    .input
+                    .slice(stmt.span.end, comment.span.start)
+                    .chars()
+                    .all(|c| c == ' ' || c == '\t')
+        });
+        let trailing = trailing.cloned();
+        self.claimed += usize::from(trailing.is_some());
+
+        stmt.trivia = Trivia {
+            blank_line_before,
+            leading,
+            trailing,
+            inner,
+        };
+        Ok(stmt)
+    }
+
+    /// Take the comments no statement has taken yet that end by `end`
+    fn claim_comments(&mut self, end: usize) -> Vec<Comment> {
+        let unclaimed = &self.tokens.comments[self.claimed..];
+        let count = unclaimed
+            .iter()
+            .take_while(|comment| comment.span.end <= end)
+            .count();
+        self.claimed += count;
+        unclaimed[..count].to_vec()
+    }
+
+    fn parse_statement_kind(&mut self) -> Result<StmtKind> {
         if let Some(token) = self.peek()? {
             match token {
                 Token::Punct(p) if p == "#" => {
                     self.next()?; // consume #
+                    if let Some(Token::Punct(next)) = self.peek()? {
+                        if next == "{" {
+                            // A map literal used as an expression statement
+                            self.push_back(Token::Punct(p));
+                            let expr = self.parse_expression()?;
+                            self.maybe_consume_semicolon();
+                            return Ok(StmtKind::Expr(expr));
+                        }
+                    }
                     self.parse_attribute_statement()
                 }
                 Token::Keyword(k) if k == "let" => {
@@ -345,7 +740,12 @@ impl<'a> Parser<'a> {
                 Token::Keyword(k) if k == "fn" => self.parse_function_decl(false, false),
                 Token::Keyword(k) if k == "teach" => {
                     self.next()?; // consume 'teach'
-                    self.parse_function_decl(false, true)
+                    match self.peek()? {
+                        Some(Token::Keyword(k)) if k == "learn" => {
+                            self.parse_import_statement(true)
+                        }
+                        _ => self.parse_function_decl(false, true),
+                    }
                 }
                 Token::Keyword(k) if k == "async" => {
                     self.next()?; // consume 'async'
@@ -356,38 +756,51 @@ impl<'a> Parser<'a> {
                             self.parse_function_decl(true, false)
                         } else {
                             // This is 'async <expr>' - put back the async token and parse as expression
-                            self.tokens.push_back(Token::Keyword("async".to_string()));
+                            self.push_back(Token::Keyword("async".to_string()));
                             let expr = self.parse_expression()?;
                             self.maybe_consume_semicolon();
-                            Ok(Stmt::Expr(expr))
+                            Ok(StmtKind::Expr(expr))
                         }
                     } else {
                         // This is 'async <expr>' - put back the async token and parse as expression
-                        self.tokens.push_back(Token::Keyword("async".to_string()));
+                        self.push_back(Token::Keyword("async".to_string()));
                         let expr = self.parse_expression()?;
                         self.maybe_consume_semicolon();
-                        Ok(Stmt::Expr(expr))
+                        Ok(StmtKind::Expr(expr))
                     }
                 }
                 Token::Keyword(k) if k == "def" => self.parse_struct_decl(),
                 Token::Keyword(k) if k == "enum" => self.parse_enum_decl(),
                 Token::Keyword(k) if k == "trait" => self.parse_trait_decl(),
                 Token::Keyword(k) if k == "impl" => self.parse_impl_block(),
-                Token::Keyword(k) if k == "learn" => self.parse_import_statement(),
+                Token::Keyword(k) if k == "learn" => self.parse_import_statement(false),
                 Token::Keyword(k) if k == "if" => self.parse_if_statement(),
                 Token::Keyword(k) if k == "while" => self.parse_while_statement(),
                 Token::Keyword(k) if k == "for" => self.parse_for_statement(),
                 Token::Keyword(k) if k == "match" => self.parse_match_statement(),
                 Token::Keyword(k) if k == "return" => self.parse_return_statement(),
+                Token::Keyword(k) if k == "try" => self.parse_try_statement(),
+                Token::Keyword(k) if k == "throw" => {
+                    self.next()?;
+                    let value = self.parse_expression()?;
+                    self.maybe_consume_semicolon();
+                    Ok(StmtKind::Throw(value))
+                }
+                Token::Keyword(k) if k == "defer" => {
+                    self.next()?;
+                    let deferred = self.parse_expression()?;
+                    self.maybe_consume_semicolon();
+                    Ok(StmtKind::Defer(deferred))
+                }
                 Token::Keyword(k) if k == "break" => {
                     self.next()?;
                     self.maybe_consume_semicolon();
-                    Ok(Stmt::Break)
+                    Ok(StmtKind::Break)
                 }
                 Token::Keyword(k) if k == "continue" => {
                     self.next()?;
                     self.maybe_consume_semicolon();
-                    Ok(Stmt::Continue)
+                    Ok(StmtKind::Continue)
                 }
                 Token::Punct(p) if p == "{" => self.parse_block_statement(),
                 _ => {
@@ -398,20 +811,20 @@ impl<'a> Parser<'a> {
                         self.next()?; // consume the identifier
 
                         // Start building the expression, handle field access and postfixes
-                        let mut left = Expr::Ident(name_clone);
+                        let mut left = Expr::new(ExprKind::Ident(name_clone), self.last_span);
                         left = self.parse_postfix(left)?;
 
                         // Now check if the next token is an assignment operator
                         if let Some(Token::Op(op)) = self.peek()? {
                             if op == "=" {
                                 // This is an assignment to a field or variable
-                                // For now, we only support assignment to a simple variable in Stmt::Assign
+                                // For now, we only support assignment to a simple variable in StmtKind::Assign
                                 // If it's a field access, we might need a different Stmt variant
-                                if let Expr::Ident(var_name) = left {
+                                if let ExprKind::Ident(var_name) = left.kind {
                                     self.next()?; // consume '='
                                     let value = self.parse_expression()?;
                                     self.maybe_consume_semicolon();
-                                    return Ok(Stmt::Assign {
+                                    return Ok(StmtKind::Assign {
                                         name: var_name,
                                         value,
                                     });
@@ -422,15 +835,19 @@ impl<'a> Parser<'a> {
                                     self.next()?; // consume '='
                                     let value = self.parse_expression()?;
                                     self.maybe_consume_semicolon();
-                                    // We need to return something, if we don't have Stmt::AssignField
+                                    // We need to return something, if we don't have StmtKind::AssignField
                                     // we can just treat it as Expr(BinOp(left, "=", value)) if we want
                                     // but let's stick to what we have or add more if needed.
                                     // For now, let's treat it as an expression statement.
-                                    return Ok(Stmt::Expr(Expr::BinOp {
-                                        op: "=".to_string(),
-                                        left: Box::new(left),
-                                        right: Box::new(value),
-                                    }));
+                                    let span = left.span.to(value.span);
+                                    return Ok(StmtKind::Expr(Expr::new(
+                                        ExprKind::BinOp {
+                                            op: "=".to_string(),
+                                            left: Box::new(left),
+                                            right: Box::new(value),
+                                        },
+                                        span,
+                                    )));
                                 }
                             }
                         }
@@ -439,13 +856,13 @@ impl<'a> Parser<'a> {
                         // We just need to handle it as an expression statement
                         let expr = self.parse_binary_expr_with_left(left, 0)?;
                         self.maybe_consume_semicolon();
-                        return Ok(Stmt::Expr(expr));
+                        return Ok(StmtKind::Expr(expr));
                     }
 
                     // Parse as expression (token will be consumed by parse_expression)
                     let expr = self.parse_expression()?;
                     self.maybe_consume_semicolon();
-                    Ok(Stmt::Expr(expr))
+                    Ok(StmtKind::Expr(expr))
                 }
             }
         } else {
@@ -455,7 +872,7 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn parse_attribute_statement(&mut self) -> Result<Stmt> {
+    fn parse_attribute_statement(&mut self) -> Result<StmtKind> {
         self.expect_punct("[")?;
 
         let name = match self.next()? {
@@ -508,13 +925,13 @@ impl<'a> Parser<'a> {
 
         self.expect_punct("]")?;
         let stmt = self.parse_statement()?;
-        Ok(Stmt::AttrStmt {
+        Ok(StmtKind::AttrStmt {
             attr: Attribute { name, args },
             stmt: Box::new(stmt),
         })
     }
 
-    fn parse_var_decl_after_keyword(&mut self, mutable: bool) -> Result<Stmt> {
+    fn parse_var_decl_after_keyword(&mut self, mutable: bool) -> Result<StmtKind> {
         let name_token = self.next()?;
         let name = match name_token {
             Some(Token::Ident(name)) => name,
@@ -554,7 +971,7 @@ impl<'a> Parser<'a> {
         };
 
         self.maybe_consume_semicolon();
-        Ok(Stmt::VarDecl {
+        Ok(StmtKind::VarDecl {
             name,
             var_type,
             mutable,
@@ -562,7 +979,7 @@ impl<'a> Parser<'a> {
         })
     }
 
-    fn parse_const_decl(&mut self) -> Result<Stmt> {
+    fn parse_const_decl(&mut self) -> Result<StmtKind> {
         self.expect_keyword("const")?;
 
         let name_token = self.next()?;
@@ -596,7 +1013,7 @@ impl<'a> Parser<'a> {
         let value = self.parse_expression()?;
 
         self.maybe_consume_semicolon();
-        Ok(Stmt::ConstDecl {
+        Ok(StmtKind::ConstDecl {
             name,
             const_type,
             value,
@@ -607,6 +1024,7 @@ impl<'a> Parser<'a> {
         let name_token = self.next()?;
         let name = match name_token {
             Some(Token::Ident(name)) => name,
+            Some(Token::Keyword(k)) if k == "fn" => return self.parse_function_type(),
             Some(token) => {
                 let error_msg = format!("Expected type name but got {}", token);
                 return Err(self.tokens.croak(error_msg, None));
@@ -655,7 +1073,37 @@ impl<'a> Parser<'a> {
         Ok(Type::Named(name))
     }
 
-    fn parse_if_statement(&mut self) -> Result<Stmt> {
+    /// Parse the rest of a function type such as `fn(num, str) -> bool`, after `fn`
+    fn parse_function_type(&mut self) -> Result<Type> {
+        self.expect_punct("(")?;
+        let mut params = Vec::new();
+        while let Some(token) = self.peek()? {
+            if self.is_punct(&token, ")") {
+                break;
+            }
+            params.push(self.parse_type()?);
+            if let Some(token) = self.peek()? {
+                if self.is_punct(&token, ",") {
+                    self.next()?; // consume ','
+                }
+            }
+        }
+        self.expect_punct(")")?;
+
+        let return_type = match self.peek()? {
+            Some(Token::Op(op)) if op == "->" => {
+                self.next()?; // consume '->'
+                self.parse_type()?
+            }
+            _ => Type::Named("void".to_string()),
+        };
+        Ok(Type::Function {
+            params,
+            return_type: Box::new(return_type),
+        })
+    }
+
+    fn parse_if_statement(&mut self) -> Result<StmtKind> {
         self.expect_keyword("if")?;
         self.expect_punct("(")?;
         let condition = self.parse_expression()?;
@@ -674,24 +1122,24 @@ impl<'a> Parser<'a> {
             None
         };
 
-        Ok(Stmt::If {
+        Ok(StmtKind::If {
             condition,
             then_branch,
             else_branch,
         })
     }
 
-    fn parse_while_statement(&mut self) -> Result<Stmt> {
+    fn parse_while_statement(&mut self) -> Result<StmtKind> {
         self.expect_keyword("while")?;
         self.expect_punct("(")?;
         let condition = self.parse_expression()?;
         self.expect_punct(")")?;
         let body = Box::new(self.parse_statement()?);
 
-        Ok(Stmt::While { condition, body })
+        Ok(StmtKind::While { condition, body })
     }
 
-    fn parse_return_statement(&mut self) -> Result<Stmt> {
+    fn parse_return_statement(&mut self) -> Result<StmtKind> {
         self.expect_keyword("return")?;
 
         // Check if there's an expression to return
@@ -706,24 +1154,134 @@ impl<'a> Parser<'a> {
         };
 
         self.maybe_consume_semicolon();
-        Ok(Stmt::Return(value))
+        Ok(StmtKind::Return(value))
     }
 
-    fn parse_import_statement(&mut self) -> Result<Stmt> {
+    fn parse_try_statement(&mut self) -> Result<StmtKind> {
+        self.expect_keyword("try")?;
+        let body = Box::new(self.spanned_stmt(Self::parse_block_statement)?);
+
+        let catch = match self.peek()? {
+            Some(token) if self.is_keyword(&token, "catch") => {
+                self.next()?;
+                // The error binding is optional: `catch { ... }`
+                let var = match self.peek()? {
+                    Some(token) if self.is_punct(&token, "(") => {
+                        self.next()?;
+                        let var = match self.next()? {
+                            Some(Token::Ident(name)) => name,
+                            _ => {
+                                return Err(self
+                                    .tokens
+                                    .croak("Expected error variable name".to_string(), None))
+                            }
+                        };
+                        self.expect_punct(")")?;
+                        Some(var)
+                    }
+                    _ => None,
+                };
+                let body = Box::new(self.spanned_stmt(Self::parse_block_statement)?);
+                Some((var, body))
+            }
+            _ => None,
+        };
+
+        let finally = match self.peek()? {
+            Some(token) if self.is_keyword(&token, "finally") => {
+                self.next()?;
+                Some(Box::new(self.spanned_stmt(Self::parse_block_statement)?))
+            }
+            _ => None,
+        };
+
+        if catch.is_none() && finally.is_none() {
+            return Err(self.tokens.croak(
+                "Expected 'catch' or 'finally' after try block".to_string(),
+                None,
+            ));
+        }
+
+        Ok(StmtKind::Try {
+            body,
+            catch,
+            finally,
+        })
+    }
+
+    fn parse_import_statement(&mut self, is_exported: bool) -> Result<StmtKind> {
         self.expect_keyword("learn")?;
 
-        let module_token = self.next()?;
-        let module_str = match module_token {
+        // Selective import: learn { a, b as c } from "module"
+        let items = match self.peek()? {
+            Some(Token::Punct(p)) if p == "{" => {
+                self.next()?; // consume '{'
+                let mut items = Vec::new();
+                loop {
+                    match self.next()? {
+                        Some(Token::Punct(p)) if p == "}" => break,
+                        Some(Token::Ident(name)) => {
+                            let alias = if self.peek_contextual_keyword("as")? {
+                                self.next()?; // consume 'as'
+                                Some(self.expect_import_name("'as'")?)
+                            } else {
+                                None
+                            };
+                            items.push(ImportItem { name, alias });
+                        }
+                        Some(token) => {
+                            return Err(self.tokens.croak(
+                                format!("Expected a name to import but got {}", token),
+                                None,
+                            ))
+                        }
+                        None => {
+                            return Err(self
+                                .tokens
+                                .croak("Unexpected EOF in import list".to_string(), None))
+                        }
+                    }
+                    if let Some(token) = self.peek()? {
+                        if self.is_punct(&token, ",") {
+                            self.next()?; // consume ','
+                        }
+                    }
+                }
+                if !self.peek_contextual_keyword("from")? {
+                    return Err(self
+                        .tokens
+                        .croak("Expected 'from' after import list".to_string(), None));
+                }
+                self.next()?; // consume 'from'
+                Some(items)
+            }
+            _ => None,
+        };
+
+        let module_str = match self.next()? {
             Some(Token::String(s)) => s,
+            // A bare module name, optionally with `::` separators
+            Some(Token::Ident(first)) => {
+                let mut module = first;
+                while let Some(Token::Punct(p)) = self.peek()? {
+                    if p != "::" {
+                        break;
+                    }
+                    self.next()?; // consume '::'
+                    module.push_str("::");
+                    module.push_str(&self.expect_import_name("'::'")?);
+                }
+                module
+            }
             Some(token) => {
                 return Err(self.tokens.croak(
-                    format!("Expected string literal after 'learn' but got {}", token),
+                    format!("Expected module name after 'learn' but got {}", token),
                     None,
                 ))
             }
             None => {
                 return Err(self.tokens.croak(
-                    "Expected string literal after 'learn' but got EOF".to_string(),
+                    "Expected module name after 'learn' but got EOF".to_string(),
                     None,
                 ))
             }
@@ -738,11 +1296,42 @@ impl<'a> Parser<'a> {
                 .croak("Import path cannot be empty".to_string(), None));
         }
 
+        let alias = if items.is_none() && self.peek_contextual_keyword("as")? {
+            self.next()?; // consume 'as'
+            Some(self.expect_import_name("'as'")?)
+        } else {
+            None
+        };
+
         self.maybe_consume_semicolon();
-        Ok(Stmt::ImportDecl { path })
+        Ok(StmtKind::ImportDecl {
+            path,
+            alias,
+            items,
+            is_exported,
+        })
     }
 
-    fn parse_function_decl(&mut self, is_async: bool, is_exported: bool) -> Result<Stmt> {
+    /// Whether the next token is the identifier `word`, used for words like
+    /// `as` and `from` that are only keywords inside an import
+    fn peek_contextual_keyword(&mut self, word: &str) -> Result<bool> {
+        Ok(matches!(self.peek()?, Some(Token::Ident(name)) if name == word))
+    }
+
+    fn expect_import_name(&mut self, after: &str) -> Result<String> {
+        match self.next()? {
+            Some(Token::Ident(name)) => Ok(name),
+            Some(token) => Err(self.tokens.croak(
+                format!("Expected a name after {} but got {}", after, token),
+                None,
+            )),
+            None => Err(self
+                .tokens
+                .croak(format!("Expected a name after {} but got EOF", after), None)),
+        }
+    }
+
+    fn parse_function_decl(&mut self, is_async: bool, is_exported: bool) -> Result<StmtKind> {
         self.expect_keyword("fn")?;
 
         let name_token = self.next()?;
@@ -760,46 +1349,27 @@ impl<'a> Parser<'a> {
             }
         };
 
-        // Parse optional type parameters
-        let mut type_params = Vec::new();
-        if let Some(Token::Op(op)) = self.peek()? {
-            if op == "<" {
-                self.next()?; // consume '<'
-                loop {
-                    let param_token = self.next()?;
-                    if let Some(Token::Ident(param)) = param_token {
-                        type_params.push(param);
-                    } else {
-                        return Err(self
-                            .tokens
-                            .croak("Expected type parameter name".to_string(), None));
-                    }
-
-                    if let Some(token) = self.peek()? {
-                        if self.is_punct(&token, ",") {
-                            self.next()?; // consume ','
-                        } else if self.is_op(&token, ">") {
-                            self.next()?; // consume '>'
-                            break;
-                        } else {
-                            return Err(self.tokens.croak(
-                                "Expected ',' or '>' in type parameters".to_string(),
-                                None,
-                            ));
-                        }
-                    }
-                }
-            }
-        }
+        let type_params = self.parse_type_params()?;
 
         // Parse parameters
         self.expect_punct("(")?;
         let mut params = Vec::new();
+        let mut variadic = false;
 
         while let Some(token) = self.peek()? {
             if self.is_punct(&token, ")") {
                 break;
             }
+            if variadic {
+                return Err(self.tokens.croak(
+                    "A rest parameter must be the last parameter".to_string(),
+                    None,
+                ));
+            }
+            if matches!(&token, Token::Op(op) if op == "...") {
+                self.next()?; // consume '...'
+                variadic = true;
+            }
 
             let param_name = match self.next()? {
                 Some(Token::Ident(name)) => name,
@@ -861,12 +1431,13 @@ impl<'a> Parser<'a> {
         };
 
         // Parse body
-        let body = Box::new(self.parse_block_statement()?);
+        let body = Box::new(self.spanned_stmt(Self::parse_block_statement)?);
 
-        Ok(Stmt::FunctionDecl {
+        Ok(StmtKind::FunctionDecl {
             name,
             type_params,
             params,
+            variadic,
             return_type,
             body,
             is_async,
@@ -874,7 +1445,42 @@ impl<'a> Parser<'a> {
         })
     }
 
-    fn parse_struct_decl(&mut self) -> Result<Stmt> {
+    /// Parse optional generic parameters such as `<T, U>` after a function or struct name
+    fn parse_type_params(&mut self) -> Result<Vec<String>> {
+        let mut type_params = Vec::new();
+        if let Some(Token::Op(op)) = self.peek()? {
+            if op == "<" {
+                self.next()?; // consume '<'
+                loop {
+                    let param_token = self.next()?;
+                    if let Some(Token::Ident(param)) = param_token {
+                        type_params.push(param);
+                    } else {
+                        return Err(self
+                            .tokens
+                            .croak("Expected type parameter name".to_string(), None));
+                    }
+
+                    if let Some(token) = self.peek()? {
+                        if self.is_punct(&token, ",") {
+                            self.next()?; // consume ','
+                        } else if self.is_op(&token, ">") {
+                            self.next()?; // consume '>'
+                            break;
+                        } else {
+                            return Err(self.tokens.croak(
+                                "Expected ',' or '>' in type parameters".to_string(),
+                                None,
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(type_params)
+    }
+
+    fn parse_struct_decl(&mut self) -> Result<StmtKind> {
         self.expect_keyword("def")?;
 
         let name_token = self.next()?;
@@ -892,6 +1498,7 @@ impl<'a> Parser<'a> {
             }
         };
 
+        let type_params = self.parse_type_params()?;
         self.expect_punct("{")?;
         let mut fields = Vec::new();
 
@@ -928,10 +1535,14 @@ impl<'a> Parser<'a> {
 
         self.expect_punct("}")?;
 
-        Ok(Stmt::StructDecl { name, fields })
+        Ok(StmtKind::StructDecl {
+            name,
+            type_params,
+            fields,
+        })
     }
 
-    fn parse_enum_decl(&mut self) -> Result<Stmt> {
+    fn parse_enum_decl(&mut self) -> Result<StmtKind> {
         self.expect_keyword("enum")?;
 
         let name_token = self.next()?;
@@ -1011,10 +1622,10 @@ impl<'a> Parser<'a> {
 
         self.expect_punct("}")?;
 
-        Ok(Stmt::EnumDecl { name, variants })
+        Ok(StmtKind::EnumDecl { name, variants })
     }
 
-    fn parse_trait_decl(&mut self) -> Result<Stmt> {
+    fn parse_trait_decl(&mut self) -> Result<StmtKind> {
         self.expect_keyword("trait")?;
 
         let name_token = self.next()?;
@@ -1084,12 +1695,12 @@ impl<'a> Parser<'a> {
                         self.next()?; // consume ':'
                         self.parse_type()?
                     } else {
-                        // Implicit type for 'self'
-                        Type::Named(param_name.clone())
+                        // Implicit type for 'self', as in methods of impl blocks
+                        Type::Named("Self".to_string())
                     }
                 } else {
                     // No type annotation, assume it's 'self' type
-                    Type::Named(param_name.clone())
+                    Type::Named("Self".to_string())
                 };
 
                 params.push((param_name, param_type));
@@ -1117,7 +1728,7 @@ impl<'a> Parser<'a> {
                     });
                 } else if self.is_punct(&token, "{") {
                     // Default implementation
-                    let body = Box::new(self.parse_block_statement()?);
+                    let body = Box::new(self.spanned_stmt(Self::parse_block_statement)?);
                     methods.push(TraitMethod::Default {
                         name: method_name,
                         params,
@@ -1140,10 +1751,10 @@ impl<'a> Parser<'a> {
 
         self.expect_punct("}")?;
 
-        Ok(Stmt::TraitDecl { name, methods })
+        Ok(StmtKind::TraitDecl { name, methods })
     }
 
-    fn parse_impl_block(&mut self) -> Result<Stmt> {
+    fn parse_impl_block(&mut self) -> Result<StmtKind> {
         self.expect_keyword("impl")?;
 
         // Check if implementing a trait
@@ -1185,19 +1796,19 @@ impl<'a> Parser<'a> {
                 break;
             }
 
-            methods.push(self.parse_function_decl(false, false)?);
+            methods.push(self.spanned_stmt(|p| p.parse_function_decl(false, false))?);
         }
 
         self.expect_punct("}")?;
 
-        Ok(Stmt::ImplBlock {
+        Ok(StmtKind::ImplBlock {
             type_name,
             trait_name,
             methods,
         })
     }
 
-    fn parse_for_statement(&mut self) -> Result<Stmt> {
+    fn parse_for_statement(&mut self) -> Result<StmtKind> {
         self.expect_keyword("for")?;
 
         let var = match self.next()? {
@@ -1214,16 +1825,16 @@ impl<'a> Parser<'a> {
         // Parse the iterable, preventing it from consuming the { as a struct literal
         let iterable = self.parse_match_subject()?;
 
-        let body = Box::new(self.parse_block_statement()?);
+        let body = Box::new(self.spanned_stmt(Self::parse_block_statement)?);
 
-        Ok(Stmt::For {
+        Ok(StmtKind::For {
             var,
             iterable,
             body,
         })
     }
 
-    fn parse_match_expr(&mut self) -> Result<Expr> {
+    fn parse_match_expr(&mut self) -> Result<ExprKind> {
         // 'match' keyword already consumed
         // Parse the match subject without allowing struct literal syntax
         // since the { for the match block would be confused with struct literal
@@ -1255,20 +1866,58 @@ impl<'a> Parser<'a> {
 
         self.expect_punct("}")?;
 
-        Ok(Expr::Match {
+        Ok(ExprKind::Match {
             expr: Box::new(expr),
             arms,
         })
     }
 
+    /// `if (cond) { a } else { b }` as an expression, whose value is the
+    /// value of the branch taken. Unlike the statement, both branches are blocks.
+    fn parse_if_expr(&mut self) -> Result<ExprKind> {
+        // 'if' keyword already consumed
+        self.expect_punct("(")?;
+        let condition = self.parse_expression()?;
+        self.expect_punct(")")?;
+
+        let then_start = self.peek_span()?;
+        let then_stmts = self.parse_block()?;
+        let then_branch = Box::new(self.expr(ExprKind::Block(then_stmts), then_start));
+
+        let else_branch = match self.peek()? {
+            Some(token) if self.is_keyword(&token, "else") => {
+                self.next()?; // consume 'else'
+                let else_start = self.peek_span()?;
+                let kind = match self.peek()? {
+                    Some(token) if self.is_keyword(&token, "if") => {
+                        self.next()?; // consume 'if'
+                        self.parse_if_expr()?
+                    }
+                    _ => ExprKind::Block(self.parse_block()?),
+                };
+                Some(Box::new(self.expr(kind, else_start)))
+            }
+            _ => None,
+        };
+
+        Ok(ExprKind::If {
+            condition: Box::new(condition),
+            then_branch,
+            else_branch,
+        })
+    }
+
     // Parse match subject expression without struct literal postfix
     fn parse_match_subject(&mut self) -> Result<Expr> {
         let mut left = self.parse_primary_expr()?;
+        let start = left.span;
+        let links = self.links;
 
         // Handle postfix operations but NOT struct literals
         while let Some(token) = self.peek()? {
             match token {
                 Token::Punct(ref p) if p == "(" => {
+                    self.chain_link()?;
                         // Function call
                         self.next()?; // consume '('
                         let mut args = Vec::new();
@@ -1277,7 +1926,7 @@ impl<'a> Parser<'a> {
                             if self.is_punct(&token, ")") {
                                 break;
                             }
-                            args.push(self.parse_expression()?);
+                            args.push(self.nested(Self::parse_element)?);
                             if let Some(token) = self.peek()? {
                                 if self.is_punct(&token, ",") {
                                     self.next()?; // consume ','
@@ -1285,42 +1934,47 @@ impl<'a> Parser<'a> {
                             }
                         }
 
-                        self.expect_punct(")")?;
-                        left = Expr::Call {
+                    self.expect_punct(")")?;
+                    left = self.expr(
+                        ExprKind::Call {
                             func: Box::new(left),
                             args,
-                        };
-                    }
-                    Token::Op(ref p) if p == "." => {
-                        // Field access
-                        self.next()?; // consume '.'
-                        let field_token = self.next()?;
-                        let field = match field_token {
-                            Some(Token::Ident(name)) => name,
-                            _ => {
-                                return Err(self
-                                    .tokens
-                                    .croak("Expected field name after '.'".to_string(), None))
-                            }
-                        };
-                        left = Expr::FieldAccess {
+                        },
+                        start,
+                    );
+                }
+                Token::Op(ref p) if p == "." => {
+                    self.chain_link()?;
+                    // Field access
+                    self.next()?; // consume '.'
+                    let field_token = self.next()?;
+                    let field = match field_token {
+                        Some(Token::Ident(name)) => name,
+                        _ => {
+                            return Err(self
+                                .tokens
+                                .croak("Expected field name after '.'".to_string(), None))
+                        }
+                    };
+                    left = self.expr(
+                        ExprKind::FieldAccess {
                             object: Box::new(left),
                             field,
-                        };
-                    }
-                    Token::Punct(ref p) if p == "[" => {
-                        // Array index
-                        self.next()?; // consume '['
-                        let index = self.parse_expression()?;
-                        self.expect_punct("]")?;
-                        left = Expr::Index {
-                            array: Box::new(left),
-                            index: Box::new(index),
-                        };
-                    }
-                    _ => break,
+                        },
+                        start,
+                    );
+                }
+                Token::Punct(ref p) if p == "[" => {
+                    self.chain_link()?;
+                    // Array index
+                    self.next()?; // consume '['
+                    let kind = self.nested(|parser| parser.parse_index(left))?;
+                    left = self.expr(kind, start);
                 }
+                _ => break,
+            }
         }
+        self.links = links;
 
         // Now handle binary operations
         self.parse_binary_expr_with_left(left, 0)
@@ -1329,6 +1983,7 @@ impl<'a> Parser<'a> {
     // Parse a pattern expression (similar to primary expr but without struct literal parsing)
     fn parse_pattern_expr(&mut self) -> Result<Expr> {
         let mut expr = self.parse_pattern_primary()?;
+        let start = expr.span;
 
         // Handle postfix operations but don't interpret { as struct literal
         while let Some(token) = self.peek()? {
@@ -1350,31 +2005,37 @@ impl<'a> Parser<'a> {
                             }
                         }
 
-                        self.expect_punct(")")?;
-                        expr = Expr::Call {
+                    self.expect_punct(")")?;
+                    expr = self.expr(
+                        ExprKind::Call {
                             func: Box::new(expr),
                             args,
-                        };
-                    }
-                    Token::Op(ref p) if p == "." => {
-                        // Field access
-                        self.next()?; // consume '.'
-                        let field_token = self.next()?;
-                        let field = match field_token {
-                            Some(Token::Ident(name)) => name,
-                            _ => {
-                                return Err(self
-                                    .tokens
-                                    .croak("Expected field name after '.'".to_string(), None))
-                            }
-                        };
-                        expr = Expr::FieldAccess {
+                        },
+                        start,
+                    );
+                }
+                Token::Op(ref p) if p == "." => {
+                    // Field access
+                    self.next()?; // consume '.'
+                    let field_token = self.next()?;
+                    let field = match field_token {
+                        Some(Token::Ident(name)) => name,
+                        _ => {
+                            return Err(self
+                                .tokens
+                                .croak("Expected field name after '.'".to_string(), None))
+                        }
+                    };
+                    expr = self.expr(
+                        ExprKind::FieldAccess {
                             object: Box::new(expr),
                             field,
-                        };
-                    }
-                    _ => break,
+                        },
+                        start,
+                    );
                 }
+                _ => break,
+            }
         }
 
         Ok(expr)
@@ -1382,12 +2043,18 @@ impl<'a> Parser<'a> {
 
     fn parse_pattern_primary(&mut self) -> Result<Expr> {
         let token_opt = self.next()?;
+        let start = self.last_span;
         match token_opt {
-            Some(Token::Number(n)) => Ok(Expr::Number(n)),
-            Some(Token::String(s)) => Ok(Expr::String(s)),
-            Some(Token::Keyword(k)) if k == "true" => Ok(Expr::Boolean(true)),
-            Some(Token::Keyword(k)) if k == "false" => Ok(Expr::Boolean(false)),
-            Some(Token::Ident(name)) => Ok(Expr::Ident(name)),
+            Some(Token::Number(n)) => Ok(self.expr(ExprKind::Number(n), start)),
+            Some(Token::String(s)) => {
+                let s = self.intern(s);
+                Ok(self.expr(ExprKind::String(s), start))
+            }
+            Some(Token::Keyword(k)) if k == "true" => Ok(self.expr(ExprKind::Boolean(true), start)),
+            Some(Token::Keyword(k)) if k == "false" => {
+                Ok(self.expr(ExprKind::Boolean(false), start))
+            }
+            Some(Token::Ident(name)) => Ok(self.expr(ExprKind::Ident(name), start)),
             Some(Token::Punct(p)) if p == "(" => {
                 let expr = self.parse_pattern_expr()?;
                 self.expect_punct(")")?;
@@ -1402,7 +2069,7 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn parse_match_statement(&mut self) -> Result<Stmt> {
+    fn parse_match_statement(&mut self) -> Result<StmtKind> {
         self.expect_keyword("match")?;
 
         let expr = self.parse_match_subject()?;
@@ -1430,12 +2097,12 @@ impl<'a> Parser<'a> {
 
         self.expect_punct("}")?;
 
-        Ok(Stmt::Match { expr, arms })
+        Ok(StmtKind::Match { expr, arms })
     }
 
-    fn parse_block_statement(&mut self) -> Result<Stmt> {
+    fn parse_block_statement(&mut self) -> Result<StmtKind> {
         let statements = self.parse_block()?;
-        Ok(Stmt::Block(statements))
+        Ok(StmtKind::Block(statements))
     }
 
     fn parse_block(&mut self) -> Result<Vec<Stmt>> {
@@ -1459,6 +2126,7 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_binary_expr(&mut self, min_prec: u8) -> Result<Expr> {
+        let links = self.links;
         let mut left = self.parse_primary_expr()?;
 
         // Handle postfix operations first (highest precedence)
@@ -1472,22 +2140,29 @@ impl<'a> Parser<'a> {
                 }
 
                 self.next()?; // consume operator
+                self.chain_link()?;
                 let right = self.parse_binary_expr(prec + 1)?;
-                left = Expr::BinOp {
-                    op,
-                    left: Box::new(left),
-                    right: Box::new(right),
-                };
+                let span = left.span.to(right.span);
+                left = Expr::new(
+                    ExprKind::BinOp {
+                        op,
+                        left: Box::new(left),
+                        right: Box::new(right),
+                    },
+                    span,
+                );
             } else {
                 break;
             }
         }
 
+        self.links = links;
         Ok(left)
     }
 
     // Parse binary expressions without postfix operations (for array elements, function args, etc.)
     fn parse_binary_expr_with_left(&mut self, mut left: Expr, min_prec: u8) -> Result<Expr> {
+        let links = self.links;
         while let Some(token) = self.peek()? {
             if let Token::Op(op) = token {
                 let prec = self.get_precedence(&op);
@@ -1496,58 +2171,92 @@ impl<'a> Parser<'a> {
                 }
 
                 self.next()?; // consume operator
-                              // For the right side, parse primary + postfix + binary
+                self.chain_link()?;
+                // For the right side, parse primary + postfix + binary
                 let right_primary = self.parse_primary_expr()?;
                 let right_postfix = self.parse_postfix(right_primary)?;
                 let right = self.parse_binary_expr_with_left(right_postfix, prec + 1)?;
-                left = Expr::BinOp {
-                    op,
-                    left: Box::new(left),
-                    right: Box::new(right),
-                };
+                let span = left.span.to(right.span);
+                left = Expr::new(
+                    ExprKind::BinOp {
+                        op,
+                        left: Box::new(left),
+                        right: Box::new(right),
+                    },
+                    span,
+                );
             } else {
                 break;
             }
         }
 
+        self.links = links;
         Ok(left)
     }
 
     fn parse_primary_expr(&mut self) -> Result<Expr> {
+        self.nested(Self::parse_primary)
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr> {
         let token_opt = self.next()?;
+        let start = self.last_span;
         match token_opt {
-            Some(Token::Number(n)) => Ok(Expr::Number(n)),
-            Some(Token::String(s)) => Ok(Expr::String(s)),
+            Some(Token::Number(n)) => Ok(self.expr(ExprKind::Number(n), start)),
+            Some(Token::String(s)) => {
+                let s = self.intern(s);
+                Ok(self.expr(ExprKind::String(s), start))
+            }
             Some(Token::TemplateStart) => {
                 // Parse template literal
-                self.parse_template_literal()
+                let kind = self.parse_template_literal()?;
+                Ok(self.expr(kind, start))
+            }
+            Some(Token::Keyword(k)) if k == "true" => Ok(self.expr(ExprKind::Boolean(true), start)),
+            Some(Token::Keyword(k)) if k == "false" => {
+                Ok(self.expr(ExprKind::Boolean(false), start))
             }
-            Some(Token::Keyword(k)) if k == "true" => Ok(Expr::Boolean(true)),
-            Some(Token::Keyword(k)) if k == "false" => Ok(Expr::Boolean(false)),
             Some(Token::Keyword(k)) if k == "await" => {
                 // Parse await expression: await <expr>
                 // We need to parse the primary expression and then handle postfix operations
                 let expr = self.parse_primary_expr()?;
                 let expr_with_postfix = self.parse_postfix(expr)?;
-                Ok(Expr::Await(Box::new(expr_with_postfix)))
+                Ok(self.expr(ExprKind::Await(Box::new(expr_with_postfix)), start))
             }
             Some(Token::Keyword(k)) if k == "async" => {
                 // Parse async expression: async <expr>
                 // This creates an eagerly-started async expression
                 let expr = self.parse_primary_expr()?;
                 let expr_with_postfix = self.parse_postfix(expr)?;
-                Ok(Expr::Async(Box::new(expr_with_postfix)))
+                Ok(self.expr(ExprKind::Async(Box::new(expr_with_postfix)), start))
             }
             Some(Token::Keyword(k)) if k == "lazy" => {
                 // Parse lazy expression: lazy <expr>
                 // This creates a lazily-evaluated async expression
                 let expr = self.parse_primary_expr()?;
                 let expr_with_postfix = self.parse_postfix(expr)?;
-                Ok(Expr::Lazy(Box::new(expr_with_postfix)))
+                Ok(self.expr(ExprKind::Lazy(Box::new(expr_with_postfix)), start))
             }
             Some(Token::Keyword(k)) if k == "match" => {
                 // Parse match expression
-                self.parse_match_expr()
+                let kind = self.parse_match_expr()?;
+                Ok(self.expr(kind, start))
+            }
+            Some(Token::Keyword(k)) if k == "if" => {
+                let kind = self.parse_if_expr()?;
+                Ok(self.expr(kind, start))
+            }
+            Some(Token::Op(op)) if op == "-" => {
+                // Negation binds tighter than any binary operator: -x * 2 is (-x) * 2
+                let operand = self.parse_primary_expr()?;
+                let operand = self.parse_postfix(operand)?;
+                Ok(self.expr(
+                    ExprKind::UnaryOp {
+                        op,
+                        expr: Box::new(operand),
+                    },
+                    start,
+                ))
             }
             Some(Token::Ident(name)) => {
                 // Check if this is a lambda expression (v => ...)
@@ -1555,11 +2264,12 @@ impl<'a> Parser<'a> {
                     if op == "=>" {
                         self.next()?; // consume '=>'
                                       // This is a lambda: v => body
-                        return self.parse_lambda_body(vec![(name, None)], None);
+                        let kind = self.parse_lambda_body(vec![(name, None)], None)?;
+                        return Ok(self.expr(kind, start));
                     }
                 }
                 // Return identifier, postfix parsing will be handled by parse_binary_expr
-                Ok(Expr::Ident(name))
+                Ok(self.expr(ExprKind::Ident(name), start))
             }
             Some(Token::Punct(p)) if p == "(" => {
                 // Could be: (expr), (params) => body, or function call
@@ -1567,7 +2277,8 @@ impl<'a> Parser<'a> {
                 let is_lambda = self.is_lambda_params()?;
 
                 if is_lambda {
-                    self.parse_lambda_with_parens()
+                    let kind = self.parse_lambda_with_parens()?;
+                    Ok(self.expr(kind, start))
                 } else {
                     let expr = self.parse_expression()?;
                     self.expect_punct(")")?;
@@ -1584,7 +2295,7 @@ impl<'a> Parser<'a> {
                     }
 
                     // Use parse_expression to correctly handle postfix operations like struct literals
-                    elements.push(self.parse_expression()?);
+                    elements.push(self.parse_element()?);
 
                     if let Some(token) = self.peek()? {
                         if self.is_punct(&token, ",") {
@@ -1594,13 +2305,18 @@ impl<'a> Parser<'a> {
                 }
 
                 self.expect_punct("]")?;
-                Ok(Expr::ArrayLiteral(elements))
+                Ok(self.expr(ExprKind::ArrayLiteral(elements), start))
+            }
+            Some(Token::Punct(p)) if p == "#" => {
+                // Map literal: #{ key: value, ... }
+                let kind = self.parse_map_literal()?;
+                Ok(self.expr(kind, start))
             }
             Some(Token::Punct(p)) if p == "{" => {
                 // Put back the '{' token for block parsing
-                self.tokens.push_back(Token::Punct(p));
+                self.push_back(Token::Punct(p));
                 let statements = self.parse_block()?;
-                Ok(Expr::Block(statements))
+                Ok(self.expr(ExprKind::Block(statements), start))
             }
             Some(token) => {
                 let error_msg = format!("Unexpected token in expression: {}", token);
@@ -1612,58 +2328,149 @@ impl<'a> Parser<'a> {
         }
     }
 
+    // Parse the entries of a map literal after the leading '#'
+    // Bare identifier keys are treated as string keys, like struct literal fields
+    fn parse_map_literal(&mut self) -> Result<ExprKind> {
+        self.expect_punct("{")?;
+        let mut entries = Vec::new();
+
+        while let Some(token) = self.peek()? {
+            if self.is_punct(&token, "}") {
+                break;
+            }
+
+            let key = self.parse_expression()?;
+            let key = match key.kind {
+                ExprKind::Ident(name) => Expr::new(ExprKind::String(self.intern(name)), key.span),
+                _ => key,
+            };
+            self.expect_punct(":")?;
+            let value = self.parse_expression()?;
+            entries.push((key, value));
+
+            if let Some(token) = self.peek()? {
+                if self.is_punct(&token, ",") {
+                    self.next()?; // consume ','
+                } else if !self.is_punct(&token, "}") {
+                    return Err(self
+                        .tokens
+                        .croak("Expected ',' or '}' in map literal".to_string(), None));
+                }
+            }
+        }
+
+        self.expect_punct("}")?;
+        Ok(ExprKind::MapLiteral(entries))
+    }
+
     // Parse postfix operations like function calls, field access, and indexing
     fn parse_postfix(&mut self, mut expr: Expr) -> Result<Expr> {
+        let start = expr.span;
+        let links = self.links;
         while let Some(token) = self.peek()? {
             match token {
-                    Token::Punct(ref p) if p == "(" => {
-                        expr = self.parse_call(expr)?;
-                    }
-                    Token::Op(ref op) if op == "." => {
-                        self.next()?; // consume '.'
-                        let field = match self.next()? {
-                            Some(Token::Ident(name)) => name,
-                            _ => {
-                                return Err(self
-                                    .tokens
-                                    .croak("Expected field name after '.'".to_string(), None))
-                            }
-                        };
-                        expr = Expr::FieldAccess {
+                Token::Punct(ref p) if p == "(" => {
+                    self.chain_link()?;
+                    expr = self.parse_call(expr)?;
+                }
+                Token::Op(ref op) if op == "." => {
+                    self.chain_link()?;
+                    self.next()?; // consume '.'
+                    let field = match self.next()? {
+                        Some(Token::Ident(name)) => name,
+                        _ => {
+                            return Err(self
+                                .tokens
+                                .croak("Expected field name after '.'".to_string(), None))
+                        }
+                    };
+                    expr = self.expr(
+                        ExprKind::FieldAccess {
                             object: Box::new(expr),
                             field,
-                        };
-                    }
-                    Token::Punct(ref p) if p == "[" => {
-                        self.next()?; // consume '['
-                        let index = self.parse_expression()?;
-                        self.expect_punct("]")?;
-                        expr = Expr::Index {
-                            array: Box::new(expr),
-                            index: Box::new(index),
-                        };
-                    }
-                    Token::Punct(ref p) if p == "{" => {
-                        // Check if this is a struct literal (identifier followed by {)
-                        if let Expr::Ident(name) = expr {
-                            expr = self.parse_struct_literal(name)?;
-                        } else {
-                            // Not a struct literal, break out
-                            break;
+                        },
+                        start,
+                    );
+                }
+                Token::Punct(ref p) if p == "::" => {
+                    self.chain_link()?;
+                    // `Point::new` is another way to write `Point.new`
+                    self.next()?; // consume '::'
+                    let field = match self.next()? {
+                        Some(Token::Ident(name)) => name,
+                        _ => {
+                            return Err(self
+                                .tokens
+                                .croak("Expected a name after '::'".to_string(), None))
                         }
+                    };
+                    expr = self.expr(
+                        ExprKind::FieldAccess {
+                            object: Box::new(expr),
+                            field,
+                        },
+                        start,
+                    );
+                }
+                Token::Punct(ref p) if p == "[" => {
+                    self.chain_link()?;
+                    self.next()?; // consume '['
+                    let kind = self.nested(|parser| parser.parse_index(expr))?;
+                    expr = self.expr(kind, start);
+                }
+                Token::Punct(ref p) if p == "{" => {
+                    // Check if this is a struct literal (identifier followed by {)
+                    if let ExprKind::Ident(name) = &expr.kind {
+                        self.chain_link()?;
+                        let name = name.clone();
+                        expr = self.nested(|parser| parser.parse_struct_literal(name, start))?;
+                    } else {
+                        // Not a struct literal, break out
+                        break;
                     }
-                    Token::Op(ref op) if op == "?" => {
-                        // Error propagation operator
-                        self.next()?; // consume '?'
-                        expr = Expr::Try(Box::new(expr));
-                    }
-                    _ => break,
                 }
+                Token::Op(ref op) if op == "?" => {
+                    self.chain_link()?;
+                    // Error propagation operator
+                    self.next()?; // consume '?'
+                    expr = self.expr(ExprKind::Try(Box::new(expr)), start);
+                }
+                _ => break,
+            }
         }
+        self.links = links;
         Ok(expr)
     }
 
-    fn parse_struct_literal(&mut self, name: String) -> Result<Expr> {
+    /// `[index]` or `[start..end]` after `array`, with the '[' consumed
+    fn parse_index(&mut self, array: Expr) -> Result<ExprKind> {
+        let array = Box::new(array);
+        let is_range = |parser: &mut Self| -> Result<bool> {
+            Ok(matches!(parser.peek()?, Some(Token::Op(op)) if op == ".."))
+        };
+
+        // Bounds stop before `..`, which binds looser than every operator
+        let start = if is_range(self)? {
+            None
+        } else {
+            Some(Box::new(self.parse_binary_expr(1)?))
+        };
+        let kind = match start {
+            Some(index) if !is_range(self)? => ExprKind::Index { array, index },
+            start => {
+                self.next()?; // consume '..'
+                let end = match self.peek()? {
+                    Some(token) if self.is_punct(&token, "]") => None,
+                    _ => Some(Box::new(self.parse_binary_expr(1)?)),
+                };
+                ExprKind::Slice { array, start, end }
+            }
+        };
+        self.expect_punct("]")?;
+        Ok(kind)
+    }
+
+    fn parse_struct_literal(&mut self, name: String, start: Span) -> Result<Expr> {
         self.expect_punct("{")?;
         let mut fields = Vec::new();
 
@@ -1708,10 +2515,10 @@ impl<'a> Parser<'a> {
 
         self.expect_punct("}")?;
 
-        Ok(Expr::StructLiteral { name, fields })
+        Ok(self.expr(ExprKind::StructLiteral { name, fields }, start))
     }
 
-    fn parse_template_literal(&mut self) -> Result<Expr> {
+    fn parse_template_literal(&mut self) -> Result<ExprKind> {
         let mut parts = Vec::new();
 
         loop {
@@ -1722,10 +2529,25 @@ impl<'a> Parser<'a> {
                 Some(Token::TemplateExprStart) => {
                     // Parse the expression inside ${}
                     let expr = self.parse_expression()?;
-                    parts.push(TemplatePart::Expression(expr));
+                    let mut format = None;
+
+                    // Expect the closing brace, after an optional format specifier
+                    let mut end = self.next()?;
+                    if let Some(Token::TemplateFormat(spec)) = end {
+                        format = Some(FormatSpec::parse(&spec).ok_or_else(|| {
+                            self.tokens.croak(
+                                format!(
+                                    "Unknown format specifier ':{}', expected d, dN, fN, hex, HEX, oct, bin, <N, >N or ^N",
+                                    spec
+                                ),
+                                None,
+                            )
+                        })?);
+                        end = self.next()?;
+                    }
+                    parts.push(TemplatePart::Expression(expr, format));
 
-                    // Expect the closing brace
-                    match self.next()? {
+                    match end {
                         Some(Token::TemplateExprEnd) => {
                             // Continue parsing
                         }
@@ -1762,53 +2584,55 @@ impl<'a> Parser<'a> {
             }
         }
 
-        Ok(Expr::TemplateLiteral { parts })
+        Ok(ExprKind::TemplateLiteral { parts })
     }
 
     // Helper function to check if we're looking at lambda params
     fn is_lambda_params(&mut self) -> Result<bool> {
         // Save the current state by collecting tokens
         let mut tokens_to_restore = Vec::new();
+        let last_span = self.last_span;
         let mut depth = 0;
         let mut found_arrow = false;
 
         // Look ahead to find ) => pattern
         while let Some(token) = self.next()? {
-            tokens_to_restore.push(token.clone());
+            tokens_to_restore.push((token.clone(), self.last_span));
 
             match &token {
-                        Token::Punct(p) if p == ")" && depth == 0 => {
-                            // Check next token for =>
-                            if let Some(token) = self.next()? {
-                                tokens_to_restore.push(token.clone());
-                                if let Token::Op(op) = token {
-                                    if op == "=>" {
-                                        found_arrow = true;
-                                    }
-                                }
+                Token::Punct(p) if p == ")" && depth == 0 => {
+                    // Check next token for =>
+                    if let Some(token) = self.next()? {
+                        tokens_to_restore.push((token.clone(), self.last_span));
+                        if let Token::Op(op) = token {
+                            if op == "=>" {
+                                found_arrow = true;
                             }
-                            break;
                         }
-                        Token::Punct(p) if p == "(" => depth += 1,
-                        Token::Punct(p) if p == ")" => depth -= 1,
-                        _ => {}
                     }
+                    break;
+                }
+                Token::Punct(p) if p == "(" => depth += 1,
+                Token::Punct(p) if p == ")" => depth -= 1,
+                _ => {}
+            }
 
-                    // Safety limit
-                    if tokens_to_restore.len() > 100 {
-                        break;
-                    }
+            // Safety limit
+            if tokens_to_restore.len() > 100 {
+                break;
+            }
         }
 
         // Restore all tokens in reverse order (inserting at the front)
         for token in tokens_to_restore.into_iter().rev() {
             self.tokens.buffer.insert(0, token);
         }
+        self.last_span = last_span;
 
         Ok(found_arrow)
     }
 
-    fn parse_lambda_with_parens(&mut self) -> Result<Expr> {
+    fn parse_lambda_with_parens(&mut self) -> Result<ExprKind> {
         // The '(' has already been consumed by parse_primary_expr
         // Parse parameters
         let mut params = Vec::new();
@@ -1867,13 +2691,14 @@ impl<'a> Parser<'a> {
         &mut self,
         params: Vec<(String, Option<Type>)>,
         return_type: Option<Type>,
-    ) -> Result<Expr> {
+    ) -> Result<ExprKind> {
         // Body
         let body = if let Some(Token::Punct(p)) = self.peek()? {
             if p == "{" {
                 // Block body
+                let start = self.peek_span()?;
                 let statements = self.parse_block()?;
-                Box::new(Expr::Block(statements))
+                Box::new(self.expr(ExprKind::Block(statements), start))
             } else {
                 // Expression body
                 Box::new(self.parse_expression()?)
@@ -1882,14 +2707,28 @@ impl<'a> Parser<'a> {
             Box::new(self.parse_expression()?)
         };
 
-        Ok(Expr::Lambda {
+        Ok(ExprKind::Lambda {
             params,
             return_type,
             body,
         })
     }
 
+    /// An argument or array element, which may spread an array: `...items`
+    fn parse_element(&mut self) -> Result<Expr> {
+        match self.peek()? {
+            Some(Token::Op(op)) if op == "..." => {
+                let start = self.peek_span()?;
+                self.next()?; // consume '...'
+                let expr = self.parse_expression()?;
+                Ok(self.expr(ExprKind::Spread(Box::new(expr)), start))
+            }
+            _ => self.parse_expression(),
+        }
+    }
+
     fn parse_call(&mut self, func: Expr) -> Result<Expr> {
+        let start = func.span;
         self.expect_punct("(")?;
         let mut args = Vec::new();
 
@@ -1898,7 +2737,7 @@ impl<'a> Parser<'a> {
                 break;
             }
 
-            args.push(self.parse_expression()?);
+            args.push(self.nested(Self::parse_element)?);
 
             if let Some(token) = self.peek()? {
                 // println!("In parse_call: next token is {:?}", token);
@@ -1913,10 +2752,13 @@ impl<'a> Parser<'a> {
         }
 
         self.expect_punct(")")?;
-        Ok(Expr::Call {
-            func: Box::new(func),
-            args,
-        })
+        Ok(self.expr(
+            ExprKind::Call {
+                func: Box::new(func),
+                args,
+            },
+            start,
+        ))
     }
 
     fn get_precedence(&self, op: &str) -> u8 {
@@ -1962,10 +2804,22 @@ impl<'a> Parser<'a> {
 // Extension methods for TokenStream to support lookahead
 impl<'a> TokenStream<'a> {
     pub fn new(input: InputStream<'a>) -> Self {
-        Self {
+        let mut stream = Self {
             input,
             buffer: Vec::new(),
-            last_doc_comment: None,
+            comments: Vec::new(),
+        };
+        stream.skip_shebang();
+        stream
+    }
+
+    /// Skip a `#!` line at the very start, which lets a script run as an
+    /// executable with `#!/usr/bin/env loft`
+    fn skip_shebang(&mut self) {
+        if self.input.peek() == Some('#') && self.input.peek_nth(1) == Some('!') {
+            while self.input.peek().is_some_and(|c| c != '\n') {
+                self.input.next();
+            }
         }
     }
 
@@ -2000,10 +2854,13 @@ mod inline_tests {
         let result = parser.parse().unwrap();
         assert_eq!(result.len(), 1);
 
-        match &result[0] {
-            Stmt::VarDecl { name, value, .. } => {
+        match &result[0].kind {
+            StmtKind::VarDecl { name, value, .. } => {
                 assert_eq!(name, "x");
-                assert!(matches!(value, Some(Expr::Number(_))));
+                assert!(matches!(
+                    value.as_ref().map(|v| &v.kind),
+                    Some(ExprKind::Number(_))
+                ));
             }
             _ => panic!("Expected variable declaration"),
         }
@@ -2018,11 +2875,11 @@ mod inline_tests {
         let expr = parser.parse_expression().unwrap();
 
         // Should parse as 2 + (3 * 4) due to precedence
-        match expr {
-            Expr::BinOp { op, left, right } => {
+        match expr.kind {
+            ExprKind::BinOp { op, left, right } => {
                 assert_eq!(op, "+");
-                assert!(matches!(*left, Expr::Number(_)));
-                assert!(matches!(*right, Expr::BinOp { op, .. } if op == "*"));
+                assert!(matches!(left.kind, ExprKind::Number(_)));
+                assert!(matches!(right.kind, ExprKind::BinOp { op, .. } if op == "*"));
             }
             _ => panic!("Expected binary operation"),
         }
@@ -2036,14 +2893,14 @@ mod inline_tests {
 
         let stmt = parser.parse_statement().unwrap();
 
-        match stmt {
-            Stmt::If {
+        match stmt.kind {
+            StmtKind::If {
                 condition,
                 then_branch,
                 else_branch,
             } => {
-                assert!(matches!(condition, Expr::BinOp { .. }));
-                assert!(matches!(*then_branch, Stmt::Block(_)));
+                assert!(matches!(condition.kind, ExprKind::BinOp { .. }));
+                assert!(matches!(then_branch.kind, StmtKind::Block(_)));
                 assert!(else_branch.is_none());
             }
             _ => panic!("Expected if statement"),
@@ -2059,8 +2916,8 @@ mod inline_tests {
         let result = parser.parse().unwrap();
         assert_eq!(result.len(), 1);
 
-        match &result[0] {
-            Stmt::ImportDecl { path } => {
+        match &result[0].kind {
+            StmtKind::ImportDecl { path, .. } => {
                 assert_eq!(path.len(), 1);
                 assert_eq!(path[0], "std");
             }
@@ -2077,8 +2934,8 @@ mod inline_tests {
         let result = parser.parse().unwrap();
         assert_eq!(result.len(), 1);
 
-        match &result[0] {
-            Stmt::ImportDecl { path } => {
+        match &result[0].kind {
+            StmtKind::ImportDecl { path, .. } => {
                 assert_eq!(path.len(), 2);
                 assert_eq!(path[0], "project");
                 assert_eq!(path[1], "value");
@@ -2096,8 +2953,8 @@ mod inline_tests {
         let result = parser.parse().unwrap();
         assert_eq!(result.len(), 1);
 
-        match &result[0] {
-            Stmt::ImportDecl { path } => {
+        match &result[0].kind {
+            StmtKind::ImportDecl { path, .. } => {
                 assert_eq!(path.len(), 3);
                 assert_eq!(path[0], "project");
                 assert_eq!(path[1], "module");
@@ -2116,8 +2973,8 @@ mod inline_tests {
         let result = parser.parse().unwrap();
         assert_eq!(result.len(), 1);
 
-        match &result[0] {
-            Stmt::FunctionDecl {
+        match &result[0].kind {
+            StmtKind::FunctionDecl {
                 name,
                 is_exported,
                 params,
@@ -2140,8 +2997,8 @@ mod inline_tests {
         let result = parser.parse().unwrap();
         assert_eq!(result.len(), 1);
 
-        match &result[0] {
-            Stmt::StructDecl { name, fields } => {
+        match &result[0].kind {
+            StmtKind::StructDecl { name, fields, .. } => {
                 assert_eq!(name, "Person");
                 assert_eq!(fields.len(), 2);
             }
@@ -2158,11 +3015,11 @@ mod inline_tests {
         let result = parser.parse().unwrap();
         assert_eq!(result.len(), 1);
 
-        match &result[0] {
-            Stmt::VarDecl { name, value, .. } => {
+        match &result[0].kind {
+            StmtKind::VarDecl { name, value, .. } => {
                 assert_eq!(name, "f");
-                match value {
-                    Some(Expr::Lambda { params, .. }) => {
+                match value.as_ref().map(|v| &v.kind) {
+                    Some(ExprKind::Lambda { params, .. }) => {
                         assert_eq!(params.len(), 1);
                         assert_eq!(params[0].0, "v");
                     }
@@ -2182,11 +3039,11 @@ mod inline_tests {
         let result = parser.parse().unwrap();
         assert_eq!(result.len(), 1);
 
-        match &result[0] {
-            Stmt::VarDecl { name, value, .. } => {
+        match &result[0].kind {
+            StmtKind::VarDecl { name, value, .. } => {
                 assert_eq!(name, "f");
-                match value {
-                    Some(Expr::Lambda { params, .. }) => {
+                match value.as_ref().map(|v| &v.kind) {
+                    Some(ExprKind::Lambda { params, .. }) => {
                         assert_eq!(params.len(), 1);
                         assert_eq!(params[0].0, "v");
                     }
@@ -2206,11 +3063,11 @@ mod inline_tests {
         let result = parser.parse().unwrap();
         assert_eq!(result.len(), 1);
 
-        match &result[0] {
-            Stmt::VarDecl { name, value, .. } => {
+        match &result[0].kind {
+            StmtKind::VarDecl { name, value, .. } => {
                 assert_eq!(name, "f");
-                match value {
-                    Some(Expr::Lambda { params, .. }) => {
+                match value.as_ref().map(|v| &v.kind) {
+                    Some(ExprKind::Lambda { params, .. }) => {
                         assert_eq!(params.len(), 2);
                         assert_eq!(params[0].0, "v");
                         assert_eq!(params[1].0, "a");
@@ -2239,8 +3096,8 @@ mod inline_tests {
         let result = parser.parse().unwrap();
         assert_eq!(result.len(), 1);
 
-        match &result[0] {
-            Stmt::TraitDecl { name, methods } => {
+        match &result[0].kind {
+            StmtKind::TraitDecl { name, methods } => {
                 assert_eq!(name, "ToString");
                 assert_eq!(methods.len(), 2);
                 // First should be signature
@@ -2271,12 +3128,12 @@ mod inline_tests {
         let result = parser.parse().unwrap();
         assert_eq!(result.len(), 1);
 
-        match &result[0] {
-            Stmt::VarDecl { name, value, .. } => {
+        match &result[0].kind {
+            StmtKind::VarDecl { name, value, .. } => {
                 assert_eq!(name, "result");
-                match value {
-                    Some(Expr::Await(expr)) => match &**expr {
-                        Expr::Ident(n) => assert_eq!(n, "future"),
+                match value.as_ref().map(|v| &v.kind) {
+                    Some(ExprKind::Await(expr)) => match &expr.kind {
+                        ExprKind::Ident(n) => assert_eq!(n, "future"),
                         _ => panic!("Expected identifier in await"),
                     },
                     _ => panic!("Expected await expression"),
@@ -2295,13 +3152,13 @@ mod inline_tests {
         let result = parser.parse().unwrap();
         assert_eq!(result.len(), 1);
 
-        match &result[0] {
-            Stmt::VarDecl { name, value, .. } => {
+        match &result[0].kind {
+            StmtKind::VarDecl { name, value, .. } => {
                 assert_eq!(name, "promise");
-                match value {
-                    Some(Expr::Async(expr)) => match &**expr {
-                        Expr::Call { func, .. } => match &**func {
-                            Expr::Ident(n) => assert_eq!(n, "fetch_data"),
+                match value.as_ref().map(|v| &v.kind) {
+                    Some(ExprKind::Async(expr)) => match &expr.kind {
+                        ExprKind::Call { func, .. } => match &func.kind {
+                            ExprKind::Ident(n) => assert_eq!(n, "fetch_data"),
                             _ => panic!("Expected function identifier"),
                         },
                         _ => panic!("Expected function call in async"),
@@ -2322,13 +3179,13 @@ mod inline_tests {
         let result = parser.parse().unwrap();
         assert_eq!(result.len(), 1);
 
-        match &result[0] {
-            Stmt::VarDecl { name, value, .. } => {
+        match &result[0].kind {
+            StmtKind::VarDecl { name, value, .. } => {
                 assert_eq!(name, "future");
-                match value {
-                    Some(Expr::Lazy(expr)) => match &**expr {
-                        Expr::Call { func, .. } => match &**func {
-                            Expr::Ident(n) => assert_eq!(n, "expensive_computation"),
+                match value.as_ref().map(|v| &v.kind) {
+                    Some(ExprKind::Lazy(expr)) => match &expr.kind {
+                        ExprKind::Call { func, .. } => match &func.kind {
+                            ExprKind::Ident(n) => assert_eq!(n, "expensive_computation"),
                             _ => panic!("Expected function identifier"),
                         },
                         _ => panic!("Expected function call in lazy"),
@@ -2354,14 +3211,14 @@ mod inline_tests {
         assert_eq!(result.len(), 2);
 
         // Test await with async
-        match &result[0] {
-            Stmt::VarDecl { name, value, .. } => {
+        match &result[0].kind {
+            StmtKind::VarDecl { name, value, .. } => {
                 assert_eq!(name, "eager_result");
-                match value {
-                    Some(Expr::Await(expr)) => match &**expr {
-                        Expr::Async(async_expr) => match &**async_expr {
-                            Expr::Call { func, .. } => match &**func {
-                                Expr::Ident(n) => assert_eq!(n, "fetch_data"),
+                match value.as_ref().map(|v| &v.kind) {
+                    Some(ExprKind::Await(expr)) => match &expr.kind {
+                        ExprKind::Async(async_expr) => match &async_expr.kind {
+                            ExprKind::Call { func, .. } => match &func.kind {
+                                ExprKind::Ident(n) => assert_eq!(n, "fetch_data"),
                                 _ => panic!("Expected function identifier"),
                             },
                             _ => panic!("Expected function call in async"),
@@ -2375,14 +3232,14 @@ mod inline_tests {
         }
 
         // Test await with lazy
-        match &result[1] {
-            Stmt::VarDecl { name, value, .. } => {
+        match &result[1].kind {
+            StmtKind::VarDecl { name, value, .. } => {
                 assert_eq!(name, "lazy_result");
-                match value {
-                    Some(Expr::Await(expr)) => match &**expr {
-                        Expr::Lazy(lazy_expr) => match &**lazy_expr {
-                            Expr::Call { func, .. } => match &**func {
-                                Expr::Ident(n) => assert_eq!(n, "compute_value"),
+                match value.as_ref().map(|v| &v.kind) {
+                    Some(ExprKind::Await(expr)) => match &expr.kind {
+                        ExprKind::Lazy(lazy_expr) => match &lazy_expr.kind {
+                            ExprKind::Call { func, .. } => match &func.kind {
+                                ExprKind::Ident(n) => assert_eq!(n, "compute_value"),
                                 _ => panic!("Expected function identifier"),
                             },
                             _ => panic!("Expected function call in lazy"),
@@ -2410,8 +3267,8 @@ mod inline_tests {
         assert_eq!(result.len(), 2);
 
         // First should be async function declaration
-        match &result[0] {
-            Stmt::FunctionDecl { name, is_async, .. } => {
+        match &result[0].kind {
+            StmtKind::FunctionDecl { name, is_async, .. } => {
                 assert_eq!(name, "fetch_data");
                 assert!(*is_async);
             }
@@ -2419,11 +3276,11 @@ mod inline_tests {
         }
 
         // Second should be variable declaration with async expression
-        match &result[1] {
-            Stmt::VarDecl { name, value, .. } => {
+        match &result[1].kind {
+            StmtKind::VarDecl { name, value, .. } => {
                 assert_eq!(name, "promise");
-                match value {
-                    Some(Expr::Async(_)) => {
+                match value.as_ref().map(|v| &v.kind) {
+                    Some(ExprKind::Async(_)) => {
                         // Success - we correctly parsed async expression
                     }
                     _ => panic!("Expected async expression"),
@@ -2432,4 +3289,28 @@ mod inline_tests {
             _ => panic!("Expected variable declaration with async expression"),
         }
     }
+
+    #[test]
+    fn test_parse_map_literal() {
+        let input = r#"let m = #{ name: "loft", 1: true };"#.to_string();
+        let stream = InputStream::new("test", &input);
+        let mut parser = Parser::new(stream);
+
+        let result = parser.parse().unwrap();
+        match &result[0].kind {
+            StmtKind::VarDecl {
+                value:
+                    Some(Expr {
+                        kind: ExprKind::MapLiteral(entries),
+                        ..
+                    }),
+                ..
+            } => {
+                assert_eq!(entries.len(), 2);
+                assert_eq!(entries[0].0.kind, ExprKind::String("name".into()));
+                assert!(matches!(entries[1].0.kind, ExprKind::Number(_)));
+            }
+            _ => panic!("Expected variable declaration with map literal"),
+        }
+    }
 }
@@ -0,0 +1,47 @@
+use serde::Serialize;
+
+/// Location of a node in its source file
+///
+/// `start` and `end` are byte offsets, `line` and `column` are the zero-based
+/// position of `start`. Spans never take part in equality, so two nodes
+/// written in different places still compare equal when their contents match.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: usize, column: usize) -> Self {
+        Self {
+            start,
+            end,
+            line,
+            column,
+        }
+    }
+
+    /// Span covering both `self` and everything up to the end of `other`
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            end: other.end.max(self.end),
+            ..self
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end.saturating_sub(self.start)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl PartialEq for Span {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
@@ -10,10 +10,13 @@ fn test_parse_var_decl() {
     let result = parser.parse().unwrap();
     assert_eq!(result.len(), 1);
 
-    match &result[0] {
-        Stmt::VarDecl { name, value, .. } => {
+    match &result[0].kind {
+        StmtKind::VarDecl { name, value, .. } => {
             assert_eq!(name, "x");
-            assert!(matches!(value, Some(Expr::Number(_))));
+            assert!(matches!(
+                value.as_ref().map(|v| &v.kind),
+                Some(ExprKind::Number(_))
+            ));
         }
         _ => panic!("Expected variable declaration"),
     }
@@ -28,11 +31,11 @@ fn test_parse_binary_expr() {
     let expr = parser.parse_expression().unwrap();
 
     // Should parse as 2 + (3 * 4) due to precedence
-    match expr {
-        Expr::BinOp { op, left, right } => {
+    match expr.kind {
+        ExprKind::BinOp { op, left, right } => {
             assert_eq!(op, "+");
-            assert!(matches!(*left, Expr::Number(_)));
-            assert!(matches!(*right, Expr::BinOp { op, .. } if op == "*"));
+            assert!(matches!(left.kind, ExprKind::Number(_)));
+            assert!(matches!(right.kind, ExprKind::BinOp { op, .. } if op == "*"));
         }
         _ => panic!("Expected binary operation"),
     }
@@ -46,14 +49,14 @@ fn test_parse_if_statement() {
 
     let stmt = parser.parse_statement().unwrap();
 
-    match stmt {
-        Stmt::If {
+    match stmt.kind {
+        StmtKind::If {
             condition,
             then_branch,
             else_branch,
         } => {
-            assert!(matches!(condition, Expr::BinOp { .. }));
-            assert!(matches!(*then_branch, Stmt::Block(_)));
+            assert!(matches!(condition.kind, ExprKind::BinOp { .. }));
+            assert!(matches!(then_branch.kind, StmtKind::Block(_)));
             assert!(else_branch.is_none());
         }
         _ => panic!("Expected if statement"),
@@ -69,8 +72,8 @@ fn test_parse_import() {
     let result = parser.parse().unwrap();
     assert_eq!(result.len(), 1);
 
-    match &result[0] {
-        Stmt::ImportDecl { path } => {
+    match &result[0].kind {
+        StmtKind::ImportDecl { path, .. } => {
             assert_eq!(path.len(), 1);
             assert_eq!(path[0], "std");
         }
@@ -87,8 +90,8 @@ fn test_parse_import_with_path() {
     let result = parser.parse().unwrap();
     assert_eq!(result.len(), 1);
 
-    match &result[0] {
-        Stmt::ImportDecl { path } => {
+    match &result[0].kind {
+        StmtKind::ImportDecl { path, .. } => {
             assert_eq!(path.len(), 2);
             assert_eq!(path[0], "project");
             assert_eq!(path[1], "value");
@@ -106,8 +109,8 @@ fn test_parse_import_with_module_path() {
     let result = parser.parse().unwrap();
     assert_eq!(result.len(), 1);
 
-    match &result[0] {
-        Stmt::ImportDecl { path } => {
+    match &result[0].kind {
+        StmtKind::ImportDecl { path, .. } => {
             assert_eq!(path.len(), 3);
             assert_eq!(path[0], "project");
             assert_eq!(path[1], "module");
@@ -117,6 +120,59 @@ fn test_parse_import_with_module_path() {
     }
 }
 
+#[test]
+fn test_parse_aliased_and_selective_imports() {
+    let input = "learn utils as u;\nlearn { a, b as c } from \"./utils\";\nteach learn \"./utils\" as helpers;"
+        .to_string();
+    let stream = InputStream::new("test", &input);
+    let mut parser = Parser::new(stream);
+
+    let result = parser.parse().unwrap();
+    assert_eq!(result.len(), 3);
+
+    match &result[0].kind {
+        StmtKind::ImportDecl {
+            path,
+            alias,
+            items,
+            is_exported,
+        } => {
+            assert_eq!(path, &vec!["utils".to_string()]);
+            assert_eq!(alias.as_deref(), Some("u"));
+            assert!(items.is_none());
+            assert!(!is_exported);
+        }
+        _ => panic!("Expected import declaration"),
+    }
+
+    match &result[1].kind {
+        StmtKind::ImportDecl { items, alias, .. } => {
+            let items = items.as_ref().expect("Expected selective import");
+            let names: Vec<_> = items
+                .iter()
+                .map(|item| (item.name.as_str(), item.local_name()))
+                .collect();
+            assert_eq!(names, vec![("a", "a"), ("b", "c")]);
+            assert!(alias.is_none());
+        }
+        _ => panic!("Expected import declaration"),
+    }
+
+    match &result[2].kind {
+        StmtKind::ImportDecl {
+            path,
+            alias,
+            is_exported,
+            ..
+        } => {
+            assert_eq!(import_binding_name(path), "utils");
+            assert_eq!(alias.as_deref(), Some("helpers"));
+            assert!(is_exported);
+        }
+        _ => panic!("Expected import declaration"),
+    }
+}
+
 #[test]
 fn test_parse_exported_function() {
     let input = "teach fn add(a: num, b: num) -> num { return a + b; }".to_string();
@@ -126,8 +182,8 @@ fn test_parse_exported_function() {
     let result = parser.parse().unwrap();
     assert_eq!(result.len(), 1);
 
-    match &result[0] {
-        Stmt::FunctionDecl {
+    match &result[0].kind {
+        StmtKind::FunctionDecl {
             name,
             is_exported,
             params,
@@ -150,8 +206,8 @@ fn test_parse_struct_with_def() {
     let result = parser.parse().unwrap();
     assert_eq!(result.len(), 1);
 
-    match &result[0] {
-        Stmt::StructDecl { name, fields } => {
+    match &result[0].kind {
+        StmtKind::StructDecl { name, fields, .. } => {
             assert_eq!(name, "Person");
             assert_eq!(fields.len(), 2);
         }
@@ -159,6 +215,46 @@ fn test_parse_struct_with_def() {
     }
 }
 
+#[test]
+fn test_parse_generic_struct_and_function() {
+    let input = "def Pair<A, B> { first: A, second: B }\nfn first<T>(items: Array<T>) -> T { return items[0]; }".to_string();
+    let stream = InputStream::new("test", &input);
+    let mut parser = Parser::new(stream);
+
+    let result = parser.parse().unwrap();
+    assert_eq!(result.len(), 2);
+
+    match &result[0].kind {
+        StmtKind::StructDecl {
+            name,
+            type_params,
+            fields,
+        } => {
+            assert_eq!(name, "Pair");
+            assert_eq!(type_params, &["A".to_string(), "B".to_string()]);
+            assert_eq!(fields[1].1, Type::Named("B".to_string()));
+        }
+        _ => panic!("Expected struct declaration"),
+    }
+    match &result[1].kind {
+        StmtKind::FunctionDecl {
+            type_params,
+            params,
+            ..
+        } => {
+            assert_eq!(type_params, &["T".to_string()]);
+            assert_eq!(
+                params[0].1,
+                Type::Generic {
+                    base: "Array".to_string(),
+                    type_args: vec![Type::Named("T".to_string())],
+                }
+            );
+        }
+        _ => panic!("Expected function declaration"),
+    }
+}
+
 #[test]
 fn test_parse_lambda_simple() {
     let input = "let f = v => v;".to_string();
@@ -168,11 +264,11 @@ fn test_parse_lambda_simple() {
     let result = parser.parse().unwrap();
     assert_eq!(result.len(), 1);
 
-    match &result[0] {
-        Stmt::VarDecl { name, value, .. } => {
+    match &result[0].kind {
+        StmtKind::VarDecl { name, value, .. } => {
             assert_eq!(name, "f");
-            match value {
-                Some(Expr::Lambda { params, .. }) => {
+            match value.as_ref().map(|v| &v.kind) {
+                Some(ExprKind::Lambda { params, .. }) => {
                     assert_eq!(params.len(), 1);
                     assert_eq!(params[0].0, "v");
                 }
@@ -192,11 +288,11 @@ fn test_parse_lambda_single_param_with_type() {
     let result = parser.parse().unwrap();
     assert_eq!(result.len(), 1);
 
-    match &result[0] {
-        Stmt::VarDecl { name, value, .. } => {
+    match &result[0].kind {
+        StmtKind::VarDecl { name, value, .. } => {
             assert_eq!(name, "f");
-            match value {
-                Some(Expr::Lambda { params, .. }) => {
+            match value.as_ref().map(|v| &v.kind) {
+                Some(ExprKind::Lambda { params, .. }) => {
                     assert_eq!(params.len(), 1);
                     assert_eq!(params[0].0, "v");
                 }
@@ -216,11 +312,11 @@ fn test_parse_lambda_with_types() {
     let result = parser.parse().unwrap();
     assert_eq!(result.len(), 1);
 
-    match &result[0] {
-        Stmt::VarDecl { name, value, .. } => {
+    match &result[0].kind {
+        StmtKind::VarDecl { name, value, .. } => {
             assert_eq!(name, "f");
-            match value {
-                Some(Expr::Lambda { params, .. }) => {
+            match value.as_ref().map(|v| &v.kind) {
+                Some(ExprKind::Lambda { params, .. }) => {
                     assert_eq!(params.len(), 2);
                     assert_eq!(params[0].0, "v");
                     assert_eq!(params[1].0, "a");
@@ -249,8 +345,8 @@ fn test_parse_trait_with_default_impl() {
     let result = parser.parse().unwrap();
     assert_eq!(result.len(), 1);
 
-    match &result[0] {
-        Stmt::TraitDecl { name, methods } => {
+    match &result[0].kind {
+        StmtKind::TraitDecl { name, methods } => {
             assert_eq!(name, "ToString");
             assert_eq!(methods.len(), 2);
             // First should be signature
@@ -281,12 +377,12 @@ fn test_parse_await_expr() {
     let result = parser.parse().unwrap();
     assert_eq!(result.len(), 1);
 
-    match &result[0] {
-        Stmt::VarDecl { name, value, .. } => {
+    match &result[0].kind {
+        StmtKind::VarDecl { name, value, .. } => {
             assert_eq!(name, "result");
-            match value {
-                Some(Expr::Await(expr)) => match &**expr {
-                    Expr::Ident(n) => assert_eq!(n, "future"),
+            match value.as_ref().map(|v| &v.kind) {
+                Some(ExprKind::Await(expr)) => match &expr.kind {
+                    ExprKind::Ident(n) => assert_eq!(n, "future"),
                     _ => panic!("Expected identifier in await"),
                 },
                 _ => panic!("Expected await expression"),
@@ -305,13 +401,13 @@ fn test_parse_async_expr() {
     let result = parser.parse().unwrap();
     assert_eq!(result.len(), 1);
 
-    match &result[0] {
-        Stmt::VarDecl { name, value, .. } => {
+    match &result[0].kind {
+        StmtKind::VarDecl { name, value, .. } => {
             assert_eq!(name, "promise");
-            match value {
-                Some(Expr::Async(expr)) => match &**expr {
-                    Expr::Call { func, .. } => match &**func {
-                        Expr::Ident(n) => assert_eq!(n, "fetch_data"),
+            match value.as_ref().map(|v| &v.kind) {
+                Some(ExprKind::Async(expr)) => match &expr.kind {
+                    ExprKind::Call { func, .. } => match &func.kind {
+                        ExprKind::Ident(n) => assert_eq!(n, "fetch_data"),
                         _ => panic!("Expected function identifier"),
                     },
                     _ => panic!("Expected function call in async"),
@@ -332,13 +428,13 @@ fn test_parse_lazy_expr() {
     let result = parser.parse().unwrap();
     assert_eq!(result.len(), 1);
 
-    match &result[0] {
-        Stmt::VarDecl { name, value, .. } => {
+    match &result[0].kind {
+        StmtKind::VarDecl { name, value, .. } => {
             assert_eq!(name, "future");
-            match value {
-                Some(Expr::Lazy(expr)) => match &**expr {
-                    Expr::Call { func, .. } => match &**func {
-                        Expr::Ident(n) => assert_eq!(n, "expensive_computation"),
+            match value.as_ref().map(|v| &v.kind) {
+                Some(ExprKind::Lazy(expr)) => match &expr.kind {
+                    ExprKind::Call { func, .. } => match &func.kind {
+                        ExprKind::Ident(n) => assert_eq!(n, "expensive_computation"),
                         _ => panic!("Expected function identifier"),
                     },
                     _ => panic!("Expected function call in lazy"),
@@ -364,14 +460,14 @@ fn test_parse_await_async_and_lazy() {
     assert_eq!(result.len(), 2);
 
     // Test await with async
-    match &result[0] {
-        Stmt::VarDecl { name, value, .. } => {
+    match &result[0].kind {
+        StmtKind::VarDecl { name, value, .. } => {
             assert_eq!(name, "eager_result");
-            match value {
-                Some(Expr::Await(expr)) => match &**expr {
-                    Expr::Async(async_expr) => match &**async_expr {
-                        Expr::Call { func, .. } => match &**func {
-                            Expr::Ident(n) => assert_eq!(n, "fetch_data"),
+            match value.as_ref().map(|v| &v.kind) {
+                Some(ExprKind::Await(expr)) => match &expr.kind {
+                    ExprKind::Async(async_expr) => match &async_expr.kind {
+                        ExprKind::Call { func, .. } => match &func.kind {
+                            ExprKind::Ident(n) => assert_eq!(n, "fetch_data"),
                             _ => panic!("Expected function identifier"),
                         },
                         _ => panic!("Expected function call in async"),
@@ -385,14 +481,14 @@ fn test_parse_await_async_and_lazy() {
     }
 
     // Test await with lazy
-    match &result[1] {
-        Stmt::VarDecl { name, value, .. } => {
+    match &result[1].kind {
+        StmtKind::VarDecl { name, value, .. } => {
             assert_eq!(name, "lazy_result");
-            match value {
-                Some(Expr::Await(expr)) => match &**expr {
-                    Expr::Lazy(lazy_expr) => match &**lazy_expr {
-                        Expr::Call { func, .. } => match &**func {
-                            Expr::Ident(n) => assert_eq!(n, "compute_value"),
+            match value.as_ref().map(|v| &v.kind) {
+                Some(ExprKind::Await(expr)) => match &expr.kind {
+                    ExprKind::Lazy(lazy_expr) => match &lazy_expr.kind {
+                        ExprKind::Call { func, .. } => match &func.kind {
+                            ExprKind::Ident(n) => assert_eq!(n, "compute_value"),
                             _ => panic!("Expected function identifier"),
                         },
                         _ => panic!("Expected function call in lazy"),
@@ -420,8 +516,8 @@ fn test_async_fn_vs_async_expr() {
     assert_eq!(result.len(), 2);
 
     // First should be async function declaration
-    match &result[0] {
-        Stmt::FunctionDecl { name, is_async, .. } => {
+    match &result[0].kind {
+        StmtKind::FunctionDecl { name, is_async, .. } => {
             assert_eq!(name, "fetch_data");
             assert!(*is_async);
         }
@@ -429,11 +525,11 @@ fn test_async_fn_vs_async_expr() {
     }
 
     // Second should be variable declaration with async expression
-    match &result[1] {
-        Stmt::VarDecl { name, value, .. } => {
+    match &result[1].kind {
+        StmtKind::VarDecl { name, value, .. } => {
             assert_eq!(name, "promise");
-            match value {
-                Some(Expr::Async(_)) => {
+            match value.as_ref().map(|v| &v.kind) {
+                Some(ExprKind::Async(_)) => {
                     // Success - we correctly parsed async expression
                 }
                 _ => panic!("Expected async expression"),
@@ -473,3 +569,290 @@ fn test_mixed_expression() {
         Err(e) => panic!("Parse failed: {}", e),
     }
 }
+
+#[test]
+fn test_parse_try_catch_finally_and_throw() {
+    let input =
+        "try { risky(); } catch (e) { throw e; } finally { done(); }\ntry { risky(); } catch { }\n"
+            .to_string();
+    let stream = InputStream::new("test", &input);
+    let mut parser = Parser::new(stream);
+
+    let result = parser.parse().unwrap();
+    assert_eq!(result.len(), 2);
+
+    match &result[0].kind {
+        StmtKind::Try {
+            catch: Some((var, catch_body)),
+            finally: Some(_),
+            ..
+        } => {
+            assert_eq!(var.as_deref(), Some("e"));
+            match &catch_body.kind {
+                StmtKind::Block(stmts) => assert!(matches!(stmts[0].kind, StmtKind::Throw(_))),
+                _ => panic!("Expected catch block"),
+            }
+        }
+        _ => panic!("Expected try statement with catch and finally"),
+    }
+    assert!(matches!(
+        &result[1].kind,
+        StmtKind::Try {
+            catch: Some((None, _)),
+            finally: None,
+            ..
+        }
+    ));
+
+    // A try block needs a catch or finally block
+    let input = "try { risky(); }".to_string();
+    let mut parser = Parser::new(InputStream::new("test", &input));
+    assert!(parser.parse().is_err());
+}
+
+#[test]
+fn test_number_literals() {
+    let number = |source: &str| {
+        let input = source.to_string();
+        let expr = Parser::new(InputStream::new("test", &input))
+            .parse_expression()
+            .map_err(|e| e.message)?;
+        match expr.kind {
+            ExprKind::Number(n) => Ok(n.to_string()),
+            kind => panic!("Expected a number, got {:?}", kind),
+        }
+    };
+
+    assert_eq!(number("0xFF"), Ok("255".to_string()));
+    assert_eq!(number("0o755"), Ok("493".to_string()));
+    assert_eq!(number("0B1010"), Ok("10".to_string()));
+    assert_eq!(number("1_000_000"), Ok("1000000".to_string()));
+    assert_eq!(number("3.141_592"), Ok("3.141592".to_string()));
+
+    assert_eq!(
+        number("0b102"),
+        Err("Invalid digit in binary literal '0b102'".to_string())
+    );
+    assert_eq!(
+        number("1__000"),
+        Err("Underscores in '1__000' must be between digits".to_string())
+    );
+    assert_eq!(
+        number("0x"),
+        Err("Missing digits in hexadecimal literal '0x'".to_string())
+    );
+
+    // A dot without a digit after it starts a method call
+    let input = "6.is_int()".to_string();
+    let expr = Parser::new(InputStream::new("test", &input))
+        .parse_expression()
+        .unwrap();
+    assert!(matches!(expr.kind, ExprKind::Call { .. }));
+}
+
+#[test]
+fn test_template_format_specifiers() {
+    let formats = |source: &str| {
+        let input = source.to_string();
+        let expr = Parser::new(InputStream::new("test", &input))
+            .parse_expression()
+            .map_err(|e| e.message)?;
+        match expr.kind {
+            ExprKind::TemplateLiteral { parts } => Ok(parts
+                .into_iter()
+                .filter_map(|part| match part {
+                    TemplatePart::Expression(_, format) => Some(format),
+                    TemplatePart::Text(_) => None,
+                })
+                .collect::<Vec<_>>()),
+            kind => panic!("Expected a template literal, got {:?}", kind),
+        }
+    };
+
+    assert_eq!(
+        formats("`${n:d2} ${n} ${x:hex} ${name:>8}`"),
+        Ok(vec![
+            Some(FormatSpec::Integer { width: 2 }),
+            None,
+            Some(FormatSpec::Radix {
+                radix: 16,
+                upper: false
+            }),
+            Some(FormatSpec::Align {
+                align: Align::Right,
+                width: 8
+            }),
+        ])
+    );
+    // Colons inside brackets, strings and paths are part of the expression
+    assert_eq!(
+        formats("`${m[\"a:b\"]} ${Point::origin()}`"),
+        Ok(vec![None, None])
+    );
+    assert_eq!(
+        formats("`${n:x2}`"),
+        Err(
+            "Unknown format specifier ':x2', expected d, dN, fN, hex, HEX, oct, bin, <N, >N or ^N"
+                .to_string()
+        )
+    );
+}
+
+#[test]
+fn test_shebang_line_is_skipped() {
+    let input = "#!/usr/bin/env loft\nlet x = 1;\nlet y = ;".to_string();
+    let mut parser = Parser::new(InputStream::new("test", &input));
+    let error = parser.parse().unwrap_err();
+    // Lines are still counted from the top of the file
+    assert_eq!(error.line, 2);
+
+    let input = "#!/usr/bin/env loft\nlet x = 1;".to_string();
+    let stmts = Parser::new(InputStream::new("test", &input))
+        .parse()
+        .unwrap();
+    assert_eq!(stmts.len(), 1);
+    assert!(
+        Parser::new(InputStream::new("test", &"let x = 1;\n#!oops".to_string()))
+            .parse()
+            .is_err()
+    );
+}
+
+#[test]
+fn test_deep_nesting_is_an_error() {
+    // Debug builds need more stack per level than the test threads get
+    let handle = std::thread::Builder::new()
+        .stack_size(8 * 1024 * 1024)
+        .spawn(|| {
+            let message = |code: String| Parser::parse_fuzz(code.as_bytes()).unwrap_err().message;
+            let limit = format!(
+                "Code is nested too deeply, the limit is {} levels",
+                MAX_NESTING
+            );
+            for code in [
+                "(".repeat(10_000),
+                "if (1) {".repeat(10_000),
+                "f(".repeat(10_000),
+            ] {
+                assert_eq!(message(code), limit);
+            }
+            let limit = format!("Chain is too long, the limit is {} links", MAX_CHAIN_LINKS);
+            for code in [
+                format!("x = {};", ["1"; 10_000].join(" + ")),
+                format!("x = a{};", ".b".repeat(10_000)),
+                format!("x = a{};", ".b()".repeat(MAX_CHAIN_LINKS)),
+            ] {
+                assert_eq!(message(code), limit);
+            }
+
+            let nested = format!("let x = {}1{};", "(".repeat(50), ")".repeat(50));
+            assert!(Parser::parse_fuzz(nested.as_bytes()).is_ok());
+            // Chains are flat, so they aren't held to the nesting limit
+            for code in [
+                format!("let x = {};", ["1"; 1000].join(" + ")),
+                format!("let x = a{};", ".b()".repeat(MAX_CHAIN_LINKS / 2)),
+                format!(
+                    "let x = {}{}{};",
+                    "(".repeat(50),
+                    ["1"; 1000].join(" * "),
+                    ")".repeat(50)
+                ),
+            ] {
+                assert!(Parser::parse_fuzz(code.as_bytes()).is_ok());
+            }
+        })
+        .unwrap();
+    handle.join().unwrap();
+}
+
+#[test]
+fn test_parse_fuzz_takes_any_bytes() {
+    assert!(Parser::parse_fuzz(b"let x = \xff;").is_err());
+    assert_eq!(
+        Parser::parse_fuzz(b"let s = \"\xc3\x28\";").unwrap().len(),
+        1
+    );
+    assert!(Parser::parse_fuzz(b"").unwrap().is_empty());
+}
+
+#[test]
+fn test_recovery_skips_comments_in_failed_statement() {
+    let input = "(e//\nt\"\";e".to_string();
+    let (_, errors) = Parser::new(InputStream::new("test", &input)).parse_recoverable();
+    assert!(!errors.is_empty());
+    assert!(Parser::parse_fuzz(input.as_bytes()).is_err());
+
+    let input = "term.println(x // note\nlet s = \"\";\nfoo".to_string();
+    let (stmts, errors) = Parser::new(InputStream::new("test", &input)).parse_recoverable();
+    assert_eq!(errors.len(), 1);
+    assert!(stmts[0].trivia.leading.is_empty());
+}
+
+#[test]
+fn test_comments_are_attached_to_statements() {
+    let input = "#!/usr/bin/env loft
+// Setup
+let x = 1; // one
+
+/// Adds one
+/// to `n`
+fn inc(n: num) -> num {
+    return n + 1; /* tail */
+    // nothing after
+}
+term.println(inc(x, /* the value */));
+// the end
+"
+    .to_string();
+    let mut parser = Parser::new(InputStream::new("test", &input));
+    let stmts = parser.parse().unwrap();
+    let text = |comments: &[Comment]| -> Vec<String> {
+        comments
+            .iter()
+            .map(|comment| comment.text.clone())
+            .collect()
+    };
+
+    let first = &stmts[0].trivia;
+    assert!(!first.blank_line_before);
+    assert_eq!(text(&first.leading), ["// Setup"]);
+    assert_eq!(first.trailing.as_ref().unwrap().text, "// one");
+    assert_eq!(first.doc(), None);
+
+    let function = &stmts[1].trivia;
+    assert!(function.blank_line_before);
+    assert_eq!(function.doc().unwrap(), "Adds one\nto `n`");
+    let StmtKind::FunctionDecl { body, .. } = &stmts[1].kind else {
+        panic!("Expected a function");
+    };
+    assert_eq!(text(&body.trivia.inner), ["// nothing after"]);
+    let StmtKind::Block(body) = &body.kind else {
+        panic!("Expected a block");
+    };
+    assert_eq!(body[0].trivia.trailing.as_ref().unwrap().text, "/* tail */");
+
+    assert_eq!(text(&stmts[2].trivia.inner), ["/* the value */"]);
+    assert_eq!(text(parser.unclaimed_comments()), ["// the end"]);
+    assert_eq!(parser.comments().len(), 8);
+}
+
+#[test]
+fn test_block_doc_comment() {
+    let input = "/**
+ * Where things are
+ *
+ *     x, y
+ */
+def Point { x: num, y: num }
+/** Short */ let origin = 0;
+"
+    .to_string();
+    let stmts = Parser::new(InputStream::new("test", &input))
+        .parse()
+        .unwrap();
+    assert_eq!(
+        stmts[0].trivia.doc().unwrap(),
+        "Where things are\n\n    x, y"
+    );
+    assert_eq!(stmts[1].trivia.doc().unwrap(), "Short");
+}
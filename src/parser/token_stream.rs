@@ -3,7 +3,9 @@ use std::str::FromStr;
 
 use rust_decimal::Decimal;
 
-use crate::parser::input_stream::{InputStream, Result};
+use crate::parser::input_stream::{InputStream, Position, Result};
+use crate::parser::span::Span;
+use crate::parser::trivia::Comment;
 
 #[derive(Clone, Debug)]
 pub enum Token {
@@ -19,6 +21,7 @@ pub enum Token {
     TemplateString(String), // Text part of template literal
     TemplateExprStart,      // ${
     TemplateExprEnd,        // }
+    TemplateFormat(String), // Format specifier after `:` in ${value:spec}
     TemplateEnd,            // `
 }
 
@@ -37,6 +40,7 @@ impl fmt::Display for Token {
             Token::TemplateString(s) => write!(f, "template text \"{}\"", s),
             Token::TemplateExprStart => write!(f, "'${{'"),
             Token::TemplateExprEnd => write!(f, "'}}'"),
+            Token::TemplateFormat(spec) => write!(f, "format specifier ':{}'", spec),
             Token::TemplateEnd => write!(f, "'`'"),
         }
     }
@@ -44,31 +48,99 @@ impl fmt::Display for Token {
 
 pub struct TokenStream<'a> {
     pub(crate) input: InputStream<'a>,
-    pub(crate) buffer: Vec<Token>,
-    pub(crate) last_doc_comment: Option<String>, // Store the last doc comment
+    /// Tokens read ahead of the parser, with where they were written
+    pub(crate) buffer: Vec<(Token, Span)>,
+    /// Every comment skipped so far, in order
+    pub(crate) comments: Vec<Comment>,
 }
 
 pub const KEYWORDS: &[&str] = &[
     "let", "const", "fn", "if", "else", "while", "for", "in", "return", "break", "continue",
     "match", "def", "enum", "impl", "trait", "async", "await", "lazy", "mut", "true", "false",
-    "learn", "teach",
+    "learn", "teach", "try", "catch", "finally", "throw", "defer",
 ];
 pub const OPERATORS: &[char] = &[
     '+', '-', '*', '/', '%', '=', '!', '<', '>', '&', '|', '^', '~', '.', '@', '?',
 ];
 pub const PUNCT: &[char] = &[',', ';', ':', '(', ')', '{', '}', '[', ']', '#'];
 
+/// Read the text of a number literal starting at a digit: `42`, `3.14`,
+/// `1_000_000`, `0xFF`, `0o755` or `0b1010`. A `.` is only part of the number
+/// when a digit follows it, so `(6).is_int()` and `6.is_int()` both call a method.
+pub fn read_number_literal(input: &mut InputStream) -> String {
+    let mut text = String::new();
+    let radix = input.peek() == Some('0')
+        && matches!(input.peek_nth(1), Some('x' | 'X' | 'o' | 'O' | 'b' | 'B'));
+    if radix {
+        text.extend(input.by_ref().take(2));
+        // Any letter is taken, so a bad digit like `0xFG` is reported rather than split
+        while let Some(c) = input.peek() {
+            if !(c.is_ascii_alphanumeric() || c == '_') {
+                break;
+            }
+            text.push(c);
+            input.next();
+        }
+        return text;
+    }
+
+    let mut seen_dot = false;
+    while let Some(c) = input.peek() {
+        let fraction_starts =
+            c == '.' && !seen_dot && input.peek_nth(1).is_some_and(|next| next.is_ascii_digit());
+        if fraction_starts {
+            seen_dot = true;
+        } else if !(c.is_ascii_digit() || c == '_') {
+            break;
+        }
+        text.push(c);
+        input.next();
+    }
+    text
+}
+
+/// The value of a number literal read by [`read_number_literal`]. Underscores
+/// may separate digits, but not start or end a group of them.
+pub fn parse_number_literal(text: &str) -> std::result::Result<Decimal, String> {
+    let lower = text.to_ascii_lowercase();
+    let (radix, digits, name) = match lower.get(..2) {
+        Some("0x") => (16, &text[2..], "hexadecimal"),
+        Some("0o") => (8, &text[2..], "octal"),
+        Some("0b") => (2, &text[2..], "binary"),
+        _ => (10, text, "number"),
+    };
+
+    let groups_ok = |part: &str| part.split('_').all(|group| !group.is_empty());
+    if digits.is_empty() {
+        return Err(format!("Missing digits in {} literal '{}'", name, text));
+    }
+    if !digits.split('.').all(groups_ok) {
+        return Err(format!("Underscores in '{}' must be between digits", text));
+    }
+
+    let digits = digits.replace('_', "");
+    if radix == 10 {
+        return Decimal::from_str(&digits).map_err(|e| e.to_string());
+    }
+    let value = u128::from_str_radix(&digits, radix)
+        .map_err(|_| format!("Invalid digit in {} literal '{}'", name, text))?;
+    i128::try_from(value)
+        .ok()
+        .and_then(|value| Decimal::try_from_i128_with_scale(value, 0).ok())
+        .ok_or_else(|| format!("Number literal '{}' is too large", text))
+}
+
 impl TokenStream<'_> {
     pub fn is_keyword(s: &str) -> bool {
         KEYWORDS.contains(&s)
     }
 
     pub fn is_digit(c: char) -> bool {
-        c.is_numeric()
+        c.is_ascii_digit()
     }
 
     pub fn is_ident_start(c: char) -> bool {
-        c.is_alphabetic()
+        c.is_alphabetic() || c == '_'
     }
 
     pub fn is_ident_body(c: char) -> bool {
@@ -96,23 +168,10 @@ impl TokenStream<'_> {
     }
 
     pub fn read_number(&mut self) -> Result<Token> {
-        let mut is_float = false;
-        let num = self.read_while(|c| {
-            if c == '.' {
-                if is_float {
-                    false
-                } else {
-                    is_float = true;
-                    true
-                }
-            } else {
-                c.is_numeric()
-            }
-        });
-
-        Ok(Token::Number(Decimal::from_str(&num).map_err(|e| {
-            self.input.croak(e.to_string(), Some(num.len()))
-        })?))
+        let num = read_number_literal(&mut self.input);
+        Ok(Token::Number(
+            parse_number_literal(&num).map_err(|e| self.input.croak(e, Some(num.len())))?,
+        ))
     }
 
     pub fn read_ident(&mut self) -> Result<Token> {
@@ -278,6 +337,13 @@ impl TokenStream<'_> {
                 .croak("Unterminated template expression".to_string(), None));
         }
 
+        // A `:` outside any brackets or strings starts a format specifier
+        let spec = Self::template_format_split(&expr_text).map(|at| {
+            let spec = expr_text[at + 1..].trim().to_string();
+            expr_text.truncate(at);
+            spec
+        });
+
         // Now parse the expression text as a separate token stream
         if !expr_text.is_empty() {
             let expr_input = InputStream::new("template_expr", &expr_text);
@@ -285,13 +351,41 @@ impl TokenStream<'_> {
 
             // Parse all tokens from the expression
             while let Some(token_result) = expr_stream.read_next_token() {
-                tokens.push(token_result?);
+                tokens.push(token_result?.0);
             }
         }
+        tokens.extend(spec.map(Token::TemplateFormat));
 
         Ok(tokens)
     }
 
+    /// Byte offset of the `:` that separates a template expression from its
+    /// format specifier, skipping `::` paths and anything nested or quoted
+    fn template_format_split(expr: &str) -> Option<usize> {
+        let bytes = expr.as_bytes();
+        let mut depth = 0usize;
+        let mut quote = None;
+        let mut i = 0;
+        while i < bytes.len() {
+            let c = bytes[i];
+            match quote {
+                Some(_) if c == b'\\' => i += 1,
+                Some(q) if c == q => quote = None,
+                Some(_) => {}
+                None => match c {
+                    b'"' | b'`' => quote = Some(c),
+                    b'(' | b'[' | b'{' => depth += 1,
+                    b')' | b']' | b'}' => depth = depth.saturating_sub(1),
+                    b':' if bytes.get(i + 1) == Some(&b':') => i += 1,
+                    b':' if depth == 0 => return Some(i),
+                    _ => {}
+                },
+            }
+            i += 1;
+        }
+        None
+    }
+
     pub fn skip_whitespace_and_comments(&mut self) -> Result<()> {
         loop {
             // Skip regular whitespace
@@ -311,31 +405,14 @@ impl TokenStream<'_> {
                 if !self.input.eof() {
                     let next = self.input.peek().unwrap();
                     if next == '/' {
-                        self.input.next(); // consume second '/'
-
-                        // Check if it's a doc comment (///)
-                        if !self.input.eof() && self.input.peek().unwrap() == '/' {
-                            self.input.next(); // consume third '/'
-                                               // This is a doc comment, capture it
-                            let doc_text = self.read_while(|c| c != '\n').trim().to_string();
-                            self.last_doc_comment = Some(doc_text);
-                            continue;
-                        } else {
-                            // Regular inline comment: skip to end of line
-                            self.read_while(|c| c != '\n');
-                            continue;
-                        }
+                        // Line comment, `///` for docs: skip to end of line
+                        self.read_while(|c| c != '\n');
+                        self.record_comment(pos);
+                        continue;
                     } else if next == '*' {
                         self.input.next(); // consume '*'
 
-                        // Check if it's a doc comment (/** */)
-                        let is_doc_comment = !self.input.eof() && self.input.peek().unwrap() == '*';
-                        if is_doc_comment {
-                            self.input.next(); // consume third '*'
-                        }
-
-                        // Read block comment content
-                        let mut comment_text = String::new();
+                        // Block comment, `/** */` for docs
                         let mut found_end = false;
                         while !self.input.eof() {
                             let c = self.input.next().unwrap();
@@ -345,9 +422,6 @@ impl TokenStream<'_> {
                                     found_end = true;
                                     break;
                                 }
-                            if is_doc_comment {
-                                comment_text.push(c);
-                            }
                         }
 
                         if !found_end {
@@ -356,9 +430,7 @@ impl TokenStream<'_> {
                                 .croak("Unterminated block comment".to_string(), None));
                         }
 
-                        if is_doc_comment {
-                            self.last_doc_comment = Some(comment_text.trim().to_string());
-                        }
+                        self.record_comment(pos);
                         continue;
                     }
                 }
@@ -374,7 +446,14 @@ impl TokenStream<'_> {
         Ok(())
     }
 
-    pub fn parse_next(&mut self) -> Result<Option<Token>> {
+    /// Keep the comment read since `start`
+    fn record_comment(&mut self, start: Position) {
+        let span = self.input.span_from(start);
+        let text = self.input.slice(span.start, span.end).into_owned();
+        self.comments.push(Comment { text, span });
+    }
+
+    pub fn parse_next(&mut self) -> Result<Option<(Token, Span)>> {
         // Check if there's a token in the buffer first
         if !self.buffer.is_empty() {
             return Ok(Some(self.buffer.remove(0)));
@@ -385,20 +464,28 @@ impl TokenStream<'_> {
             return Ok(None);
         }
 
+        let start = self.input.save_position();
         let tok = match self.input.peek().unwrap() {
             '"' => self.read_string(),
             '`' => {
                 // Handle template literals
                 let template_tokens = self.read_template_literal()?;
+                // Every part of a template shares the span of the whole literal
+                let span = self.input.span_from(start);
                 // Add all tokens except the first one to the buffer
                 for token in template_tokens.into_iter().skip(1).rev() {
-                    self.buffer.insert(0, token);
+                    self.buffer.insert(0, (token, span));
                 }
                 // Return the first token (TemplateStart)
                 Ok(Token::TemplateStart)
             }
             c if Self::is_digit(c) => self.read_number(),
             c if Self::is_ident_start(c) => self.read_ident(),
+            ':' if self.input.peek_nth(1) == Some(':') => {
+                self.input.next();
+                self.input.next();
+                Ok(Token::Punct("::".to_string()))
+            }
             c if Self::is_punct(c) => {
                 self.input.next(); // consume the character
                 Ok(Token::Punct(c.to_string()))
@@ -411,12 +498,22 @@ impl TokenStream<'_> {
             }
         };
 
-        Some(tok).transpose()
+        let span = self.input.span_from(start);
+        Some(tok.map(|tok| (tok, span))).transpose()
     }
 
     fn read_op(&mut self) -> Result<Token> {
         let mut op = String::new();
 
+        // The spread operator and rest parameters: `...items`
+        if self.input.peek() == Some('.')
+            && self.input.peek_nth(1) == Some('.')
+            && self.input.peek_nth(2) == Some('.')
+        {
+            op.extend(self.input.by_ref().take(3));
+            return Ok(Token::Op(op));
+        }
+
         while let Some(c) = self.input.peek() {
             if Self::is_op(c) {
                 // Peek lookahead for specific multi-character operators
@@ -426,7 +523,8 @@ impl TokenStream<'_> {
                 } else {
                     let combined = format!("{}{}", op, c);
                     match combined.as_str() {
-                        "==" | "!=" | "<=" | ">=" | "&&" | "||" | "<<" | ">>" | "=>" | "->" => {
+                        "==" | "!=" | "<=" | ">=" | "&&" | "||" | "<<" | ">>" | "=>" | "->"
+                        | ".." => {
                             op.push(c);
                             self.input.next();
                             break; // Done with these specific 2-char ops
@@ -442,15 +540,11 @@ impl TokenStream<'_> {
         Ok(Token::Op(op))
     }
 
-    pub fn read_next_token(&mut self) -> Option<Result<Token>> {
+    pub fn read_next_token(&mut self) -> Option<Result<(Token, Span)>> {
         self.parse_next().transpose()
     }
 
-    pub fn push_back(&mut self, token: Token) {
-        self.buffer.insert(0, token);
-    }
-
-    pub fn take_last_doc_comment(&mut self) -> Option<String> {
-        self.last_doc_comment.take()
+    pub fn push_back(&mut self, token: Token, span: Span) {
+        self.buffer.insert(0, (token, span));
     }
 }
@@ -0,0 +1,115 @@
+//! Comments and blank lines, attached to the statements they belong to.
+//!
+//! The lexer records every comment it skips. As the parser finishes a
+//! statement it takes the comments written before it, inside it and after it
+//! on the same line, and notes whether a blank line separates it from the
+//! code before. Tools that print or rewrite code use them to keep comments
+//! in place, and documentation is read from [`Trivia::doc`].
+
+use super::span::Span;
+use serde::Serialize;
+
+/// A comment as written, markers included
+#[derive(Debug, Clone, Serialize)]
+pub struct Comment {
+    /// e.g. `// note` or `/** Docs */`
+    pub text: String,
+    pub span: Span,
+}
+
+impl Comment {
+    /// Whether this is a `/* */` comment rather than one running to the end of the line
+    pub fn is_block(&self) -> bool {
+        self.text.starts_with("/*")
+    }
+
+    /// Whether this is a `///` or `/** */` doc comment
+    pub fn is_doc(&self) -> bool {
+        self.text.starts_with("///") || (self.text.starts_with("/**") && self.text != "/**/")
+    }
+}
+
+/// The comments and blank lines around a statement
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Trivia {
+    /// Whether a blank line separates the statement, or its leading
+    /// comments, from the code before it
+    pub blank_line_before: bool,
+    /// Comments between the previous code and the statement
+    pub leading: Vec<Comment>,
+    /// A comment after the end of the statement on its line, such as
+    /// `x = 1; // why`. When statements end together, like a function and
+    /// its body, the innermost one gets it.
+    pub trailing: Option<Comment>,
+    /// Comments inside the statement that no statement within it took,
+    /// like one at the end of a block or between the arguments of a call
+    pub inner: Vec<Comment>,
+}
+
+impl Trivia {
+    pub fn is_empty(&self) -> bool {
+        !self.blank_line_before
+            && self.leading.is_empty()
+            && self.trailing.is_none()
+            && self.inner.is_empty()
+    }
+
+    /// The text of the doc comment right before the statement: a run of
+    /// `///` lines or a `/** */` comment. Blank lines and indentation inside
+    /// it are kept, so it can be read as Markdown.
+    pub fn doc(&self) -> Option<String> {
+        let last = self.leading.last().filter(|comment| comment.is_doc())?;
+        let lines: Vec<&str> = if last.is_block() {
+            block_doc_lines(&last.text)
+        } else {
+            let first = self
+                .leading
+                .iter()
+                .rposition(|comment| !comment.is_doc() || comment.is_block())
+                .map_or(0, |i| i + 1);
+            self.leading[first..]
+                .iter()
+                .map(|comment| doc_line(&comment.text[3..]))
+                .collect()
+        };
+
+        let start = lines.iter().position(|line| !line.is_empty());
+        let end = lines.iter().rposition(|line| !line.is_empty());
+        Some(match (start, end) {
+            (Some(start), Some(end)) => lines[start..=end].join("\n"),
+            _ => String::new(),
+        })
+    }
+}
+
+/// Comments never take part in equality, like spans
+impl PartialEq for Trivia {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+/// The lines of a `/** */` comment, on one line or with a `*` starting each line
+fn block_doc_lines(text: &str) -> Vec<&str> {
+    let text = text.strip_prefix("/**").unwrap_or(text);
+    let text = text.strip_suffix("*/").unwrap_or(text);
+    let mut lines: Vec<&str> = text.lines().collect();
+    if let [line] = lines.as_slice() {
+        return vec![line.trim()];
+    }
+    for line in lines.iter_mut().skip(1) {
+        let trimmed = line.trim();
+        *line = trimmed.strip_prefix('*').unwrap_or(trimmed);
+    }
+    lines.into_iter().map(doc_line).collect()
+}
+
+/// The text of one doc comment line after its `///` or `*`, keeping indentation past the first space
+pub fn doc_line(text: &str) -> &str {
+    text.strip_prefix(' ').unwrap_or(text).trim_end()
+}
+
+/// Whether the whitespace between two pieces of code holds an empty line
+pub(crate) fn has_blank_line(gap: &str) -> bool {
+    gap.matches('\n').count() > 1
+}
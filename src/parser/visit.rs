@@ -0,0 +1,243 @@
+//! Walking the syntax tree without writing out every variant.
+//!
+//! An analysis implements [`Visitor`] and overrides only the methods for the
+//! nodes it cares about. Each default method calls the matching `walk_*`
+//! function, which visits the children of the node, so an override that
+//! still wants to look inside calls it too:
+//!
+//! ```
+//! use loft::parser::visit::{self, Visitor};
+//! use loft::parser::{Expr, ExprKind};
+//!
+//! /// Counts the calls in a program
+//! struct Calls(usize);
+//!
+//! impl Visitor for Calls {
+//!     fn visit_expr(&mut self, expr: &Expr) {
+//!         if let ExprKind::Call { .. } = expr.kind {
+//!             self.0 += 1;
+//!         }
+//!         visit::walk_expr(self, expr);
+//!     }
+//! }
+//! ```
+
+use super::{Attribute, Expr, ExprKind, Stmt, StmtKind, TemplatePart, TraitMethod};
+
+pub trait Visitor {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr);
+    }
+
+    /// The pattern of a `match` arm, which is parsed as an expression but
+    /// binds names rather than reading them
+    fn visit_pattern(&mut self, pattern: &Expr) {
+        walk_expr(self, pattern);
+    }
+
+    fn visit_attribute(&mut self, attr: &Attribute) {
+        attr.args.iter().for_each(|arg| self.visit_expr(arg));
+    }
+}
+
+/// Visit every statement of a program or block
+pub fn walk_stmts<V: Visitor + ?Sized>(visitor: &mut V, stmts: &[Stmt]) {
+    stmts.iter().for_each(|stmt| visitor.visit_stmt(stmt));
+}
+
+/// Visit the statements and expressions directly inside `stmt`
+pub fn walk_stmt<V: Visitor + ?Sized>(visitor: &mut V, stmt: &Stmt) {
+    match &stmt.kind {
+        StmtKind::VarDecl { value, .. } => value.iter().for_each(|v| visitor.visit_expr(v)),
+        StmtKind::ConstDecl { value, .. } | StmtKind::Assign { value, .. } => {
+            visitor.visit_expr(value)
+        }
+        StmtKind::FunctionDecl { body, .. } => visitor.visit_stmt(body),
+        StmtKind::AttrStmt { attr, stmt } => {
+            visitor.visit_attribute(attr);
+            visitor.visit_stmt(stmt);
+        }
+        StmtKind::ImplBlock { methods, .. } => walk_stmts(visitor, methods),
+        StmtKind::TraitDecl { methods, .. } => {
+            for method in methods {
+                if let TraitMethod::Default { body, .. } = method {
+                    visitor.visit_stmt(body);
+                }
+            }
+        }
+        StmtKind::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            visitor.visit_expr(condition);
+            visitor.visit_stmt(then_branch);
+            else_branch.iter().for_each(|e| visitor.visit_stmt(e));
+        }
+        StmtKind::While { condition, body } => {
+            visitor.visit_expr(condition);
+            visitor.visit_stmt(body);
+        }
+        StmtKind::For { iterable, body, .. } => {
+            visitor.visit_expr(iterable);
+            visitor.visit_stmt(body);
+        }
+        StmtKind::Match { expr, arms } => {
+            visitor.visit_expr(expr);
+            for (pattern, body) in arms {
+                visitor.visit_pattern(pattern);
+                visitor.visit_stmt(body);
+            }
+        }
+        StmtKind::Return(value) => value.iter().for_each(|v| visitor.visit_expr(v)),
+        StmtKind::Try {
+            body,
+            catch,
+            finally,
+        } => {
+            visitor.visit_stmt(body);
+            if let Some((_, catch_body)) = catch {
+                visitor.visit_stmt(catch_body);
+            }
+            finally.iter().for_each(|f| visitor.visit_stmt(f));
+        }
+        StmtKind::Throw(expr) | StmtKind::Defer(expr) | StmtKind::Expr(expr) => {
+            visitor.visit_expr(expr)
+        }
+        StmtKind::Block(stmts) => walk_stmts(visitor, stmts),
+        StmtKind::ImportDecl { .. }
+        | StmtKind::StructDecl { .. }
+        | StmtKind::EnumDecl { .. }
+        | StmtKind::Break
+        | StmtKind::Continue => {}
+    }
+}
+
+/// Visit the statements and expressions directly inside `expr`
+pub fn walk_expr<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expr) {
+    match &expr.kind {
+        ExprKind::BinOp { left, right, .. } => {
+            visitor.visit_expr(left);
+            visitor.visit_expr(right);
+        }
+        ExprKind::UnaryOp { expr, .. }
+        | ExprKind::Await(expr)
+        | ExprKind::Async(expr)
+        | ExprKind::Lazy(expr)
+        | ExprKind::Try(expr)
+        | ExprKind::Spread(expr) => visitor.visit_expr(expr),
+        ExprKind::Call { func, args } => {
+            visitor.visit_expr(func);
+            args.iter().for_each(|a| visitor.visit_expr(a));
+        }
+        ExprKind::FieldAccess { object, .. } => visitor.visit_expr(object),
+        ExprKind::ArrayLiteral(items) => items.iter().for_each(|i| visitor.visit_expr(i)),
+        ExprKind::MapLiteral(entries) => {
+            for (key, value) in entries {
+                visitor.visit_expr(key);
+                visitor.visit_expr(value);
+            }
+        }
+        ExprKind::StructLiteral { fields, .. } => {
+            fields.iter().for_each(|(_, v)| visitor.visit_expr(v))
+        }
+        ExprKind::Index { array, index } => {
+            visitor.visit_expr(array);
+            visitor.visit_expr(index);
+        }
+        ExprKind::Slice { array, start, end } => {
+            visitor.visit_expr(array);
+            [start, end]
+                .into_iter()
+                .flatten()
+                .for_each(|bound| visitor.visit_expr(bound));
+        }
+        ExprKind::Lambda { body, .. } => visitor.visit_expr(body),
+        ExprKind::Block(stmts) => walk_stmts(visitor, stmts),
+        ExprKind::TemplateLiteral { parts } => {
+            for part in parts {
+                if let TemplatePart::Expression(expr, _) = part {
+                    visitor.visit_expr(expr);
+                }
+            }
+        }
+        ExprKind::Match { expr, arms } => {
+            visitor.visit_expr(expr);
+            for (pattern, body) in arms {
+                visitor.visit_pattern(pattern);
+                visitor.visit_expr(body);
+            }
+        }
+        ExprKind::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            visitor.visit_expr(condition);
+            visitor.visit_expr(then_branch);
+            else_branch.iter().for_each(|e| visitor.visit_expr(e));
+        }
+        ExprKind::Number(_)
+        | ExprKind::Ident(_)
+        | ExprKind::Local { .. }
+        | ExprKind::String(_)
+        | ExprKind::Boolean(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{InputStream, Parser};
+
+    /// Records the identifiers read and the names bound by patterns
+    #[derive(Default)]
+    struct Names {
+        read: Vec<String>,
+        patterns: usize,
+    }
+
+    impl Visitor for Names {
+        fn visit_expr(&mut self, expr: &Expr) {
+            if let ExprKind::Ident(name) = &expr.kind {
+                self.read.push(name.clone());
+            }
+            walk_expr(self, expr);
+        }
+
+        fn visit_pattern(&mut self, _pattern: &Expr) {
+            self.patterns += 1;
+        }
+    }
+
+    #[test]
+    fn test_walk_reaches_nested_code() {
+        let code = r#"
+            #[test(a)]
+            fn f() {
+                let g = (x) => { return b[c..]; };
+                let r = match d { Some(y) => `${e}`, None => #{ "k": f2(...h) } };
+            }
+            trait T {
+                fn m(self) -> num { return i; }
+            }
+            if (j) { while (k) { try { throw l; } catch (err) { defer m(); } } }
+        "#
+        .to_string();
+        let stmts = Parser::new(InputStream::new("test", &code))
+            .parse()
+            .unwrap();
+
+        let mut names = Names::default();
+        walk_stmts(&mut names, &stmts);
+        assert_eq!(
+            names.read,
+            ["a", "b", "c", "d", "e", "f2", "h", "i", "j", "k", "l", "m"]
+        );
+        assert_eq!(names.patterns, 2);
+    }
+}
@@ -0,0 +1,352 @@
+//! Client for the loft package registry HTTP API.
+//!
+//! Used by `loft add`, `loft update`, `loft publish` and the other commands
+//! that talk to a registry. Requests time out instead of hanging, and reads
+//! are retried when the connection fails or the registry returns a 5xx.
+
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Registry used when `LOFT_REGISTRY` isn't set
+pub const DEFAULT_REGISTRY: &str = "https://loft.fargone.sh";
+
+/// How many times a read is attempted before giving up
+const MAX_ATTEMPTS: u32 = 3;
+/// Delay before the first retry, doubled for each following one
+const RETRY_DELAY: Duration = Duration::from_millis(250);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// One version of a package as listed by the registry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PackageVersion {
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub repository: Option<String>,
+    #[serde(default)]
+    pub authors: Vec<String>,
+    #[serde(default)]
+    pub license: Option<String>,
+    #[serde(default)]
+    pub owners: Vec<String>,
+    #[serde(default)]
+    pub yanked: bool,
+}
+
+/// Body of `POST /packages/publish`
+#[derive(Debug, Clone, Serialize)]
+pub struct PublishRequest {
+    pub name: String,
+    pub version: String,
+    pub description: Option<String>,
+    pub repository: Option<String>,
+    pub authors: Vec<String>,
+    pub license: Option<String>,
+    pub manifest: serde_json::Value,
+    /// Base64-encoded `.tar.gz` of the package
+    pub tarball: String,
+}
+
+#[derive(Debug)]
+pub enum RegistryError {
+    /// The registry couldn't be reached or the request timed out
+    Network(reqwest::Error),
+    NotFound,
+    /// No token, or the registry rejected it
+    Unauthorized,
+    /// The token's user doesn't own the package
+    Forbidden,
+    /// The version already exists
+    Conflict,
+    /// Any other unsuccessful response
+    Status(StatusCode, String),
+    InvalidResponse(String),
+}
+
+impl std::fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegistryError::Network(e) => write!(f, "Failed to connect to registry: {}", e),
+            RegistryError::NotFound => write!(f, "Not found in registry"),
+            RegistryError::Unauthorized => write!(f, "Not logged in. Run 'loft login' first."),
+            RegistryError::Forbidden => write!(f, "Not an owner of this package"),
+            RegistryError::Conflict => write!(f, "Version already exists in the registry"),
+            RegistryError::Status(status, body) if body.is_empty() => {
+                write!(f, "Registry returned {}", status)
+            }
+            RegistryError::Status(status, body) => {
+                write!(f, "Registry returned {} - {}", status, body)
+            }
+            RegistryError::InvalidResponse(e) => {
+                write!(f, "Failed to parse registry response: {}", e)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+impl From<reqwest::Error> for RegistryError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_decode() {
+            RegistryError::InvalidResponse(e.to_string())
+        } else {
+            RegistryError::Network(e)
+        }
+    }
+}
+
+pub type RegistryResult<T> = Result<T, RegistryError>;
+
+pub struct RegistryClient {
+    url: String,
+    token: Option<String>,
+    http: Client,
+}
+
+impl RegistryClient {
+    pub fn new(url: impl Into<String>) -> Self {
+        let http = Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .user_agent(concat!("loft/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .unwrap_or_default();
+        Self {
+            url: normalize_url(url.into()),
+            token: None,
+            http,
+        }
+    }
+
+    /// The registry named by `LOFT_REGISTRY`, or the default one
+    pub fn from_env() -> Self {
+        Self::new(std::env::var("LOFT_REGISTRY").unwrap_or_else(|_| DEFAULT_REGISTRY.to_string()))
+    }
+
+    /// Authenticate requests that change the registry with an API token
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Base URL of the registry, without a trailing slash
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Every version of a package, oldest first
+    pub fn get_package(&self, name: &str) -> RegistryResult<Vec<PackageVersion>> {
+        let url = format!("{}/packages/{}", self.url, urlencoding::encode(name));
+        Ok(self.get(&url)?.json()?)
+    }
+
+    /// The `.tar.gz` of one version of a package
+    pub fn download(&self, name: &str, version: &str) -> RegistryResult<Vec<u8>> {
+        let url = format!(
+            "{}/packages/{}/{}/download",
+            self.url,
+            urlencoding::encode(name),
+            urlencoding::encode(version)
+        );
+        Ok(self.get(&url)?.bytes()?.to_vec())
+    }
+
+    /// Packages matching every word of `query`, best match first
+    pub fn search(&self, query: &str) -> RegistryResult<Vec<PackageVersion>> {
+        let url = format!(
+            "{}/packages/search?q={}",
+            self.url,
+            urlencoding::encode(query)
+        );
+        Ok(self.get(&url)?.json()?)
+    }
+
+    /// Publish a new version. Not retried, since the registry may have stored
+    /// the version before the connection dropped.
+    pub fn publish(&self, request: &PublishRequest) -> RegistryResult<()> {
+        let url = format!("{}/packages/publish", self.url);
+        self.send(self.authorized(self.http.post(&url))?.json(request))?;
+        Ok(())
+    }
+
+    /// Yank a version so resolvers skip it, or restore it with `yanked: false`
+    pub fn set_yanked(&self, name: &str, version: &str, yanked: bool) -> RegistryResult<()> {
+        let url = format!(
+            "{}/packages/{}/{}",
+            self.url,
+            urlencoding::encode(name),
+            urlencoding::encode(version)
+        );
+        let request = if yanked {
+            self.http.delete(&url)
+        } else {
+            self.http
+                .patch(&url)
+                .json(&serde_json::json!({ "yanked": false }))
+        };
+        self.send(self.authorized(request)?)?;
+        Ok(())
+    }
+
+    fn authorized(&self, request: RequestBuilder) -> RegistryResult<RequestBuilder> {
+        match &self.token {
+            Some(token) => Ok(request.bearer_auth(token)),
+            None => Err(RegistryError::Unauthorized),
+        }
+    }
+
+    /// GET `url`, retrying connection failures and server errors
+    fn get(&self, url: &str) -> RegistryResult<Response> {
+        let mut delay = RETRY_DELAY;
+        let mut attempt = 1;
+        loop {
+            let result = self.send(self.http.get(url));
+            let retryable = match &result {
+                Err(RegistryError::Network(_)) => true,
+                Err(RegistryError::Status(status, _)) => status.is_server_error(),
+                _ => false,
+            };
+            if !retryable || attempt == MAX_ATTEMPTS {
+                return result;
+            }
+            std::thread::sleep(delay);
+            delay *= 2;
+            attempt += 1;
+        }
+    }
+
+    fn send(&self, request: RequestBuilder) -> RegistryResult<Response> {
+        let response = request.send()?;
+        match response.status() {
+            status if status.is_success() => Ok(response),
+            StatusCode::NOT_FOUND => Err(RegistryError::NotFound),
+            StatusCode::UNAUTHORIZED => Err(RegistryError::Unauthorized),
+            StatusCode::FORBIDDEN => Err(RegistryError::Forbidden),
+            StatusCode::CONFLICT => Err(RegistryError::Conflict),
+            status => Err(RegistryError::Status(
+                status,
+                response.text().unwrap_or_default(),
+            )),
+        }
+    }
+}
+
+/// Downgrade https → http for loopback addresses so local dev servers
+/// (which don't have TLS) work without extra flags.
+pub fn normalize_url(url: String) -> String {
+    let url = url.trim_end_matches('/').to_string();
+    if url.starts_with("https://127.0.0.1")
+        || url.starts_with("https://localhost")
+        || url.starts_with("https://[::1]")
+    {
+        url.replacen("https://", "http://", 1)
+    } else {
+        url
+    }
+}
+
+/// Where `loft login` stores the API token: `~/.loft/token`
+pub fn token_path() -> Option<PathBuf> {
+    let home = std::env::var("HOME").ok()?;
+    Some(PathBuf::from(home).join(".loft").join("token"))
+}
+
+/// The API token saved by `loft login`
+pub fn load_token() -> Option<String> {
+    let token = std::fs::read_to_string(token_path()?).ok()?;
+    let token = token.trim();
+    (!token.is_empty()).then(|| token.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    /// Serve one canned response per connection, in order
+    fn serve(responses: Vec<(&'static str, &'static str)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for (status, body) in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buf = [0; 4096];
+                let _ = stream.read(&mut buf);
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn test_get_package_retries_server_errors() {
+        let url = serve(vec![
+            ("503 Service Unavailable", ""),
+            (
+                "200 OK",
+                r#"[{"name":"http","version":"1.0.0","yanked":true},{"name":"http","version":"1.1.0","description":"Client"}]"#,
+            ),
+        ]);
+        let versions = RegistryClient::new(url).get_package("http").unwrap();
+        assert_eq!(versions.len(), 2);
+        assert!(versions[0].yanked);
+        assert_eq!(versions[1].version, "1.1.0");
+        assert_eq!(versions[1].description.as_deref(), Some("Client"));
+    }
+
+    #[test]
+    fn test_errors() {
+        let url = serve(vec![("404 Not Found", ""), ("409 Conflict", "")]);
+        let client = RegistryClient::new(url);
+        assert!(matches!(
+            client.download("http", "9.9.9"),
+            Err(RegistryError::NotFound)
+        ));
+
+        // Changes need a token, and publishing is never retried
+        assert!(matches!(
+            client.set_yanked("http", "1.0.0", true),
+            Err(RegistryError::Unauthorized)
+        ));
+        let request = PublishRequest {
+            name: "http".to_string(),
+            version: "1.0.0".to_string(),
+            description: None,
+            repository: None,
+            authors: Vec::new(),
+            license: None,
+            manifest: serde_json::json!({}),
+            tarball: String::new(),
+        };
+        assert!(matches!(
+            client.with_token("secret").publish(&request),
+            Err(RegistryError::Conflict)
+        ));
+    }
+
+    #[test]
+    fn test_normalize_url() {
+        assert_eq!(
+            normalize_url("https://localhost:5050/".to_string()),
+            "http://localhost:5050"
+        );
+        assert_eq!(
+            normalize_url("https://loft.fargone.sh".to_string()),
+            "https://loft.fargone.sh"
+        );
+    }
+}
@@ -0,0 +1,389 @@
+//! Benchmark harness used by `loft bench`.
+//!
+//! A bench file is any `*_bench.lf` file. If the file declares functions
+//! marked with `#[bench]`, each of them is timed on its own; otherwise the
+//! whole file is timed as a single bench. Every bench first runs a few warmup
+//! iterations that aren't measured, then the measured ones, which are
+//! summarized by their mean, median and standard deviation.
+//!
+//! Results can be saved as JSON and compared against by a later run, so
+//! slowdowns in loft code, or in the interpreter itself, show up as a change
+//! from the saved mean.
+
+#[cfg(not(target_arch = "wasm32"))]
+use super::test_harness::discover_files;
+use super::test_harness::functions_marked;
+use super::{Interpreter, RuntimeError};
+use crate::parser::{InputStream, Parser, Stmt};
+use serde::{Deserialize, Serialize};
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Suffix that marks a file as a bench file
+pub const BENCH_FILE_SUFFIX: &str = "_bench.lf";
+
+/// How many times each bench runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchOptions {
+    /// Runs before measuring, to fill caches and settle the CPU
+    pub warmup: usize,
+    /// Measured runs, at least one
+    pub iterations: usize,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        Self {
+            warmup: 3,
+            iterations: 10,
+        }
+    }
+}
+
+/// Timings of the measured runs of a bench
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BenchStats {
+    pub iterations: usize,
+    pub mean: Duration,
+    pub median: Duration,
+    /// Sample standard deviation, zero for a single run
+    pub stddev: Duration,
+    pub min: Duration,
+    pub max: Duration,
+}
+
+impl BenchStats {
+    /// Summarize the durations of the measured runs, which must not be empty
+    pub fn from_samples(samples: &[Duration]) -> Self {
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+        let n = sorted.len();
+        let seconds: Vec<f64> = sorted.iter().map(Duration::as_secs_f64).collect();
+        let mean = seconds.iter().sum::<f64>() / n as f64;
+        let median = if n.is_multiple_of(2) {
+            (seconds[n / 2 - 1] + seconds[n / 2]) / 2.0
+        } else {
+            seconds[n / 2]
+        };
+        let variance = if n > 1 {
+            seconds.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / (n - 1) as f64
+        } else {
+            0.0
+        };
+
+        Self {
+            iterations: n,
+            mean: Duration::from_secs_f64(mean),
+            median: Duration::from_secs_f64(median),
+            stddev: Duration::from_secs_f64(variance.sqrt()),
+            min: sorted[0],
+            max: sorted[n - 1],
+        }
+    }
+}
+
+/// The outcome of a single bench
+#[derive(Debug, Clone)]
+pub enum BenchOutcome {
+    Measured(BenchStats),
+    Failed(RuntimeError),
+}
+
+#[derive(Debug, Clone)]
+pub struct BenchCaseResult {
+    pub name: String,
+    pub outcome: BenchOutcome,
+}
+
+/// All bench results for one bench file
+#[derive(Debug, Clone)]
+pub struct BenchFileReport {
+    pub path: String,
+    pub cases: Vec<BenchCaseResult>,
+}
+
+/// A bench result as saved with `loft bench --save`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchRecord {
+    pub file: String,
+    pub name: String,
+    pub iterations: usize,
+    pub mean_ns: f64,
+    pub median_ns: f64,
+    pub stddev_ns: f64,
+}
+
+impl BenchRecord {
+    pub fn new(file: &str, name: &str, stats: &BenchStats) -> Self {
+        let nanos = |d: Duration| d.as_secs_f64() * 1e9;
+        Self {
+            file: file.to_string(),
+            name: name.to_string(),
+            iterations: stats.iterations,
+            mean_ns: nanos(stats.mean),
+            median_ns: nanos(stats.median),
+            stddev_ns: nanos(stats.stddev),
+        }
+    }
+
+    /// How this result compares to the same bench in `baseline`, if it has it
+    pub fn compare(&self, baseline: &[BenchRecord]) -> Option<BenchChange> {
+        let before = baseline
+            .iter()
+            .find(|b| b.file == self.file && b.name == self.name)?;
+        let difference = self.mean_ns - before.mean_ns;
+        Some(BenchChange {
+            percent: difference / before.mean_ns * 100.0,
+            // Differences within the spread of either run are noise
+            significant: difference.abs() > before.stddev_ns + self.stddev_ns,
+        })
+    }
+}
+
+/// The change in a bench's mean from a saved run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BenchChange {
+    /// Positive when the bench got slower
+    pub percent: f64,
+    pub significant: bool,
+}
+
+/// Find all `*_bench.lf` files below `root`, skipping hidden directories,
+/// installed dependencies and build output. A file path is returned as-is.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn discover_bench_files(root: &Path) -> Vec<PathBuf> {
+    discover_files(root, BENCH_FILE_SUFFIX)
+}
+
+/// Time every bench in a source file whose name contains `filter`
+///
+/// A parse error is reported as a single failed bench named after the file.
+pub fn run_bench_source(
+    path: &str,
+    source: &str,
+    features: &[String],
+    filter: Option<&str>,
+    options: BenchOptions,
+) -> BenchFileReport {
+    let source_string = source.to_string();
+    let stmts = match Parser::new(InputStream::new(path, &source_string)).parse() {
+        Ok(stmts) => stmts,
+        Err(e) => {
+            return BenchFileReport {
+                path: path.to_string(),
+                cases: vec![BenchCaseResult {
+                    name: path.to_string(),
+                    outcome: BenchOutcome::Failed(RuntimeError::with_source(
+                        e.message,
+                        path.to_string(),
+                        source_string.clone(),
+                        e.position.saturating_sub(e.len.unwrap_or_default()),
+                        e.len.unwrap_or_default(),
+                    )),
+                }],
+            };
+        }
+    };
+
+    let new_interpreter =
+        || Interpreter::with_source(path, source).with_features(features.to_vec());
+    let bench_functions = functions_marked(&stmts, "bench");
+    let mut cases = Vec::new();
+
+    if bench_functions.is_empty() {
+        // The file itself is the bench, run from scratch every time
+        if filter.is_none_or(|f| path.contains(f)) {
+            let outcome = measure(options, || {
+                new_interpreter().eval_program(stmts.clone()).map(|_| ())
+            });
+            cases.push(BenchCaseResult {
+                name: path.to_string(),
+                outcome,
+            });
+        }
+    } else {
+        for name in bench_functions {
+            if !filter.is_none_or(|f| name.contains(f)) {
+                continue;
+            }
+
+            let mut interpreter = new_interpreter();
+            let outcome = match declare(&mut interpreter, &stmts, &name) {
+                Ok(function) => measure(options, || {
+                    interpreter.call_value(function.clone(), vec![]).map(|_| ())
+                }),
+                Err(e) => BenchOutcome::Failed(e),
+            };
+            cases.push(BenchCaseResult { name, outcome });
+        }
+    }
+
+    BenchFileReport {
+        path: path.to_string(),
+        cases,
+    }
+}
+
+/// Evaluate the top-level declarations of a bench file and look up one bench function
+fn declare(
+    interpreter: &mut Interpreter,
+    stmts: &[Stmt],
+    name: &str,
+) -> Result<super::Value, RuntimeError> {
+    // Top-level statements are evaluated one by one so that `main` is not invoked
+    for stmt in stmts {
+        interpreter.eval_stmt(stmt.clone())?;
+    }
+    interpreter
+        .env
+        .get(name)
+        .cloned()
+        .ok_or_else(|| RuntimeError::new(format!("Bench function '{}' not found", name)))
+}
+
+/// Run `bench` for the warmup iterations and then time the measured ones
+fn measure(
+    options: BenchOptions,
+    mut bench: impl FnMut() -> Result<(), RuntimeError>,
+) -> BenchOutcome {
+    let mut run = || -> Result<BenchStats, RuntimeError> {
+        for _ in 0..options.warmup {
+            bench()?;
+        }
+        let mut samples = Vec::with_capacity(options.iterations.max(1));
+        for _ in 0..options.iterations.max(1) {
+            let start = Instant::now();
+            bench()?;
+            samples.push(start.elapsed());
+        }
+        Ok(BenchStats::from_samples(&samples))
+    };
+    match run() {
+        Ok(stats) => BenchOutcome::Measured(stats),
+        Err(e) => BenchOutcome::Failed(e),
+    }
+}
+
+/// Read and run a bench file from disk
+#[cfg(not(target_arch = "wasm32"))]
+pub fn run_bench_file(
+    path: &Path,
+    features: &[String],
+    filter: Option<&str>,
+    options: BenchOptions,
+) -> BenchFileReport {
+    let display_path = path.display().to_string();
+    match std::fs::read_to_string(path) {
+        Ok(source) => run_bench_source(&display_path, &source, features, filter, options),
+        Err(e) => BenchFileReport {
+            path: display_path.clone(),
+            cases: vec![BenchCaseResult {
+                name: display_path,
+                outcome: BenchOutcome::Failed(RuntimeError::new(format!(
+                    "Failed to read bench file: {}",
+                    e
+                ))),
+            }],
+        },
+    }
+}
+
+/// A duration with a unit that keeps it readable, e.g. `1.25 ms`
+pub fn format_duration(duration: Duration) -> String {
+    let nanos = duration.as_secs_f64() * 1e9;
+    if nanos < 1e3 {
+        format!("{:.0} ns", nanos)
+    } else if nanos < 1e6 {
+        format!("{:.2} µs", nanos / 1e3)
+    } else if nanos < 1e9 {
+        format!("{:.2} ms", nanos / 1e6)
+    } else {
+        format!("{:.2} s", nanos / 1e9)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(values: &[u64]) -> Vec<Duration> {
+        values.iter().copied().map(Duration::from_millis).collect()
+    }
+
+    #[test]
+    fn test_bench_stats() {
+        let stats = BenchStats::from_samples(&millis(&[4, 1, 3, 2]));
+        assert_eq!(stats.iterations, 4);
+        assert_eq!(stats.mean, Duration::from_micros(2500));
+        assert_eq!(stats.median, Duration::from_micros(2500));
+        assert_eq!((stats.min, stats.max), (millis(&[1])[0], millis(&[4])[0]));
+        assert_eq!(format_duration(stats.stddev), "1.29 ms");
+
+        let single = BenchStats::from_samples(&millis(&[7]));
+        assert_eq!(
+            (single.median, single.stddev),
+            (millis(&[7])[0], Duration::ZERO)
+        );
+        assert_eq!(format_duration(Duration::from_nanos(850)), "850 ns");
+    }
+
+    #[test]
+    fn test_runs_each_bench_function() {
+        let source = r#"
+            fn sum_to(n: num) -> num {
+                let total = 0;
+                let i = 0;
+                while (i < n) {
+                    total = total + i;
+                    i = i + 1;
+                }
+                return total;
+            }
+
+            #[bench]
+            fn sum_100() { sum_to(100); }
+
+            #[bench]
+            fn failing() { assert.fail("broken"); }
+        "#;
+
+        let options = BenchOptions {
+            warmup: 1,
+            iterations: 3,
+        };
+        let report = run_bench_source("sum_bench.lf", source, &[], None, options);
+        let names: Vec<&str> = report.cases.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["sum_100", "failing"]);
+        assert!(matches!(
+            report.cases[0].outcome,
+            BenchOutcome::Measured(BenchStats { iterations: 3, .. })
+        ));
+        assert!(matches!(report.cases[1].outcome, BenchOutcome::Failed(_)));
+
+        let report = run_bench_source("plain_bench.lf", "let x = 1;", &[], Some("sum"), options);
+        assert!(report.cases.is_empty());
+    }
+
+    #[test]
+    fn test_compare_with_saved_run() {
+        let stats = |mean: u64, spread: u64| BenchStats {
+            stddev: Duration::from_millis(spread),
+            ..BenchStats::from_samples(&millis(&[mean]))
+        };
+        let baseline = vec![BenchRecord::new("a_bench.lf", "sort", &stats(10, 1))];
+        let json = serde_json::to_string(&baseline).unwrap();
+        let baseline: Vec<BenchRecord> = serde_json::from_str(&json).unwrap();
+
+        let slower = BenchRecord::new("a_bench.lf", "sort", &stats(15, 1)).compare(&baseline);
+        let change = slower.unwrap();
+        assert!((change.percent - 50.0).abs() < 1e-6 && change.significant);
+
+        let noisy = BenchRecord::new("a_bench.lf", "sort", &stats(11, 1)).compare(&baseline);
+        assert!(!noisy.unwrap().significant);
+        assert_eq!(
+            BenchRecord::new("a_bench.lf", "search", &stats(1, 0)).compare(&baseline),
+            None
+        );
+    }
+}
@@ -0,0 +1,241 @@
+//! Configuring an interpreter for code the host doesn't trust, like examples
+//! run on a server: which builtins it gets, what they may access and how much
+//! it may use. Unless given other permissions, the interpreter is denied every
+//! file, network and process access.
+//!
+//! ```
+//! use loft::runtime::Interpreter;
+//! use std::time::Duration;
+//!
+//! let interpreter = Interpreter::builder()
+//!     .disable_builtins(["fs", "web", "proc"])
+//!     .max_steps(1_000_000)
+//!     .max_memory(16 * 1024 * 1024)
+//!     .max_duration(Duration::from_secs(2))
+//!     .build();
+//! ```
+
+use super::builtin_registry::BuiltinRegistration;
+use super::limits::ExecutionLimits;
+use super::permissions::PermissionManager;
+use super::Interpreter;
+use std::collections::HashSet;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Default)]
+pub struct InterpreterBuilder {
+    features: Vec<String>,
+    disabled_builtins: HashSet<String>,
+    limits: ExecutionLimits,
+    permissions: Option<PermissionManager>,
+    source: Option<(String, String)>,
+}
+
+impl Interpreter {
+    pub fn builder() -> InterpreterBuilder {
+        InterpreterBuilder::default()
+    }
+}
+
+impl InterpreterBuilder {
+    /// Enable a feature-gated builtin such as `ffi`. None are enabled by default.
+    pub fn feature(mut self, feature: impl Into<String>) -> Self {
+        self.features.push(feature.into());
+        self
+    }
+
+    /// Leave out a builtin module, so code can neither use nor `learn` it
+    pub fn disable_builtin(mut self, name: impl Into<String>) -> Self {
+        self.disabled_builtins.insert(name.into());
+        self
+    }
+
+    pub fn disable_builtins<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.disabled_builtins
+            .extend(names.into_iter().map(Into::into));
+        self
+    }
+
+    /// Leave out every builtin module except the `allowed` ones
+    pub fn only_builtins(mut self, allowed: &[&str]) -> Self {
+        self.disabled_builtins = inventory::iter::<BuiltinRegistration>
+            .into_iter()
+            .map(|registration| registration.name)
+            .filter(|name| !allowed.contains(name))
+            .map(str::to_string)
+            .collect();
+        self
+    }
+
+    /// Most statements and expressions evaluated before the program is stopped
+    pub fn max_steps(mut self, steps: u64) -> Self {
+        self.limits.max_steps = Some(steps);
+        self
+    }
+
+    /// Most bytes the program's variables may hold, as estimated by
+    /// [`Value::approx_size`](super::value::Value::approx_size)
+    pub fn max_memory(mut self, bytes: usize) -> Self {
+        self.limits.max_memory = Some(bytes);
+        self
+    }
+
+    /// How long the program may run, counted from its first step
+    pub fn max_duration(mut self, duration: Duration) -> Self {
+        self.limits.max_duration = Some(duration);
+        self
+    }
+
+    pub fn limits(mut self, limits: ExecutionLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// What builtins may access while the interpreter runs a program, instead
+    /// of [`PermissionManager::deny_all`]
+    pub fn permissions(mut self, permissions: PermissionManager) -> Self {
+        self.permissions = Some(permissions);
+        self
+    }
+
+    /// The file errors point at and relative imports are resolved from
+    pub fn source(mut self, path: impl Into<String>, code: impl Into<String>) -> Self {
+        self.source = Some((path.into(), code.into()));
+        self
+    }
+
+    pub fn build(self) -> Interpreter {
+        let mut interpreter = Interpreter::new();
+        interpreter.disabled_builtins = self.disabled_builtins;
+        interpreter.permissions =
+            Some(self.permissions.unwrap_or_else(PermissionManager::deny_all));
+        let mut interpreter = interpreter
+            .with_features(self.features)
+            .with_limits(self.limits);
+        if let Some((path, code)) = self.source {
+            interpreter.set_source(path, code);
+        }
+        interpreter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{InputStream, Parser};
+    use crate::runtime::value::Value;
+
+    fn run(interpreter: &mut Interpreter, code: &str) -> Result<Value, String> {
+        let code = code.to_string();
+        let stmts = Parser::new(InputStream::new("sandbox.lf", &code))
+            .parse()
+            .unwrap();
+        interpreter.eval_program(stmts).map_err(|e| e.message)
+    }
+
+    #[test]
+    fn test_sandboxed_interpreter() {
+        let mut interpreter = Interpreter::builder()
+            .disable_builtins(["fs", "proc"])
+            .build();
+        assert_eq!(interpreter.env.get("fs"), None);
+        assert!(interpreter.env.get("math").is_some());
+        assert_eq!(
+            run(&mut interpreter, "learn \"fs\";"),
+            Err("The 'fs' builtin is disabled in this interpreter".to_string())
+        );
+
+        // Disabled builtins stay away after a reset
+        interpreter.reset();
+        assert_eq!(interpreter.env.get("proc"), None);
+
+        let interpreter = Interpreter::builder().only_builtins(&["term"]).build();
+        assert!(interpreter.env.get("term").is_some());
+        assert_eq!(interpreter.env.get("math"), None);
+    }
+
+    #[test]
+    fn test_permissions_default_to_deny_all() {
+        let mut interpreter = Interpreter::builder().feature("io").build();
+        let error = run(&mut interpreter, "fs.read(\"Cargo.toml\");").unwrap_err();
+        assert!(error.starts_with("Permission denied"), "{}", error);
+
+        let mut interpreter = Interpreter::builder()
+            .feature("io")
+            .permissions(PermissionManager::allow_all())
+            .build();
+        assert!(run(&mut interpreter, "fs.read(\"Cargo.toml\");").is_ok());
+        // The host's thread keeps its own permissions
+        assert!(crate::runtime::permission_context::with_permissions(|_| ()).is_none());
+    }
+
+    #[test]
+    fn test_tasks_share_the_step_limit() {
+        // Each task fits the limit on its own, but not all of them together
+        let code = "fn spin() {\n    let i = 0;\n    while (i < 500) {\n        i = i + 1;\n    }\n}\nlet tasks = [async spin(), async spin(), async spin(), async spin()];\nfor task in tasks {\n    await task;\n}\n";
+        let mut interpreter = Interpreter::builder().max_steps(10_000).build();
+        assert_eq!(
+            run(&mut interpreter, code),
+            Err("Execution limit exceeded: more than 10000 steps".to_string())
+        );
+    }
+
+    #[test]
+    fn test_blocking_builtins_stop_at_the_deadline() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let silent_server = format!(
+            "await web.get(\"http://{}\");",
+            listener.local_addr().unwrap()
+        );
+        for code in [
+            "await time.sleep(60000);",
+            "let channel = sync.channel();\nawait channel.recv();",
+            &silent_server,
+        ] {
+            let mut interpreter = Interpreter::builder()
+                .permissions(PermissionManager::allow_all())
+                .max_duration(Duration::from_millis(100))
+                .build();
+            let start = std::time::Instant::now();
+            assert_eq!(
+                run(&mut interpreter, code),
+                Err("Execution limit exceeded: ran for more than 100 ms".to_string()),
+                "{}",
+                code
+            );
+            assert!(start.elapsed() < Duration::from_secs(10), "{}", code);
+        }
+    }
+
+    #[test]
+    fn test_timers_stop_with_the_program() {
+        let mut interpreter = Interpreter::builder().max_steps(1_000_000).build();
+        run(
+            &mut interpreter,
+            "let ticks = sync.mutex(0);\ntime.interval(1, () => ticks.update((n) => n + 1));",
+        )
+        .unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        let first = run(&mut interpreter, "ticks.get();").unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(run(&mut interpreter, "ticks.get();").unwrap(), first);
+    }
+
+    #[test]
+    fn test_memory_limit() {
+        let mut interpreter = Interpreter::builder().max_memory(64 * 1024).build();
+        let error = run(
+            &mut interpreter,
+            "let text = \"\";\nwhile (true) {\n    text = text + \"some text to fill memory with\";\n}\n",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error,
+            "Execution limit exceeded: used more than 65536 bytes of memory"
+        );
+    }
+}
@@ -35,6 +35,27 @@ impl BuiltinRegistration {
 // This allows builtins to be collected at compile time
 inventory::collect!(BuiltinRegistration);
 
+/// Whether an import path names a builtin, as in `learn "math";`
+pub fn is_builtin_module(path: &[String]) -> bool {
+    match path {
+        [name] => inventory::iter::<BuiltinRegistration>
+            .into_iter()
+            .any(|registration| registration.name == name),
+        _ => false,
+    }
+}
+
+/// The names of every registered builtin module, sorted
+pub fn module_names() -> Vec<&'static str> {
+    let mut names: Vec<&'static str> = inventory::iter::<BuiltinRegistration>
+        .into_iter()
+        .map(|registration| registration.name)
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    names
+}
+
 /// Submit a builtin registration to the inventory
 #[macro_export]
 macro_rules! submit_builtin {
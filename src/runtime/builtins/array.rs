@@ -4,8 +4,8 @@ use crate::runtime::{RuntimeError, RuntimeResult};
 use loft_builtin_macros::loft_builtin;
 use rust_decimal::Decimal;
 
-/// Get the length of an array
-#[loft_builtin(array.length)]
+/// Get the length of the array
+#[loft_builtin(Array.length() -> num)]
 // TODO: Elide with #[required] and #[types(array)] for 'this'
 fn array_length(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     match this {
@@ -14,9 +14,8 @@ fn array_length(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     }
 }
 
-/// Push a value to the end of an array
-/// Returns a new array with the value added
-#[loft_builtin(array.push)]
+/// Add element to end (returns new array)
+#[loft_builtin(Array.push(value: any) -> Array)]
 // TODO: Elide with #[required] and #[types(array)] for 'this'
 fn array_push(this: &Value, #[required] args: &[Value]) -> RuntimeResult<Value> {
     // Note: 'this' type check is manual
@@ -30,10 +29,8 @@ fn array_push(this: &Value, #[required] args: &[Value]) -> RuntimeResult<Value>
     }
 }
 
-/// Pop a value from the end of an array
-/// Returns the popped value, or Unit if array is empty
-/// Note: This doesn't modify the original array
-#[loft_builtin(array.pop)]
+/// Get and remove last element
+#[loft_builtin(Array.pop() -> any)]
 // TODO: Elide with #[required] and #[types(array)] for 'this'
 fn array_pop(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     match this {
@@ -48,8 +45,8 @@ fn array_pop(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     }
 }
 
-/// Remove last element and return new array
-#[loft_builtin(array.remove_last)]
+/// Remove last element (returns new array)
+#[loft_builtin(Array.remove_last() -> Array)]
 // TODO: Elide with #[required] and #[types(array)] for 'this'
 fn array_remove_last(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     match this {
@@ -68,8 +65,8 @@ fn array_remove_last(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     }
 }
 
-/// Get a value at a specific index
-#[loft_builtin(array.get)]
+/// Get element at index
+#[loft_builtin(Array.get(index: num) -> any)]
 // TODO: Elide with #[required] and #[types(array)] for 'this'
 fn array_get(this: &Value, #[types(number)] args: &[Value]) -> RuntimeResult<Value> {
     match (this, &args[0]) {
@@ -86,8 +83,8 @@ fn array_get(this: &Value, #[types(number)] args: &[Value]) -> RuntimeResult<Val
     }
 }
 
-/// Set a value at a specific index (returns new array)
-#[loft_builtin(array.set)]
+/// Set element at index (returns new array)
+#[loft_builtin(Array.set(index: num, value: any) -> Array)]
 // TODO: Elide with #[required] and #[types(array)] for 'this'
 fn array_set(this: &Value, #[types(number, _)] args: &[Value]) -> RuntimeResult<Value> {
     match (this, &args[0], &args[1]) {
@@ -114,7 +111,7 @@ fn array_set(this: &Value, #[types(number, _)] args: &[Value]) -> RuntimeResult<
 }
 
 /// Check if array is empty
-#[loft_builtin(array.is_empty)]
+#[loft_builtin(Array.is_empty() -> bool)]
 // TODO: Elide with #[required] and #[types(array)] for 'this'
 fn array_is_empty(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     match this {
@@ -123,8 +120,8 @@ fn array_is_empty(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     }
 }
 
-/// Create a slice of the array
-#[loft_builtin(array.slice)]
+/// Get a slice of the array
+#[loft_builtin(Array.slice(start: num, end: num) -> Array)]
 // TODO: Elide with #[required] and #[types(array)] for 'this'
 fn array_slice(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     match this {
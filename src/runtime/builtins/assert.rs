@@ -0,0 +1,134 @@
+use crate::runtime::builtin::{BuiltinMethod, BuiltinStruct};
+use crate::runtime::traits::ToString;
+use crate::runtime::value::Value;
+use crate::runtime::{RuntimeError, RuntimeResult};
+use loft_builtin_macros::loft_builtin;
+
+/// Build the failure message, preferring a user supplied message at `index`
+fn failure_message(args: &[Value], index: usize, default: String) -> String {
+    match args.get(index) {
+        Some(Value::String(s)) => s.to_string(),
+        _ => default,
+    }
+}
+
+/// Assert that a value is truthy
+#[loft_builtin(assert.ok(value: any, message?: str))]
+fn assert_ok(_this: &Value, #[required] args: &[Value]) -> RuntimeResult<Value> {
+    if !args[0].is_truthy() {
+        return Err(RuntimeError::new(failure_message(
+            args,
+            1,
+            format!("Assertion failed: {} is not truthy", args[0].to_string()),
+        )));
+    }
+
+    Ok(Value::Unit)
+}
+
+/// Assert that two values are equal, showing both sides on failure
+#[loft_builtin(assert.eq(left: any, right: any, message?: str))]
+fn assert_eq(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    if args.len() < 2 {
+        return Err(RuntimeError::new("assert.eq() requires two arguments"));
+    }
+
+    if args[0] != args[1] {
+        return Err(RuntimeError::new(failure_message(
+            args,
+            2,
+            format!(
+                "Assertion failed: left != right\n  left:  {}\n  right: {}",
+                args[0].to_string(),
+                args[1].to_string()
+            ),
+        )));
+    }
+
+    Ok(Value::Unit)
+}
+
+/// Assert that two values are not equal
+#[loft_builtin(assert.ne(left: any, right: any, message?: str))]
+fn assert_ne(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    if args.len() < 2 {
+        return Err(RuntimeError::new("assert.ne() requires two arguments"));
+    }
+
+    if args[0] == args[1] {
+        return Err(RuntimeError::new(failure_message(
+            args,
+            2,
+            format!("Assertion failed: both values are {}", args[0].to_string()),
+        )));
+    }
+
+    Ok(Value::Unit)
+}
+
+/// Assert that calling the function throws an error, optionally containing the
+/// expected text. Returns the error message
+// The call itself is performed by the interpreter, which intercepts `assert.throws`
+#[loft_builtin(assert.throws(func: fn, expected?: str) -> str)]
+fn assert_throws(_this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    Err(RuntimeError::new(
+        "assert.throws() requires a function argument",
+    ))
+}
+
+/// Fail unconditionally
+#[loft_builtin(assert.fail(message?: str))]
+fn assert_fail(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    Err(RuntimeError::new(failure_message(
+        args,
+        0,
+        "Assertion failed".to_string(),
+    )))
+}
+
+/// Create the assert builtin struct
+pub fn create_assert_builtin() -> BuiltinStruct {
+    let mut assert = BuiltinStruct::new("assert");
+
+    assert.add_method("ok", assert_ok as BuiltinMethod);
+    assert.add_method("eq", assert_eq as BuiltinMethod);
+    assert.add_method("ne", assert_ne as BuiltinMethod);
+    assert.add_method("throws", assert_throws as BuiltinMethod);
+    assert.add_method("fail", assert_fail as BuiltinMethod);
+
+    assert
+}
+
+// Register the builtin automatically
+crate::submit_builtin!("assert", create_assert_builtin);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    #[test]
+    fn test_assert_eq_reports_both_sides() {
+        let err = assert_eq(
+            &Value::Unit,
+            &[
+                Value::Number(Decimal::from(1)),
+                Value::Number(Decimal::from(2)),
+            ],
+        )
+        .unwrap_err();
+        assert!(err.message.contains("left:  1"));
+        assert!(err.message.contains("right: 2"));
+    }
+
+    #[test]
+    fn test_assert_ok_uses_custom_message() {
+        let err = assert_ok(
+            &Value::Unit,
+            &[Value::Boolean(false), Value::String("boom".into())],
+        )
+        .unwrap_err();
+        assert_eq!(err.message, "boom");
+        assert!(assert_ok(&Value::Unit, &[Value::Boolean(true)]).is_ok());
+    }
+}
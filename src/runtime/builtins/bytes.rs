@@ -0,0 +1,280 @@
+use crate::runtime::builtin::{BuiltinMethod, BuiltinStruct};
+use crate::runtime::traits::{Add, Index};
+use crate::runtime::value::Value;
+use crate::runtime::{RuntimeError, RuntimeResult};
+use base64::{engine::general_purpose, Engine as _};
+use loft_builtin_macros::loft_builtin;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+
+/// The bytes a method was called on
+fn this_bytes<'a>(this: &'a Value, method: &str) -> RuntimeResult<&'a [u8]> {
+    match this {
+        Value::Bytes(bytes) => Ok(bytes),
+        _ => Err(RuntimeError::new(format!(
+            "{}() can only be called on Bytes",
+            method
+        ))),
+    }
+}
+
+/// A byte offset argument, clamped to `len`
+fn offset_arg(value: Option<&Value>, default: usize, len: usize) -> RuntimeResult<usize> {
+    match value {
+        None => Ok(default),
+        Some(Value::Number(n)) => n
+            .to_usize()
+            .map(|n| n.min(len))
+            .ok_or_else(|| RuntimeError::new("Byte offsets must be non-negative integers")),
+        Some(_) => Err(RuntimeError::new("Byte offsets must be numbers")),
+    }
+}
+
+/// Encode a string as UTF-8 bytes
+#[loft_builtin(bytes.from_utf8(text: str) -> Bytes)]
+fn bytes_from_utf8(
+    #[required] _this: &Value,
+    #[required]
+    #[types(string)]
+    args: &[Value],
+) -> RuntimeResult<Value> {
+    match &args[0] {
+        Value::String(s) => Ok(Value::Bytes(s.as_bytes().into())),
+        _ => unreachable!(),
+    }
+}
+
+/// Decode a hexadecimal string such as "48656c6c6f"
+#[loft_builtin(bytes.from_hex(hex: str) -> Bytes)]
+fn bytes_from_hex(
+    #[required] _this: &Value,
+    #[required]
+    #[types(string)]
+    args: &[Value],
+) -> RuntimeResult<Value> {
+    let hex = match &args[0] {
+        Value::String(s) => s.trim(),
+        _ => unreachable!(),
+    };
+    if hex.len() % 2 != 0 {
+        return Err(RuntimeError::new(
+            "Hex string must have an even number of digits",
+        ));
+    }
+
+    let data = (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| RuntimeError::new(format!("Invalid hex digits at offset {}", i)))
+        })
+        .collect::<RuntimeResult<Vec<u8>>>()?;
+    Ok(Value::Bytes(data.into()))
+}
+
+/// Decode a base64 string
+#[loft_builtin(bytes.from_base64(text: str) -> Bytes)]
+fn bytes_from_base64(
+    #[required] _this: &Value,
+    #[required]
+    #[types(string)]
+    args: &[Value],
+) -> RuntimeResult<Value> {
+    let input = match &args[0] {
+        Value::String(s) => s,
+        _ => unreachable!(),
+    };
+
+    let data = general_purpose::STANDARD
+        .decode(input.trim())
+        .map_err(|e| RuntimeError::new(format!("Failed to decode base64: {}", e)))?;
+    Ok(Value::Bytes(data.into()))
+}
+
+/// Build bytes from an array of numbers between 0 and 255
+#[loft_builtin(bytes.from_array(values: Array<num>) -> Bytes)]
+fn bytes_from_array(
+    #[required] _this: &Value,
+    #[required]
+    #[types(array)]
+    args: &[Value],
+) -> RuntimeResult<Value> {
+    let items = match &args[0] {
+        Value::Array(items) => items,
+        _ => unreachable!(),
+    };
+
+    let data = items
+        .iter()
+        .map(|item| match item {
+            Value::Number(n) => n
+                .to_u8()
+                .filter(|_| n.fract().is_zero())
+                .ok_or_else(|| RuntimeError::new(format!("{} is not a valid byte", n))),
+            _ => Err(RuntimeError::new("Bytes can only be built from numbers")),
+        })
+        .collect::<RuntimeResult<Vec<u8>>>()?;
+    Ok(Value::Bytes(data.into()))
+}
+
+/// Number of bytes
+#[loft_builtin(Bytes.length() -> num)]
+fn bytes_length(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    Ok(Value::Number(Decimal::from(
+        this_bytes(this, "length")?.len(),
+    )))
+}
+
+/// The byte at an index, also available as bytes[index]
+#[loft_builtin(Bytes.get(index: num) -> num)]
+fn bytes_get(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    this_bytes(this, "get")?;
+    this.index(args.first().unwrap_or(&Value::Unit))
+}
+
+/// Bytes from start up to but not including end (the end if not given)
+#[loft_builtin(Bytes.slice(start: num, end?: num) -> Bytes)]
+fn bytes_slice(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let data = this_bytes(this, "slice")?;
+    let start = offset_arg(args.first(), 0, data.len())?;
+    let end = offset_arg(args.get(1), data.len(), data.len())?;
+    Ok(Value::Bytes(data[start..end.max(start)].into()))
+}
+
+/// These bytes followed by another Bytes value, also available as a + b
+#[loft_builtin(Bytes.concat(other: Bytes) -> Bytes)]
+fn bytes_concat(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    this_bytes(this, "concat")?;
+    this.add(args.first().unwrap_or(&Value::Unit))
+}
+
+/// Decode the bytes as UTF-8 text, failing on invalid sequences
+#[loft_builtin(Bytes.to_utf8() -> str)]
+fn bytes_to_utf8(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let text = std::str::from_utf8(this_bytes(this, "to_utf8")?)
+        .map_err(|e| RuntimeError::new(format!("Bytes are not valid UTF-8: {}", e)))?;
+    Ok(Value::String(text.into()))
+}
+
+/// Decode the bytes as UTF-8 text, replacing invalid sequences with U+FFFD
+#[loft_builtin(Bytes.to_utf8_lossy() -> str)]
+fn bytes_to_utf8_lossy(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let data = this_bytes(this, "to_utf8_lossy")?;
+    Ok(Value::String(String::from_utf8_lossy(data).into()))
+}
+
+/// Lowercase hexadecimal representation
+#[loft_builtin(Bytes.to_hex() -> str)]
+fn bytes_to_hex(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let hex: String = this_bytes(this, "to_hex")?
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    Ok(Value::String(hex.into()))
+}
+
+/// Base64 representation
+#[loft_builtin(Bytes.to_base64() -> str)]
+fn bytes_to_base64(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let data = this_bytes(this, "to_base64")?;
+    Ok(Value::String(general_purpose::STANDARD.encode(data).into()))
+}
+
+/// The bytes as an array of numbers
+#[loft_builtin(Bytes.to_array() -> Array<num>)]
+fn bytes_to_array(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let data = this_bytes(this, "to_array")?;
+    Ok(Value::Array(
+        data.iter()
+            .map(|b| Value::Number(Decimal::from(*b)))
+            .collect(),
+    ))
+}
+
+pub fn create_bytes_builtin() -> BuiltinStruct {
+    let mut bytes = BuiltinStruct::new("bytes");
+
+    // Constructors
+    bytes.add_method("from_utf8", bytes_from_utf8 as BuiltinMethod);
+    bytes.add_method("from_hex", bytes_from_hex as BuiltinMethod);
+    bytes.add_method("from_base64", bytes_from_base64 as BuiltinMethod);
+    bytes.add_method("from_array", bytes_from_array as BuiltinMethod);
+
+    // Methods on Bytes values
+    bytes.add_method("length", bytes_length as BuiltinMethod);
+    bytes.add_method("len", bytes_length as BuiltinMethod); // Alias
+    bytes.add_method("get", bytes_get as BuiltinMethod);
+    bytes.add_method("slice", bytes_slice as BuiltinMethod);
+    bytes.add_method("concat", bytes_concat as BuiltinMethod);
+    bytes.add_method("to_utf8", bytes_to_utf8 as BuiltinMethod);
+    bytes.add_method("to_utf8_lossy", bytes_to_utf8_lossy as BuiltinMethod);
+    bytes.add_method("to_hex", bytes_to_hex as BuiltinMethod);
+    bytes.add_method("to_base64", bytes_to_base64 as BuiltinMethod);
+    bytes.add_method("to_array", bytes_to_array as BuiltinMethod);
+
+    bytes
+}
+
+// Register the builtin automatically
+crate::submit_builtin!("bytes", create_bytes_builtin);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::builtins::testing::string;
+
+    fn bytes(data: &[u8]) -> Value {
+        Value::Bytes(data.into())
+    }
+
+    #[test]
+    fn test_bytes_conversions() {
+        let hello = bytes_from_hex(&Value::Unit, &[string("48656C6c6f")]).unwrap();
+        assert_eq!(hello, bytes(b"Hello"));
+        assert_eq!(bytes_to_hex(&hello, &[]).unwrap(), string("48656c6c6f"));
+        assert_eq!(bytes_to_base64(&hello, &[]).unwrap(), string("SGVsbG8="));
+        assert_eq!(
+            bytes_from_base64(&Value::Unit, &[string("SGVsbG8=")]).unwrap(),
+            hello
+        );
+        assert_eq!(bytes_to_utf8(&hello, &[]).unwrap(), string("Hello"));
+
+        let invalid = bytes(&[0xff, b'a']);
+        assert!(bytes_to_utf8(&invalid, &[]).is_err());
+        assert_eq!(
+            bytes_to_utf8_lossy(&invalid, &[]).unwrap(),
+            string("\u{fffd}a")
+        );
+        assert!(bytes_from_hex(&Value::Unit, &[string("abc")]).is_err());
+        assert!(bytes_from_array(
+            &Value::Unit,
+            &[Value::Array(vec![Value::Number(256.into())])]
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_bytes_slicing_and_indexing() {
+        let data = bytes(&[1, 2, 3, 4, 5]);
+        let number = |n: i64| Value::Number(Decimal::from(n));
+
+        assert_eq!(
+            bytes_slice(&data, &[number(1), number(3)]).unwrap(),
+            bytes(&[2, 3])
+        );
+        assert_eq!(bytes_slice(&data, &[number(3)]).unwrap(), bytes(&[4, 5]));
+        assert_eq!(
+            bytes_slice(&data, &[number(4), number(2)]).unwrap(),
+            bytes(&[])
+        );
+        assert_eq!(bytes_get(&data, &[number(4)]).unwrap(), number(5));
+        assert!(bytes_get(&data, &[number(5)]).is_err());
+        assert_eq!(
+            bytes_concat(&data, &[bytes(&[6])]).unwrap(),
+            bytes(&[1, 2, 3, 4, 5, 6])
+        );
+        assert!(bytes_concat(&data, &[string("x")]).is_err());
+        assert!(bytes_length(&string("x"), &[]).is_err());
+    }
+}
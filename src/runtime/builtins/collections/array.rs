@@ -4,6 +4,7 @@ use crate::runtime::value::Value;
 use crate::runtime::{RuntimeError, RuntimeResult};
 use loft_builtin_macros::loft_builtin;
 use rust_decimal::Decimal;
+use std::cmp::Ordering;
 
 /// Map function - applies a function to each element (placeholder for now)
 /// Note: Full implementation would require function execution
@@ -63,7 +64,7 @@ fn array_find(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
 }
 
 /// Zip two arrays together
-#[loft_builtin(array.zip)]
+#[loft_builtin(array.zip(arr1: Array, arr2: Array) -> Array<Array>)]
 fn array_zip(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.len() < 2 {
         return Err(RuntimeError::new("zip() requires two array arguments"));
@@ -81,8 +82,8 @@ fn array_zip(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     }
 }
 
-/// Chain/concatenate multiple arrays
-#[loft_builtin(array.chain)]
+/// Concatenate multiple arrays
+#[loft_builtin(array.chain(...arrays: Array) -> Array)]
 fn array_chain(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     let mut result = Vec::new();
 
@@ -96,8 +97,8 @@ fn array_chain(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     Ok(Value::Array(result))
 }
 
-/// Flatten an array of arrays by one level
-#[loft_builtin(array.flatten)]
+/// Flatten array of arrays by one level
+#[loft_builtin(Array.flatten() -> Array)]
 fn array_flatten(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     match this {
         Value::Array(arr) => {
@@ -116,8 +117,8 @@ fn array_flatten(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     }
 }
 
-/// Reverse an array
-#[loft_builtin(array.reverse)]
+/// Reverse the array (returns new array)
+#[loft_builtin(Array.reverse() -> Array)]
 fn array_reverse(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     match this {
         Value::Array(arr) => {
@@ -129,34 +130,141 @@ fn array_reverse(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     }
 }
 
-/// Sort an array (numbers only for now)
-#[loft_builtin(array.sort)]
+/// Order two values for sorting: numbers, strings, booleans and dates by value,
+/// arrays element by element
+pub fn compare_values(a: &Value, b: &Value) -> RuntimeResult<Ordering> {
+    match (a, b) {
+        (Value::Number(l), Value::Number(r)) => Ok(l.cmp(r)),
+        (Value::String(l), Value::String(r)) => Ok(l.cmp(r)),
+        (Value::Boolean(l), Value::Boolean(r)) => Ok(l.cmp(r)),
+        (Value::DateTime(l), Value::DateTime(r)) => Ok(l.cmp(r)),
+        (Value::Array(l), Value::Array(r)) => {
+            for (x, y) in l.iter().zip(r) {
+                let ordering = compare_values(x, y)?;
+                if ordering != Ordering::Equal {
+                    return Ok(ordering);
+                }
+            }
+            Ok(l.len().cmp(&r.len()))
+        }
+        _ => Err(RuntimeError::new(format!(
+            "Cannot compare {} and {} when sorting",
+            a.to_string(),
+            b.to_string()
+        ))),
+    }
+}
+
+/// Stable merge sort with a comparison that can fail
+///
+/// Unlike `slice::sort_by`, an inconsistent comparison (as a user callback may
+/// give) only produces an odd order instead of a panic.
+pub fn stable_sort_by<T: Clone>(
+    items: Vec<T>,
+    compare: &mut dyn FnMut(&T, &T) -> RuntimeResult<Ordering>,
+) -> RuntimeResult<Vec<T>> {
+    if items.len() <= 1 {
+        return Ok(items);
+    }
+
+    let mut left = items;
+    let right = left.split_off(left.len() / 2);
+    let left = stable_sort_by(left, compare)?;
+    let right = stable_sort_by(right, compare)?;
+
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    while let (Some(l), Some(r)) = (left.peek(), right.peek()) {
+        // Take from the left on ties so equal elements keep their order
+        if compare(r, l)? == Ordering::Less {
+            merged.extend(right.next());
+        } else {
+            merged.extend(left.next());
+        }
+    }
+    merged.extend(left);
+    merged.extend(right);
+    Ok(merged)
+}
+
+/// Sort numbers, strings, booleans, dates or arrays in ascending order (returns
+/// new array). The sort is stable
+#[loft_builtin(Array.sort() -> Array)]
 fn array_sort(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    match this {
+        Value::Array(arr) => Ok(Value::Array(stable_sort_by(
+            arr.clone(),
+            &mut compare_values,
+        )?)),
+        _ => Err(RuntimeError::new("sort() can only be called on arrays")),
+    }
+}
+
+/// Stable sort with a comparator returning a negative number if a comes first,
+/// a positive number if b comes first, or zero (returns new array)
+// Note: the comparator is run by the interpreter, which intercepts this call
+#[loft_builtin(Array.sort_by(compare: fn) -> Array)]
+fn array_sort_by(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    match this {
+        Value::Array(_) => Err(RuntimeError::new(
+            "sort_by() requires a comparator function",
+        )),
+        _ => Err(RuntimeError::new("sort_by() can only be called on arrays")),
+    }
+}
+
+/// Stable sort by the key a function returns for each element; the function
+/// runs once per element (returns new array)
+// Note: the key function is run by the interpreter, which intercepts this call
+#[loft_builtin(Array.sort_by_key(key: fn) -> Array)]
+fn array_sort_by_key(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    match this {
+        Value::Array(_) => Err(RuntimeError::new("sort_by_key() requires a key function")),
+        _ => Err(RuntimeError::new(
+            "sort_by_key() can only be called on arrays",
+        )),
+    }
+}
+
+/// Remove consecutive duplicate elements (returns new array). Sort first to
+/// remove all duplicates
+#[loft_builtin(Array.dedup() -> Array)]
+fn array_dedup(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     match this {
         Value::Array(arr) => {
-            // Check if all elements are numbers
-            let mut numbers: Vec<Decimal> = Vec::new();
-            for item in arr {
-                match item {
-                    Value::Number(n) => numbers.push(*n),
-                    _ => {
-                        return Err(RuntimeError::new(
-                            "sort() currently only supports arrays of numbers",
-                        ))
-                    }
+            let mut deduped = arr.clone();
+            deduped.dedup();
+            Ok(Value::Array(deduped))
+        }
+        _ => Err(RuntimeError::new("dedup() can only be called on arrays")),
+    }
+}
+
+/// Find a value in a sorted array, returning its index or -1 if it is missing
+#[loft_builtin(Array.binary_search(value: any) -> num)]
+fn array_binary_search(this: &Value, #[required] args: &[Value]) -> RuntimeResult<Value> {
+    match this {
+        Value::Array(arr) => {
+            let (mut low, mut high) = (0, arr.len());
+            while low < high {
+                let mid = low + (high - low) / 2;
+                match compare_values(&arr[mid], &args[0])? {
+                    Ordering::Less => low = mid + 1,
+                    Ordering::Greater => high = mid,
+                    Ordering::Equal => return Ok(Value::Number(Decimal::from(mid))),
                 }
             }
-
-            numbers.sort();
-            let sorted: Vec<Value> = numbers.into_iter().map(Value::Number).collect();
-            Ok(Value::Array(sorted))
+            Ok(Value::Number(Decimal::from(-1)))
         }
-        _ => Err(RuntimeError::new("sort() can only be called on arrays")),
+        _ => Err(RuntimeError::new(
+            "binary_search() can only be called on arrays",
+        )),
     }
 }
 
-/// Check if array includes a value
-#[loft_builtin(array.includes)]
+/// Check if array contains a value
+#[loft_builtin(Array.includes(value: any) -> bool)]
 fn array_includes(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new("includes() requires a value argument"));
@@ -171,8 +279,8 @@ fn array_includes(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     }
 }
 
-/// Find the index of a value in an array
-#[loft_builtin(array.index_of)]
+/// Find the index of a value (-1 if not found)
+#[loft_builtin(Array.index_of(value: any) -> num)]
 fn array_index_of(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new("index_of() requires a value argument"));
@@ -190,8 +298,8 @@ fn array_index_of(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     }
 }
 
-/// Get first element of array
-#[loft_builtin(array.first)]
+/// Get the first element
+#[loft_builtin(Array.first() -> any)]
 fn array_first(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     match this {
         Value::Array(arr) => {
@@ -205,8 +313,8 @@ fn array_first(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     }
 }
 
-/// Get last element of array
-#[loft_builtin(array.last)]
+/// Get the last element
+#[loft_builtin(Array.last() -> any)]
 fn array_last(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     match this {
         Value::Array(arr) => {
@@ -221,7 +329,7 @@ fn array_last(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
 }
 
 /// Take first n elements
-#[loft_builtin(array.take)]
+#[loft_builtin(Array.take(n: num) -> Array)]
 fn array_take(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new("take() requires a count argument"));
@@ -242,7 +350,7 @@ fn array_take(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
 }
 
 /// Skip first n elements
-#[loft_builtin(array.skip)]
+#[loft_builtin(Array.skip(n: num) -> Array)]
 fn array_skip(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new("skip() requires a count argument"));
@@ -262,8 +370,8 @@ fn array_skip(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     }
 }
 
-/// Get unique elements (dedup)
-#[loft_builtin(array.unique)]
+/// Remove duplicates (returns new array)
+#[loft_builtin(Array.unique() -> Array)]
 fn array_unique(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     match this {
         Value::Array(arr) => {
@@ -280,7 +388,7 @@ fn array_unique(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
 }
 
 /// Sum all numbers in array
-#[loft_builtin(array.sum)]
+#[loft_builtin(Array.sum() -> num)]
 fn array_sum(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     match this {
         Value::Array(arr) => {
@@ -301,8 +409,8 @@ fn array_sum(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     }
 }
 
-/// Get average of all numbers in array
-#[loft_builtin(array.average)]
+/// Average of all numbers in array
+#[loft_builtin(Array.average() -> num)]
 fn array_average(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     match this {
         Value::Array(arr) => {
@@ -329,14 +437,15 @@ fn array_average(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     }
 }
 
-/// Joins an array together by a delimiter
-#[loft_builtin(array.join)]
+/// Join the elements into a string, separated by the delimiter ("," if not
+/// given)
+#[loft_builtin(Array.join(delimiter?: str) -> str)]
 fn array_join(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     match this {
         Value::Array(arr) => {
             let delimiter = if let Some(arg) = args.first() {
                 match arg {
-                    Value::String(s) => s.as_str(),
+                    Value::String(s) => s.as_ref(),
                     _ => return Err(RuntimeError::new("join() delimiter must be a string")),
                 }
             } else {
@@ -350,7 +459,7 @@ fn array_join(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
                 }
                 result.push_str(&item.to_string());
             }
-            Ok(Value::String(result))
+            Ok(Value::String(result.into()))
         }
         _ => Err(RuntimeError::new("join() can only be called on arrays")),
     }
@@ -369,6 +478,10 @@ pub fn register_collection_methods(builtin: &mut BuiltinStruct) {
     builtin.add_method("flatten", array_flatten as BuiltinMethod);
     builtin.add_method("reverse", array_reverse as BuiltinMethod);
     builtin.add_method("sort", array_sort as BuiltinMethod);
+    builtin.add_method("sort_by", array_sort_by as BuiltinMethod);
+    builtin.add_method("sort_by_key", array_sort_by_key as BuiltinMethod);
+    builtin.add_method("dedup", array_dedup as BuiltinMethod);
+    builtin.add_method("binary_search", array_binary_search as BuiltinMethod);
     builtin.add_method("includes", array_includes as BuiltinMethod);
     builtin.add_method("index_of", array_index_of as BuiltinMethod);
     builtin.add_method("first", array_first as BuiltinMethod);
@@ -0,0 +1,186 @@
+use crate::runtime::builtin::{BuiltinMethod, BuiltinStruct};
+use crate::runtime::value::Value;
+use crate::runtime::{RuntimeError, RuntimeResult};
+use loft_builtin_macros::loft_builtin;
+use rust_decimal::Decimal;
+
+/// Get the number of entries
+#[loft_builtin(Map.length() -> num)]
+fn map_length(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    match this {
+        Value::Map(entries) => Ok(Value::Number(Decimal::from(entries.len()))),
+        _ => Err(RuntimeError::new("length() can only be called on maps")),
+    }
+}
+
+/// Check if the map has no entries
+#[loft_builtin(Map.is_empty() -> bool)]
+fn map_is_empty(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    match this {
+        Value::Map(entries) => Ok(Value::Boolean(entries.is_empty())),
+        _ => Err(RuntimeError::new("is_empty() can only be called on maps")),
+    }
+}
+
+/// Get the value stored under a key, or the default if missing
+#[loft_builtin(Map.get(key: any, default?: any) -> any)]
+fn map_get(this: &Value, #[required] args: &[Value]) -> RuntimeResult<Value> {
+    match this {
+        Value::Map(entries) => Ok(entries
+            .iter()
+            .find(|(k, _)| *k == args[0])
+            .map(|(_, v)| v.clone())
+            .unwrap_or_else(|| args.get(1).cloned().unwrap_or(Value::Unit))),
+        _ => Err(RuntimeError::new("get() can only be called on maps")),
+    }
+}
+
+/// Insert or replace an entry (returns new map)
+#[loft_builtin(Map.set(key: any, value: any) -> Map)]
+fn map_set(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    if args.len() < 2 {
+        return Err(RuntimeError::new("set() requires a key and a value"));
+    }
+
+    match this {
+        Value::Map(entries) => {
+            let mut new_entries = entries.clone();
+            if let Some(entry) = new_entries.iter_mut().find(|(k, _)| *k == args[0]) {
+                entry.1 = args[1].clone();
+            } else {
+                new_entries.push((args[0].clone(), args[1].clone()));
+            }
+            Ok(Value::Map(new_entries))
+        }
+        _ => Err(RuntimeError::new("set() can only be called on maps")),
+    }
+}
+
+/// Check if the map contains a key
+#[loft_builtin(Map.contains_key(key: any) -> bool)]
+fn map_contains_key(this: &Value, #[required] args: &[Value]) -> RuntimeResult<Value> {
+    match this {
+        Value::Map(entries) => Ok(Value::Boolean(entries.iter().any(|(k, _)| *k == args[0]))),
+        _ => Err(RuntimeError::new(
+            "contains_key() can only be called on maps",
+        )),
+    }
+}
+
+/// Remove an entry (returns new map)
+#[loft_builtin(Map.remove(key: any) -> Map)]
+fn map_remove(this: &Value, #[required] args: &[Value]) -> RuntimeResult<Value> {
+    match this {
+        Value::Map(entries) => Ok(Value::Map(
+            entries
+                .iter()
+                .filter(|(k, _)| *k != args[0])
+                .cloned()
+                .collect(),
+        )),
+        _ => Err(RuntimeError::new("remove() can only be called on maps")),
+    }
+}
+
+/// Get all keys in insertion order
+#[loft_builtin(Map.keys() -> Array)]
+fn map_keys(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    match this {
+        Value::Map(entries) => Ok(Value::Array(
+            entries.iter().map(|(k, _)| k.clone()).collect(),
+        )),
+        _ => Err(RuntimeError::new("keys() can only be called on maps")),
+    }
+}
+
+/// Get all values in insertion order
+#[loft_builtin(Map.values() -> Array)]
+fn map_values(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    match this {
+        Value::Map(entries) => Ok(Value::Array(
+            entries.iter().map(|(_, v)| v.clone()).collect(),
+        )),
+        _ => Err(RuntimeError::new("values() can only be called on maps")),
+    }
+}
+
+/// Get all entries as [key, value] pairs
+#[loft_builtin(Map.entries() -> Array<Array>)]
+fn map_entries(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    match this {
+        Value::Map(entries) => Ok(Value::Array(
+            entries
+                .iter()
+                .map(|(k, v)| Value::Array(vec![k.clone(), v.clone()]))
+                .collect(),
+        )),
+        _ => Err(RuntimeError::new("entries() can only be called on maps")),
+    }
+}
+
+/// Create the Map builtin struct holding the methods available on map values
+pub fn create_map_builtin() -> BuiltinStruct {
+    let mut map = BuiltinStruct::new("map");
+
+    map.add_method("length", map_length as BuiltinMethod);
+    map.add_method("len", map_length as BuiltinMethod); // Alias
+    map.add_method("is_empty", map_is_empty as BuiltinMethod);
+    map.add_method("get", map_get as BuiltinMethod);
+    map.add_method("set", map_set as BuiltinMethod);
+    map.add_method("contains_key", map_contains_key as BuiltinMethod);
+    map.add_method("remove", map_remove as BuiltinMethod);
+    map.add_method("keys", map_keys as BuiltinMethod);
+    map.add_method("values", map_values as BuiltinMethod);
+    map.add_method("entries", map_entries as BuiltinMethod);
+
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Value {
+        Value::Map(vec![
+            (Value::String("a".into()), Value::Number(Decimal::from(1))),
+            (Value::String("b".into()), Value::Number(Decimal::from(2))),
+        ])
+    }
+
+    #[test]
+    fn test_map_get_and_set() {
+        let map = sample();
+        let key = Value::String("b".into());
+        assert_eq!(
+            map_get(&map, std::slice::from_ref(&key)).unwrap(),
+            Value::Number(Decimal::from(2))
+        );
+
+        let updated = map_set(&map, &[key.clone(), Value::Boolean(true)]).unwrap();
+        assert_eq!(map_get(&updated, &[key]).unwrap(), Value::Boolean(true));
+        assert_eq!(
+            map_length(&updated, &[]).unwrap(),
+            Value::Number(Decimal::from(2))
+        );
+    }
+
+    #[test]
+    fn test_map_remove_and_contains_key() {
+        let map = sample();
+        let key = Value::String("a".into());
+        assert_eq!(
+            map_contains_key(&map, std::slice::from_ref(&key)).unwrap(),
+            Value::Boolean(true)
+        );
+
+        let removed = map_remove(&map, std::slice::from_ref(&key)).unwrap();
+        assert_eq!(
+            map_contains_key(&removed, &[key]).unwrap(),
+            Value::Boolean(false)
+        );
+        assert_eq!(
+            map_keys(&removed, &[]).unwrap(),
+            Value::Array(vec![Value::String("b".into())])
+        );
+    }
+}
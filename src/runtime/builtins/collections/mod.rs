@@ -1,4 +1,6 @@
 pub mod array;
+pub mod map;
+pub mod set;
 
 // This module enhances array operations beyond the basic ones
-// Additional collection types (maps, sets, etc.) can be added here in the future
+// and provides the methods available on map values
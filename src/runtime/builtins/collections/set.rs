@@ -0,0 +1,257 @@
+// SetItem holds Value, which can contain a Task with interior mutability, but
+// SetItem::new only accepts plain data values, so set ordering never changes
+#![allow(clippy::mutable_key_type)]
+
+use crate::runtime::builtin::{BuiltinMethod, BuiltinStruct};
+use crate::runtime::value::{SetItem, Value};
+use crate::runtime::{RuntimeError, RuntimeResult};
+use loft_builtin_macros::loft_builtin;
+use rust_decimal::Decimal;
+use std::collections::BTreeSet;
+
+/// The set a method was called on
+fn this_set<'a>(this: &'a Value, method: &str) -> RuntimeResult<&'a BTreeSet<SetItem>> {
+    match this {
+        Value::Set(items) => Ok(items),
+        _ => Err(RuntimeError::new(format!(
+            "{}() can only be called on sets",
+            method
+        ))),
+    }
+}
+
+/// The set passed as the first argument
+fn set_arg<'a>(args: &'a [Value], method: &str) -> RuntimeResult<&'a BTreeSet<SetItem>> {
+    match args.first() {
+        Some(Value::Set(items)) => Ok(items),
+        _ => Err(RuntimeError::new(format!(
+            "{}() requires a Set argument",
+            method
+        ))),
+    }
+}
+
+fn item(value: &Value) -> RuntimeResult<SetItem> {
+    SetItem::new(value.clone()).map_err(RuntimeError::new)
+}
+
+/// Create an empty set
+#[loft_builtin(Set.new() -> Set)]
+fn set_new(_this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    Ok(Value::Set(BTreeSet::new()))
+}
+
+/// Create a set from the elements of an array, dropping duplicates
+#[loft_builtin(Set.from(items: Array) -> Set)]
+fn set_from(
+    #[required] _this: &Value,
+    #[required]
+    #[types(array)]
+    args: &[Value],
+) -> RuntimeResult<Value> {
+    match &args[0] {
+        Value::Array(items) => Ok(Value::Set(
+            items.iter().map(item).collect::<RuntimeResult<_>>()?,
+        )),
+        _ => unreachable!(),
+    }
+}
+
+/// Number of elements
+#[loft_builtin(Set.length() -> num)]
+fn set_length(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    Ok(Value::Number(Decimal::from(
+        this_set(this, "length")?.len(),
+    )))
+}
+
+/// Check if the set has no elements
+#[loft_builtin(Set.is_empty() -> bool)]
+fn set_is_empty(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    Ok(Value::Boolean(this_set(this, "is_empty")?.is_empty()))
+}
+
+/// Check if a value is in the set
+#[loft_builtin(Set.contains(value: any) -> bool)]
+fn set_contains(this: &Value, #[required] args: &[Value]) -> RuntimeResult<Value> {
+    let items = this_set(this, "contains")?;
+    // Values that cannot be set members are never in a set
+    Ok(Value::Boolean(
+        SetItem::new(args[0].clone()).is_ok_and(|needle| items.contains(&needle)),
+    ))
+}
+
+/// Returns a new set with the value added
+#[loft_builtin(Set.insert(value: any) -> Set)]
+fn set_insert(this: &Value, #[required] args: &[Value]) -> RuntimeResult<Value> {
+    let mut items = this_set(this, "insert")?.clone();
+    items.insert(item(&args[0])?);
+    Ok(Value::Set(items))
+}
+
+/// Returns a new set without the value
+#[loft_builtin(Set.remove(value: any) -> Set)]
+fn set_remove(this: &Value, #[required] args: &[Value]) -> RuntimeResult<Value> {
+    let mut items = this_set(this, "remove")?.clone();
+    if let Ok(needle) = SetItem::new(args[0].clone()) {
+        items.remove(&needle);
+    }
+    Ok(Value::Set(items))
+}
+
+/// Elements in either set
+#[loft_builtin(Set.union(other: Set) -> Set)]
+fn set_union(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let items = this_set(this, "union")?;
+    let other = set_arg(args, "union")?;
+    Ok(Value::Set(items.union(other).cloned().collect()))
+}
+
+/// Elements in both sets
+#[loft_builtin(Set.intersection(other: Set) -> Set)]
+fn set_intersection(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let items = this_set(this, "intersection")?;
+    let other = set_arg(args, "intersection")?;
+    Ok(Value::Set(items.intersection(other).cloned().collect()))
+}
+
+/// Elements in this set but not the other
+#[loft_builtin(Set.difference(other: Set) -> Set)]
+fn set_difference(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let items = this_set(this, "difference")?;
+    let other = set_arg(args, "difference")?;
+    Ok(Value::Set(items.difference(other).cloned().collect()))
+}
+
+/// Elements in exactly one of the sets
+#[loft_builtin(Set.symmetric_difference(other: Set) -> Set)]
+fn set_symmetric_difference(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let items = this_set(this, "symmetric_difference")?;
+    let other = set_arg(args, "symmetric_difference")?;
+    Ok(Value::Set(
+        items.symmetric_difference(other).cloned().collect(),
+    ))
+}
+
+/// Check if every element of this set is in the other
+#[loft_builtin(Set.is_subset(other: Set) -> bool)]
+fn set_is_subset(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let items = this_set(this, "is_subset")?;
+    let other = set_arg(args, "is_subset")?;
+    Ok(Value::Boolean(items.is_subset(other)))
+}
+
+/// The elements as a sorted array
+#[loft_builtin(Set.to_array() -> Array)]
+fn set_to_array(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    Ok(Value::Array(
+        this_set(this, "to_array")?
+            .iter()
+            .map(|item| item.value().clone())
+            .collect(),
+    ))
+}
+
+pub fn create_set_builtin() -> BuiltinStruct {
+    let mut set = BuiltinStruct::new("Set");
+
+    // Constructors
+    set.add_method("new", set_new as BuiltinMethod);
+    set.add_method("from", set_from as BuiltinMethod);
+
+    // Methods on Set values
+    set.add_method("length", set_length as BuiltinMethod);
+    set.add_method("len", set_length as BuiltinMethod); // Alias
+    set.add_method("is_empty", set_is_empty as BuiltinMethod);
+    set.add_method("contains", set_contains as BuiltinMethod);
+    set.add_method("insert", set_insert as BuiltinMethod);
+    set.add_method("remove", set_remove as BuiltinMethod);
+    set.add_method("union", set_union as BuiltinMethod);
+    set.add_method("intersection", set_intersection as BuiltinMethod);
+    set.add_method("difference", set_difference as BuiltinMethod);
+    set.add_method(
+        "symmetric_difference",
+        set_symmetric_difference as BuiltinMethod,
+    );
+    set.add_method("is_subset", set_is_subset as BuiltinMethod);
+    set.add_method("to_array", set_to_array as BuiltinMethod);
+
+    set
+}
+
+// Register the builtin automatically
+crate::submit_builtin!("Set", create_set_builtin);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn num(n: i64) -> Value {
+        Value::Number(Decimal::from(n))
+    }
+
+    fn set_of(items: &[i64]) -> Value {
+        let array = Value::Array(items.iter().map(|n| num(*n)).collect());
+        set_from(&Value::Unit, &[array]).unwrap()
+    }
+
+    #[test]
+    fn test_set_membership() {
+        let set = set_of(&[3, 1, 3, 2]);
+        assert_eq!(set_length(&set, &[]).unwrap(), num(3));
+        assert_eq!(
+            set_to_array(&set, &[]).unwrap(),
+            Value::Array(vec![num(1), num(2), num(3)])
+        );
+        assert_eq!(set_contains(&set, &[num(2)]).unwrap(), Value::Boolean(true));
+
+        let set = set_remove(&set, &[num(2)]).unwrap();
+        let set = set_insert(&set, &[Value::String("x".into())]).unwrap();
+        assert_eq!(
+            set_contains(&set, &[num(2)]).unwrap(),
+            Value::Boolean(false)
+        );
+        assert_eq!(
+            set_contains(&set, &[Value::String("x".into())]).unwrap(),
+            Value::Boolean(true)
+        );
+
+        let unhashable = Value::Struct {
+            name: "Object".to_string(),
+            fields: Default::default(),
+        };
+        assert!(set_insert(&set, std::slice::from_ref(&unhashable)).is_err());
+        assert_eq!(
+            set_contains(&set, &[unhashable]).unwrap(),
+            Value::Boolean(false)
+        );
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a = set_of(&[1, 2, 3]);
+        let b = set_of(&[2, 3, 4]);
+
+        assert_eq!(
+            set_union(&a, std::slice::from_ref(&b)).unwrap(),
+            set_of(&[1, 2, 3, 4])
+        );
+        assert_eq!(
+            set_intersection(&a, std::slice::from_ref(&b)).unwrap(),
+            set_of(&[2, 3])
+        );
+        assert_eq!(
+            set_difference(&a, std::slice::from_ref(&b)).unwrap(),
+            set_of(&[1])
+        );
+        assert_eq!(
+            set_symmetric_difference(&a, std::slice::from_ref(&b)).unwrap(),
+            set_of(&[1, 4])
+        );
+        assert_eq!(
+            set_is_subset(&set_of(&[2]), &[a]).unwrap(),
+            Value::Boolean(true)
+        );
+        assert!(set_union(&b, &[num(1)]).is_err());
+    }
+}
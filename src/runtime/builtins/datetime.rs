@@ -0,0 +1,527 @@
+//! Calendar dates and times.
+//!
+//! `DateTime` values carry their UTC offset, so formatting shows the time
+//! as it was given while comparisons and arithmetic use the instant. Adding
+//! or subtracting a number moves a date by that many milliseconds, and
+//! subtracting two dates gives the difference in milliseconds.
+
+use crate::runtime::builtin::{BuiltinMethod, BuiltinStruct};
+use crate::runtime::traits::{Add, Sub};
+use crate::runtime::value::Value;
+use crate::runtime::{RuntimeError, RuntimeResult};
+use chrono::{
+    DateTime, Datelike, FixedOffset, Local, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeZone,
+    Timelike, Utc,
+};
+use loft_builtin_macros::loft_builtin;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+
+const SECOND_MS: i64 = 1000;
+const MINUTE_MS: i64 = 60 * SECOND_MS;
+const HOUR_MS: i64 = 60 * MINUTE_MS;
+const DAY_MS: i64 = 24 * HOUR_MS;
+
+/// The date a method was called on
+fn this_datetime(this: &Value, method: &str) -> RuntimeResult<DateTime<FixedOffset>> {
+    match this {
+        Value::DateTime(dt) => Ok(*dt),
+        _ => Err(RuntimeError::new(format!(
+            "{}() can only be called on a DateTime",
+            method
+        ))),
+    }
+}
+
+fn number(n: i64) -> Value {
+    Value::Number(Decimal::from(n))
+}
+
+/// An integer argument
+fn int_arg(args: &[Value], index: usize, method: &str) -> RuntimeResult<i64> {
+    match args.get(index) {
+        Some(Value::Number(n)) if n.fract().is_zero() => n
+            .to_i64()
+            .ok_or_else(|| RuntimeError::new(format!("{}(): {} is out of range", method, n))),
+        _ => Err(RuntimeError::new(format!(
+            "{}() requires an integer argument",
+            method
+        ))),
+    }
+}
+
+/// A UTC offset given as minutes east of UTC or as a string like "+05:30", "-0800" or "Z"
+fn offset_arg(value: &Value) -> RuntimeResult<FixedOffset> {
+    let seconds = match value {
+        Value::Number(minutes) => minutes.to_i32().and_then(|m| m.checked_mul(60)),
+        Value::String(s) => match &**s {
+            "Z" | "z" | "UTC" => Some(0),
+            s => parse_offset(s),
+        },
+        _ => None,
+    };
+    seconds
+        .and_then(FixedOffset::east_opt)
+        .ok_or_else(|| RuntimeError::new(format!("Invalid UTC offset {:?}", value)))
+}
+
+/// Seconds east of UTC for "+HH:MM", "+HHMM" or "+HH"
+fn parse_offset(s: &str) -> Option<i32> {
+    let (sign, rest) = match s.as_bytes().first()? {
+        b'+' => (1, &s[1..]),
+        b'-' => (-1, &s[1..]),
+        _ => return None,
+    };
+    let digits: String = rest.chars().filter(|c| *c != ':').collect();
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let (hours, minutes) = match digits.len() {
+        2 => (digits.parse::<i32>().ok()?, 0),
+        4 => (
+            digits[..2].parse::<i32>().ok()?,
+            digits[2..].parse::<i32>().ok()?,
+        ),
+        _ => return None,
+    };
+    (minutes < 60).then_some(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Parse ISO 8601 text; dates and times without an offset are taken as UTC
+fn parse_iso(text: &str) -> Option<DateTime<FixedOffset>> {
+    let utc = FixedOffset::east_opt(0)?;
+    DateTime::parse_from_rfc3339(text).ok().or_else(|| {
+        [
+            "%Y-%m-%dT%H:%M:%S%.f",
+            "%Y-%m-%d %H:%M:%S%.f",
+            "%Y-%m-%dT%H:%M",
+        ]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(text, fmt).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .ok()
+                .map(|date| date.and_time(NaiveTime::MIN))
+        })
+        .and_then(|naive| utc.from_local_datetime(&naive).single())
+    })
+}
+
+/// Parse text with a strftime format; formats without an offset are taken as UTC
+fn parse_with_format(text: &str, format: &str) -> Option<DateTime<FixedOffset>> {
+    let utc = FixedOffset::east_opt(0)?;
+    DateTime::parse_from_str(text, format).ok().or_else(|| {
+        NaiveDateTime::parse_from_str(text, format)
+            .ok()
+            .or_else(|| {
+                NaiveDate::parse_from_str(text, format)
+                    .ok()
+                    .map(|date| date.and_time(NaiveTime::MIN))
+            })
+            .and_then(|naive| utc.from_local_datetime(&naive).single())
+    })
+}
+
+/// The current time in the local timezone
+#[loft_builtin(datetime.now() -> DateTime)]
+fn datetime_now(_this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    Ok(Value::DateTime(Local::now().fixed_offset()))
+}
+
+/// The current time in UTC
+#[loft_builtin(datetime.utc_now() -> DateTime)]
+fn datetime_utc_now(_this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    Ok(Value::DateTime(Utc::now().fixed_offset()))
+}
+
+/// Parse an ISO 8601 string, or a string matching a strftime format such as
+/// "%d.%m.%Y". Times without an offset are taken as UTC
+#[loft_builtin(datetime.parse(text: str, format?: str) -> DateTime)]
+fn datetime_parse(
+    #[required] _this: &Value,
+    #[required]
+    #[types(string, string)]
+    args: &[Value],
+) -> RuntimeResult<Value> {
+    let text = match &args[0] {
+        Value::String(s) => s.trim(),
+        _ => unreachable!(),
+    };
+
+    let parsed = match args.get(1) {
+        Some(Value::String(format)) => parse_with_format(text, format),
+        _ => parse_iso(text),
+    };
+    parsed
+        .map(Value::DateTime)
+        .ok_or_else(|| RuntimeError::new(format!("Invalid date '{}'", text)))
+}
+
+/// A date from milliseconds since the Unix epoch, in UTC or the given offset
+#[loft_builtin(datetime.from_timestamp(millis: num, offset?: num | str) -> DateTime)]
+fn datetime_from_timestamp(
+    #[required] _this: &Value,
+    #[required]
+    #[types(number)]
+    args: &[Value],
+) -> RuntimeResult<Value> {
+    let millis = int_arg(args, 0, "datetime.from_timestamp")?;
+    let offset = match args.get(1) {
+        Some(offset) => offset_arg(offset)?,
+        None => FixedOffset::east_opt(0).unwrap(),
+    };
+
+    DateTime::from_timestamp_millis(millis)
+        .map(|dt| Value::DateTime(dt.with_timezone(&offset)))
+        .ok_or_else(|| RuntimeError::new(format!("Timestamp {} is out of range", millis)))
+}
+
+/// A UTC date from its parts
+#[loft_builtin(datetime.utc(year: num, month: num, day: num, hour?: num, minute?: num, second?: num) -> DateTime)]
+fn datetime_utc(
+    #[required] _this: &Value,
+    #[required]
+    #[types(number, number, number)]
+    args: &[Value],
+) -> RuntimeResult<Value> {
+    let mut parts = [0i64; 6];
+    for (i, part) in parts.iter_mut().enumerate() {
+        if i < 3 || i < args.len() {
+            *part = int_arg(args, i, "datetime.utc")?;
+        }
+    }
+
+    let [year, month, day, hour, minute, second] = parts.map(|p| u32::try_from(p).ok());
+    let date = year
+        .and_then(|y| i32::try_from(y).ok())
+        .zip(month.zip(day))
+        .and_then(|(y, (m, d))| NaiveDate::from_ymd_opt(y, m, d));
+    let time = hour
+        .zip(minute.zip(second))
+        .and_then(|(h, (m, s))| NaiveTime::from_hms_opt(h, m, s));
+
+    match date.zip(time) {
+        Some((date, time)) => Ok(Value::DateTime(
+            date.and_time(time).and_utc().fixed_offset(),
+        )),
+        None => Err(RuntimeError::new("datetime.utc(): invalid date")),
+    }
+}
+
+/// Format with a strftime pattern such as "%Y-%m-%d %H:%M", or as ISO 8601 if
+/// not given
+#[loft_builtin(DateTime.format(pattern?: str) -> str)]
+fn datetime_format(this: &Value, #[types(string)] args: &[Value]) -> RuntimeResult<Value> {
+    let dt = this_datetime(this, "format")?;
+    match args.first() {
+        None => Ok(Value::String(dt.to_rfc3339().into())),
+        Some(Value::String(format)) => {
+            // chrono reports bad specifiers through fmt::Error, which format!() would panic on
+            use std::fmt::Write;
+            let mut out = String::new();
+            write!(out, "{}", dt.format(format))
+                .map_err(|_| RuntimeError::new(format!("Invalid date format '{}'", format)))?;
+            Ok(Value::String(out.into()))
+        }
+        Some(_) => Err(RuntimeError::new("format() requires a string pattern")),
+    }
+}
+
+/// ISO 8601 representation, e.g. "2024-03-01T12:30:00+00:00"
+#[loft_builtin(DateTime.to_iso() -> str)]
+fn datetime_to_iso(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    Ok(Value::String(
+        this_datetime(this, "to_iso")?.to_rfc3339().into(),
+    ))
+}
+
+/// Milliseconds since the Unix epoch
+#[loft_builtin(DateTime.timestamp() -> num)]
+fn datetime_timestamp(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    Ok(number(this_datetime(this, "timestamp")?.timestamp_millis()))
+}
+
+/// UTC offset in minutes east of UTC
+#[loft_builtin(DateTime.offset() -> num)]
+fn datetime_offset(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let dt = this_datetime(this, "offset")?;
+    Ok(number(i64::from(dt.offset().local_minus_utc() / 60)))
+}
+
+/// The year
+#[loft_builtin(DateTime.year() -> num)]
+fn datetime_year(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let dt = this_datetime(this, "year")?;
+    Ok(number(i64::from(dt.year())))
+}
+
+/// The month, 1 to 12
+#[loft_builtin(DateTime.month() -> num)]
+fn datetime_month(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let dt = this_datetime(this, "month")?;
+    Ok(number(i64::from(dt.month())))
+}
+
+/// The day of the month, 1 to 31
+#[loft_builtin(DateTime.day() -> num)]
+fn datetime_day(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let dt = this_datetime(this, "day")?;
+    Ok(number(i64::from(dt.day())))
+}
+
+/// The hour, 0 to 23
+#[loft_builtin(DateTime.hour() -> num)]
+fn datetime_hour(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let dt = this_datetime(this, "hour")?;
+    Ok(number(i64::from(dt.hour())))
+}
+
+/// The minute, 0 to 59
+#[loft_builtin(DateTime.minute() -> num)]
+fn datetime_minute(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let dt = this_datetime(this, "minute")?;
+    Ok(number(i64::from(dt.minute())))
+}
+
+/// The second, 0 to 59
+#[loft_builtin(DateTime.second() -> num)]
+fn datetime_second(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let dt = this_datetime(this, "second")?;
+    Ok(number(i64::from(dt.second())))
+}
+
+/// The millisecond, 0 to 999
+#[loft_builtin(DateTime.millisecond() -> num)]
+fn datetime_millisecond(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let dt = this_datetime(this, "millisecond")?;
+    Ok(number(i64::from(dt.timestamp_subsec_millis())))
+}
+
+/// The day of the week, 1 (Monday) to 7 (Sunday)
+#[loft_builtin(DateTime.weekday() -> num)]
+fn datetime_weekday(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let dt = this_datetime(this, "weekday")?;
+    Ok(number(i64::from(dt.weekday().number_from_monday())))
+}
+
+/// The day of the year, 1 to 366
+#[loft_builtin(DateTime.day_of_year() -> num)]
+fn datetime_day_of_year(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let dt = this_datetime(this, "day_of_year")?;
+    Ok(number(i64::from(dt.ordinal())))
+}
+
+/// The same instant in UTC
+#[loft_builtin(DateTime.to_utc() -> DateTime)]
+fn datetime_to_utc(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    Ok(Value::DateTime(
+        this_datetime(this, "to_utc")?.to_utc().fixed_offset(),
+    ))
+}
+
+/// The same instant in the local timezone
+#[loft_builtin(DateTime.to_local() -> DateTime)]
+fn datetime_to_local(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let dt = this_datetime(this, "to_local")?;
+    Ok(Value::DateTime(dt.with_timezone(&Local).fixed_offset()))
+}
+
+/// The same instant at another UTC offset, given in minutes or as "+HH:MM"
+#[loft_builtin(DateTime.to_offset(offset: num | str) -> DateTime)]
+fn datetime_to_offset(this: &Value, #[required] args: &[Value]) -> RuntimeResult<Value> {
+    let dt = this_datetime(this, "to_offset")?;
+    let offset = offset_arg(&args[0])?;
+    Ok(Value::DateTime(dt.with_timezone(&offset)))
+}
+
+/// Add a number of milliseconds, also available as date + millis
+#[loft_builtin(DateTime.add(millis: num) -> DateTime)]
+fn datetime_add(
+    this: &Value,
+    #[required]
+    #[types(number)]
+    args: &[Value],
+) -> RuntimeResult<Value> {
+    this_datetime(this, "add")?;
+    this.add(&args[0])
+}
+
+/// Add a number of days, keeping the time of day
+#[loft_builtin(DateTime.add_days(days: num) -> DateTime)]
+fn datetime_add_days(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    this_datetime(this, "add_days")?;
+    let days = int_arg(args, 0, "add_days")?;
+    let millis = days
+        .checked_mul(DAY_MS)
+        .ok_or_else(|| RuntimeError::new("add_days(): too many days"))?;
+    this.add(&number(millis))
+}
+
+/// Add a number of calendar months, clamping the day to the end of shorter
+/// months
+#[loft_builtin(DateTime.add_months(months: num) -> DateTime)]
+fn datetime_add_months(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let dt = this_datetime(this, "add_months")?;
+    let months = int_arg(args, 0, "add_months")?;
+    let count = u32::try_from(months.unsigned_abs())
+        .map(Months::new)
+        .map_err(|_| RuntimeError::new("add_months(): too many months"))?;
+
+    let shifted = if months >= 0 {
+        dt.checked_add_months(count)
+    } else {
+        dt.checked_sub_months(count)
+    };
+    shifted
+        .map(Value::DateTime)
+        .ok_or_else(|| RuntimeError::new("add_months(): date out of range"))
+}
+
+/// Milliseconds from another date to this one, also available as a - b
+#[loft_builtin(DateTime.diff(other: DateTime) -> num)]
+fn datetime_diff(this: &Value, #[required] args: &[Value]) -> RuntimeResult<Value> {
+    this_datetime(this, "diff")?;
+    match &args[0] {
+        other @ Value::DateTime(_) => this.sub(other),
+        _ => Err(RuntimeError::new("diff() requires a DateTime argument")),
+    }
+}
+
+/// -1, 0 or 1 as this date is before, at or after another
+#[loft_builtin(DateTime.compare(other: DateTime) -> num)]
+fn datetime_compare(this: &Value, #[required] args: &[Value]) -> RuntimeResult<Value> {
+    let dt = this_datetime(this, "compare")?;
+    match &args[0] {
+        Value::DateTime(other) => Ok(number(dt.cmp(other) as i64)),
+        _ => Err(RuntimeError::new("compare() requires a DateTime argument")),
+    }
+}
+
+/// Midnight at the start of the same day, at the same offset
+#[loft_builtin(DateTime.start_of_day() -> DateTime)]
+fn datetime_start_of_day(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let dt = this_datetime(this, "start_of_day")?;
+    dt.timezone()
+        .from_local_datetime(&dt.date_naive().and_time(NaiveTime::MIN))
+        .single()
+        .map(Value::DateTime)
+        .ok_or_else(|| RuntimeError::new("start_of_day(): date out of range"))
+}
+
+/// Create the datetime builtin struct
+pub fn create_datetime_builtin() -> BuiltinStruct {
+    let mut datetime = BuiltinStruct::new("datetime");
+
+    // Durations in milliseconds, for use with + and -
+    datetime.add_field("SECOND", number(SECOND_MS));
+    datetime.add_field("MINUTE", number(MINUTE_MS));
+    datetime.add_field("HOUR", number(HOUR_MS));
+    datetime.add_field("DAY", number(DAY_MS));
+    datetime.add_field("WEEK", number(7 * DAY_MS));
+
+    // Constructors
+    datetime.add_method("now", datetime_now as BuiltinMethod);
+    datetime.add_method("utc_now", datetime_utc_now as BuiltinMethod);
+    datetime.add_method("parse", datetime_parse as BuiltinMethod);
+    datetime.add_method("from_timestamp", datetime_from_timestamp as BuiltinMethod);
+    datetime.add_method("utc", datetime_utc as BuiltinMethod);
+
+    // Methods on DateTime values
+    datetime.add_method("format", datetime_format as BuiltinMethod);
+    datetime.add_method("to_iso", datetime_to_iso as BuiltinMethod);
+    datetime.add_method("timestamp", datetime_timestamp as BuiltinMethod);
+    datetime.add_method("offset", datetime_offset as BuiltinMethod);
+    datetime.add_method("year", datetime_year as BuiltinMethod);
+    datetime.add_method("month", datetime_month as BuiltinMethod);
+    datetime.add_method("day", datetime_day as BuiltinMethod);
+    datetime.add_method("hour", datetime_hour as BuiltinMethod);
+    datetime.add_method("minute", datetime_minute as BuiltinMethod);
+    datetime.add_method("second", datetime_second as BuiltinMethod);
+    datetime.add_method("millisecond", datetime_millisecond as BuiltinMethod);
+    datetime.add_method("weekday", datetime_weekday as BuiltinMethod);
+    datetime.add_method("day_of_year", datetime_day_of_year as BuiltinMethod);
+    datetime.add_method("to_utc", datetime_to_utc as BuiltinMethod);
+    datetime.add_method("to_local", datetime_to_local as BuiltinMethod);
+    datetime.add_method("to_offset", datetime_to_offset as BuiltinMethod);
+    datetime.add_method("add", datetime_add as BuiltinMethod);
+    datetime.add_method("add_days", datetime_add_days as BuiltinMethod);
+    datetime.add_method("add_months", datetime_add_months as BuiltinMethod);
+    datetime.add_method("diff", datetime_diff as BuiltinMethod);
+    datetime.add_method("compare", datetime_compare as BuiltinMethod);
+    datetime.add_method("start_of_day", datetime_start_of_day as BuiltinMethod);
+
+    datetime
+}
+
+// Register the builtin automatically
+crate::submit_builtin!("datetime", create_datetime_builtin);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::builtins::testing::string;
+    use crate::runtime::traits::Ord;
+
+    fn parse(args: &[Value]) -> Value {
+        datetime_parse(&Value::Unit, args).unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_format() {
+        let dt = parse(&[string("2024-03-01T12:30:45.250+05:30")]);
+        assert_eq!(datetime_year(&dt, &[]).unwrap(), number(2024));
+        assert_eq!(datetime_hour(&dt, &[]).unwrap(), number(12));
+        assert_eq!(datetime_millisecond(&dt, &[]).unwrap(), number(250));
+        assert_eq!(datetime_offset(&dt, &[]).unwrap(), number(330));
+        assert_eq!(datetime_weekday(&dt, &[]).unwrap(), number(5));
+        assert_eq!(
+            datetime_format(&dt, &[string("%d/%m/%Y %H:%M")]).unwrap(),
+            string("01/03/2024 12:30")
+        );
+        assert_eq!(
+            datetime_to_iso(&datetime_to_utc(&dt, &[]).unwrap(), &[]).unwrap(),
+            string("2024-03-01T07:00:45.250+00:00")
+        );
+
+        // Without an offset, dates are UTC
+        let date = parse(&[string("2024-03-01")]);
+        assert_eq!(
+            datetime_timestamp(&date, &[]).unwrap(),
+            number(1709251200000)
+        );
+        let custom = parse(&[string("01.03.2024 00:00"), string("%d.%m.%Y %H:%M")]);
+        assert_eq!(custom, date);
+
+        assert!(datetime_parse(&Value::Unit, &[string("yesterday")]).is_err());
+        assert!(datetime_format(&date, &[string("%Q")]).is_err());
+    }
+
+    #[test]
+    fn test_arithmetic_and_comparison() {
+        let start = datetime_utc(&Value::Unit, &[number(2024), number(1), number(31)]).unwrap();
+        let later = start.add(&number(DAY_MS + HOUR_MS)).unwrap();
+        assert_eq!(
+            datetime_to_iso(&later, &[]).unwrap(),
+            string("2024-02-01T01:00:00+00:00")
+        );
+        assert_eq!(later.sub(&start).unwrap(), number(DAY_MS + HOUR_MS));
+        assert_eq!(later.gt(&start).unwrap(), Value::Boolean(true));
+        assert_eq!(
+            datetime_compare(&start, std::slice::from_ref(&later)).unwrap(),
+            number(-1)
+        );
+
+        // The same instant at another offset is still equal
+        let shifted = datetime_to_offset(&start, &[string("-08:00")]).unwrap();
+        assert_eq!(shifted, start);
+        assert_eq!(datetime_day(&shifted, &[]).unwrap(), number(30));
+
+        // Month arithmetic clamps to the last day of the month
+        let february = datetime_add_months(&start, &[number(1)]).unwrap();
+        assert_eq!(datetime_day(&february, &[]).unwrap(), number(29));
+        let back = datetime_add_months(&february, &[number(-2)]).unwrap();
+        assert_eq!(datetime_month(&back, &[]).unwrap(), number(12));
+
+        assert!(datetime_utc(&Value::Unit, &[number(2024), number(2), number(30)]).is_err());
+    }
+}
@@ -6,7 +6,7 @@ use loft_builtin_macros::{loft_builtin, types};
 use rust_decimal::Decimal;
 
 /// Encode a string to base64
-#[loft_builtin(encoding.base64_encode)]
+#[loft_builtin(encoding.base64_encode(data: str) -> str)]
 #[types(string)]
 fn base64_encode(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     let input = match &args[0] {
@@ -15,11 +15,11 @@ fn base64_encode(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     };
 
     let encoded = general_purpose::STANDARD.encode(input);
-    Ok(Value::String(encoded))
+    Ok(Value::String(encoded.into()))
 }
 
 /// Decode a base64 string
-#[loft_builtin(encoding.base64_decode)]
+#[loft_builtin(encoding.base64_decode(encoded: str) -> str)]
 #[types(string)]
 fn base64_decode(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     let input = match &args[0] {
@@ -28,17 +28,17 @@ fn base64_decode(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     };
 
     let decoded = general_purpose::STANDARD
-        .decode(input)
+        .decode(&**input)
         .map_err(|e| RuntimeError::new(format!("Failed to decode base64: {}", e)))?;
 
     let decoded_str = String::from_utf8(decoded)
         .map_err(|e| RuntimeError::new(format!("Decoded data is not valid UTF-8: {}", e)))?;
 
-    Ok(Value::String(decoded_str))
+    Ok(Value::String(decoded_str.into()))
 }
 
 /// URL encode a string
-#[loft_builtin(encoding.url_encode)]
+#[loft_builtin(encoding.url_encode(data: str) -> str)]
 fn url_encode(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new(
@@ -56,11 +56,11 @@ fn url_encode(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     };
 
     let encoded = urlencoding::encode(input);
-    Ok(Value::String(encoded.to_string()))
+    Ok(Value::String(encoded.into()))
 }
 
 /// URL decode a string
-#[loft_builtin(encoding.url_decode)]
+#[loft_builtin(encoding.url_decode(encoded: str) -> str)]
 fn url_decode(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new(
@@ -80,11 +80,11 @@ fn url_decode(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     let decoded = urlencoding::decode(input)
         .map_err(|e| RuntimeError::new(format!("Failed to decode URL: {}", e)))?;
 
-    Ok(Value::String(decoded.to_string()))
+    Ok(Value::String(decoded.into()))
 }
 
 /// Convert string to bytes array
-#[loft_builtin(encoding.to_bytes)]
+#[loft_builtin(encoding.to_bytes(data: str) -> Array<num>)]
 fn to_bytes(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new(
@@ -111,7 +111,7 @@ fn to_bytes(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
 }
 
 /// Convert bytes array to string
-#[loft_builtin(encoding.from_bytes)]
+#[loft_builtin(encoding.from_bytes(bytes: Array<num>) -> str)]
 fn from_bytes(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new(
@@ -145,7 +145,7 @@ fn from_bytes(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     let string = String::from_utf8(bytes)
         .map_err(|e| RuntimeError::new(format!("Invalid UTF-8 bytes: {}", e)))?;
 
-    Ok(Value::String(string))
+    Ok(Value::String(string.into()))
 }
 
 pub fn create_encoding_builtin() -> BuiltinStruct {
@@ -171,7 +171,7 @@ mod tests {
     #[test]
     fn test_base64_encode_decode() {
         let input = "Hello World";
-        let encoded = base64_encode(&Value::Unit, &[Value::String(input.to_string())]).unwrap();
+        let encoded = base64_encode(&Value::Unit, &[Value::String(input.into())]).unwrap();
 
         let encoded_str = match encoded {
             Value::String(s) => s,
@@ -181,7 +181,7 @@ mod tests {
         let decoded = base64_decode(&Value::Unit, &[Value::String(encoded_str)]).unwrap();
 
         match decoded {
-            Value::String(s) => assert_eq!(s, input),
+            Value::String(s) => assert_eq!(&*s, input),
             _ => panic!("Expected string"),
         }
     }
@@ -189,7 +189,7 @@ mod tests {
     #[test]
     fn test_url_encode_decode() {
         let input = "hello world!";
-        let encoded = url_encode(&Value::Unit, &[Value::String(input.to_string())]).unwrap();
+        let encoded = url_encode(&Value::Unit, &[Value::String(input.into())]).unwrap();
 
         let encoded_str = match encoded {
             Value::String(s) => s,
@@ -201,7 +201,7 @@ mod tests {
         let decoded = url_decode(&Value::Unit, &[Value::String(encoded_str)]).unwrap();
 
         match decoded {
-            Value::String(s) => assert_eq!(s, input),
+            Value::String(s) => assert_eq!(&*s, input),
             _ => panic!("Expected string"),
         }
     }
@@ -209,7 +209,7 @@ mod tests {
     #[test]
     fn test_to_bytes_from_bytes() {
         let input = "Hi";
-        let bytes = to_bytes(&Value::Unit, &[Value::String(input.to_string())]).unwrap();
+        let bytes = to_bytes(&Value::Unit, &[Value::String(input.into())]).unwrap();
 
         let bytes_array = match bytes {
             Value::Array(arr) => arr,
@@ -221,7 +221,7 @@ mod tests {
         let decoded = from_bytes(&Value::Unit, &[Value::Array(bytes_array)]).unwrap();
 
         match decoded {
-            Value::String(s) => assert_eq!(s, input),
+            Value::String(s) => assert_eq!(&*s, input),
             _ => panic!("Expected string"),
         }
     }
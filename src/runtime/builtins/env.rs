@@ -0,0 +1,159 @@
+use crate::runtime::builtin::{BuiltinMethod, BuiltinStruct};
+use crate::runtime::value::Value;
+use crate::runtime::{RuntimeError, RuntimeResult};
+use loft_builtin_macros::loft_builtin;
+use std::sync::Mutex;
+
+/// Arguments given after the script path, exposed as `env.args`
+static SCRIPT_ARGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Set the arguments scripts see in `env.args`
+///
+/// Must be called before the interpreter is created.
+pub fn set_script_args(args: Vec<String>) {
+    *SCRIPT_ARGS.lock().unwrap() = args;
+}
+
+/// Get an environment variable, or the default (void if not given) when it is
+/// unset
+#[loft_builtin(env.get(name: str, default: str) -> str)]
+fn env_get(
+    #[required] _this: &Value,
+    #[required]
+    #[types(string)]
+    args: &[Value],
+) -> RuntimeResult<Value> {
+    let name = match &args[0] {
+        Value::String(name) => name,
+        _ => unreachable!(),
+    };
+
+    match std::env::var(&**name) {
+        Ok(value) => Ok(Value::String(value.into())),
+        Err(_) => Ok(args.get(1).cloned().unwrap_or(Value::Unit)),
+    }
+}
+
+/// Set an environment variable for this process and the commands it runs
+#[loft_builtin(env.set(name: str, value: str))]
+fn env_set(
+    #[required] _this: &Value,
+    #[required]
+    #[types(string, string)]
+    args: &[Value],
+) -> RuntimeResult<Value> {
+    match (&args[0], args.get(1)) {
+        (Value::String(name), Some(Value::String(value))) => {
+            if name.is_empty() || name.contains(['=', '\0']) || value.contains('\0') {
+                return Err(RuntimeError::new(format!(
+                    "Invalid environment variable '{}'",
+                    name
+                )));
+            }
+            std::env::set_var(&**name, &**value);
+            Ok(Value::Unit)
+        }
+        _ => Err(RuntimeError::new("env.set() requires a name and a value")),
+    }
+}
+
+/// Get all environment variables as a map, sorted by name
+#[loft_builtin(env.vars() -> Map<str, str>)]
+fn env_vars(_this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let mut vars: Vec<(String, String)> = std::env::vars_os()
+        .map(|(k, v)| {
+            (
+                k.to_string_lossy().to_string(),
+                v.to_string_lossy().to_string(),
+            )
+        })
+        .collect();
+    vars.sort();
+
+    Ok(Value::Map(
+        vars.into_iter()
+            .map(|(k, v)| (Value::String(k.into()), Value::String(v.into())))
+            .collect(),
+    ))
+}
+
+/// Get the current working directory
+#[loft_builtin(env.cwd() -> str)]
+fn env_cwd(_this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let cwd = std::env::current_dir()
+        .map_err(|e| RuntimeError::new(format!("Failed to get current directory: {}", e)))?;
+    Ok(Value::String(cwd.to_string_lossy().into()))
+}
+
+/// Stop the program with the given status code (0 if not given), the same as
+/// exit()
+#[loft_builtin(env.exit(code: num))]
+fn env_exit(_this: &Value, #[types(number)] args: &[Value]) -> RuntimeResult<Value> {
+    crate::runtime::exit::exit(args)
+}
+
+/// Create the env builtin struct
+pub fn create_env_builtin() -> BuiltinStruct {
+    let mut env = BuiltinStruct::new("env");
+
+    let args = SCRIPT_ARGS.lock().unwrap().clone();
+    env.add_field(
+        "args",
+        Value::Array(
+            args.into_iter()
+                .map(|arg| Value::String(arg.into()))
+                .collect(),
+        ),
+    );
+    env.add_field("platform", Value::String(std::env::consts::OS.into()));
+    env.add_field("arch", Value::String(std::env::consts::ARCH.into()));
+
+    env.add_method("get", env_get as BuiltinMethod);
+    env.add_method("set", env_set as BuiltinMethod);
+    env.add_method("vars", env_vars as BuiltinMethod);
+    env.add_method("cwd", env_cwd as BuiltinMethod);
+    env.add_method("exit", env_exit as BuiltinMethod);
+
+    env
+}
+
+// Register the builtin automatically
+crate::submit_builtin!("env", create_env_builtin);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_set_and_defaults() {
+        let name = Value::String("LOFT_ENV_TEST_VALUE".into());
+        assert_eq!(
+            env_get(&Value::Unit, std::slice::from_ref(&name)).unwrap(),
+            Value::Unit
+        );
+        assert_eq!(
+            env_get(
+                &Value::Unit,
+                &[name.clone(), Value::String("fallback".into())]
+            )
+            .unwrap(),
+            Value::String("fallback".into())
+        );
+
+        env_set(&Value::Unit, &[name.clone(), Value::String("42".into())]).unwrap();
+        assert_eq!(
+            env_get(&Value::Unit, std::slice::from_ref(&name)).unwrap(),
+            Value::String("42".into())
+        );
+        match env_vars(&Value::Unit, &[]).unwrap() {
+            Value::Map(entries) => assert!(entries.contains(&(name, Value::String("42".into())))),
+            other => panic!("expected a map, got {:?}", other),
+        }
+
+        assert!(env_set(
+            &Value::Unit,
+            &[Value::String("A=B".into()), Value::String("x".into())]
+        )
+        .is_err());
+    }
+}
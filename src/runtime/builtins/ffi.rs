@@ -75,20 +75,20 @@ fn f32_to_value(result: f32) -> RuntimeResult<Value> {
     ))
 }
 
-/// Load a shared library
+/// Load a shared library from the given path
 ///
 /// # Example
 /// ```loft
 /// let lib = ffi.load("libm.so.6");
 /// ```
-#[loft_builtin(ffi.load)]
+#[loft_builtin(ffi.load(path: str) -> FfiLibrary)]
 #[types(string)]
 pub fn ffi_load(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new("ffi_load requires a library path"));
     }
     let path = match &args[0] {
-        Value::String(s) => s.clone(),
+        Value::String(s) => s.to_string(),
         _ => unreachable!(),
     };
 
@@ -108,7 +108,7 @@ pub fn ffi_load(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
 
     // Create the FFI library struct
     let mut ffi_struct = BuiltinStruct::new("FfiLibrary");
-    ffi_struct.add_field("path", Value::String(path.clone()));
+    ffi_struct.add_field("path", Value::String(path.clone().into()));
     ffi_struct.add_method("symbol", ffi_symbol);
     ffi_struct.add_method("call", ffi_lib_call);
 
@@ -125,12 +125,12 @@ fn ffi_lib_call(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     }
 
     let symbol_name = match &args[0] {
-        Value::String(s) => s.clone(),
+        Value::String(s) => s.to_string(),
         _ => return Err(RuntimeError::new("Symbol name must be a string")),
     };
 
     // Get the symbol first
-    let symbol_result = ffi_symbol(this, &[Value::String(symbol_name)])?;
+    let symbol_result = ffi_symbol(this, &[Value::String(symbol_name.into())])?;
 
     // Then call it with the remaining arguments
     ffi_call(&symbol_result, &args[1..])
@@ -145,7 +145,7 @@ fn ffi_symbol(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     // Extract the library path from the struct
     let path = match this {
         Value::Builtin(builtin) => match builtin.fields.get("path") {
-            Some(Value::String(s)) => s.clone(),
+            Some(Value::String(s)) => s.to_string(),
             _ => {
                 return Err(RuntimeError::new(
                     "Internal error: FfiLibrary missing path field",
@@ -160,7 +160,7 @@ fn ffi_symbol(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     };
 
     let symbol_name = match &args[0] {
-        Value::String(s) => s.clone(),
+        Value::String(s) => s.to_string(),
         _ => return Err(RuntimeError::new("Symbol name must be a string")),
     };
 
@@ -183,8 +183,8 @@ fn ffi_symbol(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
 
     // Create a callable FFI function struct
     let mut ffi_func = BuiltinStruct::new("FfiFunction");
-    ffi_func.add_field("name", Value::String(symbol_name.clone()));
-    ffi_func.add_field("library_path", Value::String(path));
+    ffi_func.add_field("name", Value::String(symbol_name.clone().into()));
+    ffi_func.add_field("library_path", Value::String(path.into()));
     ffi_func.add_method("call", ffi_call);
 
     Ok(Value::Builtin(ffi_func))
@@ -196,7 +196,7 @@ fn ffi_call(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     let (symbol_name, library_path) = match this {
         Value::Builtin(builtin) => {
             let name = match builtin.fields.get("name") {
-                Some(Value::String(s)) => s.clone(),
+                Some(Value::String(s)) => s.to_string(),
                 _ => {
                     return Err(RuntimeError::new(
                         "Internal error: FfiFunction missing name field",
@@ -204,7 +204,7 @@ fn ffi_call(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
                 }
             };
             let path = match builtin.fields.get("library_path") {
-                Some(Value::String(s)) => s.clone(),
+                Some(Value::String(s)) => s.to_string(),
                 _ => {
                     return Err(RuntimeError::new(
                         "Internal error: FfiFunction missing library_path field",
@@ -232,7 +232,7 @@ fn ffi_call(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     }
 
     let signature = match &args[0] {
-        Value::String(s) => s.clone(),
+        Value::String(s) => s.to_string(),
         _ => {
             return Err(RuntimeError::new(
                 "First argument must be a signature string",
@@ -442,7 +442,7 @@ mod tests {
 
     #[test]
     fn test_ffi_load_invalid_path() {
-        let result = ffi_load(&Value::Unit, &[Value::String("nonexistent.so".to_string())]);
+        let result = ffi_load(&Value::Unit, &[Value::String("nonexistent.so".into())]);
         assert!(result.is_err());
     }
 
@@ -0,0 +1,114 @@
+//! Tables of the resources builtins hand out as handles.
+//!
+//! Sockets, open files, databases and the like can't be loft values, so each
+//! kind lives in a process-wide `Handles` table and the builtin struct a script
+//! holds only carries the id of its entry. Every copy of a handle refers to the
+//! same resource, and removing the entry closes it for all of them.
+
+use crate::runtime::value::Value;
+use crate::runtime::{RuntimeError, RuntimeResult};
+use rust_decimal::prelude::ToPrimitive;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+pub struct Handles<T> {
+    entries: Mutex<BTreeMap<u64, T>>,
+    next_id: AtomicU64,
+}
+
+impl<T> Handles<T> {
+    pub const fn new() -> Self {
+        Self {
+            entries: Mutex::new(BTreeMap::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Store a resource and return the id its handles carry
+    pub fn insert(&self, resource: T) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.entries.lock().unwrap().insert(id, resource);
+        id
+    }
+
+    /// Take a resource out of the table, closing it for every handle
+    pub fn remove(&self, id: u64) -> Option<T> {
+        self.entries.lock().unwrap().remove(&id)
+    }
+
+    /// Run `f` on a resource with the whole table locked, for resources that
+    /// don't have a lock of their own
+    pub fn with<R>(&self, id: u64, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        self.entries.lock().unwrap().get_mut(&id).map(f)
+    }
+}
+
+impl<T> Default for Handles<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> Handles<T> {
+    /// A copy of a resource, usually an `Arc`, so that using it doesn't hold
+    /// the table
+    pub fn get(&self, id: u64) -> Option<T> {
+        self.entries.lock().unwrap().get(&id).cloned()
+    }
+}
+
+/// The id in the `field` of a handle whose struct is one of `names`, `kind`
+/// naming what the method expects, such as "a socket". Checking the name keeps
+/// another builtin's id from reaching into this table.
+pub fn handle_id(
+    this: &Value,
+    names: &[&str],
+    field: &str,
+    method: &str,
+    kind: &str,
+) -> RuntimeResult<u64> {
+    match this {
+        Value::Builtin(handle) if names.contains(&handle.name.as_str()) => {
+            match handle.fields.get(field) {
+                Some(Value::Number(id)) => id.to_u64(),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+    .ok_or_else(|| RuntimeError::new(format!("{}() can only be called on {}", method, kind)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::builtin::BuiltinStruct;
+    use rust_decimal::Decimal;
+
+    #[test]
+    fn test_handles() {
+        let table = Handles::new();
+        let first = table.insert(String::from("first"));
+        let second = table.insert(String::from("second"));
+        assert_ne!(first, second);
+
+        table.with(first, |entry| entry.push('!'));
+        assert_eq!(table.get(first).as_deref(), Some("first!"));
+        assert_eq!(table.remove(first).as_deref(), Some("first!"));
+        assert_eq!(table.get(first), None);
+        assert_eq!(table.with(first, |_| ()), None);
+        assert_eq!(table.get(second).as_deref(), Some("second"));
+
+        let mut handle = BuiltinStruct::new("Thing");
+        handle.add_field("id", Value::Number(Decimal::from(second)));
+        let handle = Value::Builtin(handle);
+        assert_eq!(
+            handle_id(&handle, &["Thing"], "id", "use", "a thing").unwrap(),
+            second
+        );
+        let error = handle_id(&Value::Unit, &["Thing"], "id", "use", "a thing").unwrap_err();
+        assert_eq!(error.message, "use() can only be called on a thing");
+        assert!(handle_id(&handle, &["Other"], "id", "use", "another").is_err());
+    }
+}
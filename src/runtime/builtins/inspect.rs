@@ -0,0 +1,305 @@
+//! Memory and environment inspection for debugging long-running scripts.
+//!
+//! Sizes are approximate: every value counts its own slot plus the data it
+//! owns, and strings shared between values are counted once per reference.
+//! Builtin modules such as `term` are left out of every count.
+
+use crate::runtime::builtin::{BuiltinMethod, BuiltinStruct};
+use crate::runtime::traits::ToString;
+use crate::runtime::value::Value;
+use crate::runtime::{RuntimeError, RuntimeResult, Scope};
+use loft_builtin_macros::loft_builtin;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, HashMap};
+
+/// Approximate number of bytes used by a value and everything it holds
+pub fn heap_size(value: &Value) -> usize {
+    let slot = std::mem::size_of::<Value>();
+    let fields = |fields: &HashMap<String, Value>| -> usize {
+        fields
+            .iter()
+            .map(|(name, value)| name.len() + heap_size(value))
+            .sum()
+    };
+
+    match value {
+        Value::Builtin(_) | Value::BuiltinFn(_) => 0,
+        Value::String(s) => slot + s.len(),
+        Value::Bytes(b) => slot + b.len(),
+        Value::Array(items) => slot + items.iter().map(heap_size).sum::<usize>(),
+        Value::Map(entries) => {
+            slot + entries
+                .iter()
+                .map(|(key, value)| heap_size(key) + heap_size(value))
+                .sum::<usize>()
+        }
+        Value::Set(items) => {
+            slot + items
+                .iter()
+                .map(|item| heap_size(item.value()))
+                .sum::<usize>()
+        }
+        Value::Struct { name, fields: f } => slot + name.len() + fields(f),
+        Value::Closure { captured_env, .. } => slot + fields(captured_env),
+        Value::Module { name, exports } => slot + name.len() + fields(exports),
+        Value::EnumVariant {
+            enum_name,
+            variant_name,
+            values,
+        } => {
+            slot + enum_name.len()
+                + variant_name.len()
+                + values.iter().map(heap_size).sum::<usize>()
+        }
+        Value::Promise(inner) => slot + heap_size(inner),
+        Value::BoundMethod { object, .. } | Value::UserMethod { object, .. } => {
+            slot + heap_size(object)
+        }
+        Value::Function { name, .. } => slot + name.len(),
+        _ => slot,
+    }
+}
+
+/// Count a value and everything it holds by type name
+pub fn count_values(value: &Value, counts: &mut BTreeMap<String, usize>) {
+    if matches!(value, Value::Builtin(_) | Value::BuiltinFn(_)) {
+        return;
+    }
+    *counts.entry(value.type_name()).or_default() += 1;
+
+    match value {
+        Value::Array(items) | Value::EnumVariant { values: items, .. } => {
+            items.iter().for_each(|item| count_values(item, counts))
+        }
+        Value::Map(entries) => {
+            for (key, value) in entries {
+                count_values(key, counts);
+                count_values(value, counts);
+            }
+        }
+        Value::Set(items) => items
+            .iter()
+            .for_each(|item| count_values(item.value(), counts)),
+        Value::Struct { fields, .. } => fields
+            .values()
+            .for_each(|value| count_values(value, counts)),
+        Value::Promise(inner) => count_values(inner, counts),
+        _ => {}
+    }
+}
+
+/// Variables defined in the environment, skipping builtin modules
+fn variables(scopes: &[Scope]) -> impl Iterator<Item = &Value> {
+    scopes
+        .iter()
+        .flat_map(|scope| scope.values())
+        .filter(|value| !matches!(value, Value::Builtin(_) | Value::BuiltinFn(_)))
+}
+
+fn object(fields: HashMap<String, Value>) -> Value {
+    Value::Struct {
+        name: "Object".to_string(),
+        fields,
+    }
+}
+
+/// Scope depth, variable count, value counts and approximate size of an environment
+pub fn environment_stats(scopes: &[Scope]) -> Value {
+    let mut counts = BTreeMap::new();
+    let mut heap_bytes = 0;
+    let mut variable_count = 0;
+    for value in variables(scopes) {
+        count_values(value, &mut counts);
+        heap_bytes += heap_size(value);
+        variable_count += 1;
+    }
+
+    let values = counts
+        .into_iter()
+        .map(|(name, count)| (name, Value::Number(Decimal::from(count))))
+        .collect();
+    object(HashMap::from([
+        (
+            "depth".to_string(),
+            Value::Number(Decimal::from(scopes.len())),
+        ),
+        (
+            "variables".to_string(),
+            Value::Number(Decimal::from(variable_count)),
+        ),
+        ("values".to_string(), object(values)),
+        (
+            "heap_bytes".to_string(),
+            Value::Number(Decimal::from(heap_bytes)),
+        ),
+    ]))
+}
+
+/// A value as JSON, describing values that JSON cannot hold such as functions
+pub fn describe(value: &Value) -> serde_json::Value {
+    use serde_json::Value as Json;
+
+    match value {
+        Value::Unit => Json::Null,
+        Value::Boolean(b) => Json::Bool(*b),
+        Value::Number(n) if n.fract().is_zero() && n.to_i64().is_some() => {
+            Json::from(n.to_i64().unwrap())
+        }
+        Value::Number(n) => n
+            .to_f64()
+            .and_then(serde_json::Number::from_f64)
+            .map_or_else(|| Json::String(n.to_string()), Json::Number),
+        Value::String(s) => Json::String(s.to_string()),
+        Value::Array(items) => Json::Array(items.iter().map(describe).collect()),
+        Value::Set(items) => Json::Array(items.iter().map(|item| describe(item.value())).collect()),
+        Value::Map(entries) => Json::Object(
+            entries
+                .iter()
+                .map(|(key, value)| (key.to_string(), describe(value)))
+                .collect(),
+        ),
+        Value::Struct { name, fields } => {
+            let mut object: serde_json::Map<_, _> = fields
+                .iter()
+                .map(|(field, value)| (field.clone(), describe(value)))
+                .collect();
+            if name != "Object" {
+                object.insert("$type".to_string(), Json::String(name.clone()));
+            }
+            Json::Object(object)
+        }
+        Value::Function { name, .. } => Json::String(format!("<function {}>", name)),
+        Value::Module { name, .. } => Json::String(format!("<module {}>", name)),
+        other => Json::String(other.to_string()),
+    }
+}
+
+/// The variables of each scope as a JSON string, outermost scope first
+pub fn environment_json(scopes: &[Scope]) -> RuntimeResult<String> {
+    let scopes: Vec<serde_json::Value> = scopes
+        .iter()
+        .map(|scope| {
+            let mut names: Vec<_> = scope
+                .iter()
+                .filter(|(_, value)| !matches!(value, Value::Builtin(_) | Value::BuiltinFn(_)))
+                .collect();
+            names.sort_by(|a, b| a.0.cmp(b.0));
+            serde_json::Value::Object(
+                names
+                    .into_iter()
+                    .map(|(name, value)| (name.clone(), describe(value)))
+                    .collect(),
+            )
+        })
+        .collect();
+    serde_json::to_string_pretty(&scopes)
+        .map_err(|e| RuntimeError::new(format!("Failed to dump environment: {}", e)))
+}
+
+/// Approximate number of bytes used by a value and everything it holds
+#[loft_builtin(inspect.size(value: any) -> num)]
+fn inspect_size(_this: &Value, #[required] args: &[Value]) -> RuntimeResult<Value> {
+    Ok(Value::Number(Decimal::from(heap_size(&args[0]))))
+}
+
+/// Count a value and everything it holds by type, e.g. { array: 1, num: 3 }
+#[loft_builtin(inspect.counts(value: any) -> Object)]
+fn inspect_counts(_this: &Value, #[required] args: &[Value]) -> RuntimeResult<Value> {
+    let mut counts = BTreeMap::new();
+    count_values(&args[0], &mut counts);
+    Ok(object(
+        counts
+            .into_iter()
+            .map(|(name, count)| (name, Value::Number(Decimal::from(count))))
+            .collect(),
+    ))
+}
+
+/// Statistics for the running program: depth (number of scopes), variables,
+/// values (counts by type) and heap_bytes (approximate size of all variables)
+// The environment is read by the interpreter, which intercepts `inspect.stats`
+#[loft_builtin(inspect.stats() -> Object)]
+fn inspect_stats(_this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    Err(RuntimeError::new(
+        "inspect.stats() can only be called from a running program",
+    ))
+}
+
+/// The variables in scope as a JSON string, one object per scope with the
+/// outermost first
+// The environment is read by the interpreter, which intercepts `inspect.env`
+#[loft_builtin(inspect.env() -> str)]
+fn inspect_env(_this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    Err(RuntimeError::new(
+        "inspect.env() can only be called from a running program",
+    ))
+}
+
+/// Create the inspect builtin struct
+pub fn create_inspect_builtin() -> BuiltinStruct {
+    let mut inspect = BuiltinStruct::new("inspect");
+
+    inspect.add_method("size", inspect_size as BuiltinMethod);
+    inspect.add_method("counts", inspect_counts as BuiltinMethod);
+    inspect.add_method("stats", inspect_stats as BuiltinMethod);
+    inspect.add_method("env", inspect_env as BuiltinMethod);
+
+    inspect
+}
+
+// Register the builtin automatically
+crate::submit_builtin!("inspect", create_inspect_builtin);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn num(n: i64) -> Value {
+        Value::Number(Decimal::from(n))
+    }
+
+    fn field(value: &Value, name: &str) -> Value {
+        match value {
+            Value::Struct { fields, .. } => fields[name].clone(),
+            _ => panic!("Expected an object, got {:?}", value),
+        }
+    }
+
+    #[test]
+    fn test_sizes_and_counts() {
+        let slot = std::mem::size_of::<Value>();
+        let array = Value::Array(vec![num(1), Value::String("abcd".into())]);
+        assert_eq!(heap_size(&array), 3 * slot + 4);
+
+        let counts = inspect_counts(&Value::Unit, std::slice::from_ref(&array)).unwrap();
+        assert_eq!(field(&counts, "array"), num(1));
+        assert_eq!(field(&counts, "num"), num(1));
+        assert_eq!(field(&counts, "str"), num(1));
+    }
+
+    #[test]
+    fn test_environment_stats_and_json() {
+        let globals = Scope::from([
+            (
+                "term".to_string(),
+                Value::Builtin(BuiltinStruct::new("term")),
+            ),
+            ("items".to_string(), Value::Array(vec![num(1), num(2)])),
+        ]);
+        let locals = Scope::from([("name".to_string(), Value::String("loft".into()))]);
+        let scopes = vec![globals, locals];
+
+        let stats = environment_stats(&scopes);
+        assert_eq!(field(&stats, "depth"), num(2));
+        assert_eq!(field(&stats, "variables"), num(2));
+        assert_eq!(field(&field(&stats, "values"), "num"), num(2));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&environment_json(&scopes).unwrap()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!([{ "items": [1, 2] }, { "name": "loft" }])
+        );
+    }
+}
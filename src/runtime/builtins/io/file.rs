@@ -0,0 +1,326 @@
+//! File handles returned by `fs.open()` for reading and writing in pieces.
+//!
+//! Reads go through a buffer that is kept with the open file, so copies of a
+//! handle share one position and a `read_line` followed by `read_chunk`
+//! carries on where the line ended. Permission is checked once, when the file
+//! is opened, for the access its mode asks for.
+
+use crate::runtime::builtin::{BuiltinMethod, BuiltinStruct};
+use crate::runtime::builtins::handles::{self, Handles};
+use crate::runtime::permission_context::{check_read_permission, check_write_permission};
+use crate::runtime::value::Value;
+use crate::runtime::{RuntimeError, RuntimeResult};
+use loft_builtin_macros::loft_builtin;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+
+/// An open file and the mode it was opened with
+#[loft_builtin(fs::file)]
+pub struct File {
+    reader: BufReader<std::fs::File>,
+    readable: bool,
+    writable: bool,
+}
+
+static OPEN_FILES: Handles<File> = Handles::new();
+
+/// Open a file for streaming reads and writes. Modes are "r" (default), "w",
+/// "a", "r+", "w+" and "a+"
+#[loft_builtin(fs.open(path: str, mode: str) -> File)]
+pub fn fs_open(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let path = match args.first() {
+        Some(Value::String(path)) => path.clone(),
+        _ => return Err(RuntimeError::new("fs.open() requires a path argument")),
+    };
+    let mode = match args.get(1) {
+        Some(Value::String(mode)) => mode.to_string(),
+        None => "r".to_string(),
+        _ => return Err(RuntimeError::new("fs.open() mode must be a string")),
+    };
+
+    if crate::runtime::vfs::is_mounted() {
+        return Err(RuntimeError::new(
+            "fs.open() is not available on the in-memory file system, use fs.read() and fs.write()",
+        ));
+    }
+
+    let mut options = std::fs::OpenOptions::new();
+    let (readable, writable) = match mode.as_str() {
+        "r" => (true, false),
+        "w" => (false, true),
+        "a" => (false, true),
+        "r+" => (true, true),
+        "w+" => (true, true),
+        "a+" => (true, true),
+        _ => {
+            return Err(RuntimeError::new(format!(
+                "Invalid file mode '{}', expected one of r, w, a, r+, w+, a+",
+                mode
+            )))
+        }
+    };
+    match mode.as_str() {
+        "w" | "w+" => options.create(true).truncate(true),
+        "a" | "a+" => options.create(true).append(true),
+        _ => &mut options,
+    }
+    .read(readable)
+    .write(writable);
+
+    if readable {
+        check_read_permission(&path, Some("fs.open()")).map_err(RuntimeError::new)?;
+    }
+    if writable {
+        check_write_permission(&path, Some("fs.open()")).map_err(RuntimeError::new)?;
+    }
+
+    let file = options
+        .open(&*path)
+        .map_err(|e| RuntimeError::new(format!("Failed to open file '{}': {}", path, e)))?;
+
+    let id = OPEN_FILES.insert(File {
+        reader: BufReader::new(file),
+        readable,
+        writable,
+    });
+
+    let mut handle = create_file_builtin();
+    handle.add_field("id", Value::Number(Decimal::from(id)));
+    handle.add_field("path", Value::String(path));
+    handle.add_field("mode", Value::String(mode.into()));
+    Ok(Value::Builtin(handle))
+}
+
+/// Id of the open file a handle refers to
+fn handle_id(this: &Value, method: &str) -> RuntimeResult<u64> {
+    handles::handle_id(this, &["File"], "id", method, "a File")
+}
+
+/// Run `f` on the open file behind a handle
+fn with_file<T>(
+    this: &Value,
+    method: &str,
+    f: impl FnOnce(&mut File) -> std::io::Result<T>,
+) -> RuntimeResult<T> {
+    OPEN_FILES
+        .with(handle_id(this, method)?, f)
+        .ok_or_else(|| RuntimeError::new(format!("{}() called on a closed file", method)))?
+        .map_err(|e| RuntimeError::new(format!("{}() failed: {}", method, e)))
+}
+
+fn not_permitted(what: &str) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::PermissionDenied,
+        format!("file was not opened for {}", what),
+    )
+}
+
+/// Read up to size bytes, or an empty string at end of file
+#[loft_builtin(File.read_chunk(size: num) -> str)]
+fn file_read_chunk(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let size = match args.first() {
+        Some(Value::Number(n)) => n.to_usize().filter(|n| *n > 0),
+        _ => None,
+    }
+    .ok_or_else(|| RuntimeError::new("read_chunk() requires a positive size"))?;
+
+    with_file(this, "read_chunk", |file| {
+        if !file.readable {
+            return Err(not_permitted("reading"));
+        }
+
+        let mut bytes = Vec::with_capacity(size);
+        (&mut file.reader)
+            .take(size as u64)
+            .read_to_end(&mut bytes)?;
+
+        // Finish a multi-byte character cut off at the end of the chunk
+        while let Err(e) = std::str::from_utf8(&bytes) {
+            if e.error_len().is_some() || bytes.len() - e.valid_up_to() >= 4 {
+                break;
+            }
+            let mut next = [0u8];
+            if file.reader.read(&mut next)? == 0 {
+                break;
+            }
+            bytes.push(next[0]);
+        }
+
+        Ok(Value::String(String::from_utf8_lossy(&bytes).into()))
+    })
+}
+
+/// Read the next line including its newline, or an empty string at end of file
+#[loft_builtin(File.read_line() -> str)]
+fn file_read_line(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    with_file(this, "read_line", |file| {
+        if !file.readable {
+            return Err(not_permitted("reading"));
+        }
+        let mut bytes = Vec::new();
+        file.reader.read_until(b'\n', &mut bytes)?;
+        Ok(Value::String(String::from_utf8_lossy(&bytes).into()))
+    })
+}
+
+/// Write a string at the current position and return the number of bytes
+/// written
+#[loft_builtin(File.write_chunk(data: str) -> num)]
+fn file_write_chunk(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let data = match args.first() {
+        Some(Value::String(data)) => data.clone(),
+        _ => {
+            return Err(RuntimeError::new(
+                "write_chunk() requires a string argument",
+            ))
+        }
+    };
+
+    with_file(this, "write_chunk", |file| {
+        if !file.writable {
+            return Err(not_permitted("writing"));
+        }
+        // Drop read-ahead so the write lands at the logical position
+        let position = file.reader.stream_position()?;
+        file.reader.seek(SeekFrom::Start(position))?;
+        file.reader.get_mut().write_all(data.as_bytes())?;
+        Ok(Value::Number(Decimal::from(data.len())))
+    })
+}
+
+/// Move to a byte offset from "start" (default), "current" or "end" and return
+/// the new position
+#[loft_builtin(File.seek(offset: num, whence: str) -> num)]
+fn file_seek(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let offset = match args.first() {
+        Some(Value::Number(n)) => n.to_i64(),
+        _ => None,
+    }
+    .ok_or_else(|| RuntimeError::new("seek() requires an offset"))?;
+    let target = match args.get(1) {
+        None => SeekFrom::Start(offset.max(0) as u64),
+        Some(Value::String(whence)) => match &**whence {
+            "start" => SeekFrom::Start(offset.max(0) as u64),
+            "current" => SeekFrom::Current(offset),
+            "end" => SeekFrom::End(offset),
+            other => {
+                return Err(RuntimeError::new(format!(
+                    "Invalid seek origin '{}', expected start, current or end",
+                    other
+                )))
+            }
+        },
+        _ => return Err(RuntimeError::new("seek() origin must be a string")),
+    };
+
+    with_file(this, "seek", |file| {
+        let position = file.reader.seek(target)?;
+        Ok(Value::Number(Decimal::from(position)))
+    })
+}
+
+/// Current byte offset in the file
+#[loft_builtin(File.position() -> num)]
+fn file_position(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    with_file(this, "position", |file| {
+        Ok(Value::Number(Decimal::from(file.reader.stream_position()?)))
+    })
+}
+
+/// Write any buffered data to disk
+#[loft_builtin(File.flush())]
+fn file_flush(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    with_file(this, "flush", |file| {
+        file.reader.get_mut().sync_data()?;
+        Ok(Value::Unit)
+    })
+}
+
+/// Close the file
+#[loft_builtin(File.close())]
+fn file_close(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    match OPEN_FILES.remove(handle_id(this, "close")?) {
+        Some(_) => Ok(Value::Unit),
+        None => Err(RuntimeError::new("close() called on a closed file")),
+    }
+}
+
+/// Create the methods shared by every file handle
+fn create_file_builtin() -> BuiltinStruct {
+    let mut file = BuiltinStruct::new("File");
+
+    file.add_method("read_chunk", file_read_chunk as BuiltinMethod);
+    file.add_method("read_line", file_read_line as BuiltinMethod);
+    file.add_method("write_chunk", file_write_chunk as BuiltinMethod);
+    file.add_method("seek", file_seek as BuiltinMethod);
+    file.add_method("position", file_position as BuiltinMethod);
+    file.add_method("flush", file_flush as BuiltinMethod);
+    file.add_method("close", file_close as BuiltinMethod);
+
+    file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::builtins::testing::{call, number, string};
+
+    #[test]
+    fn test_chunked_read_write_and_seek() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.txt");
+        let path = path.to_str().unwrap();
+
+        let file = fs_open(&Value::Unit, &[string(path), string("w+")]).unwrap();
+        assert_eq!(
+            call(&file, "write_chunk", &[string("héllo\nworld\n")]).unwrap(),
+            number(13)
+        );
+        assert_eq!(call(&file, "seek", &[number(0)]).unwrap(), number(0));
+
+        // The two-byte 'é' straddles the boundary and is read whole
+        assert_eq!(
+            call(&file, "read_chunk", &[number(2)]).unwrap(),
+            string("hé")
+        );
+        assert_eq!(call(&file, "read_line", &[]).unwrap(), string("llo\n"));
+        assert_eq!(call(&file, "position", &[]).unwrap(), number(7));
+        assert_eq!(
+            call(&file, "read_chunk", &[number(100)]).unwrap(),
+            string("world\n")
+        );
+        assert_eq!(
+            call(&file, "read_chunk", &[number(100)]).unwrap(),
+            string("")
+        );
+
+        call(&file, "seek", &[number(-6), string("end")]).unwrap();
+        call(&file, "write_chunk", &[string("WORLD")]).unwrap();
+        call(&file, "close", &[]).unwrap();
+
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "héllo\nWORLD\n");
+        assert!(call(&file, "read_line", &[]).is_err());
+    }
+
+    #[test]
+    fn test_mode_limits_operations() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("log.txt");
+        std::fs::write(&path, "first\n").unwrap();
+        let path = path.to_str().unwrap();
+
+        let file = fs_open(&Value::Unit, &[string(path), string("a")]).unwrap();
+        assert!(call(&file, "read_line", &[]).is_err());
+        call(&file, "write_chunk", &[string("second\n")]).unwrap();
+        call(&file, "close", &[]).unwrap();
+
+        let file = fs_open(&Value::Unit, &[string(path)]).unwrap();
+        assert!(call(&file, "write_chunk", &[string("x")]).is_err());
+        assert_eq!(call(&file, "read_line", &[]).unwrap(), string("first\n"));
+        assert_eq!(call(&file, "read_line", &[]).unwrap(), string("second\n"));
+        call(&file, "close", &[]).unwrap();
+
+        assert!(fs_open(&Value::Unit, &[string(path), string("rw")]).is_err());
+    }
+}
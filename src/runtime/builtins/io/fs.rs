@@ -1,13 +1,15 @@
+use super::walk;
 use crate::runtime::builtin::{BuiltinMethod, BuiltinStruct};
 use crate::runtime::permission_context::{check_read_permission, check_write_permission};
 use crate::runtime::value::Value;
+use crate::runtime::vfs;
 use crate::runtime::{RuntimeError, RuntimeResult};
 use loft_builtin_macros::{loft_builtin, types};
-use std::fs;
+use std::collections::HashMap;
 use std::path::Path;
 
 /// Read entire file contents as a string
-#[loft_builtin(fs.read)]
+#[loft_builtin(fs.read(path: str) -> str)]
 #[types(string)]
 fn fs_read_file(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     match &args[0] {
@@ -15,16 +17,16 @@ fn fs_read_file(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
             // Check read permission
             check_read_permission(path, Some("fs.read()")).map_err(RuntimeError::new)?;
 
-            fs::read_to_string(path)
-                .map(Value::String)
+            vfs::read_to_string(&**path)
+                .map(|content| Value::String(content.into()))
                 .map_err(|e| RuntimeError::new(format!("Failed to read file: {}", e)))
         }
         _ => unreachable!(),
     }
 }
 
-/// Write string contents to a file
-#[loft_builtin(fs.write)]
+/// Write string contents to a file (overwrites)
+#[loft_builtin(fs.write(path: str, content: str))]
 #[types(string, string)]
 fn fs_write_file(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     match (&args[0], &args[1]) {
@@ -32,7 +34,7 @@ fn fs_write_file(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
             // Check write permission
             check_write_permission(path, Some("fs.write()")).map_err(RuntimeError::new)?;
 
-            fs::write(path, content)
+            vfs::write(&**path, &**content)
                 .map(|_| Value::Unit)
                 .map_err(|e| RuntimeError::new(format!("Failed to write file: {}", e)))
         }
@@ -40,8 +42,54 @@ fn fs_write_file(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     }
 }
 
+/// Read entire file contents as Bytes
+#[loft_builtin(fs.read_bytes(path: str) -> Bytes)]
+#[types(string)]
+fn fs_read_bytes(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    match args.first() {
+        Some(Value::String(path)) => {
+            check_read_permission(path, Some("fs.read_bytes()")).map_err(RuntimeError::new)?;
+
+            vfs::read(&**path)
+                .map(|content| Value::Bytes(content.into()))
+                .map_err(|e| RuntimeError::new(format!("Failed to read file: {}", e)))
+        }
+        _ => Err(RuntimeError::new(
+            "fs.read_bytes() requires a path argument",
+        )),
+    }
+}
+
+/// Write Bytes (or a string as UTF-8) to a file
+#[loft_builtin(fs.write_bytes(path: str, data: Bytes))]
+fn fs_write_bytes(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let path = match args.first() {
+        Some(Value::String(path)) => path,
+        _ => {
+            return Err(RuntimeError::new(
+                "fs.write_bytes() requires a path argument",
+            ))
+        }
+    };
+    let content: &[u8] = match args.get(1) {
+        Some(Value::Bytes(data)) => data,
+        Some(Value::String(text)) => text.as_bytes(),
+        _ => {
+            return Err(RuntimeError::new(
+                "fs.write_bytes() requires Bytes to write",
+            ))
+        }
+    };
+
+    check_write_permission(path, Some("fs.write_bytes()")).map_err(RuntimeError::new)?;
+
+    vfs::write(&**path, content)
+        .map(|_| Value::Unit)
+        .map_err(|e| RuntimeError::new(format!("Failed to write file: {}", e)))
+}
+
 /// Append string contents to a file
-#[loft_builtin(fs.append)]
+#[loft_builtin(fs.append(path: str, content: str))]
 #[types(string, string)]
 fn fs_append_file(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     match (&args[0], &args[1]) {
@@ -49,14 +97,7 @@ fn fs_append_file(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
             // Check write permission
             check_write_permission(path, Some("fs.append()")).map_err(RuntimeError::new)?;
 
-            use std::fs::OpenOptions;
-            use std::io::Write;
-
-            OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(path)
-                .and_then(|mut file| file.write_all(content.as_bytes()))
+            vfs::append(&**path, &**content)
                 .map(|_| Value::Unit)
                 .map_err(|e| RuntimeError::new(format!("Failed to append to file: {}", e)))
         }
@@ -64,8 +105,8 @@ fn fs_append_file(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     }
 }
 
-/// Check if a file exists
-#[loft_builtin(fs.exists)]
+/// Check if a file or directory exists
+#[loft_builtin(fs.exists(path: str) -> bool)]
 #[types(string)]
 fn fs_exists(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     match &args[0] {
@@ -73,14 +114,14 @@ fn fs_exists(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
             // Check read permission
             check_read_permission(path, Some("fs.exists()")).map_err(RuntimeError::new)?;
 
-            Ok(Value::Boolean(Path::new(path).exists()))
+            Ok(Value::Boolean(vfs::exists(&**path)))
         }
         _ => unreachable!(),
     }
 }
 
 /// Check if path is a file
-#[loft_builtin(fs.is_file)]
+#[loft_builtin(fs.is_file(path: str) -> bool)]
 fn fs_is_file(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new("fs.is_file() requires a path argument"));
@@ -91,14 +132,14 @@ fn fs_is_file(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
             // Check read permission
             check_read_permission(path, Some("fs.is_file()")).map_err(RuntimeError::new)?;
 
-            Ok(Value::Boolean(Path::new(path).is_file()))
+            Ok(Value::Boolean(vfs::is_file(&**path)))
         }
         _ => Err(RuntimeError::new("fs.is_file() argument must be a string")),
     }
 }
 
 /// Check if path is a directory
-#[loft_builtin(fs.is_dir)]
+#[loft_builtin(fs.is_dir(path: str) -> bool)]
 fn fs_is_dir(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new("fs.is_dir() requires a path argument"));
@@ -109,14 +150,14 @@ fn fs_is_dir(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
             // Check read permission
             check_read_permission(path, Some("fs.is_dir()")).map_err(RuntimeError::new)?;
 
-            Ok(Value::Boolean(Path::new(path).is_dir()))
+            Ok(Value::Boolean(vfs::is_dir(&**path)))
         }
         _ => Err(RuntimeError::new("fs.is_dir() argument must be a string")),
     }
 }
 
-/// Create a directory
-#[loft_builtin(fs.create_dir)]
+/// Create a directory (creates parent directories if needed)
+#[loft_builtin(fs.create_dir(path: str))]
 fn fs_create_dir(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new(
@@ -130,7 +171,7 @@ fn fs_create_dir(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
             check_write_permission(path, Some("fs.create_dir()"))
                 .map_err(RuntimeError::new)?;
 
-            fs::create_dir_all(path)
+            vfs::create_dir_all(&**path)
                 .map(|_| Value::Unit)
                 .map_err(|e| RuntimeError::new(format!("Failed to create directory: {}", e)))
         }
@@ -141,7 +182,7 @@ fn fs_create_dir(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
 }
 
 /// Remove a file
-#[loft_builtin(fs.remove_file)]
+#[loft_builtin(fs.remove_file(path: str))]
 fn fs_remove_file(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new(
@@ -155,7 +196,7 @@ fn fs_remove_file(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
             check_write_permission(path, Some("fs.remove_file()"))
                 .map_err(RuntimeError::new)?;
 
-            fs::remove_file(path)
+            vfs::remove_file(&**path)
                 .map(|_| Value::Unit)
                 .map_err(|e| RuntimeError::new(format!("Failed to remove file: {}", e)))
         }
@@ -166,7 +207,7 @@ fn fs_remove_file(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
 }
 
 /// Remove a directory
-#[loft_builtin(fs.remove_dir)]
+#[loft_builtin(fs.remove_dir(path: str))]
 fn fs_remove_dir(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new(
@@ -180,7 +221,7 @@ fn fs_remove_dir(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
             check_write_permission(path, Some("fs.remove_dir()"))
                 .map_err(RuntimeError::new)?;
 
-            fs::remove_dir_all(path)
+            vfs::remove_dir_all(&**path)
                 .map(|_| Value::Unit)
                 .map_err(|e| RuntimeError::new(format!("Failed to remove directory: {}", e)))
         }
@@ -190,8 +231,8 @@ fn fs_remove_dir(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     }
 }
 
-/// List directory contents
-#[loft_builtin(fs.list_dir)]
+/// List contents of a directory
+#[loft_builtin(fs.list_dir(path: str) -> Array<str>)]
 fn fs_list_dir(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new("fs.list_dir() requires a path argument"));
@@ -202,34 +243,85 @@ fn fs_list_dir(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
             // Check read permission
             check_read_permission(path, Some("fs.list_dir()")).map_err(RuntimeError::new)?;
 
-            fs::read_dir(path)
+            vfs::read_dir(&**path)
                 .map_err(|e| RuntimeError::new(format!("Failed to read directory: {}", e)))
-                .and_then(|entries| {
-                    let mut files = Vec::new();
-                    for entry in entries {
-                        match entry {
-                            Ok(entry) => {
-                                if let Some(file_name) = entry.file_name().to_str() {
-                                    files.push(Value::String(file_name.to_string()));
-                                }
-                            }
-                            Err(e) => {
-                                return Err(RuntimeError::new(format!(
-                                    "Failed to read entry: {}",
-                                    e
-                                )))
-                            }
-                        }
-                    }
-                    Ok(Value::Array(files))
+                .map(|entries| {
+                    Value::Array(
+                        entries
+                            .into_iter()
+                            .map(|(name, _)| Value::String(name.into()))
+                            .collect(),
+                    )
                 })
         }
         _ => Err(RuntimeError::new("fs.list_dir() argument must be a string")),
     }
 }
 
+/// Recursively list every file and directory below a path, checking read
+/// permission for each directory
+#[loft_builtin(fs.walk(path: str) -> Array<DirEntry>)]
+#[types(string)]
+fn fs_walk(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    match &args[0] {
+        Value::String(path) => {
+            let entries = walk::walk(Path::new(&**path), "fs.walk()").map_err(RuntimeError::new)?;
+
+            Ok(Value::Array(
+                entries
+                    .into_iter()
+                    .map(|entry| {
+                        let name = entry
+                            .path
+                            .file_name()
+                            .map(|n| n.to_string_lossy().to_string())
+                            .unwrap_or_default();
+                        let mut fields = HashMap::new();
+                        fields.insert(
+                            "path".to_string(),
+                            Value::String(entry.path.to_string_lossy().into()),
+                        );
+                        fields.insert("name".to_string(), Value::String(name.into()));
+                        fields.insert("is_dir".to_string(), Value::Boolean(entry.is_dir));
+                        fields.insert("is_file".to_string(), Value::Boolean(!entry.is_dir));
+                        fields.insert(
+                            "depth".to_string(),
+                            Value::Number(rust_decimal::Decimal::from(entry.depth)),
+                        );
+                        Value::Struct {
+                            name: "DirEntry".to_string(),
+                            fields,
+                        }
+                    })
+                    .collect(),
+            ))
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// Find paths matching a glob pattern such as "src/**/*.lf", where ** matches
+/// any number of directories
+#[loft_builtin(fs.glob(pattern: str) -> Array<str>)]
+#[types(string)]
+fn fs_glob(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    match &args[0] {
+        Value::String(pattern) => walk::glob(pattern, "fs.glob()")
+            .map(|paths| {
+                Value::Array(
+                    paths
+                        .into_iter()
+                        .map(|p| Value::String(p.to_string_lossy().into()))
+                        .collect(),
+                )
+            })
+            .map_err(RuntimeError::new),
+        _ => unreachable!(),
+    }
+}
+
 /// Copy a file
-#[loft_builtin(fs.copy)]
+#[loft_builtin(fs.copy(source: str, destination: str))]
 fn fs_copy(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.len() < 2 {
         return Err(RuntimeError::new(
@@ -244,7 +336,7 @@ fn fs_copy(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
             // Check write permission for destination
             check_write_permission(dst, Some("fs.copy()")).map_err(RuntimeError::new)?;
 
-            fs::copy(src, dst)
+            vfs::copy(&**src, &**dst)
                 .map(|_| Value::Unit)
                 .map_err(|e| RuntimeError::new(format!("Failed to copy file: {}", e)))
         }
@@ -252,8 +344,8 @@ fn fs_copy(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     }
 }
 
-/// Rename/move a file
-#[loft_builtin(fs.rename)]
+/// Rename or move a file
+#[loft_builtin(fs.rename(old_path: str, new_path: str))]
 fn fs_rename(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.len() < 2 {
         return Err(RuntimeError::new(
@@ -267,7 +359,7 @@ fn fs_rename(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
             check_write_permission(src, Some("fs.rename()")).map_err(RuntimeError::new)?;
             check_write_permission(dst, Some("fs.rename()")).map_err(RuntimeError::new)?;
 
-            fs::rename(src, dst)
+            vfs::rename(&**src, &**dst)
                 .map(|_| Value::Unit)
                 .map_err(|e| RuntimeError::new(format!("Failed to rename file: {}", e)))
         }
@@ -275,8 +367,8 @@ fn fs_rename(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     }
 }
 
-/// Get file metadata (size, modified time, etc.)
-#[loft_builtin(fs.metadata)]
+/// Get file metadata as an array [size, is_file, is_dir]
+#[loft_builtin(fs.metadata(path: str) -> Array<num>)]
 fn fs_metadata(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new("fs.metadata() requires a path argument"));
@@ -287,16 +379,16 @@ fn fs_metadata(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
             // Check read permission
             check_read_permission(path, Some("fs.metadata()")).map_err(RuntimeError::new)?;
 
-            fs::metadata(path)
+            vfs::metadata(&**path)
                 .map_err(|e| RuntimeError::new(format!("Failed to get metadata: {}", e)))
                 .map(|metadata| {
                     use rust_decimal::Decimal;
 
                     // Return an array with [size, is_file, is_dir]
                     Value::Array(vec![
-                        Value::Number(Decimal::from(metadata.len())),
-                        Value::Boolean(metadata.is_file()),
-                        Value::Boolean(metadata.is_dir()),
+                        Value::Number(Decimal::from(metadata.len)),
+                        Value::Boolean(metadata.is_file),
+                        Value::Boolean(metadata.is_dir),
                     ])
                 })
         }
@@ -309,6 +401,8 @@ pub fn create_fs_builtin() -> BuiltinStruct {
 
     fs.add_method("read", fs_read_file as BuiltinMethod);
     fs.add_method("write", fs_write_file as BuiltinMethod);
+    fs.add_method("read_bytes", fs_read_bytes as BuiltinMethod);
+    fs.add_method("write_bytes", fs_write_bytes as BuiltinMethod);
     fs.add_method("append", fs_append_file as BuiltinMethod);
     fs.add_method("exists", fs_exists as BuiltinMethod);
     fs.add_method("is_file", fs_is_file as BuiltinMethod);
@@ -317,6 +411,9 @@ pub fn create_fs_builtin() -> BuiltinStruct {
     fs.add_method("remove_file", fs_remove_file as BuiltinMethod);
     fs.add_method("remove_dir", fs_remove_dir as BuiltinMethod);
     fs.add_method("list_dir", fs_list_dir as BuiltinMethod);
+    fs.add_method("walk", fs_walk as BuiltinMethod);
+    fs.add_method("glob", fs_glob as BuiltinMethod);
+    fs.add_method("open", super::file::fs_open as BuiltinMethod);
     fs.add_method("copy", fs_copy as BuiltinMethod);
     fs.add_method("rename", fs_rename as BuiltinMethod);
     fs.add_method("metadata", fs_metadata as BuiltinMethod);
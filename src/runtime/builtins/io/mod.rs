@@ -1,6 +1,8 @@
 use crate::runtime::builtin::BuiltinStruct;
 
+pub mod file;
 pub mod fs;
+mod walk;
 
 pub fn create_io_builtin() -> BuiltinStruct {
     // For now, we'll just provide the fs builtin
@@ -0,0 +1,357 @@
+//! Recursive directory traversal behind `fs.walk()` and `fs.glob()`.
+//!
+//! Every directory is checked for read permission before it is listed or a
+//! name is looked up in it, so a scoped `--allow-read` only lets a walk see
+//! the trees it covers. Symbolic
+//! links are reported but never followed, which keeps cyclic links from
+//! looping forever.
+
+use crate::runtime::permission_context::check_read_permission;
+use crate::runtime::vfs;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A file or directory found while walking a tree
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalkEntry {
+    pub path: PathBuf,
+    pub is_dir: bool,
+    /// Number of directories between the walk root and this entry
+    pub depth: usize,
+}
+
+/// Check read permission for a directory before listing it or looking up a
+/// name in it, which tells as much about it, returning the path to use for it
+fn readable_dir<'a>(dir: &'a Path, context: &str) -> Result<&'a Path, String> {
+    let shown = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    check_read_permission(&shown.to_string_lossy(), Some(context))?;
+    Ok(shown)
+}
+
+/// Entries of a directory sorted by name, after checking read permission for it
+fn read_dir_sorted(dir: &Path, context: &str) -> Result<Vec<(String, PathBuf, bool)>, String> {
+    let shown = readable_dir(dir, context)?;
+
+    let mut entries = Vec::new();
+    for (name, is_dir) in vfs::read_dir(shown)
+        .map_err(|e| format!("Failed to read directory '{}': {}", shown.display(), e))?
+    {
+        let path = dir.join(&name);
+        entries.push((name, path, is_dir));
+    }
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(entries)
+}
+
+/// Every entry below `root`, depth first, with each directory before its contents
+pub fn walk(root: &Path, context: &str) -> Result<Vec<WalkEntry>, String> {
+    fn visit(
+        dir: &Path,
+        depth: usize,
+        context: &str,
+        out: &mut Vec<WalkEntry>,
+    ) -> Result<(), String> {
+        for (_, path, is_dir) in read_dir_sorted(dir, context)? {
+            out.push(WalkEntry {
+                path: path.clone(),
+                is_dir,
+                depth,
+            });
+            if is_dir {
+                visit(&path, depth + 1, context, out)?;
+            }
+        }
+        Ok(())
+    }
+
+    let mut out = Vec::new();
+    visit(root, 0, context, &mut out)?;
+    Ok(out)
+}
+
+/// Paths matching a glob pattern such as `src/**/*.lf`, sorted
+///
+/// `*` and `?` match within a single path component, `[abc]` and `[!abc]`
+/// match one character from a set, and `**` matches any number of
+/// directories. Hidden entries only match components that start with `.`.
+pub fn glob(pattern: &str, context: &str) -> Result<Vec<PathBuf>, String> {
+    let components: Vec<&str> = pattern.split('/').filter(|c| !c.is_empty()).collect();
+
+    // Components without wildcards are joined into the directory the search starts in
+    let literal = components.iter().take_while(|c| !has_wildcard(c)).count();
+    let mut base = if pattern.starts_with('/') {
+        PathBuf::from("/")
+    } else {
+        PathBuf::new()
+    };
+    for component in &components[..literal] {
+        base.push(component);
+    }
+
+    let mut out = Vec::new();
+    if literal == components.len() {
+        readable_dir(base.parent().unwrap_or(&base), context)?;
+        let found = if vfs::is_mounted() {
+            vfs::exists(&base)
+        } else {
+            fs::symlink_metadata(&base).is_ok()
+        };
+        if found {
+            out.push(base);
+        }
+        return Ok(out);
+    }
+
+    search(&base, &components[literal..], context, &mut out)?;
+    out.sort();
+    out.dedup();
+    Ok(out)
+}
+
+fn search(dir: &Path, rest: &[&str], context: &str, out: &mut Vec<PathBuf>) -> Result<(), String> {
+    let Some((&component, remaining)) = rest.split_first() else {
+        out.push(dir.to_path_buf());
+        return Ok(());
+    };
+
+    if component == "**" {
+        // Zero directories, then one more level at a time
+        if !remaining.is_empty() || !dir.as_os_str().is_empty() {
+            search(dir, remaining, context, out)?;
+        }
+        for (name, path, is_dir) in read_dir_sorted(dir, context)? {
+            if name.starts_with('.') {
+                continue;
+            }
+            if remaining.is_empty() {
+                out.push(path.clone());
+            }
+            if is_dir {
+                search(&path, rest, context, out)?;
+            }
+        }
+        return Ok(());
+    }
+
+    if !has_wildcard(component) {
+        readable_dir(dir, context)?;
+        let path = dir.join(component);
+        match vfs::metadata(&path) {
+            Ok(_) if remaining.is_empty() => out.push(path),
+            Ok(metadata) if metadata.is_dir => search(&path, remaining, context, out)?,
+            _ => {}
+        }
+        return Ok(());
+    }
+
+    for (name, path, is_dir) in read_dir_sorted(dir, context)? {
+        if name.starts_with('.') && !component.starts_with('.') {
+            continue;
+        }
+        if !matches_component(component, &name) {
+            continue;
+        }
+        if remaining.is_empty() {
+            out.push(path);
+        } else if is_dir {
+            search(&path, remaining, context, out)?;
+        }
+    }
+    Ok(())
+}
+
+fn has_wildcard(component: &str) -> bool {
+    component.contains(['*', '?', '['])
+}
+
+/// Match a single path component against a pattern component
+fn matches_component(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    matches_from(&pattern, &name)
+}
+
+/// Walks pattern and name together. On a mismatch it goes back to the last
+/// `*` and lets it take one more character, which is enough since a later
+/// star can take whatever an earlier one would have, so this never
+/// backtracks further than that.
+fn matches_from(pattern: &[char], name: &[char]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    // Where the pattern continues after the last star, and where in the
+    // name that continuation was last tried from
+    let mut star: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if pattern.get(p) == Some(&'*') {
+            p += 1;
+            star = Some((p, n));
+        } else if let Some(len) = matches_one(&pattern[p..], name[n]) {
+            p += len;
+            n += 1;
+        } else if let Some((after, from)) = star {
+            p = after;
+            n = from + 1;
+            star = Some((after, n));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// How much of the start of `pattern` matches the character `c`, if it does
+fn matches_one(pattern: &[char], c: char) -> Option<usize> {
+    match pattern.first()? {
+        '?' => Some(1),
+        '[' => {
+            let Some(close) = pattern
+                .iter()
+                .skip(1)
+                .position(|&c| c == ']')
+                .map(|i| i + 1)
+            else {
+                // An unclosed bracket is matched literally
+                return (c == '[').then_some(1);
+            };
+            let (negated, set) = match pattern[1] {
+                '!' | '^' => (true, &pattern[2..close]),
+                _ => (false, &pattern[1..close]),
+            };
+            (in_set(set, c) != negated).then_some(close + 1)
+        }
+        &literal => (c == literal).then_some(1),
+    }
+}
+
+/// Whether `c` is in a bracket set such as `a-z0-9_`
+fn in_set(set: &[char], c: char) -> bool {
+    let mut i = 0;
+    while i < set.len() {
+        if i + 2 < set.len() && set[i + 1] == '-' {
+            if (set[i]..=set[i + 2]).contains(&c) {
+                return true;
+            }
+            i += 3;
+        } else {
+            if set[i] == c {
+                return true;
+            }
+            i += 1;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src/lib/deep")).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        for file in [
+            "src/main.lf",
+            "src/lib/util.lf",
+            "src/lib/deep/inner.lf",
+            "src/lib/notes.md",
+            ".git/config",
+            "README.md",
+        ] {
+            fs::write(root.join(file), "").unwrap();
+        }
+        dir
+    }
+
+    fn relative(root: &Path, paths: Vec<PathBuf>) -> Vec<String> {
+        paths
+            .into_iter()
+            .map(|p| p.strip_prefix(root).unwrap().to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_component_matching() {
+        assert!(matches_component("*.lf", "main.lf"));
+        assert!(!matches_component("*.lf", "main.lfx"));
+        assert!(matches_component("ma?n.lf", "main.lf"));
+        assert!(matches_component("[a-m]ain.lf", "main.lf"));
+        assert!(!matches_component("[!m]ain.lf", "main.lf"));
+        assert!(matches_component("*a*b", "xaab"));
+        assert!(matches_component("[*", "[*"));
+        assert!(matches_component("**", ""));
+        assert!(!matches_component("?", ""));
+    }
+
+    #[test]
+    fn test_many_stars_match_quickly() {
+        let name = "a".repeat(200);
+        let start = std::time::Instant::now();
+        assert!(!matches_component("*a*a*a*a*a*a*a*a*b", &name));
+        assert!(matches_component("*a*a*a*a*a*a*a*a*", &name));
+        assert!(start.elapsed() < std::time::Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_walk_lists_tree_in_order() {
+        let dir = tree();
+        let entries = walk(&dir.path().join("src"), "test").unwrap();
+        let paths = relative(dir.path(), entries.iter().map(|e| e.path.clone()).collect());
+        assert_eq!(
+            paths,
+            vec![
+                "src/lib",
+                "src/lib/deep",
+                "src/lib/deep/inner.lf",
+                "src/lib/notes.md",
+                "src/lib/util.lf",
+                "src/main.lf",
+            ]
+        );
+        assert!(entries[0].is_dir);
+        assert_eq!(entries[2].depth, 2);
+    }
+
+    #[test]
+    fn test_glob_recursive_pattern() {
+        let dir = tree();
+        let root = dir.path().to_string_lossy().to_string();
+
+        let found = glob(&format!("{}/src/**/*.lf", root), "test").unwrap();
+        assert_eq!(
+            relative(dir.path(), found),
+            vec!["src/lib/deep/inner.lf", "src/lib/util.lf", "src/main.lf"]
+        );
+
+        let found = glob(&format!("{}/*.md", root), "test").unwrap();
+        assert_eq!(relative(dir.path(), found), vec!["README.md"]);
+
+        let found = glob(&format!("{}/**/config", root), "test").unwrap();
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn test_glob_checks_permission_before_looking() {
+        use crate::runtime::permission_context::replace_permissions;
+        use crate::runtime::permissions::PermissionManager;
+
+        let dir = tree();
+        let root = dir.path().to_string_lossy().to_string();
+        let previous = replace_permissions(Some(PermissionManager::deny_all()));
+        // Whether a file exists or not, the answer is the same
+        let results: Vec<_> = ["README.md", "missing.md", "src/main.lf"]
+            .iter()
+            .map(|file| glob(&format!("{}/{}", root, file), "test"))
+            .collect();
+        replace_permissions(previous);
+
+        for result in results {
+            assert!(result.unwrap_err().starts_with("Permission denied"));
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use crate::runtime::builtin::{BuiltinMethod, BuiltinStruct};
+use crate::runtime::traits::ToString;
 use crate::runtime::value::Value;
 use crate::runtime::{RuntimeError, RuntimeResult};
 use loft_builtin_macros::{loft_builtin, required};
@@ -7,7 +8,7 @@ use serde_json;
 use std::collections::HashMap;
 
 /// Parse a JSON string into a loft value
-#[loft_builtin(json.parse)]
+#[loft_builtin(json.parse(json: str) -> any)]
 fn json_parse(#[required] _this: &Value, #[types(string)] args: &[Value]) -> RuntimeResult<Value> {
     let json_str = match &args[0] {
         Value::String(s) => s,
@@ -21,28 +22,28 @@ fn json_parse(#[required] _this: &Value, #[types(string)] args: &[Value]) -> Run
 }
 
 /// Convert a loft value to a JSON string
-#[loft_builtin(json.stringify)]
+#[loft_builtin(json.stringify(value: any) -> str)]
 #[required]
 fn json_stringify(#[required] _this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     let json_value = loft_value_to_json(&args[0])?;
     let json_str = serde_json::to_string(&json_value)
         .map_err(|e| RuntimeError::new(format!("Failed to stringify JSON: {}", e)))?;
 
-    Ok(Value::String(json_str))
+    Ok(Value::String(json_str.into()))
 }
 
 /// Convert a loft value to a pretty-printed JSON string
-#[loft_builtin(json.stringify_pretty)]
+#[loft_builtin(json.stringify_pretty(value: any) -> str)]
 #[required]
 fn json_stringify_pretty(#[required] _this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     let json_value = loft_value_to_json(&args[0])?;
     let json_str = serde_json::to_string_pretty(&json_value)
         .map_err(|e| RuntimeError::new(format!("Failed to stringify JSON: {}", e)))?;
 
-    Ok(Value::String(json_str))
+    Ok(Value::String(json_str.into()))
 }
 
-fn json_to_loft_value(json: serde_json::Value) -> RuntimeResult<Value> {
+pub(crate) fn json_to_loft_value(json: serde_json::Value) -> RuntimeResult<Value> {
     match json {
         serde_json::Value::Null => Ok(Value::Unit),
         serde_json::Value::Bool(b) => Ok(Value::Boolean(b)),
@@ -57,7 +58,7 @@ fn json_to_loft_value(json: serde_json::Value) -> RuntimeResult<Value> {
                 Err(RuntimeError::new("Invalid JSON number"))
             }
         }
-        serde_json::Value::String(s) => Ok(Value::String(s)),
+        serde_json::Value::String(s) => Ok(Value::String(s.into())),
         serde_json::Value::Array(arr) => {
             let mut values = Vec::new();
             for item in arr {
@@ -78,7 +79,7 @@ fn json_to_loft_value(json: serde_json::Value) -> RuntimeResult<Value> {
     }
 }
 
-fn loft_value_to_json(value: &Value) -> RuntimeResult<serde_json::Value> {
+pub(crate) fn loft_value_to_json(value: &Value) -> RuntimeResult<serde_json::Value> {
     use rust_decimal::prelude::ToPrimitive;
 
     match value {
@@ -91,7 +92,7 @@ fn loft_value_to_json(value: &Value) -> RuntimeResult<serde_json::Value> {
                 Err(RuntimeError::new("Failed to convert number to JSON"))
             }
         }
-        Value::String(s) => Ok(serde_json::Value::String(s.clone())),
+        Value::String(s) => Ok(serde_json::Value::String(s.to_string())),
         Value::Array(arr) => {
             let mut json_arr = Vec::new();
             for item in arr {
@@ -106,6 +107,19 @@ fn loft_value_to_json(value: &Value) -> RuntimeResult<serde_json::Value> {
             }
             Ok(serde_json::Value::Object(json_obj))
         }
+        Value::Set(items) => Ok(serde_json::Value::Array(
+            items
+                .iter()
+                .map(|item| loft_value_to_json(item.value()))
+                .collect::<RuntimeResult<_>>()?,
+        )),
+        Value::Map(entries) => {
+            let mut json_obj = serde_json::Map::new();
+            for (key, value) in entries {
+                json_obj.insert(key.to_string(), loft_value_to_json(value)?);
+            }
+            Ok(serde_json::Value::Object(json_obj))
+        }
         _ => Err(RuntimeError::new(format!(
             "Cannot convert {:?} to JSON",
             value
@@ -134,14 +148,14 @@ mod tests {
     #[test]
     fn test_json_parse_simple() {
         let json_str = r#"{"name": "Alice", "age": 30}"#;
-        let result = json_parse(&Value::Unit, &[Value::String(json_str.to_string())]);
+        let result = json_parse(&Value::Unit, &[Value::String(json_str.into())]);
         assert!(result.is_ok());
     }
 
     #[test]
     fn test_json_stringify_object() {
         let mut fields = HashMap::new();
-        fields.insert("name".to_string(), Value::String("Bob".to_string()));
+        fields.insert("name".to_string(), Value::String("Bob".into()));
         fields.insert("age".to_string(), Value::Number(Decimal::from(25)));
 
         let obj = Value::Struct {
@@ -164,7 +178,7 @@ mod tests {
     #[test]
     fn test_json_parse_array() {
         let json_str = "[1, 2, 3]";
-        let result = json_parse(&Value::Unit, &[Value::String(json_str.to_string())]);
+        let result = json_parse(&Value::Unit, &[Value::String(json_str.into())]);
         assert!(result.is_ok());
 
         match result.unwrap() {
@@ -5,7 +5,7 @@ use loft_builtin_macros::loft_builtin;
 use rust_decimal::Decimal;
 
 /// Round a number to the nearest integer
-#[loft_builtin(math.round)]
+#[loft_builtin(math.round(x: num) -> num)]
 fn math_round(#[required] _this: &Value, #[types(number)] args: &[Value]) -> RuntimeResult<Value> {
     match &args[0] {
         Value::Number(n) => {
@@ -16,8 +16,8 @@ fn math_round(#[required] _this: &Value, #[types(number)] args: &[Value]) -> Run
     }
 }
 
-/// Floor a number (round down to nearest integer)
-#[loft_builtin(math.floor)]
+/// Round down to the nearest integer
+#[loft_builtin(math.floor(x: num) -> num)]
 fn math_floor(#[required] _this: &Value, #[types(number)] args: &[Value]) -> RuntimeResult<Value> {
     match &args[0] {
         Value::Number(n) => {
@@ -28,8 +28,8 @@ fn math_floor(#[required] _this: &Value, #[types(number)] args: &[Value]) -> Run
     }
 }
 
-/// Ceiling a number (round up to nearest integer)
-#[loft_builtin(math.ceil)]
+/// Round up to the nearest integer
+#[loft_builtin(math.ceil(x: num) -> num)]
 fn math_ceil(#[required] _this: &Value, #[types(number)] args: &[Value]) -> RuntimeResult<Value> {
     match &args[0] {
         Value::Number(n) => {
@@ -40,8 +40,8 @@ fn math_ceil(#[required] _this: &Value, #[types(number)] args: &[Value]) -> Runt
     }
 }
 
-/// Absolute value of a number
-#[loft_builtin(math.abs)]
+/// Get the absolute value of a number
+#[loft_builtin(math.abs(x: num) -> num)]
 fn math_abs(#[required] _this: &Value, #[types(number)] args: &[Value]) -> RuntimeResult<Value> {
     match &args[0] {
         Value::Number(n) => {
@@ -52,8 +52,8 @@ fn math_abs(#[required] _this: &Value, #[types(number)] args: &[Value]) -> Runti
     }
 }
 
-/// Sign of a number (-1, 0, or 1)
-#[loft_builtin(math.sign)]
+/// Get the sign of a number (-1, 0, or 1)
+#[loft_builtin(math.sign(x: num) -> num)]
 fn math_sign(#[required] _this: &Value, #[types(number)] args: &[Value]) -> RuntimeResult<Value> {
     match &args[0] {
         Value::Number(n) => {
@@ -69,8 +69,8 @@ fn math_sign(#[required] _this: &Value, #[types(number)] args: &[Value]) -> Runt
     }
 }
 
-/// Minimum of two numbers
-#[loft_builtin(math.min)]
+/// Get the minimum of two numbers
+#[loft_builtin(math.min(a: num, b: num) -> num)]
 fn math_min(#[required] _this: &Value, #[types(number*)] args: &[Value]) -> RuntimeResult<Value> {
     if args.len() < 2 {
         return Err(RuntimeError::new(
@@ -84,8 +84,8 @@ fn math_min(#[required] _this: &Value, #[types(number*)] args: &[Value]) -> Runt
     }
 }
 
-/// Maximum of two numbers
-#[loft_builtin(math.max)]
+/// Get the maximum of two numbers
+#[loft_builtin(math.max(a: num, b: num) -> num)]
 fn math_max(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.len() < 2 {
         return Err(RuntimeError::new(
@@ -99,8 +99,8 @@ fn math_max(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     }
 }
 
-/// Clamp a number between min and max
-#[loft_builtin(math.clamp)]
+/// Clamp a value between min and max
+#[loft_builtin(math.clamp(value: num, min: num, max: num) -> num)]
 fn math_clamp(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.len() < 3 {
         return Err(RuntimeError::new(
@@ -5,8 +5,8 @@ use loft_builtin_macros::loft_builtin;
 use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::Decimal;
 
-/// Power: base^exponent
-#[loft_builtin(math.pow)]
+/// Raise a number to a power
+#[loft_builtin(math.pow(base: num, exponent: num) -> num)]
 fn math_pow(#[required] _this: &Value, #[types(number, number)] args: &[Value]) -> RuntimeResult<Value> {
     match (&args[0], &args[1]) {
         (Value::Number(base), Value::Number(exp)) => {
@@ -26,8 +26,8 @@ fn math_pow(#[required] _this: &Value, #[types(number, number)] args: &[Value])
     }
 }
 
-/// Square root
-#[loft_builtin(math.sqrt)]
+/// Calculate the square root
+#[loft_builtin(math.sqrt(x: num) -> num)]
 fn math_sqrt(#[required] _this: &Value, #[types(number)] args: &[Value]) -> RuntimeResult<Value> {
     match &args[0] {
         Value::Number(n) => {
@@ -49,8 +49,8 @@ fn math_sqrt(#[required] _this: &Value, #[types(number)] args: &[Value]) -> Runt
     }
 }
 
-/// Exponential function (e^x)
-#[loft_builtin(math.exp)]
+/// Calculate e^x
+#[loft_builtin(math.exp(x: num) -> num)]
 fn math_exp(#[required] _this: &Value, #[types(number)] args: &[Value]) -> RuntimeResult<Value> {
     match &args[0] {
         Value::Number(n) => {
@@ -67,8 +67,8 @@ fn math_exp(#[required] _this: &Value, #[types(number)] args: &[Value]) -> Runti
     }
 }
 
-/// Natural logarithm (ln)
-#[loft_builtin(math.ln)]
+/// Calculate the natural logarithm (base e)
+#[loft_builtin(math.ln(x: num) -> num)]
 fn math_ln(#[required] _this: &Value, #[types(number)] args: &[Value]) -> RuntimeResult<Value> {
     match &args[0] {
         Value::Number(n) => {
@@ -90,8 +90,8 @@ fn math_ln(#[required] _this: &Value, #[types(number)] args: &[Value]) -> Runtim
     }
 }
 
-/// Base-10 logarithm
-#[loft_builtin(math.log10)]
+/// Calculate the base-10 logarithm
+#[loft_builtin(math.log10(x: num) -> num)]
 fn math_log10(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new("math.log10() requires a number argument"));
@@ -117,8 +117,8 @@ fn math_log10(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     }
 }
 
-/// Logarithm with custom base
-#[loft_builtin(math.log)]
+/// Calculate the logarithm with a custom base
+#[loft_builtin(math.log(value: num, base: num) -> num)]
 fn math_log(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.len() < 2 {
         return Err(RuntimeError::new(
@@ -5,8 +5,8 @@ use loft_builtin_macros::loft_builtin;
 use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::Decimal;
 
-/// Sine function
-#[loft_builtin(math.sin)]
+/// Calculate sine (radians)
+#[loft_builtin(math.sin(x: num) -> num)]
 fn math_sin(#[required] _this: &Value, #[types(number)] args: &[Value]) -> RuntimeResult<Value> {
     match &args[0] {
         Value::Number(n) => {
@@ -23,8 +23,8 @@ fn math_sin(#[required] _this: &Value, #[types(number)] args: &[Value]) -> Runti
     }
 }
 
-/// Cosine function
-#[loft_builtin(math.cos)]
+/// Calculate cosine (radians)
+#[loft_builtin(math.cos(x: num) -> num)]
 fn math_cos(#[required] _this: &Value, #[types(number)] args: &[Value]) -> RuntimeResult<Value> {
     match &args[0] {
         Value::Number(n) => {
@@ -41,8 +41,8 @@ fn math_cos(#[required] _this: &Value, #[types(number)] args: &[Value]) -> Runti
     }
 }
 
-/// Tangent function
-#[loft_builtin(math.tan)]
+/// Calculate tangent (radians)
+#[loft_builtin(math.tan(x: num) -> num)]
 fn math_tan(#[required] _this: &Value, #[types(number)] args: &[Value]) -> RuntimeResult<Value> {
     match &args[0] {
         Value::Number(n) => {
@@ -59,8 +59,8 @@ fn math_tan(#[required] _this: &Value, #[types(number)] args: &[Value]) -> Runti
     }
 }
 
-/// Arcsine function
-#[loft_builtin(math.asin)]
+/// Calculate arcsine (returns radians)
+#[loft_builtin(math.asin(x: num) -> num)]
 fn math_asin(#[required] _this: &Value, #[types(number)] args: &[Value]) -> RuntimeResult<Value> {
     match &args[0] {
         Value::Number(n) => {
@@ -82,8 +82,8 @@ fn math_asin(#[required] _this: &Value, #[types(number)] args: &[Value]) -> Runt
     }
 }
 
-/// Arccosine function
-#[loft_builtin(math.acos)]
+/// Calculate arccosine (returns radians)
+#[loft_builtin(math.acos(x: num) -> num)]
 fn math_acos(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new("math.acos() requires a number argument"));
@@ -109,8 +109,8 @@ fn math_acos(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     }
 }
 
-/// Arctangent function
-#[loft_builtin(math.atan)]
+/// Calculate arctangent (returns radians)
+#[loft_builtin(math.atan(x: num) -> num)]
 fn math_atan(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new("math.atan() requires a number argument"));
@@ -131,8 +131,8 @@ fn math_atan(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     }
 }
 
-/// Two-argument arctangent function
-#[loft_builtin(math.atan2)]
+/// Calculate the arctangent of y/x, handling quadrants correctly
+#[loft_builtin(math.atan2(y: num, x: num) -> num)]
 fn math_atan2(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.len() < 2 {
         return Err(RuntimeError::new("math.atan2() requires y and x arguments"));
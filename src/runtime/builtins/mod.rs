@@ -1,21 +1,46 @@
 pub mod array;
+pub mod assert;
+pub mod bytes;
 pub mod collections;
+pub mod datetime;
 pub mod encoding;
 #[cfg(not(target_arch = "wasm32"))]
+pub mod env;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod ffi;
+pub mod handles;
+pub mod inspect;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod io;
 pub mod json;
 pub mod math;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod net;
+pub mod number;
 pub mod object;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod os;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod proc;
+pub mod promise;
 pub mod random;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod sqlite;
 pub mod string;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod sync;
 pub mod term;
 pub mod test;
+#[cfg(test)]
+pub(crate) mod testing;
 pub mod time;
+pub mod toml;
 pub mod traits;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod web;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod worker;
+pub mod yaml;
 
 use crate::runtime::builtin_registry::BuiltinRegistration;
 use crate::runtime::value::Value;
@@ -48,6 +73,9 @@ pub fn init_builtins(enabled_features: Option<&[String]>) -> Vec<(String, Value)
         }
     }
 
+    // The one builtin called as a plain function rather than through a struct
+    builtins.push(("exit".to_string(), Value::BuiltinFn(super::exit::exit)));
+
     // Note: Array methods are available directly on array values
     // via method calls (e.g., arr.push(value), arr.length(), etc.)
 
@@ -0,0 +1,380 @@
+//! TCP and UDP sockets exposed as `net.tcp` and `net.udp`.
+//!
+//! A handle stands for a connected stream, a listener or a UDP socket, and
+//! `recv`, `read_line` and `accept` block until data or a peer arrives. Every
+//! connect, listen, bind and send is checked against `--allow-net` using the
+//! `host:port` it targets.
+
+use crate::runtime::builtin::{BuiltinMethod, BuiltinStruct};
+use crate::runtime::builtins::handles::{self, Handles};
+use crate::runtime::permission_context::check_net_permission;
+use crate::runtime::value::Value;
+use crate::runtime::{RuntimeError, RuntimeResult};
+use loft_builtin_macros::loft_builtin;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// An open socket behind a loft handle
+enum Socket {
+    Stream(BufReader<TcpStream>),
+    Listener(TcpListener),
+    Udp(UdpSocket),
+}
+
+// Each socket has its own lock so a blocking accept or read only holds that socket
+static SOCKETS: Handles<Arc<Mutex<Socket>>> = Handles::new();
+
+/// Store a socket and build the handle for it
+fn register(socket: Socket, mut handle: BuiltinStruct) -> Value {
+    let id = SOCKETS.insert(Arc::new(Mutex::new(socket)));
+    handle.add_field("id", Value::Number(Decimal::from(id)));
+    Value::Builtin(handle)
+}
+
+/// Id of the socket a handle refers to
+fn handle_id(this: &Value, method: &str) -> RuntimeResult<u64> {
+    handles::handle_id(
+        this,
+        &["TcpListener", "TcpStream", "UdpSocket"],
+        "id",
+        method,
+        "a socket",
+    )
+}
+
+/// Run `f` on the socket behind a handle
+fn with_socket<T>(
+    this: &Value,
+    method: &str,
+    f: impl FnOnce(&mut Socket) -> std::io::Result<T>,
+) -> RuntimeResult<T> {
+    let socket = SOCKETS
+        .get(handle_id(this, method)?)
+        .ok_or_else(|| RuntimeError::new(format!("{}() called on a closed socket", method)))?;
+    let mut socket = socket.lock().unwrap();
+    f(&mut socket).map_err(|e| RuntimeError::new(format!("{}() failed: {}", method, e)))
+}
+
+fn wrong_kind(method: &str) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        format!("{}() is not supported on this socket", method),
+    )
+}
+
+/// Parse a `(host, port)` argument pair starting at `index`
+fn address_args(args: &[Value], index: usize, method: &str) -> RuntimeResult<String> {
+    match (args.get(index), args.get(index + 1)) {
+        (Some(Value::String(host)), Some(Value::Number(port))) => {
+            let port = port
+                .to_u16()
+                .ok_or_else(|| RuntimeError::new(format!("{}(): invalid port {}", method, port)))?;
+            Ok(format!("{}:{}", host, port))
+        }
+        _ => Err(RuntimeError::new(format!(
+            "{}() requires a host and a port",
+            method
+        ))),
+    }
+}
+
+fn addr_string(addr: std::io::Result<SocketAddr>) -> Value {
+    addr.map(|a| Value::String(a.to_string().into()))
+        .unwrap_or(Value::Unit)
+}
+
+/// Open a TCP connection to host:port
+#[loft_builtin(Tcp.connect(host: str, port: num) -> TcpStream)]
+fn tcp_connect(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let address = address_args(args, 0, "tcp.connect")?;
+    check_net_permission(&address, Some("net.tcp.connect()")).map_err(RuntimeError::new)?;
+
+    let stream = TcpStream::connect(&address)
+        .map_err(|e| RuntimeError::new(format!("Failed to connect to {}: {}", address, e)))?;
+    Ok(stream_handle(stream))
+}
+
+/// Listen for TCP connections on host:port; port 0 picks a free port
+#[loft_builtin(Tcp.listen(host: str, port: num) -> TcpListener)]
+fn tcp_listen(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let address = address_args(args, 0, "tcp.listen")?;
+    check_net_permission(&address, Some("net.tcp.listen()")).map_err(RuntimeError::new)?;
+
+    let listener = TcpListener::bind(&address)
+        .map_err(|e| RuntimeError::new(format!("Failed to listen on {}: {}", address, e)))?;
+
+    let mut handle = BuiltinStruct::new("TcpListener");
+    handle.add_field("local", addr_string(listener.local_addr()));
+    handle.add_method("accept", listener_accept as BuiltinMethod);
+    handle.add_method("close", socket_close as BuiltinMethod);
+    Ok(register(Socket::Listener(listener), handle))
+}
+
+fn stream_handle(stream: TcpStream) -> Value {
+    let mut handle = BuiltinStruct::new("TcpStream");
+    handle.add_field("local", addr_string(stream.local_addr()));
+    handle.add_field("peer", addr_string(stream.peer_addr()));
+    handle.add_method("read", stream_read as BuiltinMethod);
+    handle.add_method("read_line", stream_read_line as BuiltinMethod);
+    handle.add_method("write", stream_write as BuiltinMethod);
+    handle.add_method("set_timeout", stream_set_timeout as BuiltinMethod);
+    handle.add_method("close", socket_close as BuiltinMethod);
+    register(Socket::Stream(BufReader::new(stream)), handle)
+}
+
+/// Wait for the next incoming connection
+#[loft_builtin(TcpListener.accept() -> TcpStream)]
+fn listener_accept(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let stream = with_socket(this, "accept", |socket| match socket {
+        Socket::Listener(listener) => listener.accept().map(|(stream, _)| stream),
+        _ => Err(wrong_kind("accept")),
+    })?;
+    Ok(stream_handle(stream))
+}
+
+/// Read up to size bytes as they arrive; an empty string means the peer closed
+/// the connection
+#[loft_builtin(TcpStream.read(size: num) -> str)]
+fn stream_read(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let size = match args.first() {
+        Some(Value::Number(n)) => n.to_usize().filter(|n| *n > 0),
+        None => Some(8192),
+        _ => None,
+    }
+    .ok_or_else(|| RuntimeError::new("read() requires a positive size"))?;
+
+    with_socket(this, "read", |socket| match socket {
+        Socket::Stream(stream) => {
+            let mut buffer = vec![0; size];
+            let read = stream.read(&mut buffer)?;
+            Ok(Value::String(
+                String::from_utf8_lossy(&buffer[..read]).into(),
+            ))
+        }
+        _ => Err(wrong_kind("read")),
+    })
+}
+
+/// Read up to and including the next newline
+#[loft_builtin(TcpStream.read_line() -> str)]
+fn stream_read_line(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    with_socket(this, "read_line", |socket| match socket {
+        Socket::Stream(stream) => {
+            let mut bytes = Vec::new();
+            stream.read_until(b'\n', &mut bytes)?;
+            Ok(Value::String(String::from_utf8_lossy(&bytes).into()))
+        }
+        _ => Err(wrong_kind("read_line")),
+    })
+}
+
+/// Send a string and return the number of bytes written
+#[loft_builtin(TcpStream.write(data: str) -> num)]
+fn stream_write(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let data = match args.first() {
+        Some(Value::String(data)) => data.clone(),
+        _ => return Err(RuntimeError::new("write() requires a string argument")),
+    };
+
+    with_socket(this, "write", |socket| match socket {
+        Socket::Stream(stream) => {
+            stream.get_mut().write_all(data.as_bytes())?;
+            Ok(Value::Number(Decimal::from(data.len())))
+        }
+        _ => Err(wrong_kind("write")),
+    })
+}
+
+/// Limit how long reads and writes may block; 0 removes the limit
+#[loft_builtin(TcpStream.set_timeout(ms: num))]
+fn stream_set_timeout(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let timeout = match args.first() {
+        Some(Value::Number(ms)) => ms
+            .to_u64()
+            .map(|ms| (ms > 0).then(|| Duration::from_millis(ms))),
+        _ => None,
+    }
+    .ok_or_else(|| RuntimeError::new("set_timeout() requires a duration in milliseconds"))?;
+
+    with_socket(this, "set_timeout", |socket| match socket {
+        Socket::Stream(stream) => {
+            stream.get_ref().set_read_timeout(timeout)?;
+            stream.get_ref().set_write_timeout(timeout)?;
+            Ok(Value::Unit)
+        }
+        _ => Err(wrong_kind("set_timeout")),
+    })
+}
+
+/// Bind a UDP socket to host:port; port 0 picks a free port
+#[loft_builtin(Udp.bind(host: str, port: num) -> UdpSocket)]
+fn udp_bind(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let address = address_args(args, 0, "udp.bind")?;
+    check_net_permission(&address, Some("net.udp.bind()")).map_err(RuntimeError::new)?;
+
+    let socket = UdpSocket::bind(&address)
+        .map_err(|e| RuntimeError::new(format!("Failed to bind {}: {}", address, e)))?;
+
+    let mut handle = BuiltinStruct::new("UdpSocket");
+    handle.add_field("local", addr_string(socket.local_addr()));
+    handle.add_method("send", udp_send as BuiltinMethod);
+    handle.add_method("recv", udp_recv as BuiltinMethod);
+    handle.add_method("close", socket_close as BuiltinMethod);
+    Ok(register(Socket::Udp(socket), handle))
+}
+
+/// Send a datagram to host:port and return the number of bytes sent
+#[loft_builtin(UdpSocket.send(data: str, host: str, port: num) -> num)]
+fn udp_send(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let data = match args.first() {
+        Some(Value::String(data)) => data.clone(),
+        _ => return Err(RuntimeError::new("send() requires a string argument")),
+    };
+    let address = address_args(args, 1, "send")?;
+    check_net_permission(&address, Some("UdpSocket.send()")).map_err(RuntimeError::new)?;
+
+    with_socket(this, "send", |socket| match socket {
+        Socket::Udp(udp) => {
+            let sent = udp.send_to(data.as_bytes(), &address)?;
+            Ok(Value::Number(Decimal::from(sent)))
+        }
+        _ => Err(wrong_kind("send")),
+    })
+}
+
+/// Wait for a datagram of up to size bytes
+#[loft_builtin(UdpSocket.recv(size: num) -> Datagram)]
+fn udp_recv(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let size = match args.first() {
+        Some(Value::Number(n)) => n.to_usize().filter(|n| *n > 0),
+        None => Some(65507),
+        _ => None,
+    }
+    .ok_or_else(|| RuntimeError::new("recv() requires a positive size"))?;
+
+    with_socket(this, "recv", |socket| match socket {
+        Socket::Udp(udp) => {
+            let mut buffer = vec![0; size];
+            let (read, from) = udp.recv_from(&mut buffer)?;
+
+            let mut fields = HashMap::new();
+            fields.insert(
+                "data".to_string(),
+                Value::String(String::from_utf8_lossy(&buffer[..read]).into()),
+            );
+            fields.insert(
+                "host".to_string(),
+                Value::String(from.ip().to_string().into()),
+            );
+            fields.insert(
+                "port".to_string(),
+                Value::Number(Decimal::from(from.port())),
+            );
+            Ok(Value::Struct {
+                name: "Datagram".to_string(),
+                fields,
+            })
+        }
+        _ => Err(wrong_kind("recv")),
+    })
+}
+
+/// Close a socket; later calls on the handle fail
+#[loft_builtin(socket.close)]
+fn socket_close(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let socket = SOCKETS
+        .remove(handle_id(this, "close")?)
+        .ok_or_else(|| RuntimeError::new("close() called on a closed socket"))?;
+
+    if let Socket::Stream(stream) = &*socket.lock().unwrap() {
+        // Other handles may still hold the stream, so shut it down explicitly
+        let _ = stream.get_ref().shutdown(Shutdown::Both);
+    }
+    Ok(Value::Unit)
+}
+
+/// Create the net builtin with its `tcp` and `udp` namespaces
+pub fn create_net_builtin() -> BuiltinStruct {
+    let mut tcp = BuiltinStruct::new("tcp");
+    tcp.add_method("connect", tcp_connect as BuiltinMethod);
+    tcp.add_method("listen", tcp_listen as BuiltinMethod);
+
+    let mut udp = BuiltinStruct::new("udp");
+    udp.add_method("bind", udp_bind as BuiltinMethod);
+
+    let mut net = BuiltinStruct::new("net");
+    net.add_field("tcp", Value::Builtin(tcp));
+    net.add_field("udp", Value::Builtin(udp));
+    net
+}
+
+// Register the builtin automatically
+crate::submit_builtin!("net", create_net_builtin);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::builtins::testing::{call, number, string};
+
+    fn local_port(handle: &Value) -> i64 {
+        match handle {
+            Value::Builtin(b) => match &b.fields["local"] {
+                Value::String(addr) => addr.rsplit(':').next().unwrap().parse().unwrap(),
+                _ => panic!("missing local address"),
+            },
+            _ => panic!("expected a socket handle"),
+        }
+    }
+
+    #[test]
+    fn test_tcp_echo() {
+        let listener = tcp_listen(&Value::Unit, &[string("127.0.0.1"), number(0)]).unwrap();
+        let port = local_port(&listener);
+
+        let client = tcp_connect(&Value::Unit, &[string("127.0.0.1"), number(port)]).unwrap();
+        let server = call(&listener, "accept", &[]).unwrap();
+
+        call(&client, "write", &[string("ping\nrest")]).unwrap();
+        assert_eq!(call(&server, "read_line", &[]).unwrap(), string("ping\n"));
+        assert_eq!(call(&server, "read", &[number(4)]).unwrap(), string("rest"));
+
+        call(&server, "write", &[string("pong")]).unwrap();
+        call(&server, "close", &[]).unwrap();
+        assert_eq!(call(&client, "read", &[]).unwrap(), string("pong"));
+        assert_eq!(call(&client, "read", &[]).unwrap(), string(""));
+
+        call(&client, "close", &[]).unwrap();
+        call(&listener, "close", &[]).unwrap();
+        assert!(call(&client, "write", &[string("late")]).is_err());
+    }
+
+    #[test]
+    fn test_udp_roundtrip() {
+        let a = udp_bind(&Value::Unit, &[string("127.0.0.1"), number(0)]).unwrap();
+        let b = udp_bind(&Value::Unit, &[string("127.0.0.1"), number(0)]).unwrap();
+
+        let sent = call(
+            &a,
+            "send",
+            &[string("hello"), string("127.0.0.1"), number(local_port(&b))],
+        )
+        .unwrap();
+        assert_eq!(sent, number(5));
+
+        match call(&b, "recv", &[]).unwrap() {
+            Value::Struct { name, fields } => {
+                assert_eq!(name, "Datagram");
+                assert_eq!(fields["data"], string("hello"));
+                assert_eq!(fields["port"], number(local_port(&a)));
+            }
+            other => panic!("expected a datagram, got {:?}", other),
+        }
+
+        assert!(udp_bind(&Value::Unit, &[string("127.0.0.1"), number(70000)]).is_err());
+    }
+}
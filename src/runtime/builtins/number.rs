@@ -0,0 +1,100 @@
+use crate::runtime::builtin::{BuiltinMethod, BuiltinStruct};
+use crate::runtime::value::Value;
+use crate::runtime::{RuntimeError, RuntimeResult};
+use loft_builtin_macros::loft_builtin;
+use rust_decimal::Decimal;
+
+/// The number a method works on and its remaining arguments. Methods can be
+/// called on a number, `x.is_int()`, or through the builtin, `num.is_int(x)`.
+fn receiver<'a>(
+    this: &Value,
+    args: &'a [Value],
+    method: &str,
+) -> RuntimeResult<(Decimal, &'a [Value])> {
+    match (this, args) {
+        (Value::Number(n), _) => Ok((*n, args)),
+        (_, [Value::Number(n), rest @ ..]) => Ok((*n, rest)),
+        _ => Err(RuntimeError::new(format!(
+            "num.{}() expects a number",
+            method
+        ))),
+    }
+}
+
+/// A number argument after the receiver
+fn number_arg(args: &[Value], method: &str) -> RuntimeResult<Decimal> {
+    match args.first() {
+        Some(Value::Number(n)) => Ok(*n),
+        _ => Err(RuntimeError::new(format!(
+            "num.{}() expects a number argument",
+            method
+        ))),
+    }
+}
+
+/// Whether the number has no fractional part
+#[loft_builtin(num.is_int() -> bool)]
+fn num_is_int(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let (n, _) = receiver(this, args, "is_int")?;
+    Ok(Value::Boolean(n.fract().is_zero()))
+}
+
+/// The integer part of the number, dropping any fraction
+#[loft_builtin(num.trunc() -> num)]
+fn num_trunc(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let (n, _) = receiver(this, args, "trunc")?;
+    Ok(Value::Number(n.trunc()))
+}
+
+/// Integer division rounding toward zero, so that a == b * a.div_int(b) + a % b
+#[loft_builtin(num.div_int(divisor: num) -> num)]
+fn num_div_int(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let (n, rest) = receiver(this, args, "div_int")?;
+    let divisor = number_arg(rest, "div_int")?;
+    if divisor.is_zero() {
+        return Err(RuntimeError::new("Division by zero"));
+    }
+    n.checked_div(divisor)
+        .map(|quotient| Value::Number(quotient.trunc()))
+        .ok_or_else(|| RuntimeError::new("Number overflow in division"))
+}
+
+pub fn create_number_builtin() -> BuiltinStruct {
+    let mut num = BuiltinStruct::new("num");
+
+    // These can be called on number values or as num.method(x)
+    num.add_method("is_int", num_is_int as BuiltinMethod);
+    num.add_method("trunc", num_trunc as BuiltinMethod);
+    num.add_method("div_int", num_div_int as BuiltinMethod);
+
+    num
+}
+
+crate::submit_builtin!("num", create_number_builtin);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::builtins::testing::number;
+
+    #[test]
+    fn test_number_methods() {
+        let half = Value::Number(Decimal::new(5, 1));
+        assert_eq!(num_is_int(&number(3), &[]).unwrap(), Value::Boolean(true));
+        assert_eq!(
+            num_is_int(&Value::Unit, &[half]).unwrap(),
+            Value::Boolean(false)
+        );
+
+        assert_eq!(num_div_int(&number(7), &[number(2)]).unwrap(), number(3));
+        assert_eq!(num_div_int(&number(-7), &[number(2)]).unwrap(), number(-3));
+        assert_eq!(
+            num_div_int(&number(1), &[number(0)]).unwrap_err().message,
+            "Division by zero"
+        );
+        assert_eq!(
+            num_trunc(&Value::Number(Decimal::new(-25, 1)), &[]).unwrap(),
+            number(-2)
+        );
+    }
+}
@@ -6,11 +6,14 @@ use rust_decimal::Decimal;
 use std::collections::HashMap;
 
 /// Get all keys from an object
-#[loft_builtin(object.keys)]
+#[loft_builtin(object.keys(object: Object) -> Array<str>)]
 fn object_keys(#[required] _this: &Value, #[types(object)] args: &[Value]) -> RuntimeResult<Value> {
     match &args[0] {
         Value::Struct { fields, .. } => {
-            let keys: Vec<Value> = fields.keys().map(|k| Value::String(k.clone())).collect();
+            let keys: Vec<Value> = fields
+                .keys()
+                .map(|k| Value::String(k.as_str().into()))
+                .collect();
             Ok(Value::Array(keys))
         }
         _ => unreachable!(),
@@ -18,7 +21,7 @@ fn object_keys(#[required] _this: &Value, #[types(object)] args: &[Value]) -> Ru
 }
 
 /// Get all values from an object
-#[loft_builtin(object.values)]
+#[loft_builtin(object.values(object: Object) -> Array<any>)]
 fn object_values(#[required] _this: &Value, #[types(object)] args: &[Value]) -> RuntimeResult<Value> {
     match &args[0] {
         Value::Struct { fields, .. } => {
@@ -30,13 +33,13 @@ fn object_values(#[required] _this: &Value, #[types(object)] args: &[Value]) ->
 }
 
 /// Get all entries from an object as [key, value] pairs
-#[loft_builtin(object.entries)]
+#[loft_builtin(object.entries(object: Object) -> Array<Array>)]
 fn object_entries(#[required] _this: &Value, #[types(object)] args: &[Value]) -> RuntimeResult<Value> {
     match &args[0] {
         Value::Struct { fields, .. } => {
             let entries: Vec<Value> = fields
                 .iter()
-                .map(|(k, v)| Value::Array(vec![Value::String(k.clone()), v.clone()]))
+                .map(|(k, v)| Value::Array(vec![Value::String(k.as_str().into()), v.clone()]))
                 .collect();
             Ok(Value::Array(entries))
         }
@@ -45,18 +48,18 @@ fn object_entries(#[required] _this: &Value, #[types(object)] args: &[Value]) ->
 }
 
 /// Check if object has a property
-#[loft_builtin(object.has)]
+#[loft_builtin(object.has(object: Object, key: str) -> bool)]
 fn object_has(#[required] _this: &Value, #[types(object, string)] args: &[Value]) -> RuntimeResult<Value> {
     match (&args[0], &args[1]) {
         (Value::Struct { fields, .. }, Value::String(key)) => {
-            Ok(Value::Boolean(fields.contains_key(key)))
+            Ok(Value::Boolean(fields.contains_key(&**key)))
         }
         _ => unreachable!(),
     }
 }
 
 /// Assign properties from source objects to target object
-#[loft_builtin(object.assign)]
+#[loft_builtin(object.assign(target: Object, ...sources: Object) -> Object)]
 #[required]
 #[types(type*)]
 fn object_assign(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
@@ -88,7 +91,7 @@ fn object_assign(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
 }
 
 /// Create an object from entries [[key, value], ...]
-#[loft_builtin(object.from_entries)]
+#[loft_builtin(object.from_entries(entries: Array<Array>) -> Object)]
 #[required]
 #[types(array)]
 fn object_from_entries(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
@@ -100,7 +103,7 @@ fn object_from_entries(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
                 match entry {
                     Value::Array(pair) if pair.len() >= 2 => {
                         if let Value::String(key) = &pair[0] {
-                            fields.insert(key.clone(), pair[1].clone());
+                            fields.insert(key.to_string(), pair[1].clone());
                         } else {
                             return Err(RuntimeError::new("Entry key must be a string"));
                         }
@@ -119,7 +122,7 @@ fn object_from_entries(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
 }
 
 /// Get the number of properties in an object
-#[loft_builtin(object.size)]
+#[loft_builtin(object.size(object: Object) -> num)]
 #[required]
 #[types(object)]
 fn object_size(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
@@ -129,6 +132,34 @@ fn object_size(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     }
 }
 
+/// Whether two values are the same, compared field by field without calling any
+/// `eq` method they implement
+#[loft_builtin(object.is(a: any, b: any) -> bool)]
+fn object_is(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    match args {
+        [a, b] => Ok(Value::Boolean(a == b)),
+        _ => Err(RuntimeError::new("object.is() expects two arguments")),
+    }
+}
+
+/// Copy a value. Values are never shared, so this is the same as
+/// `value.clone()`
+#[loft_builtin(object.clone(value: any) -> any)]
+fn object_clone(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    match args {
+        [value] => Ok(value.clone()),
+        _ => Err(RuntimeError::new("object.clone() expects one argument")),
+    }
+}
+
+/// `value.clone()`, which every value has unless its type defines its own
+pub fn clone_method(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    if !args.is_empty() {
+        return Err(RuntimeError::new("clone() takes no arguments"));
+    }
+    Ok(this.clone())
+}
+
 pub fn create_object_builtin() -> BuiltinStruct {
     let mut object = BuiltinStruct::new("object");
 
@@ -139,6 +170,8 @@ pub fn create_object_builtin() -> BuiltinStruct {
     object.add_method("assign", object_assign as BuiltinMethod);
     object.add_method("from_entries", object_from_entries as BuiltinMethod);
     object.add_method("size", object_size as BuiltinMethod);
+    object.add_method("is", object_is as BuiltinMethod);
+    object.add_method("clone", object_clone as BuiltinMethod);
 
     object
 }
@@ -0,0 +1,270 @@
+//! Operating system hooks, starting with signal handlers from `os.on_signal()`.
+//!
+//! Registering the first handler for a signal replaces its default action,
+//! which would stop the program on the spot. When the signal arrives, the
+//! handlers run in the order they were registered and the program then exits
+//! with the usual code for that signal, or the one a handler passes to
+//! `exit()`. Sending the signal a second time while the handlers run exits
+//! right away.
+
+use crate::runtime::builtin::{BuiltinMethod, BuiltinStruct};
+use crate::runtime::limits;
+use crate::runtime::permission_context;
+use crate::runtime::value::Value;
+use crate::runtime::{RuntimeError, RuntimeResult};
+use loft_builtin_macros::loft_builtin;
+use std::sync::Mutex;
+
+type Handler = Box<dyn FnMut() -> RuntimeResult<Value> + Send>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    Interrupt,
+    #[cfg(unix)]
+    Terminate,
+    #[cfg(unix)]
+    Hangup,
+}
+
+impl Signal {
+    #[cfg(unix)]
+    const ALL: &'static [Signal] = &[Signal::Interrupt, Signal::Terminate, Signal::Hangup];
+    #[cfg(not(unix))]
+    const ALL: &'static [Signal] = &[Signal::Interrupt];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Signal::Interrupt => "SIGINT",
+            #[cfg(unix)]
+            Signal::Terminate => "SIGTERM",
+            #[cfg(unix)]
+            Signal::Hangup => "SIGHUP",
+        }
+    }
+
+    pub fn parse(name: &str) -> RuntimeResult<Signal> {
+        Signal::ALL
+            .iter()
+            .copied()
+            .find(|signal| signal.name() == name)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Signal::ALL.iter().map(|s| s.name()).collect();
+                RuntimeError::new(format!(
+                    "Unknown signal '{}', expected one of {}",
+                    name,
+                    names.join(", ")
+                ))
+            })
+    }
+
+    /// The exit code of a program stopped by this signal, as shells report it
+    fn exit_code(self) -> i32 {
+        let number = match self {
+            Signal::Interrupt => 2,
+            #[cfg(unix)]
+            Signal::Terminate => 15,
+            #[cfg(unix)]
+            Signal::Hangup => 1,
+        };
+        128 + number
+    }
+}
+
+/// Handlers by signal, in the order they were registered
+static HANDLERS: Mutex<Vec<(Signal, Handler)>> = Mutex::new(Vec::new());
+/// Signals that already have a listener thread
+static LISTENING: Mutex<Vec<Signal>> = Mutex::new(Vec::new());
+
+/// Run `handler` when `signal` arrives, before the program exits. It runs with
+/// the permissions and the budget of the thread registering it, whichever
+/// thread that is on
+pub fn on_signal(
+    signal: Signal,
+    mut handler: impl FnMut() -> RuntimeResult<Value> + Send + 'static,
+) -> RuntimeResult<()> {
+    let permissions = permission_context::with_permissions(|pm| pm.clone());
+    let budget = limits::current_budget();
+    let handler = move || {
+        let previous = permission_context::replace_permissions(permissions.clone());
+        let previous_budget = limits::replace_budget(budget.clone());
+        let result = handler();
+        limits::replace_budget(previous_budget);
+        permission_context::replace_permissions(previous);
+        result
+    };
+    HANDLERS.lock().unwrap().push((signal, Box::new(handler)));
+
+    let mut listening = LISTENING.lock().unwrap();
+    if !listening.contains(&signal) {
+        listen(signal)?;
+        listening.push(signal);
+    }
+    Ok(())
+}
+
+/// Run every handler registered for `signal`, reporting their errors
+pub fn run_handlers(signal: Signal) {
+    // Taken out of the list so handlers can register others without waiting on it
+    let handlers: Vec<(Signal, Handler)> = {
+        let mut all = HANDLERS.lock().unwrap();
+        let (handlers, others) = std::mem::take(&mut *all)
+            .into_iter()
+            .partition(|(s, _)| *s == signal);
+        *all = others;
+        handlers
+    };
+    for (_, mut handler) in handlers {
+        if let Err(e) = handler() {
+            if let Some(code) = e.exit_code() {
+                crate::runtime::exit::exit_process(code);
+            }
+            eprintln!("Error in {} handler: {}", signal.name(), e.message);
+        }
+    }
+}
+
+fn exit(signal: Signal) -> ! {
+    crate::runtime::exit::exit_process(signal.exit_code())
+}
+
+/// Take over `signal` on a thread of its own, which runs the handlers when it arrives
+fn listen(signal: Signal) -> RuntimeResult<()> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| RuntimeError::new(format!("Failed to listen for signals: {}", e)))?;
+    // Registering here rather than on the thread means the signal is taken over
+    // by the time `os.on_signal()` returns
+    let mut next = runtime
+        .block_on(async { stream(signal) })
+        .map_err(|e| RuntimeError::new(format!("Failed to listen for {}: {}", signal.name(), e)))?;
+
+    std::thread::Builder::new()
+        .name(format!("loft-{}", signal.name()))
+        .spawn(move || {
+            runtime.block_on(next.recv());
+            std::thread::spawn(move || {
+                run_handlers(signal);
+                exit(signal);
+            });
+            runtime.block_on(next.recv());
+            exit(signal);
+        })
+        .map_err(|e| RuntimeError::new(format!("Failed to listen for signals: {}", e)))?;
+    Ok(())
+}
+
+#[cfg(unix)]
+struct SignalStream(tokio::signal::unix::Signal);
+
+#[cfg(unix)]
+impl SignalStream {
+    async fn recv(&mut self) {
+        self.0.recv().await;
+    }
+}
+
+#[cfg(unix)]
+fn stream(signal: Signal) -> std::io::Result<SignalStream> {
+    use tokio::signal::unix::{signal as unix_signal, SignalKind};
+
+    let kind = match signal {
+        Signal::Interrupt => SignalKind::interrupt(),
+        Signal::Terminate => SignalKind::terminate(),
+        Signal::Hangup => SignalKind::hangup(),
+    };
+    unix_signal(kind).map(SignalStream)
+}
+
+#[cfg(not(unix))]
+struct SignalStream(tokio::signal::windows::CtrlC);
+
+#[cfg(not(unix))]
+impl SignalStream {
+    async fn recv(&mut self) {
+        self.0.recv().await;
+    }
+}
+
+#[cfg(not(unix))]
+fn stream(_signal: Signal) -> std::io::Result<SignalStream> {
+    tokio::signal::windows::ctrl_c().map(SignalStream)
+}
+
+/// Run a function when the program receives a signal such as "SIGINT", and then
+/// exit
+// The handler is registered by the interpreter, which intercepts `os.on_signal`
+#[loft_builtin(os.on_signal(signal: str, handler: fn() -> any))]
+fn os_on_signal(_this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    Err(RuntimeError::new(
+        "os.on_signal() requires a signal name and a function",
+    ))
+}
+
+/// Create the os builtin struct
+pub fn create_os_builtin() -> BuiltinStruct {
+    let mut os = BuiltinStruct::new("os");
+    os.add_method("on_signal", os_on_signal as BuiltinMethod);
+    os
+}
+
+// Register the builtin automatically
+crate::submit_builtin!("os", create_os_builtin);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_signal_names() {
+        assert_eq!(Signal::parse("SIGINT").unwrap(), Signal::Interrupt);
+        assert_eq!(Signal::Interrupt.exit_code(), 130);
+        assert!(Signal::parse("SIGKILL")
+            .unwrap_err()
+            .message
+            .starts_with("Unknown signal 'SIGKILL', expected one of SIGINT"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_handlers_run_in_order() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        for n in 1..=2 {
+            let calls = Arc::clone(&calls);
+            on_signal(Signal::Hangup, move || {
+                calls.lock().unwrap().push(n);
+                Ok(Value::Unit)
+            })
+            .unwrap();
+        }
+        on_signal(Signal::Hangup, || Err(RuntimeError::new("ignored"))).unwrap();
+
+        run_handlers(Signal::Hangup);
+        assert_eq!(*calls.lock().unwrap(), vec![1, 2]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_handlers_keep_permissions() {
+        use crate::runtime::permission_context::{check_read_permission, replace_permissions};
+        use crate::runtime::permissions::PermissionManager;
+
+        let allowed = Arc::new(Mutex::new(None));
+        let previous = replace_permissions(Some(PermissionManager::deny_all()));
+        let seen = Arc::clone(&allowed);
+        let registered = on_signal(Signal::Terminate, move || {
+            let read = check_read_permission("/etc/hostname", Some("fs.read()"));
+            *seen.lock().unwrap() = Some(read.is_ok());
+            Ok(Value::Unit)
+        });
+        replace_permissions(previous);
+        registered.unwrap();
+
+        // Handlers run on another thread, which has no permissions of its own
+        std::thread::spawn(|| run_handlers(Signal::Terminate))
+            .join()
+            .unwrap();
+        assert_eq!(*allowed.lock().unwrap(), Some(false));
+    }
+}
@@ -0,0 +1,306 @@
+//! Child processes started with `proc.spawn()`.
+//!
+//! A `Process` handle exposes the child's pipes as `stdin`, `stdout` and
+//! `stderr` sub-handles. Each pipe has its own lock, so reading stdout does
+//! not block writing to stdin or killing the child.
+
+use crate::runtime::builtin::{BuiltinMethod, BuiltinStruct};
+use crate::runtime::builtins::handles::{self, Handles};
+use crate::runtime::permission_context::check_run_permission;
+use crate::runtime::traits::ToString;
+use crate::runtime::value::Value;
+use crate::runtime::{RuntimeError, RuntimeResult};
+use loft_builtin_macros::loft_builtin;
+use rust_decimal::Decimal;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStderr, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::{Arc, Mutex};
+
+/// A spawned child and the pipes that have not been closed yet
+struct Process {
+    child: Mutex<Child>,
+    stdin: Mutex<Option<ChildStdin>>,
+    stdout: Mutex<Option<BufReader<ChildStdout>>>,
+    stderr: Mutex<Option<BufReader<ChildStderr>>>,
+}
+
+static PROCESSES: Handles<Arc<Process>> = Handles::new();
+
+/// Start a command with piped stdin, stdout and stderr. Requires --allow-run
+#[loft_builtin(proc.spawn(command: str, args: Array<str>) -> Process)]
+fn proc_spawn(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let command = match args.first() {
+        Some(Value::String(command)) => command.clone(),
+        _ => return Err(RuntimeError::new("proc.spawn() requires a command")),
+    };
+    let arguments: Vec<String> = match args.get(1) {
+        Some(Value::Array(items)) => items.iter().map(|item| item.to_string()).collect(),
+        None => Vec::new(),
+        _ => return Err(RuntimeError::new("proc.spawn() arguments must be an array")),
+    };
+
+    check_run_permission(&command, Some("proc.spawn()")).map_err(RuntimeError::new)?;
+
+    let mut child = Command::new(&*command)
+        .args(&arguments)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| RuntimeError::new(format!("Failed to spawn '{}': {}", command, e)))?;
+
+    let pid = child.id();
+    let process = Process {
+        stdin: Mutex::new(child.stdin.take()),
+        stdout: Mutex::new(child.stdout.take().map(BufReader::new)),
+        stderr: Mutex::new(child.stderr.take().map(BufReader::new)),
+        child: Mutex::new(child),
+    };
+    let id = PROCESSES.insert(Arc::new(process));
+
+    let id_value = Value::Number(Decimal::from(id));
+    let mut stdin = BuiltinStruct::new("ProcessStdin");
+    stdin.add_field("id", id_value.clone());
+    stdin.add_method("write", stdin_write as BuiltinMethod);
+    stdin.add_method("close", stdin_close as BuiltinMethod);
+
+    let mut handle = BuiltinStruct::new("Process");
+    handle.add_field("id", id_value.clone());
+    handle.add_field("pid", Value::Number(Decimal::from(pid)));
+    handle.add_field("command", Value::String(command));
+    handle.add_field("stdin", Value::Builtin(stdin));
+    handle.add_field("stdout", output_handle(&id_value, "stdout"));
+    handle.add_field("stderr", output_handle(&id_value, "stderr"));
+    handle.add_method("wait", process_wait as BuiltinMethod);
+    handle.add_method("kill", process_kill as BuiltinMethod);
+    handle.add_method("exit_code", process_exit_code as BuiltinMethod);
+    Ok(Value::Builtin(handle))
+}
+
+fn output_handle(id: &Value, stream: &str) -> Value {
+    let mut output = BuiltinStruct::new("ProcessOutput");
+    output.add_field("id", id.clone());
+    output.add_field("stream", Value::String(stream.into()));
+    output.add_method("read_line", output_read_line as BuiltinMethod);
+    output.add_method("read", output_read as BuiltinMethod);
+    Value::Builtin(output)
+}
+
+/// The process a handle or one of its pipes belongs to
+fn process_for(this: &Value, method: &str) -> RuntimeResult<Arc<Process>> {
+    let id = handles::handle_id(
+        this,
+        &["Process", "ProcessStdin", "ProcessOutput"],
+        "id",
+        method,
+        "a process",
+    )?;
+    PROCESSES
+        .get(id)
+        .ok_or_else(|| RuntimeError::new(format!("{}(): unknown process", method)))
+}
+
+/// Run `f` on the stdout or stderr reader behind a pipe handle
+fn with_output<T>(
+    this: &Value,
+    method: &str,
+    f: impl FnOnce(&mut dyn BufRead) -> std::io::Result<T>,
+) -> RuntimeResult<T> {
+    let process = process_for(this, method)?;
+    let is_stderr = matches!(
+        this,
+        Value::Builtin(handle)
+            if matches!(handle.fields.get("stream"), Some(Value::String(s)) if &**s == "stderr")
+    );
+
+    let result = if is_stderr {
+        match process.stderr.lock().unwrap().as_mut() {
+            Some(reader) => f(reader),
+            None => return Err(RuntimeError::new(format!("{}(): stderr is closed", method))),
+        }
+    } else {
+        match process.stdout.lock().unwrap().as_mut() {
+            Some(reader) => f(reader),
+            None => return Err(RuntimeError::new(format!("{}(): stdout is closed", method))),
+        }
+    };
+    result.map_err(|e| RuntimeError::new(format!("{}() failed: {}", method, e)))
+}
+
+/// Read the next line including its newline, or an empty string once the child
+/// closes the pipe
+#[loft_builtin(ProcessOutput.read_line() -> str)]
+fn output_read_line(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    with_output(this, "read_line", |reader| {
+        let mut bytes = Vec::new();
+        reader.read_until(b'\n', &mut bytes)?;
+        Ok(Value::String(String::from_utf8_lossy(&bytes).into()))
+    })
+}
+
+/// Read everything until the child closes the pipe
+#[loft_builtin(ProcessOutput.read() -> str)]
+fn output_read(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    with_output(this, "read", |reader| {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Ok(Value::String(String::from_utf8_lossy(&bytes).into()))
+    })
+}
+
+/// Write a string to the child and return the number of bytes written
+#[loft_builtin(ProcessStdin.write(data: str) -> num)]
+fn stdin_write(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let data = match args.first() {
+        Some(Value::String(data)) => data.clone(),
+        _ => return Err(RuntimeError::new("write() requires a string argument")),
+    };
+
+    let process = process_for(this, "write")?;
+    let mut stdin = process.stdin.lock().unwrap();
+    let pipe = stdin
+        .as_mut()
+        .ok_or_else(|| RuntimeError::new("write(): stdin is closed"))?;
+    pipe.write_all(data.as_bytes())
+        .and_then(|_| pipe.flush())
+        .map_err(|e| RuntimeError::new(format!("write() failed: {}", e)))?;
+    Ok(Value::Number(Decimal::from(data.len())))
+}
+
+/// Close stdin so the child sees end of input
+#[loft_builtin(ProcessStdin.close())]
+fn stdin_close(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    process_for(this, "close")?.stdin.lock().unwrap().take();
+    Ok(Value::Unit)
+}
+
+fn exit_value(status: std::process::ExitStatus) -> Value {
+    // A child stopped by a signal has no exit code
+    status
+        .code()
+        .map(|code| Value::Number(Decimal::from(code)))
+        .unwrap_or(Value::Unit)
+}
+
+/// Close stdin, wait for the child to exit and return its exit code
+#[loft_builtin(Process.wait() -> num)]
+fn process_wait(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let process = process_for(this, "wait")?;
+    process.stdin.lock().unwrap().take();
+    let status = process
+        .child
+        .lock()
+        .unwrap()
+        .wait()
+        .map_err(|e| RuntimeError::new(format!("wait() failed: {}", e)))?;
+    Ok(exit_value(status))
+}
+
+/// Stop the child immediately
+#[loft_builtin(Process.kill())]
+fn process_kill(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let process = process_for(this, "kill")?;
+    let mut child = process.child.lock().unwrap();
+    // Killing a child that already exited is not an error
+    if child.try_wait().ok().flatten().is_none() {
+        child
+            .kill()
+            .map_err(|e| RuntimeError::new(format!("kill() failed: {}", e)))?;
+    }
+    Ok(Value::Unit)
+}
+
+/// The exit code if the child has exited, without waiting
+#[loft_builtin(Process.exit_code() -> num)]
+fn process_exit_code(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let process = process_for(this, "exit_code")?;
+    let status = process
+        .child
+        .lock()
+        .unwrap()
+        .try_wait()
+        .map_err(|e| RuntimeError::new(format!("exit_code() failed: {}", e)))?;
+    Ok(status.map(exit_value).unwrap_or(Value::Unit))
+}
+
+/// Create the proc builtin struct
+pub fn create_proc_builtin() -> BuiltinStruct {
+    let mut proc = BuiltinStruct::new("proc");
+    proc.add_method("spawn", proc_spawn as BuiltinMethod);
+    proc
+}
+
+// Register the builtin automatically
+crate::submit_builtin!("proc", create_proc_builtin);
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::runtime::builtins::testing::string;
+
+    fn call(handle: &Value, path: &[&str], method: &str, args: &[Value]) -> RuntimeResult<Value> {
+        let mut target = handle.clone();
+        for field in path {
+            target = match target {
+                Value::Builtin(b) => b.fields[*field].clone(),
+                _ => panic!("expected a builtin"),
+            };
+        }
+        match &target {
+            Value::Builtin(b) => b.methods[method](&target, args),
+            _ => panic!("expected a builtin"),
+        }
+    }
+
+    #[test]
+    fn test_spawn_streams_output() {
+        let child = proc_spawn(&Value::Unit, &[string("cat")]).unwrap();
+        call(&child, &["stdin"], "write", &[string("one\ntwo\n")]).unwrap();
+        assert_eq!(
+            call(&child, &["stdout"], "read_line", &[]).unwrap(),
+            string("one\n")
+        );
+
+        call(&child, &["stdin"], "close", &[]).unwrap();
+        assert_eq!(
+            call(&child, &["stdout"], "read", &[]).unwrap(),
+            string("two\n")
+        );
+        assert_eq!(
+            call(&child, &[], "wait", &[]).unwrap(),
+            Value::Number(Decimal::from(0))
+        );
+        assert!(call(&child, &["stdin"], "write", &[string("late")]).is_err());
+    }
+
+    #[test]
+    fn test_exit_code_and_kill() {
+        let child = proc_spawn(
+            &Value::Unit,
+            &[
+                string("sh"),
+                Value::Array(vec![string("-c"), string("echo oops >&2; exit 3")]),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            call(&child, &["stderr"], "read_line", &[]).unwrap(),
+            string("oops\n")
+        );
+        assert_eq!(
+            call(&child, &[], "wait", &[]).unwrap(),
+            Value::Number(Decimal::from(3))
+        );
+
+        let sleeper = proc_spawn(
+            &Value::Unit,
+            &[string("sleep"), Value::Array(vec![string("10")])],
+        )
+        .unwrap();
+        assert_eq!(call(&sleeper, &[], "exit_code", &[]).unwrap(), Value::Unit);
+        call(&sleeper, &[], "kill", &[]).unwrap();
+        assert_eq!(call(&sleeper, &[], "wait", &[]).unwrap(), Value::Unit);
+
+        assert!(proc_spawn(&Value::Unit, &[string("definitely-not-a-command")]).is_err());
+    }
+}
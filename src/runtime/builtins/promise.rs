@@ -0,0 +1,96 @@
+use crate::runtime::builtin::{BuiltinMethod, BuiltinStruct};
+use crate::runtime::scheduler::{self, Task};
+use crate::runtime::value::Value;
+use crate::runtime::{RuntimeError, RuntimeResult};
+use loft_builtin_macros::loft_builtin;
+
+/// Resolve a value that may or may not be a promise
+fn settle(value: Value) -> RuntimeResult<Value> {
+    match value {
+        Value::Promise(inner) => Ok(*inner),
+        Value::Task(task) => settle(task.wait()?),
+        value => Ok(value),
+    }
+}
+
+/// Take the array argument shared by promise.all and promise.race
+fn promise_array(args: &[Value], name: &str) -> RuntimeResult<Vec<Value>> {
+    match args.first() {
+        Some(Value::Array(items)) => Ok(items.clone()),
+        _ => Err(RuntimeError::new(format!(
+            "promise.{}() requires an array of promises",
+            name
+        ))),
+    }
+}
+
+/// Wait for every promise concurrently and resolve to an array of their results
+#[loft_builtin(promise.all(promises: Array<Promise<any>>) -> Promise<Array<any>>)]
+fn promise_all(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let items = promise_array(args, "all")?;
+    for item in &items {
+        if let Value::Task(task) = item {
+            task.start();
+        }
+    }
+
+    Ok(Value::Task(Task::lazy(move || {
+        items
+            .into_iter()
+            .map(settle)
+            .collect::<RuntimeResult<Vec<_>>>()
+            .map(Value::Array)
+    })))
+}
+
+/// Resolve to the result of the first promise that finishes
+#[loft_builtin(promise.race(promises: Array<Promise<any>>) -> Promise<any>)]
+fn promise_race(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let items = promise_array(args, "race")?;
+    if items.is_empty() {
+        return Err(RuntimeError::new(
+            "promise.race() requires at least one promise",
+        ));
+    }
+
+    if let Some(resolved) = items.iter().find(|item| !matches!(item, Value::Task(_))) {
+        return Ok(Value::Promise(Box::new(settle(resolved.clone())?)));
+    }
+
+    let tasks: Vec<Task> = items
+        .into_iter()
+        .filter_map(|item| match item {
+            Value::Task(task) => Some(task),
+            _ => None,
+        })
+        .collect();
+    for task in &tasks {
+        task.start();
+    }
+
+    Ok(Value::Task(Task::lazy(move || {
+        settle(scheduler::race(&tasks)?)
+    })))
+}
+
+/// Wrap a value in an already resolved promise
+#[loft_builtin(promise.resolve(value: any) -> Promise<any>)]
+fn promise_resolve(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    Ok(Value::Promise(Box::new(
+        args.first().cloned().unwrap_or(Value::Unit),
+    )))
+}
+
+/// Create the promise builtin struct
+pub fn create_promise_builtin() -> BuiltinStruct {
+    let mut promise = BuiltinStruct::new("promise");
+
+    promise.add_method("all", promise_all as BuiltinMethod);
+    promise.add_method("race", promise_race as BuiltinMethod);
+    promise.add_method("resolve", promise_resolve as BuiltinMethod);
+
+    promise
+}
+
+// Register the builtin automatically
+crate::submit_builtin!("promise", create_promise_builtin);
@@ -33,7 +33,7 @@ fn next_random() -> u64 {
 }
 
 /// Generate a random number between 0 and 1
-#[loft_builtin(random.random)]
+#[loft_builtin(random.random() -> num)]
 fn random_random(_this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     init_rng();
     let r = next_random();
@@ -44,7 +44,7 @@ fn random_random(_this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
 }
 
 /// Generate a random integer in range [min, max)
-#[loft_builtin(random.range)]
+#[loft_builtin(random.range(min: num, max: num) -> num)]
 fn random_range(#[required] _this: &Value, #[types(number, number)] args: &[Value]) -> RuntimeResult<Value> {
     let min = match &args[0] {
         Value::Number(n) => n
@@ -73,7 +73,7 @@ fn random_range(#[required] _this: &Value, #[types(number, number)] args: &[Valu
 }
 
 /// Pick a random element from an array
-#[loft_builtin(random.choice)]
+#[loft_builtin(random.choice(array: Array) -> any)]
 fn random_choice(#[required] _this: &Value, #[types(array)] args: &[Value]) -> RuntimeResult<Value> {
     match &args[0] {
         Value::Array(arr) => {
@@ -93,7 +93,7 @@ fn random_choice(#[required] _this: &Value, #[types(array)] args: &[Value]) -> R
 }
 
 /// Shuffle an array randomly
-#[loft_builtin(random.shuffle)]
+#[loft_builtin(random.shuffle(array: Array) -> Array)]
 fn random_shuffle(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new(
@@ -122,8 +122,8 @@ fn random_shuffle(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     }
 }
 
-/// Set the random seed
-#[loft_builtin(random.seed)]
+/// Set the random seed for reproducible random numbers
+#[loft_builtin(random.seed(seed: num))]
 fn random_seed(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new(
@@ -198,9 +198,9 @@ mod tests {
     #[test]
     fn test_random_choice() {
         let arr = vec![
-            Value::String("a".to_string()),
-            Value::String("b".to_string()),
-            Value::String("c".to_string()),
+            Value::String("a".into()),
+            Value::String("b".into()),
+            Value::String("c".into()),
         ];
 
         let result = random_choice(&Value::Unit, &[Value::Array(arr.clone())]);
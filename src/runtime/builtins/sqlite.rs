@@ -0,0 +1,681 @@
+//! SQLite databases opened with `sqlite.open()`.
+//!
+//! A `Database` handle owns one connection. A `Statement` only keeps its SQL
+//! and the database it belongs to, and finds its compiled form in the
+//! connection's statement cache each time it runs, so it stops working once
+//! the database is closed.
+//!
+//! Opening a database file asks for read permission on its path, and for
+//! write permission too when it doesn't exist yet; every statement that can
+//! change the database asks for write permission. In-memory databases need neither. Statements that attach
+//! another database file, `ATTACH` and `VACUUM INTO`, ask for read and write
+//! permission on that file, whatever database they run on.
+
+use crate::runtime::builtin::{BuiltinMethod, BuiltinStruct};
+use crate::runtime::builtins::handles::{self, Handles};
+use crate::runtime::permission_context::{check_read_permission, check_write_permission};
+use crate::runtime::value::Value;
+use crate::runtime::{RuntimeError, RuntimeResult};
+use loft_builtin_macros::loft_builtin;
+use rusqlite::hooks::{AuthAction, AuthContext, Authorization};
+use rusqlite::types::{Value as SqlValue, ValueRef};
+use rusqlite::{Batch, Connection, ErrorCode, Statement};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// The path `sqlite.open()` takes for a database that only lives in memory
+const IN_MEMORY: &str = ":memory:";
+
+/// An open connection behind a loft handle
+struct Database {
+    connection: Connection,
+    /// The file, or `None` in memory
+    path: Option<String>,
+}
+
+// A connection can't be used by two threads at once, so tasks sharing a
+// database take turns through its lock
+static DATABASES: Handles<Arc<Mutex<Database>>> = Handles::new();
+
+thread_local! {
+    /// Why `authorize` last refused a statement, reported instead of SQLite's
+    /// "not authorized"
+    static REFUSED: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Let a statement attach a database file only with permission to read and
+/// write it. SQLite asks while preparing the statement, on the thread running
+/// it, so the permissions are that thread's
+fn authorize(context: AuthContext<'_>) -> Authorization {
+    let filename = match context.action {
+        AuthAction::Attach { filename } if !filename.is_empty() && filename != IN_MEMORY => {
+            filename
+        }
+        _ => return Authorization::Allow,
+    };
+    let allowed = if crate::runtime::vfs::is_mounted() {
+        Err("Database files are not available on the in-memory file system".to_string())
+    } else {
+        check_read_permission(filename, Some("ATTACH"))
+            .and_then(|_| check_write_permission(filename, Some("ATTACH")))
+    };
+    match allowed {
+        Ok(_) => Authorization::Allow,
+        Err(message) => {
+            REFUSED.with(|refused| *refused.borrow_mut() = Some(message));
+            Authorization::Deny
+        }
+    }
+}
+
+fn sql_error(method: &str, error: rusqlite::Error) -> RuntimeError {
+    if error.sqlite_error_code() == Some(ErrorCode::AuthorizationForStatementDenied) {
+        if let Some(message) = REFUSED.with(|refused| refused.borrow_mut().take()) {
+            return RuntimeError::new(format!("{}() failed: {}", method, message));
+        }
+    }
+    RuntimeError::new(format!("{}() failed: {}", method, error))
+}
+
+/// Id of the database a `Database` or `Statement` handle refers to
+fn handle_id(this: &Value, method: &str) -> RuntimeResult<u64> {
+    handles::handle_id(
+        this,
+        &["Database", "Statement"],
+        "database",
+        method,
+        "a database",
+    )
+}
+
+/// Run `f` on the database behind a handle
+fn with_database<T>(
+    this: &Value,
+    method: &str,
+    f: impl FnOnce(&Database) -> RuntimeResult<T>,
+) -> RuntimeResult<T> {
+    let database = DATABASES
+        .get(handle_id(this, method)?)
+        .ok_or_else(|| RuntimeError::new(format!("{}() called on a closed database", method)))?;
+    let database = database.lock().unwrap();
+    f(&database)
+}
+
+fn sql_arg<'a>(args: &'a [Value], method: &str) -> RuntimeResult<&'a str> {
+    match args.first() {
+        Some(Value::String(sql)) => Ok(sql),
+        _ => Err(RuntimeError::new(format!(
+            "{}() requires an SQL string",
+            method
+        ))),
+    }
+}
+
+/// Convert a loft value to what SQLite stores
+fn to_sql(value: &Value, method: &str) -> RuntimeResult<SqlValue> {
+    Ok(match value {
+        Value::Unit => SqlValue::Null,
+        Value::Boolean(b) => SqlValue::Integer(i64::from(*b)),
+        Value::Number(n) => match n.to_i64() {
+            Some(i) if n.fract().is_zero() => SqlValue::Integer(i),
+            _ => SqlValue::Real(n.to_f64().unwrap_or_default()),
+        },
+        Value::String(s) => SqlValue::Text(s.to_string()),
+        Value::Bytes(bytes) => SqlValue::Blob(bytes.to_vec()),
+        Value::DateTime(date) => SqlValue::Text(date.to_rfc3339()),
+        other => {
+            return Err(RuntimeError::new(format!(
+                "{}(): {} can't be stored in SQLite",
+                method,
+                other.type_name()
+            )))
+        }
+    })
+}
+
+/// Convert a column of a row to a loft value
+fn from_sql(value: ValueRef, method: &str) -> RuntimeResult<Value> {
+    Ok(match value {
+        ValueRef::Null => Value::Unit,
+        ValueRef::Integer(i) => Value::Number(Decimal::from(i)),
+        ValueRef::Real(f) => Value::Number(Decimal::try_from(f).map_err(|e| {
+            RuntimeError::new(format!("{}(): can't read {} as a number: {}", method, f, e))
+        })?),
+        ValueRef::Text(text) => Value::String(String::from_utf8_lossy(text).into()),
+        ValueRef::Blob(bytes) => Value::Bytes(bytes.into()),
+    })
+}
+
+/// Bind the parameters of a statement: an array for `?` placeholders, or an
+/// object or map for named ones like `:id`, `@id` and `$id`
+fn bind(statement: &mut Statement, params: Option<&Value>, method: &str) -> RuntimeResult<()> {
+    let count = statement.parameter_count();
+    match params {
+        None | Some(Value::Unit) if count == 0 => Ok(()),
+        Some(Value::Array(values)) => {
+            if values.len() != count {
+                return Err(RuntimeError::new(format!(
+                    "{}(): the statement takes {} parameters, got {}",
+                    method,
+                    count,
+                    values.len()
+                )));
+            }
+            for (i, value) in values.iter().enumerate() {
+                statement
+                    .raw_bind_parameter(i + 1, to_sql(value, method)?)
+                    .map_err(|e| sql_error(method, e))?;
+            }
+            Ok(())
+        }
+        Some(named @ (Value::Struct { .. } | Value::Map(_))) => {
+            for i in 1..=count {
+                let name = statement.parameter_name(i).ok_or_else(|| {
+                    RuntimeError::new(format!(
+                        "{}(): parameter {} has no name, pass an array instead",
+                        method, i
+                    ))
+                })?;
+                let key = &name[1..];
+                let value = match named {
+                    Value::Struct { fields, .. } => fields.get(key),
+                    Value::Map(entries) => entries
+                        .iter()
+                        .find(|(k, _)| matches!(k, Value::String(k) if &**k == key))
+                        .map(|(_, v)| v),
+                    _ => None,
+                }
+                .ok_or_else(|| {
+                    RuntimeError::new(format!("{}(): no value for parameter {}", method, name))
+                })?;
+                let value = to_sql(value, method)?;
+                statement
+                    .raw_bind_parameter(i, value)
+                    .map_err(|e| sql_error(method, e))?;
+            }
+            Ok(())
+        }
+        None | Some(Value::Unit) => Err(RuntimeError::new(format!(
+            "{}(): the statement takes {} parameters, got none",
+            method, count
+        ))),
+        Some(other) => Err(RuntimeError::new(format!(
+            "{}(): parameters must be an array or an object, got {}",
+            method,
+            other.type_name()
+        ))),
+    }
+}
+
+/// Ask for write permission before running a statement that can change the database
+fn check_writable(database: &Database, statement: &Statement, method: &str) -> RuntimeResult<()> {
+    match &database.path {
+        Some(path) if !statement.readonly() => {
+            check_write_permission(path, Some(&format!("Database.{}()", method)))
+                .map_err(RuntimeError::new)?;
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Run a statement, returning its rows as objects keyed by column name
+fn run_query(
+    database: &Database,
+    statement: &mut Statement,
+    params: Option<&Value>,
+    method: &str,
+) -> RuntimeResult<Value> {
+    check_writable(database, statement, method)?;
+    bind(statement, params, method)?;
+    let columns: Vec<String> = statement
+        .column_names()
+        .into_iter()
+        .map(String::from)
+        .collect();
+
+    let mut rows = statement.raw_query();
+    let mut records = Vec::new();
+    while let Some(row) = rows.next().map_err(|e| sql_error(method, e))? {
+        let mut fields = HashMap::new();
+        for (i, column) in columns.iter().enumerate() {
+            let value = row.get_ref(i).map_err(|e| sql_error(method, e))?;
+            fields.insert(column.clone(), from_sql(value, method)?);
+        }
+        records.push(Value::Struct {
+            name: "Object".to_string(),
+            fields,
+        });
+    }
+    Ok(Value::Array(records))
+}
+
+/// Run a statement for its effect, returning how many rows it changed
+fn run_exec(
+    database: &Database,
+    statement: &mut Statement,
+    params: Option<&Value>,
+    method: &str,
+) -> RuntimeResult<u64> {
+    check_writable(database, statement, method)?;
+    bind(statement, params, method)?;
+    let before = database.connection.total_changes();
+    let mut rows = statement.raw_query();
+    while rows.next().map_err(|e| sql_error(method, e))?.is_some() {}
+    Ok(database.connection.total_changes() - before)
+}
+
+/// Open the SQLite database at a path, creating the file if it doesn't exist,
+/// or a database in memory for ":memory:"
+#[loft_builtin(sqlite.open(path: str) -> Database)]
+fn sqlite_open(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let path = match args.first() {
+        Some(Value::String(path)) => path.to_string(),
+        _ => return Err(RuntimeError::new("sqlite.open() requires a path")),
+    };
+
+    let connection = if path == IN_MEMORY {
+        Connection::open_in_memory()
+    } else {
+        if crate::runtime::vfs::is_mounted() {
+            return Err(RuntimeError::new(
+                "sqlite.open() is not available on the in-memory file system",
+            ));
+        }
+        check_read_permission(&path, Some("sqlite.open()")).map_err(RuntimeError::new)?;
+        if !std::path::Path::new(&path).exists() {
+            check_write_permission(&path, Some("sqlite.open()")).map_err(RuntimeError::new)?;
+        }
+        Connection::open(&path)
+    }
+    .map_err(|e| RuntimeError::new(format!("Failed to open database '{}': {}", path, e)))?;
+    connection.authorizer(Some(authorize));
+
+    let database = Database {
+        connection,
+        path: (path != IN_MEMORY).then(|| path.clone()),
+    };
+    let id = DATABASES.insert(Arc::new(Mutex::new(database)));
+
+    let mut handle = create_database_builtin();
+    handle.add_field("database", Value::Number(Decimal::from(id)));
+    handle.add_field("path", Value::String(path.into()));
+    Ok(Value::Builtin(handle))
+}
+
+/// Run one or more statements separated by semicolons and return how many
+/// rows they changed. Parameters fill the placeholders of a single statement
+#[loft_builtin(Database.exec(sql: str, params?: Array | Object) -> num)]
+fn database_exec(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let sql = sql_arg(args, "exec")?;
+    with_database(this, "exec", |database| {
+        let mut batch = Batch::new(&database.connection, sql);
+        let mut changed = 0;
+        while let Some(mut statement) = batch.next().map_err(|e| sql_error("exec", e))? {
+            changed += run_exec(database, &mut statement, args.get(1), "exec")?;
+        }
+        Ok(Value::Number(Decimal::from(changed)))
+    })
+}
+
+/// Run a statement and return its rows as objects keyed by column name
+#[loft_builtin(Database.query(sql: str, params?: Array | Object) -> Array<Object>)]
+fn database_query(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let sql = sql_arg(args, "query")?;
+    with_database(this, "query", |database| {
+        let mut statement = database
+            .connection
+            .prepare_cached(sql)
+            .map_err(|e| sql_error("query", e))?;
+        run_query(database, &mut statement, args.get(1), "query")
+    })
+}
+
+/// Compile a statement once to run it many times with different parameters
+#[loft_builtin(Database.prepare(sql: str) -> Statement)]
+fn database_prepare(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let sql = sql_arg(args, "prepare")?;
+    let id = handle_id(this, "prepare")?;
+    with_database(this, "prepare", |database| {
+        // Compiled statements are cached by their SQL, so this one is reused
+        database
+            .connection
+            .prepare_cached(sql)
+            .map_err(|e| sql_error("prepare", e))?;
+        Ok(())
+    })?;
+
+    let mut statement = BuiltinStruct::new("Statement");
+    statement.add_method("query", statement_query as BuiltinMethod);
+    statement.add_method("exec", statement_exec as BuiltinMethod);
+    statement.add_field("database", Value::Number(Decimal::from(id)));
+    statement.add_field("sql", Value::String(sql.into()));
+    Ok(Value::Builtin(statement))
+}
+
+fn statement_sql(this: &Value) -> Option<String> {
+    match this {
+        Value::Builtin(handle) => match handle.fields.get("sql") {
+            Some(Value::String(sql)) => Some(sql.to_string()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Run the statement and return its rows as objects keyed by column name
+#[loft_builtin(Statement.query(params?: Array | Object) -> Array<Object>)]
+fn statement_query(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let sql = statement_sql(this)
+        .ok_or_else(|| RuntimeError::new("query() can only be called on a statement"))?;
+    with_database(this, "query", |database| {
+        let mut statement = database
+            .connection
+            .prepare_cached(&sql)
+            .map_err(|e| sql_error("query", e))?;
+        run_query(database, &mut statement, args.first(), "query")
+    })
+}
+
+/// Run the statement and return how many rows it changed
+#[loft_builtin(Statement.exec(params?: Array | Object) -> num)]
+fn statement_exec(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let sql = statement_sql(this)
+        .ok_or_else(|| RuntimeError::new("exec() can only be called on a statement"))?;
+    with_database(this, "exec", |database| {
+        let mut statement = database
+            .connection
+            .prepare_cached(&sql)
+            .map_err(|e| sql_error("exec", e))?;
+        let changed = run_exec(database, &mut statement, args.first(), "exec")?;
+        Ok(Value::Number(Decimal::from(changed)))
+    })
+}
+
+fn transaction_control(this: &Value, method: &str, sql: &str) -> RuntimeResult<Value> {
+    with_database(this, method, |database| {
+        database
+            .connection
+            .execute_batch(sql)
+            .map_err(|e| sql_error(method, e))?;
+        Ok(Value::Unit)
+    })
+}
+
+/// Start a transaction: changes made until commit() are kept together, or
+/// dropped by rollback()
+#[loft_builtin(Database.begin())]
+fn database_begin(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    transaction_control(this, "begin", "BEGIN")
+}
+
+/// Keep the changes made since begin()
+#[loft_builtin(Database.commit())]
+fn database_commit(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    transaction_control(this, "commit", "COMMIT")
+}
+
+/// Drop the changes made since begin()
+#[loft_builtin(Database.rollback())]
+fn database_rollback(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    transaction_control(this, "rollback", "ROLLBACK")
+}
+
+/// Whether a transaction started by begin() is still open
+#[loft_builtin(Database.in_transaction() -> bool)]
+fn database_in_transaction(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    with_database(this, "in_transaction", |database| {
+        Ok(Value::Boolean(!database.connection.is_autocommit()))
+    })
+}
+
+/// The rowid of the last row inserted
+#[loft_builtin(Database.last_insert_id() -> num)]
+fn database_last_insert_id(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    with_database(this, "last_insert_id", |database| {
+        Ok(Value::Number(Decimal::from(
+            database.connection.last_insert_rowid(),
+        )))
+    })
+}
+
+/// Close the database, rolling back an open transaction; later calls on the
+/// handle and its statements fail
+#[loft_builtin(Database.close())]
+fn database_close(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    DATABASES
+        .remove(handle_id(this, "close")?)
+        .ok_or_else(|| RuntimeError::new("close() called on a closed database"))?;
+    Ok(Value::Unit)
+}
+
+fn create_database_builtin() -> BuiltinStruct {
+    let mut database = BuiltinStruct::new("Database");
+
+    database.add_method("exec", database_exec as BuiltinMethod);
+    database.add_method("query", database_query as BuiltinMethod);
+    database.add_method("prepare", database_prepare as BuiltinMethod);
+    database.add_method("begin", database_begin as BuiltinMethod);
+    database.add_method("commit", database_commit as BuiltinMethod);
+    database.add_method("rollback", database_rollback as BuiltinMethod);
+    database.add_method("in_transaction", database_in_transaction as BuiltinMethod);
+    database.add_method("last_insert_id", database_last_insert_id as BuiltinMethod);
+    database.add_method("close", database_close as BuiltinMethod);
+
+    database
+}
+
+pub fn create_sqlite_builtin() -> BuiltinStruct {
+    let mut sqlite = BuiltinStruct::new("sqlite");
+    sqlite.add_method("open", sqlite_open as BuiltinMethod);
+    sqlite
+}
+
+// Register the builtin automatically
+crate::submit_builtin!("sqlite", create_sqlite_builtin);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::builtins::testing::{call, number, string};
+
+    fn object(fields: &[(&str, Value)]) -> Value {
+        Value::Struct {
+            name: "Object".to_string(),
+            fields: fields
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect(),
+        }
+    }
+
+    fn open_memory() -> Value {
+        let db = sqlite_open(&Value::Unit, &[string(":memory:")]).unwrap();
+        call(
+            &db,
+            "exec",
+            &[string(
+                "CREATE TABLE people (id INTEGER PRIMARY KEY, name TEXT, score REAL);
+                 INSERT INTO people (name, score) VALUES ('ada', 1.5);",
+            )],
+        )
+        .unwrap();
+        db
+    }
+
+    #[test]
+    fn test_exec_and_query() {
+        let db = open_memory();
+        let inserted = call(
+            &db,
+            "exec",
+            &[
+                string("INSERT INTO people (name, score) VALUES (?, ?)"),
+                Value::Array(vec![string("grace"), Value::Unit]),
+            ],
+        )
+        .unwrap();
+        assert_eq!(inserted, number(1));
+        assert_eq!(call(&db, "last_insert_id", &[]).unwrap(), number(2));
+
+        let rows = call(
+            &db,
+            "query",
+            &[
+                string("SELECT name, score FROM people WHERE id >= :id ORDER BY id"),
+                object(&[("id", number(1))]),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            rows,
+            Value::Array(vec![
+                object(&[
+                    ("name", string("ada")),
+                    ("score", Value::Number(Decimal::new(15, 1)))
+                ]),
+                object(&[("name", string("grace")), ("score", Value::Unit)]),
+            ])
+        );
+
+        let error = call(&db, "query", &[string("SELECT ?"), Value::Array(vec![])]).unwrap_err();
+        assert!(error.message.contains("takes 1 parameters"), "{}", error);
+        assert!(call(&db, "query", &[string("SELECT * FROM missing")]).is_err());
+        call(&db, "close", &[]).unwrap();
+    }
+
+    #[test]
+    fn test_prepared_statements_and_transactions() {
+        let db = open_memory();
+        let insert = call(
+            &db,
+            "prepare",
+            &[string("INSERT INTO people (name) VALUES (?)")],
+        )
+        .unwrap();
+
+        call(&db, "begin", &[]).unwrap();
+        assert_eq!(
+            call(&db, "in_transaction", &[]).unwrap(),
+            Value::Boolean(true)
+        );
+        for name in ["a", "b", "c"] {
+            call(&insert, "exec", &[Value::Array(vec![string(name)])]).unwrap();
+        }
+        call(&db, "rollback", &[]).unwrap();
+        assert_eq!(
+            call(&db, "in_transaction", &[]).unwrap(),
+            Value::Boolean(false)
+        );
+
+        call(&db, "begin", &[]).unwrap();
+        call(&insert, "exec", &[Value::Array(vec![string("d")])]).unwrap();
+        call(&db, "commit", &[]).unwrap();
+
+        let count = call(
+            &call(
+                &db,
+                "prepare",
+                &[string("SELECT count(*) AS n FROM people")],
+            )
+            .unwrap(),
+            "query",
+            &[],
+        )
+        .unwrap();
+        assert_eq!(count, Value::Array(vec![object(&[("n", number(2))])]));
+
+        call(&db, "close", &[]).unwrap();
+        assert!(call(&insert, "exec", &[Value::Array(vec![string("e")])]).is_err());
+        assert!(call(&db, "close", &[]).is_err());
+    }
+
+    #[test]
+    fn test_database_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.db").to_string_lossy().to_string();
+
+        let db = sqlite_open(&Value::Unit, &[string(&path)]).unwrap();
+        call(
+            &db,
+            "exec",
+            &[string(
+                "CREATE TABLE t (data BLOB); INSERT INTO t VALUES (x'0102');",
+            )],
+        )
+        .unwrap();
+        call(&db, "close", &[]).unwrap();
+
+        let db = sqlite_open(&Value::Unit, &[string(&path)]).unwrap();
+        assert_eq!(
+            call(&db, "query", &[string("SELECT data FROM t")]).unwrap(),
+            Value::Array(vec![object(&[("data", Value::Bytes(vec![1, 2].into()))])])
+        );
+        call(&db, "close", &[]).unwrap();
+    }
+
+    #[test]
+    fn test_permissions() {
+        use crate::runtime::permission_context::replace_permissions;
+        use crate::runtime::permissions::PermissionManager;
+
+        let previous = replace_permissions(Some(PermissionManager::deny_all()));
+        let denied = sqlite_open(&Value::Unit, &[string("data.db")]);
+        let in_memory = sqlite_open(&Value::Unit, &[string(":memory:")]);
+        replace_permissions(previous);
+
+        assert!(denied.is_err());
+        let db = in_memory.unwrap();
+        call(&db, "exec", &[string("CREATE TABLE t (x)")]).unwrap();
+        call(&db, "close", &[]).unwrap();
+    }
+
+    #[test]
+    fn test_attach_permissions() {
+        use crate::runtime::permission_context::replace_permissions;
+        use crate::runtime::permissions::PermissionManager;
+
+        let dir = tempfile::tempdir().unwrap();
+        let attached = dir.path().join("attached.db");
+        let vacuumed = dir.path().join("vacuumed.db");
+        let db = open_memory();
+
+        let previous = replace_permissions(Some(PermissionManager::deny_all()));
+        let attach = call(
+            &db,
+            "exec",
+            &[string(&format!(
+                "ATTACH DATABASE '{}' AS e",
+                attached.display()
+            ))],
+        );
+        let vacuum = call(
+            &db,
+            "exec",
+            &[string(&format!("VACUUM INTO '{}'", vacuumed.display()))],
+        );
+        let in_memory = call(&db, "exec", &[string("ATTACH DATABASE ':memory:' AS m")]);
+        replace_permissions(previous);
+
+        assert!(attach.unwrap_err().message.contains("Permission denied"));
+        assert!(vacuum.unwrap_err().message.contains("Permission denied"));
+        assert!(!attached.exists() && !vacuumed.exists());
+        in_memory.unwrap();
+
+        // With permission the file is attached as usual
+        call(
+            &db,
+            "exec",
+            &[string(&format!(
+                "ATTACH DATABASE '{}' AS e; CREATE TABLE e.t (x)",
+                attached.display()
+            ))],
+        )
+        .unwrap();
+        assert!(attached.exists());
+        call(&db, "close", &[]).unwrap();
+    }
+}
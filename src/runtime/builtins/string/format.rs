@@ -0,0 +1,364 @@
+//! printf-style formatting for the `fmt` builtin and `str.format()`.
+//!
+//! A placeholder is `%[flags][width][.precision]conversion`:
+//!
+//! - flags: `-` left-aligns, `0` pads numbers with zeros, `+` always shows
+//!   the sign and `,` groups thousands
+//! - conversions: `s` any value, `d` integer, `f` fixed-point number,
+//!   `x`/`X` hexadecimal, `o` octal, `b` binary and `%%` for a literal `%`
+//!
+//! Precision is the number of decimals for `f` (6 if not given) and the
+//! maximum number of characters for `s`.
+
+use crate::runtime::builtin::{BuiltinMethod, BuiltinStruct};
+use crate::runtime::traits::ToString;
+use crate::runtime::value::Value;
+use crate::runtime::{RuntimeError, RuntimeResult};
+use loft_builtin_macros::loft_builtin;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::{Decimal, RoundingStrategy};
+
+/// A parsed `%...` placeholder
+#[derive(Default)]
+struct Spec {
+    left_align: bool,
+    zero_pad: bool,
+    plus_sign: bool,
+    group: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+/// Round to `digits` decimals (halves away from zero) and always show that many
+pub fn to_fixed(n: Decimal, digits: u32) -> String {
+    let mut rounded = n.round_dp_with_strategy(digits, RoundingStrategy::MidpointAwayFromZero);
+    rounded.rescale(digits);
+    rounded.to_string()
+}
+
+/// Insert `separator` between groups of three digits in the integer part
+pub fn group_thousands(number: &str, separator: &str) -> String {
+    let (sign, unsigned) = match number.strip_prefix(['-', '+']) {
+        Some(rest) => (&number[..1], rest),
+        None => ("", number),
+    };
+    let (int_part, fraction) = match unsigned.find('.') {
+        Some(dot) => unsigned.split_at(dot),
+        None => (unsigned, ""),
+    };
+
+    let mut grouped = String::new();
+    for (i, digit) in int_part.chars().enumerate() {
+        if i > 0 && (int_part.len() - i) % 3 == 0 {
+            grouped.push_str(separator);
+        }
+        grouped.push(digit);
+    }
+    format!("{}{}{}", sign, grouped, fraction)
+}
+
+fn number_arg(value: &Value, conversion: char) -> RuntimeResult<Decimal> {
+    match value {
+        Value::Number(n) => Ok(*n),
+        _ => Err(RuntimeError::new(format!(
+            "%{} expects a number, got {}",
+            conversion,
+            value.to_string()
+        ))),
+    }
+}
+
+fn integer_arg(value: &Value, conversion: char) -> RuntimeResult<i128> {
+    let n = number_arg(value, conversion)?;
+    n.round_dp_with_strategy(0, RoundingStrategy::ToZero)
+        .to_i128()
+        .ok_or_else(|| RuntimeError::new(format!("{} is too large for %{}", n, conversion)))
+}
+
+/// Format one argument for a placeholder, before padding to its width
+fn format_arg(spec: &Spec, conversion: char, value: &Value) -> RuntimeResult<String> {
+    let signed = |digits: String, negative: bool| {
+        let digits = if spec.group {
+            group_thousands(&digits, ",")
+        } else {
+            digits
+        };
+        match (negative, spec.plus_sign) {
+            (true, _) => format!("-{}", digits),
+            (false, true) => format!("+{}", digits),
+            (false, false) => digits,
+        }
+    };
+
+    Ok(match conversion {
+        's' => {
+            let text = value.to_string();
+            match spec.precision {
+                Some(max) => text.chars().take(max).collect(),
+                None => text,
+            }
+        }
+        'd' => {
+            let n = integer_arg(value, conversion)?;
+            signed(n.unsigned_abs().to_string(), n < 0)
+        }
+        'f' => {
+            let n = number_arg(value, conversion)?;
+            let fixed = to_fixed(n.abs(), spec.precision.unwrap_or(6) as u32);
+            let negative = n.is_sign_negative() && fixed.chars().any(|c| matches!(c, '1'..='9'));
+            signed(fixed, negative)
+        }
+        'x' | 'X' | 'o' | 'b' => {
+            let n = integer_arg(value, conversion)?;
+            let digits = match conversion {
+                'x' => format!("{:x}", n.unsigned_abs()),
+                'X' => format!("{:X}", n.unsigned_abs()),
+                'o' => format!("{:o}", n.unsigned_abs()),
+                _ => format!("{:b}", n.unsigned_abs()),
+            };
+            match (n < 0, spec.plus_sign) {
+                (true, _) => format!("-{}", digits),
+                (false, true) => format!("+{}", digits),
+                (false, false) => digits,
+            }
+        }
+        other => {
+            return Err(RuntimeError::new(format!(
+                "Unknown format conversion '%{}'",
+                other
+            )))
+        }
+    })
+}
+
+/// Pad a formatted argument to the placeholder's width
+fn pad(spec: &Spec, conversion: char, text: String) -> String {
+    let len = text.chars().count();
+    if len >= spec.width {
+        return text;
+    }
+    let fill = spec.width - len;
+
+    if spec.left_align {
+        format!("{}{}", text, " ".repeat(fill))
+    } else if spec.zero_pad && conversion != 's' {
+        // Zeros go between the sign and the digits
+        let sign_len = usize::from(text.starts_with(['-', '+']));
+        format!(
+            "{}{}{}",
+            &text[..sign_len],
+            "0".repeat(fill),
+            &text[sign_len..]
+        )
+    } else {
+        format!("{}{}", " ".repeat(fill), text)
+    }
+}
+
+/// Substitute `args` into the placeholders of `template`
+pub fn sprintf(template: &str, args: &[Value]) -> RuntimeResult<String> {
+    let mut out = String::new();
+    let mut chars = template.chars().peekable();
+    let mut next_arg = args.iter();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        if chars.peek() == Some(&'%') {
+            chars.next();
+            out.push('%');
+            continue;
+        }
+
+        let mut spec = Spec::default();
+        while let Some(&flag) = chars.peek() {
+            match flag {
+                '-' => spec.left_align = true,
+                '0' => spec.zero_pad = true,
+                '+' => spec.plus_sign = true,
+                ',' => spec.group = true,
+                _ => break,
+            }
+            chars.next();
+        }
+        while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+            spec.width = spec.width * 10 + digit as usize;
+            chars.next();
+        }
+        if chars.peek() == Some(&'.') {
+            chars.next();
+            let mut precision = 0;
+            while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+                precision = precision * 10 + digit as usize;
+                chars.next();
+            }
+            spec.precision = Some(precision);
+        }
+
+        let conversion = chars.next().ok_or_else(|| {
+            RuntimeError::new("Format string ends in the middle of a placeholder")
+        })?;
+        let value = next_arg.next().ok_or_else(|| {
+            RuntimeError::new(format!(
+                "Not enough arguments for format string '{}'",
+                template
+            ))
+        })?;
+        let text = format_arg(&spec, conversion, value)?;
+        out.push_str(&pad(&spec, conversion, text));
+    }
+
+    if next_arg.next().is_some() {
+        return Err(RuntimeError::new(format!(
+            "Too many arguments for format string '{}'",
+            template
+        )));
+    }
+    Ok(out)
+}
+
+/// Format values into a template with printf-style placeholders: %s, %d, %f,
+/// %x, %X, %o, %b and %% for a literal percent sign. Flags go between % and the
+/// width: - left-aligns, 0 pads with zeros, + always shows the sign and ,
+/// groups thousands. Precision sets the decimals for %f (6 if not given) and
+/// the maximum length for %s, e.g. %-8s, %05d, %,.2f
+#[loft_builtin(fmt.sprintf(template: str, ...args: any) -> str)]
+fn fmt_sprintf(
+    #[required] _this: &Value,
+    #[required]
+    #[types(string)]
+    args: &[Value],
+) -> RuntimeResult<Value> {
+    match &args[0] {
+        Value::String(template) => Ok(Value::String(sprintf(template, &args[1..])?.into())),
+        _ => unreachable!(),
+    }
+}
+
+/// Format a number with a fixed number of decimals (0 if not given), rounding
+/// halves away from zero
+#[loft_builtin(fmt.to_fixed(value: num, digits?: num) -> str)]
+fn fmt_to_fixed(
+    #[required] _this: &Value,
+    #[required]
+    #[types(number, number)]
+    args: &[Value],
+) -> RuntimeResult<Value> {
+    let digits = match args.get(1) {
+        Some(Value::Number(digits)) => digits
+            .to_u32()
+            .filter(|d| *d <= 28)
+            .ok_or_else(|| RuntimeError::new("to_fixed() digits must be between 0 and 28"))?,
+        _ => 0,
+    };
+    match &args[0] {
+        Value::Number(n) => Ok(Value::String(to_fixed(*n, digits).into())),
+        _ => unreachable!(),
+    }
+}
+
+/// Format a number with thousands separators ("," if not given), e.g. 1234567.5
+/// as "1,234,567.5"
+#[loft_builtin(fmt.number(value: num, separator?: str) -> str)]
+fn fmt_number(
+    #[required] _this: &Value,
+    #[required]
+    #[types(number, string)]
+    args: &[Value],
+) -> RuntimeResult<Value> {
+    let separator = match args.get(1) {
+        Some(Value::String(separator)) => separator.to_string(),
+        _ => ",".to_string(),
+    };
+    match &args[0] {
+        Value::Number(n) => Ok(Value::String(
+            group_thousands(&n.normalize().to_string(), &separator).into(),
+        )),
+        _ => unreachable!(),
+    }
+}
+
+/// Create the fmt builtin struct
+pub fn create_fmt_builtin() -> BuiltinStruct {
+    let mut fmt = BuiltinStruct::new("fmt");
+
+    fmt.add_method("sprintf", fmt_sprintf as BuiltinMethod);
+    fmt.add_method("to_fixed", fmt_to_fixed as BuiltinMethod);
+    fmt.add_method("number", fmt_number as BuiltinMethod);
+
+    fmt
+}
+
+// Register the builtin automatically
+crate::submit_builtin!("fmt", create_fmt_builtin);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::builtins::testing::string;
+
+    fn num(s: &str) -> Value {
+        Value::Number(s.parse().unwrap())
+    }
+
+    #[test]
+    fn test_sprintf_placeholders() {
+        let cases: &[(&str, Vec<Value>, &str)] = &[
+            (
+                "%s has %d items",
+                vec![string("cart"), num("3")],
+                "cart has 3 items",
+            ),
+            ("%.2f%%", vec![num("12.345")], "12.35%"),
+            ("[%5d|%-5d]", vec![num("42"), num("42")], "[   42|42   ]"),
+            ("%08.3f", vec![num("-3.14159")], "-003.142"),
+            ("%+d %,d", vec![num("7"), num("-1234567")], "+7 -1,234,567"),
+            (
+                "%x %X %o %b",
+                vec![num("255"), num("255"), num("8"), num("5")],
+                "ff FF 10 101",
+            ),
+            (
+                "%.3s|%6s",
+                vec![string("abcdef"), Value::Boolean(true)],
+                "abc|  true",
+            ),
+            ("%.1f", vec![num("-0.01")], "0.0"),
+        ];
+        for (template, args, expected) in cases {
+            assert_eq!(sprintf(template, args).unwrap(), *expected, "{}", template);
+        }
+
+        assert!(sprintf("%d %d", &[num("1")]).is_err());
+        assert!(sprintf("%d", &[num("1"), num("2")]).is_err());
+        assert!(sprintf("%d", &[string("x")]).is_err());
+        assert!(sprintf("%q", &[num("1")]).is_err());
+    }
+
+    #[test]
+    fn test_number_helpers() {
+        assert_eq!(
+            fmt_to_fixed(&Value::Unit, &[num("2.5"), num("0")]).unwrap(),
+            string("3")
+        );
+        assert_eq!(
+            fmt_to_fixed(&Value::Unit, &[num("1.005"), num("2")]).unwrap(),
+            string("1.01")
+        );
+        assert_eq!(
+            fmt_to_fixed(&Value::Unit, &[num("7"), num("3")]).unwrap(),
+            string("7.000")
+        );
+        assert_eq!(
+            fmt_number(&Value::Unit, &[num("-1234567.50")]).unwrap(),
+            string("-1,234,567.5")
+        );
+        assert_eq!(
+            fmt_number(&Value::Unit, &[num("999"), string(".")]).unwrap(),
+            string("999")
+        );
+        assert_eq!(group_thousands("1000000", " "), "1 000 000");
+    }
+}
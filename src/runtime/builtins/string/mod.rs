@@ -2,16 +2,20 @@ use crate::runtime::builtin::{BuiltinMethod, BuiltinStruct};
 use crate::runtime::value::Value;
 use crate::runtime::{RuntimeError, RuntimeResult};
 use loft_builtin_macros::loft_builtin;
+use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::Decimal;
+use unicode_segmentation::UnicodeSegmentation;
+
+pub mod format;
 
 /// Split a string by a delimiter
-#[loft_builtin(string.split)]
+#[loft_builtin(str.split(delimiter: str) -> Array<str>)]
 fn string_split(#[required] this: &Value, #[types(string)] args: &[Value]) -> RuntimeResult<Value> {
     match (this, &args[0]) {
         (Value::String(s), Value::String(delim)) => {
             let parts: Vec<Value> = s
-                .split(delim.as_str())
-                .map(|part| Value::String(part.to_string()))
+                .split(delim.as_ref())
+                .map(|part| Value::String(part.into()))
                 .collect();
             Ok(Value::Array(parts))
         }
@@ -19,15 +23,15 @@ fn string_split(#[required] this: &Value, #[types(string)] args: &[Value]) -> Ru
     }
 }
 
-/// Join an array of strings with a delimiter
-#[loft_builtin(string.join)]
+/// Join an array of values with a delimiter
+#[loft_builtin(string.join(array: Array, delimiter: str) -> str)]
 fn string_join(#[required] _this: &Value, #[types(array, string)] args: &[Value]) -> RuntimeResult<Value> {
     match (&args[0], &args[1]) {
         (Value::Array(arr), Value::String(delim)) => {
             let strings: Result<Vec<String>, RuntimeError> = arr
                 .iter()
                 .map(|v| match v {
-                    Value::String(s) => Ok(s.clone()),
+                    Value::String(s) => Ok(s.to_string()),
                     Value::Number(n) => Ok(n.to_string()),
                     Value::Boolean(b) => Ok(b.to_string()),
                     _ => Err(RuntimeError::new(
@@ -37,48 +41,48 @@ fn string_join(#[required] _this: &Value, #[types(array, string)] args: &[Value]
                 .collect();
 
             let strings = strings?;
-            Ok(Value::String(strings.join(delim)))
+            Ok(Value::String(strings.join(delim).into()))
         }
         _ => unreachable!(),
     }
 }
 
-/// Trim whitespace from both ends of a string
-#[loft_builtin(string.trim)]
+/// Remove whitespace from both ends
+#[loft_builtin(str.trim() -> str)]
 // TODO: Elide with #[required] once 'this' support is verified
 fn string_trim(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     match this {
-        Value::String(s) => Ok(Value::String(s.trim().to_string())),
+        Value::String(s) => Ok(Value::String(s.trim().into())),
         _ => Err(RuntimeError::new("trim() can only be called on strings")),
     }
 }
 
-/// Trim whitespace from the start of a string
-#[loft_builtin(string.trim_start)]
+/// Remove whitespace from the start
+#[loft_builtin(str.trim_start() -> str)]
 // TODO: Elide with #[required] once 'this' support is verified
 fn string_trim_start(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     match this {
-        Value::String(s) => Ok(Value::String(s.trim_start().to_string())),
+        Value::String(s) => Ok(Value::String(s.trim_start().into())),
         _ => Err(RuntimeError::new(
             "trim_start() can only be called on strings",
         )),
     }
 }
 
-/// Trim whitespace from the end of a string
-#[loft_builtin(string.trim_end)]
+/// Remove whitespace from the end
+#[loft_builtin(str.trim_end() -> str)]
 // TODO: Elide with #[required] once 'this' support is verified
 fn string_trim_end(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     match this {
-        Value::String(s) => Ok(Value::String(s.trim_end().to_string())),
+        Value::String(s) => Ok(Value::String(s.trim_end().into())),
         _ => Err(RuntimeError::new(
             "trim_end() can only be called on strings",
         )),
     }
 }
 
-/// Replace all occurrences of a substring with another
-#[loft_builtin(string.replace)]
+/// Replace all occurrences of a pattern
+#[loft_builtin(str.replace(pattern: str, replacement: str) -> str)]
 // TODO: Elide with #[required] and #[types(string, string)]
 fn string_replace(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.len() < 2 {
@@ -89,39 +93,39 @@ fn string_replace(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
 
     match (this, &args[0], &args[1]) {
         (Value::String(s), Value::String(pattern), Value::String(replacement)) => Ok(
-            Value::String(s.replace(pattern.as_str(), replacement.as_str())),
+            Value::String(s.replace(pattern.as_ref(), replacement.as_ref()).into()),
         ),
         (Value::String(_), _, _) => Err(RuntimeError::new("replace() arguments must be strings")),
         _ => Err(RuntimeError::new("replace() can only be called on strings")),
     }
 }
 
-/// Convert string to uppercase
-#[loft_builtin(string.to_upper)]
+/// Convert to uppercase
+#[loft_builtin(str.to_upper() -> str)]
 // TODO: Elide with #[required] once 'this' support is verified
 fn string_to_upper(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     match this {
-        Value::String(s) => Ok(Value::String(s.to_uppercase())),
+        Value::String(s) => Ok(Value::String(s.to_uppercase().into())),
         _ => Err(RuntimeError::new(
             "to_upper() can only be called on strings",
         )),
     }
 }
 
-/// Convert string to lowercase
-#[loft_builtin(string.to_lower)]
+/// Convert to lowercase
+#[loft_builtin(str.to_lower() -> str)]
 // TODO: Elide with #[required] once 'this' support is verified
 fn string_to_lower(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     match this {
-        Value::String(s) => Ok(Value::String(s.to_lowercase())),
+        Value::String(s) => Ok(Value::String(s.to_lowercase().into())),
         _ => Err(RuntimeError::new(
             "to_lower() can only be called on strings",
         )),
     }
 }
 
-/// Check if string starts with a prefix
-#[loft_builtin(string.starts_with)]
+/// Check if string starts with prefix
+#[loft_builtin(str.starts_with(prefix: str) -> bool)]
 // TODO: Elide with #[required] and #[types(string)]
 fn string_starts_with(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
@@ -132,7 +136,7 @@ fn string_starts_with(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
 
     match (this, &args[0]) {
         (Value::String(s), Value::String(prefix)) => {
-            Ok(Value::Boolean(s.starts_with(prefix.as_str())))
+            Ok(Value::Boolean(s.starts_with(prefix.as_ref())))
         }
         (Value::String(_), _) => Err(RuntimeError::new("starts_with() argument must be a string")),
         _ => Err(RuntimeError::new(
@@ -141,8 +145,8 @@ fn string_starts_with(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     }
 }
 
-/// Check if string ends with a suffix
-#[loft_builtin(string.ends_with)]
+/// Check if string ends with suffix
+#[loft_builtin(str.ends_with(suffix: str) -> bool)]
 // TODO: Elide with #[required] and #[types(string)]
 fn string_ends_with(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
@@ -151,7 +155,7 @@ fn string_ends_with(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
 
     match (this, &args[0]) {
         (Value::String(s), Value::String(suffix)) => {
-            Ok(Value::Boolean(s.ends_with(suffix.as_str())))
+            Ok(Value::Boolean(s.ends_with(suffix.as_ref())))
         }
         (Value::String(_), _) => Err(RuntimeError::new("ends_with() argument must be a string")),
         _ => Err(RuntimeError::new(
@@ -161,7 +165,7 @@ fn string_ends_with(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
 }
 
 /// Check if string contains a substring
-#[loft_builtin(string.contains)]
+#[loft_builtin(str.contains(substring: str) -> bool)]
 fn string_contains(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new(
@@ -171,7 +175,7 @@ fn string_contains(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
 
     match (this, &args[0]) {
         (Value::String(s), Value::String(substring)) => {
-            Ok(Value::Boolean(s.contains(substring.as_str())))
+            Ok(Value::Boolean(s.contains(substring.as_ref())))
         }
         (Value::String(_), _) => Err(RuntimeError::new("contains() argument must be a string")),
         _ => Err(RuntimeError::new(
@@ -180,8 +184,8 @@ fn string_contains(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     }
 }
 
-/// Get the length of a string
-#[loft_builtin(string.length)]
+/// Get the length of the string
+#[loft_builtin(str.length() -> num)]
 fn string_length(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     match this {
         Value::String(s) => Ok(Value::Number(Decimal::from(s.len()))),
@@ -189,8 +193,9 @@ fn string_length(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     }
 }
 
-/// Get a substring
-#[loft_builtin(string.substring)]
+/// Get a substring between byte offsets. End is optional. Use slice() to count
+/// characters
+#[loft_builtin(str.substring(start: num, end?: num) -> str)]
 fn string_substring(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new("substring() requires a start index"));
@@ -217,11 +222,10 @@ fn string_substring(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
                 s.len()
             };
 
-            if start > s.len() || end > s.len() || start > end {
-                return Err(RuntimeError::new("Invalid substring indices"));
-            }
-
-            Ok(Value::String(s[start..end].to_string()))
+            // Byte offsets inside a multi-byte character are invalid too
+            s.get(start..end)
+                .map(|sub| Value::String(sub.into()))
+                .ok_or_else(|| RuntimeError::new("Invalid substring indices"))
         }
         _ => Err(RuntimeError::new(
             "substring() can only be called on strings",
@@ -230,7 +234,7 @@ fn string_substring(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
 }
 
 /// Repeat a string n times
-#[loft_builtin(string.repeat)]
+#[loft_builtin(str.repeat(count: num) -> str)]
 fn string_repeat(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new("repeat() requires a count argument"));
@@ -242,15 +246,123 @@ fn string_repeat(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
                 .to_string()
                 .parse::<usize>()
                 .map_err(|_| RuntimeError::new("Count must be a non-negative integer"))?;
-            Ok(Value::String(s.repeat(count)))
+            Ok(Value::String(s.repeat(count).into()))
         }
         (Value::String(_), _) => Err(RuntimeError::new("repeat() argument must be a number")),
         _ => Err(RuntimeError::new("repeat() can only be called on strings")),
     }
 }
 
-/// Pad string to a certain length with spaces on the left
-#[loft_builtin(string.pad_start)]
+/// A character index argument of `method`
+fn char_index(value: &Value, method: &str) -> RuntimeResult<usize> {
+    match value {
+        Value::Number(n) => n.to_usize().filter(|_| n.fract().is_zero()).ok_or_else(|| {
+            RuntimeError::new(format!("{}() index must be a non-negative integer", method))
+        }),
+        _ => Err(RuntimeError::new(format!(
+            "{}() index must be a number",
+            method
+        ))),
+    }
+}
+
+/// Split into user-perceived characters (grapheme clusters), keeping accents
+/// and emoji whole
+#[loft_builtin(str.chars() -> Array<str>)]
+fn string_chars(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    match this {
+        Value::String(s) => Ok(Value::Array(
+            s.graphemes(true)
+                .map(|grapheme| Value::String(grapheme.into()))
+                .collect(),
+        )),
+        _ => Err(RuntimeError::new("chars() can only be called on strings")),
+    }
+}
+
+/// The UTF-8 bytes of the string
+#[loft_builtin(str.bytes() -> Bytes)]
+fn string_bytes(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    match this {
+        Value::String(s) => Ok(Value::Bytes(s.as_bytes().into())),
+        _ => Err(RuntimeError::new("bytes() can only be called on strings")),
+    }
+}
+
+/// Characters from start up to but not including end, counted in code points.
+/// Indices past the end are clamped
+#[loft_builtin(str.slice(start: num, end?: num) -> str)]
+fn string_slice(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let s = match this {
+        Value::String(s) => s,
+        _ => return Err(RuntimeError::new("slice() can only be called on strings")),
+    };
+    let start = match args.first() {
+        Some(start) => char_index(start, "slice")?,
+        None => return Err(RuntimeError::new("slice() requires a start index")),
+    };
+    let end = match args.get(1) {
+        Some(end) => char_index(end, "slice")?,
+        None => usize::MAX,
+    };
+    if start >= end {
+        return Ok(Value::String("".into()));
+    }
+
+    // Byte offset of the code point at each index, or the end of the string
+    let offset = |index: usize| {
+        s.char_indices()
+            .nth(index)
+            .map_or(s.len(), |(offset, _)| offset)
+    };
+    Ok(Value::String(s[offset(start)..offset(end)].into()))
+}
+
+/// The code point index where a substring first occurs, or -1
+#[loft_builtin(str.index_of(substring: str) -> num)]
+fn string_index_of(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    match (this, args.first()) {
+        (Value::String(s), Some(Value::String(needle))) => {
+            let index = s
+                .find(needle.as_ref())
+                .map_or(-1, |offset| s[..offset].chars().count() as i64);
+            Ok(Value::Number(Decimal::from(index)))
+        }
+        (Value::String(_), Some(_)) => {
+            Err(RuntimeError::new("index_of() argument must be a string"))
+        }
+        (Value::String(_), None) => Err(RuntimeError::new(
+            "index_of() requires a substring argument",
+        )),
+        _ => Err(RuntimeError::new(
+            "index_of() can only be called on strings",
+        )),
+    }
+}
+
+/// The Unicode code point at a code point index
+#[loft_builtin(str.code_point_at(index: num) -> num)]
+fn string_code_point_at(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let s = match this {
+        Value::String(s) => s,
+        _ => {
+            return Err(RuntimeError::new(
+                "code_point_at() can only be called on strings",
+            ))
+        }
+    };
+    let index = match args.first() {
+        Some(index) => char_index(index, "code_point_at")?,
+        None => return Err(RuntimeError::new("code_point_at() requires an index")),
+    };
+    s.chars()
+        .nth(index)
+        .map(|c| Value::Number(Decimal::from(c as u32)))
+        .ok_or_else(|| RuntimeError::new(format!("String index {} out of bounds", index)))
+}
+
+/// Pad string to length at start. Char defaults to space
+#[loft_builtin(str.pad_start(length: num, char?: str) -> str)]
 fn string_pad_start(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new("pad_start() requires a length argument"));
@@ -281,7 +393,7 @@ fn string_pad_start(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
                 Ok(Value::String(s.clone()))
             } else {
                 let padding = pad_char.to_string().repeat(target_len - s.len());
-                Ok(Value::String(format!("{}{}", padding, s)))
+                Ok(Value::String(format!("{}{}", padding, s).into()))
             }
         }
         (Value::String(_), _) => Err(RuntimeError::new("pad_start() length must be a number")),
@@ -291,8 +403,8 @@ fn string_pad_start(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     }
 }
 
-/// Pad string to a certain length with spaces on the right
-#[loft_builtin(string.pad_end)]
+/// Pad string to length at end. Char defaults to space
+#[loft_builtin(str.pad_end(length: num, char?: str) -> str)]
 fn string_pad_end(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new("pad_end() requires a length argument"));
@@ -323,7 +435,7 @@ fn string_pad_end(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
                 Ok(Value::String(s.clone()))
             } else {
                 let padding = pad_char.to_string().repeat(target_len - s.len());
-                Ok(Value::String(format!("{}{}", s, padding)))
+                Ok(Value::String(format!("{}{}", s, padding).into()))
             }
         }
         (Value::String(_), _) => Err(RuntimeError::new("pad_end() length must be a number")),
@@ -331,6 +443,16 @@ fn string_pad_end(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     }
 }
 
+/// Substitute arguments into printf-style placeholders in this string, e.g.
+/// "%s: %.2f".format(name, total). See fmt.sprintf for the placeholder syntax
+#[loft_builtin(str.format(...args: any) -> str)]
+fn string_format(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    match this {
+        Value::String(template) => Ok(Value::String(format::sprintf(template, args)?.into())),
+        _ => Err(RuntimeError::new("format() can only be called on strings")),
+    }
+}
+
 pub fn create_string_builtin() -> BuiltinStruct {
     let mut string = BuiltinStruct::new("string");
 
@@ -351,11 +473,71 @@ pub fn create_string_builtin() -> BuiltinStruct {
     string.add_method("len", string_length as BuiltinMethod); // Alias
     string.add_method("substring", string_substring as BuiltinMethod);
     string.add_method("repeat", string_repeat as BuiltinMethod);
+    string.add_method("chars", string_chars as BuiltinMethod);
+    string.add_method("bytes", string_bytes as BuiltinMethod);
+    string.add_method("slice", string_slice as BuiltinMethod);
+    string.add_method("index_of", string_index_of as BuiltinMethod);
+    string.add_method("code_point_at", string_code_point_at as BuiltinMethod);
     string.add_method("pad_start", string_pad_start as BuiltinMethod);
     string.add_method("pad_end", string_pad_end as BuiltinMethod);
+    string.add_method("format", string_format as BuiltinMethod);
 
     string
 }
 
 // Register the builtin automatically
 crate::submit_builtin!("string", create_string_builtin);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::builtins::testing::{number, string};
+
+    #[test]
+    fn test_unicode_methods() {
+        let text = string("cafe\u{301} 🇳🇴!");
+        assert_eq!(
+            string_chars(&text, &[]).unwrap(),
+            Value::Array(vec![
+                string("c"),
+                string("a"),
+                string("f"),
+                string("e\u{301}"),
+                string(" "),
+                string("🇳🇴"),
+                string("!"),
+            ])
+        );
+        assert_eq!(
+            string_bytes(&string("é"), &[]).unwrap(),
+            Value::Bytes(vec![0xc3, 0xa9].into())
+        );
+
+        let text = string("naïve café");
+        assert_eq!(
+            string_slice(&text, &[number(2), number(5)]).unwrap(),
+            string("ïve")
+        );
+        assert_eq!(string_slice(&text, &[number(6)]).unwrap(), string("café"));
+        assert_eq!(
+            string_slice(&text, &[number(8), number(100)]).unwrap(),
+            string("fé")
+        );
+        assert_eq!(
+            string_index_of(&text, &[string("café")]).unwrap(),
+            number(6)
+        );
+        assert_eq!(
+            string_index_of(&text, &[string("tea")]).unwrap(),
+            number(-1)
+        );
+        assert_eq!(
+            string_code_point_at(&text, &[number(2)]).unwrap(),
+            number(0xef)
+        );
+        assert!(string_code_point_at(&text, &[number(10)]).is_err());
+
+        // Byte offsets inside a character are an error rather than a panic
+        assert!(string_substring(&string("ï"), &[number(1)]).is_err());
+    }
+}
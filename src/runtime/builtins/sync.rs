@@ -0,0 +1,353 @@
+//! Channels, mutexes and semaphores for coordinating `async` code.
+//!
+//! Tasks each work on their own copy of the program's values, so anything
+//! they share has to live here. Handles only carry an id, which survives
+//! being copied into a task, and every copy refers to the same primitive.
+//! Waiting operations (`recv` and `acquire`) return promises, which block
+//! whoever awaits them rather than the task that created them.
+
+use crate::runtime::builtin::{BuiltinMethod, BuiltinStruct};
+use crate::runtime::builtins::handles::{self, Handles};
+use crate::runtime::limits;
+use crate::runtime::scheduler::Task;
+use crate::runtime::value::Value;
+use crate::runtime::{RuntimeError, RuntimeResult};
+use loft_builtin_macros::loft_builtin;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::ThreadId;
+
+#[derive(Default)]
+struct ChannelState {
+    queue: VecDeque<Value>,
+    closed: bool,
+}
+
+#[derive(Default)]
+struct Channel {
+    state: Mutex<ChannelState>,
+    ready: Condvar,
+}
+
+/// A value that tasks share, along with the thread currently updating it
+struct SharedValue {
+    value: Mutex<Value>,
+    updating: Mutex<Option<ThreadId>>,
+}
+
+struct Semaphore {
+    permits: Mutex<u64>,
+    released: Condvar,
+}
+
+static CHANNELS: Handles<Arc<Channel>> = Handles::new();
+static MUTEXES: Handles<Arc<SharedValue>> = Handles::new();
+static SEMAPHORES: Handles<Arc<Semaphore>> = Handles::new();
+
+fn handle(name: &str, id: u64, methods: &[(&str, BuiltinMethod)]) -> Value {
+    let mut handle = BuiltinStruct::new(name);
+    handle.add_field("id", Value::Number(Decimal::from(id)));
+    for (method, function) in methods {
+        handle.add_method(*method, *function);
+    }
+    Value::Builtin(handle)
+}
+
+/// The primitive a handle refers to, `name` being its struct, e.g. `Channel`
+fn lookup<T>(
+    registry: &Handles<Arc<T>>,
+    this: &Value,
+    name: &str,
+    method: &str,
+) -> RuntimeResult<Arc<T>> {
+    let kind = format!("a {}", name.to_lowercase());
+    let id = handles::handle_id(this, &[name], "id", method, &kind)?;
+    registry
+        .get(id)
+        .ok_or_else(|| RuntimeError::new(format!("{}() can only be called on {}", method, kind)))
+}
+
+/// Wait for `condvar` to be notified, failing instead once the program
+/// waiting is past its deadline
+fn wait<'a, T>(condvar: &Condvar, guard: MutexGuard<'a, T>) -> RuntimeResult<MutexGuard<'a, T>> {
+    let Some(timeout) = limits::blocking_timeout(None) else {
+        return Ok(condvar.wait(guard).unwrap());
+    };
+    let (guard, waited) = condvar.wait_timeout(guard, timeout).unwrap();
+    if waited.timed_out() {
+        limits::check_deadline().map_err(RuntimeError::new)?;
+    }
+    Ok(guard)
+}
+
+/// Create a channel for passing values between tasks
+#[loft_builtin(sync.channel() -> Channel)]
+fn sync_channel(_this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let id = CHANNELS.insert(Arc::new(Channel::default()));
+    Ok(handle(
+        "Channel",
+        id,
+        &[
+            ("send", channel_send as BuiltinMethod),
+            ("recv", channel_recv as BuiltinMethod),
+            ("try_recv", channel_try_recv as BuiltinMethod),
+            ("close", channel_close as BuiltinMethod),
+        ],
+    ))
+}
+
+/// Queue a value for whoever receives next, returning a resolved promise
+#[loft_builtin(Channel.send(value: any) -> Promise<void>)]
+fn channel_send(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let value = args
+        .first()
+        .cloned()
+        .ok_or_else(|| RuntimeError::new("send() requires a value"))?;
+    let channel = lookup(&CHANNELS, this, "Channel", "send")?;
+    let mut state = channel.state.lock().unwrap();
+    if state.closed {
+        return Err(RuntimeError::new("send(): the channel is closed"));
+    }
+    state.queue.push_back(value);
+    channel.ready.notify_one();
+    Ok(Value::Promise(Box::new(Value::Unit)))
+}
+
+/// A promise of the next value, or of unit once the channel is closed and empty
+#[loft_builtin(Channel.recv() -> Promise<any>)]
+fn channel_recv(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let channel = lookup(&CHANNELS, this, "Channel", "recv")?;
+    Ok(Value::Task(Task::lazy(move || {
+        let mut state = channel.state.lock().unwrap();
+        loop {
+            if let Some(value) = state.queue.pop_front() {
+                return Ok(value);
+            }
+            if state.closed {
+                return Ok(Value::Unit);
+            }
+            state = wait(&channel.ready, state)?;
+        }
+    })))
+}
+
+/// The next value if one is waiting, or unit, without waiting
+#[loft_builtin(Channel.try_recv() -> any)]
+fn channel_try_recv(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let channel = lookup(&CHANNELS, this, "Channel", "try_recv")?;
+    let value = channel.state.lock().unwrap().queue.pop_front();
+    Ok(value.unwrap_or(Value::Unit))
+}
+
+/// Stop accepting values; receivers still get what was sent before
+#[loft_builtin(Channel.close())]
+fn channel_close(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let channel = lookup(&CHANNELS, this, "Channel", "close")?;
+    channel.state.lock().unwrap().closed = true;
+    channel.ready.notify_all();
+    Ok(Value::Unit)
+}
+
+/// Create a mutex holding a value that tasks can share
+#[loft_builtin(sync.mutex(value: any) -> Mutex)]
+fn sync_mutex(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let shared = SharedValue {
+        value: Mutex::new(args.first().cloned().unwrap_or(Value::Unit)),
+        updating: Mutex::new(None),
+    };
+    let id = MUTEXES.insert(Arc::new(shared));
+    Ok(handle(
+        "Mutex",
+        id,
+        &[
+            ("get", mutex_get as BuiltinMethod),
+            ("set", mutex_set as BuiltinMethod),
+            ("update", mutex_update as BuiltinMethod),
+        ],
+    ))
+}
+
+fn shared_value(this: &Value, method: &str) -> RuntimeResult<Arc<SharedValue>> {
+    let shared = lookup(&MUTEXES, this, "Mutex", method)?;
+    // Waiting for the lock this thread holds would never end
+    if *shared.updating.lock().unwrap() == Some(std::thread::current().id()) {
+        return Err(RuntimeError::new(format!(
+            "{}(): the mutex is being updated by this same update()",
+            method
+        )));
+    }
+    Ok(shared)
+}
+
+/// A copy of the value the mutex holds
+#[loft_builtin(Mutex.get() -> any)]
+fn mutex_get(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let shared = shared_value(this, "get")?;
+    let value = shared.value.lock().unwrap().clone();
+    Ok(value)
+}
+
+/// Replace the value the mutex holds
+#[loft_builtin(Mutex.set(value: any))]
+fn mutex_set(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let shared = shared_value(this, "set")?;
+    *shared.value.lock().unwrap() = args.first().cloned().unwrap_or(Value::Unit);
+    Ok(Value::Unit)
+}
+
+/// Replace the value with what a function returns for it, with no other task
+/// touching the mutex in between, and return the new value
+// The call itself is performed by the interpreter, which intercepts `update`
+#[loft_builtin(Mutex.update(f: fn(any) -> any) -> any)]
+fn mutex_update(_this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    Err(RuntimeError::new("update() requires a function"))
+}
+
+/// Hold the mutex behind `this` while `f` computes its new value
+pub fn update(this: &Value, f: impl FnOnce(Value) -> RuntimeResult<Value>) -> RuntimeResult<Value> {
+    let shared = shared_value(this, "update")?;
+    let mut value = shared.value.lock().unwrap();
+    *shared.updating.lock().unwrap() = Some(std::thread::current().id());
+    let result = f(value.clone());
+    *shared.updating.lock().unwrap() = None;
+
+    let result = result?;
+    *value = result.clone();
+    Ok(result)
+}
+
+/// Create a semaphore with a number of permits
+#[loft_builtin(sync.semaphore(permits: num) -> Semaphore)]
+fn sync_semaphore(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let permits = match args.first() {
+        Some(Value::Number(n)) if n.is_integer() => n.to_u64(),
+        _ => None,
+    }
+    .ok_or_else(|| RuntimeError::new("sync.semaphore() requires a whole number of permits"))?;
+
+    let semaphore = Semaphore {
+        permits: Mutex::new(permits),
+        released: Condvar::new(),
+    };
+    let id = SEMAPHORES.insert(Arc::new(semaphore));
+    Ok(handle(
+        "Semaphore",
+        id,
+        &[
+            ("acquire", semaphore_acquire as BuiltinMethod),
+            ("try_acquire", semaphore_try_acquire as BuiltinMethod),
+            ("release", semaphore_release as BuiltinMethod),
+        ],
+    ))
+}
+
+/// A promise that resolves once a permit has been taken
+#[loft_builtin(Semaphore.acquire() -> Promise<void>)]
+fn semaphore_acquire(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let semaphore = lookup(&SEMAPHORES, this, "Semaphore", "acquire")?;
+    Ok(Value::Task(Task::lazy(move || {
+        let mut permits = semaphore.permits.lock().unwrap();
+        while *permits == 0 {
+            permits = wait(&semaphore.released, permits)?;
+        }
+        *permits -= 1;
+        Ok(Value::Unit)
+    })))
+}
+
+/// Take a permit if one is free, without waiting, and return whether it was
+#[loft_builtin(Semaphore.try_acquire() -> bool)]
+fn semaphore_try_acquire(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let semaphore = lookup(&SEMAPHORES, this, "Semaphore", "try_acquire")?;
+    let mut permits = semaphore.permits.lock().unwrap();
+    let acquired = *permits > 0;
+    if acquired {
+        *permits -= 1;
+    }
+    Ok(Value::Boolean(acquired))
+}
+
+/// Give a permit back, letting one waiting task continue
+#[loft_builtin(Semaphore.release())]
+fn semaphore_release(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let semaphore = lookup(&SEMAPHORES, this, "Semaphore", "release")?;
+    *semaphore.permits.lock().unwrap() += 1;
+    semaphore.released.notify_one();
+    Ok(Value::Unit)
+}
+
+/// Create the sync builtin struct
+pub fn create_sync_builtin() -> BuiltinStruct {
+    let mut sync = BuiltinStruct::new("sync");
+    sync.add_method("channel", sync_channel as BuiltinMethod);
+    sync.add_method("mutex", sync_mutex as BuiltinMethod);
+    sync.add_method("semaphore", sync_semaphore as BuiltinMethod);
+    sync
+}
+
+// Register the builtin automatically
+crate::submit_builtin!("sync", create_sync_builtin);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::builtins::testing::{call, number};
+
+    fn wait(promise: Value) -> RuntimeResult<Value> {
+        match promise {
+            Value::Task(task) => task.wait(),
+            other => panic!("expected a promise, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_channel_between_tasks() {
+        let channel = sync_channel(&Value::Unit, &[]).unwrap();
+        let pending = call(&channel, "recv", &[]).unwrap();
+        let sender = channel.clone();
+        let producer = Task::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            call(&sender, "send", &[number(1)])?;
+            call(&sender, "send", &[number(2)])?;
+            call(&sender, "close", &[])
+        });
+
+        assert_eq!(wait(pending).unwrap(), number(1));
+        producer.wait().unwrap();
+        assert_eq!(call(&channel, "try_recv", &[]).unwrap(), number(2));
+        assert_eq!(
+            wait(call(&channel, "recv", &[]).unwrap()).unwrap(),
+            Value::Unit
+        );
+        assert!(call(&channel, "send", &[number(3)]).is_err());
+    }
+
+    #[test]
+    fn test_mutex_and_semaphore() {
+        let mutex = sync_mutex(&Value::Unit, &[number(1)]).unwrap();
+        let updated = update(&mutex, |value| {
+            assert!(call(&mutex, "get", &[]).is_err());
+            Ok(Value::Array(vec![value]))
+        })
+        .unwrap();
+        assert_eq!(updated, Value::Array(vec![number(1)]));
+        assert_eq!(call(&mutex, "get", &[]).unwrap(), updated);
+        assert!(update(&mutex, |_| Err(RuntimeError::new("boom"))).is_err());
+        assert_eq!(call(&mutex, "get", &[]).unwrap(), updated);
+
+        let semaphore = sync_semaphore(&Value::Unit, &[number(1)]).unwrap();
+        wait(call(&semaphore, "acquire", &[]).unwrap()).unwrap();
+        assert_eq!(
+            call(&semaphore, "try_acquire", &[]).unwrap(),
+            Value::Boolean(false)
+        );
+        call(&semaphore, "release", &[]).unwrap();
+        assert_eq!(
+            call(&semaphore, "try_acquire", &[]).unwrap(),
+            Value::Boolean(true)
+        );
+        assert!(sync_semaphore(&Value::Unit, &[number(-1)]).is_err());
+    }
+}
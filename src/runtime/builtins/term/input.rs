@@ -0,0 +1,249 @@
+//! Reading from the terminal for `term.read_line()`, `term.read_key()` and friends
+
+use crate::runtime::value::Value;
+use crate::runtime::{RuntimeError, RuntimeResult};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::terminal;
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// Whether a script has switched the terminal to raw mode with `term.raw_mode()`
+static RAW_MODE: AtomicBool = AtomicBool::new(false);
+
+pub fn flush() {
+    let _ = io::stdout().flush();
+}
+
+pub fn show_prompt(args: &[Value], method: &str) -> RuntimeResult<()> {
+    match args.first() {
+        None => {}
+        Some(Value::String(prompt)) => {
+            super::write(super::Stream::Stdout, prompt);
+            flush();
+        }
+        Some(_) => {
+            return Err(RuntimeError::new(format!(
+                "term.{}() prompt must be a string",
+                method
+            )))
+        }
+    }
+    Ok(())
+}
+
+/// The next line of input without its line ending, or `None` at the end of input
+pub fn read_line() -> RuntimeResult<Option<String>> {
+    let mut line = String::new();
+    let read = io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .map_err(|e| RuntimeError::new(format!("Failed to read line: {}", e)))?;
+    if read == 0 {
+        return Ok(None);
+    }
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    Ok(Some(line))
+}
+
+/// The answer a line gives to a yes or no question, or `None` to ask again
+pub fn parse_answer(line: &str, default: bool) -> Option<bool> {
+    match line.trim().to_lowercase().as_str() {
+        "" => Some(default),
+        "y" | "yes" => Some(true),
+        "n" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+/// Raw mode for as long as it is held, unless the script already turned it on
+struct RawGuard {
+    restore: bool,
+}
+
+impl RawGuard {
+    fn new(method: &str) -> RuntimeResult<RawGuard> {
+        if !atty::is(atty::Stream::Stdin) {
+            return Err(RuntimeError::new(format!(
+                "term.{}() needs an interactive terminal",
+                method
+            )));
+        }
+        let restore = !RAW_MODE.load(Ordering::SeqCst);
+        if restore {
+            terminal::enable_raw_mode().map_err(terminal_error)?;
+        }
+        Ok(RawGuard { restore })
+    }
+}
+
+impl Drop for RawGuard {
+    fn drop(&mut self) {
+        if self.restore {
+            let _ = terminal::disable_raw_mode();
+        }
+    }
+}
+
+fn terminal_error(e: io::Error) -> RuntimeError {
+    RuntimeError::new(format!("Terminal error: {}", e))
+}
+
+pub fn read_password() -> RuntimeResult<String> {
+    // Piped input is not shown anyway, so read it like any other line
+    if !atty::is(atty::Stream::Stdin) {
+        return Ok(read_line()?.unwrap_or_default());
+    }
+
+    let mut password = String::new();
+    {
+        let _raw = RawGuard::new("read_password")?;
+        loop {
+            let Event::Key(key) = event::read().map_err(terminal_error)? else {
+                continue;
+            };
+            if key.kind == KeyEventKind::Release {
+                continue;
+            }
+            let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+            match key.code {
+                KeyCode::Enter => break,
+                KeyCode::Char('c') if ctrl => {
+                    super::write(super::Stream::Stdout, "\r\n");
+                    return Err(RuntimeError::new("term.read_password() was interrupted"));
+                }
+                KeyCode::Char(c) if !ctrl => password.push(c),
+                KeyCode::Backspace => {
+                    password.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+    // Enter was not echoed, so end the prompt's line
+    super::write(super::Stream::Stdout, "\n");
+    Ok(password)
+}
+
+pub fn set_raw_mode(enabled: bool) -> RuntimeResult<()> {
+    if enabled {
+        terminal::enable_raw_mode().map_err(terminal_error)?;
+    } else {
+        terminal::disable_raw_mode().map_err(terminal_error)?;
+    }
+    RAW_MODE.store(enabled, Ordering::SeqCst);
+    Ok(())
+}
+
+pub fn restore() {
+    if RAW_MODE.swap(false, Ordering::SeqCst) {
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+pub fn read_key(timeout: Option<Duration>) -> RuntimeResult<Option<Value>> {
+    let _raw = RawGuard::new("read_key")?;
+    let deadline = timeout.map(|t| std::time::Instant::now() + t);
+    loop {
+        if let Some(deadline) = deadline {
+            let left = deadline.saturating_duration_since(std::time::Instant::now());
+            if !event::poll(left).map_err(terminal_error)? {
+                return Ok(None);
+            }
+        }
+        if let Event::Key(key) = event::read().map_err(terminal_error)? {
+            if let Some(value) = key_value(key) {
+                return Ok(Some(value));
+            }
+        }
+    }
+}
+
+/// A key press as a loft KeyEvent, or `None` for releases and keys without a name
+pub fn key_value(key: KeyEvent) -> Option<Value> {
+    if key.kind == KeyEventKind::Release {
+        return None;
+    }
+    let mut shift = key.modifiers.contains(KeyModifiers::SHIFT);
+    let name = match key.code {
+        KeyCode::Char(' ') => "space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Enter => "enter".to_string(),
+        KeyCode::Esc => "escape".to_string(),
+        KeyCode::Backspace => "backspace".to_string(),
+        KeyCode::Tab => "tab".to_string(),
+        KeyCode::BackTab => {
+            shift = true;
+            "tab".to_string()
+        }
+        KeyCode::Up => "up".to_string(),
+        KeyCode::Down => "down".to_string(),
+        KeyCode::Left => "left".to_string(),
+        KeyCode::Right => "right".to_string(),
+        KeyCode::Home => "home".to_string(),
+        KeyCode::End => "end".to_string(),
+        KeyCode::PageUp => "page_up".to_string(),
+        KeyCode::PageDown => "page_down".to_string(),
+        KeyCode::Delete => "delete".to_string(),
+        KeyCode::Insert => "insert".to_string(),
+        KeyCode::F(n) => format!("f{}", n),
+        _ => return None,
+    };
+
+    let mut fields = HashMap::new();
+    fields.insert("key".to_string(), Value::String(name.into()));
+    fields.insert(
+        "ctrl".to_string(),
+        Value::Boolean(key.modifiers.contains(KeyModifiers::CONTROL)),
+    );
+    fields.insert(
+        "alt".to_string(),
+        Value::Boolean(key.modifiers.contains(KeyModifiers::ALT)),
+    );
+    fields.insert("shift".to_string(), Value::Boolean(shift));
+    Some(Value::Struct {
+        name: "KeyEvent".to_string(),
+        fields,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confirm_answers() {
+        assert_eq!(parse_answer("Yes", false), Some(true));
+        assert_eq!(parse_answer(" n ", true), Some(false));
+        assert_eq!(parse_answer("", true), Some(true));
+        assert_eq!(parse_answer("maybe", true), None);
+    }
+
+    #[test]
+    fn test_key_events() {
+        let field = |key: KeyEvent, name: &str| match key_value(key) {
+            Some(Value::Struct { fields, .. }) => fields[name].clone(),
+            other => panic!("expected a KeyEvent, got {:?}", other),
+        };
+        let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert_eq!(field(ctrl_c, "key"), Value::String("c".into()));
+        assert_eq!(field(ctrl_c, "ctrl"), Value::Boolean(true));
+        let back_tab = KeyEvent::new(KeyCode::BackTab, KeyModifiers::NONE);
+        assert_eq!(field(back_tab, "key"), Value::String("tab".into()));
+        assert_eq!(field(back_tab, "shift"), Value::Boolean(true));
+        assert_eq!(
+            field(KeyEvent::new(KeyCode::F(5), KeyModifiers::NONE), "key"),
+            Value::String("f5".into())
+        );
+
+        let mut release = KeyEvent::new(KeyCode::Up, KeyModifiers::NONE);
+        release.kind = KeyEventKind::Release;
+        assert_eq!(key_value(release), None);
+    }
+}
@@ -0,0 +1,565 @@
+use crate::runtime::builtin::{BuiltinMethod, BuiltinStruct};
+use crate::runtime::permission_context::check_run_permission;
+use crate::runtime::traits::ToString;
+use crate::runtime::value::Value;
+use crate::runtime::{RuntimeError, RuntimeResult};
+use loft_builtin_macros::loft_builtin;
+use rust_decimal::prelude::ToPrimitive;
+use std::sync::RwLock;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod input;
+mod progress;
+mod style;
+
+/// One of the streams a program writes to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// Takes everything the term builtin writes, for hosts that show the output
+/// themselves, like the playground
+pub type OutputSink = fn(&str, Stream);
+
+static OUTPUT_SINK: RwLock<Option<OutputSink>> = RwLock::new(None);
+
+/// Send all terminal output to `sink`, or back to stdout and stderr with `None`
+pub fn set_output_sink(sink: Option<OutputSink>) {
+    *OUTPUT_SINK.write().unwrap() = sink;
+}
+
+fn output_sink() -> Option<OutputSink> {
+    *OUTPUT_SINK.read().unwrap()
+}
+
+/// Write text to one of the program's streams, or to the sink if there is one
+pub(crate) fn write(stream: Stream, text: &str) {
+    match (output_sink(), stream) {
+        (Some(sink), _) => sink(text, stream),
+        (None, Stream::Stdout) => print!("{}", text),
+        (None, Stream::Stderr) => eprint!("{}", text),
+    }
+}
+
+/// Values separated by spaces, as the print and log methods show them
+fn join(args: &[Value], show: impl Fn(&Value) -> String) -> String {
+    args.iter().map(show).collect::<Vec<_>>().join(" ")
+}
+
+/// Print values to the terminal without a newline
+#[loft_builtin(term.print(...values))]
+fn term_print(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    write(Stream::Stdout, &join(args, |arg| arg.to_string()));
+    Ok(Value::Unit)
+}
+
+/// Print values to the terminal with a newline
+#[loft_builtin(term.println(...values))]
+fn term_println(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    write(Stream::Stdout, &(join(args, |arg| arg.to_string()) + "\n"));
+    Ok(Value::Unit)
+}
+
+/// Clear the terminal screen
+#[loft_builtin(term.clear())]
+fn term_clear(_this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    style::control("\x1B[2J\x1B[1;1H");
+    Ok(Value::Unit)
+}
+
+/// Clear the line the cursor is on and move to its start
+#[loft_builtin(term.clear_line())]
+fn term_clear_line(_this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    style::control("\r\x1B[2K");
+    Ok(Value::Unit)
+}
+
+/// Move the cursor to a column and row, counting from 0 at the top left
+#[loft_builtin(term.move_to(column: num, row: num))]
+fn term_move_to(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let position = |i: usize| match args.get(i) {
+        Some(Value::Number(n)) => n.to_u32(),
+        _ => None,
+    };
+    let (Some(column), Some(row)) = (position(0), position(1)) else {
+        return Err(RuntimeError::new(
+            "term.move_to() requires a column and a row",
+        ));
+    };
+    style::control(&format!("\x1B[{};{}H", row + 1, column + 1));
+    Ok(Value::Unit)
+}
+
+/// Move the cursor by a number of cells in one direction, which defaults to 1
+fn move_cursor(args: &[Value], method: &str, direction: char) -> RuntimeResult<Value> {
+    let cells = match args.first() {
+        None => 1,
+        Some(Value::Number(n)) => n.to_u32().ok_or_else(|| {
+            RuntimeError::new(format!(
+                "term.{}() requires a non-negative whole number",
+                method
+            ))
+        })?,
+        Some(_) => {
+            return Err(RuntimeError::new(format!(
+                "term.{}() requires a number",
+                method
+            )))
+        }
+    };
+    if cells > 0 {
+        style::control(&format!("\x1B[{}{}", cells, direction));
+    }
+    Ok(Value::Unit)
+}
+
+/// Move the cursor up a number of lines, 1 by default
+#[loft_builtin(term.move_up(lines?: num))]
+fn term_move_up(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    move_cursor(args, "move_up", 'A')
+}
+
+/// Move the cursor down a number of lines, 1 by default
+#[loft_builtin(term.move_down(lines?: num))]
+fn term_move_down(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    move_cursor(args, "move_down", 'B')
+}
+
+/// Move the cursor right a number of columns, 1 by default
+#[loft_builtin(term.move_right(columns?: num))]
+fn term_move_right(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    move_cursor(args, "move_right", 'C')
+}
+
+/// Move the cursor left a number of columns, 1 by default
+#[loft_builtin(term.move_left(columns?: num))]
+fn term_move_left(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    move_cursor(args, "move_left", 'D')
+}
+
+/// Hide the cursor until term.show_cursor() or the end of the program
+#[loft_builtin(term.hide_cursor())]
+fn term_hide_cursor(_this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    style::set_cursor_hidden(true);
+    Ok(Value::Unit)
+}
+
+/// Show the cursor again
+#[loft_builtin(term.show_cursor())]
+fn term_show_cursor(_this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    style::set_cursor_hidden(false);
+    Ok(Value::Unit)
+}
+
+/// Whether output goes to a terminal, rather than a pipe or a file
+#[loft_builtin(term.is_terminal() -> bool)]
+fn term_is_terminal(_this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    Ok(Value::Boolean(style::is_terminal()))
+}
+
+/// Print an optional prompt and read a line of input from the user
+#[loft_builtin(term.read_line(prompt?: str) -> str)]
+fn term_read_line(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let _ = args;
+        return Err(RuntimeError::new("Input not supported in WASM environment"));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        input::show_prompt(args, "read_line")?;
+        let line = input::read_line()?.unwrap_or_default();
+        Ok(Value::String(line.into()))
+    }
+}
+
+/// Print an optional prompt and read a line without showing what is typed
+#[loft_builtin(term.read_password(prompt?: str) -> str)]
+fn term_read_password(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let _ = args;
+        return Err(RuntimeError::new("Input not supported in WASM environment"));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        input::show_prompt(args, "read_password")?;
+        Ok(Value::String(input::read_password()?.into()))
+    }
+}
+
+/// Ask a yes or no question; an empty answer picks the default, which is false
+/// unless given
+#[loft_builtin(term.confirm(prompt?: str, default?: bool) -> bool)]
+fn term_confirm(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let default = match args.get(1) {
+        None => false,
+        Some(Value::Boolean(b)) => *b,
+        Some(_) => {
+            return Err(RuntimeError::new(
+                "term.confirm() default must be a boolean",
+            ))
+        }
+    };
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        let _ = default;
+        return Err(RuntimeError::new("Input not supported in WASM environment"));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let choices = if default { "[Y/n] " } else { "[y/N] " };
+        let prompt = match args.first() {
+            None => choices.to_string(),
+            Some(Value::String(question)) => format!("{} {}", question, choices),
+            Some(_) => return Err(RuntimeError::new("term.confirm() prompt must be a string")),
+        };
+        loop {
+            write(Stream::Stdout, &prompt);
+            input::flush();
+            // Running out of input takes the default rather than asking forever
+            let Some(line) = input::read_line()? else {
+                return Ok(Value::Boolean(default));
+            };
+            if let Some(answer) = input::parse_answer(&line, default) {
+                return Ok(Value::Boolean(answer));
+            }
+        }
+    }
+}
+
+/// Switch the terminal in or out of raw mode, where keys reach read_key() as
+/// they are pressed and are not shown
+#[loft_builtin(term.raw_mode(enabled: bool))]
+fn term_raw_mode(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let enabled = match args.first() {
+        Some(Value::Boolean(b)) => *b,
+        _ => return Err(RuntimeError::new("term.raw_mode() requires a boolean")),
+    };
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        let _ = enabled;
+        return Err(RuntimeError::new("Input not supported in WASM environment"));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        input::set_raw_mode(enabled)?;
+        Ok(Value::Unit)
+    }
+}
+
+/// Wait for a key press, or return unit if the optional timeout in milliseconds
+/// passes first
+#[loft_builtin(term.read_key(timeout?: num) -> KeyEvent)]
+fn term_read_key(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let timeout = match args.first() {
+        None => None,
+        Some(Value::Number(ms)) if !ms.is_sign_negative() => {
+            use rust_decimal::prelude::ToPrimitive;
+            ms.to_u64().map(std::time::Duration::from_millis)
+        }
+        Some(_) => {
+            return Err(RuntimeError::new(
+                "term.read_key() timeout must be a non-negative number of milliseconds",
+            ))
+        }
+    };
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        let _ = timeout;
+        return Err(RuntimeError::new("Input not supported in WASM environment"));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        Ok(input::read_key(timeout)?.unwrap_or(Value::Unit))
+    }
+}
+
+/// Leave raw mode and show the cursor if a script changed them, so the shell
+/// gets back a usable terminal
+pub fn restore() {
+    #[cfg(not(target_arch = "wasm32"))]
+    input::restore();
+    style::restore();
+}
+
+/// Get the terminal size as an array [width, height]
+#[loft_builtin(term.size() -> Array<num>)]
+fn term_size(_this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    use std::process::Command;
+
+    // Check permission to run tput command
+    check_run_permission("tput", Some("term.size()")).map_err(RuntimeError::new)?;
+
+    // Try to get terminal size using tput
+    let width_output = Command::new("tput")
+        .arg("cols")
+        .output()
+        .ok()
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+        .and_then(|s| s.trim().parse::<i32>().ok());
+
+    let height_output = Command::new("tput")
+        .arg("lines")
+        .output()
+        .ok()
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+        .and_then(|s| s.trim().parse::<i32>().ok());
+
+    match (width_output, height_output) {
+        (Some(width), Some(height)) => {
+            use rust_decimal::Decimal;
+            Ok(Value::Array(vec![
+                Value::Number(Decimal::from(width)),
+                Value::Number(Decimal::from(height)),
+            ]))
+        }
+        _ => {
+            // Default fallback size
+            use rust_decimal::Decimal;
+            Ok(Value::Array(vec![
+                Value::Number(Decimal::from(80)),
+                Value::Number(Decimal::from(24)),
+            ]))
+        }
+    }
+}
+
+/// Set the color or style of the text printed after it, until "reset". Takes
+/// the same names as term.style()
+#[loft_builtin(term.color(color: str))]
+fn term_color(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    if args.is_empty() {
+        return Err(RuntimeError::new("term.color() requires a color argument"));
+    }
+
+    if let Value::String(color) = &args[0] {
+        let codes = style::codes(color)
+            .ok_or_else(|| RuntimeError::new(format!("Unknown color: {}", color)))?;
+        style::color(&codes);
+        Ok(Value::Unit)
+    } else {
+        Err(RuntimeError::new("term.color() argument must be a string"))
+    }
+}
+
+/// Return text with colors and styles such as "red", "bright_blue", "on_green",
+/// "bold", "underline" or "#00ff41"; the text comes back unchanged when output
+/// doesn't go to a terminal
+#[loft_builtin(term.style(text: any, ...styles: str) -> str)]
+fn term_style(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let Some((text, styles)) = args.split_first() else {
+        return Err(RuntimeError::new("term.style() requires text to style"));
+    };
+    let mut codes = Vec::new();
+    for name in styles {
+        let Value::String(name) = name else {
+            return Err(RuntimeError::new("term.style() styles must be strings"));
+        };
+        codes.push(
+            style::codes(name)
+                .ok_or_else(|| RuntimeError::new(format!("Unknown style: {}", name)))?,
+        );
+    }
+
+    let text = text.to_string();
+    if !crate::output::colors_enabled() {
+        return Ok(Value::String(text.into()));
+    }
+    Ok(Value::String(style::paint(&text, &codes).into()))
+}
+
+/// Helper function to format values for console-style output
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::Unit => "null".to_string(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.to_string(),
+        Value::Bytes(b) => Value::bytes_preview(b),
+        Value::DateTime(dt) => dt.to_rfc3339(),
+        Value::Array(arr) => {
+            let items: Vec<String> = arr.iter().map(format_value).collect();
+            format!("[{}]", items.join(", "))
+        }
+        Value::Map(entries) => {
+            let items: Vec<String> = entries
+                .iter()
+                .map(|(k, v)| format!("{}: {}", format_value(k), format_value(v)))
+                .collect();
+            format!("#{{{}}}", items.join(", "))
+        }
+        Value::Set(items) => {
+            let items: Vec<String> = items.iter().map(|v| format_value(v.value())).collect();
+            format!("Set{{{}}}", items.join(", "))
+        }
+        Value::Struct { name, fields } => {
+            if name == "Object" {
+                let items: Vec<String> = fields
+                    .iter()
+                    .map(|(k, v)| format!("{}: {}", k, format_value(v)))
+                    .collect();
+                format!("{{ {} }}", items.join(", "))
+            } else {
+                format!("{}(...)", name)
+            }
+        }
+        Value::Function { .. } => "[Function]".to_string(),
+        Value::Closure { .. } => "[Closure]".to_string(),
+        Value::Builtin(_) => "[Builtin]".to_string(),
+        Value::Promise(_) | Value::Task(_) => "[Promise]".to_string(),
+        Value::BuiltinFn(_) => "[BuiltinFn]".to_string(),
+        Value::BoundMethod { .. } => "[BoundMethod]".to_string(),
+        Value::UserMethod { .. } => "[Method]".to_string(),
+        Value::EnumVariant {
+            enum_name,
+            variant_name,
+            values,
+        } => {
+            if values.is_empty() {
+                format!("{}.{}", enum_name, variant_name)
+            } else {
+                let vals: Vec<String> = values.iter().map(format_value).collect();
+                format!("{}.{}({})", enum_name, variant_name, vals.join(", "))
+            }
+        }
+        Value::EnumConstructor {
+            enum_name,
+            variant_name,
+            ..
+        } => {
+            format!("{}.{}", enum_name, variant_name)
+        }
+        Value::Module { name, .. } => {
+            format!("<module {}>", name)
+        }
+    }
+}
+
+/// Write a line of logged values, after `prefix`
+fn log(stream: Stream, prefix: &str, args: &[Value]) {
+    write(stream, &format!("{}{}\n", prefix, join(args, format_value)));
+}
+
+/// Log values to the terminal (alias for println)
+#[loft_builtin(term.log(...values))]
+fn term_log(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    log(Stream::Stdout, "", args);
+    Ok(Value::Unit)
+}
+
+/// Log an error message to the terminal
+#[loft_builtin(term.error(...values))]
+fn term_error(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    log(Stream::Stderr, "[ERROR] ", args);
+    Ok(Value::Unit)
+}
+
+/// Log a warning message to the terminal
+#[loft_builtin(term.warn(...values))]
+fn term_warn(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    log(Stream::Stderr, "[WARN] ", args);
+    Ok(Value::Unit)
+}
+
+/// Log an info message to the terminal
+#[loft_builtin(term.info(...values))]
+fn term_info(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    log(Stream::Stdout, "[INFO] ", args);
+    Ok(Value::Unit)
+}
+
+/// Log a debug message to the terminal
+#[loft_builtin(term.debug(...values))]
+fn term_debug(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    log(Stream::Stdout, "[DEBUG] ", args);
+    Ok(Value::Unit)
+}
+
+/// Create the Term builtin struct
+pub fn create_term_builtin() -> BuiltinStruct {
+    let mut term = BuiltinStruct::new("term");
+
+    term.add_method("print", term_print as BuiltinMethod);
+    term.add_method("println", term_println as BuiltinMethod);
+    term.add_method("clear", term_clear as BuiltinMethod);
+    term.add_method("read_line", term_read_line as BuiltinMethod);
+    term.add_method("read_password", term_read_password as BuiltinMethod);
+    term.add_method("confirm", term_confirm as BuiltinMethod);
+    term.add_method("raw_mode", term_raw_mode as BuiltinMethod);
+    term.add_method("read_key", term_read_key as BuiltinMethod);
+    term.add_method("size", term_size as BuiltinMethod);
+    term.add_method("color", term_color as BuiltinMethod);
+    term.add_method("style", term_style as BuiltinMethod);
+    term.add_method("clear_line", term_clear_line as BuiltinMethod);
+    term.add_method("move_to", term_move_to as BuiltinMethod);
+    term.add_method("move_up", term_move_up as BuiltinMethod);
+    term.add_method("move_down", term_move_down as BuiltinMethod);
+    term.add_method("move_left", term_move_left as BuiltinMethod);
+    term.add_method("move_right", term_move_right as BuiltinMethod);
+    term.add_method("hide_cursor", term_hide_cursor as BuiltinMethod);
+    term.add_method("show_cursor", term_show_cursor as BuiltinMethod);
+    term.add_method("is_terminal", term_is_terminal as BuiltinMethod);
+    term.add_method("progress", progress::term_progress as BuiltinMethod);
+
+    // Console-style methods (merged from console module)
+    term.add_method("log", term_log as BuiltinMethod);
+    term.add_method("error", term_error as BuiltinMethod);
+    term.add_method("warn", term_warn as BuiltinMethod);
+    term.add_method("info", term_info as BuiltinMethod);
+    term.add_method("debug", term_debug as BuiltinMethod);
+
+    term
+}
+
+// Register the builtin automatically
+crate::submit_builtin!("term", create_term_builtin);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::builtins::testing::{number, string};
+    use std::sync::Mutex;
+
+    static WRITTEN: Mutex<String> = Mutex::new(String::new());
+
+    fn collect(text: &str, stream: Stream) {
+        let tag = match stream {
+            Stream::Stdout => "out",
+            Stream::Stderr => "err",
+        };
+        WRITTEN
+            .lock()
+            .unwrap()
+            .push_str(&format!("{}:{}|", tag, text));
+    }
+
+    #[test]
+    fn test_output_sink_gets_everything() {
+        set_output_sink(Some(collect));
+        term_println(&Value::Unit, &[string("hi")]).unwrap();
+        term_error(&Value::Unit, &[string("boom")]).unwrap();
+        term_info(&Value::Unit, &[string("note")]).unwrap();
+        let bar = progress::term_progress(&Value::Unit, &[number(2)]).unwrap();
+        if let Value::Builtin(b) = &bar {
+            b.methods["finish"](&bar, &[]).unwrap();
+        }
+        set_output_sink(None);
+
+        let written = WRITTEN.lock().unwrap();
+        assert!(written.contains("out:hi\n|"));
+        assert!(written.contains("err:[ERROR] boom\n|"));
+        assert!(written.contains("out:[INFO] note\n|"));
+        assert!(written.contains(&format!("out:\r[{}]   0%|", "░".repeat(30))));
+    }
+}
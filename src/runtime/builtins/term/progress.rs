@@ -0,0 +1,195 @@
+//! Progress bars from `term.progress()`, redrawn in place on a single line
+//!
+//! A bar is only drawn when stdout is a terminal; otherwise updating it does
+//! nothing, so scripts can report progress without cluttering piped output.
+//! A host taking the output, like the playground, gets every redraw as `\r`
+//! followed by the bar.
+
+use super::{output_sink, style, write, Stream};
+use crate::runtime::builtin::{BuiltinMethod, BuiltinStruct};
+use crate::runtime::builtins::handles::{self, Handles};
+use crate::runtime::value::Value;
+use crate::runtime::{RuntimeError, RuntimeResult};
+use loft_builtin_macros::loft_builtin;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+
+/// Cells in the bar itself, between the label and the percentage
+const WIDTH: usize = 30;
+
+struct Progress {
+    total: Decimal,
+    done: Decimal,
+    label: String,
+}
+
+static BARS: Handles<Progress> = Handles::new();
+
+impl Progress {
+    /// The line showing this bar, such as `files [█████░░░░░] 50%`
+    fn render(&self) -> String {
+        let fraction = (self.done / self.total).clamp(Decimal::ZERO, Decimal::ONE);
+        let filled = (fraction * Decimal::from(WIDTH))
+            .floor()
+            .to_usize()
+            .unwrap_or(0);
+        let percent = (fraction * Decimal::ONE_HUNDRED).floor();
+        let bar = format!(
+            "[{}{}] {:>3}%",
+            "█".repeat(filled),
+            "░".repeat(WIDTH - filled),
+            percent
+        );
+        if self.label.is_empty() {
+            bar
+        } else {
+            format!("{} {}", self.label, bar)
+        }
+    }
+
+    fn draw(&self) {
+        match output_sink() {
+            Some(_) => write(Stream::Stdout, &format!("\r{}", self.render())),
+            None => style::control(&format!("\r\x1B[2K{}", self.render())),
+        }
+    }
+}
+
+fn amount(value: Option<&Value>, method: &str) -> RuntimeResult<Option<Decimal>> {
+    match value {
+        None => Ok(None),
+        Some(Value::Number(n)) => Ok(Some(*n)),
+        Some(_) => Err(RuntimeError::new(format!("{}() requires a number", method))),
+    }
+}
+
+fn bar_id(this: &Value, method: &str) -> RuntimeResult<u64> {
+    handles::handle_id(this, &["ProgressBar"], "id", method, "a progress bar")
+}
+
+/// Update the bar behind a handle and redraw it
+fn update(this: &Value, method: &str, change: impl FnOnce(&mut Progress)) -> RuntimeResult<Value> {
+    BARS.with(bar_id(this, method)?, |bar| {
+        change(bar);
+        bar.draw();
+    })
+    .ok_or_else(|| {
+        RuntimeError::new(format!(
+            "{}(): the progress bar has already finished",
+            method
+        ))
+    })?;
+    Ok(Value::Unit)
+}
+
+/// Set how much of the total is done
+#[loft_builtin(ProgressBar.set(done: num))]
+fn progress_set(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let done =
+        amount(args.first(), "set")?.ok_or_else(|| RuntimeError::new("set() requires a number"))?;
+    update(this, "set", |bar| bar.done = done)
+}
+
+/// Add to how much is done, by one unless given an amount
+#[loft_builtin(ProgressBar.inc(amount?: num))]
+fn progress_inc(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let step = amount(args.first(), "inc")?.unwrap_or(Decimal::ONE);
+    update(this, "inc", |bar| bar.done += step)
+}
+
+/// Change the text shown before the bar
+#[loft_builtin(ProgressBar.set_label(label: str))]
+fn progress_set_label(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let label = match args.first() {
+        Some(Value::String(label)) => label.to_string(),
+        _ => return Err(RuntimeError::new("set_label() requires a string")),
+    };
+    update(this, "set_label", |bar| bar.label = label)
+}
+
+/// Draw the bar one last time and move to the next line
+#[loft_builtin(ProgressBar.finish())]
+fn progress_finish(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    update(this, "finish", |_| {})?;
+    match output_sink() {
+        Some(_) => write(Stream::Stdout, "\n"),
+        None => style::control("\n"),
+    }
+    BARS.remove(bar_id(this, "finish")?);
+    Ok(Value::Unit)
+}
+
+/// Start a progress bar counting up to a total, redrawn in place as it changes
+#[loft_builtin(term.progress(total: num, label?: str) -> ProgressBar)]
+pub fn term_progress(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let total = match args.first() {
+        Some(Value::Number(total)) if *total > Decimal::ZERO => *total,
+        _ => {
+            return Err(RuntimeError::new(
+                "term.progress() requires a positive total",
+            ))
+        }
+    };
+    let label = match args.get(1) {
+        None => String::new(),
+        Some(Value::String(label)) => label.to_string(),
+        Some(_) => return Err(RuntimeError::new("term.progress() label must be a string")),
+    };
+
+    let bar = Progress {
+        total,
+        done: Decimal::ZERO,
+        label,
+    };
+    bar.draw();
+    let id = BARS.insert(bar);
+
+    let mut handle = BuiltinStruct::new("ProgressBar");
+    handle.add_field("id", Value::Number(Decimal::from(id)));
+    handle.add_method("set", progress_set as BuiltinMethod);
+    handle.add_method("inc", progress_inc as BuiltinMethod);
+    handle.add_method("set_label", progress_set_label as BuiltinMethod);
+    handle.add_method("finish", progress_finish as BuiltinMethod);
+    Ok(Value::Builtin(handle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::builtins::testing::number;
+
+    #[test]
+    fn test_progress_rendering() {
+        let mut bar = Progress {
+            total: Decimal::from(4),
+            done: Decimal::ZERO,
+            label: String::new(),
+        };
+        assert_eq!(bar.render(), format!("[{}]   0%", "░".repeat(30)));
+        bar.done = Decimal::from(3);
+        bar.label = "files".to_string();
+        assert_eq!(
+            bar.render(),
+            format!("files [{}{}]  75%", "█".repeat(22), "░".repeat(8))
+        );
+        bar.done = Decimal::from(9);
+        assert_eq!(bar.render(), format!("files [{}] 100%", "█".repeat(30)));
+    }
+
+    #[test]
+    fn test_progress_handle() {
+        let bar = term_progress(&Value::Unit, &[number(10)]).unwrap();
+        let call = |method: &str, args: &[Value]| match &bar {
+            Value::Builtin(b) => b.methods[method](&bar, args),
+            _ => panic!("expected a builtin"),
+        };
+        call("inc", &[]).unwrap();
+        call("inc", &[number(2)]).unwrap();
+        call("finish", &[]).unwrap();
+        assert_eq!(
+            call("set", &[number(5)]).unwrap_err().message,
+            "set(): the progress bar has already finished"
+        );
+        assert!(term_progress(&Value::Unit, &[number(0)]).is_err());
+    }
+}
@@ -0,0 +1,140 @@
+//! ANSI colors, text styles and cursor control for `term.style()` and friends
+//!
+//! Everything here is left out when stdout isn't a terminal, so output that
+//! is piped or written to a file stays plain text. Colors are also left out
+//! when they are turned off, as `crate::output` decides for loft's own output.
+
+use super::{write, Stream};
+use crate::output;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const COLORS: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+/// Whether a script has hidden the cursor with `term.hide_cursor()`
+static CURSOR_HIDDEN: AtomicBool = AtomicBool::new(false);
+
+/// Whether stdout is a terminal that understands control sequences
+pub fn is_terminal() -> bool {
+    output::stdout_is_terminal()
+}
+
+/// Write a color or text style straight away, if colors are enabled
+pub fn color(codes: &str) {
+    if output::colors_enabled() {
+        write(Stream::Stdout, &format!("\x1B[{}m", codes));
+        let _ = std::io::stdout().flush();
+    }
+}
+
+/// Write a control sequence straight away, if stdout is a terminal
+pub fn control(sequence: &str) {
+    if is_terminal() {
+        write(Stream::Stdout, sequence);
+        let _ = std::io::stdout().flush();
+    }
+}
+
+pub fn set_cursor_hidden(hidden: bool) {
+    control(if hidden { "\x1B[?25l" } else { "\x1B[?25h" });
+    CURSOR_HIDDEN.store(hidden, Ordering::SeqCst);
+}
+
+/// Show the cursor again if a script hid it
+pub fn restore() {
+    if CURSOR_HIDDEN.swap(false, Ordering::SeqCst) {
+        control("\x1B[?25h");
+    }
+}
+
+/// The SGR parameters of a style such as "red", "bright_blue", "on_green",
+/// "bold" or "#00ff41", or `None` if there is no such style
+pub fn codes(name: &str) -> Option<String> {
+    let name = name.to_lowercase();
+    let (background, color) = match name.strip_prefix("on_") {
+        Some(color) => (true, color),
+        None => (false, name.as_str()),
+    };
+
+    if let Some(hex) = color.strip_prefix('#') {
+        let channel = |i: usize| {
+            hex.get(i..i + 2)
+                .and_then(|c| u8::from_str_radix(c, 16).ok())
+        };
+        if hex.len() != 6 {
+            return None;
+        }
+        let (r, g, b) = (channel(0)?, channel(2)?, channel(4)?);
+        let layer = if background { 48 } else { 38 };
+        return Some(format!("{};2;{};{};{}", layer, r, g, b));
+    }
+
+    let (bright, base) = match color {
+        "gray" | "grey" => (true, "black"),
+        "purple" => (false, "magenta"),
+        _ => match color.strip_prefix("bright_") {
+            Some(base) => (true, base),
+            None => (false, color),
+        },
+    };
+    if let Some(index) = COLORS.iter().position(|c| *c == base) {
+        let offset = match (background, bright) {
+            (false, false) => 30,
+            (false, true) => 90,
+            (true, false) => 40,
+            (true, true) => 100,
+        };
+        return Some((offset + index).to_string());
+    }
+
+    if background {
+        return None;
+    }
+    let code = match color {
+        "reset" => 0,
+        "bold" => 1,
+        "dim" => 2,
+        "italic" => 3,
+        "underline" => 4,
+        "inverse" => 7,
+        "strikethrough" => 9,
+        _ => return None,
+    };
+    Some(code.to_string())
+}
+
+/// `text` wrapped in the control sequences for `codes`, which ends by resetting
+/// every style
+pub fn paint(text: &str, codes: &[String]) -> String {
+    if codes.is_empty() {
+        return text.to_string();
+    }
+    format!("\x1B[{}m{}\x1B[0m", codes.join(";"), text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_style_codes() {
+        assert_eq!(codes("red").as_deref(), Some("31"));
+        assert_eq!(codes("Bright_Blue").as_deref(), Some("94"));
+        assert_eq!(codes("on_green").as_deref(), Some("42"));
+        assert_eq!(codes("on_bright_white").as_deref(), Some("107"));
+        assert_eq!(codes("gray").as_deref(), Some("90"));
+        assert_eq!(codes("purple").as_deref(), Some("35"));
+        assert_eq!(codes("bold").as_deref(), Some("1"));
+        assert_eq!(codes("#00ff41").as_deref(), Some("38;2;0;255;65"));
+        assert_eq!(codes("on_#000000").as_deref(), Some("48;2;0;0;0"));
+        assert_eq!(codes("on_bold"), None);
+        assert_eq!(codes("#0f0"), None);
+        assert_eq!(codes("chartreuse"), None);
+
+        let styles = vec!["1".to_string(), "32".to_string()];
+        assert_eq!(paint("ok", &styles), "\x1B[1;32mok\x1B[0m");
+        assert_eq!(paint("ok", &[]), "ok");
+    }
+}
@@ -3,8 +3,8 @@ use crate::runtime::value::Value;
 use crate::runtime::{RuntimeError, RuntimeResult};
 use loft_builtin_macros::loft_builtin;
 
-/// Assert that a condition is true
-#[loft_builtin(test.assert)]
+/// Assert that a condition is true. Throws an error if false.
+#[loft_builtin(test.assert(condition: bool, message?: str))]
 pub fn test_assert(#[required] _this: &Value, #[types(bool*)] args: &[Value]) -> RuntimeResult<Value> {
     let condition = match &args[0] {
         Value::Boolean(b) => *b,
@@ -14,7 +14,7 @@ pub fn test_assert(#[required] _this: &Value, #[types(bool*)] args: &[Value]) ->
     if !condition {
         let message = if args.len() > 1 {
             match &args[1] {
-                Value::String(s) => s.clone(),
+                Value::String(s) => s.to_string(),
                 _ => "Assertion failed".to_string(),
             }
         } else {
@@ -26,8 +26,8 @@ pub fn test_assert(#[required] _this: &Value, #[types(bool*)] args: &[Value]) ->
     Ok(Value::Unit)
 }
 
-/// Assert that two values are equal
-#[loft_builtin(test.assert_eq)]
+/// Assert that two values are equal. Throws an error if they are not.
+#[loft_builtin(test.assert_eq(left: any, right: any, message?: str))]
 pub fn test_assert_eq(#[required] _this: &Value, #[required] args: &[Value]) -> RuntimeResult<Value> {
     if args.len() < 2 {
         return Err(RuntimeError::new("test.assert_eq() requires two arguments"));
@@ -39,7 +39,7 @@ pub fn test_assert_eq(#[required] _this: &Value, #[required] args: &[Value]) ->
     if left != right {
         let message = if args.len() > 2 {
             match &args[2] {
-                Value::String(s) => s.clone(),
+                Value::String(s) => s.to_string(),
                 _ => format!("Assertion failed: {:?} != {:?}", left, right),
             }
         } else {
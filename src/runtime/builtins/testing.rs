@@ -0,0 +1,22 @@
+//! Helpers shared by the tests of builtins, which call methods on handles the
+//! way a script would.
+
+use crate::runtime::value::Value;
+use crate::runtime::RuntimeResult;
+use rust_decimal::Decimal;
+
+pub fn string(s: &str) -> Value {
+    Value::String(s.into())
+}
+
+pub fn number(n: i64) -> Value {
+    Value::Number(Decimal::from(n))
+}
+
+/// Call a method of a builtin handle
+pub fn call(handle: &Value, method: &str, args: &[Value]) -> RuntimeResult<Value> {
+    match handle {
+        Value::Builtin(b) => b.methods[method](handle, args),
+        _ => panic!("expected a handle, got {:?}", handle),
+    }
+}
@@ -1,33 +1,156 @@
 use crate::runtime::builtin::{BuiltinMethod, BuiltinStruct};
+use crate::runtime::limits;
+use crate::runtime::permission_context;
+use crate::runtime::scheduler::Task;
 use crate::runtime::value::Value;
 use crate::runtime::{RuntimeError, RuntimeResult};
 use loft_builtin_macros::loft_builtin;
 use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
 use std::thread;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-/// Sleep for the specified number of milliseconds and return a promise
-#[loft_builtin(time.sleep)]
+/// Return a promise that resolves after the specified number of milliseconds,
+/// without blocking other tasks
+#[loft_builtin(time.sleep(ms: num) -> Promise<void>)]
 fn time_sleep(#[required] _this: &Value, #[types(number)] args: &[Value]) -> RuntimeResult<Value> {
     let duration_ms = match &args[0] {
-        Value::Number(n) => {
-            
-            n.to_f64().unwrap_or(0.0) as u64
-        }
+        Value::Number(n) => n.to_f64().unwrap_or(0.0).max(0.0) as u64,
         _ => unreachable!(),
     };
 
-    // Actually sleep (this blocks the current thread)
-    // In a real async runtime, this would create a timer future
-    thread::sleep(Duration::from_millis(duration_ms));
+    Ok(Value::Task(Task::timer(Duration::from_millis(duration_ms))))
+}
+
+/// Set to true to stop a timer started with `time.interval` or `time.timeout`
+type Cancel = Arc<(Mutex<bool>, Condvar)>;
+
+static TIMERS: Mutex<BTreeMap<u64, Cancel>> = Mutex::new(BTreeMap::new());
+static NEXT_TIMER: AtomicU64 = AtomicU64::new(1);
+
+/// The delay a timer is started with, at least a millisecond so that an
+/// interval of 0 does not spin
+pub fn timer_delay(value: Option<&Value>, name: &str) -> RuntimeResult<Duration> {
+    match value {
+        Some(Value::Number(n)) if !n.is_sign_negative() => {
+            Ok(Duration::from_millis(n.to_u64().unwrap_or(u64::MAX).max(1)))
+        }
+        _ => Err(RuntimeError::new(format!(
+            "time.{}() requires a number of milliseconds",
+            name
+        ))),
+    }
+}
+
+/// Run `tick` on its own thread after `delay`, and then every `delay` again if
+/// `repeat` is set, until the returned handle is cancelled or `tick` fails
+pub fn start_timer(
+    name: &'static str,
+    delay: Duration,
+    repeat: bool,
+    mut tick: impl FnMut() -> RuntimeResult<Value> + Send + 'static,
+) -> RuntimeResult<Value> {
+    let id = NEXT_TIMER.fetch_add(1, Ordering::Relaxed);
+    let cancel: Cancel = Arc::new((Mutex::new(false), Condvar::new()));
+    TIMERS.lock().unwrap().insert(id, Arc::clone(&cancel));
+
+    // The callback is held to the permissions and the budget of the thread that started it
+    let permissions = permission_context::with_permissions(|pm| pm.clone());
+    let budget = limits::current_budget();
+    thread::Builder::new()
+        .name(format!("loft-{}-{}", name, id))
+        .spawn(move || {
+            if let Some(permissions) = permissions {
+                permission_context::init_permissions(permissions);
+            }
+            limits::replace_budget(budget);
+            let (cancelled, signal) = &*cancel;
+            let mut next = Instant::now() + delay;
+            loop {
+                let mut stopped = cancelled.lock().unwrap();
+                let mut now = Instant::now();
+                while !*stopped && now < next {
+                    stopped = signal.wait_timeout(stopped, next - now).unwrap().0;
+                    now = Instant::now();
+                }
+                // Timers stop with the program that started them
+                if *stopped || limits::is_finished() {
+                    break;
+                }
+                drop(stopped);
+
+                // Nothing awaits a timer, so its errors are reported here
+                if let Err(e) = tick() {
+                    if limits::is_finished() {
+                        break;
+                    }
+                    if let Some(code) = e.exit_code() {
+                        crate::runtime::exit::exit_process(code);
+                    }
+                    eprintln!("Error in time.{}() callback: {}", name, e.message);
+                    break;
+                }
+                if !repeat {
+                    break;
+                }
+                // A callback that overran its period skips the ticks it missed
+                next = (next + delay).max(Instant::now());
+            }
+            TIMERS.lock().unwrap().remove(&id);
+        })
+        .map_err(|e| RuntimeError::new(format!("Failed to start timer: {}", e)))?;
+
+    let mut handle = BuiltinStruct::new("Timer");
+    handle.add_field("id", Value::Number(Decimal::from(id)));
+    handle.add_method("cancel", timer_cancel as BuiltinMethod);
+    Ok(Value::Builtin(handle))
+}
+
+/// Stop the timer; a callback that is already running finishes first
+#[loft_builtin(Timer.cancel())]
+fn timer_cancel(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let id = match this {
+        Value::Builtin(handle) => match handle.fields.get("id") {
+            Some(Value::Number(id)) => id.to_u64(),
+            _ => None,
+        },
+        _ => None,
+    }
+    .ok_or_else(|| RuntimeError::new("cancel() can only be called on a timer"))?;
+
+    // Timers that already finished are no longer listed
+    if let Some(cancel) = TIMERS.lock().unwrap().get(&id) {
+        *cancel.0.lock().unwrap() = true;
+        cancel.1.notify_all();
+    }
+    Ok(Value::Unit)
+}
+
+/// Call a function every given number of milliseconds until the returned timer
+/// is cancelled
+// The timer is started by the interpreter, which intercepts `time.interval`
+#[loft_builtin(time.interval(ms: num, callback: fn() -> any) -> Timer)]
+fn time_interval(_this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    Err(RuntimeError::new(
+        "time.interval() requires a number of milliseconds and a function",
+    ))
+}
 
-    // Return a promise that resolves to Unit (void)
-    Ok(Value::Promise(Box::new(Value::Unit)))
+/// Call a function once after a number of milliseconds, unless the returned
+/// timer is cancelled
+// The timer is started by the interpreter, which intercepts `time.timeout`
+#[loft_builtin(time.timeout(ms: num, callback: fn() -> any) -> Timer)]
+fn time_timeout(_this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    Err(RuntimeError::new(
+        "time.timeout() requires a number of milliseconds and a function",
+    ))
 }
 
-/// Get the current time in milliseconds since Unix epoch
-#[loft_builtin(time.now)]
+/// Get the current Unix timestamp in milliseconds
+#[loft_builtin(time.now() -> num)]
 fn time_now(_this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -37,8 +160,8 @@ fn time_now(_this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     Ok(Value::Number(Decimal::from(millis)))
 }
 
-/// Create a high-resolution timer for performance measurement
-#[loft_builtin(time.perf_now)]
+/// Get high-resolution performance timing
+#[loft_builtin(time.perf_now() -> num)]
 fn time_perf_now(_this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     // For simplicity, we'll use a static start time
     // In a real implementation, this would be more sophisticated
@@ -53,8 +176,8 @@ fn time_perf_now(_this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
     ))
 }
 
-/// Format a duration in milliseconds to a human-readable string
-#[loft_builtin(time.format)]
+/// Format a duration in milliseconds as text such as "1.50s" or "2.00m"
+#[loft_builtin(time.format(ms: num) -> str)]
 fn time_format(#[required] _this: &Value, #[types(number)] args: &[Value]) -> RuntimeResult<Value> {
     let duration_ms = match &args[0] {
         Value::Number(n) => n.to_f64().unwrap_or(0.0),
@@ -73,7 +196,7 @@ fn time_format(#[required] _this: &Value, #[types(number)] args: &[Value]) -> Ru
         format!("{:.2}h", hours)
     };
 
-    Ok(Value::String(formatted))
+    Ok(Value::String(formatted.into()))
 }
 
 /// Create a benchmark function that measures execution time
@@ -87,9 +210,7 @@ fn time_benchmark(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
 
     // For now, just return a placeholder since we don't have full function execution
     // In a full implementation, this would execute the function and measure time
-    Ok(Value::String(
-        "Benchmark not yet fully implemented".to_string(),
-    ))
+    Ok(Value::String("Benchmark not yet fully implemented".into()))
 }
 
 /// Create the Time builtin struct
@@ -101,9 +222,35 @@ pub fn create_time_builtin() -> BuiltinStruct {
     time.add_method("perf_now", time_perf_now as BuiltinMethod);
     time.add_method("format", time_format as BuiltinMethod);
     time.add_method("benchmark", time_benchmark as BuiltinMethod);
+    time.add_method("interval", time_interval as BuiltinMethod);
+    time.add_method("timeout", time_timeout as BuiltinMethod);
 
     time
 }
 
 // Register the builtin automatically
 crate::submit_builtin!("time", create_time_builtin);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::permission_context::{check_read_permission, replace_permissions};
+    use crate::runtime::permissions::PermissionManager;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_timer_keeps_permissions() {
+        let (sender, receiver) = mpsc::channel();
+        let previous = replace_permissions(Some(PermissionManager::deny_all()));
+        let timer = start_timer("timeout", Duration::from_millis(1), false, move || {
+            let allowed = check_read_permission("/etc/hostname", Some("fs.read()")).is_ok();
+            sender.send(allowed).unwrap();
+            Ok(Value::Unit)
+        });
+        replace_permissions(previous);
+
+        timer.unwrap();
+        let allowed = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(!allowed, "the callback ran without its permissions");
+    }
+}
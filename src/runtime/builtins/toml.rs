@@ -0,0 +1,178 @@
+use crate::runtime::builtin::{BuiltinMethod, BuiltinStruct};
+use crate::runtime::traits::ToString;
+use crate::runtime::value::Value;
+use crate::runtime::{RuntimeError, RuntimeResult};
+use loft_builtin_macros::{loft_builtin, required};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+
+/// Parse a TOML document into an object. Dates are returned as strings
+#[loft_builtin(toml.parse(text: str) -> Object)]
+fn toml_parse(#[required] _this: &Value, #[types(string)] args: &[Value]) -> RuntimeResult<Value> {
+    let toml_str = match &args[0] {
+        Value::String(s) => s,
+        _ => unreachable!(),
+    };
+
+    let table: ::toml::Table = ::toml::from_str(toml_str)
+        .map_err(|e| RuntimeError::new(format!("Failed to parse TOML: {}", e)))?;
+
+    toml_to_loft_value(::toml::Value::Table(table))
+}
+
+/// Convert an object to a TOML document. Fields set to void are left out
+#[loft_builtin(toml.stringify(value: Object) -> str)]
+#[required]
+fn toml_stringify(#[required] _this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let table = match loft_value_to_toml(&args[0])? {
+        Some(::toml::Value::Table(table)) => table,
+        _ => {
+            return Err(RuntimeError::new(
+                "toml.stringify() requires an object at the top level",
+            ))
+        }
+    };
+
+    let toml_str = ::toml::to_string(&table)
+        .map_err(|e| RuntimeError::new(format!("Failed to stringify TOML: {}", e)))?;
+    Ok(Value::String(toml_str.into()))
+}
+
+fn toml_to_loft_value(toml: ::toml::Value) -> RuntimeResult<Value> {
+    match toml {
+        ::toml::Value::String(s) => Ok(Value::String(s.into())),
+        ::toml::Value::Integer(i) => Ok(Value::Number(Decimal::from(i))),
+        ::toml::Value::Float(f) => {
+            Ok(Value::Number(Decimal::try_from(f).map_err(|e| {
+                RuntimeError::new(format!("Invalid number: {}", e))
+            })?))
+        }
+        ::toml::Value::Boolean(b) => Ok(Value::Boolean(b)),
+        // loft has no date type, so dates keep their TOML spelling
+        ::toml::Value::Datetime(d) => Ok(Value::String(d.to_string().into())),
+        ::toml::Value::Array(arr) => {
+            let mut values = Vec::new();
+            for item in arr {
+                values.push(toml_to_loft_value(item)?);
+            }
+            Ok(Value::Array(values))
+        }
+        ::toml::Value::Table(table) => {
+            let mut fields = HashMap::new();
+            for (key, value) in table {
+                fields.insert(key, toml_to_loft_value(value)?);
+            }
+            Ok(Value::Struct {
+                name: "Object".to_string(),
+                fields,
+            })
+        }
+    }
+}
+
+/// Convert a loft value to TOML; `None` for void, which TOML cannot represent
+fn loft_value_to_toml(value: &Value) -> RuntimeResult<Option<::toml::Value>> {
+    let table = |entries: Vec<(String, &Value)>| -> RuntimeResult<Option<::toml::Value>> {
+        let mut table = ::toml::Table::new();
+        for (key, value) in entries {
+            // Void fields are left out, like a missing key
+            if let Some(value) = loft_value_to_toml(value)? {
+                table.insert(key, value);
+            }
+        }
+        Ok(Some(::toml::Value::Table(table)))
+    };
+
+    match value {
+        Value::Unit => Ok(None),
+        Value::Boolean(b) => Ok(Some(::toml::Value::Boolean(*b))),
+        Value::Number(n) => match n.fract().is_zero().then(|| n.to_i64()).flatten() {
+            Some(i) => Ok(Some(::toml::Value::Integer(i))),
+            None => n
+                .to_f64()
+                .map(|f| Some(::toml::Value::Float(f)))
+                .ok_or_else(|| RuntimeError::new("Failed to convert number to TOML")),
+        },
+        Value::String(s) => Ok(Some(::toml::Value::String(s.to_string()))),
+        Value::Array(arr) => {
+            let mut toml_arr = Vec::new();
+            for item in arr {
+                toml_arr.push(
+                    loft_value_to_toml(item)?.ok_or_else(|| {
+                        RuntimeError::new("TOML arrays cannot contain void values")
+                    })?,
+                );
+            }
+            Ok(Some(::toml::Value::Array(toml_arr)))
+        }
+        Value::Struct { fields, .. } => table(fields.iter().map(|(k, v)| (k.clone(), v)).collect()),
+        Value::Map(entries) => table(entries.iter().map(|(k, v)| (k.to_string(), v)).collect()),
+        _ => Err(RuntimeError::new(format!(
+            "Cannot convert {:?} to TOML",
+            value
+        ))),
+    }
+}
+
+pub fn create_toml_builtin() -> BuiltinStruct {
+    let mut toml = BuiltinStruct::new("toml");
+
+    toml.add_method("parse", toml_parse as BuiltinMethod);
+    toml.add_method("stringify", toml_stringify as BuiltinMethod);
+
+    toml
+}
+
+// Register the builtin automatically
+crate::submit_builtin!("toml", create_toml_builtin);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Value {
+        toml_parse(&Value::Unit, &[Value::String(source.into())]).unwrap()
+    }
+
+    fn field<'a>(value: &'a Value, name: &str) -> &'a Value {
+        match value {
+            Value::Struct { fields, .. } => &fields[name],
+            _ => panic!("Expected object"),
+        }
+    }
+
+    #[test]
+    fn test_toml_parse_tables() {
+        let doc = parse(
+            "[package]\nname = \"demo\"\nversion = \"1.0.0\"\n\n[dependencies]\nserde = { version = \"1\", optional = true }\nweights = [1, 2.5]\n",
+        );
+
+        let package = field(&doc, "package");
+        assert_eq!(field(package, "name"), &Value::String("demo".into()));
+        let serde = field(field(&doc, "dependencies"), "serde");
+        assert_eq!(field(serde, "optional"), &Value::Boolean(true));
+        assert_eq!(
+            field(field(&doc, "dependencies"), "weights"),
+            &Value::Array(vec![
+                Value::Number(Decimal::from(1)),
+                Value::Number(Decimal::new(25, 1)),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_toml_roundtrip() {
+        let doc = parse("title = \"x\"\ncount = 3\nratio = 0.5\n\n[owner]\nname = \"Ada\"\n");
+        let out = match toml_stringify(&Value::Unit, std::slice::from_ref(&doc)).unwrap() {
+            Value::String(s) => s,
+            _ => panic!("Expected string"),
+        };
+
+        assert!(out.contains("count = 3\n"));
+        assert!(out.contains("[owner]"));
+        assert_eq!(parse(&out), doc);
+
+        assert!(toml_stringify(&Value::Unit, &[Value::Number(Decimal::from(1))]).is_err());
+    }
+}
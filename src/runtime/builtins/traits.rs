@@ -48,6 +48,17 @@ pub fn builtin_div(_interpreter: &mut Interpreter, this: &Value, args: &[Value])
     this.div(&args[0])
 }
 
+/// rem method - remainder of dividing two values (wraps Rem trait)
+#[loft_builtin(value.rem)]
+pub fn builtin_rem(_interpreter: &mut Interpreter, this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    if args.len() != 1 {
+        return Err(RuntimeError::new("rem() requires exactly one argument"));
+    }
+
+    use crate::runtime::traits::Rem;
+    this.rem(&args[0])
+}
+
 /// gt method - greater than comparison (wraps Ord trait)
 #[loft_builtin(value.gt)]
 pub fn builtin_gt(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
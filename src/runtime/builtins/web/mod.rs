@@ -1,12 +1,17 @@
 use crate::runtime::builtin::{BuiltinMethod, BuiltinStruct};
-use crate::runtime::permission_context::check_net_permission;
+use crate::runtime::builtins::json::loft_value_to_json;
+use crate::runtime::limits;
+use crate::runtime::permission_context::{check_net_permission, check_write_permission};
 use crate::runtime::value::Value;
+use crate::runtime::vfs;
 use crate::runtime::{RuntimeError, RuntimeResult};
 use loft_builtin_macros::loft_builtin;
+use reqwest::blocking::multipart;
 use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::Decimal;
 use serde_json;
 use std::collections::HashMap;
+use std::time::Duration;
 
 /// Buffer type for representing binary data
 #[derive(Clone, Debug, PartialEq)]
@@ -87,31 +92,57 @@ impl HttpMethod {
 #[derive(Clone, Debug)]
 pub struct HttpResponse {
     pub status: u16,
+    /// Where the response came from, after any redirects
+    pub url: String,
     pub headers: HashMap<String, String>,
     pub body: Buffer,
 }
 
 impl HttpResponse {
-    pub fn new(status: u16, headers: HashMap<String, String>, body: Buffer) -> Self {
+    pub fn new(status: u16, url: String, headers: HashMap<String, String>, body: Buffer) -> Self {
         Self {
             status,
+            url,
             headers,
             body,
         }
     }
 }
 
+/// What a request sends as its body
+#[derive(Clone, Debug, PartialEq)]
+pub enum RequestBody {
+    /// Bytes sent as they are
+    Raw(Buffer),
+    /// Fields encoded as `application/x-www-form-urlencoded`
+    Form(Vec<(String, String)>),
+    /// Fields sent as `multipart/form-data`, strings as text and `Bytes` as a
+    /// file named after its field
+    Multipart(Vec<(String, Value)>),
+}
+
 /// HTTP Request Builder structure
 #[derive(Clone, Debug)]
 pub struct RequestBuilder {
     pub url: String,
     pub method: HttpMethod,
     pub headers: HashMap<String, String>,
-    pub body: Option<Buffer>,
+    pub body: Option<RequestBody>,
     pub timeout: Option<u64>,
     pub follow_redirects: bool,
+    /// How many redirects are followed before giving up
+    pub max_redirects: u64,
+    /// How many more times a failed request is sent
+    pub retries: u64,
+    /// Milliseconds before the first retry, doubled for each one after it
+    pub retry_delay: u64,
 }
 
+/// Milliseconds a request may take when its builder doesn't set a timeout
+const DEFAULT_TIMEOUT: u64 = 30_000;
+const DEFAULT_MAX_REDIRECTS: u64 = 10;
+const DEFAULT_RETRY_DELAY: u64 = 200;
+
 impl RequestBuilder {
     pub fn new(url: String) -> Self {
         Self {
@@ -121,6 +152,9 @@ impl RequestBuilder {
             body: None,
             timeout: None,
             follow_redirects: true,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            retries: 0,
+            retry_delay: DEFAULT_RETRY_DELAY,
         }
     }
 }
@@ -178,134 +212,284 @@ impl TryFrom<&Value> for Buffer {
                     Err(RuntimeError::new("Buffer struct must have a 'data' field"))
                 }
             }
+            Value::Bytes(data) => Ok(Buffer::new(data.to_vec())),
             Value::String(s) => Ok(Buffer::from_string(s)),
             _ => Err(RuntimeError::new("Cannot convert value to Buffer")),
         }
     }
 }
 
+fn headers_value(headers: HashMap<String, String>) -> Value {
+    Value::Struct {
+        name: "Headers".to_string(),
+        fields: headers
+            .into_iter()
+            .map(|(key, value)| (key, Value::String(value.into())))
+            .collect(),
+    }
+}
+
+fn number(n: u64) -> Value {
+    Value::Number(Decimal::from(n))
+}
+
 // Convert HttpResponse to Value
 impl From<HttpResponse> for Value {
     fn from(response: HttpResponse) -> Self {
-        let mut fields = HashMap::new();
-        fields.insert(
-            "status".to_string(),
-            Value::Number(Decimal::from(response.status)),
+        let mut handle = BuiltinStruct::new("Response");
+        handle.add_method("json", response_json as BuiltinMethod);
+        handle.add_method("text", response_text as BuiltinMethod);
+        handle.add_method("bytes", response_bytes as BuiltinMethod);
+
+        handle.add_field("status", number(response.status.into()));
+        handle.add_field("ok", Value::Boolean((200..300).contains(&response.status)));
+        handle.add_field("url", Value::String(response.url.into()));
+        handle.add_field("headers", headers_value(response.headers));
+        // Body as a Promise<Bytes>
+        handle.add_field(
+            "body",
+            Value::Promise(Box::new(Value::Bytes(response.body.data.into()))),
         );
-
-        // Convert headers to Value::Struct
-        let mut header_fields = HashMap::new();
-        for (key, value) in response.headers {
-            header_fields.insert(key, Value::String(value));
-        }
-        fields.insert(
-            "headers".to_string(),
-            Value::Struct {
-                name: "Headers".to_string(),
-                fields: header_fields,
-            },
-        );
-
-        // Body as a Promise<Buffer>
-        fields.insert(
-            "body".to_string(),
-            Value::Promise(Box::new(response.body.into())),
-        );
-
-        Value::Struct {
-            name: "Response".to_string(),
-            fields,
-        }
+        Value::Builtin(handle)
     }
 }
 
-// Convert RequestBuilder to Value
+// Convert RequestBuilder to Value. Its fields would hide the methods setting
+// them, so everything but the URL is kept in `options`
 impl From<RequestBuilder> for Value {
     fn from(builder: RequestBuilder) -> Self {
-        let mut fields = HashMap::new();
-        fields.insert("url".to_string(), Value::String(builder.url));
-        fields.insert(
+        let mut options = HashMap::new();
+        options.insert(
             "method".to_string(),
-            Value::String(format!("{:?}", builder.method)),
-        );
-
-        let mut header_fields = HashMap::new();
-        for (key, value) in builder.headers {
-            header_fields.insert(key, Value::String(value));
-        }
-        fields.insert(
-            "headers".to_string(),
-            Value::Struct {
-                name: "Headers".to_string(),
-                fields: header_fields,
-            },
+            Value::String(format!("{:?}", builder.method).into()),
         );
+        options.insert("headers".to_string(), headers_value(builder.headers));
 
-        if let Some(body) = builder.body {
-            fields.insert("body".to_string(), body.into());
+        match builder.body {
+            Some(RequestBody::Raw(buffer)) => {
+                options.insert("body".to_string(), Value::Bytes(buffer.data.into()));
+            }
+            Some(RequestBody::Form(fields)) => {
+                let fields = fields
+                    .into_iter()
+                    .map(|(name, value)| (Value::String(name.into()), Value::String(value.into())))
+                    .collect();
+                options.insert("form".to_string(), Value::Map(fields));
+            }
+            Some(RequestBody::Multipart(parts)) => {
+                let parts = parts
+                    .into_iter()
+                    .map(|(name, value)| (Value::String(name.into()), value))
+                    .collect();
+                options.insert("multipart".to_string(), Value::Map(parts));
+            }
+            None => {}
         }
 
         if let Some(timeout) = builder.timeout {
-            fields.insert("timeout".to_string(), Value::Number(Decimal::from(timeout)));
+            options.insert("timeout".to_string(), number(timeout));
         }
 
-        fields.insert(
+        options.insert(
             "followRedirects".to_string(),
             Value::Boolean(builder.follow_redirects),
         );
+        options.insert("maxRedirects".to_string(), number(builder.max_redirects));
+        options.insert("retries".to_string(), number(builder.retries));
+        options.insert("retryDelay".to_string(), number(builder.retry_delay));
+
+        let mut handle = BuiltinStruct::new("RequestBuilder");
+        handle.add_method("method", request_method as BuiltinMethod);
+        handle.add_method("header", request_header as BuiltinMethod);
+        handle.add_method("headers", request_headers as BuiltinMethod);
+        handle.add_method("body", request_body as BuiltinMethod);
+        handle.add_method("json", request_json as BuiltinMethod);
+        handle.add_method("form", request_form as BuiltinMethod);
+        handle.add_method("multipart", request_multipart as BuiltinMethod);
+        handle.add_method("timeout", request_timeout as BuiltinMethod);
+        handle.add_method("followRedirects", request_follow_redirects as BuiltinMethod);
+        handle.add_method("retry", request_retry as BuiltinMethod);
+        handle.add_method("send", request_send as BuiltinMethod);
+        handle.add_method("save", request_save as BuiltinMethod);
+
+        handle.add_field("url", Value::String(builder.url.into()));
+        handle.add_field(
+            "options",
+            Value::Struct {
+                name: "Object".to_string(),
+                fields: options,
+            },
+        );
+        Value::Builtin(handle)
+    }
+}
 
-        Value::Struct {
-            name: "RequestBuilder".to_string(),
-            fields,
+impl TryFrom<&Value> for RequestBuilder {
+    type Error = RuntimeError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        let (url, options) = match value {
+            Value::Builtin(handle) if handle.name == "RequestBuilder" => {
+                match (handle.fields.get("url"), handle.fields.get("options")) {
+                    (Some(Value::String(url)), Some(Value::Struct { fields, .. })) => (url, fields),
+                    _ => return Err(RuntimeError::new("RequestBuilder missing URL or options")),
+                }
+            }
+            _ => return Err(RuntimeError::new("Cannot convert value to RequestBuilder")),
+        };
+        let number = |name: &str| match options.get(name) {
+            Some(Value::Number(n)) => n.to_u64(),
+            _ => None,
+        };
+
+        let mut builder = RequestBuilder::new(url.to_string());
+        if let Some(Value::String(method)) = options.get("method") {
+            builder.method = HttpMethod::from_string(method)?;
         }
+        if let Some(Value::Struct { fields, .. }) = options.get("headers") {
+            for (key, value) in fields {
+                if let Value::String(value) = value {
+                    builder.headers.insert(key.clone(), value.to_string());
+                }
+            }
+        }
+
+        builder.body = if let Some(body) = options.get("body") {
+            Some(RequestBody::Raw(Buffer::try_from(body)?))
+        } else if let Some(form) = options.get("form") {
+            Some(RequestBody::Form(form_fields(form, "form")?))
+        } else if let Some(parts) = options.get("multipart") {
+            Some(RequestBody::Multipart(multipart_fields(parts)?))
+        } else {
+            None
+        };
+
+        builder.timeout = number("timeout");
+        if let Some(Value::Boolean(follow)) = options.get("followRedirects") {
+            builder.follow_redirects = *follow;
+        }
+        builder.max_redirects = number("maxRedirects").unwrap_or(DEFAULT_MAX_REDIRECTS);
+        builder.retries = number("retries").unwrap_or(0);
+        builder.retry_delay = number("retryDelay").unwrap_or(DEFAULT_RETRY_DELAY);
+        Ok(builder)
+    }
+}
+
+/// The entries of an object, in name order, or of a map with string keys
+fn entries(value: &Value, method: &str) -> RuntimeResult<Vec<(String, Value)>> {
+    match value {
+        Value::Struct { fields, .. } => {
+            let mut entries: Vec<_> = fields
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            Ok(entries)
+        }
+        Value::Map(entries) => entries
+            .iter()
+            .map(|(name, value)| match name {
+                Value::String(name) => Ok((name.to_string(), value.clone())),
+                _ => Err(RuntimeError::new(format!(
+                    "{}() keys must be strings",
+                    method
+                ))),
+            })
+            .collect(),
+        _ => Err(RuntimeError::new(format!(
+            "{}() requires an object or a map",
+            method
+        ))),
+    }
+}
+
+/// A string, number or boolean as the text sent for it in a form or header
+fn field_text(value: &Value, method: &str) -> RuntimeResult<String> {
+    match value {
+        Value::String(s) => Ok(s.to_string()),
+        Value::Number(n) => Ok(n.normalize().to_string()),
+        Value::Boolean(b) => Ok(b.to_string()),
+        _ => Err(RuntimeError::new(format!(
+            "{}() values must be strings, numbers or booleans",
+            method
+        ))),
     }
 }
 
-/// Create a new HTTP request builder
-#[loft_builtin(web.request)]
+fn form_fields(value: &Value, method: &str) -> RuntimeResult<Vec<(String, String)>> {
+    entries(value, method)?
+        .into_iter()
+        .map(|(name, value)| Ok((name, field_text(&value, method)?)))
+        .collect()
+}
+
+fn multipart_fields(value: &Value) -> RuntimeResult<Vec<(String, Value)>> {
+    entries(value, "multipart")?
+        .into_iter()
+        .map(|(name, value)| match value {
+            Value::Bytes(_) => Ok((name, value)),
+            _ => Ok((name, Value::String(field_text(&value, "multipart")?.into()))),
+        })
+        .collect()
+}
+
+fn request_builder(this: &Value, method: &str) -> RuntimeResult<RequestBuilder> {
+    match this {
+        Value::Builtin(handle) if handle.name == "RequestBuilder" => RequestBuilder::try_from(this),
+        _ => Err(RuntimeError::new(format!(
+            "{}() can only be called on RequestBuilder",
+            method
+        ))),
+    }
+}
+
+/// A copy of the builder a method is called on, changed by `change`
+fn update(
+    this: &Value,
+    method: &str,
+    change: impl FnOnce(&mut RequestBuilder),
+) -> RuntimeResult<Value> {
+    let mut builder = request_builder(this, method)?;
+    change(&mut builder);
+    Ok(builder.into())
+}
+
+/// Create a new HTTP request builder for the given URL
+#[loft_builtin(web.request(url: str) -> RequestBuilder)]
 fn web_request(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new("web.request() requires a URL argument"));
     }
 
     let url = match &args[0] {
-        Value::String(s) => s.clone(),
+        Value::String(s) => s.to_string(),
         _ => return Err(RuntimeError::new("web.request() URL must be a string")),
     };
 
-    let builder = RequestBuilder::new(url);
+    let builder = RequestBuilder::new(url.to_string());
     Ok(builder.into())
 }
 
-/// Set HTTP method on request builder
-#[loft_builtin(web.method)]
-fn web_method(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+/// Set HTTP method (GET, POST, PUT, DELETE, PATCH, HEAD, OPTIONS, TRACE,
+/// CONNECT)
+#[loft_builtin(RequestBuilder.method(method: str) -> RequestBuilder)]
+fn request_method(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new("method() requires a method argument"));
     }
 
     let method_str = match &args[0] {
-        Value::String(s) => s.clone(),
+        Value::String(s) => s.to_string(),
         _ => return Err(RuntimeError::new("method() argument must be a string")),
     };
 
     let method = HttpMethod::from_string(&method_str)?;
-
-    if let Value::Struct { name, mut fields } = this.clone() {
-        if name == "RequestBuilder" {
-            fields.insert("method".to_string(), Value::String(format!("{:?}", method)));
-            return Ok(Value::Struct { name, fields });
-        }
-    }
-
-    Err(RuntimeError::new(
-        "method() can only be called on RequestBuilder",
-    ))
+    update(this, "method", |builder| builder.method = method)
 }
 
-/// Add header to request builder
-#[loft_builtin(web.header)]
-fn web_header(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+/// Add a header to the request
+#[loft_builtin(RequestBuilder.header(key: str, value: str) -> RequestBuilder)]
+fn request_header(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.len() < 2 {
         return Err(RuntimeError::new(
             "header() requires key and value arguments",
@@ -313,69 +497,99 @@ fn web_header(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     }
 
     let key = match &args[0] {
-        Value::String(s) => s.clone(),
+        Value::String(s) => s.to_string(),
         _ => return Err(RuntimeError::new("header() key must be a string")),
     };
 
     let value = match &args[1] {
-        Value::String(s) => s.clone(),
+        Value::String(s) => s.to_string(),
         _ => return Err(RuntimeError::new("header() value must be a string")),
     };
 
-    if let Value::Struct { name, mut fields } = this.clone() {
-        if name == "RequestBuilder" {
-            // Get existing headers or create new ones
-            let mut header_fields = HashMap::new();
-            if let Some(Value::Struct {
-                fields: existing_headers,
-                ..
-            }) = fields.get("headers")
-            {
-                header_fields = existing_headers.clone();
-            }
-
-            header_fields.insert(key, Value::String(value));
-            fields.insert(
-                "headers".to_string(),
-                Value::Struct {
-                    name: "Headers".to_string(),
-                    fields: header_fields,
-                },
-            );
+    update(this, "header", |builder| {
+        builder.headers.insert(key, value);
+    })
+}
 
-            return Ok(Value::Struct { name, fields });
-        }
-    }
+/// Add every header of an object or map, e.g.
+/// `headers(#{ "Accept": "application/json", "X-Retries": 3 })`
+#[loft_builtin(RequestBuilder.headers(headers: Object | Map<str, str>) -> RequestBuilder)]
+fn request_headers(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let headers = match args.first() {
+        Some(headers) => form_fields(headers, "headers")?,
+        None => return Err(RuntimeError::new("headers() requires an object or a map")),
+    };
 
-    Err(RuntimeError::new(
-        "header() can only be called on RequestBuilder",
-    ))
+    update(this, "headers", |builder| builder.headers.extend(headers))
 }
 
-/// Set request body
-#[loft_builtin(web.body)]
-fn web_body(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+/// Set the request body from Bytes, a string or a Buffer
+#[loft_builtin(RequestBuilder.body(data: Bytes | str | Buffer) -> RequestBuilder)]
+fn request_body(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new("body() requires a body argument"));
     }
 
     let body = Buffer::try_from(&args[0])?;
+    update(this, "body", |builder| {
+        builder.body = Some(RequestBody::Raw(body))
+    })
+}
+
+/// Send a value encoded as JSON, with a `content-type` of `application/json`
+/// unless a header already sets one
+#[loft_builtin(RequestBuilder.json(value: any) -> RequestBuilder)]
+fn request_json(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let json = match args.first() {
+        Some(value) => loft_value_to_json(value)?.to_string(),
+        None => return Err(RuntimeError::new("json() requires a value")),
+    };
 
-    if let Value::Struct { name, mut fields } = this.clone() {
-        if name == "RequestBuilder" {
-            fields.insert("body".to_string(), body.into());
-            return Ok(Value::Struct { name, fields });
+    update(this, "json", |builder| {
+        builder.body = Some(RequestBody::Raw(Buffer::from_string(&json)));
+        let has_content_type = builder
+            .headers
+            .keys()
+            .any(|key| key.eq_ignore_ascii_case("content-type"));
+        if !has_content_type {
+            builder
+                .headers
+                .insert("content-type".to_string(), "application/json".to_string());
         }
-    }
+    })
+}
+
+/// Send the fields of an object or map as a URL-encoded form
+#[loft_builtin(RequestBuilder.form(fields: Object | Map<str, str>) -> RequestBuilder)]
+fn request_form(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let fields = match args.first() {
+        Some(fields) => form_fields(fields, "form")?,
+        None => return Err(RuntimeError::new("form() requires an object or a map")),
+    };
 
-    Err(RuntimeError::new(
-        "body() can only be called on RequestBuilder",
-    ))
+    update(this, "form", |builder| {
+        builder.body = Some(RequestBody::Form(fields))
+    })
+}
+
+/// Send the fields of an object or map as `multipart/form-data`. Strings are
+/// sent as text and Bytes as a file named after its field, e.g.
+/// `multipart(#{ "title": "Report", "report.pdf": fs.read_bytes(path) })`
+#[loft_builtin(RequestBuilder.multipart(fields: Object | Map<str, str | Bytes>) -> RequestBuilder)]
+fn request_multipart(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let parts = match args.first() {
+        Some(parts) => multipart_fields(parts)?,
+        None => return Err(RuntimeError::new("multipart() requires an object or a map")),
+    };
+
+    update(this, "multipart", |builder| {
+        builder.body = Some(RequestBody::Multipart(parts))
+    })
 }
 
 /// Set request timeout in milliseconds
-#[loft_builtin(web.timeout)]
-fn web_timeout(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+#[loft_builtin(RequestBuilder.timeout(milliseconds: num) -> RequestBuilder)]
+fn request_timeout(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new("timeout() requires a timeout argument"));
     }
@@ -387,24 +601,15 @@ fn web_timeout(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
         _ => return Err(RuntimeError::new("timeout() argument must be a number")),
     };
 
-    if let Value::Struct { name, mut fields } = this.clone() {
-        if name == "RequestBuilder" {
-            fields.insert(
-                "timeout".to_string(),
-                Value::Number(Decimal::from(timeout_ms)),
-            );
-            return Ok(Value::Struct { name, fields });
-        }
-    }
-
-    Err(RuntimeError::new(
-        "timeout() can only be called on RequestBuilder",
-    ))
+    update(this, "timeout", |builder| {
+        builder.timeout = Some(timeout_ms)
+    })
 }
 
-/// Set whether to follow redirects
-#[loft_builtin(web.followRedirects)]
-fn web_follow_redirects(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+/// Set whether to follow HTTP redirects automatically, and optionally how
+/// many to follow before failing (10 by default)
+#[loft_builtin(RequestBuilder.followRedirects(follow: bool, limit?: num) -> RequestBuilder)]
+fn request_follow_redirects(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new(
             "followRedirects() requires a boolean argument",
@@ -420,162 +625,309 @@ fn web_follow_redirects(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
         }
     };
 
-    if let Value::Struct { name, mut fields } = this.clone() {
-        if name == "RequestBuilder" {
-            fields.insert("followRedirects".to_string(), Value::Boolean(follow));
-            return Ok(Value::Struct { name, fields });
+    let limit = match args.get(1) {
+        Some(Value::Number(n)) => Some(n.to_u64().ok_or_else(|| {
+            RuntimeError::new("followRedirects() limit must be a positive number")
+        })?),
+        Some(_) => {
+            return Err(RuntimeError::new(
+                "followRedirects() limit must be a number",
+            ))
         }
-    }
+        None => None,
+    };
+
+    update(this, "followRedirects", |builder| {
+        builder.follow_redirects = follow;
+        if let Some(limit) = limit {
+            builder.max_redirects = limit;
+        }
+    })
+}
+
+/// Send the request again when it can't connect, times out or gets a 429 or
+/// 5xx status, up to `times` more times. The first retry waits `delay`
+/// milliseconds (200 by default) and each one after it waits twice as long
+#[loft_builtin(RequestBuilder.retry(times: num, delay?: num) -> RequestBuilder)]
+fn request_retry(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let times = match args.first() {
+        Some(Value::Number(n)) => n
+            .to_u64()
+            .ok_or_else(|| RuntimeError::new("retry() times must be a positive number"))?,
+        _ => return Err(RuntimeError::new("retry() requires a number of times")),
+    };
+
+    let delay = match args.get(1) {
+        Some(Value::Number(n)) => Some(
+            n.to_u64()
+                .ok_or_else(|| RuntimeError::new("retry() delay must be a positive number"))?,
+        ),
+        Some(_) => return Err(RuntimeError::new("retry() delay must be a number")),
+        None => None,
+    };
 
-    Err(RuntimeError::new(
-        "followRedirects() can only be called on RequestBuilder",
-    ))
+    update(this, "retry", |builder| {
+        builder.retries = times;
+        if let Some(delay) = delay {
+            builder.retry_delay = delay;
+        }
+    })
 }
 
-/// Send HTTP request and return response
-#[loft_builtin(web.send)]
-fn web_send(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
-    if let Value::Struct { name, fields } = this {
-        if name == "RequestBuilder" {
-            // Extract request details
-            let url = if let Some(Value::String(url)) = fields.get("url") {
-                url.clone()
-            } else {
-                return Err(RuntimeError::new("RequestBuilder missing URL"));
-            };
+/// Execute the HTTP request and return a Promise<Response>
+#[loft_builtin(RequestBuilder.send() -> Promise<Response>)]
+fn request_send(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let builder = request_builder(this, "send")?;
+    send(&builder, "send()")
+}
 
-            // Extract host from URL for permission check
-            let host = url::Url::parse(&url)
-                .map(|u| u.host_str().unwrap_or("unknown").to_string())
-                .unwrap_or_else(|_| url.clone());
+/// Execute the HTTP request and stream the response body into a file rather
+/// than memory, leaving the response's body empty. The file is written
+/// whatever the status, so check `ok` before trusting its contents
+#[loft_builtin(RequestBuilder.save(path: str) -> Promise<Response>)]
+fn request_save(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let path = match args.first() {
+        Some(Value::String(path)) => path.to_string(),
+        _ => return Err(RuntimeError::new("save() requires a path argument")),
+    };
+    let builder = request_builder(this, "save")?;
+    check_write_permission(&path, Some("save()")).map_err(RuntimeError::new)?;
+
+    let mut response = execute(&builder, "save()")?;
+    let head = http_response(&response, Buffer::new(Vec::new()));
+    let write_error =
+        |e: std::io::Error| RuntimeError::new(format!("Failed to write file '{}': {}", path, e));
+    if vfs::is_mounted() {
+        let body = response
+            .bytes()
+            .map_err(|e| RuntimeError::new(format!("Failed to read response body: {}", e)))?;
+        vfs::write(&path, body).map_err(write_error)?;
+    } else {
+        let mut file = std::fs::File::create(&path).map_err(write_error)?;
+        response
+            .copy_to(&mut file)
+            .map_err(|e| RuntimeError::new(format!("Failed to read response body: {}", e)))?;
+    }
 
-            // Check network permission
-            check_net_permission(&host, Some("web.send()")).map_err(RuntimeError::new)?;
+    Ok(Value::Promise(Box::new(head.into())))
+}
 
-            let method_str = if let Some(Value::String(method)) = fields.get("method") {
-                method.clone()
-            } else {
-                "GET".to_string()
-            };
-
-            let method = HttpMethod::from_string(&method_str)?;
-
-            // Build reqwest client and request
-            let client = reqwest::blocking::Client::builder()
-                .timeout(std::time::Duration::from_millis(30000)) // Default 30s timeout
-                .build()
-                .map_err(|e| RuntimeError::new(format!("Failed to create HTTP client: {}", e)))?;
-
-            let mut request = client.request(method.to_reqwest_method(), &url);
-
-            // Add headers
-            if let Some(Value::Struct {
-                fields: header_fields,
-                ..
-            }) = fields.get("headers")
-            {
-                for (key, value) in header_fields {
-                    if let Value::String(header_value) = value {
-                        request = request.header(key, header_value);
-                    }
-                }
-            }
+/// Where a URL connects to, as net permissions name it
+fn net_target(url: &reqwest::Url) -> String {
+    let host = url.host_str().unwrap_or("unknown");
+    match url.port_or_known_default() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    }
+}
 
-            // Add body
-            if let Some(body_value) = fields.get("body") {
-                let buffer = Buffer::try_from(body_value)?;
-                request = request.body(buffer.data);
-            }
+/// Headers only sent to the host the request was made for
+fn is_credential(header: &str) -> bool {
+    ["authorization", "cookie", "proxy-authorization"]
+        .iter()
+        .any(|name| header.eq_ignore_ascii_case(name))
+}
 
-            // Set timeout
-            if let Some(Value::Number(timeout)) = fields.get("timeout") {
-                if let Some(timeout_ms) = timeout.to_u64() {
-                    request = request.timeout(std::time::Duration::from_millis(timeout_ms));
-                }
+fn multipart_form(parts: &[(String, Value)]) -> RuntimeResult<multipart::Form> {
+    let mut form = multipart::Form::new();
+    for (name, value) in parts {
+        form = match value {
+            Value::Bytes(data) => form.part(
+                name.clone(),
+                multipart::Part::bytes(data.to_vec()).file_name(name.clone()),
+            ),
+            Value::String(text) => form.text(name.clone(), text.to_string()),
+            _ => {
+                return Err(RuntimeError::new(
+                    "multipart() values must be strings or Bytes",
+                ))
             }
+        };
+    }
+    Ok(form)
+}
 
-            // Execute request
-            let response = request
-                .send()
-                .map_err(|e| RuntimeError::new(format!("HTTP request failed: {}", e)))?;
+/// Send a request to one URL, and again while it fails and retries are left
+fn send_with_retries(
+    client: &reqwest::blocking::Client,
+    builder: &RequestBuilder,
+    url: &reqwest::Url,
+    method: &reqwest::Method,
+    body: Option<&RequestBody>,
+    with_credentials: bool,
+) -> RuntimeResult<reqwest::blocking::Response> {
+    let mut attempt = 0;
+    loop {
+        let mut request = client.request(method.clone(), url.clone());
+        for (key, value) in &builder.headers {
+            if with_credentials || !is_credential(key) {
+                request = request.header(key, value);
+            }
+        }
+        request = match body {
+            Some(RequestBody::Raw(buffer)) => request.body(buffer.data.clone()),
+            Some(RequestBody::Form(fields)) => request.form(fields),
+            Some(RequestBody::Multipart(parts)) => request.multipart(multipart_form(parts)?),
+            None => request,
+        };
+
+        let result = request.send();
+        // A request cut short by the program's deadline fails with the limit
+        if result.is_err() {
+            limits::check_deadline().map_err(RuntimeError::new)?;
+        }
+        let failed = match &result {
+            Ok(response) => {
+                response.status().is_server_error()
+                    || response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS
+            }
+            Err(e) => !e.is_builder(),
+        };
+        if !failed || attempt >= builder.retries {
+            return result.map_err(|e| RuntimeError::new(format!("HTTP request failed: {}", e)));
+        }
 
-            // Extract response data
-            let status = response.status().as_u16();
+        let delay = Duration::from_millis(builder.retry_delay.saturating_mul(1 << attempt.min(16)));
+        std::thread::sleep(limits::blocking_timeout(Some(delay)).unwrap_or(delay));
+        limits::check_deadline().map_err(RuntimeError::new)?;
+        attempt += 1;
+    }
+}
 
-            let mut headers = HashMap::new();
-            for (key, value) in response.headers() {
-                if let Ok(value_str) = value.to_str() {
-                    headers.insert(key.to_string(), value_str.to_string());
-                }
-            }
+/// Send a request and return the response once its status and headers have
+/// arrived. Redirects are followed here rather than by reqwest so that every
+/// host the request goes to is checked against the net permissions
+fn execute(builder: &RequestBuilder, context: &str) -> RuntimeResult<reqwest::blocking::Response> {
+    let mut url = reqwest::Url::parse(&builder.url)
+        .map_err(|e| RuntimeError::new(format!("Invalid URL '{}': {}", builder.url, e)))?;
+    let host = url.host_str().map(str::to_string);
+    let mut method = builder.method.to_reqwest_method();
+    let mut body = builder.body.as_ref();
+
+    let timeout = Duration::from_millis(builder.timeout.unwrap_or(DEFAULT_TIMEOUT));
+    let client = reqwest::blocking::Client::builder()
+        .timeout(limits::blocking_timeout(Some(timeout)).unwrap_or(timeout))
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .map_err(|e| RuntimeError::new(format!("Failed to create HTTP client: {}", e)))?;
+
+    let mut redirects = 0;
+    loop {
+        check_net_permission(&net_target(&url), Some(context)).map_err(RuntimeError::new)?;
+
+        let same_host = url.host_str() == host.as_deref();
+        let response = send_with_retries(&client, builder, &url, &method, body, same_host)?;
+
+        let status = response.status();
+        let location = response
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|location| location.to_str().ok());
+        let location = match location {
+            Some(location) if builder.follow_redirects && status.is_redirection() => location,
+            _ => return Ok(response),
+        };
+        if redirects >= builder.max_redirects {
+            return Err(RuntimeError::new(format!(
+                "HTTP request failed: too many redirects (more than {})",
+                builder.max_redirects
+            )));
+        }
 
-            let body_bytes = response
-                .bytes()
-                .map_err(|e| RuntimeError::new(format!("Failed to read response body: {}", e)))?;
-            let body = Buffer::new(body_bytes.to_vec());
+        url = url
+            .join(location)
+            .map_err(|e| RuntimeError::new(format!("Invalid redirect to '{}': {}", location, e)))?;
+        // Like browsers, follow a 303, or a 301 or 302 after a POST, with a GET
+        if status == reqwest::StatusCode::SEE_OTHER
+            || (matches!(status.as_u16(), 301 | 302) && method == reqwest::Method::POST)
+        {
+            method = reqwest::Method::GET;
+            body = None;
+        }
+        redirects += 1;
+    }
+}
 
-            let http_response = HttpResponse::new(status, headers, body);
-            return Ok(Value::Promise(Box::new(http_response.into())));
+/// The status, final URL and headers of a response, with a body
+fn http_response(response: &reqwest::blocking::Response, body: Buffer) -> HttpResponse {
+    let mut headers = HashMap::new();
+    for (key, value) in response.headers() {
+        if let Ok(value_str) = value.to_str() {
+            headers.insert(key.to_string(), value_str.to_string());
         }
     }
+    HttpResponse::new(
+        response.status().as_u16(),
+        response.url().to_string(),
+        headers,
+        body,
+    )
+}
 
-    Err(RuntimeError::new(
-        "send() can only be called on RequestBuilder",
-    ))
+/// Send a request and read the whole response into a Promise<Response>
+fn send(builder: &RequestBuilder, context: &str) -> RuntimeResult<Value> {
+    let response = execute(builder, context)?;
+    let head = http_response(&response, Buffer::new(Vec::new()));
+    let body_bytes = response
+        .bytes()
+        .map_err(|e| RuntimeError::new(format!("Failed to read response body: {}", e)))?;
+
+    let http_response = HttpResponse {
+        body: Buffer::new(body_bytes.to_vec()),
+        ..head
+    };
+    Ok(Value::Promise(Box::new(http_response.into())))
 }
 
-/// Parse response body as JSON
-#[loft_builtin(web.json)]
-fn web_json(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
-    if let Value::Struct { name, fields } = this {
-        if name == "Response" {
-            if let Some(Value::Promise(body_promise)) = fields.get("body") {
-                if let Value::Struct {
-                    name: buffer_name,
-                    fields: _buffer_fields,
-                } = body_promise.as_ref()
-                {
-                    if buffer_name == "Buffer" {
-                        let buffer = Buffer::try_from(body_promise.as_ref())?;
-                        let json_str = buffer.to_string().map_err(|e| {
-                            RuntimeError::new(format!("Invalid UTF-8 in response body: {}", e))
-                        })?;
-
-                        let json_value: serde_json::Value = serde_json::from_str(&json_str)
-                            .map_err(|e| RuntimeError::new(format!("Invalid JSON: {}", e)))?;
-
-                        let loft_value = json_to_loft_value(json_value)?;
-                        return Ok(Value::Promise(Box::new(loft_value)));
-                    }
-                }
-            }
-        }
+/// The body of the response a method is called on
+fn response_body(this: &Value, method: &str) -> RuntimeResult<Buffer> {
+    match this {
+        Value::Builtin(handle) if handle.name == "Response" => match handle.fields.get("body") {
+            Some(Value::Promise(body)) => Buffer::try_from(body.as_ref()),
+            _ => Err(RuntimeError::new("Response missing body")),
+        },
+        _ => Err(RuntimeError::new(format!(
+            "{}() can only be called on Response",
+            method
+        ))),
     }
+}
 
-    Err(RuntimeError::new("json() can only be called on Response"))
+/// Parse response body as JSON
+#[loft_builtin(Response.json() -> Promise<Object>)]
+fn response_json(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let buffer = response_body(this, "json")?;
+    let json_str = buffer
+        .to_string()
+        .map_err(|e| RuntimeError::new(format!("Invalid UTF-8 in response body: {}", e)))?;
+
+    let json_value: serde_json::Value = serde_json::from_str(&json_str)
+        .map_err(|e| RuntimeError::new(format!("Invalid JSON: {}", e)))?;
+
+    let loft_value = json_to_loft_value(json_value)?;
+    Ok(Value::Promise(Box::new(loft_value)))
 }
 
-/// Parse response body as text
-#[loft_builtin(web.text)]
-fn web_text(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
-    if let Value::Struct { name, fields } = this {
-        if name == "Response" {
-            if let Some(Value::Promise(body_promise)) = fields.get("body") {
-                let buffer = Buffer::try_from(body_promise.as_ref())?;
-                let text = buffer.to_string().map_err(|e| {
-                    RuntimeError::new(format!("Invalid UTF-8 in response body: {}", e))
-                })?;
+/// Get response body as text
+#[loft_builtin(Response.text() -> Promise<str>)]
+fn response_text(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let buffer = response_body(this, "text")?;
+    let text = buffer
+        .to_string()
+        .map_err(|e| RuntimeError::new(format!("Invalid UTF-8 in response body: {}", e)))?;
 
-                return Ok(Value::Promise(Box::new(Value::String(text))));
-            }
-        }
-    }
+    Ok(Value::Promise(Box::new(Value::String(text.into()))))
+}
 
-    Err(RuntimeError::new("text() can only be called on Response"))
+/// Get response body as Bytes
+#[loft_builtin(Response.bytes() -> Promise<Bytes>)]
+fn response_bytes(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let buffer = response_body(this, "bytes")?;
+    Ok(Value::Promise(Box::new(Value::Bytes(buffer.data.into()))))
 }
 
-/// Create a Buffer from string
-#[loft_builtin(web.buffer)]
+/// Create a Buffer from string or array of bytes (0-255)
+#[loft_builtin(web.buffer(data: str | Array<num>) -> Buffer)]
 fn web_buffer(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Ok(Buffer::new(Vec::new()).into());
@@ -622,7 +974,7 @@ fn json_to_loft_value(json: serde_json::Value) -> RuntimeResult<Value> {
                 Err(RuntimeError::new("Invalid JSON number"))
             }
         }
-        serde_json::Value::String(s) => Ok(Value::String(s)),
+        serde_json::Value::String(s) => Ok(Value::String(s.into())),
         serde_json::Value::Array(arr) => {
             let mut values = Vec::new();
             for item in arr {
@@ -643,108 +995,92 @@ fn json_to_loft_value(json: serde_json::Value) -> RuntimeResult<Value> {
     }
 }
 
-/// GET request shorthand
-#[loft_builtin(web.get)]
+/// Perform a GET request to the specified URL
+#[loft_builtin(web.get(url: str) -> Promise<Response>)]
 fn web_get(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new("web.get() requires a URL argument"));
     }
 
     let url = match &args[0] {
-        Value::String(s) => s.clone(),
+        Value::String(s) => s.to_string(),
         _ => return Err(RuntimeError::new("web.get() URL must be a string")),
     };
 
-    let builder = RequestBuilder::new(url);
-    let builder_value: Value = builder.into();
-
-    // Call send() on the builder
-    web_send(&builder_value, &[])
+    let builder = RequestBuilder::new(url.to_string());
+    send(&builder, "web.get()")
 }
 
-/// POST request shorthand with optional body
-#[loft_builtin(web.post)]
+/// Perform a POST request with optional body
+#[loft_builtin(web.post(url: str, body?: Bytes | str | Buffer) -> Promise<Response>)]
 fn web_post(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new("web.post() requires a URL argument"));
     }
 
     let url = match &args[0] {
-        Value::String(s) => s.clone(),
+        Value::String(s) => s.to_string(),
         _ => return Err(RuntimeError::new("web.post() URL must be a string")),
     };
 
-    let mut builder = RequestBuilder::new(url);
+    let mut builder = RequestBuilder::new(url.to_string());
     builder.method = HttpMethod::POST;
 
     // Add body if provided
     if args.len() > 1 {
-        builder.body = Some(Buffer::try_from(&args[1])?);
+        builder.body = Some(RequestBody::Raw(Buffer::try_from(&args[1])?));
     }
 
-    let builder_value: Value = builder.into();
-    web_send(&builder_value, &[])
+    send(&builder, "web.post()")
 }
 
-/// PUT request shorthand with optional body
-#[loft_builtin(web.put)]
+/// Perform a PUT request with optional body
+#[loft_builtin(web.put(url: str, body?: Bytes | str | Buffer) -> Promise<Response>)]
 fn web_put(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new("web.put() requires a URL argument"));
     }
 
     let url = match &args[0] {
-        Value::String(s) => s.clone(),
+        Value::String(s) => s.to_string(),
         _ => return Err(RuntimeError::new("web.put() URL must be a string")),
     };
 
-    let mut builder = RequestBuilder::new(url);
+    let mut builder = RequestBuilder::new(url.to_string());
     builder.method = HttpMethod::PUT;
 
     // Add body if provided
     if args.len() > 1 {
-        builder.body = Some(Buffer::try_from(&args[1])?);
+        builder.body = Some(RequestBody::Raw(Buffer::try_from(&args[1])?));
     }
 
-    let builder_value: Value = builder.into();
-    web_send(&builder_value, &[])
+    send(&builder, "web.put()")
 }
 
-/// DELETE request shorthand
-#[loft_builtin(web.delete)]
+/// Perform a DELETE request to the specified URL
+#[loft_builtin(web.delete(url: str) -> Promise<Response>)]
 fn web_delete(_this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     if args.is_empty() {
         return Err(RuntimeError::new("web.delete() requires a URL argument"));
     }
 
     let url = match &args[0] {
-        Value::String(s) => s.clone(),
+        Value::String(s) => s.to_string(),
         _ => return Err(RuntimeError::new("web.delete() URL must be a string")),
     };
 
-    let mut builder = RequestBuilder::new(url);
+    let mut builder = RequestBuilder::new(url.to_string());
     builder.method = HttpMethod::DELETE;
 
-    let builder_value: Value = builder.into();
-    web_send(&builder_value, &[])
+    send(&builder, "web.delete()")
 }
 
 /// Create the Web builtin struct
 pub fn create_web_builtin() -> BuiltinStruct {
     let mut web = BuiltinStruct::new("web");
 
-    // Request building methods
+    // Request building, continued by the methods of the RequestBuilder
     web.add_method("request", web_request as BuiltinMethod);
-    web.add_method("method", web_method as BuiltinMethod);
-    web.add_method("header", web_header as BuiltinMethod);
-    web.add_method("body", web_body as BuiltinMethod);
-    web.add_method("timeout", web_timeout as BuiltinMethod);
-    web.add_method("followRedirects", web_follow_redirects as BuiltinMethod);
-    web.add_method("send", web_send as BuiltinMethod);
-
-    // Response processing methods
-    web.add_method("json", web_json as BuiltinMethod);
-    web.add_method("text", web_text as BuiltinMethod);
 
     // Utility methods
     web.add_method("buffer", web_buffer as BuiltinMethod);
@@ -764,6 +1100,78 @@ crate::submit_builtin!("web", create_web_builtin);
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::runtime::builtins::testing::{call, string};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    fn field(handle: &Value, name: &str) -> Value {
+        match handle {
+            Value::Builtin(b) => b.fields[name].clone(),
+            Value::Promise(value) => field(value, name),
+            _ => panic!("expected a handle"),
+        }
+    }
+
+    fn body_text(response: &Value) -> String {
+        let response = match response {
+            Value::Promise(response) => response,
+            _ => panic!("expected a promise"),
+        };
+        match call(response, "text", &[]).unwrap() {
+            Value::Promise(text) => match *text {
+                Value::String(text) => text.to_string(),
+                other => panic!("expected text, got {:?}", other),
+            },
+            other => panic!("expected a promise, got {:?}", other),
+        }
+    }
+
+    fn response(status: &str, headers: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            headers,
+            body.len(),
+            body
+        )
+    }
+
+    /// Answer each connection with the next response, and hand back the
+    /// requests that came in
+    fn serve(responses: Vec<String>) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut request = String::new();
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            length = value.trim().parse().unwrap();
+                        }
+                    }
+                    request.push_str(&line);
+                    if line == "\r\n" {
+                        break;
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                request.push_str(&String::from_utf8_lossy(&body));
+                reader.get_mut().write_all(response.as_bytes()).unwrap();
+                requests.push(request);
+            }
+            requests
+        });
+        (url, server)
+    }
 
     #[test]
     fn test_buffer_creation() {
@@ -806,7 +1214,7 @@ mod tests {
 
     #[test]
     fn test_web_buffer_function() {
-        let result = web_buffer(&Value::Unit, &[Value::String("Test".to_string())]);
+        let result = web_buffer(&Value::Unit, &[Value::String("Test".into())]);
         assert!(result.is_ok());
 
         let value = result.unwrap();
@@ -820,18 +1228,222 @@ mod tests {
 
     #[test]
     fn test_web_request_creation() {
-        let result = web_request(
-            &Value::Unit,
-            &[Value::String("https://example.com".to_string())],
-        );
+        let result = web_request(&Value::Unit, &[Value::String("https://example.com".into())]);
         assert!(result.is_ok());
 
         let value = result.unwrap();
         match value {
-            Value::Struct { name, .. } => {
-                assert_eq!(name, "RequestBuilder");
+            Value::Builtin(handle) => {
+                assert_eq!(handle.name, "RequestBuilder");
             }
-            _ => panic!("Expected RequestBuilder struct"),
+            _ => panic!("Expected RequestBuilder handle"),
         }
     }
+
+    #[test]
+    fn test_request_builder_options() {
+        let request = web_request(&Value::Unit, &[string("https://example.com")]).unwrap();
+        let request = call(&request, "method", &[string("post")]).unwrap();
+        let request = call(
+            &request,
+            "header",
+            &[string("Accept"), string("text/plain")],
+        )
+        .unwrap();
+        let headers = Value::Map(vec![(string("X-Count"), Value::Number(Decimal::from(3)))]);
+        let request = call(&request, "headers", &[headers]).unwrap();
+        let form = Value::Map(vec![(string("name"), string("ada"))]);
+        let request = call(&request, "form", &[form]).unwrap();
+        let request = call(&request, "timeout", &[Value::Number(Decimal::from(500))]).unwrap();
+        let request = call(&request, "followRedirects", &[Value::Boolean(false)]).unwrap();
+        let request = call(&request, "retry", &[Value::Number(Decimal::from(2))]).unwrap();
+
+        let builder = RequestBuilder::try_from(&request).unwrap();
+        assert_eq!(builder.method, HttpMethod::POST);
+        assert_eq!(builder.headers["Accept"], "text/plain");
+        assert_eq!(builder.headers["X-Count"], "3");
+        assert_eq!(
+            builder.body,
+            Some(RequestBody::Form(vec![(
+                "name".to_string(),
+                "ada".to_string()
+            )]))
+        );
+        assert_eq!(builder.timeout, Some(500));
+        assert!(!builder.follow_redirects);
+        assert_eq!(builder.retries, 2);
+        assert_eq!(builder.retry_delay, DEFAULT_RETRY_DELAY);
+
+        // A body replaces the form, and JSON sets its content type
+        let request = call(
+            &request,
+            "json",
+            &[Value::Array(vec![Value::Boolean(true)])],
+        )
+        .unwrap();
+        let builder = RequestBuilder::try_from(&request).unwrap();
+        assert_eq!(
+            builder.body,
+            Some(RequestBody::Raw(Buffer::from_string("[true]")))
+        );
+        assert_eq!(builder.headers["content-type"], "application/json");
+
+        assert!(call(&request, "form", &[string("name=ada")]).is_err());
+    }
+
+    #[test]
+    fn test_send_headers_and_form() {
+        let (url, server) = serve(vec![response(
+            "200 OK",
+            "Content-Type: application/json\r\n",
+            r#"{"id": 7}"#,
+        )]);
+
+        let request = web_request(&Value::Unit, &[string(&format!("{}/people", url))]).unwrap();
+        let request = call(&request, "method", &[string("POST")]).unwrap();
+        let request = call(&request, "header", &[string("X-Token"), string("abc")]).unwrap();
+        let form = Value::Map(vec![
+            (string("name"), string("ada lovelace")),
+            (string("age"), Value::Number(Decimal::from(36))),
+        ]);
+        let request = call(&request, "form", &[form]).unwrap();
+        let response = call(&request, "send", &[]).unwrap();
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with("POST /people HTTP/1.1"));
+        assert!(requests[0].contains("x-token: abc"));
+        assert!(requests[0].contains("application/x-www-form-urlencoded"));
+        assert!(requests[0].ends_with("name=ada+lovelace&age=36"));
+
+        assert_eq!(
+            field(&response, "status"),
+            Value::Number(Decimal::from(200))
+        );
+        assert_eq!(field(&response, "ok"), Value::Boolean(true));
+        let Value::Promise(response) = response else {
+            panic!("expected a promise");
+        };
+        let json = call(&response, "json", &[]).unwrap();
+        let Value::Promise(json) = json else {
+            panic!("expected a promise");
+        };
+        assert_eq!(
+            *json,
+            Value::Struct {
+                name: "Object".to_string(),
+                fields: HashMap::from([("id".to_string(), Value::Number(Decimal::from(7)))]),
+            }
+        );
+    }
+
+    #[test]
+    fn test_multipart() {
+        let (url, server) = serve(vec![response("200 OK", "", "")]);
+
+        let request = web_request(&Value::Unit, &[string(&url)]).unwrap();
+        let request = call(&request, "method", &[string("POST")]).unwrap();
+        let parts = Value::Map(vec![
+            (string("title"), string("notes")),
+            (string("notes.txt"), Value::Bytes(b"hello".to_vec().into())),
+        ]);
+        let request = call(&request, "multipart", &[parts]).unwrap();
+        call(&request, "send", &[]).unwrap();
+
+        let request = &server.join().unwrap()[0];
+        assert!(request.contains("multipart/form-data; boundary="));
+        assert!(request.contains("name=\"title\"\r\n\r\nnotes\r\n"));
+        assert!(request.contains("name=\"notes.txt\"; filename=\"notes.txt\""));
+        assert!(request.contains("\r\n\r\nhello\r\n"));
+    }
+
+    #[test]
+    fn test_redirects() {
+        let (url, server) = serve(vec![
+            response("302 Found", "Location: /next\r\n", ""),
+            response("200 OK", "", "done"),
+            response("302 Found", "Location: /next\r\n", ""),
+        ]);
+
+        let request = web_request(&Value::Unit, &[string(&format!("{}/start", url))]).unwrap();
+        let followed = call(&request, "send", &[]).unwrap();
+        assert_eq!(body_text(&followed), "done");
+        assert_eq!(field(&followed, "url"), string(&format!("{}/next", url)));
+
+        let request = call(&request, "followRedirects", &[Value::Boolean(false)]).unwrap();
+        let not_followed = call(&request, "send", &[]).unwrap();
+        assert_eq!(
+            field(&not_followed, "status"),
+            Value::Number(Decimal::from(302))
+        );
+
+        let requests = server.join().unwrap();
+        assert!(requests[1].starts_with("GET /next HTTP/1.1"));
+    }
+
+    #[test]
+    fn test_retries() {
+        let (url, server) = serve(vec![
+            response("503 Service Unavailable", "", ""),
+            response("429 Too Many Requests", "", ""),
+            response("200 OK", "", "finally"),
+            response("500 Internal Server Error", "", ""),
+        ]);
+
+        let request = web_request(&Value::Unit, &[string(&url)]).unwrap();
+        let retried = call(
+            &request,
+            "retry",
+            &[
+                Value::Number(Decimal::from(2)),
+                Value::Number(Decimal::from(1)),
+            ],
+        )
+        .unwrap();
+        let response = call(&retried, "send", &[]).unwrap();
+        assert_eq!(body_text(&response), "finally");
+
+        // Without retries the error status is the answer
+        let response = call(&request, "send", &[]).unwrap();
+        assert_eq!(
+            field(&response, "status"),
+            Value::Number(Decimal::from(500))
+        );
+        assert_eq!(field(&response, "ok"), Value::Boolean(false));
+        assert_eq!(server.join().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_save() {
+        let (url, server) = serve(vec![response("200 OK", "", "saved body")]);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("download.txt");
+        let path = path.to_str().unwrap();
+
+        let request = web_request(&Value::Unit, &[string(&url)]).unwrap();
+        let response = call(&request, "save", &[string(path)]).unwrap();
+        server.join().unwrap();
+
+        assert_eq!(
+            field(&response, "status"),
+            Value::Number(Decimal::from(200))
+        );
+        assert_eq!(body_text(&response), "");
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "saved body");
+    }
+
+    #[test]
+    fn test_permissions() {
+        use crate::runtime::permission_context::replace_permissions;
+        use crate::runtime::permissions::PermissionManager;
+
+        let request = web_request(&Value::Unit, &[string("http://127.0.0.1:1/")]).unwrap();
+        let previous = replace_permissions(Some(PermissionManager::deny_all()));
+        let sent = call(&request, "send", &[]);
+        let saved = call(&request, "save", &[string("download.txt")]);
+        replace_permissions(previous);
+
+        assert!(sent.unwrap_err().message.starts_with("Permission denied"));
+        assert!(saved.unwrap_err().message.starts_with("Permission denied"));
+        assert!(!std::path::Path::new("download.txt").exists());
+    }
 }
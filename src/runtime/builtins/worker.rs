@@ -0,0 +1,291 @@
+//! Workers started with `worker.spawn()`.
+//!
+//! Each worker runs its own interpreter on an OS thread, so CPU-bound work
+//! can use several cores. Workers share nothing with the script that started
+//! them: the two sides only talk by sending messages, which travel as JSON
+//! and arrive the way `json.parse(json.stringify(value))` would return them.
+//!
+//! Both sides hold an endpoint with `send`, `recv` and `try_recv`. The parent
+//! gets a `Worker` handle, which can also `join` the worker, while the worker
+//! gets a `WorkerPort` as the argument of its function or from `worker.parent()`.
+
+use super::json::{json_to_loft_value, loft_value_to_json};
+use crate::runtime::builtin::{BuiltinMethod, BuiltinStruct};
+use crate::runtime::builtins::handles::{self, Handles};
+use crate::runtime::limits;
+use crate::runtime::permission_context;
+use crate::runtime::value::Value;
+use crate::runtime::{RuntimeError, RuntimeResult};
+use loft_builtin_macros::loft_builtin;
+use rust_decimal::Decimal;
+use std::cell::Cell;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+type Message = serde_json::Value;
+
+/// One side of the channel pair between a worker and its parent
+struct Endpoint {
+    /// Taken once this side is closed, so the other side sees the end of messages
+    sender: Mutex<Option<Sender<Message>>>,
+    receiver: Mutex<Receiver<Message>>,
+    /// The worker's thread, on the parent's side until it is joined
+    thread: Mutex<Option<JoinHandle<RuntimeResult<Message>>>>,
+}
+
+static ENDPOINTS: Handles<Arc<Endpoint>> = Handles::new();
+/// The usual main thread stack, which the tree-walking interpreter needs for
+/// recursive scripts; spawned threads get far less by default
+const STACK_SIZE: usize = 8 * 1024 * 1024;
+
+thread_local! {
+    /// The endpoint of the worker running on this thread
+    static PARENT: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+fn register(sender: Sender<Message>, receiver: Receiver<Message>) -> u64 {
+    ENDPOINTS.insert(Arc::new(Endpoint {
+        sender: Mutex::new(Some(sender)),
+        receiver: Mutex::new(receiver),
+        thread: Mutex::new(None),
+    }))
+}
+
+/// Start a worker thread running `job`, which is given the worker's port
+/// Returns the parent's handle to the worker
+pub fn spawn(
+    job: impl FnOnce(Value) -> RuntimeResult<Value> + Send + 'static,
+) -> RuntimeResult<Value> {
+    let (to_worker, from_parent) = mpsc::channel();
+    let (to_parent, from_worker) = mpsc::channel();
+    let parent_id = register(to_worker, from_worker);
+    let worker_id = register(to_parent, from_parent);
+
+    let permissions = permission_context::with_permissions(|pm| pm.clone());
+    let budget = limits::current_budget();
+    let thread = std::thread::Builder::new()
+        .name(format!("loft-worker-thread-{}", parent_id))
+        .stack_size(STACK_SIZE)
+        .spawn(move || {
+            if let Some(permissions) = permissions {
+                permission_context::init_permissions(permissions);
+            }
+            limits::replace_budget(budget);
+            PARENT.with(|parent| parent.set(Some(worker_id)));
+            let result = job(port_handle(worker_id)).and_then(|value| {
+                loft_value_to_json(&value).map_err(|e| {
+                    RuntimeError::new(format!(
+                        "A worker can only return JSON-serializable values: {}",
+                        e.message
+                    ))
+                })
+            });
+            // Dropping the worker's endpoint tells the parent no more messages are coming
+            ENDPOINTS.remove(worker_id);
+            result
+        })
+        .map_err(|e| RuntimeError::new(format!("Failed to start worker: {}", e)))?;
+    if let Some(endpoint) = ENDPOINTS.get(parent_id) {
+        endpoint.thread.lock().unwrap().replace(thread);
+    }
+
+    let mut handle = endpoint_handle("Worker", parent_id);
+    handle.add_method("join", worker_join as BuiltinMethod);
+    Ok(Value::Builtin(handle))
+}
+
+fn endpoint_handle(name: &str, id: u64) -> BuiltinStruct {
+    let mut handle = BuiltinStruct::new(name);
+    handle.add_field("id", Value::Number(Decimal::from(id)));
+    handle.add_method("send", endpoint_send as BuiltinMethod);
+    handle.add_method("recv", endpoint_recv as BuiltinMethod);
+    handle.add_method("try_recv", endpoint_try_recv as BuiltinMethod);
+    handle.add_method("close", endpoint_close as BuiltinMethod);
+    handle
+}
+
+fn port_handle(id: u64) -> Value {
+    Value::Builtin(endpoint_handle("WorkerPort", id))
+}
+
+/// The endpoint behind a handle, or `None` once its worker has finished
+fn endpoint_for(this: &Value, method: &str) -> RuntimeResult<Option<Arc<Endpoint>>> {
+    let id = handles::handle_id(
+        this,
+        &["Worker", "WorkerPort"],
+        "id",
+        method,
+        "a worker or its port",
+    )?;
+    Ok(ENDPOINTS.get(id))
+}
+
+/// Send a JSON-serializable value to the other side
+#[loft_builtin(Worker.send(value: any))]
+fn endpoint_send(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let value = args
+        .first()
+        .ok_or_else(|| RuntimeError::new("send() requires a value"))?;
+    let message = loft_value_to_json(value).map_err(|e| {
+        RuntimeError::new(format!(
+            "send() requires a JSON-serializable value: {}",
+            e.message
+        ))
+    })?;
+
+    let closed = || RuntimeError::new("send(): the other side is no longer receiving");
+    let endpoint = endpoint_for(this, "send")?.ok_or_else(closed)?;
+    let sender = endpoint.sender.lock().unwrap();
+    sender
+        .as_ref()
+        .ok_or_else(|| RuntimeError::new("send(): this side has been closed"))?
+        .send(message)
+        .map_err(|_| closed())?;
+    Ok(Value::Unit)
+}
+
+/// Wait for the next message, or return unit once the other side has finished
+/// or closed and every message has been received
+#[loft_builtin(Worker.recv() -> any)]
+fn endpoint_recv(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let Some(endpoint) = endpoint_for(this, "recv")? else {
+        return Ok(Value::Unit);
+    };
+    let receiver = endpoint.receiver.lock().unwrap();
+    let message = loop {
+        match limits::blocking_timeout(None) {
+            Some(timeout) => match receiver.recv_timeout(timeout) {
+                Ok(message) => break Some(message),
+                Err(RecvTimeoutError::Disconnected) => break None,
+                Err(RecvTimeoutError::Timeout) => {
+                    limits::check_deadline().map_err(RuntimeError::new)?
+                }
+            },
+            None => break receiver.recv().ok(),
+        }
+    };
+    match message {
+        Some(message) => json_to_loft_value(message),
+        None => Ok(Value::Unit),
+    }
+}
+
+/// The next message if one is waiting, without blocking, or unit
+#[loft_builtin(Worker.try_recv() -> any)]
+fn endpoint_try_recv(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let Some(endpoint) = endpoint_for(this, "try_recv")? else {
+        return Ok(Value::Unit);
+    };
+    let message = endpoint.receiver.lock().unwrap().try_recv();
+    match message {
+        Ok(message) => json_to_loft_value(message),
+        Err(TryRecvError::Empty | TryRecvError::Disconnected) => Ok(Value::Unit),
+    }
+}
+
+/// Stop sending, so that recv() on the other side returns unit once it has
+/// received everything sent so far
+#[loft_builtin(Worker.close())]
+fn endpoint_close(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    if let Some(endpoint) = endpoint_for(this, "close")? {
+        endpoint.sender.lock().unwrap().take();
+    }
+    Ok(Value::Unit)
+}
+
+/// Close the worker's input, wait for it to finish and return what its function
+/// returned, or fail with the worker's error
+#[loft_builtin(Worker.join() -> any)]
+fn worker_join(this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    let endpoint = endpoint_for(this, "join")?
+        .ok_or_else(|| RuntimeError::new("join() can only be called on a worker"))?;
+    endpoint.sender.lock().unwrap().take();
+
+    let thread = endpoint
+        .thread
+        .lock()
+        .unwrap()
+        .take()
+        .ok_or_else(|| RuntimeError::new("join(): the worker has already been joined"))?;
+    match thread.join() {
+        Ok(result) => json_to_loft_value(result?),
+        Err(_) => Err(RuntimeError::new("join(): the worker panicked")),
+    }
+}
+
+/// Start a worker running a function, which is called with the worker's port,
+/// or the loft file at a path
+// The interpreter performs the spawn, since the worker needs an interpreter of its own
+#[loft_builtin(worker.spawn(target: any) -> Worker)]
+fn worker_spawn(_this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    Err(RuntimeError::new(
+        "worker.spawn() requires a function or the path of a loft file",
+    ))
+}
+
+/// The port of the worker this code runs in, or unit outside of a worker
+#[loft_builtin(worker.parent() -> WorkerPort)]
+fn worker_parent(_this: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+    Ok(PARENT
+        .with(|parent| parent.get())
+        .map(port_handle)
+        .unwrap_or(Value::Unit))
+}
+
+/// Create the worker builtin struct
+pub fn create_worker_builtin() -> BuiltinStruct {
+    let mut worker = BuiltinStruct::new("worker");
+    worker.add_method("spawn", worker_spawn as BuiltinMethod);
+    worker.add_method("parent", worker_parent as BuiltinMethod);
+    worker
+}
+
+// Register the builtin automatically
+crate::submit_builtin!("worker", create_worker_builtin);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::builtins::testing::{call, number};
+
+    #[test]
+    fn test_worker_messages() {
+        // Doubles every number it receives until the parent closes its side
+        let worker = spawn(|port| {
+            let mut count = 0;
+            loop {
+                match call(&port, "recv", &[])? {
+                    Value::Number(n) => call(&port, "send", &[Value::Number(n * Decimal::TWO)])?,
+                    _ => return Ok(number(count)),
+                };
+                count += 1;
+            }
+        })
+        .unwrap();
+
+        call(&worker, "send", &[number(2)]).unwrap();
+        call(&worker, "send", &[number(5)]).unwrap();
+        assert_eq!(call(&worker, "recv", &[]).unwrap(), number(4));
+        assert_eq!(call(&worker, "recv", &[]).unwrap(), number(10));
+        assert_eq!(call(&worker, "join", &[]).unwrap(), number(2));
+        assert_eq!(call(&worker, "recv", &[]).unwrap(), Value::Unit);
+        assert!(call(&worker, "send", &[number(1)]).is_err());
+        assert!(call(&worker, "join", &[]).is_err());
+    }
+
+    #[test]
+    fn test_worker_errors() {
+        let failing = spawn(|_| Err(RuntimeError::new("boom"))).unwrap();
+        assert_eq!(call(&failing, "join", &[]).unwrap_err().message, "boom");
+
+        let worker = spawn(|port| call(&port, "recv", &[])).unwrap();
+        let closure = Value::BuiltinFn(|_| Ok(Value::Unit));
+        assert!(call(&worker, "send", &[closure])
+            .unwrap_err()
+            .message
+            .starts_with("send() requires a JSON-serializable value"));
+        assert_eq!(call(&worker, "join", &[]).unwrap(), Value::Unit);
+        assert_eq!(worker_parent(&Value::Unit, &[]).unwrap(), Value::Unit);
+    }
+}
@@ -0,0 +1,179 @@
+use crate::runtime::builtin::{BuiltinMethod, BuiltinStruct};
+use crate::runtime::traits::ToString;
+use crate::runtime::value::Value;
+use crate::runtime::{RuntimeError, RuntimeResult};
+use loft_builtin_macros::{loft_builtin, required};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+
+/// Parse a YAML document into a loft value
+#[loft_builtin(yaml.parse(text: str) -> any)]
+fn yaml_parse(#[required] _this: &Value, #[types(string)] args: &[Value]) -> RuntimeResult<Value> {
+    let yaml_str = match &args[0] {
+        Value::String(s) => s,
+        _ => unreachable!(),
+    };
+
+    let yaml_value: serde_yaml::Value = serde_yaml::from_str(yaml_str)
+        .map_err(|e| RuntimeError::new(format!("Failed to parse YAML: {}", e)))?;
+
+    yaml_to_loft_value(yaml_value)
+}
+
+/// Convert a value to a YAML document
+#[loft_builtin(yaml.stringify(value: any) -> str)]
+#[required]
+fn yaml_stringify(#[required] _this: &Value, args: &[Value]) -> RuntimeResult<Value> {
+    let yaml_value = loft_value_to_yaml(&args[0])?;
+    let yaml_str = serde_yaml::to_string(&yaml_value)
+        .map_err(|e| RuntimeError::new(format!("Failed to stringify YAML: {}", e)))?;
+
+    Ok(Value::String(yaml_str.into()))
+}
+
+fn yaml_to_loft_value(yaml: serde_yaml::Value) -> RuntimeResult<Value> {
+    match yaml {
+        serde_yaml::Value::Null => Ok(Value::Unit),
+        serde_yaml::Value::Bool(b) => Ok(Value::Boolean(b)),
+        serde_yaml::Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                Ok(Value::Number(Decimal::from(i)))
+            } else if let Some(f) = n.as_f64() {
+                Ok(Value::Number(Decimal::try_from(f).map_err(|e| {
+                    RuntimeError::new(format!("Invalid number: {}", e))
+                })?))
+            } else {
+                Err(RuntimeError::new("Invalid YAML number"))
+            }
+        }
+        serde_yaml::Value::String(s) => Ok(Value::String(s.into())),
+        serde_yaml::Value::Sequence(seq) => {
+            let mut values = Vec::new();
+            for item in seq {
+                values.push(yaml_to_loft_value(item)?);
+            }
+            Ok(Value::Array(values))
+        }
+        serde_yaml::Value::Mapping(mapping) => {
+            let mut fields = HashMap::new();
+            for (key, value) in mapping {
+                // Objects are keyed by strings, so scalar keys such as `1:` are converted
+                let key = match yaml_to_loft_value(key)? {
+                    Value::String(s) => s.to_string(),
+                    key @ (Value::Number(_) | Value::Boolean(_)) => key.to_string(),
+                    _ => return Err(RuntimeError::new("YAML mapping keys must be scalars")),
+                };
+                fields.insert(key, yaml_to_loft_value(value)?);
+            }
+            Ok(Value::Struct {
+                name: "Object".to_string(),
+                fields,
+            })
+        }
+        // Tags such as `!env` carry no meaning in loft, so only the value is kept
+        serde_yaml::Value::Tagged(tagged) => yaml_to_loft_value(tagged.value),
+    }
+}
+
+fn loft_value_to_yaml(value: &Value) -> RuntimeResult<serde_yaml::Value> {
+    match value {
+        Value::Unit => Ok(serde_yaml::Value::Null),
+        Value::Boolean(b) => Ok(serde_yaml::Value::Bool(*b)),
+        Value::Number(n) => match n.fract().is_zero().then(|| n.to_i64()).flatten() {
+            Some(i) => Ok(serde_yaml::Value::Number(i.into())),
+            None => n
+                .to_f64()
+                .map(|f| serde_yaml::Value::Number(f.into()))
+                .ok_or_else(|| RuntimeError::new("Failed to convert number to YAML")),
+        },
+        Value::String(s) => Ok(serde_yaml::Value::String(s.to_string())),
+        Value::Array(arr) => {
+            let mut yaml_seq = Vec::new();
+            for item in arr {
+                yaml_seq.push(loft_value_to_yaml(item)?);
+            }
+            Ok(serde_yaml::Value::Sequence(yaml_seq))
+        }
+        Value::Struct { fields, .. } => {
+            // Sort keys so the output does not depend on hash order
+            let mut keys: Vec<&String> = fields.keys().collect();
+            keys.sort();
+
+            let mut mapping = serde_yaml::Mapping::new();
+            for key in keys {
+                mapping.insert(
+                    serde_yaml::Value::String(key.clone()),
+                    loft_value_to_yaml(&fields[key])?,
+                );
+            }
+            Ok(serde_yaml::Value::Mapping(mapping))
+        }
+        Value::Map(entries) => {
+            let mut mapping = serde_yaml::Mapping::new();
+            for (key, value) in entries {
+                mapping.insert(loft_value_to_yaml(key)?, loft_value_to_yaml(value)?);
+            }
+            Ok(serde_yaml::Value::Mapping(mapping))
+        }
+        _ => Err(RuntimeError::new(format!(
+            "Cannot convert {:?} to YAML",
+            value
+        ))),
+    }
+}
+
+pub fn create_yaml_builtin() -> BuiltinStruct {
+    let mut yaml = BuiltinStruct::new("yaml");
+
+    yaml.add_method("parse", yaml_parse as BuiltinMethod);
+    yaml.add_method("stringify", yaml_stringify as BuiltinMethod);
+
+    yaml
+}
+
+// Register the builtin automatically
+crate::submit_builtin!("yaml", create_yaml_builtin);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Value {
+        yaml_parse(&Value::Unit, &[Value::String(source.into())]).unwrap()
+    }
+
+    #[test]
+    fn test_yaml_parse_nested() {
+        let doc = parse("name: demo\nports:\n  - 80\n  - 443\nowner:\n  email: ~\n");
+        let fields = match &doc {
+            Value::Struct { fields, .. } => fields,
+            _ => panic!("Expected object"),
+        };
+
+        assert_eq!(fields["name"], Value::String("demo".into()));
+        assert_eq!(
+            fields["ports"],
+            Value::Array(vec![
+                Value::Number(Decimal::from(80)),
+                Value::Number(Decimal::from(443)),
+            ])
+        );
+        match &fields["owner"] {
+            Value::Struct { fields, .. } => assert_eq!(fields["email"], Value::Unit),
+            _ => panic!("Expected object"),
+        }
+    }
+
+    #[test]
+    fn test_yaml_roundtrip() {
+        let doc = parse("b: [1, 2.5, true]\na: hello\n");
+        let out = match yaml_stringify(&Value::Unit, std::slice::from_ref(&doc)).unwrap() {
+            Value::String(s) => s,
+            _ => panic!("Expected string"),
+        };
+
+        assert!(out.starts_with("a: hello\n"));
+        assert_eq!(parse(&out), doc);
+    }
+}
@@ -0,0 +1,379 @@
+//! Conversions between Rust types and loft values, used by host functions
+//! written with plain Rust parameters:
+//!
+//! ```ignore
+//! #[loft_builtin(host.add)]
+//! fn add(a: f64, b: f64) -> f64 {
+//!     a + b
+//! }
+//! ```
+//!
+//! `#[loft_builtin]` turns such a function into a [`BuiltinMethod`] that
+//! converts each argument with [`FromValue`] and the result with
+//! [`IntoReturn`], so it can be registered with `BuiltinStruct::add_method`.
+//! Structs with named fields can derive both traits to map to loft structs:
+//!
+//! ```ignore
+//! #[derive(FromValue, IntoValue)]
+//! struct Point {
+//!     x: f64,
+//!     y: f64,
+//! }
+//! ```
+//!
+//! [`BuiltinMethod`]: super::builtin::BuiltinMethod
+
+use super::value::Value;
+use super::{RuntimeError, RuntimeResult};
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+
+pub use loft_builtin_macros::{FromValue, IntoValue};
+
+/// Read a Rust value out of a loft value
+pub trait FromValue: Sized {
+    fn from_value(value: &Value) -> RuntimeResult<Self>;
+}
+
+/// Turn a Rust value into a loft value
+pub trait IntoValue {
+    fn into_value(self) -> Value;
+}
+
+/// What a host function may return: a value, or a result of one
+pub trait IntoReturn {
+    fn into_return(self) -> RuntimeResult<Value>;
+}
+
+impl<T: IntoValue> IntoReturn for T {
+    fn into_return(self) -> RuntimeResult<Value> {
+        Ok(self.into_value())
+    }
+}
+
+impl<T: IntoValue> IntoReturn for RuntimeResult<T> {
+    fn into_return(self) -> RuntimeResult<Value> {
+        self.map(IntoValue::into_value)
+    }
+}
+
+fn mismatch(expected: &str, value: &Value) -> RuntimeError {
+    RuntimeError::new(format!("expected {}, got {}", expected, value.type_name()))
+}
+
+/// Convert the argument at `index` of a host function, naming the function
+/// and the argument in the error
+pub fn argument<T: FromValue>(function: &str, args: &[Value], index: usize) -> RuntimeResult<T> {
+    T::from_value(args.get(index).unwrap_or(&Value::Unit)).map_err(|e| {
+        RuntimeError::new(format!(
+            "{}() argument {}: {}",
+            function,
+            index + 1,
+            e.message
+        ))
+    })
+}
+
+/// A field of a struct instance, or an entry of a map, for derived
+/// [`FromValue`] impls. Missing fields are `void`.
+pub fn field(value: &Value, struct_name: &str, name: &str) -> RuntimeResult<Value> {
+    match value {
+        Value::Struct { fields, .. } => Ok(fields.get(name).cloned().unwrap_or(Value::Unit)),
+        Value::Map(entries) => Ok(entries
+            .iter()
+            .find(|(key, _)| matches!(key, Value::String(key) if &**key == name))
+            .map_or(Value::Unit, |(_, value)| value.clone())),
+        other => Err(mismatch(struct_name, other)),
+    }
+}
+
+impl FromValue for Value {
+    fn from_value(value: &Value) -> RuntimeResult<Self> {
+        Ok(value.clone())
+    }
+}
+
+impl IntoValue for Value {
+    fn into_value(self) -> Value {
+        self
+    }
+}
+
+impl IntoValue for () {
+    fn into_value(self) -> Value {
+        Value::Unit
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: &Value) -> RuntimeResult<Self> {
+        match value {
+            Value::Boolean(b) => Ok(*b),
+            other => Err(mismatch("bool", other)),
+        }
+    }
+}
+
+impl IntoValue for bool {
+    fn into_value(self) -> Value {
+        Value::Boolean(self)
+    }
+}
+
+impl FromValue for Decimal {
+    fn from_value(value: &Value) -> RuntimeResult<Self> {
+        match value {
+            Value::Number(n) => Ok(*n),
+            other => Err(mismatch("num", other)),
+        }
+    }
+}
+
+impl IntoValue for Decimal {
+    fn into_value(self) -> Value {
+        Value::Number(self)
+    }
+}
+
+impl FromValue for f64 {
+    fn from_value(value: &Value) -> RuntimeResult<Self> {
+        Decimal::from_value(value)?
+            .to_f64()
+            .ok_or_else(|| RuntimeError::new("Invalid number"))
+    }
+}
+
+/// NaN and infinities have no loft number and become `void`
+impl IntoValue for f64 {
+    fn into_value(self) -> Value {
+        Decimal::from_f64_retain(self).map_or(Value::Unit, Value::Number)
+    }
+}
+
+macro_rules! integer_conversions {
+    ($($int:ty => $to:ident, $from:ident);* $(;)?) => {
+        $(
+            impl FromValue for $int {
+                fn from_value(value: &Value) -> RuntimeResult<Self> {
+                    let n = Decimal::from_value(value)?;
+                    if !n.fract().is_zero() {
+                        return Err(RuntimeError::new(format!(
+                            "expected a whole number, got {}",
+                            n
+                        )));
+                    }
+                    n.$to().ok_or_else(|| {
+                        RuntimeError::new(format!(
+                            "{} is out of range for {}",
+                            n,
+                            stringify!($int)
+                        ))
+                    })
+                }
+            }
+
+            impl IntoValue for $int {
+                fn into_value(self) -> Value {
+                    Value::Number(Decimal::$from(self).unwrap_or_default())
+                }
+            }
+        )*
+    };
+}
+
+integer_conversions! {
+    i32 => to_i32, from_i32;
+    i64 => to_i64, from_i64;
+    u32 => to_u32, from_u32;
+    u64 => to_u64, from_u64;
+    usize => to_usize, from_usize;
+}
+
+impl FromValue for String {
+    fn from_value(value: &Value) -> RuntimeResult<Self> {
+        match value {
+            Value::String(s) => Ok(s.to_string()),
+            other => Err(mismatch("str", other)),
+        }
+    }
+}
+
+impl IntoValue for String {
+    fn into_value(self) -> Value {
+        Value::String(self.into())
+    }
+}
+
+impl IntoValue for &str {
+    fn into_value(self) -> Value {
+        Value::String(self.into())
+    }
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(value: &Value) -> RuntimeResult<Self> {
+        match value {
+            Value::Array(items) => items.iter().map(T::from_value).collect(),
+            other => Err(mismatch("array", other)),
+        }
+    }
+}
+
+impl<T: IntoValue> IntoValue for Vec<T> {
+    fn into_value(self) -> Value {
+        Value::Array(self.into_iter().map(IntoValue::into_value).collect())
+    }
+}
+
+/// `void`, or a missing argument, is `None`
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: &Value) -> RuntimeResult<Self> {
+        match value {
+            Value::Unit => Ok(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+}
+
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value(self) -> Value {
+        self.map_or(Value::Unit, IntoValue::into_value)
+    }
+}
+
+/// A map with string keys
+impl<T: FromValue> FromValue for HashMap<String, T> {
+    fn from_value(value: &Value) -> RuntimeResult<Self> {
+        match value {
+            Value::Map(entries) => entries
+                .iter()
+                .map(|(key, value)| Ok((String::from_value(key)?, T::from_value(value)?)))
+                .collect(),
+            other => Err(mismatch("map", other)),
+        }
+    }
+}
+
+/// Entries are sorted by key, since loft maps keep their order
+impl<T: IntoValue> IntoValue for HashMap<String, T> {
+    fn into_value(self) -> Value {
+        let mut entries: Vec<(String, T)> = self.into_iter().collect();
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Value::Map(
+            entries
+                .into_iter()
+                .map(|(key, value)| (key.into_value(), value.into_value()))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::builtin::BuiltinStruct;
+    use crate::Engine;
+    use loft_builtin_macros::loft_builtin;
+
+    /// Add two numbers
+    #[loft_builtin(host.add)]
+    fn add(a: f64, b: f64) -> f64 {
+        a + b
+    }
+
+    #[loft_builtin(host.repeat)]
+    fn repeat(text: String, times: Option<usize>) -> RuntimeResult<Vec<String>> {
+        match times {
+            Some(0) => Err(RuntimeError::new("times must be positive")),
+            times => Ok(vec![text; times.unwrap_or(2)]),
+        }
+    }
+
+    #[test]
+    fn test_typed_host_functions() {
+        let mut engine = Engine::new();
+        let mut host = BuiltinStruct::new("host");
+        host.add_method("add", add);
+        host.add_method("repeat", repeat);
+        engine.register_builtin_module(host);
+
+        assert_eq!(engine.eval("host.add(1.5, 2);").unwrap(), 3.5.into_value());
+        assert_eq!(
+            engine.eval("host.repeat(\"a\");").unwrap(),
+            vec!["a", "a"].into_value()
+        );
+
+        let error = engine.eval("host.add(1, \"2\");").unwrap_err();
+        assert_eq!(error.to_string(), "add() argument 2: expected num, got str");
+        let error = engine.eval("host.add(1, 2, 3);").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "add() takes 2 arguments but 3 were given"
+        );
+        let error = engine.eval("host.repeat(\"a\", 0);").unwrap_err();
+        assert_eq!(error.to_string(), "times must be positive");
+    }
+
+    #[derive(Debug, PartialEq, FromValue, IntoValue)]
+    struct Point {
+        x: f64,
+        y: f64,
+        label: Option<String>,
+    }
+
+    #[loft_builtin(host.mirror)]
+    fn mirror(point: Point) -> Point {
+        Point {
+            x: -point.x,
+            label: point.label.or(Some("mirrored".to_string())),
+            ..point
+        }
+    }
+
+    #[test]
+    fn test_derived_conversions() {
+        let mut engine = Engine::new();
+        let mut host = BuiltinStruct::new("host");
+        host.add_method("mirror", mirror);
+        engine.register_builtin_module(host);
+
+        let value = engine
+            .eval("def Point {\n    x: num,\n    y: num\n}\nhost.mirror(Point { x: 1, y: 2 });")
+            .unwrap();
+        assert_eq!(
+            Point::from_value(&value).unwrap(),
+            Point {
+                x: -1.0,
+                y: 2.0,
+                label: Some("mirrored".to_string()),
+            }
+        );
+        assert!(matches!(&value, Value::Struct { name, .. } if name == "Point"));
+
+        let error = engine
+            .eval("host.mirror(Point { x: \"1\", y: 2 });")
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "mirror() argument 1: field 'x' of Point: expected num, got str"
+        );
+    }
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(i64::from_value(&7i64.into_value()).unwrap(), 7);
+        assert!(i64::from_value(&1.5.into_value()).is_err());
+        assert!(u32::from_value(&(-1i64).into_value()).is_err());
+        assert_eq!(
+            Vec::<Option<bool>>::from_value(&Value::Array(vec![Value::Boolean(true), Value::Unit]))
+                .unwrap(),
+            [Some(true), None]
+        );
+        let map = HashMap::from([("b".to_string(), 2i64), ("a".to_string(), 1)]);
+        assert_eq!(
+            HashMap::<String, i64>::from_value(&map.clone().into_value()).unwrap(),
+            map
+        );
+    }
+}
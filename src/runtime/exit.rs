@@ -0,0 +1,130 @@
+//! How a loft program sets its exit status.
+//!
+//! `exit(code)` doesn't stop the process on the spot: it raises an error that
+//! unwinds the program, running deferred code and `finally` blocks on the
+//! way, and that no `catch` stops. Whoever runs the program then exits with
+//! the code, the way `loft` does. A program that fails with any other error
+//! exits with 1.
+//!
+//! With `loft --exit-with-result`, the value the program ends with becomes
+//! the status instead, see [`result_code`].
+
+use super::value::Value;
+use super::{RuntimeError, RuntimeResult};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+
+/// Stop the program with a status code, 0 if not given
+pub fn exit(args: &[Value]) -> RuntimeResult<Value> {
+    let code = match args.first() {
+        None | Some(Value::Unit) => 0,
+        Some(Value::Number(n)) => code(n)?,
+        Some(other) => {
+            return Err(RuntimeError::new(format!(
+                "exit() requires a number, got {}",
+                other.type_name()
+            )))
+        }
+    };
+    Err(RuntimeError::exit(code))
+}
+
+/// The exit status for the value a program ended with: a number is the code
+/// itself, `true` and unit are 0 and `false` is 1
+pub fn result_code(value: &Value) -> RuntimeResult<i32> {
+    match value {
+        Value::Number(n) => code(n),
+        Value::Boolean(true) | Value::Unit => Ok(0),
+        Value::Boolean(false) => Ok(1),
+        Value::Promise(value) => result_code(value),
+        other => Err(RuntimeError::new(format!(
+            "The program must end with a number or a boolean to set the exit code, got {}",
+            other.type_name()
+        ))),
+    }
+}
+
+fn code(n: &Decimal) -> RuntimeResult<i32> {
+    n.is_integer()
+        .then(|| n.to_i32())
+        .flatten()
+        .ok_or_else(|| RuntimeError::new(format!("Invalid exit code {}", n)))
+}
+
+/// Leave the process with `code`, once the terminal is restored and
+/// everything printed has been written out
+pub fn exit_process(code: i32) -> ! {
+    use std::io::Write;
+
+    super::builtins::term::restore();
+    let _ = std::io::stdout().flush();
+    let _ = std::io::stderr().flush();
+    std::process::exit(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{InputStream, Parser};
+    use crate::runtime::Interpreter;
+
+    fn run(code: &str) -> (RuntimeResult<Value>, Interpreter) {
+        let stmts = Parser::new(InputStream::new("test.lf", &code.to_string()))
+            .parse()
+            .unwrap();
+        let mut interpreter = Interpreter::new();
+        (interpreter.eval_program(stmts), interpreter)
+    }
+
+    #[test]
+    fn test_exit_unwinds_past_catch() {
+        let (result, interpreter) = run(r#"
+            let steps = [];
+            fn note(step: str) {
+                steps = steps.push(step);
+            }
+            fn finish() {
+                try {
+                    exit(3);
+                } catch (e) {
+                    note("catch");
+                } finally {
+                    note("finally");
+                }
+                note("after");
+            }
+            finish();
+        "#);
+        assert_eq!(result.unwrap_err().exit_code(), Some(3));
+        assert_eq!(
+            interpreter.env.get("steps"),
+            Some(&Value::Array(vec![Value::String("finally".into())]))
+        );
+
+        assert_eq!(run("exit();").0.unwrap_err().exit_code(), Some(0));
+        assert_eq!(run("env.exit(2);").0.unwrap_err().exit_code(), Some(2));
+        let error = run("exit(1.5);").0.unwrap_err();
+        assert_eq!(
+            (error.exit_code(), error.message.as_str()),
+            (None, "Invalid exit code 1.5")
+        );
+        let error = run(r#"assert.throws(() => { exit(4); });"#).0.unwrap_err();
+        assert_eq!(error.exit_code(), Some(4));
+    }
+
+    #[test]
+    fn test_result_codes() {
+        let number = |n: i64| Value::Number(Decimal::from(n));
+        assert_eq!(result_code(&number(7)).unwrap(), 7);
+        assert_eq!(result_code(&Value::Boolean(false)).unwrap(), 1);
+        assert_eq!(result_code(&Value::Unit).unwrap(), 0);
+        assert_eq!(
+            result_code(&Value::Promise(Box::new(number(2)))).unwrap(),
+            2
+        );
+        assert!(result_code(&Value::String("done".into()))
+            .unwrap_err()
+            .message
+            .ends_with("got str"));
+    }
+}
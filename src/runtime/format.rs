@@ -0,0 +1,111 @@
+//! Format specifiers of template literals, such as `${n:d2}` or `${n:hex}`
+
+use super::value::Value;
+use super::{RuntimeError, RuntimeResult};
+use crate::parser::{Align, FormatSpec};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::{Decimal, RoundingStrategy};
+
+/// Write `value`, whose plain text is `text`, as `spec` asks
+pub fn apply_format(value: &Value, text: String, spec: &FormatSpec) -> RuntimeResult<String> {
+    match *spec {
+        FormatSpec::Integer { width } => {
+            let n = integer(value, spec)?;
+            let digits = format!("{:0width$}", n.unsigned_abs(), width = width);
+            Ok(signed(n, digits))
+        }
+        FormatSpec::Fixed { decimals } => {
+            let n = number(value, spec)?;
+            let rounded =
+                n.round_dp_with_strategy(decimals, RoundingStrategy::MidpointAwayFromZero);
+            Ok(format!("{:.*}", decimals as usize, rounded))
+        }
+        FormatSpec::Radix { radix, upper } => {
+            let n = integer(value, spec)?;
+            let magnitude = n.unsigned_abs();
+            let digits = match (radix, upper) {
+                (16, true) => format!("{:X}", magnitude),
+                (16, false) => format!("{:x}", magnitude),
+                (8, _) => format!("{:o}", magnitude),
+                _ => format!("{:b}", magnitude),
+            };
+            Ok(signed(n, digits))
+        }
+        FormatSpec::Align { align, width } => {
+            let padding = width.saturating_sub(text.chars().count());
+            let (left, right) = match align {
+                Align::Left => (0, padding),
+                Align::Right => (padding, 0),
+                Align::Center => (padding / 2, padding - padding / 2),
+            };
+            Ok(format!("{}{}{}", " ".repeat(left), text, " ".repeat(right)))
+        }
+    }
+}
+
+/// `digits` of the magnitude of `n`, with its sign
+fn signed(n: i128, digits: String) -> String {
+    if n < 0 {
+        format!("-{}", digits)
+    } else {
+        digits
+    }
+}
+
+fn number(value: &Value, spec: &FormatSpec) -> RuntimeResult<Decimal> {
+    match value {
+        Value::Number(n) => Ok(*n),
+        other => Err(RuntimeError::new(format!(
+            "Format ':{}' needs a number, got {}",
+            spec,
+            other.type_name()
+        ))),
+    }
+}
+
+fn integer(value: &Value, spec: &FormatSpec) -> RuntimeResult<i128> {
+    let n = number(value, spec)?;
+    n.is_integer()
+        .then(|| n.to_i128())
+        .flatten()
+        .ok_or_else(|| RuntimeError::new(format!("Format ':{}' needs an integer, got {}", spec, n)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::traits::ToString;
+
+    fn format(value: Value, spec: &str) -> RuntimeResult<String> {
+        let text = value.to_string();
+        apply_format(&value, text, &FormatSpec::parse(spec).unwrap())
+    }
+
+    #[test]
+    fn test_format_specs() {
+        let number = |n: &str| Value::Number(n.parse().unwrap());
+        assert_eq!(format(number("7"), "d2").unwrap(), "07");
+        assert_eq!(format(number("-7"), "d3").unwrap(), "-007");
+        assert_eq!(format(number("1234"), "d2").unwrap(), "1234");
+        assert_eq!(format(number("2.345"), "f2").unwrap(), "2.35");
+        assert_eq!(format(number("2"), "f1").unwrap(), "2.0");
+        assert_eq!(format(number("255"), "hex").unwrap(), "ff");
+        assert_eq!(format(number("255"), "HEX").unwrap(), "FF");
+        assert_eq!(format(number("-8"), "oct").unwrap(), "-10");
+        assert_eq!(format(number("5"), "bin").unwrap(), "101");
+        assert_eq!(format(Value::String("ab".into()), ">4").unwrap(), "  ab");
+        assert_eq!(format(Value::String("ab".into()), "<4").unwrap(), "ab  ");
+        assert_eq!(format(Value::String("ab".into()), "^5").unwrap(), " ab  ");
+
+        assert_eq!(
+            format(number("1.5"), "hex").unwrap_err().message,
+            "Format ':hex' needs an integer, got 1.5"
+        );
+        assert_eq!(
+            format(Value::String("x".into()), "f2").unwrap_err().message,
+            "Format ':f2' needs a number, got str"
+        );
+        assert_eq!(FormatSpec::parse("x2"), None);
+        assert_eq!(FormatSpec::parse("f"), None);
+    }
+}
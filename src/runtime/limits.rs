@@ -0,0 +1,261 @@
+//! Step and time budgets for hosts that can't kill a runaway program from
+//! outside, like the web playground where `while (true) {}` would otherwise
+//! hang the tab.
+//!
+//! Every statement and expression the interpreter evaluates is one step. Once
+//! a budget runs out every following step fails too, so `try`/`catch` can't
+//! keep the program going.
+//!
+//! Memory is estimated from the values the program's variables hold, see
+//! [`Value::approx_size`](super::value::Value::approx_size), and measured
+//! as often as the clock is checked.
+//!
+//! Tasks, timers and workers started by a program share its budget. Like
+//! [`permission_context`](super::permission_context), the budget of the
+//! program running on a thread is kept in a thread local, so builtins that
+//! block, like awaiting `time.sleep` or `recv()`, give up at the deadline.
+//! Once the program itself returns, whatever it left running stops at its
+//! next step.
+
+use chrono::{DateTime, TimeDelta, Utc};
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+/// How many steps run between checks of the clock and of memory
+const CLOCK_INTERVAL: u64 = 1024;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExecutionLimits {
+    /// Most statements and expressions evaluated before the program is stopped
+    pub max_steps: Option<u64>,
+    /// Longest the program may run
+    pub max_duration: Option<Duration>,
+    /// Most bytes the program's variables may hold, roughly
+    pub max_memory: Option<usize>,
+}
+
+impl ExecutionLimits {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_steps.is_none() && self.max_duration.is_none() && self.max_memory.is_none()
+    }
+}
+
+/// The limits of a running program and how much of them has been used, shared
+/// by everything the program runs
+#[derive(Debug)]
+pub(crate) struct Budget {
+    limits: ExecutionLimits,
+    steps: AtomicU64,
+    /// Set by the first step, so the clock starts when the program does
+    deadline: OnceLock<DateTime<Utc>>,
+    /// The error of the limit that ran out
+    exceeded: OnceLock<String>,
+    /// Set once the program returned, to stop what it left running
+    finished: AtomicBool,
+}
+
+impl Budget {
+    pub fn new(limits: ExecutionLimits) -> Self {
+        Budget {
+            limits,
+            steps: AtomicU64::new(0),
+            deadline: OnceLock::new(),
+            exceeded: OnceLock::new(),
+            finished: AtomicBool::new(false),
+        }
+    }
+
+    pub fn limits(&self) -> ExecutionLimits {
+        self.limits
+    }
+
+    /// Count one step, failing with the error message once a limit is exceeded
+    pub fn step(&self) -> Result<(), String> {
+        if let Some(message) = self.exceeded.get() {
+            return Err(message.clone());
+        }
+        if self.is_finished() {
+            return Err("Execution stopped: the program has finished".to_string());
+        }
+        let steps = self.steps.fetch_add(1, Ordering::Relaxed) + 1;
+
+        if let Some(max_steps) = self.limits.max_steps {
+            if steps > max_steps {
+                return self.fail_with(format!(
+                    "Execution limit exceeded: more than {} steps",
+                    max_steps
+                ));
+            }
+        }
+        if let Some(deadline) = self.deadline() {
+            if steps.is_multiple_of(CLOCK_INTERVAL) && Utc::now() > deadline {
+                return self.fail_with(self.time_exceeded());
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the memory limit against `used`, which is only called on the
+    /// steps that also check the clock since estimating memory is slow
+    pub fn check_memory(&self, used: impl FnOnce() -> usize) -> Result<(), String> {
+        let Some(max_memory) = self.limits.max_memory else {
+            return Ok(());
+        };
+        let steps = self.steps.load(Ordering::Relaxed);
+        if !steps.is_multiple_of(CLOCK_INTERVAL) || self.exceeded.get().is_some() {
+            return Ok(());
+        }
+        if used() > max_memory {
+            return self.fail_with(format!(
+                "Execution limit exceeded: used more than {} bytes of memory",
+                max_memory
+            ));
+        }
+        Ok(())
+    }
+
+    /// How long the program may still run, if it has a time limit
+    pub fn remaining(&self) -> Option<Duration> {
+        let deadline = self.deadline()?;
+        Some((deadline - Utc::now()).to_std().unwrap_or(Duration::ZERO))
+    }
+
+    /// Fail once the deadline has passed, whatever the step count
+    pub fn check_deadline(&self) -> Result<(), String> {
+        if let Some(message) = self.exceeded.get() {
+            return Err(message.clone());
+        }
+        match self.remaining() {
+            Some(Duration::ZERO) => self.fail_with(self.time_exceeded()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Stop the tasks, timers and workers the program left running
+    pub fn finish(&self) {
+        self.finished.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Relaxed)
+    }
+
+    /// A budget for running more code after the program finished, which has
+    /// used up as much as this one
+    pub fn resume(&self) -> Self {
+        Budget {
+            limits: self.limits,
+            steps: AtomicU64::new(self.steps.load(Ordering::Relaxed)),
+            deadline: self.deadline.clone(),
+            exceeded: self.exceeded.clone(),
+            finished: AtomicBool::new(false),
+        }
+    }
+
+    /// When the program has to be done by, starting the clock on first use
+    fn deadline(&self) -> Option<DateTime<Utc>> {
+        let max_duration = self.limits.max_duration?;
+        Some(*self.deadline.get_or_init(|| {
+            TimeDelta::from_std(max_duration)
+                .ok()
+                .and_then(|duration| Utc::now().checked_add_signed(duration))
+                .unwrap_or(DateTime::<Utc>::MAX_UTC)
+        }))
+    }
+
+    fn time_exceeded(&self) -> String {
+        format!(
+            "Execution limit exceeded: ran for more than {} ms",
+            self.limits.max_duration.unwrap_or_default().as_millis()
+        )
+    }
+
+    fn fail_with(&self, message: String) -> Result<(), String> {
+        Err(self.exceeded.get_or_init(|| message).clone())
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Arc<Budget>>> = const { RefCell::new(None) };
+}
+
+/// Swap the budget of the program running on the current thread, returning
+/// the previous one
+pub(crate) fn replace_budget(budget: Option<Arc<Budget>>) -> Option<Arc<Budget>> {
+    CURRENT.with(|current| std::mem::replace(&mut *current.borrow_mut(), budget))
+}
+
+/// The budget of the program running on the current thread, to hand to
+/// threads it starts
+pub(crate) fn current_budget() -> Option<Arc<Budget>> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/// How long a builtin may block for: `wanted`, or less if the program
+/// running on the current thread reaches its deadline first
+pub(crate) fn blocking_timeout(wanted: Option<Duration>) -> Option<Duration> {
+    let remaining = current_budget().and_then(|budget| budget.remaining());
+    match (wanted, remaining) {
+        (Some(wanted), Some(remaining)) => Some(wanted.min(remaining)),
+        (wanted, remaining) => wanted.or(remaining),
+    }
+}
+
+/// Fail once the program running on the current thread is past its
+/// deadline, for builtins that stopped blocking at [`blocking_timeout`]
+pub(crate) fn check_deadline() -> Result<(), String> {
+    current_budget().map_or(Ok(()), |budget| budget.check_deadline())
+}
+
+/// Whether the program that started the current thread's work has returned
+pub(crate) fn is_finished() -> bool {
+    current_budget().is_some_and(|budget| budget.is_finished())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget() {
+        let budget = Budget::new(ExecutionLimits {
+            max_steps: Some(3),
+            ..Default::default()
+        });
+        assert!((0..3).all(|_| budget.step().is_ok()));
+        assert_eq!(
+            budget.step(),
+            Err("Execution limit exceeded: more than 3 steps".to_string())
+        );
+        assert!(budget.step().is_err());
+
+        let budget = Budget::new(ExecutionLimits {
+            max_duration: Some(Duration::ZERO),
+            ..Default::default()
+        });
+        budget.step().unwrap();
+        std::thread::sleep(Duration::from_millis(2));
+        let result = (0..CLOCK_INTERVAL).try_for_each(|_| budget.step());
+        assert!(result.unwrap_err().contains("ran for more than 0 ms"));
+        assert!(budget.step().is_err());
+
+        let budget = Budget::new(ExecutionLimits {
+            max_memory: Some(100),
+            ..Default::default()
+        });
+        for _ in 0..CLOCK_INTERVAL - 1 {
+            budget.step().unwrap();
+            budget.check_memory(|| unreachable!()).unwrap();
+        }
+        budget.step().unwrap();
+        assert!(budget.check_memory(|| 50).is_ok());
+        (0..CLOCK_INTERVAL).try_for_each(|_| budget.step()).unwrap();
+        assert!(budget
+            .check_memory(|| 200)
+            .unwrap_err()
+            .contains("100 bytes"));
+        assert!(budget.step().is_err());
+    }
+}
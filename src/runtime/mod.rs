@@ -1,10 +1,21 @@
+pub mod bench_harness;
+pub mod builder;
 pub mod builtin;
 pub mod builtin_registry;
 pub mod builtins;
+pub mod convert;
+pub mod exit;
+pub mod format;
+pub mod limits;
 pub mod permission_context;
 pub mod permissions;
+pub mod profiler;
+pub mod resolve;
+pub mod scheduler;
+pub mod test_harness;
 pub mod traits;
 pub mod value;
+pub mod vfs;
 
 pub use builtin::*;
 pub use builtin_registry::*;
@@ -14,11 +25,16 @@ pub use permissions::*;
 pub use traits::*;
 pub use value::*;
 
-use crate::parser::{Expr, InputStream, Parser, Stmt, TraitMethod, Type};
+use crate::parser::{
+    import_binding_name, is_method, Expr, ExprKind, InputStream, Parser, Span, Stmt, StmtKind,
+    TraitMethod, Type,
+};
+use indexmap::IndexMap;
 use miette::{Diagnostic, LabeledSpan, NamedSource};
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::path::PathBuf;
+use std::sync::Arc;
 // Removed 'use value::Value;' as it conflicts with 'pub use value::*;'
 
 fn init_stdlib_traits() -> HashMap<String, Vec<TraitMethod>> {
@@ -60,6 +76,19 @@ fn init_stdlib_traits() -> HashMap<String, Vec<TraitMethod>> {
         }],
     );
 
+    // Rem
+    traits.insert(
+        "Rem".to_string(),
+        vec![TraitMethod::Signature {
+            name: "rem".to_string(),
+            params: vec![
+                ("self".to_string(), Type::Named("Self".to_string())),
+                ("other".to_string(), Type::Named("any".to_string())),
+            ],
+            return_type: Type::Named("any".to_string()),
+        }],
+    );
+
     // Add
     traits.insert(
         "Add".to_string(),
@@ -231,6 +260,43 @@ pub struct RuntimeError {
     pub source: Option<Box<NamedSource<String>>>,
     pub position: Option<usize>,
     pub len: Option<usize>,
+    /// Calls that were running when the error was raised, innermost first
+    pub backtrace: Vec<BacktraceFrame>,
+    /// What the program raised itself, if the error came from `throw` or `exit()`
+    pub raised: Option<Box<Raised>>,
+}
+
+/// An error raised by the program, or found in it before running, rather
+/// than by the interpreter
+#[derive(Debug, Clone)]
+pub enum Raised {
+    /// The value passed to `throw`
+    Thrown(Value),
+    /// The status passed to `exit()`, which ends the program past any `catch`
+    Exit(i32),
+    /// A mistake the checker also reports, under this code
+    Rejected(&'static crate::codes::Code),
+}
+
+/// One call in a runtime error's backtrace
+#[derive(Debug, Clone, PartialEq)]
+pub struct BacktraceFrame {
+    pub function: String,
+    pub path: Option<String>,
+    /// 1-based line and column the frame was executing
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+impl Display for BacktraceFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.function)?;
+        match (&self.path, self.line, self.column) {
+            (Some(path), Some(line), Some(column)) => write!(f, " at {}:{}:{}", path, line, column),
+            (Some(path), _, _) => write!(f, " at {}", path),
+            _ => Ok(()),
+        }
+    }
 }
 
 impl RuntimeError {
@@ -241,6 +307,8 @@ impl RuntimeError {
             source: None,
             position: None,
             len: None,
+            backtrace: Vec::new(),
+            raised: None,
         }
     }
 
@@ -257,6 +325,46 @@ impl RuntimeError {
             source: Some(Box::new(NamedSource::new(path, source_code))),
             position: Some(position),
             len: Some(len),
+            backtrace: Vec::new(),
+            raised: None,
+        }
+    }
+
+    /// Report the error under a diagnostic's code rather than `runtime_error`
+    pub fn with_code(mut self, name: &str) -> Self {
+        self.raised = crate::codes::by_name(name).map(|code| Box::new(Raised::Rejected(code)));
+        self
+    }
+
+    /// The code given by [`with_code`](Self::with_code), if any
+    pub fn rejected_code(&self) -> Option<&'static crate::codes::Code> {
+        match self.raised.as_deref() {
+            Some(Raised::Rejected(code)) => Some(code),
+            _ => None,
+        }
+    }
+
+    /// The error raised by `exit(code)`, see [`exit`](self::exit)
+    pub fn exit(code: i32) -> Self {
+        Self {
+            raised: Some(Box::new(Raised::Exit(code))),
+            ..Self::new(format!("Program exited with code {}", code))
+        }
+    }
+
+    /// The status passed to `exit()`, if that is how the program ended
+    pub fn exit_code(&self) -> Option<i32> {
+        match self.raised.as_deref() {
+            Some(Raised::Exit(code)) => Some(*code),
+            _ => None,
+        }
+    }
+
+    /// The value passed to `throw`, if the error was thrown by the program
+    pub fn thrown(&self) -> Option<&Value> {
+        match self.raised.as_deref() {
+            Some(Raised::Thrown(value)) => Some(value),
+            _ => None,
         }
     }
 
@@ -273,7 +381,22 @@ impl RuntimeError {
             source: Some(Box::new(NamedSource::new(path, source_code))),
             position: None,
             len: None,
+            backtrace: Vec::new(),
+            raised: None,
+        }
+    }
+
+    /// The backtrace as one line per call, marking the innermost call with `→`
+    pub fn format_backtrace(&self) -> Option<String> {
+        if self.backtrace.is_empty() {
+            return None;
         }
+        let mut out = String::from("backtrace, innermost call first:");
+        for (index, frame) in self.backtrace.iter().enumerate() {
+            let marker = if index == 0 { "→" } else { " " };
+            out.push_str(&format!("\n  {} {}: {}", marker, index, frame));
+        }
+        Some(out)
     }
 }
 
@@ -286,6 +409,10 @@ impl Display for RuntimeError {
 impl std::error::Error for RuntimeError {}
 
 impl Diagnostic for RuntimeError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        crate::codes::of_runtime_error(self).map(|code| Box::new(code.code) as Box<dyn Display>)
+    }
+
     fn severity(&self) -> Option<miette::Severity> {
         Some(miette::Severity::Error)
     }
@@ -294,6 +421,11 @@ impl Diagnostic for RuntimeError {
         self.source.as_ref().map(|s| s.as_ref() as &dyn miette::SourceCode)
     }
 
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.format_backtrace()
+            .map(|backtrace| Box::new(backtrace) as Box<dyn Display>)
+    }
+
     fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
         if let (Some(position), Some(len)) = (self.position, self.len) {
             Some(Box::new(
@@ -311,9 +443,23 @@ impl Diagnostic for RuntimeError {
     }
 }
 
+/// A file the interpreter runs code from, kept so errors can point into it
+#[derive(Debug)]
+pub struct SourceFile {
+    pub path: String,
+    pub code: String,
+}
+
+/// The variables of one scope in the order they were defined. A variable
+/// keeps its index, which [`resolve`] refers to it by, when it is reassigned.
+pub type Scope = IndexMap<String, Value>;
+
 #[derive(Debug, Clone)] // Assuming Environment is Clone or I can add it
 pub struct Environment {
-    scopes: Vec<HashMap<String, Value>>,
+    scopes: Vec<Scope>,
+    /// Scopes below this one are a copy of the code that started an `async`
+    /// or `lazy` task, so assigning to their variables is an error
+    frozen: usize,
 }
 
 impl Default for Environment {
@@ -325,12 +471,19 @@ impl Default for Environment {
 impl Environment {
     pub fn new() -> Self {
         Self {
-            scopes: vec![HashMap::new()],
+            scopes: vec![Scope::new()],
+            frozen: 0,
         }
     }
 
+    /// Make assigning to the variables of every scope open now an error, for
+    /// a task that only sees a copy of them
+    pub fn freeze(&mut self) {
+        self.frozen = self.scopes.len();
+    }
+
     pub fn push_scope(&mut self) {
-        self.scopes.push(HashMap::new());
+        self.scopes.push(Scope::new());
     }
 
     pub fn pop_scope(&mut self) {
@@ -339,6 +492,16 @@ impl Environment {
         }
     }
 
+    /// Number of scopes currently open
+    pub fn depth(&self) -> usize {
+        self.scopes.len()
+    }
+
+    /// Close the scopes left open by code that failed, down to `depth`
+    pub fn truncate(&mut self, depth: usize) {
+        self.scopes.truncate(depth.max(1));
+    }
+
     pub fn set(&mut self, name: String, value: Value) {
         if let Some(scope) = self.scopes.last_mut() {
             scope.insert(name, value);
@@ -354,11 +517,29 @@ impl Environment {
         None
     }
 
+    /// The variable at `slot` of the scope `depth` scopes up, as found by
+    /// [`resolve`], or the variable `name` if that slot holds another one
+    pub fn get_local(&self, name: &str, depth: usize, slot: usize) -> Option<&Value> {
+        self.scopes
+            .len()
+            .checked_sub(depth + 1)
+            .and_then(|index| self.scopes[index].get_index(slot))
+            .filter(|(slot_name, _)| *slot_name == name)
+            .map(|(_, value)| value)
+            .or_else(|| self.get(name))
+    }
+
     pub fn update(&mut self, name: &str, value: Value) -> RuntimeResult<()> {
         // Allow re-assignment of any variable (not just mutable ones)
         // Also allow shadowing by creating a new variable in the current scope if not found
-        for scope in self.scopes.iter_mut().rev() {
+        for (index, scope) in self.scopes.iter_mut().enumerate().rev() {
             if scope.contains_key(name) {
+                if index < self.frozen {
+                    return Err(RuntimeError::new(format!(
+                        "Cannot assign to '{}' inside an async or lazy task: the task works on a copy of the variables around it, so the change would be lost. Return the new value from the task instead",
+                        name
+                    )));
+                }
                 scope.insert(name.to_string(), value);
                 return Ok(());
             }
@@ -370,6 +551,15 @@ impl Environment {
         Ok(())
     }
 
+    /// Rough number of bytes the variables of every scope hold
+    pub fn approx_size(&self) -> usize {
+        self.scopes
+            .iter()
+            .flatten()
+            .map(|(name, value)| name.len() + value.approx_size())
+            .sum()
+    }
+
     /// Capture all variables from the current environment
     /// This is used when creating closures to capture their environment
     pub fn capture_all(&self) -> HashMap<String, Value> {
@@ -526,7 +716,7 @@ fn res_unwrap_err(this: &Value, _: &[Value]) -> RuntimeResult<Value> {
 
 fn opt_res_expect(this: &Value, args: &[Value]) -> RuntimeResult<Value> {
     let msg = if let Some(Value::String(s)) = args.first() {
-        s.clone()
+        s.to_string()
     } else {
         "expect() failed".to_string()
     };
@@ -605,8 +795,7 @@ type ImplMethod = (
 
 pub struct Interpreter {
     pub env: Environment,
-    source_path: Option<String>,
-    source_code: Option<String>,
+    source: Option<Arc<SourceFile>>,
     // Track trait declarations: trait_name -> methods
     traits: HashMap<String, Vec<TraitMethod>>,
     // Track impl blocks: type_name -> method_name -> (params, return_type, body)
@@ -617,12 +806,40 @@ pub struct Interpreter {
     enums: BuiltinEnums,
     // Module cache: module_path -> exported_values
     module_cache: HashMap<String, HashMap<String, Value>>,
+    // Files still being loaded by the imports that led to this module, outermost first
+    import_chain: Vec<String>,
     // Current module's exports
     exports: HashMap<String, Value>,
     // Enabled features for gating
     enabled_features: std::collections::HashSet<String>,
     // Control flow: set when a `return` statement is executed
     returning: Option<Value>,
+    // Modules bundled into a single file by `loft bundle`
+    bundle: Option<std::sync::Arc<crate::bundle::Bundle>>,
+    // Call timing, when running with `--profile`
+    profiler: Option<profiler::Profiler>,
+    // User functions currently running, outermost first, for error backtraces
+    call_stack: Vec<CallFrame>,
+    // Call depth of each enclosing `try` block, where `?` throws instead of returning a value
+    try_blocks: Vec<usize>,
+    // Expressions scheduled with `defer`, with the call depth that runs them on exit
+    deferred: Vec<(usize, Value)>,
+    // Step and time limits, when the host set any, shared with the tasks,
+    // timers and workers the program starts
+    budget: Option<Arc<limits::Budget>>,
+    // Builtin modules the host took away, like `fs` in a sandbox
+    disabled_builtins: std::collections::HashSet<String>,
+    // Permissions the host gave this interpreter, which replace those of the
+    // thread while a program runs
+    permissions: Option<permissions::PermissionManager>,
+}
+
+/// A running call to a user function
+struct CallFrame {
+    function: String,
+    // The caller's source file and the span of the call in it, when called from code
+    caller_source: Option<Arc<SourceFile>>,
+    call_site: Option<Span>,
 }
 
 impl Default for Interpreter {
@@ -642,15 +859,23 @@ impl Interpreter {
 
         Self {
             env,
-            source_path: None,
-            source_code: None,
+            source: None,
             traits: init_stdlib_traits(),
             impl_methods: HashMap::new(),
             enums: init_builtin_enums(),
             module_cache: HashMap::new(),
+            import_chain: Vec::new(),
             exports: HashMap::new(),
             enabled_features: std::collections::HashSet::new(),
             returning: None,
+            bundle: None,
+            profiler: None,
+            call_stack: Vec::new(),
+            try_blocks: Vec::new(),
+            deferred: Vec::new(),
+            budget: None,
+            disabled_builtins: std::collections::HashSet::new(),
+            permissions: None,
         }
     }
 
@@ -664,42 +889,152 @@ impl Interpreter {
 
         Self {
             env,
-            source_path: Some(source_path.into()),
-            source_code: Some(source_code.into()),
+            source: Some(Arc::new(SourceFile {
+                path: source_path.into(),
+                code: source_code.into(),
+            })),
             traits: init_stdlib_traits(),
             impl_methods: HashMap::new(),
             enums: init_builtin_enums(),
             module_cache: HashMap::new(),
+            import_chain: Vec::new(),
             exports: HashMap::new(),
             enabled_features: std::collections::HashSet::new(),
             returning: None,
+            bundle: None,
+            profiler: None,
+            call_stack: Vec::new(),
+            try_blocks: Vec::new(),
+            deferred: Vec::new(),
+            budget: None,
+            disabled_builtins: std::collections::HashSet::new(),
+            permissions: None,
         }
     }
 
     pub fn with_features(mut self, features: Vec<String>) -> Self {
-        let feature_set: std::collections::HashSet<String> = features.into_iter().collect();
-        self.enabled_features = feature_set.clone();
+        self.enabled_features = features.into_iter().collect();
 
         // Re-initialize builtins with the new features
+        self.load_builtins();
+        self
+    }
+
+    /// Start over from an environment holding the builtins the enabled
+    /// features allow, without the disabled ones
+    fn load_builtins(&mut self) {
         self.env = Environment::new();
-        let features_slice: Vec<String> = self.enabled_features.iter().cloned().collect();
-        for (name, value) in init_builtins(Some(&features_slice)) {
-            self.env.set(name, value);
+        let features: Vec<String> = self.enabled_features.iter().cloned().collect();
+        for (name, value) in init_builtins(Some(&features)) {
+            if !self.disabled_builtins.contains(&name) {
+                self.env.set(name, value);
+            }
+        }
+    }
+
+    /// Copy of this interpreter's state for evaluating an expression on another thread
+    fn fork(&self) -> Interpreter {
+        Interpreter {
+            env: self.env.clone(),
+            source: self.source.clone(),
+            traits: self.traits.clone(),
+            impl_methods: self.impl_methods.clone(),
+            enums: self.enums.clone(),
+            module_cache: self.module_cache.clone(),
+            import_chain: self.import_chain.clone(),
+            exports: HashMap::new(),
+            enabled_features: self.enabled_features.clone(),
+            returning: None,
+            bundle: self.bundle.clone(),
+            profiler: None,
+            call_stack: Vec::new(),
+            try_blocks: Vec::new(),
+            deferred: Vec::new(),
+            budget: self.budget.clone(),
+            disabled_builtins: self.disabled_builtins.clone(),
+            // Copies run on threads that take the permissions along
+            permissions: None,
+        }
+    }
+
+    /// Point errors and relative imports of the code evaluated next at another source file
+    pub fn set_source(&mut self, source_path: impl Into<String>, source_code: impl Into<String>) {
+        self.source = Some(Arc::new(SourceFile {
+            path: source_path.into(),
+            code: source_code.into(),
+        }));
+    }
+
+    /// Resolve imports from the modules of a bundle instead of the file system
+    pub fn with_bundle(mut self, bundle: std::sync::Arc<crate::bundle::Bundle>) -> Self {
+        self.bundle = Some(bundle);
+        self
+    }
+
+    /// Stop the program with an error once it runs longer than `limits` allow
+    pub fn with_limits(mut self, limits: limits::ExecutionLimits) -> Self {
+        self.set_limits(limits);
+        self
+    }
+
+    /// Replace the limits, starting the step count and the clock over
+    pub fn set_limits(&mut self, limits: limits::ExecutionLimits) {
+        self.budget = (!limits.is_unlimited()).then(|| Arc::new(limits::Budget::new(limits)));
+    }
+
+    /// Forget everything the code run so far defined, as if the interpreter
+    /// was just created. Features, the bundle, the profiler and the limits are
+    /// kept, and the limits start over.
+    pub fn reset(&mut self) {
+        self.load_builtins();
+        self.traits = init_stdlib_traits();
+        self.impl_methods.clear();
+        self.enums = init_builtin_enums();
+        self.module_cache.clear();
+        self.import_chain.clear();
+        self.exports.clear();
+        self.returning = None;
+        self.call_stack.clear();
+        self.try_blocks.clear();
+        self.deferred.clear();
+        if let Some(budget) = &self.budget {
+            self.budget = Some(Arc::new(limits::Budget::new(budget.limits())));
         }
+    }
+
+    /// Count one step against the limits
+    fn step(&mut self) -> RuntimeResult<()> {
+        let Some(budget) = &self.budget else {
+            return Ok(());
+        };
+        let env = &self.env;
+        let result = budget
+            .step()
+            .and_then(|()| budget.check_memory(|| env.approx_size()));
+        result.map_err(|message| self.error(message))
+    }
 
+    /// Record call counts and timing for every function called
+    pub fn with_profiler(mut self) -> Self {
+        self.profiler = Some(profiler::Profiler::new());
         self
     }
 
+    /// The timing recorded so far, if profiling is enabled
+    pub fn profiler(&self) -> Option<&profiler::Profiler> {
+        self.profiler.as_ref()
+    }
+
     fn check_gated(&self, attr: &crate::parser::Attribute) -> bool {
         if attr.name != "gated" {
             return true;
         }
 
         fn eval_gated(expr: &Expr, enabled: &std::collections::HashSet<String>) -> bool {
-            match expr {
-                Expr::Ident(name) => enabled.contains(name),
-                Expr::Call { func, args } => {
-                    if let Expr::Ident(ref name) = **func {
+            match &expr.kind {
+                ExprKind::Ident(name) => enabled.contains(name),
+                ExprKind::Call { func, args } => {
+                    if let ExprKind::Ident(ref name) = func.kind {
                         match name.as_str() {
                             "all" => args.iter().all(|arg| eval_gated(arg, enabled)),
                             "any" => args.iter().any(|arg| eval_gated(arg, enabled)),
@@ -720,6 +1055,43 @@ impl Interpreter {
     }
 
     pub fn eval_program(&mut self, stmts: Vec<Stmt>) -> RuntimeResult<Value> {
+        // Reject matches that miss enum variants or booleans before running anything
+        if let Some(report) = crate::parser::exhaustiveness::check_program(&stmts).first() {
+            // Point at the `match` keyword, as the checker does
+            let keyword = Span {
+                end: report.span.start + "match".len(),
+                ..report.span
+            };
+            let error = self.locate(self.error(report.message()), keyword);
+            return Err(error.with_code("non_exhaustive_match"));
+        }
+
+        // The program's budget and permissions hold on this thread, and on the
+        // threads it starts, while it runs
+        let outer_budget = limits::replace_budget(self.budget.clone());
+        let outer_permissions = self
+            .permissions
+            .take()
+            .map(|permissions| permission_context::replace_permissions(Some(permissions)));
+
+        // Code deferred at the top level runs once the program is done
+        let result = self.run_program(stmts);
+        let result = self.run_deferred(result);
+
+        if let Some(outer) = outer_permissions {
+            self.permissions = permission_context::replace_permissions(outer);
+        }
+        // Unless this is a worker of another program, stop what the program
+        // left running and go on with a budget of its own
+        if let (None, Some(budget)) = (&outer_budget, &self.budget) {
+            budget.finish();
+            self.budget = Some(Arc::new(budget.resume()));
+        }
+        limits::replace_budget(outer_budget);
+        result
+    }
+
+    fn run_program(&mut self, stmts: Vec<Stmt>) -> RuntimeResult<Value> {
         let mut last_value = Value::Unit;
         for stmt in stmts {
             last_value = self.eval_stmt(stmt)?;
@@ -736,8 +1108,23 @@ impl Interpreter {
             if params.is_empty() {
                 // Call main function
                 self.env.push_scope();
-                let result = self.eval_stmt(*body)?;
-                let result = self.returning.take().unwrap_or(result);
+                if let Some(profiler) = &mut self.profiler {
+                    profiler.enter("main");
+                }
+                self.call_stack.push(CallFrame {
+                    function: "main".to_string(),
+                    caller_source: None,
+                    call_site: None,
+                });
+                let result = self
+                    .eval_stmt(*body)
+                    .map(|value| self.returning.take().unwrap_or(value));
+                let result = self.run_deferred(result).map_err(|e| self.trace(e));
+                self.call_stack.pop();
+                if let Some(profiler) = &mut self.profiler {
+                    profiler.exit();
+                }
+                let result = result?;
                 self.env.pop_scope();
 
                 // If async, wrap result in a Promise
@@ -753,12 +1140,79 @@ impl Interpreter {
     }
 
     pub fn eval_stmt(&mut self, stmt: Stmt) -> RuntimeResult<Value> {
+        let span = stmt.span;
+        self.step()
+            .and_then(|_| self.eval_stmt_kind(stmt.kind))
+            .map_err(|e| self.locate(e, span))
+    }
+
+    fn eval_stmt_kind(&mut self, stmt: StmtKind) -> RuntimeResult<Value> {
         match stmt {
-            Stmt::ImportDecl { path } => {
-                // Handle module imports
-                self.load_module(&path)
+            StmtKind::ImportDecl {
+                path,
+                alias,
+                items,
+                is_exported,
+            } => {
+                // Builtins are always in scope; importing one just binds it by name
+                let builtin = match self.env.get(&path[0]) {
+                    Some(Value::Builtin(builtin)) if is_builtin_module(&path) => {
+                        Some(builtin.clone())
+                    }
+                    _ => None,
+                };
+                let exports = match &builtin {
+                    Some(builtin) => builtin_exports(builtin),
+                    None if self.disabled_builtins.contains(&path[0])
+                        && is_builtin_module(&path) =>
+                    {
+                        return Err(self.error(format!(
+                            "The '{}' builtin is disabled in this interpreter",
+                            path[0]
+                        )));
+                    }
+                    None => self.load_module(&path)?,
+                };
+
+                let bindings = match items {
+                    // learn { a, b as c } from "module" binds just those exports
+                    Some(items) => items
+                        .iter()
+                        .map(|item| {
+                            let value = exports.get(&item.name).cloned().ok_or_else(|| {
+                                self.error(format!(
+                                    "Module '{}' does not export '{}'",
+                                    path.join("::"),
+                                    item.name
+                                ))
+                            })?;
+                            Ok((item.local_name().to_string(), value))
+                        })
+                        .collect::<RuntimeResult<Vec<_>>>()?,
+                    // learn "module" [as name] binds the module namespace
+                    None => {
+                        let module_name = import_binding_name(&path);
+                        let module_value = match builtin {
+                            Some(builtin) => Value::Builtin(builtin),
+                            None => Value::Module {
+                                name: module_name.clone(),
+                                exports,
+                            },
+                        };
+                        vec![(alias.unwrap_or(module_name), module_value)]
+                    }
+                };
+
+                for (name, value) in bindings {
+                    // teach learn ... re-exports the imported names
+                    if is_exported {
+                        self.exports.insert(name.clone(), value.clone());
+                    }
+                    self.env.set(name, value);
+                }
+                Ok(Value::Unit)
             }
-            Stmt::VarDecl {
+            StmtKind::VarDecl {
                 name,
                 var_type: _,
                 mutable: _,
@@ -772,7 +1226,7 @@ impl Interpreter {
                 self.env.set(name, val);
                 Ok(Value::Unit)
             }
-            Stmt::ConstDecl {
+            StmtKind::ConstDecl {
                 name,
                 const_type: _,
                 value,
@@ -781,19 +1235,19 @@ impl Interpreter {
                 self.env.set(name, val);
                 Ok(Value::Unit)
             }
-            Stmt::Assign { name, value } => {
+            StmtKind::Assign { name, value } => {
                 let val = self.eval_expr(value)?;
                 self.env.update(&name, val)?;
                 Ok(Value::Unit)
             }
-            Stmt::AttrStmt { attr, stmt } => {
+            StmtKind::AttrStmt { attr, stmt } => {
                 if self.check_gated(&attr) {
                     self.eval_stmt(*stmt)
                 } else {
                     Ok(Value::Unit)
                 }
             }
-            Stmt::If {
+            StmtKind::If {
                 condition,
                 then_branch,
                 else_branch,
@@ -807,7 +1261,7 @@ impl Interpreter {
                     Ok(Value::Unit)
                 }
             }
-            Stmt::While { condition, body } => {
+            StmtKind::While { condition, body } => {
                 while self.eval_expr(condition.clone())?.is_truthy() {
                     self.eval_stmt(*body.clone())?;
                     if self.returning.is_some() {
@@ -816,7 +1270,7 @@ impl Interpreter {
                 }
                 Ok(Value::Unit)
             }
-            Stmt::Return(expr) => {
+            StmtKind::Return(expr) => {
                 let val = if let Some(e) = expr {
                     self.eval_expr(e)?
                 } else {
@@ -825,8 +1279,8 @@ impl Interpreter {
                 self.returning = Some(val.clone());
                 Ok(val)
             }
-            Stmt::Expr(expr) => self.eval_expr(expr),
-            Stmt::Block(stmts) => {
+            StmtKind::Expr(expr) => self.eval_expr(expr),
+            StmtKind::Block(stmts) => {
                 self.env.push_scope();
                 let mut last_value = Value::Unit;
                 for stmt in stmts {
@@ -838,14 +1292,17 @@ impl Interpreter {
                 self.env.pop_scope();
                 Ok(last_value)
             }
-            Stmt::FunctionDecl {
+            StmtKind::FunctionDecl {
                 name,
                 params,
-                body,
+                variadic,
+                mut body,
                 is_async,
                 is_exported,
                 ..
             } => {
+                resolve::resolve_function(params.iter().map(|(n, _)| n.as_str()), &mut body);
+
                 // Store function in environment
                 let func_value = Value::Function {
                     name: name.clone(),
@@ -853,8 +1310,10 @@ impl Interpreter {
                         .iter()
                         .map(|(n, t)| (n.clone(), format!("{:?}", t)))
                         .collect(),
+                    variadic,
                     body: body.clone(),
                     is_async,
+                    source: self.source.clone(),
                 };
                 self.env.set(name.clone(), func_value.clone());
 
@@ -865,12 +1324,12 @@ impl Interpreter {
 
                 Ok(Value::Unit)
             }
-            Stmt::TraitDecl { name, methods } => {
+            StmtKind::TraitDecl { name, methods } => {
                 // Store trait declaration
                 self.traits.insert(name, methods);
                 Ok(Value::Unit)
             }
-            Stmt::ImplBlock {
+            StmtKind::ImplBlock {
                 type_name,
                 trait_name,
                 methods,
@@ -896,18 +1355,18 @@ impl Interpreter {
 
                             // Find implementation
                             let implementation = methods.iter().find(|m| {
-                                if let Stmt::FunctionDecl { name, .. } = m {
+                                if let StmtKind::FunctionDecl { name, .. } = &m.kind {
                                     name == t_method_name
                                 } else {
                                     false
                                 }
                             });
 
-                            if let Some(Stmt::FunctionDecl {
+                            if let Some(StmtKind::FunctionDecl {
                                 params: impl_params,
                                 return_type: impl_return_type,
                                 ..
-                            }) = implementation
+                            }) = implementation.map(|m| &m.kind)
                             {
                                 // Validate signature
                                 // 1. Check parameter count
@@ -922,7 +1381,10 @@ impl Interpreter {
                                 for (i, (impl_name, impl_type)) in impl_params.iter().enumerate() {
                                     let (_, t_type) = &t_params[i];
                                     let is_any = matches!(t_type, Type::Named(n) if n == "any");
-                                    if !is_any && impl_type != t_type {
+                                    // `Self` in the trait is the implementing type
+                                    let is_self = matches!(t_type, Type::Named(n) if n == "Self")
+                                        && matches!(impl_type, Type::Named(n) if *n == type_name);
+                                    if !is_any && !is_self && impl_type != t_type {
                                         return Err(RuntimeError::new(format!(
                                             "Parameter '{}' of method '{}' has incorrect type. Expected {:?}, found {:?}",
                                             impl_name, t_method_name, t_type, impl_type
@@ -957,6 +1419,22 @@ impl Interpreter {
                     }
                 }
 
+                // Rest parameters are only bound for functions
+                let variadic_method = methods.iter().find_map(|m| match &m.kind {
+                    StmtKind::FunctionDecl {
+                        name,
+                        variadic: true,
+                        ..
+                    } => Some(name),
+                    _ => None,
+                });
+                if let Some(name) = variadic_method {
+                    return Err(self.error(format!(
+                        "Method '{}' cannot have a rest parameter, only functions can",
+                        name
+                    )));
+                }
+
                 // Store methods for this type (with optional trait association)
                 let type_methods = self
                     .impl_methods
@@ -965,28 +1443,72 @@ impl Interpreter {
 
                 // Process each method in the impl block
                 for method_stmt in methods {
-                    if let Stmt::FunctionDecl {
+                    if let StmtKind::FunctionDecl {
                         name: method_name,
                         params,
                         return_type,
-                        body,
+                        mut body,
                         ..
-                    } = method_stmt
+                    } = method_stmt.kind
                     {
+                        if is_method(&params) {
+                            // Methods run with `self` in place of their first parameter
+                            let names = params.iter().skip(1).map(|(n, _)| n.as_str());
+                            resolve::resolve_function(
+                                std::iter::once("self").chain(names),
+                                &mut body,
+                            );
+                        } else {
+                            let names = params.iter().map(|(n, _)| n.as_str());
+                            resolve::resolve_function(names, &mut body);
+                        }
+
                         // Store the method with its signature
                         type_methods
                             .insert(method_name, (params, return_type, body, trait_name.clone()));
                     }
                 }
 
+                // Trait methods the impl leaves out fall back to their default bodies
+                if let Some(trait_methods) = trait_name.as_ref().and_then(|t| self.traits.get(t)) {
+                    for method in trait_methods {
+                        if let TraitMethod::Default {
+                            name,
+                            params,
+                            return_type,
+                            body,
+                        } = method
+                        {
+                            if type_methods.contains_key(name) {
+                                continue;
+                            }
+                            let mut body = body.clone();
+                            let names = params.iter().skip(1).map(|(n, _)| n.as_str());
+                            resolve::resolve_function(
+                                std::iter::once("self").chain(names),
+                                &mut body,
+                            );
+                            type_methods.insert(
+                                name.clone(),
+                                (
+                                    params.clone(),
+                                    Some(return_type.clone()),
+                                    body,
+                                    trait_name.clone(),
+                                ),
+                            );
+                        }
+                    }
+                }
+
                 Ok(Value::Unit)
             }
-            Stmt::StructDecl { .. } => {
+            StmtKind::StructDecl { .. } => {
                 // StructDecl is a declaration - it doesn't execute anything
                 // It's just a type definition
                 Ok(Value::Unit)
             }
-            Stmt::EnumDecl { name, variants } => {
+            StmtKind::EnumDecl { name, variants } => {
                 // Store the enum definition
                 self.enums.insert(name.clone(), variants.clone());
 
@@ -996,7 +1518,7 @@ impl Interpreter {
                     let full_name = format!("{}.{}", name, variant_name);
 
                     // For unit variants, store the variant directly
-                    // For tuple variants, we would need a constructor function (not yet implemented)
+                    // Tuple variants are constructed through Enum.Variant(...) field access
                     if variant_types.is_none() {
                         let variant = Value::EnumVariant {
                             enum_name: name.clone(),
@@ -1009,7 +1531,7 @@ impl Interpreter {
 
                 Ok(Value::Unit)
             }
-            Stmt::Match { expr, arms } => {
+            StmtKind::Match { expr, arms } => {
                 let value = self.eval_expr(expr)?;
 
                 // Try each pattern arm until one matches
@@ -1029,7 +1551,11 @@ impl Interpreter {
                 // No pattern matched
                 Err(self.error("Match expression did not match any pattern".to_string()))
             }
-            Stmt::For { var, iterable, body } => {
+            StmtKind::For {
+                var,
+                iterable,
+                body,
+            } => {
                 let iterable_val = self.eval_expr(iterable)?;
                 match iterable_val {
                     Value::Array(items) => {
@@ -1044,218 +1570,404 @@ impl Interpreter {
                         }
                         Ok(Value::Unit)
                     }
+                    Value::Map(entries) => {
+                        // Iterating a map yields [key, value] pairs
+                        for (key, value) in entries {
+                            self.env.push_scope();
+                            self.env.set(var.clone(), Value::Array(vec![key, value]));
+                            self.eval_stmt(*body.clone())?;
+                            self.env.pop_scope();
+                            if self.returning.is_some() {
+                                break;
+                            }
+                        }
+                        Ok(Value::Unit)
+                    }
+                    Value::Set(items) => {
+                        for item in items {
+                            self.env.push_scope();
+                            self.env.set(var.clone(), item.into_value());
+                            self.eval_stmt(*body.clone())?;
+                            self.env.pop_scope();
+                            if self.returning.is_some() {
+                                break;
+                            }
+                        }
+                        Ok(Value::Unit)
+                    }
                     _ => Err(self.error(format!("Value is not iterable: {:?}", iterable_val))),
                 }
             }
-            Stmt::Break => {
-                // Return a special value to indicate a break
-                // But this might require more changes in all loop evaluations
+            StmtKind::Try {
+                body,
+                catch,
+                finally,
+            } => self.eval_try(*body, catch, finally.map(|f| *f)),
+            StmtKind::Throw(expr) => {
+                let value = self.eval_expr(expr)?;
+                Err(self.throw(value)?)
+            }
+            StmtKind::Defer(expr) => {
+                // Captured like a closure so it can run after the scopes it was written in close
+                let deferred = Value::Closure {
+                    params: Vec::new(),
+                    return_type: None,
+                    body: Box::new(expr),
+                    captured_env: self.env.capture_all(),
+                };
+                self.deferred.push((self.call_stack.len(), deferred));
                 Ok(Value::Unit)
             }
-            Stmt::Continue => {
+            StmtKind::Break => {
+                // Return a special value to indicate a break
+                // But this might require more changes in all loop evaluations
                 Ok(Value::Unit)
             }
+            StmtKind::Continue => Ok(Value::Unit),
         }
     }
 
     pub fn eval_expr(&mut self, expr: Expr) -> RuntimeResult<Value> {
-        match expr {
-            Expr::Number(n) => Ok(Value::Number(n)),
-            Expr::String(s) => Ok(Value::String(s)),
-            Expr::Boolean(b) => Ok(Value::Boolean(b)),
-            Expr::Ident(name) => self
-                .env
-                .get(&name)
-                .cloned()
-                .ok_or_else(|| self.error(format!("Variable '{}' not found", name))),
-            Expr::BinOp { op, left, right } => {
-                let left_val = self.eval_expr(*left)?;
-                let right_val = self.eval_expr(*right)?;
-                self.eval_binop(&op, left_val, right_val)
-            }
-            Expr::Call { func, args } => {
-                // Always evaluate func first (which could be a FieldAccess or any expr)
-                let func_val = self.eval_expr(*func)?;
-                let arg_vals: RuntimeResult<Vec<_>> =
-                    args.into_iter().map(|arg| self.eval_expr(arg)).collect();
-                let arg_vals = arg_vals?;
-                match func_val {
-                    Value::Function {
-                        params,
-                        body,
-                        is_async,
-                        name,
-                        ..
-                    } => {
-                        // Check argument count
-                        if params.len() != arg_vals.len() {
-                            return Err(self.error(format!(
-                                "Function '{}' expects {} arguments, got {}",
-                                name,
-                                params.len(),
-                                arg_vals.len()
-                            )));
-                        }
-
-                        // Optional: Check argument types if type annotations exist
-                        // Note: params are stored as (name, type_string)
-                        // For now, we skip type validation since we'd need to parse the type strings
-
-                        // Create new scope for function
-                        self.env.push_scope();
-
-                        // Bind parameters
-                        for ((param_name, _), arg_val) in params.iter().zip(arg_vals.iter()) {
-                            self.env.set(param_name.clone(), arg_val.clone());
-                        }
-
-                        // Execute function body
-                        let result = self.eval_stmt(*body)?;
-                        // Clear the return signal — we've exited the function
-                        let result = self.returning.take().unwrap_or(result);
-
-                        self.env.pop_scope();
-
-                        // If async, wrap result in a Promise
-                        if is_async {
-                            Ok(Value::Promise(Box::new(result)))
-                        } else {
-                            Ok(result)
-                        }
-                    }
-                    Value::BuiltinFn(builtin_fn) => builtin_fn(&arg_vals),
-                    Value::BoundMethod {
-                        object,
-                        method_name,
-                        method,
-                        ..
-                    } => {
-                        // Special case for term.print and term.println
-                        // We intercept these to apply user-defined Printable trait implementations
-                        if (method_name == "print" || method_name == "println")
-                            && matches!(&*object, Value::Builtin(b) if b.name == "term")
-                        {
-                            let mut stringified_args = Vec::with_capacity(arg_vals.len());
-                            for arg in arg_vals {
-                                stringified_args.push(Value::String(self.value_to_string(&arg)?));
-                            }
-                            // Call the bound method with the object as 'this' and the stringified arguments
-                            method(&object, &stringified_args)
-                        } else {
-                            // Call the bound method with the object as 'this'
-                            method(&object, &arg_vals)
-                        }
-                    }
-                    Value::UserMethod {
-                        object,
-                        params,
-                        body,
-                        ..
-                    } => {
-                        // Check argument count (exclude 'self' parameter which is already bound)
-                        // The params includes 'self', but we don't include it in arg_vals
-                        if params.len() != arg_vals.len() + 1 {
-                            return Err(self.error(format!(
-                                "Expected {} arguments (plus self), got {}",
-                                params.len() - 1,
-                                arg_vals.len()
-                            )));
-                        }
-
-                        // Create new scope for method
-                        self.env.push_scope();
+        let span = expr.span;
+        self.step()
+            .and_then(|_| self.eval_expr_kind(expr.kind, span))
+            .map_err(|e| self.locate(e, span))
+    }
 
-                        // Bind 'self' to the object
-                        self.env.set("self".to_string(), (*object).clone());
+    /// Evaluate a chain of calls and field accesses like `a.b().c.d()` from
+    /// its start rather than recursing into it, so a long one doesn't take a
+    /// stack frame per link
+    fn eval_chain(&mut self, kind: ExprKind, span: Span) -> RuntimeResult<Value> {
+        enum Link {
+            Call(Vec<Expr>),
+            Field(String),
+        }
 
-                        // Bind other parameters
-                        for ((param_name, _), arg_val) in params.iter().skip(1).zip(arg_vals.iter())
-                        {
-                            self.env.set(param_name.clone(), arg_val.clone());
-                        }
+        let mut links = Vec::new();
+        let mut next = Expr::new(kind, span);
+        let start = loop {
+            // Fields of a name may be enum variants or associated functions,
+            // which evaluating the access itself looks for
+            next = match next.kind {
+                ExprKind::Call { func, args } => {
+                    links.push((Link::Call(args), next.span));
+                    *func
+                }
+                ExprKind::FieldAccess { object, field }
+                    if !matches!(object.kind, ExprKind::Ident(_) | ExprKind::Local { .. }) =>
+                {
+                    links.push((Link::Field(field), next.span));
+                    *object
+                }
+                kind => break Expr::new(kind, next.span),
+            };
+            // The first link was counted when it was evaluated
+            if links.len() > 1 {
+                self.step().map_err(|e| self.locate(e, next.span))?;
+            }
+        };
 
-                        // Execute method body
-                        let result = self.eval_stmt(*body)?;
-                        let result = self.returning.take().unwrap_or(result);
+        let mut value = self.eval_expr(start)?;
+        while let Some((link, span)) = links.pop() {
+            value = match link {
+                Link::Call(args) => self
+                    .eval_elements(args)
+                    .and_then(|args| self.call_from(Some(span), value, args)),
+                Link::Field(field) => self.member(value, field),
+            }
+            .map_err(|e| self.locate(e, span))?;
+        }
+        Ok(value)
+    }
 
-                        self.env.pop_scope();
+    /// A field or method of a value
+    fn member(&mut self, obj_val: Value, field: String) -> RuntimeResult<Value> {
+        if field == "clone" && self.has_default_clone(&obj_val) {
+            return Ok(Value::BoundMethod {
+                object: Box::new(obj_val),
+                method_name: field,
+                method: builtins::object::clone_method,
+            });
+        }
+        match obj_val {
+            Value::Builtin(builtin_struct) => {
+                // Check if it's a field
+                if let Some(field_val) = builtin_struct.fields.get(&field) {
+                    Ok(field_val.clone())
+                } else if let Some(method) = builtin_struct.methods.get(&field) {
+                    // Return a bound method
+                    Ok(Value::BoundMethod {
+                        object: Box::new(Value::Builtin(builtin_struct.clone())),
+                        method_name: field.clone(),
+                        method: *method,
+                    })
+                } else {
+                    Err(self.error(format!(
+                        "Field or method '{}' not found on builtin struct '{}'",
+                        field, builtin_struct.name
+                    )))
+                }
+            }
+            Value::Struct { fields, name } => {
+                // First check for fields
+                if let Some(field_val) = fields.get(&field) {
+                    return Ok(field_val.clone());
+                }
 
-                        Ok(result)
-                    }
-                    Value::Closure {
-                        params,
-                        body,
-                        captured_env,
-                        ..
-                    } => {
-                        // Check argument count
-                        if params.len() != arg_vals.len() {
+                // Then check for user-defined methods in impl blocks
+                if let Some(methods) = self.impl_methods.get(&name) {
+                    if let Some((params, return_type, body, _)) = methods.get(&field) {
+                        if !is_method(params) {
                             return Err(self.error(format!(
-                                "Expected {} arguments, got {}",
-                                params.len(),
-                                arg_vals.len()
+                                "'{}' is an associated function of '{}', call it as {}.{}(...)",
+                                field, name, name, field
                             )));
                         }
-
-                        // Create new scope for closure
-                        self.env.push_scope();
-
-                        // First, restore the captured environment
-                        for (name, value) in captured_env {
-                            self.env.set(name, value);
-                        }
-
-                        // Then bind parameters (which can shadow captured variables)
-                        for ((param_name, _), arg_val) in params.iter().zip(arg_vals.iter()) {
-                            self.env.set(param_name.clone(), arg_val.clone());
-                        }
-
-                        // Execute closure body (which is an expression, not a statement)
-                        let result = self.eval_expr(*body)?;
-
-                        self.env.pop_scope();
-
-                        Ok(result)
-                    }
-
-                    Value::Builtin(builtin_struct) => {
-                        // This shouldn't happen - builtins aren't directly callable
-                        Err(self.error(format!(
-                            "Cannot call builtin struct '{}' directly. Use its methods instead.",
-                            builtin_struct.name
-                        )))
+                        return Ok(Value::UserMethod {
+                            object: Box::new(Value::Struct {
+                                fields,
+                                name: name.clone(),
+                            }),
+                            method_name: field.clone(),
+                            params: params.clone(),
+                            return_type: return_type.clone(),
+                            body: body.clone(),
+                        });
                     }
-                    Value::EnumConstructor {
-                        enum_name,
-                        variant_name,
-                        arity,
-                    } => {
-                        // Check argument count
-                        if arg_vals.len() != arity {
-                            return Err(self.error(format!(
-                                "Enum variant {}.{} expects {} arguments, got {}",
-                                enum_name,
-                                variant_name,
-                                arity,
-                                arg_vals.len()
-                            )));
-                        }
+                }
 
-                        // Construct the enum variant
-                        Ok(Value::EnumVariant {
-                            enum_name,
-                            variant_name,
-                            values: arg_vals,
-                        })
-                    }
-                    _ => Err(self.error(format!("Cannot call value of type {:?}", func_val))),
+                Err(self.error(format!(
+                    "Field or method '{}' not found on struct '{}'",
+                    field, name
+                )))
+            }
+            Value::Array(_) => {
+                // Handle array methods
+                use crate::runtime::builtins::array;
+                let array_builtin = array::create_array_builtin();
+                if let Some(method) = array_builtin.methods.get(&field) {
+                    Ok(Value::BoundMethod {
+                        object: Box::new(obj_val.clone()),
+                        method_name: field.clone(),
+                        method: *method,
+                    })
+                } else {
+                    Err(self.error(format!("Method '{}' not found on array", field)))
                 }
             }
-            Expr::FieldAccess { object, field } => {
-                // Special case: check if this is an enum variant access (e.g., Color.Red)
-                if let Expr::Ident(ref type_name) = *object {
-                    if let Some(variants) = self.enums.get(type_name) {
-                        // Check if the field is a valid variant
+            Value::Map(_) => {
+                // Handle map methods
+                use crate::runtime::builtins::collections::map;
+                let map_builtin = map::create_map_builtin();
+                if let Some(method) = map_builtin.methods.get(&field) {
+                    Ok(Value::BoundMethod {
+                        object: Box::new(obj_val.clone()),
+                        method_name: field.clone(),
+                        method: *method,
+                    })
+                } else {
+                    Err(self.error(format!("Method '{}' not found on map", field)))
+                }
+            }
+            Value::Set(_) => {
+                // Handle set methods
+                use crate::runtime::builtins::collections::set;
+                let set_builtin = set::create_set_builtin();
+                if let Some(method) = set_builtin.methods.get(&field) {
+                    Ok(Value::BoundMethod {
+                        object: Box::new(obj_val.clone()),
+                        method_name: field.clone(),
+                        method: *method,
+                    })
+                } else {
+                    Err(self.error(format!("Method '{}' not found on Set", field)))
+                }
+            }
+            Value::String(_) => {
+                // Handle string methods
+                use crate::runtime::builtins::string;
+                let string_builtin = string::create_string_builtin();
+                if let Some(method) = string_builtin.methods.get(&field) {
+                    Ok(Value::BoundMethod {
+                        object: Box::new(obj_val.clone()),
+                        method_name: field.clone(),
+                        method: *method,
+                    })
+                } else {
+                    Err(self.error(format!("Method '{}' not found on string", field)))
+                }
+            }
+            Value::Number(_) => {
+                // Handle number methods
+                use crate::runtime::builtins::number;
+                let number_builtin = number::create_number_builtin();
+                if let Some(method) = number_builtin.methods.get(&field) {
+                    Ok(Value::BoundMethod {
+                        object: Box::new(obj_val.clone()),
+                        method_name: field.clone(),
+                        method: *method,
+                    })
+                } else {
+                    Err(self.error(format!("Method '{}' not found on num", field)))
+                }
+            }
+            Value::Bytes(_) => {
+                // Handle bytes methods
+                use crate::runtime::builtins::bytes;
+                let bytes_builtin = bytes::create_bytes_builtin();
+                if let Some(method) = bytes_builtin.methods.get(&field) {
+                    Ok(Value::BoundMethod {
+                        object: Box::new(obj_val.clone()),
+                        method_name: field.clone(),
+                        method: *method,
+                    })
+                } else {
+                    Err(self.error(format!("Method '{}' not found on Bytes", field)))
+                }
+            }
+            Value::DateTime(_) => {
+                // Handle DateTime methods
+                use crate::runtime::builtins::datetime;
+                let datetime_builtin = datetime::create_datetime_builtin();
+                if let Some(method) = datetime_builtin.methods.get(&field) {
+                    Ok(Value::BoundMethod {
+                        object: Box::new(obj_val.clone()),
+                        method_name: field.clone(),
+                        method: *method,
+                    })
+                } else {
+                    Err(self.error(format!("Method '{}' not found on DateTime", field)))
+                }
+            }
+            Value::Module { exports, .. } => {
+                // Access module export
+                if let Some(value) = exports.get(&field) {
+                    Ok(value.clone())
+                } else {
+                    Err(self.error(format!("Export '{}' not found in module", field)))
+                }
+            }
+            Value::EnumVariant {
+                ref enum_name,
+                ref variant_name,
+                ref values,
+            } if enum_name == "Option" || enum_name == "Result" => {
+                // Dispatch native methods on Option<T> and Result<T, E>
+                let method: Option<BuiltinMethod> = match field.as_str() {
+                    "is_some" if enum_name == "Option" => Some(opt_is_some),
+                    "is_none" if enum_name == "Option" => Some(opt_is_none),
+                    "is_ok" if enum_name == "Result" => Some(res_is_ok),
+                    "is_err" if enum_name == "Result" => Some(res_is_err),
+                    "unwrap" => Some(opt_res_unwrap),
+                    "unwrap_or" => Some(opt_res_unwrap_or),
+                    "unwrap_err" if enum_name == "Result" => Some(res_unwrap_err),
+                    "expect" => Some(opt_res_expect),
+                    _ => None,
+                };
+                if let Some(m) = method {
+                    Ok(Value::BoundMethod {
+                        object: Box::new(Value::EnumVariant {
+                            enum_name: enum_name.clone(),
+                            variant_name: variant_name.clone(),
+                            values: values.clone(),
+                        }),
+                        method_name: field.clone(),
+                        method: m,
+                    })
+                } else {
+                    Err(self.error(format!(
+                        "Method '{}' not found on {}::{}",
+                        field, enum_name, variant_name
+                    )))
+                }
+            }
+            _ => Err(self.error(format!(
+                "Cannot access field on value of type {:?}",
+                obj_val
+            ))),
+        }
+    }
+
+    /// Evaluate call arguments or array elements, spreading `...array` in place
+    fn eval_elements(&mut self, exprs: Vec<Expr>) -> RuntimeResult<Vec<Value>> {
+        let mut values = Vec::with_capacity(exprs.len());
+        for expr in exprs {
+            match expr.kind {
+                ExprKind::Spread(inner) => {
+                    let span = inner.span;
+                    match self.eval_expr(*inner)? {
+                        Value::Array(items) => values.extend(items),
+                        other => {
+                            let error = self.error(format!(
+                                "Cannot spread a value of type {}, only arrays",
+                                self.type_of(&other)
+                            ));
+                            return Err(self.locate(error, span));
+                        }
+                    }
+                }
+                _ => values.push(self.eval_expr(expr)?),
+            }
+        }
+        Ok(values)
+    }
+
+    fn eval_expr_kind(&mut self, expr: ExprKind, span: Span) -> RuntimeResult<Value> {
+        match expr {
+            ExprKind::Number(n) => Ok(Value::Number(n)),
+            ExprKind::String(s) => Ok(Value::String(s)),
+            ExprKind::Boolean(b) => Ok(Value::Boolean(b)),
+            ExprKind::Ident(name) => self
+                .env
+                .get(&name)
+                .cloned()
+                .ok_or_else(|| self.error(format!("Variable '{}' not found", name))),
+            ExprKind::Local { name, depth, slot } => self
+                .env
+                .get_local(&name, depth, slot)
+                .cloned()
+                .ok_or_else(|| self.error(format!("Variable '{}' not found", name))),
+            ExprKind::BinOp { op, left, right } => {
+                // Walk down a chain like `a + b + c` rather than recursing into
+                // it, so a long one doesn't take a stack frame per operator
+                let mut links = vec![(op, *right, span)];
+                let mut left = *left;
+                while let ExprKind::BinOp {
+                    op,
+                    left: inner,
+                    right,
+                } = left.kind
+                {
+                    self.step().map_err(|e| self.locate(e, left.span))?;
+                    links.push((op, *right, left.span));
+                    left = *inner;
+                }
+
+                let mut value = self.eval_expr(left)?;
+                while let Some((op, right, span)) = links.pop() {
+                    let right_val = self.eval_expr(right)?;
+                    value = self
+                        .eval_binop(&op, value, right_val)
+                        .map_err(|e| self.locate(e, span))?;
+                }
+                Ok(value)
+            }
+            ExprKind::Call { func, args } => self.eval_chain(ExprKind::Call { func, args }, span),
+            ExprKind::FieldAccess { object, field }
+                if !matches!(object.kind, ExprKind::Ident(_) | ExprKind::Local { .. }) =>
+            {
+                self.eval_chain(ExprKind::FieldAccess { object, field }, span)
+            }
+            ExprKind::FieldAccess { object, field } => {
+                // Special case: check if this is an enum variant access (e.g., Color.Red)
+                if let ExprKind::Ident(ref type_name)
+                | ExprKind::Local {
+                    name: ref type_name,
+                    ..
+                } = object.kind
+                {
+                    if let Some(variants) = self.enums.get(type_name) {
+                        // Check if the field is a valid variant
                         for (variant_name, variant_types) in variants {
                             if variant_name == &field {
                                 // Return the variant or a constructor function
@@ -1284,131 +1996,81 @@ impl Interpreter {
                     }
                 }
 
-                // Normal field access
-                let obj_val = self.eval_expr(*object)?;
-                match obj_val {
-                    Value::Builtin(builtin_struct) => {
-                        // Check if it's a field
-                        if let Some(field_val) = builtin_struct.fields.get(&field) {
-                            Ok(field_val.clone())
-                        } else if let Some(method) = builtin_struct.methods.get(&field) {
-                            // Return a bound method
-                            Ok(Value::BoundMethod {
-                                object: Box::new(Value::Builtin(builtin_struct.clone())),
-                                method_name: field.clone(),
-                                method: *method,
-                            })
-                        } else {
-                            Err(self.error(format!(
-                                "Field or method '{}' not found on builtin struct '{}'",
-                                field, builtin_struct.name
-                            )))
+                // Associated functions are called on the type (e.g., Point.new)
+                if let ExprKind::Ident(ref type_name) = object.kind {
+                    let associated = self
+                        .impl_methods
+                        .get(type_name)
+                        .filter(|_| self.env.get(type_name).is_none())
+                        .and_then(|methods| methods.get(&field));
+                    if let Some((params, _, body, _)) = associated {
+                        if is_method(params) {
+                            return Err(self.error(format!(
+                                "'{}' is a method of '{}' and must be called on a value",
+                                field, type_name
+                            )));
                         }
+                        return Ok(Value::Function {
+                            name: format!("{}.{}", type_name, field),
+                            params: params
+                                .iter()
+                                .map(|(n, t)| (n.clone(), format!("{:?}", t)))
+                                .collect(),
+                            variadic: false,
+                            body: body.clone(),
+                            is_async: false,
+                            source: self.source.clone(),
+                        });
                     }
-                    Value::Struct { fields, name } => {
-                        // First check for fields
-                        if let Some(field_val) = fields.get(&field) {
-                            return Ok(field_val.clone());
-                        }
+                }
 
-                        // Then check for user-defined methods in impl blocks
-                        if let Some(methods) = self.impl_methods.get(&name) {
-                            if let Some((params, return_type, body, _)) = methods.get(&field) {
-                                return Ok(Value::UserMethod {
-                                    object: Box::new(Value::Struct {
-                                        fields,
-                                        name: name.clone(),
-                                    }),
-                                    method_name: field.clone(),
-                                    params: params.clone(),
-                                    return_type: return_type.clone(),
-                                    body: body.clone(),
-                                });
+                // `Trait.method(self)` runs a trait's default body, e.g. from an override
+                if let ExprKind::Ident(ref trait_name) = object.kind {
+                    let trait_methods = self
+                        .traits
+                        .get(trait_name)
+                        .filter(|_| self.env.get(trait_name).is_none());
+                    if let Some(trait_methods) = trait_methods {
+                        let method = trait_methods.iter().find(|m| match m {
+                            TraitMethod::Signature { name, .. }
+                            | TraitMethod::Default { name, .. } => *name == field,
+                        });
+                        return match method {
+                            Some(TraitMethod::Default { params, body, .. }) => {
+                                let mut body = body.clone();
+                                resolve::resolve_function(
+                                    params.iter().map(|(n, _)| n.as_str()),
+                                    &mut body,
+                                );
+                                Ok(Value::Function {
+                                    name: format!("{}.{}", trait_name, field),
+                                    params: params
+                                        .iter()
+                                        .map(|(n, t)| (n.clone(), format!("{:?}", t)))
+                                        .collect(),
+                                    variadic: false,
+                                    body,
+                                    is_async: false,
+                                    source: self.source.clone(),
+                                })
                             }
-                        }
-
-                        Err(self.error(format!(
-                            "Field or method '{}' not found on struct '{}'",
-                            field, name
-                        )))
-                    }
-                    Value::Array(_) => {
-                        // Handle array methods
-                        use crate::runtime::builtins::array;
-                        let array_builtin = array::create_array_builtin();
-                        if let Some(method) = array_builtin.methods.get(&field) {
-                            Ok(Value::BoundMethod {
-                                object: Box::new(obj_val.clone()),
-                                method_name: field.clone(),
-                                method: *method,
-                            })
-                        } else {
-                            Err(self.error(format!("Method '{}' not found on array", field)))
-                        }
-                    }
-                    Value::String(_) => {
-                        // Handle string methods
-                        use crate::runtime::builtins::string;
-                        let string_builtin = string::create_string_builtin();
-                        if let Some(method) = string_builtin.methods.get(&field) {
-                            Ok(Value::BoundMethod {
-                                object: Box::new(obj_val.clone()),
-                                method_name: field.clone(),
-                                method: *method,
-                            })
-                        } else {
-                            Err(self.error(format!("Method '{}' not found on string", field)))
-                        }
-                    }
-                    Value::Module { exports, .. } => {
-                        // Access module export
-                        if let Some(value) = exports.get(&field) {
-                            Ok(value.clone())
-                        } else {
-                            Err(self.error(format!("Export '{}' not found in module", field)))
-                        }
-                    }
-                    Value::EnumVariant {
-                        ref enum_name,
-                        ref variant_name,
-                        ref values,
-                    } if enum_name == "Option" || enum_name == "Result" => {
-                        // Dispatch native methods on Option<T> and Result<T, E>
-                        let method: Option<BuiltinMethod> = match field.as_str() {
-                            "is_some" if enum_name == "Option" => Some(opt_is_some),
-                            "is_none" if enum_name == "Option" => Some(opt_is_none),
-                            "is_ok" if enum_name == "Result" => Some(res_is_ok),
-                            "is_err" if enum_name == "Result" => Some(res_is_err),
-                            "unwrap" => Some(opt_res_unwrap),
-                            "unwrap_or" => Some(opt_res_unwrap_or),
-                            "unwrap_err" if enum_name == "Result" => Some(res_unwrap_err),
-                            "expect" => Some(opt_res_expect),
-                            _ => None,
+                            Some(TraitMethod::Signature { .. }) => Err(self.error(format!(
+                                "Method '{}' of trait '{}' has no default body",
+                                field, trait_name
+                            ))),
+                            None => Err(self.error(format!(
+                                "Method '{}' not found on trait '{}'",
+                                field, trait_name
+                            ))),
                         };
-                        if let Some(m) = method {
-                            Ok(Value::BoundMethod {
-                                object: Box::new(Value::EnumVariant {
-                                    enum_name: enum_name.clone(),
-                                    variant_name: variant_name.clone(),
-                                    values: values.clone(),
-                                }),
-                                method_name: field.clone(),
-                                method: m,
-                            })
-                        } else {
-                            Err(self.error(format!(
-                                "Method '{}' not found on {}::{}",
-                                field, enum_name, variant_name
-                            )))
-                        }
                     }
-                    _ => Err(self.error(format!(
-                        "Cannot access field on value of type {:?}",
-                        obj_val
-                    ))),
                 }
+
+                // Normal field access
+                let obj_val = self.eval_expr(*object)?;
+                self.member(obj_val, field)
             }
-            Expr::Block(stmts) => {
+            ExprKind::Block(stmts) => {
                 self.env.push_scope();
                 let mut last_value = Value::Unit;
                 for stmt in stmts {
@@ -1417,8 +2079,15 @@ impl Interpreter {
                 self.env.pop_scope();
                 Ok(last_value)
             }
-            Expr::UnaryOp { .. } => Err(self.error("Expression type not yet implemented")),
-            Expr::Lambda {
+            ExprKind::UnaryOp { op, expr } => match (op.as_str(), self.eval_expr(*expr)?) {
+                ("-", Value::Number(n)) => Ok(Value::Number(-n)),
+                (op, value) => Err(self.error(format!(
+                    "Cannot apply unary '{}' to a value of type {}",
+                    op,
+                    self.type_of(&value)
+                ))),
+            },
+            ExprKind::Lambda {
                 params,
                 return_type,
                 body,
@@ -1432,39 +2101,61 @@ impl Interpreter {
                     captured_env,
                 })
             }
-            Expr::Await(expr) => {
+            ExprKind::Await(expr) => {
                 // Evaluate the expression (should be a Promise)
                 let value = self.eval_expr(*expr)?;
                 match value {
                     Value::Promise(result) => {
-                        // Unlike JavaScript, our promises are already resolved
-                        // since async functions execute immediately
+                        // Promises returned by async functions are already resolved
                         Ok(*result)
                     }
+                    Value::Task(task) => {
+                        // Block until the scheduled work finishes, and then for
+                        // any promise it resulted in, as with `async time.sleep(10)`
+                        let mut result = task.wait()?;
+                        while let Value::Task(inner) = result {
+                            result = inner.wait()?;
+                        }
+                        match result {
+                            Value::Promise(result) => Ok(*result),
+                            result => Ok(result),
+                        }
+                    }
                     _ => Err(self.error(format!("Cannot await non-promise value: {:?}", value))),
                 }
             }
-            Expr::Async(expr) => {
-                // Evaluate the expression eagerly and wrap in a Promise
-                // This represents "eager async" - starts execution immediately
-                let result = self.eval_expr(*expr)?;
-                Ok(Value::Promise(Box::new(result)))
+            ExprKind::Async(expr) => {
+                // Eager async: the expression starts running on the scheduler immediately
+                let mut task_interpreter = self.fork();
+                task_interpreter.env.freeze();
+                Ok(Value::Task(scheduler::Task::spawn(move || {
+                    task_interpreter.eval_expr(*expr)
+                })))
             }
-            Expr::Lazy(expr) => {
-                // Create a lazy future that doesn't execute until awaited
-                // For now, we'll treat it similar to a regular promise
-                // In a full implementation, this would create an unevaluated thunk
-                let result = self.eval_expr(*expr)?;
-                Ok(Value::Promise(Box::new(result)))
+            ExprKind::Lazy(expr) => {
+                // Lazy async: the expression only runs once it is awaited or started
+                let mut task_interpreter = self.fork();
+                task_interpreter.env.freeze();
+                Ok(Value::Task(scheduler::Task::lazy(move || {
+                    task_interpreter.eval_expr(*expr)
+                })))
             }
-            Expr::ArrayLiteral(elements) => {
-                let mut array_values = Vec::new();
-                for element in elements {
-                    array_values.push(self.eval_expr(element)?);
+            ExprKind::ArrayLiteral(elements) => Ok(Value::Array(self.eval_elements(elements)?)),
+            ExprKind::MapLiteral(entries) => {
+                let mut map_entries: Vec<(Value, Value)> = Vec::new();
+                for (key_expr, value_expr) in entries {
+                    let key = self.eval_expr(key_expr)?;
+                    let value = self.eval_expr(value_expr)?;
+                    // Later duplicate keys overwrite earlier ones
+                    if let Some(entry) = map_entries.iter_mut().find(|(k, _)| *k == key) {
+                        entry.1 = value;
+                    } else {
+                        map_entries.push((key, value));
+                    }
                 }
-                Ok(Value::Array(array_values))
+                Ok(Value::Map(map_entries))
             }
-            Expr::StructLiteral { name, fields } => {
+            ExprKind::StructLiteral { name, fields } => {
                 let mut field_values = HashMap::new();
                 for (field_name, field_expr) in fields {
                     let value = self.eval_expr(field_expr)?;
@@ -1475,7 +2166,7 @@ impl Interpreter {
                     fields: field_values,
                 })
             }
-            Expr::Index { array, index } => {
+            ExprKind::Index { array, index } => {
                 let array_val = self.eval_expr(*array)?;
                 let index_val = self.eval_expr(*index)?;
 
@@ -1514,22 +2205,33 @@ impl Interpreter {
 
                 call_index_trait(&array_val, &index_val)
             }
-            Expr::TemplateLiteral { parts } => {
+            ExprKind::Slice { array, start, end } => {
+                let array = self.eval_expr(*array)?;
+                let start = start.map(|e| self.eval_expr(*e)).transpose()?;
+                let end = end.map(|e| self.eval_expr(*e)).transpose()?;
+                slice_value(&array, start.as_ref(), end.as_ref())
+            }
+            ExprKind::TemplateLiteral { parts } => {
                 let mut result = String::new();
                 for part in parts {
                     match part {
                         crate::parser::TemplatePart::Text(text) => {
                             result.push_str(&text);
                         }
-                        crate::parser::TemplatePart::Expression(expr) => {
+                        crate::parser::TemplatePart::Expression(expr, None) => {
                             let value = self.eval_expr(expr)?;
                             result.push_str(&self.value_to_string(&value)?);
                         }
+                        crate::parser::TemplatePart::Expression(expr, Some(spec)) => {
+                            let value = self.eval_expr(expr)?;
+                            let text = self.value_to_string(&value)?;
+                            result.push_str(&format::apply_format(&value, text, &spec)?);
+                        }
                     }
                 }
-                Ok(Value::String(result))
+                Ok(Value::String(result.into()))
             }
-            Expr::Match { expr, arms } => {
+            ExprKind::Match { expr, arms } => {
                 let value = self.eval_expr(*expr)?;
 
                 // Try each pattern arm until one matches
@@ -1549,7 +2251,23 @@ impl Interpreter {
                 // No pattern matched
                 Err(self.error("Match expression did not match any pattern".to_string()))
             }
-            Expr::Try(expr) => {
+            ExprKind::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                if self.eval_expr(*condition)?.is_truthy() {
+                    self.eval_expr(*then_branch)
+                } else if let Some(else_branch) = else_branch {
+                    self.eval_expr(*else_branch)
+                } else {
+                    Ok(Value::Unit)
+                }
+            }
+            ExprKind::Spread(_) => {
+                Err(self.error("'...' can only spread into call arguments and array literals"))
+            }
+            ExprKind::Try(expr) => {
                 // Error propagation operator (?)
                 // Evaluate the expression, which should be a Result-like enum
                 let value = self.eval_expr(*expr)?;
@@ -1563,6 +2281,10 @@ impl Interpreter {
                 {
                     // Check if this is an Err/Error/None variant
                     if variant_name.to_lowercase().contains("err") || variant_name == "None" {
+                        // Directly inside a try block the error is thrown to its catch
+                        if self.try_blocks.last() == Some(&self.call_stack.len()) {
+                            return Err(self.throw(value.clone())?);
+                        }
                         // This is an error or absent value - propagate it by returning the variant
                         return Ok(value.clone());
                     }
@@ -1589,6 +2311,551 @@ impl Interpreter {
         }
     }
 
+    /// Call a callable value (function, closure, method, constructor) with evaluated arguments
+    pub fn call_value(&mut self, func_val: Value, arg_vals: Vec<Value>) -> RuntimeResult<Value> {
+        self.call_from(None, func_val, arg_vals)
+    }
+
+    /// Call a value from the code at `call_site`, tracking it for backtraces and the profiler
+    fn call_from(
+        &mut self,
+        call_site: Option<Span>,
+        func_val: Value,
+        arg_vals: Vec<Value>,
+    ) -> RuntimeResult<Value> {
+        let user_code = matches!(
+            func_val,
+            Value::Function { .. } | Value::UserMethod { .. } | Value::Closure { .. }
+        );
+        let name = if user_code || self.profiler.is_some() {
+            self.call_name(&func_val)
+        } else {
+            None
+        };
+        let Some(name) = name else {
+            return self.dispatch_call(func_val, arg_vals);
+        };
+
+        if let Some(profiler) = &mut self.profiler {
+            profiler.enter(name.clone());
+        }
+        if user_code {
+            self.call_stack.push(CallFrame {
+                function: name,
+                caller_source: self.source.clone(),
+                call_site,
+            });
+        }
+        let mut result = self.dispatch_call(func_val, arg_vals);
+        if user_code {
+            result = self.run_deferred(result).map_err(|e| self.trace(e));
+            self.call_stack.pop();
+        }
+        if let Some(profiler) = &mut self.profiler {
+            profiler.exit();
+        }
+        result
+    }
+
+    /// The name a call shows up under in profiles and backtraces, e.g. `fib`, `Point.length` or `term.println`
+    fn call_name(&self, func_val: &Value) -> Option<String> {
+        match func_val {
+            Value::Function { name, .. } => Some(name.clone()),
+            Value::UserMethod {
+                object,
+                method_name,
+                ..
+            } => Some(format!("{}.{}", self.type_of(object), method_name)),
+            Value::BoundMethod {
+                object,
+                method_name,
+                ..
+            } => Some(format!("{}.{}", self.type_of(object), method_name)),
+            Value::Closure { .. } => Some("<closure>".to_string()),
+            Value::BuiltinFn(_) => Some("<builtin>".to_string()),
+            _ => None,
+        }
+    }
+
+    fn dispatch_call(&mut self, func_val: Value, arg_vals: Vec<Value>) -> RuntimeResult<Value> {
+        match func_val {
+            Value::Function {
+                params,
+                variadic,
+                body,
+                is_async,
+                name,
+                source,
+            } => {
+                // Check argument count
+                let mut arg_vals = arg_vals;
+                if variadic {
+                    let fixed = params.len() - 1;
+                    if arg_vals.len() < fixed {
+                        return Err(self.error(format!(
+                            "Function '{}' expects at least {} arguments, got {}",
+                            name,
+                            fixed,
+                            arg_vals.len()
+                        )));
+                    }
+                    // The rest parameter gets the remaining arguments as an array
+                    let rest = arg_vals.split_off(fixed);
+                    arg_vals.push(Value::Array(rest));
+                } else if params.len() != arg_vals.len() {
+                    return Err(self.error(format!(
+                        "Function '{}' expects {} arguments, got {}",
+                        name,
+                        params.len(),
+                        arg_vals.len()
+                    )));
+                }
+
+                // Optional: Check argument types if type annotations exist
+                // Note: params are stored as (name, type_string)
+                // For now, we skip type validation since we'd need to parse the type strings
+
+                // Create new scope for function
+                self.env.push_scope();
+
+                // Bind parameters
+                for ((param_name, _), arg_val) in params.iter().zip(arg_vals.iter()) {
+                    self.env.set(param_name.clone(), arg_val.clone());
+                }
+
+                // Execute function body, pointing errors at the file it was declared in
+                let caller_source = std::mem::replace(&mut self.source, source);
+                let result = self.eval_stmt(*body);
+                self.source = caller_source;
+                let result = result?;
+                // Clear the return signal — we've exited the function
+                let result = self.returning.take().unwrap_or(result);
+
+                self.env.pop_scope();
+
+                // If async, wrap result in a Promise
+                if is_async {
+                    Ok(Value::Promise(Box::new(result)))
+                } else {
+                    Ok(result)
+                }
+            }
+            Value::BuiltinFn(builtin_fn) => builtin_fn(&arg_vals),
+            Value::BoundMethod {
+                object,
+                method_name,
+                method,
+                ..
+            } => {
+                // Special case for term.print and term.println
+                // We intercept these to apply user-defined Printable trait implementations
+                if (method_name == "print" || method_name == "println")
+                    && matches!(&*object, Value::Builtin(b) if b.name == "term")
+                {
+                    let mut stringified_args = Vec::with_capacity(arg_vals.len());
+                    for arg in arg_vals {
+                        stringified_args.push(Value::String(self.value_to_string(&arg)?.into()));
+                    }
+                    // Call the bound method with the object as 'this' and the stringified arguments
+                    method(&object, &stringified_args)
+                } else if method_name == "throws"
+                    && matches!(&*object, Value::Builtin(b) if b.name == "assert")
+                {
+                    // assert.throws needs to call back into the interpreter to run its argument
+                    self.assert_throws(&arg_vals)
+                } else if (method_name == "stats" || method_name == "env")
+                    && matches!(&*object, Value::Builtin(b) if b.name == "inspect")
+                {
+                    // Inspecting the environment needs the interpreter's scopes
+                    use crate::runtime::builtins::inspect;
+                    if method_name == "stats" {
+                        Ok(inspect::environment_stats(&self.env.scopes))
+                    } else {
+                        Ok(Value::String(
+                            inspect::environment_json(&self.env.scopes)?.into(),
+                        ))
+                    }
+                } else if (method_name == "sort_by" || method_name == "sort_by_key")
+                    && matches!(&*object, Value::Array(_))
+                {
+                    // Sorting with a callback also needs to call back into the interpreter
+                    self.sort_array_with(&object, &method_name, &arg_vals)
+                } else if method_name == "spawn"
+                    && matches!(&*object, Value::Builtin(b) if b.name == "worker")
+                {
+                    // A worker runs on an interpreter of its own
+                    self.spawn_worker(&arg_vals)
+                } else if method_name == "update"
+                    && matches!(&*object, Value::Builtin(b) if b.name == "Mutex")
+                {
+                    // The update function runs while the mutex is held
+                    self.update_mutex(&object, &arg_vals)
+                } else if (method_name == "interval" || method_name == "timeout")
+                    && matches!(&*object, Value::Builtin(b) if b.name == "time")
+                {
+                    // Timer callbacks run on a copy of the interpreter
+                    self.start_timer(method_name == "interval", &arg_vals)
+                } else if method_name == "on_signal"
+                    && matches!(&*object, Value::Builtin(b) if b.name == "os")
+                {
+                    // Signal handlers run on a copy of the interpreter too
+                    self.on_signal(&arg_vals)
+                } else {
+                    // Call the bound method with the object as 'this'
+                    method(&object, &arg_vals)
+                }
+            }
+            Value::UserMethod {
+                object,
+                params,
+                body,
+                ..
+            } => {
+                // Check argument count (exclude 'self' parameter which is already bound)
+                // The params includes 'self', but we don't include it in arg_vals
+                if params.len() != arg_vals.len() + 1 {
+                    return Err(self.error(format!(
+                        "Expected {} arguments (plus self), got {}",
+                        params.len() - 1,
+                        arg_vals.len()
+                    )));
+                }
+
+                // Create new scope for method
+                self.env.push_scope();
+
+                // Bind 'self' to the object
+                self.env.set("self".to_string(), (*object).clone());
+
+                // Bind other parameters
+                for ((param_name, _), arg_val) in params.iter().skip(1).zip(arg_vals.iter()) {
+                    self.env.set(param_name.clone(), arg_val.clone());
+                }
+
+                // Execute method body
+                let result = self.eval_stmt(*body)?;
+                let result = self.returning.take().unwrap_or(result);
+
+                self.env.pop_scope();
+
+                Ok(result)
+            }
+            Value::Closure {
+                params,
+                body,
+                captured_env,
+                ..
+            } => {
+                // Check argument count
+                if params.len() != arg_vals.len() {
+                    return Err(self.error(format!(
+                        "Expected {} arguments, got {}",
+                        params.len(),
+                        arg_vals.len()
+                    )));
+                }
+
+                // Create new scope for closure
+                self.env.push_scope();
+
+                // First, restore the captured environment
+                for (name, value) in captured_env {
+                    self.env.set(name, value);
+                }
+
+                // Then bind parameters (which can shadow captured variables)
+                for ((param_name, _), arg_val) in params.iter().zip(arg_vals.iter()) {
+                    self.env.set(param_name.clone(), arg_val.clone());
+                }
+
+                // Execute closure body (which is an expression, not a statement)
+                let result = self.eval_expr(*body)?;
+
+                self.env.pop_scope();
+
+                Ok(result)
+            }
+
+            Value::Builtin(builtin_struct) => {
+                // This shouldn't happen - builtins aren't directly callable
+                Err(self.error(format!(
+                    "Cannot call builtin struct '{}' directly. Use its methods instead.",
+                    builtin_struct.name
+                )))
+            }
+            Value::EnumConstructor {
+                enum_name,
+                variant_name,
+                arity,
+            } => {
+                // Check argument count
+                if arg_vals.len() != arity {
+                    return Err(self.error(format!(
+                        "Enum variant {}.{} expects {} arguments, got {}",
+                        enum_name,
+                        variant_name,
+                        arity,
+                        arg_vals.len()
+                    )));
+                }
+
+                // Check payload values against the declared types
+                // Generic parameters such as `T` in Option<T> accept any value
+                let declared = self
+                    .enums
+                    .get(&enum_name)
+                    .and_then(|variants| variants.iter().find(|(name, _)| *name == variant_name))
+                    .and_then(|(_, types)| types.clone())
+                    .unwrap_or_default();
+                for (index, (declared_type, value)) in declared.iter().zip(&arg_vals).enumerate() {
+                    if let Type::Named(type_name) = declared_type {
+                        let checked = matches!(type_name.as_str(), "num" | "str" | "bool")
+                            || self.enums.contains_key(type_name);
+                        let actual = self.type_of(value);
+                        if checked && actual != *type_name {
+                            return Err(self.error(format!(
+                                "Enum variant {}.{} expects {} for field {}, got {}",
+                                enum_name, variant_name, type_name, index, actual
+                            )));
+                        }
+                    }
+                }
+
+                // Construct the enum variant
+                Ok(Value::EnumVariant {
+                    enum_name,
+                    variant_name,
+                    values: arg_vals,
+                })
+            }
+            _ => Err(self.error(format!("Cannot call value of type {:?}", func_val))),
+        }
+    }
+
+    /// Sort an array with `sort_by` (a comparator) or `sort_by_key` (a key function)
+    fn sort_array_with(
+        &mut self,
+        array: &Value,
+        method: &str,
+        args: &[Value],
+    ) -> RuntimeResult<Value> {
+        use crate::runtime::builtins::collections::array::{compare_values, stable_sort_by};
+
+        let items = match array {
+            Value::Array(items) => items.clone(),
+            _ => unreachable!(),
+        };
+        let func = match args.first() {
+            Some(
+                f @ (Value::Function { .. }
+                | Value::Closure { .. }
+                | Value::BuiltinFn(_)
+                | Value::BoundMethod { .. }
+                | Value::UserMethod { .. }),
+            ) => f.clone(),
+            _ => {
+                return Err(RuntimeError::new(format!(
+                    "{}() requires a function argument",
+                    method
+                )))
+            }
+        };
+
+        if method == "sort_by" {
+            let sorted = stable_sort_by(items, &mut |a, b| {
+                let result = self.call_value(func.clone(), vec![a.clone(), b.clone()])?;
+                match result {
+                    Value::Number(n) => Ok(n.cmp(&rust_decimal::Decimal::ZERO)),
+                    other => Err(RuntimeError::new(format!(
+                        "sort_by() comparator must return a number, got {}",
+                        other.to_string()
+                    ))),
+                }
+            })?;
+            return Ok(Value::Array(sorted));
+        }
+
+        // Each key is computed once, then elements are sorted by their keys
+        let mut keyed = Vec::with_capacity(items.len());
+        for item in items {
+            let key = self.call_value(func.clone(), vec![item.clone()])?;
+            keyed.push((key, item));
+        }
+        let sorted = stable_sort_by(keyed, &mut |(a, _), (b, _)| compare_values(a, b))?;
+        Ok(Value::Array(
+            sorted.into_iter().map(|(_, item)| item).collect(),
+        ))
+    }
+
+    /// Run the callable passed to `assert.throws` and succeed only if it raises an error
+    /// An optional second argument is a substring the error message must contain
+    fn assert_throws(&mut self, args: &[Value]) -> RuntimeResult<Value> {
+        let func = match args.first() {
+            Some(
+                f @ (Value::Function { .. }
+                | Value::Closure { .. }
+                | Value::BuiltinFn(_)
+                | Value::BoundMethod { .. }
+                | Value::UserMethod { .. }),
+            ) => f.clone(),
+            _ => {
+                return Err(RuntimeError::new(
+                    "assert.throws() requires a function argument",
+                ))
+            }
+        };
+
+        let depth = self.env.scopes.len();
+        match self.call_value(func, vec![]) {
+            Ok(value) => Err(RuntimeError::new(format!(
+                "Assertion failed: expected an error but the function returned {}",
+                value.to_string()
+            ))),
+            Err(err) if err.exit_code().is_some() => Err(err),
+            Err(err) => {
+                // The failed call may have left its scopes behind
+                self.env.scopes.truncate(depth);
+                self.returning = None;
+                match args.get(1) {
+                    Some(Value::String(expected)) if !err.message.contains(expected.as_ref()) => {
+                        Err(RuntimeError::new(format!(
+                            "Assertion failed: expected error containing {:?}, got {:?}",
+                            expected, err.message
+                        )))
+                    }
+                    _ => Ok(Value::String(err.message.into())),
+                }
+            }
+        }
+    }
+
+    /// Start a worker running a function on a copy of this interpreter, or a
+    /// file on a fresh one with the same features
+    #[cfg(not(target_arch = "wasm32"))]
+    fn spawn_worker(&self, args: &[Value]) -> RuntimeResult<Value> {
+        use crate::runtime::builtins::worker;
+
+        match args.first() {
+            Some(Value::String(path)) => {
+                // Relative paths are found next to the file spawning the worker
+                let path = match self.source.as_ref() {
+                    Some(source) => std::path::Path::new(&source.path)
+                        .parent()
+                        .map_or_else(|| PathBuf::from(&**path), |dir| dir.join(&**path)),
+                    None => PathBuf::from(&**path),
+                };
+                let path = path.to_string_lossy().to_string();
+                permission_context::check_read_permission(&path, Some("worker.spawn()"))
+                    .map_err(RuntimeError::new)?;
+                let source = vfs::read_to_string(&path).map_err(|e| {
+                    RuntimeError::new(format!("Failed to read worker '{}': {}", path, e))
+                })?;
+                let stmts = Parser::new(InputStream::new(&path, &source))
+                    .parse()
+                    .map_err(|e| {
+                        RuntimeError::new(format!("Failed to parse worker '{}': {}", path, e))
+                    })?;
+
+                let mut interpreter = Interpreter::with_source(&path, source);
+                interpreter.disabled_builtins = self.disabled_builtins.clone();
+                let mut interpreter =
+                    interpreter.with_features(self.enabled_features.iter().cloned().collect());
+                interpreter.budget = self.budget.clone();
+                worker::spawn(move |_| interpreter.eval_program(stmts))
+            }
+            Some(func @ (Value::Function { .. } | Value::Closure { .. })) => {
+                let func = func.clone();
+                let mut interpreter = self.fork();
+                worker::spawn(move |port| interpreter.call_value(func, vec![port]))
+            }
+            _ => Err(RuntimeError::new(
+                "worker.spawn() requires a function or the path of a loft file",
+            )),
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn spawn_worker(&self, _args: &[Value]) -> RuntimeResult<Value> {
+        Err(RuntimeError::new(
+            "Workers are not available on this platform",
+        ))
+    }
+
+    /// Replace the value of a `sync.mutex()` with what a function returns for it
+    #[cfg(not(target_arch = "wasm32"))]
+    fn update_mutex(&mut self, mutex: &Value, args: &[Value]) -> RuntimeResult<Value> {
+        use crate::runtime::builtins::sync;
+
+        let func = match args.first() {
+            Some(func @ (Value::Function { .. } | Value::Closure { .. })) => func.clone(),
+            _ => return Err(RuntimeError::new("update() requires a function")),
+        };
+        sync::update(mutex, |value| self.call_value(func, vec![value]))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn update_mutex(&mut self, _mutex: &Value, _args: &[Value]) -> RuntimeResult<Value> {
+        Err(RuntimeError::new(
+            "Mutexes are not available on this platform",
+        ))
+    }
+
+    /// Start `time.interval` or `time.timeout` calling a function on a copy of this interpreter
+    #[cfg(not(target_arch = "wasm32"))]
+    fn start_timer(&self, repeat: bool, args: &[Value]) -> RuntimeResult<Value> {
+        use crate::runtime::builtins::time;
+
+        let name = if repeat { "interval" } else { "timeout" };
+        let delay = time::timer_delay(args.first(), name)?;
+        let func = match args.get(1) {
+            Some(func @ (Value::Function { .. } | Value::Closure { .. })) => func.clone(),
+            _ => {
+                return Err(RuntimeError::new(format!(
+                    "time.{}() requires a function to call",
+                    name
+                )))
+            }
+        };
+        let mut interpreter = self.fork();
+        time::start_timer(name, delay, repeat, move || {
+            interpreter.call_value(func.clone(), vec![])
+        })
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn start_timer(&self, _repeat: bool, _args: &[Value]) -> RuntimeResult<Value> {
+        Err(RuntimeError::new(
+            "Timers are not available on this platform",
+        ))
+    }
+
+    /// Register a function with `os.on_signal` to run on a copy of this interpreter
+    #[cfg(not(target_arch = "wasm32"))]
+    fn on_signal(&self, args: &[Value]) -> RuntimeResult<Value> {
+        use crate::runtime::builtins::os;
+
+        let (signal, func) = match args {
+            [Value::String(name), func @ (Value::Function { .. } | Value::Closure { .. })] => {
+                (os::Signal::parse(name)?, func.clone())
+            }
+            _ => {
+                return Err(RuntimeError::new(
+                    "os.on_signal() requires a signal name and a function",
+                ))
+            }
+        };
+        let mut interpreter = self.fork();
+        os::on_signal(signal, move || interpreter.call_value(func.clone(), vec![]))?;
+        Ok(Value::Unit)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn on_signal(&self, _args: &[Value]) -> RuntimeResult<Value> {
+        Err(RuntimeError::new(
+            "Signals are not available on this platform",
+        ))
+    }
+
     fn value_to_string(&mut self, value: &Value) -> RuntimeResult<String> {
         if let Value::Struct { name, .. } = value {
             let mut body_to_eval = None;
@@ -1617,7 +2884,7 @@ impl Interpreter {
                 self.env.pop_scope();
 
                 if let Value::String(s) = result {
-                    return Ok(s);
+                    return Ok(s.to_string());
                 }
             }
         }
@@ -1630,6 +2897,7 @@ impl Interpreter {
             "-" => "sub",
             "*" => "mul",
             "/" => "div",
+            "%" => "rem",
             "&" => "bit_and",
             "|" => "bit_or",
             "^" => "bit_xor",
@@ -1645,59 +2913,175 @@ impl Interpreter {
         };
 
         // Check for user-defined trait implementations for any type (not just structs)
-        if !method_name.is_empty() {
-            let type_name = self.type_of(&left);
-            if let Some(methods) = self.impl_methods.get(&type_name) {
-                if let Some((params, _return_type, body, _trait_name)) =
-                    methods.get(method_name)
-                {
-                    // Check arg count (should be 2: self, other)
-                    if params.len() != 2 {
-                        return Err(self.error(format!(
-                            "Trait method '{}' should have 2 parameters (self, other), found {}",
-                            method_name, params.len()
-                        )));
-                    }
+        if let Some(result) = self.call_operator_method(method_name, &left, &right)? {
+            return Ok(result);
+        }
+
+        // Composite values are equal when their contents are
+        match op {
+            "==" => return Ok(Value::Boolean(self.values_equal(&left, &right)?)),
+            "!=" => return Ok(Value::Boolean(!self.values_equal(&left, &right)?)),
+            _ => {}
+        }
 
-                    let other_param_name = params[1].0.clone();
-                    let body = body.clone();
+        // Handle string concatenation specially to use our custom value_to_string
+        if op == "+" {
+            if let Value::String(l_str) = &left {
+                let r_str = self.value_to_string(&right)?;
+                return Ok(Value::String(format!("{}{}", l_str, r_str).into()));
+            }
+        }
 
-                    self.env.push_scope();
+        // Fall back to built-in trait implementations
+        match call_binop_trait(op, &left, &right) {
+            Ok(v) => Ok(v),
+            Err(e) => {
+                // Provide better error message with type information
+                let left_type = self.type_of(&left);
+                let right_type = self.type_of(&right);
+                let enhanced_msg =
+                    format!("{} (left: {}, right: {})", e.message, left_type, right_type);
+                Err(self.error(enhanced_msg))
+            }
+        }
+    }
 
-                    // Bind self and other
-                    self.env.set("self".to_string(), left.clone());
-                    self.env.set(other_param_name, right.clone());
+    /// Run the `method_name` method that the type of `left` implements for an
+    /// operator, such as `add` for `+`, if it has one
+    fn call_operator_method(
+        &mut self,
+        method_name: &str,
+        left: &Value,
+        right: &Value,
+    ) -> RuntimeResult<Option<Value>> {
+        let type_name = self.type_of(left);
+        let Some((params, _return_type, body, _trait_name)) = self
+            .impl_methods
+            .get(&type_name)
+            .and_then(|methods| methods.get(method_name))
+        else {
+            return Ok(None);
+        };
 
-                    // Execute body
-                    let result = self.eval_stmt(*body)?;
+        // Check arg count (should be 2: self, other)
+        if params.len() != 2 {
+            return Err(self.error(format!(
+                "Trait method '{}' should have 2 parameters (self, other), found {}",
+                method_name,
+                params.len()
+            )));
+        }
 
-                    self.env.pop_scope();
+        let other_param_name = params[1].0.clone();
+        let body = body.clone();
 
-                    return Ok(result);
+        self.env.push_scope();
+
+        // Bind self and other
+        self.env.set("self".to_string(), left.clone());
+        self.env.set(other_param_name, right.clone());
+
+        // Execute body
+        let result = self.eval_stmt(*body);
+        let result = self.returning.take().map_or(result, Ok);
+
+        self.env.pop_scope();
+
+        result.map(Some)
+    }
+
+    /// Whether `value.clone()` is the builtin copy: modules and builtin structs
+    /// have no `clone`, and a struct's own field or method of that name wins
+    fn has_default_clone(&self, value: &Value) -> bool {
+        let type_name = match value {
+            Value::Builtin(_) | Value::Module { .. } => return false,
+            Value::Struct { fields, .. } if fields.contains_key("clone") => return false,
+            Value::Struct { name, .. } => name,
+            Value::EnumVariant { enum_name, .. } => enum_name,
+            _ => return true,
+        };
+        !self
+            .impl_methods
+            .get(type_name)
+            .is_some_and(|methods| methods.contains_key("clone"))
+    }
+
+    /// Structural equality: arrays, maps, structs and enum variants are equal
+    /// when their contents are, and a struct with an `eq` method decides for itself
+    fn values_equal(&mut self, left: &Value, right: &Value) -> RuntimeResult<bool> {
+        match (left, right) {
+            (
+                Value::Struct {
+                    name: n1,
+                    fields: f1,
+                },
+                Value::Struct {
+                    name: n2,
+                    fields: f2,
+                },
+            ) if n1 == n2 => {
+                if let Some(result) = self.call_operator_method("eq", left, right)? {
+                    return match result {
+                        Value::Boolean(equal) => Ok(equal),
+                        other => Err(self.error(format!(
+                            "Method 'eq' of '{}' must return a bool, got {}",
+                            n1,
+                            self.type_of(&other)
+                        ))),
+                    };
                 }
+                if f1.len() != f2.len() {
+                    return Ok(false);
+                }
+                for (field, value) in f1 {
+                    match f2.get(field) {
+                        Some(other) if self.values_equal(value, other)? => {}
+                        _ => return Ok(false),
+                    }
+                }
+                Ok(true)
             }
-        }
-
-        // Handle string concatenation specially to use our custom value_to_string
-        if op == "+" {
-            if let Value::String(l_str) = &left {
-                let r_str = self.value_to_string(&right)?;
-                return Ok(Value::String(format!("{}{}", l_str, r_str)));
+            (Value::Array(a), Value::Array(b)) => self.all_equal(a, b),
+            (
+                Value::EnumVariant {
+                    enum_name: e1,
+                    variant_name: v1,
+                    values: a,
+                },
+                Value::EnumVariant {
+                    enum_name: e2,
+                    variant_name: v2,
+                    values: b,
+                },
+            ) => Ok(e1 == e2 && v1 == v2 && self.all_equal(a, b)?),
+            // Maps compare equal regardless of insertion order
+            (Value::Map(a), Value::Map(b)) => {
+                if a.len() != b.len() {
+                    return Ok(false);
+                }
+                for (key, value) in a {
+                    match b.iter().find(|(k, _)| k == key) {
+                        Some((_, other)) if self.values_equal(value, other)? => {}
+                        _ => return Ok(false),
+                    }
+                }
+                Ok(true)
             }
+            _ => Ok(left == right),
         }
+    }
 
-        // Fall back to built-in trait implementations
-        match call_binop_trait(op, &left, &right) {
-            Ok(v) => Ok(v),
-            Err(e) => {
-                // Provide better error message with type information
-                let left_type = self.type_of(&left);
-                let right_type = self.type_of(&right);
-                let enhanced_msg =
-                    format!("{} (left: {}, right: {})", e.message, left_type, right_type);
-                Err(self.error(enhanced_msg))
+    /// Whether two lists of values are equal element by element
+    fn all_equal(&mut self, a: &[Value], b: &[Value]) -> RuntimeResult<bool> {
+        if a.len() != b.len() {
+            return Ok(false);
+        }
+        for (x, y) in a.iter().zip(b) {
+            if !self.values_equal(x, y)? {
+                return Ok(false);
             }
         }
+        Ok(true)
     }
 
     /// Match a pattern against a value
@@ -1708,23 +3092,23 @@ impl Interpreter {
         pattern: &Expr,
         value: &Value,
     ) -> RuntimeResult<Option<HashMap<String, Value>>> {
-        match pattern {
+        match &pattern.kind {
             // Literal patterns
-            Expr::Number(n) => {
+            ExprKind::Number(n) => {
                 if let Value::Number(v) = value {
                     Ok(if n == v { Some(HashMap::new()) } else { None })
                 } else {
                     Ok(None)
                 }
             }
-            Expr::String(s) => {
+            ExprKind::String(s) => {
                 if let Value::String(v) = value {
                     Ok(if s == v { Some(HashMap::new()) } else { None })
                 } else {
                     Ok(None)
                 }
             }
-            Expr::Boolean(b) => {
+            ExprKind::Boolean(b) => {
                 if let Value::Boolean(v) = value {
                     Ok(if b == v { Some(HashMap::new()) } else { None })
                 } else {
@@ -1733,7 +3117,7 @@ impl Interpreter {
             }
 
             // Identifier pattern - binds the value to a variable
-            Expr::Ident(name) => {
+            ExprKind::Ident(name) => {
                 if name == "_" {
                     // Wildcard pattern matches anything
                     Ok(Some(HashMap::new()))
@@ -1764,8 +3148,8 @@ impl Interpreter {
             }
 
             // Field access pattern (e.g., Color.Red)
-            Expr::FieldAccess { object, field } => {
-                if let Expr::Ident(enum_name) = &**object {
+            ExprKind::FieldAccess { object, field } => {
+                if let ExprKind::Ident(enum_name) = &object.kind {
                     // Check if value is an enum variant
                     if let Value::EnumVariant {
                         enum_name: val_enum,
@@ -1793,9 +3177,9 @@ impl Interpreter {
             }
 
             // Call pattern for tuple variants (e.g., Color.RGB(r, g, b))
-            Expr::Call { func, args } => {
-                if let Expr::FieldAccess { object, field } = &**func {
-                    if let Expr::Ident(enum_name) = &**object {
+            ExprKind::Call { func, args } => {
+                if let ExprKind::FieldAccess { object, field } = &func.kind {
+                    if let ExprKind::Ident(enum_name) = &object.kind {
                         // Check if value is an enum variant
                         if let Value::EnumVariant {
                             enum_name: val_enum,
@@ -1842,186 +3226,380 @@ impl Interpreter {
     }
 
     /// Load and execute a module, returning its exports
-    fn load_module(&mut self, path: &[String]) -> RuntimeResult<Value> {
+    fn load_module(&mut self, path: &[String]) -> RuntimeResult<HashMap<String, Value>> {
         // Convert path to module identifier
         let module_id = path.join("::");
 
-        // Extract module name from path
-        // For "./math_utils" or "math", use base name
-        // For "package::module", use last component
-        let module_name = if path.len() == 1 {
-            // Single path element - extract base name
-            let path_str = &path[0];
-            PathBuf::from(path_str)
-                .file_stem()
-                .and_then(|s| s.to_str())
-                .unwrap_or(path_str)
-                .to_string()
-        } else {
-            // Multiple components - use last one
-            path.last().unwrap().clone()
-        };
-
         // Check if module is already cached
         if let Some(exports) = self.module_cache.get(&module_id) {
-            // Create module namespace object
-            let module_value = Value::Module {
-                name: module_name.clone(),
-                exports: exports.clone(),
-            };
-            self.env.set(module_name, module_value);
-            return Ok(Value::Unit);
+            return Ok(exports.clone());
         }
 
-        // Resolve module path to file
-        let file_path = self.resolve_module_path(path)?;
+        let (file_path, source) = if let Some(bundle) = &self.bundle {
+            // Bundled modules are looked up by the path recorded for this import
+            let importer = self.source.as_ref().map_or("", |s| s.path.as_str());
+            let module = bundle.resolve(importer, &module_id).ok_or_else(|| {
+                RuntimeError::new(format!("Module not found in bundle: {}", module_id))
+            })?;
+            (module.path.clone(), module.source.clone())
+        } else {
+            // Resolve module path to file
+            let file_path = self.resolve_module_path(path)?;
+
+            // Read module source
+            let source = vfs::read_to_string(&file_path).map_err(|e| {
+                RuntimeError::new(format!(
+                    "Failed to read module '{}': {}",
+                    file_path.display(),
+                    e
+                ))
+            })?;
+            (file_path.to_str().unwrap().to_string(), source)
+        };
 
-        // Read module source
-        let source = std::fs::read_to_string(&file_path).map_err(|e| {
-            RuntimeError::new(format!(
-                "Failed to read module '{}': {}",
-                file_path.display(),
-                e
-            ))
-        })?;
+        // A module that is still loading further up the chain would import itself forever
+        let mut chain = self.import_chain.clone();
+        chain.extend(self.source.as_ref().map(|s| s.path.clone()));
+        if let Some(start) = chain
+            .iter()
+            .position(|loading| same_module_file(loading, &file_path))
+        {
+            let cycle: Vec<&str> = chain[start..]
+                .iter()
+                .map(String::as_str)
+                .chain([file_path.as_str()])
+                .collect();
+            return Err(self.error(format!("Circular import: {}", cycle.join(" -> "))));
+        }
 
         // Parse module
-        let stream = InputStream::new(file_path.to_str().unwrap(), &source);
+        let stream = InputStream::new(&file_path, &source);
         let mut parser = Parser::new(stream);
         let stmts = parser.parse().map_err(|e| {
             RuntimeError::new(format!("Failed to parse module '{}': {}", module_id, e))
         })?;
 
         // Create new interpreter for module with fresh environment
-        let mut module_interpreter = Interpreter::with_source(file_path.to_str().unwrap(), source)
+        let mut module_interpreter = Interpreter::with_source(&file_path, source)
             .with_features(self.enabled_features.iter().cloned().collect());
+        module_interpreter.bundle = self.bundle.clone();
+        module_interpreter.import_chain = chain;
 
         // Execute module
         module_interpreter.eval_program(stmts)?;
 
         // Cache the module's exports
-        let exports = module_interpreter.exports.clone();
-        self.module_cache.insert(module_id.clone(), exports.clone());
-
-        // Create module namespace object and add to environment
-        let module_value = Value::Module {
-            name: module_name.clone(),
-            exports,
-        };
-        self.env.set(module_name, module_value);
-
-        Ok(Value::Unit)
+        let exports = module_interpreter.exports;
+        self.module_cache.insert(module_id, exports.clone());
+        Ok(exports)
     }
 
     /// Resolve a module path to a file system path
     fn resolve_module_path(&self, path: &[String]) -> RuntimeResult<PathBuf> {
-        // If path starts with ".", it's a relative import
-        if path[0].starts_with('.') {
-            // Relative to current file
-            if let Some(current_path) = &self.source_path {
-                let current_file = PathBuf::from(current_path);
-                let current_dir = current_file
-                    .parent()
-                    .ok_or_else(|| RuntimeError::new("Cannot determine parent directory"))?;
+        resolve_module_path(self.source.as_ref().map(|s| s.path.as_str()), path)
+    }
+
+    /// Get the runtime type name of a value
+    pub fn type_of(&self, value: &Value) -> String {
+        value.type_name()
+    }
 
-                let relative_path = path[0].trim_start_matches("./");
-                let mut module_path = current_dir.join(relative_path);
+    /// Create a runtime error with source context if available
+    fn error(&self, message: impl Into<String>) -> RuntimeError {
+        if let Some(source) = &self.source {
+            RuntimeError::with_source_context(message, source.path.clone(), source.code.clone())
+        } else {
+            RuntimeError::new(message)
+        }
+    }
 
-                // Try .lf extension
-                module_path.set_extension("lf");
-                if module_path.exists() {
-                    return Ok(module_path);
-                }
+    /// Run a try statement: errors in `body` go to `catch`, and `finally` always runs last
+    fn eval_try(
+        &mut self,
+        body: Stmt,
+        catch: Option<(Option<String>, Box<Stmt>)>,
+        finally: Option<Stmt>,
+    ) -> RuntimeResult<Value> {
+        let depth = self.env.depth();
+        self.try_blocks.push(self.call_stack.len());
+        let mut result = self.eval_stmt(body);
+        self.try_blocks.pop();
+
+        // `exit()` ends the program past any catch
+        let catch = catch.filter(|_| !matches!(&result, Err(e) if e.exit_code().is_some()));
+        if let (Err(error), Some((var, catch_body))) = (&result, catch) {
+            let error = self.error_value(error.clone());
+            self.env.truncate(depth);
+            self.env.push_scope();
+            if let Some(var) = var {
+                self.env.set(var, error);
+            }
+            result = self.eval_stmt(*catch_body);
+            self.env.truncate(depth);
+        }
 
-                // Try as directory with mod.lf
-                let mut dir_path = current_dir.join(relative_path);
-                dir_path.push("mod.lf");
-                if dir_path.exists() {
-                    return Ok(dir_path);
-                }
+        if let Some(finally) = finally {
+            self.env.truncate(depth);
+            // A `return` in the try or catch block waits for the finally block,
+            // unless the finally block returns itself
+            let pending = self.returning.take();
+            self.eval_stmt(finally)?;
+            if let Some(value) = &self.returning {
+                return Ok(value.clone());
+            }
+            self.returning = pending;
+        }
+        result
+    }
 
-                return Err(RuntimeError::new(format!(
-                    "Module not found: {}",
-                    path.join("::")
-                )));
-            } else {
-                return Err(RuntimeError::new(
-                    "Cannot resolve relative import without source path",
-                ));
+    /// Run the expressions deferred by the current call, most recent first
+    /// An error from the call itself takes precedence over errors from deferred code
+    fn run_deferred(&mut self, mut result: RuntimeResult<Value>) -> RuntimeResult<Value> {
+        let depth = self.call_stack.len();
+        while let Some((_, deferred)) = self.deferred.pop_if(|(d, _)| *d == depth) {
+            let returning = self.returning.take();
+            let outcome = self.call_value(deferred, Vec::new());
+            self.returning = returning;
+            if let (Ok(_), Err(error)) = (&result, outcome) {
+                result = Err(error);
             }
         }
+        result
+    }
 
-        // Otherwise, treat as package import
-        // Look in .lflibs or use manifest resolution
-        let module_name = &path[0];
+    /// The error raised by `throw value`, keeping the original stack when rethrowing a caught error
+    fn throw(&mut self, value: Value) -> RuntimeResult<RuntimeError> {
+        let message = match &value {
+            Value::Struct { name, fields } if name == "Error" => fields
+                .get("message")
+                .map(|message| message.to_string())
+                .unwrap_or_default(),
+            Value::String(s) => s.to_string(),
+            Value::EnumVariant { values, .. } if values.len() == 1 => {
+                self.value_to_string(&values[0])?
+            }
+            other => self.value_to_string(other)?,
+        };
+        let mut error = self.error(message);
+        error.raised = Some(Box::new(Raised::Thrown(value)));
+        Ok(error)
+    }
 
-        // Try manifest resolution first
-        #[cfg(not(target_arch = "wasm32"))]
-        if let Some(current_path) = &self.source_path {
-            let current_file = PathBuf::from(current_path);
-            
-            // Try to find manifest.json in current directory or parents
-            if let Ok(manifest) = crate::manifest::Manifest::find_and_load(
-                current_file.parent().unwrap_or_else(|| std::path::Path::new("."))
-            ) {
-                match manifest.resolve_import(path) {
-                    Ok(resolved_path) => {
-                        let module_path = PathBuf::from(resolved_path);
-                        if module_path.exists() {
-                            return Ok(module_path);
-                        }
-                    }
-                    Err(_) => {
-                        // Continue to fallback resolution
-                    }
-                }
+    /// The `Error` value a catch block receives: `{ message, stack, value }`
+    fn error_value(&self, error: RuntimeError) -> Value {
+        if let Some(thrown) = error.thrown() {
+            if matches!(thrown, Value::Struct { name, .. } if name == "Error") {
+                return thrown.clone();
             }
         }
 
-        // Fallback: look for file in current directory
-        let mut module_path = PathBuf::from(module_name);
-        module_path.set_extension("lf");
+        let mut error = self.trace(error);
+        if error.backtrace.is_empty() {
+            error.backtrace.push(backtrace_frame(
+                "<top level>",
+                error.path.as_deref(),
+                error.source.as_ref().map(|source| source.inner().as_str()),
+                error.position,
+            ));
+        }
+        let stack = error
+            .backtrace
+            .iter()
+            .map(|frame| Value::String(frame.to_string().into()))
+            .collect();
+        let thrown = error.thrown().cloned().unwrap_or(Value::Unit);
+        Value::Struct {
+            name: "Error".to_string(),
+            fields: HashMap::from([
+                ("message".to_string(), Value::String(error.message.into())),
+                ("stack".to_string(), Value::Array(stack)),
+                ("value".to_string(), thrown),
+            ]),
+        }
+    }
 
-        if module_path.exists() {
-            return Ok(module_path);
+    /// Record the calls running when an error leaves the innermost of them
+    fn trace(&self, mut error: RuntimeError) -> RuntimeError {
+        let Some(innermost) = self.call_stack.last() else {
+            return error;
+        };
+        if !error.backtrace.is_empty() {
+            return error;
         }
 
-        Err(RuntimeError::new(format!(
-            "Module not found: {}",
-            path.join("::")
-        )))
+        // The innermost call is executing the code that failed, every other
+        // call is executing the call to the one inside it
+        error.backtrace.push(backtrace_frame(
+            &innermost.function,
+            error.path.as_deref(),
+            error.source.as_ref().map(|source| source.inner().as_str()),
+            error.position,
+        ));
+        let callers = self.call_stack.iter().rev().skip(1);
+        for (caller, callee) in callers.zip(self.call_stack.iter().rev()) {
+            error
+                .backtrace
+                .push(call_site_frame(&caller.function, callee));
+        }
+        if let Some(outermost) = self.call_stack.first().filter(|f| f.call_site.is_some()) {
+            error
+                .backtrace
+                .push(call_site_frame("<top level>", outermost));
+        }
+        error
     }
 
-    /// Get the runtime type name of a value
-    fn type_of(&self, value: &Value) -> String {
-        match value {
-            Value::Unit => "unit".to_string(),
-            Value::Number(_) => "num".to_string(),
-            Value::String(_) => "str".to_string(),
-            Value::Boolean(_) => "bool".to_string(),
-            Value::Array(_) => "array".to_string(),
-            Value::Function { .. } => "function".to_string(),
-            Value::Closure { .. } => "closure".to_string(),
-            Value::Struct { name, .. } => name.clone(),
-            Value::Builtin(b) => b.name.clone(),
-            Value::BuiltinFn(_) => "builtin_function".to_string(),
-            Value::BoundMethod { .. } => "method".to_string(),
-            Value::UserMethod { .. } => "method".to_string(),
-            Value::Promise(_) => "promise".to_string(),
-            Value::EnumVariant { enum_name, .. } => enum_name.clone(),
-            Value::EnumConstructor { enum_name, .. } => format!("{}_constructor", enum_name),
-            Value::Module { name, .. } => format!("module_{}", name),
+    /// Point an error that has no location yet at the code in `span`
+    fn locate(&self, mut error: RuntimeError, span: Span) -> RuntimeError {
+        if error.position.is_some() || span.is_empty() {
+            return error;
+        }
+        if let Some(source) = self.source.as_ref().filter(|s| span.end <= s.code.len()) {
+            error.path = Some(source.path.clone());
+            error.source = Some(Box::new(NamedSource::new(
+                &source.path,
+                source.code.clone(),
+            )));
+            error.position = Some(span.start);
+            error.len = Some(span.len());
         }
+        error
     }
+}
 
-    /// Create a runtime error with source context if available
-    fn error(&self, message: impl Into<String>) -> RuntimeError {
-        if let (Some(path), Some(source)) = (&self.source_path, &self.source_code) {
-            RuntimeError::with_source_context(message, path.clone(), source.clone())
+/// A backtrace frame for `function` executing the code at `offset` in `code`
+fn backtrace_frame(
+    function: &str,
+    path: Option<&str>,
+    code: Option<&str>,
+    offset: Option<usize>,
+) -> BacktraceFrame {
+    let before = code
+        .zip(offset)
+        .and_then(|(code, offset)| code.get(..offset));
+    BacktraceFrame {
+        function: function.to_string(),
+        path: path.map(str::to_string),
+        line: before.map(|before| before.matches('\n').count() + 1),
+        column: before.map(|before| before.rsplit('\n').next().unwrap_or("").chars().count() + 1),
+    }
+}
+
+/// A backtrace frame for `function` executing the call that started `callee`
+fn call_site_frame(function: &str, callee: &CallFrame) -> BacktraceFrame {
+    let source = callee.caller_source.as_deref();
+    backtrace_frame(
+        function,
+        source.map(|source| source.path.as_str()),
+        source.map(|source| source.code.as_str()),
+        callee.call_site.map(|span| span.start),
+    )
+}
+
+/// The fields and methods of a builtin, for `learn { sqrt } from "math";`
+fn builtin_exports(builtin: &BuiltinStruct) -> HashMap<String, Value> {
+    let mut exports = builtin.fields.clone();
+    for (name, method) in &builtin.methods {
+        exports.insert(
+            name.clone(),
+            Value::BoundMethod {
+                object: Box::new(Value::Builtin(builtin.clone())),
+                method_name: name.clone(),
+                method: *method,
+            },
+        );
+    }
+    exports
+}
+
+/// Resolve a `learn` path imported from `source_path` to a file system path
+pub fn resolve_module_path(source_path: Option<&str>, path: &[String]) -> RuntimeResult<PathBuf> {
+    // If path starts with ".", it's a relative import
+    if path[0].starts_with('.') {
+        // Relative to current file
+        if let Some(current_path) = source_path {
+            let current_file = PathBuf::from(current_path);
+            let current_dir = current_file
+                .parent()
+                .ok_or_else(|| RuntimeError::new("Cannot determine parent directory"))?;
+
+            let relative_path = path[0].trim_start_matches("./");
+            let mut module_path = current_dir.join(relative_path);
+
+            // Try .lf extension
+            module_path.set_extension("lf");
+            if vfs::exists(&module_path) {
+                return Ok(module_path);
+            }
+
+            // Try as directory with mod.lf
+            let mut dir_path = current_dir.join(relative_path);
+            dir_path.push("mod.lf");
+            if vfs::exists(&dir_path) {
+                return Ok(dir_path);
+            }
+
+            return Err(RuntimeError::new(format!(
+                "Module not found: {}",
+                path.join("::")
+            )));
         } else {
-            RuntimeError::new(message)
+            return Err(RuntimeError::new(
+                "Cannot resolve relative import without source path",
+            ));
+        }
+    }
+
+    // Otherwise, treat as package import
+    // Look in .lflibs or use manifest resolution
+    let module_name = &path[0];
+
+    // Try manifest resolution first
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(current_path) = source_path {
+        let current_file = PathBuf::from(current_path);
+
+        // Try to find manifest.json in current directory or parents
+        if let Ok(manifest) = crate::manifest::Manifest::find_and_load(
+            current_file
+                .parent()
+                .unwrap_or_else(|| std::path::Path::new(".")),
+        ) {
+            match manifest.resolve_import(path) {
+                Ok(resolved_path) => {
+                    let module_path = PathBuf::from(resolved_path);
+                    if module_path.exists() {
+                        return Ok(module_path);
+                    }
+                }
+                Err(_) => {
+                    // Continue to fallback resolution
+                }
+            }
         }
     }
+
+    // Fallback: look for file in current directory
+    let mut module_path = PathBuf::from(module_name);
+    module_path.set_extension("lf");
+
+    if vfs::exists(&module_path) {
+        return Ok(module_path);
+    }
+
+    Err(RuntimeError::new(format!(
+        "Module not found: {}",
+        path.join("::")
+    )))
+}
+
+/// Whether two module paths name the same file, e.g. `src/a.lf` and `src/./a.lf`
+fn same_module_file(a: &str, b: &str) -> bool {
+    match (std::fs::canonicalize(a), std::fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        // Bundled modules have no file on disk
+        _ => a == b,
+    }
 }
 
 #[cfg(test)]
@@ -2136,7 +3714,7 @@ mod tests {
 
         assert_eq!(
             interpreter.env.get("x"),
-            Some(&Value::String("hello world".to_string()))
+            Some(&Value::String("hello world".into()))
         );
     }
 
@@ -2173,6 +3751,223 @@ mod tests {
         assert_eq!(error.path, Some("test.lf".to_string()));
     }
 
+    #[test]
+    fn test_runtime_error_points_at_failing_expression() {
+        let input = "let x = 5;\nlet y = x + unknown;".to_string();
+        let stmts = Parser::new(InputStream::new("test.lf", &input))
+            .parse()
+            .unwrap();
+
+        let mut interpreter = Interpreter::with_source("test.lf", &input);
+        let error = interpreter.eval_program(stmts).unwrap_err();
+
+        let position = error.position.unwrap();
+        assert_eq!(&input[position..position + error.len.unwrap()], "unknown");
+    }
+
+    #[test]
+    fn test_import_builtin() {
+        let input = "learn \"math\" as m;\nlearn { sqrt, PI } from \"math\";\nlet a = m.abs(0 - 2);\nlet b = sqrt(16);\nlet c = PI > 3;"
+            .to_string();
+        let stmts = Parser::new(InputStream::new("test.lf", &input))
+            .parse()
+            .unwrap();
+
+        let mut interpreter = Interpreter::with_source("test.lf", &input);
+        interpreter.eval_program(stmts).unwrap();
+
+        assert_eq!(interpreter.env.get("a"), Some(&Value::Number(2.into())));
+        assert_eq!(interpreter.env.get("b"), Some(&Value::Number(4.into())));
+        assert_eq!(interpreter.env.get("c"), Some(&Value::Boolean(true)));
+    }
+
+    #[test]
+    fn test_non_ascii_string_literal() {
+        let input = "let word = \"café\";\nlet letters = word.chars();\nlet bytes = word.length();\nlet code = word.code_point_at(3);"
+            .to_string();
+        let stmts = Parser::new(InputStream::new("test.lf", &input))
+            .parse()
+            .unwrap();
+
+        let mut interpreter = Interpreter::with_source("test.lf", &input);
+        interpreter.eval_program(stmts).unwrap();
+
+        let letters = ["c", "a", "f", "é"]
+            .map(|letter| Value::String(letter.into()))
+            .to_vec();
+        assert_eq!(interpreter.env.get("letters"), Some(&Value::Array(letters)));
+        assert_eq!(interpreter.env.get("bytes"), Some(&Value::Number(5.into())));
+        assert_eq!(interpreter.env.get("code"), Some(&Value::Number(233.into())));
+    }
+
+    #[test]
+    fn test_execution_limits() {
+        let input =
+            "let x = 1;\ntry {\n    while (true) {}\n} catch (e) {\n    x = 2;\n}\n".to_string();
+        let stmts = Parser::new(InputStream::new("test.lf", &input))
+            .parse()
+            .unwrap();
+
+        let mut interpreter =
+            Interpreter::with_source("test.lf", &input).with_limits(limits::ExecutionLimits {
+                max_steps: Some(1000),
+                ..Default::default()
+            });
+        let error = interpreter.eval_program(stmts).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Execution limit exceeded: more than 1000 steps"
+        );
+        assert_eq!(interpreter.env.get("x"), Some(&Value::Number(1.into())));
+
+        // Resetting forgets the program and restores the budget
+        interpreter.reset();
+        assert_eq!(interpreter.env.get("x"), None);
+        let stmts = Parser::new(InputStream::new("test.lf", &"let y = 1;".to_string()))
+            .parse()
+            .unwrap();
+        interpreter.eval_program(stmts).unwrap();
+        assert_eq!(interpreter.env.get("y"), Some(&Value::Number(1.into())));
+    }
+
+    #[test]
+    fn test_runtime_error_in_imported_function_points_at_module() {
+        let dir = tempfile::tempdir().unwrap();
+        let module = "teach fn fail() -> num {\n    return nope;\n}\n";
+        std::fs::write(dir.path().join("util.lf"), module).unwrap();
+
+        let main_path = dir.path().join("main.lf");
+        let input = "learn \"./util\";\nutil.fail();\n".to_string();
+        let main_path = main_path.to_str().unwrap();
+        let stmts = Parser::new(InputStream::new(main_path, &input))
+            .parse()
+            .unwrap();
+
+        let mut interpreter = Interpreter::with_source(main_path, &input);
+        let error = interpreter.eval_program(stmts).unwrap_err();
+
+        assert!(error.path.unwrap().ends_with("util.lf"));
+        let position = error.position.unwrap();
+        assert_eq!(&module[position..position + error.len.unwrap()], "nope");
+    }
+
+    #[test]
+    fn test_profiler_counts_calls() {
+        let input = "fn fib(n: num) -> num {\n    if (n < 2) { return n; }\n    return fib(n - 1) + fib(n - 2);\n}\nlet s = \"abc\".to_upper();\nfib(5);\n".to_string();
+        let stmts = Parser::new(InputStream::new("test.lf", &input))
+            .parse()
+            .unwrap();
+
+        let mut interpreter = Interpreter::with_source("test.lf", &input).with_profiler();
+        interpreter.eval_program(stmts).unwrap();
+
+        let functions = interpreter.profiler().unwrap().functions();
+        assert_eq!(functions["fib"].calls, 15);
+        assert_eq!(functions["str.to_upper"].calls, 1);
+    }
+
+    #[test]
+    fn test_runtime_error_backtrace() {
+        let input = "fn inner(x: num) -> num {\n    return x / 0;\n}\nfn outer(x: num) -> num {\n    return inner(x);\n}\nouter(1);\n".to_string();
+        let stmts = Parser::new(InputStream::new("test.lf", &input))
+            .parse()
+            .unwrap();
+
+        let mut interpreter = Interpreter::with_source("test.lf", &input);
+        let error = interpreter.eval_program(stmts).unwrap_err();
+        let frames: Vec<String> = error.backtrace.iter().map(|f| f.to_string()).collect();
+        assert_eq!(
+            frames,
+            vec![
+                "inner at test.lf:2:12",
+                "outer at test.lf:5:12",
+                "<top level> at test.lf:7:1",
+            ]
+        );
+        assert!(error
+            .format_backtrace()
+            .unwrap()
+            .contains("→ 0: inner at test.lf:2:12"));
+    }
+
+    #[test]
+    fn test_try_catch_finally() {
+        let input = "let log = [];\nfn check(n: num) -> num {\n    try {\n        if (n > 1) { throw \"too big\"; }\n        return n;\n    } catch (e) {\n        log = log.push(e.message);\n        return 0;\n    } finally {\n        log = log.push(\"finally\");\n    }\n}\nlet a = check(1);\nlet b = check(2);\n".to_string();
+        let stmts = Parser::new(InputStream::new("test.lf", &input))
+            .parse()
+            .unwrap();
+
+        let mut interpreter = Interpreter::with_source("test.lf", &input);
+        interpreter.eval_program(stmts).unwrap();
+        assert_eq!(interpreter.env.get("a"), Some(&Value::Number(1.into())));
+        assert_eq!(interpreter.env.get("b"), Some(&Value::Number(0.into())));
+        assert_eq!(
+            interpreter.env.get("log"),
+            Some(&Value::Array(vec![
+                Value::String("finally".into()),
+                Value::String("too big".into()),
+                Value::String("finally".into()),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_defer_runs_on_exit_in_reverse_order() {
+        let run = |body: &str| {
+            let input = format!("fn fail(message: str) {{ throw message; }}\n{}", body);
+            let stmts = Parser::new(InputStream::new("test.lf", &input))
+                .parse()
+                .unwrap();
+            let mut interpreter = Interpreter::with_source("test.lf", &input);
+            interpreter.eval_program(stmts).map_err(|e| e.message)
+        };
+
+        // Deferred code runs after the body, the most recent first
+        let ordered = "fn work() -> num {\n    let name = \"first\";\n    defer fail(name);\n    defer fail(\"second\");\n    return 1;\n}\nwork();\n";
+        assert_eq!(run(ordered).unwrap_err(), "second");
+
+        // It still runs when the body fails, without hiding the body's error
+        let failing = "fn work() {\n    defer fail(\"cleanup\");\n    throw \"body\";\n}\ntry { work(); } catch (e) { fail(e.message + \" caught\"); }\n";
+        assert_eq!(run(failing).unwrap_err(), "body caught");
+
+        // Top-level code is deferred until the program finishes
+        let top_level = "defer fail(\"done\");\nlet x = 1;\n";
+        assert_eq!(run(top_level).unwrap_err(), "done");
+    }
+
+    #[test]
+    fn test_circular_import_names_the_cycle() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("a.lf"),
+            "learn \"./b\";\nteach fn x() -> num { return 1; }\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("b.lf"),
+            "learn \"./a\";\nteach fn y() -> num { return 2; }\n",
+        )
+        .unwrap();
+
+        let main_path = dir.path().join("a.lf");
+        let main_path = main_path.to_str().unwrap();
+        let input = std::fs::read_to_string(main_path).unwrap();
+        let stmts = Parser::new(InputStream::new(main_path, &input))
+            .parse()
+            .unwrap();
+
+        let mut interpreter = Interpreter::with_source(main_path, &input);
+        let error = interpreter.eval_program(stmts).unwrap_err();
+
+        let cycle: Vec<_> = error
+            .message
+            .trim_start_matches("Circular import: ")
+            .split(" -> ")
+            .map(|path| path.rsplit(['/', '\\']).next().unwrap())
+            .collect();
+        assert_eq!(cycle, vec!["a.lf", "b.lf", "a.lf"]);
+    }
+
     #[test]
     fn test_runtime_error_diagnostic_trait() {
         use miette::Diagnostic;
@@ -2239,7 +4034,7 @@ mod tests {
 
         // Check that the promise exists
         if let Some(Value::Promise(value)) = interpreter.env.get("promise") {
-            assert_eq!(**value, Value::String("test_url".to_string()));
+            assert_eq!(**value, Value::String("test_url".into()));
         } else {
             panic!("Expected promise value");
         }
@@ -2289,6 +4084,174 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_async_expressions_run_concurrently() {
+        let input = r#"
+            let start = time.perf_now();
+            let first = async time.sleep(200);
+            let second = async time.sleep(200);
+            await first;
+            await second;
+            let elapsed = time.perf_now() - start;
+        "#
+        .to_string();
+        let stream = InputStream::new("test", &input);
+        let mut parser = Parser::new(stream);
+        let stmts = parser.parse().unwrap();
+
+        let mut interpreter = Interpreter::new();
+        interpreter.eval_program(stmts).unwrap();
+
+        match interpreter.env.get("elapsed") {
+            Some(Value::Number(elapsed)) => assert!(*elapsed < Decimal::from(390)),
+            other => panic!("Expected elapsed time, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_lazy_promises_with_promise_all_and_race() {
+        let input = r#"
+            let base = 10;
+            let results = await promise.all([lazy (base + 1), async (base * 2), 3]);
+            let winner = await promise.race([lazy time.sleep(300), lazy base]);
+        "#
+        .to_string();
+        let stream = InputStream::new("test", &input);
+        let mut parser = Parser::new(stream);
+        let stmts = parser.parse().unwrap();
+
+        let mut interpreter = Interpreter::new();
+        interpreter.eval_program(stmts).unwrap();
+
+        assert_eq!(
+            interpreter.env.get("results"),
+            Some(&Value::Array(vec![
+                Value::Number(Decimal::from(11)),
+                Value::Number(Decimal::from(20)),
+                Value::Number(Decimal::from(3)),
+            ]))
+        );
+        assert_eq!(
+            interpreter.env.get("winner"),
+            Some(&Value::Number(Decimal::from(10)))
+        );
+    }
+
+    #[test]
+    fn test_async_cannot_assign_outer_variables() {
+        let run = |input: &str| {
+            let input = input.to_string();
+            let stmts = Parser::new(InputStream::new("test", &input))
+                .parse()
+                .unwrap();
+            let mut interpreter = Interpreter::new();
+            interpreter.eval_program(stmts).map(|_| interpreter)
+        };
+
+        // Variables of the task itself can change
+        let interpreter = run(r#"
+            fn count_up() -> num { let n = 1; n = n + 1; return n; }
+            let counted = await async count_up();
+        "#)
+        .unwrap();
+        assert_eq!(
+            interpreter.env.get("counted"),
+            Some(&Value::Number(Decimal::from(2)))
+        );
+
+        // The task has a copy of `count`, so the change would be lost
+        for task in ["async", "lazy"] {
+            let error = run(&format!(
+                "let count = 0;\nfn bump() {{ count = count + 1; }}\nawait {} bump();\n",
+                task
+            ))
+            .err()
+            .unwrap();
+            assert!(
+                error.message.starts_with("Cannot assign to 'count' inside an async or lazy task"),
+                "{}",
+                error.message
+            );
+        }
+    }
+
+    #[test]
+    fn test_non_exhaustive_match_is_rejected_before_running() {
+        let input = r#"
+            let mut ran = false;
+            ran = true;
+            enum Dir { Up, Down }
+            let d = Dir.Up;
+            let name = match d {
+                Dir.Up => "up",
+            };
+        "#
+        .to_string();
+        let stream = InputStream::new("test", &input);
+        let mut parser = Parser::new(stream);
+        let stmts = parser.parse().unwrap();
+
+        let mut interpreter = Interpreter::with_source("test", &input);
+        let err = interpreter.eval_program(stmts).unwrap_err();
+        assert!(err.message.contains("missing Dir.Down"));
+        assert_eq!(err.position, input.find("match"));
+        assert_eq!(err.len, Some("match".len()));
+        assert_eq!(crate::codes::of_runtime_error(&err).unwrap().code, "L0010");
+        assert_eq!(interpreter.env.get("ran"), None);
+    }
+
+    #[test]
+    fn test_enum_payload_destructuring() {
+        let input = r#"
+            enum Shape { Circle(num), Rect(num, num), Point }
+            fn area(s: Shape) -> num {
+                return match s {
+                    Shape.Circle(r) => r * r,
+                    Shape.Rect(w, h) => w * h,
+                    Shape.Point => 0,
+                };
+            }
+            let rect_area = area(Shape.Rect(2, 5));
+            let nested = match Option.Some(Shape.Circle(1)) {
+                Option.Some(Shape.Circle(1)) => "unit circle",
+                Option.Some(_) => "other shape",
+                Option.None => "nothing",
+            };
+        "#
+        .to_string();
+        let stream = InputStream::new("test", &input);
+        let mut parser = Parser::new(stream);
+        let stmts = parser.parse().unwrap();
+
+        let mut interpreter = Interpreter::new();
+        interpreter.eval_program(stmts).unwrap();
+
+        assert_eq!(
+            interpreter.env.get("rect_area"),
+            Some(&Value::Number(Decimal::from(10)))
+        );
+        assert_eq!(
+            interpreter.env.get("nested"),
+            Some(&Value::String("unit circle".into()))
+        );
+    }
+
+    #[test]
+    fn test_enum_payload_types_are_checked() {
+        let input = r#"
+            enum Token { Word(str), Count(num) }
+            let bad = Token.Count("three");
+        "#
+        .to_string();
+        let stream = InputStream::new("test", &input);
+        let mut parser = Parser::new(stream);
+        let stmts = parser.parse().unwrap();
+
+        let mut interpreter = Interpreter::new();
+        let err = interpreter.eval_program(stmts).unwrap_err();
+        assert!(err.message.contains("expects num for field 0, got str"));
+    }
+
     #[test]
     fn test_regular_function_call() {
         let input = r#"
@@ -2450,12 +4413,59 @@ mod tests {
         let mut interpreter = Interpreter::new();
         interpreter.eval_program(stmts).unwrap();
 
-        // Check that the promise exists and contains Unit
-        if let Some(Value::Promise(value)) = interpreter.env.get("promise") {
-            assert_eq!(**value, Value::Unit);
-        } else {
-            panic!("Expected promise value from time.sleep()");
+        // The promise resolves to Unit once the time has passed
+        if let Some(Value::Task(task)) = interpreter.env.get("promise") {
+            assert!(task.result().is_none());
+            assert_eq!(task.wait().unwrap(), Value::Unit);
+        } else {
+            panic!("Expected promise value from time.sleep()");
+        }
+    }
+
+    #[test]
+    fn test_timers() {
+        let input = r#"
+            let start = time.perf_now();
+            let ticks = sync.channel();
+            let timer = time.interval(20, () => ticks.send("tick"));
+            let mut count = 0;
+            for i in [1, 2, 3] {
+                await ticks.recv();
+                count = count + 1;
+            }
+            timer.cancel();
+
+            let fired = sync.channel();
+            let once = time.timeout(10, () => fired.send("timeout"));
+            let cancelled = time.timeout(10, () => fired.send("cancelled"));
+            cancelled.cancel();
+            let first = async time.sleep(100);
+            let second = async time.sleep(100);
+            await first;
+            await second;
+            let elapsed = time.perf_now() - start;
+            let first_fired = fired.try_recv();
+            let then_fired = fired.try_recv();
+        "#
+        .to_string();
+        let stmts = Parser::new(InputStream::new("test", &input))
+            .parse()
+            .unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.eval_program(stmts).unwrap();
+
+        assert_eq!(interpreter.env.get("count"), Some(&Value::Number(3.into())));
+        match interpreter.env.get("elapsed") {
+            Some(Value::Number(elapsed)) => {
+                assert!(*elapsed >= Decimal::from(160) && *elapsed < Decimal::from(400))
+            }
+            other => panic!("Expected elapsed time, got {:?}", other),
         }
+        assert_eq!(
+            interpreter.env.get("first_fired"),
+            Some(&Value::String("timeout".into()))
+        );
+        assert_eq!(interpreter.env.get("then_fired"), Some(&Value::Unit));
     }
 
     #[test]
@@ -2576,8 +4586,8 @@ mod tests {
 
         if let Some(Value::Array(arr)) = interpreter.env.get("result") {
             assert_eq!(arr.len(), 2);
-            assert_eq!(arr[0], Value::String("hello".to_string()));
-            assert_eq!(arr[1], Value::String("world".to_string()));
+            assert_eq!(arr[0], Value::String("hello".into()));
+            assert_eq!(arr[1], Value::String("world".into()));
         } else {
             panic!("result should be an array");
         }
@@ -2595,7 +4605,7 @@ mod tests {
 
         assert_eq!(
             interpreter.env.get("result"),
-            Some(&Value::String("hello".to_string()))
+            Some(&Value::String("hello".into()))
         );
     }
 
@@ -2615,11 +4625,11 @@ mod tests {
 
         assert_eq!(
             interpreter.env.get("upper"),
-            Some(&Value::String("HELLO".to_string()))
+            Some(&Value::String("HELLO".into()))
         );
         assert_eq!(
             interpreter.env.get("lower"),
-            Some(&Value::String("hello".to_string()))
+            Some(&Value::String("hello".into()))
         );
     }
 
@@ -2635,7 +4645,7 @@ mod tests {
 
         assert_eq!(
             interpreter.env.get("result"),
-            Some(&Value::String("hello rust".to_string()))
+            Some(&Value::String("hello rust".into()))
         );
     }
 
@@ -2781,7 +4791,7 @@ mod tests {
         assert_eq!(num.to_string(), "42");
 
         // Test String
-        let str_val = Value::String("hello".to_string());
+        let str_val = Value::String("hello".into());
         assert_eq!(str_val.to_string(), "hello");
 
         // Test Boolean
@@ -2795,7 +4805,7 @@ mod tests {
         // Test Array
         let arr_val = Value::Array(vec![
             Value::Number(Decimal::from(1)),
-            Value::String("hello".to_string()),
+            Value::String("hello".into()),
             Value::Boolean(false),
         ]);
         assert_eq!(arr_val.to_string(), "[1, hello, false]");
@@ -2816,7 +4826,7 @@ mod tests {
 
         assert_eq!(
             interpreter.env.get("result"),
-            Some(&Value::String("1, hello, true".to_string()))
+            Some(&Value::String("1, hello, true".into()))
         );
     }
 
@@ -2925,7 +4935,7 @@ mod tests {
 
         assert_eq!(
             interpreter.env.get("name_value"),
-            Some(&Value::String("Alice".to_string()))
+            Some(&Value::String("Alice".into()))
         );
         assert_eq!(
             interpreter.env.get("age_value"),
@@ -3016,7 +5026,687 @@ mod tests {
 
         assert_eq!(
             interpreter.env.get("char"),
-            Some(&Value::String("e".to_string()))
+            Some(&Value::String("e".into()))
+        );
+    }
+
+    #[test]
+    fn test_map_literal_and_methods() {
+        let input = r#"
+            let m = #{ "a": 1, b: 2 };
+            let a = m["a"];
+            let b = m.get("b");
+            let missing = m.get("c", 0);
+            let m2 = m.set("c", 3).remove("a");
+            let keys = m2.keys();
+            let has_a = m2.contains_key("a");
+        "#
+        .to_string();
+        let stream = InputStream::new("test", &input);
+        let mut parser = Parser::new(stream);
+        let stmts = parser.parse().unwrap();
+
+        let mut interpreter = Interpreter::new();
+        interpreter.eval_program(stmts).unwrap();
+
+        assert_eq!(
+            interpreter.env.get("a"),
+            Some(&Value::Number(Decimal::from(1)))
+        );
+        assert_eq!(
+            interpreter.env.get("b"),
+            Some(&Value::Number(Decimal::from(2)))
+        );
+        assert_eq!(
+            interpreter.env.get("missing"),
+            Some(&Value::Number(Decimal::from(0)))
+        );
+        assert_eq!(
+            interpreter.env.get("keys"),
+            Some(&Value::Array(vec![
+                Value::String("b".into()),
+                Value::String("c".into()),
+            ]))
+        );
+        assert_eq!(interpreter.env.get("has_a"), Some(&Value::Boolean(false)));
+    }
+
+    #[test]
+    fn test_map_for_loop_iteration() {
+        let input = r#"
+            let scores = #{ alice: 3, bob: 4 };
+            let mut total = 0;
+            let mut names = "";
+            for entry in scores {
+                names = names + entry[0];
+                total = total + entry[1];
+            }
+        "#
+        .to_string();
+        let stream = InputStream::new("test", &input);
+        let mut parser = Parser::new(stream);
+        let stmts = parser.parse().unwrap();
+
+        let mut interpreter = Interpreter::new();
+        interpreter.eval_program(stmts).unwrap();
+
+        assert_eq!(
+            interpreter.env.get("total"),
+            Some(&Value::Number(Decimal::from(7)))
+        );
+        assert_eq!(
+            interpreter.env.get("names"),
+            Some(&Value::String("alicebob".into()))
+        );
+    }
+
+    #[test]
+    fn test_array_sort_with_callbacks() {
+        let input = r#"
+            let words = ["pear", "fig", "banana", "kiwi"];
+            let by_length = words.sort_by_key((w) => w.length());
+            let descending = [3, 1, 2].sort_by((a, b) => b - a);
+            let sorted = words.sort();
+            let found = sorted.binary_search("kiwi");
+        "#
+        .to_string();
+        let stream = InputStream::new("test", &input);
+        let mut parser = Parser::new(stream);
+        let stmts = parser.parse().unwrap();
+
+        let mut interpreter = Interpreter::new();
+        interpreter.eval_program(stmts).unwrap();
+
+        let strings = |items: &[&str]| {
+            Value::Array(items.iter().map(|s| Value::String((*s).into())).collect())
+        };
+        // Equal keys keep their original order
+        assert_eq!(
+            interpreter.env.get("by_length"),
+            Some(&strings(&["fig", "pear", "kiwi", "banana"]))
+        );
+        assert_eq!(
+            interpreter.env.get("descending"),
+            Some(&Value::Array(vec![
+                Value::Number(Decimal::from(3)),
+                Value::Number(Decimal::from(2)),
+                Value::Number(Decimal::from(1)),
+            ]))
+        );
+        assert_eq!(
+            interpreter.env.get("sorted"),
+            Some(&strings(&["banana", "fig", "kiwi", "pear"]))
+        );
+        assert_eq!(
+            interpreter.env.get("found"),
+            Some(&Value::Number(Decimal::from(2)))
+        );
+    }
+
+    #[test]
+    fn test_integer_arithmetic() {
+        let input = r#"
+            let remainder = (0 - 7) % 2;
+            let quotient = (0 - 7).div_int(2);
+            let whole = (6).is_int();
+            let fraction = num.is_int(2.5);
+            let masked = 12 & 10;
+            let shifted = 1 << 62;
+        "#
+        .to_string();
+        let stream = InputStream::new("test", &input);
+        let mut parser = Parser::new(stream);
+        let stmts = parser.parse().unwrap();
+
+        let mut interpreter = Interpreter::new();
+        interpreter.eval_program(stmts).unwrap();
+
+        let number = |n: i64| Some(Value::Number(Decimal::from(n)));
+        assert_eq!(interpreter.env.get("remainder").cloned(), number(-1));
+        assert_eq!(interpreter.env.get("quotient").cloned(), number(-3));
+        assert_eq!(interpreter.env.get("whole"), Some(&Value::Boolean(true)));
+        assert_eq!(
+            interpreter.env.get("fraction"),
+            Some(&Value::Boolean(false))
+        );
+        assert_eq!(interpreter.env.get("masked").cloned(), number(8));
+        assert_eq!(interpreter.env.get("shifted").cloned(), number(1 << 62));
+
+        // Results that don't fit are errors rather than wrong answers
+        for (code, message) in [
+            ("79228162514264337593543950335 * 2;", "Number overflow"),
+            ("5 % 0;", "Division by zero"),
+            ("1 << 63;", "Number overflow in left shift"),
+            ("2.5 | 1;", "Bitwise OR requires integer operands, got 2.5"),
+        ] {
+            let input = code.to_string();
+            let stmts = Parser::new(InputStream::new("test", &input))
+                .parse()
+                .unwrap();
+            let error = Interpreter::new().eval_program(stmts).unwrap_err();
+            assert_eq!(
+                error.message,
+                format!("{} (left: num, right: num)", message)
+            );
+        }
+    }
+
+    #[test]
+    fn test_rest_parameters_and_spread() {
+        let input = r#"
+            fn sum(...nums: Array<num>) -> num {
+                let total = 0;
+                for n in nums {
+                    total = total + n;
+                }
+                return total;
+            }
+            fn tag(name: str, ...rest: Array<num>) -> str {
+                return `${name}:${rest.length()}`;
+            }
+            let more = [3, 4];
+            let none = sum();
+            let total = sum(1, 2, ...more);
+            let tagged = tag("x", ...more, 5);
+            let joined = [0, ...more, ...[]];
+        "#
+        .to_string();
+        let stmts = Parser::new(InputStream::new("test", &input))
+            .parse()
+            .unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.eval_program(stmts).unwrap();
+
+        let number = |n: i64| Value::Number(Decimal::from(n));
+        assert_eq!(interpreter.env.get("none"), Some(&number(0)));
+        assert_eq!(interpreter.env.get("total"), Some(&number(10)));
+        assert_eq!(
+            interpreter.env.get("tagged"),
+            Some(&Value::String("x:3".into()))
+        );
+        assert_eq!(
+            interpreter.env.get("joined"),
+            Some(&Value::Array(vec![number(0), number(3), number(4)]))
+        );
+
+        for (code, message) in [
+            (
+                "fn tag(name: str, ...rest: Array<num>) {}\ntag();",
+                "Function 'tag' expects at least 1 arguments, got 0",
+            ),
+            (
+                "fn f(...xs: Array<num>) {}\nf(...5);",
+                "Cannot spread a value of type num, only arrays",
+            ),
+        ] {
+            let input = code.to_string();
+            let stmts = Parser::new(InputStream::new("test", &input))
+                .parse()
+                .unwrap();
+            let error = Interpreter::new().eval_program(stmts).unwrap_err();
+            assert_eq!(error.message, message);
+        }
+
+        let input = "fn f(...xs: Array<num>, y: num) {}".to_string();
+        let error = Parser::new(InputStream::new("test", &input))
+            .parse()
+            .unwrap_err();
+        assert_eq!(error.message, "A rest parameter must be the last parameter");
+    }
+
+    #[test]
+    fn test_if_expression() {
+        let input = r#"
+            fn sign(n: num) -> str {
+                return if (n > 0) { "positive" } else if (n < 0) { "negative" } else { "zero" };
+            }
+            let x = 5;
+            let doubled = if (x > 3) { let d = x * 2; d + 1 } else { 0 };
+            let signs = `${sign(0 - 2)} ${sign(0)} ${sign(4)}`;
+            let missing = if (x < 3) { 1 };
+            let joined = if (true) { "a" } else { "b" } + "!";
+        "#
+        .to_string();
+        let stmts = Parser::new(InputStream::new("test", &input))
+            .parse()
+            .unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.eval_program(stmts).unwrap();
+
+        assert_eq!(
+            interpreter.env.get("doubled"),
+            Some(&Value::Number(Decimal::from(11)))
+        );
+        assert_eq!(
+            interpreter.env.get("signs"),
+            Some(&Value::String("negative zero positive".into()))
+        );
+        assert_eq!(interpreter.env.get("missing"), Some(&Value::Unit));
+        assert_eq!(
+            interpreter.env.get("joined"),
+            Some(&Value::String("a!".into()))
+        );
+    }
+
+    #[test]
+    fn test_slicing_and_negative_indices() {
+        let input = r#"
+            let items = [10, 20, 30, 40, 50];
+            let word = "hello world";
+            let middle = items[1..3];
+            let last = items[-1];
+            let head = items[..2];
+            let tail = items[-2..];
+            let inner = items[1..-1];
+            let prefix = word[..5];
+            let suffix = word[-5..];
+            let second = word[1];
+            let negated = -last * 2;
+        "#
+        .to_string();
+        let stmts = Parser::new(InputStream::new("test", &input))
+            .parse()
+            .unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.eval_program(stmts).unwrap();
+
+        let number = |n: i64| Value::Number(Decimal::from(n));
+        let numbers = |ns: &[i64]| Value::Array(ns.iter().map(|&n| number(n)).collect());
+        let string = |s: &str| Value::String(s.into());
+        assert_eq!(interpreter.env.get("middle"), Some(&numbers(&[20, 30])));
+        assert_eq!(interpreter.env.get("last"), Some(&number(50)));
+        assert_eq!(interpreter.env.get("head"), Some(&numbers(&[10, 20])));
+        assert_eq!(interpreter.env.get("tail"), Some(&numbers(&[40, 50])));
+        assert_eq!(interpreter.env.get("inner"), Some(&numbers(&[20, 30, 40])));
+        assert_eq!(interpreter.env.get("prefix"), Some(&string("hello")));
+        assert_eq!(interpreter.env.get("suffix"), Some(&string("world")));
+        assert_eq!(interpreter.env.get("second"), Some(&string("e")));
+        assert_eq!(interpreter.env.get("negated"), Some(&number(-100)));
+
+        for (code, message) in [
+            ("[1, 2][5];", "Array index 5 out of bounds for length 2"),
+            ("[1, 2][-3];", "Array index -3 out of bounds for length 2"),
+            (
+                "[1, 2][0..3];",
+                "Array slice end 3 out of bounds for length 2",
+            ),
+            ("[1, 2][2..1];", "Array slice start 2 is after its end 1"),
+            ("\"ab\"[0.5];", "String index must be an integer, got 0.5"),
+            ("5[1..];", "Cannot slice a value of type num"),
+        ] {
+            let input = code.to_string();
+            let stmts = Parser::new(InputStream::new("test", &input))
+                .parse()
+                .unwrap();
+            let error = Interpreter::new().eval_program(stmts).unwrap_err();
+            assert_eq!(error.message, message);
+        }
+    }
+
+    #[test]
+    fn test_associated_functions() {
+        let point = r#"
+            def Point { x: num, y: num }
+            impl Point {
+                fn new(x: num, y: num) -> Point {
+                    return Point { x: x, y: y };
+                }
+                fn origin() -> Point {
+                    return Point::new(0, 0);
+                }
+                fn sum(self) -> num {
+                    return self.x + self.y;
+                }
+            }
+        "#;
+        let input = format!(
+            "{}let a = Point.new(1, 2).sum(); let b = Point::origin().sum();",
+            point
+        );
+        let stmts = Parser::new(InputStream::new("test", &input))
+            .parse()
+            .unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.eval_program(stmts).unwrap();
+        assert_eq!(
+            interpreter.env.get("a"),
+            Some(&Value::Number(Decimal::from(3)))
+        );
+        assert_eq!(
+            interpreter.env.get("b"),
+            Some(&Value::Number(Decimal::from(0)))
+        );
+
+        for (code, message) in [
+            (
+                "Point.new(1, 2).new(3, 4);",
+                "'new' is an associated function of 'Point', call it as Point.new(...)",
+            ),
+            (
+                "Point.sum();",
+                "'sum' is a method of 'Point' and must be called on a value",
+            ),
+        ] {
+            let input = format!("{}{}", point, code);
+            let stmts = Parser::new(InputStream::new("test", &input))
+                .parse()
+                .unwrap();
+            let error = Interpreter::new().eval_program(stmts).unwrap_err();
+            assert_eq!(error.message, message);
+        }
+    }
+
+    #[test]
+    fn test_trait_default_methods() {
+        let input = r#"
+            trait Named {
+                fn name(self) -> str;
+                fn greet(self) -> str {
+                    return "Hello, " + self.name();
+                }
+                fn shout(self) -> str {
+                    return self.greet() + "!";
+                }
+            }
+            trait Tagged {
+                fn tag(self) -> str {
+                    return "tag:" + self.name();
+                }
+            }
+            def Dog { label: str }
+            def Cat { label: str }
+            impl Named for Dog {
+                fn name(self) -> str {
+                    return self.label;
+                }
+            }
+            impl Tagged for Dog {}
+            impl Named for Cat {
+                fn name(self) -> str {
+                    return self.label;
+                }
+                fn greet(self) -> str {
+                    return Named::greet(self) + ", says the cat";
+                }
+            }
+            let dog = Dog { label: "Rex" };
+            let cat = Cat { label: "Tom" };
+            let dog_greeting = dog.shout();
+            let dog_tag = dog.tag();
+            let cat_greeting = cat.shout();
+        "#
+        .to_string();
+        let stmts = Parser::new(InputStream::new("test", &input))
+            .parse()
+            .unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.eval_program(stmts).unwrap();
+
+        let string = |s: &str| Value::String(s.into());
+        assert_eq!(
+            interpreter.env.get("dog_greeting"),
+            Some(&string("Hello, Rex!"))
+        );
+        assert_eq!(interpreter.env.get("dog_tag"), Some(&string("tag:Rex")));
+        assert_eq!(
+            interpreter.env.get("cat_greeting"),
+            Some(&string("Hello, Tom, says the cat!"))
+        );
+
+        let input = "trait Named { fn name(self) -> str; }\nNamed.name(1);".to_string();
+        let stmts = Parser::new(InputStream::new("test", &input))
+            .parse()
+            .unwrap();
+        let error = Interpreter::new().eval_program(stmts).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Method 'name' of trait 'Named' has no default body"
+        );
+    }
+
+    #[test]
+    fn test_long_chains_run() {
+        // Debug builds need more stack per level than the test threads get
+        let handle = std::thread::Builder::new()
+            .stack_size(8 * 1024 * 1024)
+            .spawn(|| {
+                let input = format!(
+                    "def Counter {{ n: num }}
+                    impl Counter {{
+                        fn m(self) -> Counter {{
+                            return Counter {{ n: self.n + 1 }};
+                        }}
+                    }}
+                    let sum = {};
+                    let count = Counter {{ n: 0 }}{}.n;",
+                    ["1"; 1000].join(" + "),
+                    ".m()".repeat(400)
+                );
+                let stmts = Parser::new(InputStream::new("test", &input))
+                    .parse()
+                    .unwrap();
+                let mut interpreter = Interpreter::new();
+                interpreter.eval_program(stmts).unwrap();
+                assert_eq!(
+                    interpreter.env.get("sum"),
+                    Some(&Value::Number(1000.into()))
+                );
+                assert_eq!(
+                    interpreter.env.get("count"),
+                    Some(&Value::Number(400.into()))
+                );
+            })
+            .unwrap();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_deep_equality_and_ordering() {
+        let input = r#"
+            def Point { x: num, y: num }
+            def Loose { v: num }
+            impl Loose {
+                fn eq(self, other: Loose) -> bool {
+                    return true;
+                }
+            }
+            let f = (x) => x;
+            let g = (x) => x;
+            let results = [
+                Point { x: 1, y: 2 } == Point { y: 2, x: 1 },
+                Point { x: 1, y: 2 } != Point { x: 1, y: 3 },
+                [[1, 2], #{"a": [3]}] == [[1, 2], #{"a": [3]}],
+                [Loose { v: 1 }] == [Loose { v: 2 }],
+                object.is(Loose { v: 1 }, Loose { v: 2 }),
+                f == f,
+                f == g,
+                1 == "1",
+                [1, 2] < [1, 3],
+                [1] < [1, 0],
+                "apple" < "banana",
+                [2] >= [1, 5],
+            ];
+        "#
+        .to_string();
+        let stmts = Parser::new(InputStream::new("test", &input))
+            .parse()
+            .unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.eval_program(stmts).unwrap();
+
+        let expected = [
+            true, true, true, true, false, true, false, false, true, true, true, true,
+        ];
+        assert_eq!(
+            interpreter.env.get("results"),
+            Some(&Value::Array(
+                expected.into_iter().map(Value::Boolean).collect()
+            ))
+        );
+
+        let input = "[1] < [\"a\"];".to_string();
+        let stmts = Parser::new(InputStream::new("test", &input))
+            .parse()
+            .unwrap();
+        let error = Interpreter::new().eval_program(stmts).unwrap_err();
+        assert!(
+            error.message.starts_with("Cannot compare"),
+            "{}",
+            error.message
+        );
+    }
+
+    #[test]
+    fn test_clone_copies_values() {
+        let input = r#"
+            def Point { x: num, tags: Array<str> }
+            def Counter { n: num }
+            impl Counter {
+                fn clone(self) -> Counter {
+                    return Counter { n: 0 };
+                }
+            }
+            let p = Point { x: 1, tags: ["a"] };
+            let original = [1, 2];
+            let pushed = original.push(3);
+            let results = [
+                p.clone() == p,
+                [[1], #{"k": 2}].clone(),
+                object.clone("text"),
+                Counter { n: 5 }.clone().n,
+                original,
+                pushed,
+            ];
+        "#
+        .to_string();
+        let stmts = Parser::new(InputStream::new("test", &input))
+            .parse()
+            .unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.eval_program(stmts).unwrap();
+
+        let num = |n: i64| Value::Number(n.into());
+        assert_eq!(
+            interpreter.env.get("results"),
+            Some(&Value::Array(vec![
+                Value::Boolean(true),
+                Value::Array(vec![
+                    Value::Array(vec![num(1)]),
+                    Value::Map(vec![(Value::String("k".into()), num(2))]),
+                ]),
+                Value::String("text".into()),
+                num(0),
+                Value::Array(vec![num(1), num(2)]),
+                Value::Array(vec![num(1), num(2), num(3)]),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_worker_spawn() {
+        let dir = tempfile::tempdir().unwrap();
+        let child = "let port = worker.parent();\nport.send(port.recv().sum());\n";
+        std::fs::write(dir.path().join("child.lf"), child).unwrap();
+
+        let main_path = dir.path().join("main.lf");
+        let main_path = main_path.to_str().unwrap();
+        let input = r#"
+            fn square(n: num) -> num {
+                return n * n;
+            }
+            let squarer = worker.spawn((port) => {
+                let n = port.recv();
+                port.send(#{ "n": n, "square": square(n) });
+                return "done";
+            });
+            squarer.send(7);
+            let reply = squarer.recv();
+            let finished = squarer.join();
+
+            let summer = worker.spawn("child.lf");
+            summer.send([1, 2, 3]);
+            let sum = summer.recv();
+            summer.join();
+
+            let failing = worker.spawn((port) => port.send((x) => x));
+            let error = assert.throws(() => failing.join());
+        "#
+        .to_string();
+        let stmts = Parser::new(InputStream::new(main_path, &input))
+            .parse()
+            .unwrap();
+        let mut interpreter = Interpreter::with_source(main_path, &input);
+        interpreter.eval_program(stmts).unwrap();
+
+        let Some(Value::Struct { fields, .. }) = interpreter.env.get("reply") else {
+            panic!("expected the reply as an object");
+        };
+        assert_eq!(fields["square"], Value::Number(49.into()));
+        assert_eq!(
+            interpreter.env.get("finished"),
+            Some(&Value::String("done".into()))
+        );
+        assert_eq!(interpreter.env.get("sum"), Some(&Value::Number(6.into())));
+
+        let Some(Value::String(error)) = interpreter.env.get("error") else {
+            panic!("expected the worker's error");
+        };
+        assert!(error.starts_with("send() requires a JSON-serializable value"));
+    }
+
+    #[test]
+    fn test_sync_primitives_between_tasks() {
+        let input = r#"
+            let results = sync.channel();
+            let total = sync.mutex(0);
+            let seen = sync.mutex([]);
+            let slots = sync.semaphore(2);
+            fn work(n: num) {
+                await slots.acquire();
+                total.update((t) => t + n);
+                seen.update((items) => items.push(n));
+                slots.release();
+                results.send(n * 10);
+            }
+            let mut tasks = [];
+            for n in [1, 2, 3, 4] {
+                tasks = tasks.push(async work(n));
+            }
+            await promise.all(tasks);
+
+            let mut received = [];
+            for n in [1, 2, 3, 4] {
+                received = received.push(await results.recv());
+            }
+            received = received.sort();
+            results.close();
+            let after_close = await results.recv();
+            let sum = total.get();
+            let order = seen.get().sort();
+            let nested = assert.throws(() => total.update((t) => total.get()));
+        "#
+        .to_string();
+        let stmts = Parser::new(InputStream::new("test", &input))
+            .parse()
+            .unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.eval_program(stmts).unwrap();
+
+        let numbers = |items: &[i64]| {
+            Value::Array(items.iter().map(|n| Value::Number((*n).into())).collect())
+        };
+        assert_eq!(interpreter.env.get("sum"), Some(&Value::Number(10.into())));
+        assert_eq!(interpreter.env.get("order"), Some(&numbers(&[1, 2, 3, 4])));
+        assert_eq!(
+            interpreter.env.get("received"),
+            Some(&numbers(&[10, 20, 30, 40]))
+        );
+        assert_eq!(interpreter.env.get("after_close"), Some(&Value::Unit));
+        assert_eq!(
+            interpreter.env.get("nested"),
+            Some(&Value::String(
+                "get(): the mutex is being updated by this same update()".into()
+            ))
         );
     }
 }
@@ -12,6 +12,11 @@ pub fn init_permissions(manager: PermissionManager) {
     });
 }
 
+/// Swap the permission manager for the current thread, returning the previous one
+pub fn replace_permissions(manager: Option<PermissionManager>) -> Option<PermissionManager> {
+    PERMISSION_MANAGER.with(|pm| std::mem::replace(&mut *pm.borrow_mut(), manager))
+}
+
 /// Get the current permission manager (if any)
 pub fn with_permissions<F, R>(f: F) -> Option<R>
 where
@@ -2,6 +2,10 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Held while a prompt waits for an answer so threads never ask at the same time
+static PROMPT_LOCK: Mutex<()> = Mutex::new(());
 
 /// Types of permissions that can be requested
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -16,6 +20,215 @@ pub enum PermissionType {
     Run(String),
 }
 
+impl PermissionType {
+    /// Command line flag that grants this kind of permission up front
+    pub fn flag(&self) -> &'static str {
+        match self {
+            PermissionType::Read(_) => "--allow-read",
+            PermissionType::Write(_) => "--allow-write",
+            PermissionType::Net(_) => "--allow-net",
+            PermissionType::Run(_) => "--allow-run",
+        }
+    }
+}
+
+impl std::fmt::Display for PermissionType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PermissionType::Read(path) => write!(f, "read access to {}", path),
+            PermissionType::Write(path) => write!(f, "write access to {}", path),
+            PermissionType::Net(host) => write!(f, "network access to {}", host),
+            PermissionType::Run(cmd) => write!(f, "permission to run {}", cmd),
+        }
+    }
+}
+
+impl PermissionType {
+    /// Read a permission written as a kind and a target, e.g. `net` and `example.com`
+    pub fn parse(kind: &str, target: &str) -> Option<Self> {
+        let target = target.to_string();
+        match kind {
+            "read" => Some(PermissionType::Read(target)),
+            "write" => Some(PermissionType::Write(target)),
+            "net" => Some(PermissionType::Net(target)),
+            "run" => Some(PermissionType::Run(target)),
+            _ => None,
+        }
+    }
+
+    /// The kind of access, as accepted by [`PermissionType::parse`]
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PermissionType::Read(_) => "read",
+            PermissionType::Write(_) => "write",
+            PermissionType::Net(_) => "net",
+            PermissionType::Run(_) => "run",
+        }
+    }
+
+    /// The path, host or command the permission is for
+    pub fn target(&self) -> &str {
+        match self {
+            PermissionType::Read(target)
+            | PermissionType::Write(target)
+            | PermissionType::Net(target)
+            | PermissionType::Run(target) => target,
+        }
+    }
+}
+
+/// A decision saved to the permission cache with "always" or "never"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedPermission {
+    pub permission: PermissionType,
+    pub allowed: bool,
+    /// The script that asked, when known
+    #[serde(default)]
+    pub script: Option<String>,
+    /// When the decision was made, in RFC 3339
+    #[serde(default)]
+    pub decided_at: Option<String>,
+}
+
+/// An entry of the cache file, which used to hold bare `[permission, allowed]` pairs
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CacheEntry {
+    Pair(PermissionType, bool),
+    Full(CachedPermission),
+}
+
+/// Who made a cached decision and when
+#[derive(Debug, Clone, Default)]
+struct DecisionOrigin {
+    script: Option<String>,
+    decided_at: Option<String>,
+}
+
+/// What a single `--allow-*` flag grants
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum PermissionScope {
+    /// Flag not given
+    #[default]
+    None,
+    /// Flag given without values, e.g. `--allow-read`
+    All,
+    /// Flag given with values, e.g. `--allow-read=./data,./config`
+    Only(Vec<String>),
+}
+
+impl PermissionScope {
+    /// Scope for a flag parsed from the command line: absent, bare, or with values
+    pub fn from_flag(values: Option<Vec<String>>) -> Self {
+        match values {
+            None => PermissionScope::None,
+            Some(values) if values.iter().all(|v| v.trim().is_empty()) => PermissionScope::All,
+            Some(values) => PermissionScope::Only(
+                values
+                    .into_iter()
+                    .map(|v| v.trim().to_string())
+                    .filter(|v| !v.is_empty())
+                    .collect(),
+            ),
+        }
+    }
+
+    fn from_bool(allowed: bool) -> Self {
+        if allowed {
+            PermissionScope::All
+        } else {
+            PermissionScope::None
+        }
+    }
+
+    /// Whether `path` is one of the allowed paths or inside one of them
+    pub fn permits_path(&self, path: &str) -> bool {
+        match self {
+            PermissionScope::None => false,
+            PermissionScope::All => true,
+            PermissionScope::Only(allowed) => {
+                let target = resolve_path(Path::new(path));
+                allowed
+                    .iter()
+                    .any(|a| target.starts_with(resolve_path(Path::new(a))))
+            }
+        }
+    }
+
+    /// Whether `host` (`name` or `name:port`) matches an allowed host
+    ///
+    /// An allowed host without a port matches every port of that host.
+    pub fn permits_host(&self, host: &str) -> bool {
+        match self {
+            PermissionScope::None => false,
+            PermissionScope::All => true,
+            PermissionScope::Only(allowed) => {
+                let name = split_port(host).0;
+                allowed.iter().any(|a| match split_port(a) {
+                    (allowed_name, None) => allowed_name.eq_ignore_ascii_case(name),
+                    (_, Some(_)) => a.eq_ignore_ascii_case(host),
+                })
+            }
+        }
+    }
+
+    /// Whether `command` is one of the allowed commands
+    pub fn permits_command(&self, command: &str) -> bool {
+        match self {
+            PermissionScope::None => false,
+            PermissionScope::All => true,
+            PermissionScope::Only(allowed) => allowed.iter().any(|a| a == command),
+        }
+    }
+}
+
+/// Split `host:port` into its parts, leaving IPv6 literals like `[::1]` intact
+fn split_port(host: &str) -> (&str, Option<&str>) {
+    match host.rsplit_once(':') {
+        Some((name, port))
+            if !port.is_empty()
+                && port.chars().all(|c| c.is_ascii_digit())
+                && (!name.contains(':') || name.ends_with(']')) =>
+        {
+            (name, Some(port))
+        }
+        _ => (host, None),
+    }
+}
+
+/// Absolute form of a path with `.`/`..` and symlinks resolved as far as it exists
+///
+/// Paths that do not exist yet, such as files about to be written, still
+/// resolve through their closest existing ancestor.
+fn resolve_path(path: &Path) -> PathBuf {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()
+            .map(|cwd| cwd.join(path))
+            .unwrap_or_else(|_| path.to_path_buf())
+    };
+
+    // `..` goes up from where a symlink leads, as the OS does, so symlinks
+    // are resolved before each step rather than after all of them
+    let mut resolved = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                resolved.pop();
+            }
+            other => {
+                resolved.push(other);
+                if let Ok(canonical) = resolved.canonicalize() {
+                    resolved = canonical;
+                }
+            }
+        }
+    }
+    resolved
+}
+
 /// Result of a permission check
 #[derive(Debug, Clone, PartialEq)]
 pub enum PermissionState {
@@ -30,30 +243,49 @@ pub enum PermissionState {
 /// Permission response from user
 #[derive(Debug, Clone, PartialEq)]
 pub enum PermissionResponse {
-    /// Allow once
+    /// Allow for the rest of this run
     AllowOnce,
-    /// Allow this and similar requests (e.g., all files in directory)
+    /// Allow now and in later runs, saved to the permission cache
     AllowAll,
-    /// Deny once
+    /// Deny for the rest of this run
     DenyOnce,
-    /// Deny this and similar requests
+    /// Deny now and in later runs, saved to the permission cache
     DenyAll,
 }
 
+impl PermissionResponse {
+    /// Interpret an answer to a permission prompt, denying anything unrecognised
+    pub fn parse(input: &str) -> Self {
+        match input.trim().to_lowercase().as_str() {
+            "y" | "yes" => PermissionResponse::AllowOnce,
+            "always" | "a" | "all" => PermissionResponse::AllowAll,
+            "never" | "d" | "deny" => PermissionResponse::DenyAll,
+            _ => PermissionResponse::DenyOnce,
+        }
+    }
+}
+
 /// Manages permissions for the loft runtime
+#[derive(Debug, Clone)]
 pub struct PermissionManager {
     /// Global allow-all flag (from --allow-all CLI flag)
     allow_all: bool,
-    /// Allow all read operations (from --allow-read CLI flag)
-    allow_read: bool,
-    /// Allow all write operations (from --allow-write CLI flag)
-    allow_write: bool,
-    /// Allow all network operations (from --allow-net CLI flag)
-    allow_net: bool,
-    /// Allow all command execution (from --allow-run CLI flag)
-    allow_run: bool,
+    /// Paths readable without asking (from --allow-read CLI flag)
+    allow_read: PermissionScope,
+    /// Paths writable without asking (from --allow-write CLI flag)
+    allow_write: PermissionScope,
+    /// Hosts reachable without asking (from --allow-net CLI flag)
+    allow_net: PermissionScope,
+    /// Commands runnable without asking (from --allow-run CLI flag)
+    allow_run: PermissionScope,
     /// Cache of granted permissions
     granted: HashMap<PermissionType, bool>,
+    /// Where the cached decisions came from
+    origins: HashMap<PermissionType, DecisionOrigin>,
+    /// The script being run, recorded with the decisions made for it
+    script: Option<String>,
+    /// Decisions that only last for this run and are never written to the cache
+    session: HashMap<PermissionType, bool>,
     /// Path to the permission cache file
     cache_path: Option<PathBuf>,
     /// Whether to prompt for permissions (false in non-interactive mode)
@@ -65,25 +297,40 @@ impl PermissionManager {
     pub fn new() -> Self {
         Self {
             allow_all: false,
-            allow_read: false,
-            allow_write: false,
-            allow_net: false,
-            allow_run: false,
+            allow_read: PermissionScope::None,
+            allow_write: PermissionScope::None,
+            allow_net: PermissionScope::None,
+            allow_run: PermissionScope::None,
             granted: HashMap::new(),
+            origins: HashMap::new(),
+            script: None,
+            session: HashMap::new(),
             cache_path: Self::get_cache_path(),
             interactive: Self::is_interactive(),
         }
     }
 
+    /// Create a permission manager that denies every request without prompting
+    pub fn deny_all() -> Self {
+        Self {
+            cache_path: None,
+            interactive: false,
+            ..Self::new()
+        }
+    }
+
     /// Create a permission manager with all permissions allowed
     pub fn allow_all() -> Self {
         Self {
             allow_all: true,
-            allow_read: true,
-            allow_write: true,
-            allow_net: true,
-            allow_run: true,
+            allow_read: PermissionScope::All,
+            allow_write: PermissionScope::All,
+            allow_net: PermissionScope::All,
+            allow_run: PermissionScope::All,
             granted: HashMap::new(),
+            origins: HashMap::new(),
+            script: None,
+            session: HashMap::new(),
             cache_path: None,
             interactive: false,
         }
@@ -96,6 +343,23 @@ impl PermissionManager {
         allow_write: bool,
         allow_net: bool,
         allow_run: bool,
+    ) -> Self {
+        Self::with_scopes(
+            allow_all,
+            PermissionScope::from_bool(allow_read),
+            PermissionScope::from_bool(allow_write),
+            PermissionScope::from_bool(allow_net),
+            PermissionScope::from_bool(allow_run),
+        )
+    }
+
+    /// Create a permission manager where each flag may be limited to certain paths, hosts or commands
+    pub fn with_scopes(
+        allow_all: bool,
+        allow_read: PermissionScope,
+        allow_write: PermissionScope,
+        allow_net: PermissionScope,
+        allow_run: PermissionScope,
     ) -> Self {
         if allow_all {
             Self::allow_all()
@@ -107,17 +371,22 @@ impl PermissionManager {
                 allow_net,
                 allow_run,
                 granted: HashMap::new(),
+                origins: HashMap::new(),
+                script: None,
+                session: HashMap::new(),
                 cache_path: Self::get_cache_path(),
                 interactive: Self::is_interactive(),
             }
         }
     }
 
-    /// Check if running in interactive mode (has a TTY)
+    /// Check if running in interactive mode (has a TTY to prompt on)
+    ///
+    /// Prompts go to stderr, so redirecting a program's output still lets it ask.
     fn is_interactive() -> bool {
         #[cfg(not(target_arch = "wasm32"))]
         {
-            atty::is(atty::Stream::Stdin) && atty::is(atty::Stream::Stdout)
+            atty::is(atty::Stream::Stdin) && atty::is(atty::Stream::Stderr)
         }
         #[cfg(target_arch = "wasm32")]
         {
@@ -172,9 +441,27 @@ impl PermissionManager {
         if let Some(cache_path) = &self.cache_path {
             if cache_path.exists() {
                 let contents = std::fs::read_to_string(cache_path)?;
-                match serde_json::from_str::<HashMap<PermissionType, bool>>(&contents) {
+                match serde_json::from_str::<Vec<CacheEntry>>(&contents) {
                     Ok(cached) => {
-                        self.granted.extend(cached);
+                        for entry in cached {
+                            let entry = match entry {
+                                CacheEntry::Pair(permission, allowed) => CachedPermission {
+                                    permission,
+                                    allowed,
+                                    script: None,
+                                    decided_at: None,
+                                },
+                                CacheEntry::Full(entry) => entry,
+                            };
+                            self.granted.insert(entry.permission.clone(), entry.allowed);
+                            self.origins.insert(
+                                entry.permission,
+                                DecisionOrigin {
+                                    script: entry.script,
+                                    decided_at: entry.decided_at,
+                                },
+                            );
+                        }
                     }
                     Err(e) => {
                         eprintln!("Warning: Failed to parse permission cache: {}", e);
@@ -193,12 +480,60 @@ impl PermissionManager {
             if let Some(parent) = cache_path.parent() {
                 std::fs::create_dir_all(parent)?;
             }
-            let contents = serde_json::to_string_pretty(&self.granted)?;
+            let contents = serde_json::to_string_pretty(&self.cached())?;
             std::fs::write(cache_path, contents)?;
         }
         Ok(())
     }
 
+    /// Record the script being run with the decisions saved while it runs
+    pub fn set_script(&mut self, script: impl Into<String>) {
+        self.script = Some(script.into());
+    }
+
+    /// Every decision in the permission cache, ordered by script and permission
+    pub fn cached(&self) -> Vec<CachedPermission> {
+        let mut entries: Vec<CachedPermission> = self
+            .granted
+            .iter()
+            .map(|(permission, &allowed)| {
+                let origin = self.origins.get(permission).cloned().unwrap_or_default();
+                CachedPermission {
+                    permission: permission.clone(),
+                    allowed,
+                    script: origin.script,
+                    decided_at: origin.decided_at,
+                }
+            })
+            .collect();
+        entries.sort_by(|a, b| {
+            (&a.script, a.permission.kind(), a.permission.target()).cmp(&(
+                &b.script,
+                b.permission.kind(),
+                b.permission.target(),
+            ))
+        });
+        entries
+    }
+
+    /// Forget a cached decision so the next request asks again. Returns
+    /// whether there was one.
+    pub fn revoke(&mut self, perm: &PermissionType) -> io::Result<bool> {
+        self.origins.remove(perm);
+        if self.granted.remove(perm).is_none() {
+            return Ok(false);
+        }
+        self.save_cache()?;
+        Ok(true)
+    }
+
+    /// Forget every cached decision
+    pub fn clear_cache(&mut self) -> io::Result<()> {
+        self.granted.clear();
+        self.origins.clear();
+        self.save_cache()
+    }
+
     /// Check if a permission is granted
     pub fn check(&mut self, perm: &PermissionType) -> PermissionState {
         // If allow_all is set, grant everything
@@ -208,15 +543,23 @@ impl PermissionManager {
 
         // Check type-specific allow flags
         match perm {
-            PermissionType::Read(_) if self.allow_read => return PermissionState::Granted,
-            PermissionType::Write(_) if self.allow_write => return PermissionState::Granted,
-            PermissionType::Net(_) if self.allow_net => return PermissionState::Granted,
-            PermissionType::Run(_) if self.allow_run => return PermissionState::Granted,
+            PermissionType::Read(path) if self.allow_read.permits_path(path) => {
+                return PermissionState::Granted
+            }
+            PermissionType::Write(path) if self.allow_write.permits_path(path) => {
+                return PermissionState::Granted
+            }
+            PermissionType::Net(host) if self.allow_net.permits_host(host) => {
+                return PermissionState::Granted
+            }
+            PermissionType::Run(cmd) if self.allow_run.permits_command(cmd) => {
+                return PermissionState::Granted
+            }
             _ => {}
         }
 
         // Check if we've already made a decision for this permission
-        if let Some(&granted) = self.granted.get(perm) {
+        if let Some(&granted) = self.session.get(perm).or_else(|| self.granted.get(perm)) {
             return if granted {
                 PermissionState::Granted
             } else {
@@ -236,103 +579,96 @@ impl PermissionManager {
     ) -> Result<bool, String> {
         match self.check(perm) {
             PermissionState::Granted => Ok(true),
-            PermissionState::Denied => Err(format!("Permission denied: {:?}", perm)),
+            PermissionState::Denied => Err(Self::denied_message(perm)),
             PermissionState::Prompt => {
                 if !self.interactive {
                     // Non-interactive mode: deny by default
-                    self.granted.insert(perm.clone(), false);
-                    return Err(format!("Permission denied (non-interactive): {:?}", perm));
+                    self.session.insert(perm.clone(), false);
+                    return Err(Self::denied_message(perm));
                 }
 
-                // Prompt user
-                let response = self.prompt_user(perm, context)?;
-
-                match response {
-                    PermissionResponse::AllowOnce => {
-                        // Don't cache, just allow this once
-                        Ok(true)
-                    }
-                    PermissionResponse::AllowAll => {
-                        // Cache and allow
-                        self.granted.insert(perm.clone(), true);
-                        let _ = self.save_cache();
-                        Ok(true)
-                    }
-                    PermissionResponse::DenyOnce => {
-                        // Don't cache, just deny this once
-                        Err(format!("Permission denied by user: {:?}", perm))
-                    }
-                    PermissionResponse::DenyAll => {
-                        // Cache and deny
-                        self.granted.insert(perm.clone(), false);
-                        let _ = self.save_cache();
-                        Err(format!("Permission denied by user: {:?}", perm))
-                    }
-                }
+                let response = {
+                    let _guard = PROMPT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+                    self.prompt_user(perm, context)?
+                };
+                self.apply_response(perm, response)
             }
         }
     }
 
-    /// Prompt the user for permission
-    fn prompt_user(
-        &self,
+    /// Record the user's answer for a permission and return the outcome
+    fn apply_response(
+        &mut self,
         perm: &PermissionType,
-        context: Option<&str>,
-    ) -> Result<PermissionResponse, String> {
-        use owo_colors::OwoColorize;
-
-        println!();
-        println!("{}", "!! Permission Request".bright_yellow().bold());
-        println!("{}", "─────────────────────".bright_yellow());
-
-        // Show what permission is being requested
-        match perm {
-            PermissionType::Read(path) => {
-                println!("{}: Read access", "Type".bright_cyan());
-                println!("{}: {}", "Path".bright_cyan(), path.bright_white());
-            }
-            PermissionType::Write(path) => {
-                println!("{}: Write access", "Type".bright_cyan());
-                println!("{}: {}", "Path".bright_cyan(), path.bright_white());
-            }
-            PermissionType::Net(host) => {
-                println!("{}: Network access", "Type".bright_cyan());
-                println!("{}: {}", "Host".bright_cyan(), host.bright_white());
-            }
-            PermissionType::Run(cmd) => {
-                println!("{}: Command execution", "Type".bright_cyan());
-                println!("{}: {}", "Command".bright_cyan(), cmd.bright_white());
-            }
+        response: PermissionResponse,
+    ) -> Result<bool, String> {
+        let (allowed, persist) = match response {
+            PermissionResponse::AllowOnce => (true, false),
+            PermissionResponse::AllowAll => (true, true),
+            PermissionResponse::DenyOnce => (false, false),
+            PermissionResponse::DenyAll => (false, true),
+        };
+
+        if persist {
+            self.granted.insert(perm.clone(), allowed);
+            self.origins.insert(
+                perm.clone(),
+                DecisionOrigin {
+                    script: self.script.clone(),
+                    decided_at: Some(chrono::Utc::now().to_rfc3339()),
+                },
+            );
+            let _ = self.save_cache();
+        } else {
+            self.session.insert(perm.clone(), allowed);
         }
 
-        if let Some(ctx) = context {
-            println!("{}: {}", "Context".bright_cyan(), ctx.dimmed());
+        if allowed {
+            Ok(true)
+        } else {
+            Err(format!("Permission denied by user: {}", perm))
         }
+    }
 
-        println!();
-        println!("Allow this operation?");
-        println!("  {} - Allow once", "y".bright_green());
-        println!("  {} - Allow all (cached)", "a".bright_green());
-        println!("  {} - Deny once", "n".bright_red());
-        println!("  {} - Deny all (cached)", "d".bright_red());
-        print!("\n{} ", "Choice [y/a/n/d]:".bright_cyan());
-        io::stdout().flush().expect("Failed to flush stdout");
+    fn denied_message(perm: &PermissionType) -> String {
+        format!(
+            "Permission denied: {} (run with {} to allow it)",
+            perm,
+            perm.flag()
+        )
+    }
+
+    /// Ask the user on stderr whether to allow an operation
+    fn prompt_user(
+        &self,
+        perm: &PermissionType,
+        context: Option<&str>,
+    ) -> Result<PermissionResponse, String> {
+        use crate::output::Colorize;
+
+        let mut stderr = io::stderr();
+        let _ = writeln!(
+            stderr,
+            "{} {} requests {} {}",
+            "!!".bright_yellow().bold(),
+            context.unwrap_or("This program").bright_cyan(),
+            perm.bright_white(),
+            format!("(run with {} to skip this prompt)", perm.flag()).dimmed()
+        );
+        let _ = write!(
+            stderr,
+            "{} {} ",
+            format!("Allow {}?", perm).bright_yellow(),
+            "[y/N/always]".bright_cyan()
+        );
+        let _ = stderr.flush();
 
         let mut input = String::new();
         io::stdin()
             .read_line(&mut input)
             .map_err(|e| e.to_string())?;
 
-        match input.trim().to_lowercase().as_str() {
-            "y" | "yes" => Ok(PermissionResponse::AllowOnce),
-            "a" | "all" => Ok(PermissionResponse::AllowAll),
-            "n" | "no" => Ok(PermissionResponse::DenyOnce),
-            "d" | "deny" => Ok(PermissionResponse::DenyAll),
-            _ => {
-                println!("{}", "Invalid choice, denying...".bright_red());
-                Ok(PermissionResponse::DenyOnce)
-            }
-        }
+        Ok(PermissionResponse::parse(&input))
     }
 
     /// Check if a path is under a granted parent path (for batch permissions)
@@ -436,6 +772,84 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_scoped_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = dir.path().join("data");
+        std::fs::create_dir(&data).unwrap();
+        let data = data.to_str().unwrap();
+
+        let mut pm = PermissionManager::with_scopes(
+            false,
+            PermissionScope::Only(vec![data.to_string()]),
+            PermissionScope::None,
+            PermissionScope::None,
+            PermissionScope::None,
+        );
+
+        let inside = format!("{}/nested/new.txt", data);
+        let escaping = format!("{}/../secret.txt", data);
+        assert_eq!(
+            pm.check(&PermissionType::Read(inside.clone())),
+            PermissionState::Granted
+        );
+        assert_eq!(
+            pm.check(&PermissionType::Read(escaping)),
+            PermissionState::Prompt
+        );
+        assert_eq!(
+            pm.check(&PermissionType::Write(inside)),
+            PermissionState::Prompt
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_parent_of_symlink_is_where_it_leads() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = dir.path().join("data");
+        let outside = dir.path().join("outside/deeper");
+        std::fs::create_dir(&data).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::os::unix::fs::symlink(&outside, data.join("link")).unwrap();
+        let data = data.to_str().unwrap();
+
+        let mut pm = PermissionManager::with_scopes(
+            false,
+            PermissionScope::Only(vec![data.to_string()]),
+            PermissionScope::None,
+            PermissionScope::None,
+            PermissionScope::None,
+        );
+
+        // Opens `outside/x`, not `data/x`
+        assert_eq!(
+            pm.check(&PermissionType::Read(format!("{}/link/../x", data))),
+            PermissionState::Prompt
+        );
+        assert_eq!(
+            pm.check(&PermissionType::Read(format!("{}/link/../../data/x", data))),
+            PermissionState::Granted
+        );
+    }
+
+    #[test]
+    fn test_scoped_hosts() {
+        let scope = PermissionScope::from_flag(Some(vec![
+            "api.example.com:443".to_string(),
+            "localhost".to_string(),
+        ]));
+
+        assert!(scope.permits_host("api.example.com:443"));
+        assert!(!scope.permits_host("api.example.com:80"));
+        assert!(!scope.permits_host("evil.com:443"));
+        assert!(scope.permits_host("localhost:8080"));
+        assert_eq!(
+            PermissionScope::from_flag(Some(vec![])),
+            PermissionScope::All
+        );
+    }
+
     #[test]
     fn test_default_requires_prompt() {
         let mut pm = PermissionManager::new();
@@ -465,6 +879,92 @@ mod tests {
         assert_eq!(pm.check(&perm), PermissionState::Denied);
     }
 
+    #[test]
+    fn test_parse_prompt_response() {
+        assert_eq!(
+            PermissionResponse::parse("y\n"),
+            PermissionResponse::AllowOnce
+        );
+        assert_eq!(
+            PermissionResponse::parse("Always"),
+            PermissionResponse::AllowAll
+        );
+        assert_eq!(PermissionResponse::parse(""), PermissionResponse::DenyOnce);
+        assert_eq!(
+            PermissionResponse::parse("maybe"),
+            PermissionResponse::DenyOnce
+        );
+    }
+
+    #[test]
+    fn test_only_always_is_saved_to_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = dir.path().join("permissions.json");
+        let mut pm = PermissionManager::new();
+        pm.cache_path = Some(cache_path.clone());
+
+        let once = PermissionType::Read("./once".to_string());
+        let always = PermissionType::Read("./data".to_string());
+        assert_eq!(
+            pm.apply_response(&once, PermissionResponse::AllowOnce),
+            Ok(true)
+        );
+        assert_eq!(
+            pm.apply_response(&always, PermissionResponse::AllowAll),
+            Ok(true)
+        );
+
+        // Both are remembered for the rest of the run
+        assert_eq!(pm.check(&once), PermissionState::Granted);
+        assert_eq!(pm.check(&always), PermissionState::Granted);
+
+        // Only "always" survives into the next run
+        let mut next = PermissionManager::new();
+        next.cache_path = Some(cache_path);
+        next.load_cache().unwrap();
+        assert_eq!(next.check(&once), PermissionState::Prompt);
+        assert_eq!(next.check(&always), PermissionState::Granted);
+    }
+
+    #[test]
+    fn test_cache_records_scripts_and_revokes() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = dir.path().join("permissions.json");
+        // Caches written before scripts were recorded hold bare pairs
+        std::fs::write(&cache_path, r#"[[{"Net": "example.com"}, true]]"#).unwrap();
+
+        let mut pm = PermissionManager::new();
+        pm.cache_path = Some(cache_path.clone());
+        pm.load_cache().unwrap();
+        pm.set_script("/home/me/tool.lf");
+        let data = PermissionType::Read("./data".to_string());
+        pm.apply_response(&data, PermissionResponse::AllowAll)
+            .unwrap();
+
+        let mut next = PermissionManager::new();
+        next.cache_path = Some(cache_path.clone());
+        next.load_cache().unwrap();
+        let cached = next.cached();
+        assert_eq!(cached.len(), 2);
+        assert_eq!(
+            cached[0].permission,
+            PermissionType::Net("example.com".into())
+        );
+        assert_eq!(cached[0].script, None);
+        assert_eq!(cached[1].permission, data);
+        assert_eq!(cached[1].script.as_deref(), Some("/home/me/tool.lf"));
+        assert!(cached[1].decided_at.is_some());
+
+        assert!(next.revoke(&data).unwrap());
+        assert!(!next.revoke(&data).unwrap());
+        let mut last = PermissionManager::new();
+        last.cache_path = Some(cache_path);
+        last.load_cache().unwrap();
+        assert_eq!(last.check(&data), PermissionState::Prompt);
+        last.clear_cache().unwrap();
+        assert!(last.cached().is_empty());
+    }
+
     #[test]
     fn test_protected_path_detection() {
         // Test that the permissions directory is detected as protected
@@ -0,0 +1,192 @@
+//! Per-function timing for `loft --profile`.
+//!
+//! The interpreter reports every call it dispatches with [`Profiler::enter`]
+//! and [`Profiler::exit`]. Each function gets a call count, its total time
+//! (including the functions it calls) and its self time (excluding them).
+//! Self time is also recorded per call stack so it can be written in the
+//! collapsed format read by flamegraph tools, e.g. `main;parse;next 120`.
+//!
+//! Calls made by `async` tasks run on other interpreters and are not profiled.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Timing collected for one function
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FunctionStats {
+    pub calls: u64,
+    /// Time spent in the function and everything it called
+    pub total: Duration,
+    /// Time spent in the function itself
+    pub self_time: Duration,
+}
+
+/// A call that has not returned yet
+struct Frame {
+    name: String,
+    started: Instant,
+    /// Time spent in calls made from this frame so far
+    child_time: Duration,
+}
+
+#[derive(Default)]
+pub struct Profiler {
+    frames: Vec<Frame>,
+    functions: HashMap<String, FunctionStats>,
+    /// Self time per call stack, keyed by function names joined with `;`
+    stacks: HashMap<String, Duration>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start timing a call
+    pub fn enter(&mut self, name: impl Into<String>) {
+        self.frames.push(Frame {
+            name: name.into(),
+            started: Instant::now(),
+            child_time: Duration::ZERO,
+        });
+    }
+
+    /// Stop timing the innermost call
+    pub fn exit(&mut self) {
+        let stack = self.stack_key();
+        let Some(frame) = self.frames.pop() else {
+            return;
+        };
+        let elapsed = frame.started.elapsed();
+        let self_time = elapsed.saturating_sub(frame.child_time);
+
+        if let Some(parent) = self.frames.last_mut() {
+            parent.child_time += elapsed;
+        }
+        // A recursive call's time is already part of the outermost call
+        let recursive = self.frames.iter().any(|f| f.name == frame.name);
+
+        let stats = self.functions.entry(frame.name).or_default();
+        stats.calls += 1;
+        stats.self_time += self_time;
+        if !recursive {
+            stats.total += elapsed;
+        }
+        *self.stacks.entry(stack).or_default() += self_time;
+    }
+
+    fn stack_key(&self) -> String {
+        self.frames
+            .iter()
+            .map(|f| f.name.as_str())
+            .collect::<Vec<_>>()
+            .join(";")
+    }
+
+    /// Timing for every function called so far
+    pub fn functions(&self) -> &HashMap<String, FunctionStats> {
+        &self.functions
+    }
+
+    /// Functions sorted by self time, slowest first
+    pub fn sorted(&self) -> Vec<(&str, &FunctionStats)> {
+        let mut functions: Vec<_> = self
+            .functions
+            .iter()
+            .map(|(name, stats)| (name.as_str(), stats))
+            .collect();
+        functions.sort_by(|a, b| b.1.self_time.cmp(&a.1.self_time).then(a.0.cmp(b.0)));
+        functions
+    }
+
+    /// A table of calls, total and self time per function, slowest first
+    pub fn report(&self) -> String {
+        let functions = self.sorted();
+        let width = functions
+            .iter()
+            .map(|(name, _)| name.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max("Function".len());
+
+        let mut out = format!(
+            "{:<width$}  {:>8}  {:>12}  {:>12}\n",
+            "Function",
+            "Calls",
+            "Total",
+            "Self",
+            width = width
+        );
+        for (name, stats) in functions {
+            out.push_str(&format!(
+                "{:<width$}  {:>8}  {:>12}  {:>12}\n",
+                name,
+                stats.calls,
+                format_duration(stats.total),
+                format_duration(stats.self_time),
+                width = width
+            ));
+        }
+        out
+    }
+
+    /// Self time per call stack in microseconds, one `a;b;c 123` line per stack
+    pub fn collapsed_stacks(&self) -> String {
+        let mut stacks: Vec<_> = self.stacks.iter().collect();
+        stacks.sort();
+        stacks
+            .into_iter()
+            .map(|(stack, time)| format!("{} {}\n", stack, time.as_micros()))
+            .collect()
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let micros = duration.as_secs_f64() * 1_000_000.0;
+    if micros < 1_000.0 {
+        format!("{:.0} µs", micros)
+    } else if micros < 1_000_000.0 {
+        format!("{:.2} ms", micros / 1_000.0)
+    } else {
+        format!("{:.2} s", micros / 1_000_000.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_and_recursive_calls() {
+        let mut profiler = Profiler::new();
+        profiler.enter("main");
+        profiler.enter("fib");
+        profiler.enter("fib");
+        profiler.exit();
+        profiler.exit();
+        profiler.enter("term.println");
+        profiler.exit();
+        profiler.exit();
+
+        let functions = profiler.functions();
+        assert_eq!(functions["main"].calls, 1);
+        assert_eq!(functions["fib"].calls, 2);
+        assert_eq!(functions["term.println"].calls, 1);
+        assert!(functions["main"].total >= functions["fib"].total);
+        assert!(functions["fib"].total >= functions["fib"].self_time);
+
+        let stacks: Vec<_> = profiler
+            .collapsed_stacks()
+            .lines()
+            .map(|line| line.rsplit_once(' ').unwrap().0.to_string())
+            .collect();
+        assert_eq!(
+            stacks,
+            vec!["main", "main;fib", "main;fib;fib", "main;term.println"]
+        );
+
+        // An exit without a matching enter is ignored
+        profiler.exit();
+        assert!(profiler.report().starts_with("Function"));
+    }
+}
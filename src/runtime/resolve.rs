@@ -0,0 +1,414 @@
+//! Resolving the local variables of functions to slots, so that reading one
+//! indexes into a scope instead of searching every scope by name.
+//!
+//! A function runs in a scope holding its parameters, pushed on top of the
+//! caller's scopes, and every block, loop iteration, match arm and catch
+//! block pushes another one. [`resolve_function`] follows the same scopes
+//! through the body and turns each variable it can place into an
+//! [`ExprKind::Local`], counting scopes up from the innermost one. Names it
+//! can't place, like globals or variables of a closure, stay
+//! [`ExprKind::Ident`] and are looked up by name.
+//!
+//! The interpreter checks a slot's name before using it, so a variable whose
+//! definition didn't run, e.g. on an `if` branch that wasn't taken, falls
+//! back to the lookup by name.
+
+use crate::parser::{Expr, ExprKind, Stmt, StmtKind, TemplatePart};
+use std::collections::HashSet;
+
+#[derive(Debug, Default)]
+struct Scope {
+    /// Variables in the order they are defined, which is their slot
+    slots: Vec<String>,
+    /// Variables bound in an order that isn't known, like those of a match arm
+    unordered: HashSet<String>,
+    /// Whether names the resolver can't see may be defined here, e.g. by an import
+    open: bool,
+}
+
+#[derive(Debug, Default)]
+struct Resolver {
+    scopes: Vec<Scope>,
+}
+
+/// Resolve the variables of a function body, run in a scope holding `params`
+pub fn resolve_function<'a>(params: impl IntoIterator<Item = &'a str>, body: &mut Stmt) {
+    let mut resolver = Resolver::default();
+    resolver.scopes.push(Scope::default());
+    for param in params {
+        resolver.define(param);
+    }
+    resolver.stmt(body);
+}
+
+/// The names a match pattern may bind: every identifier in it
+fn pattern_names(pattern: &Expr, names: &mut HashSet<String>) {
+    match &pattern.kind {
+        ExprKind::Ident(name) => {
+            names.insert(name.clone());
+        }
+        ExprKind::Call { func, args } => {
+            pattern_names(func, names);
+            for arg in args {
+                pattern_names(arg, names);
+            }
+        }
+        ExprKind::FieldAccess { object, .. } => pattern_names(object, names),
+        ExprKind::ArrayLiteral(items) => {
+            for item in items {
+                pattern_names(item, names);
+            }
+        }
+        ExprKind::StructLiteral { fields, .. } => {
+            for (field, value) in fields {
+                names.insert(field.clone());
+                pattern_names(value, names);
+            }
+        }
+        _ => {}
+    }
+}
+
+impl Resolver {
+    fn scope(&mut self) -> &mut Scope {
+        self.scopes
+            .last_mut()
+            .expect("the function scope is never popped")
+    }
+
+    fn define(&mut self, name: &str) {
+        let scope = self.scope();
+        if !scope.slots.iter().any(|slot| slot == name) {
+            scope.slots.push(name.to_string());
+        }
+    }
+
+    /// How many scopes up `name` is defined, and at which slot
+    fn lookup(&self, name: &str) -> Option<(usize, usize)> {
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            if scope.open || scope.unordered.contains(name) {
+                return None;
+            }
+            if let Some(slot) = scope.slots.iter().position(|slot| slot == name) {
+                return Some((depth, slot));
+            }
+        }
+        None
+    }
+
+    /// Resolve `f` in a new scope that starts with `slots` and `unordered`
+    fn scoped(&mut self, slots: &[&str], unordered: HashSet<String>, f: impl FnOnce(&mut Self)) {
+        self.scopes.push(Scope {
+            unordered,
+            ..Scope::default()
+        });
+        for name in slots {
+            self.define(name);
+        }
+        f(self);
+        self.scopes.pop();
+    }
+
+    fn stmt(&mut self, stmt: &mut Stmt) {
+        match &mut stmt.kind {
+            StmtKind::VarDecl { name, value, .. } => {
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+                self.define(name);
+            }
+            StmtKind::ConstDecl { name, value, .. } => {
+                self.expr(value);
+                self.define(name);
+            }
+            // Assigning a variable that doesn't exist yet defines it
+            StmtKind::Assign { name, value } => {
+                self.expr(value);
+                if self.lookup(name).is_none() {
+                    self.define(name);
+                }
+            }
+            StmtKind::FunctionDecl { name, .. } => self.define(name),
+            StmtKind::AttrStmt { stmt, .. } => self.stmt(stmt),
+            StmtKind::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.expr(condition);
+                self.stmt(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.stmt(else_branch);
+                }
+            }
+            StmtKind::While { condition, body } => {
+                self.expr(condition);
+                self.stmt(body);
+            }
+            StmtKind::For {
+                var,
+                iterable,
+                body,
+            } => {
+                self.expr(iterable);
+                self.scoped(&[var.as_str()], HashSet::new(), |r| r.stmt(body));
+            }
+            StmtKind::Match { expr, arms } => {
+                self.expr(expr);
+                for (pattern, body) in arms {
+                    let mut names = HashSet::new();
+                    pattern_names(pattern, &mut names);
+                    self.scoped(&[], names, |r| r.stmt(body));
+                }
+            }
+            StmtKind::Return(Some(expr)) | StmtKind::Throw(expr) | StmtKind::Expr(expr) => {
+                self.expr(expr)
+            }
+            StmtKind::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                self.stmt(body);
+                if let Some((var, body)) = catch {
+                    let slots: Vec<&str> = var.iter().map(String::as_str).collect();
+                    self.scoped(&slots, HashSet::new(), |r| r.stmt(body));
+                }
+                if let Some(finally) = finally {
+                    self.stmt(finally);
+                }
+            }
+            StmtKind::Block(stmts) => self.scoped(&[], HashSet::new(), |r| {
+                for stmt in stmts {
+                    r.stmt(stmt);
+                }
+            }),
+            // These bind names the resolver doesn't track
+            StmtKind::ImportDecl { .. }
+            | StmtKind::StructDecl { .. }
+            | StmtKind::EnumDecl { .. }
+            | StmtKind::TraitDecl { .. }
+            | StmtKind::ImplBlock { .. } => self.scope().open = true,
+            // Deferred code runs after the function's scopes are gone
+            StmtKind::Defer(_) | StmtKind::Return(None) | StmtKind::Break | StmtKind::Continue => {}
+        }
+    }
+
+    fn expr(&mut self, expr: &mut Expr) {
+        match &mut expr.kind {
+            ExprKind::Ident(name) => {
+                if let Some((depth, slot)) = self.lookup(name) {
+                    expr.kind = ExprKind::Local {
+                        name: std::mem::take(name),
+                        depth,
+                        slot,
+                    };
+                }
+            }
+            ExprKind::BinOp { left, right, .. } => {
+                self.expr(left);
+                self.expr(right);
+            }
+            ExprKind::UnaryOp { expr, .. }
+            | ExprKind::Await(expr)
+            | ExprKind::Try(expr)
+            | ExprKind::Spread(expr) => self.expr(expr),
+            ExprKind::Call { func, args } => {
+                self.expr(func);
+                for arg in args {
+                    self.expr(arg);
+                }
+            }
+            ExprKind::FieldAccess { object, .. } => self.expr(object),
+            ExprKind::ArrayLiteral(items) => {
+                for item in items {
+                    self.expr(item);
+                }
+            }
+            ExprKind::MapLiteral(entries) => {
+                for (key, value) in entries {
+                    self.expr(key);
+                    self.expr(value);
+                }
+            }
+            ExprKind::StructLiteral { fields, .. } => {
+                for (_, value) in fields {
+                    self.expr(value);
+                }
+            }
+            ExprKind::Index { array, index } => {
+                self.expr(array);
+                self.expr(index);
+            }
+            ExprKind::Slice { array, start, end } => {
+                self.expr(array);
+                for bound in [start, end].into_iter().flatten() {
+                    self.expr(bound);
+                }
+            }
+            ExprKind::TemplateLiteral { parts } => {
+                for part in parts {
+                    if let TemplatePart::Expression(expr, _) = part {
+                        self.expr(expr);
+                    }
+                }
+            }
+            ExprKind::Block(stmts) => self.scoped(&[], HashSet::new(), |r| {
+                for stmt in stmts {
+                    r.stmt(stmt);
+                }
+            }),
+            ExprKind::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.expr(condition);
+                self.expr(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.expr(else_branch);
+                }
+            }
+            ExprKind::Match { expr, arms } => {
+                self.expr(expr);
+                for (pattern, body) in arms {
+                    let mut names = HashSet::new();
+                    pattern_names(pattern, &mut names);
+                    self.scoped(&[], names, |r| r.expr(body));
+                }
+            }
+            // Closures and async blocks run in scopes of their own, later
+            ExprKind::Lambda { .. } | ExprKind::Async(_) | ExprKind::Lazy(_) => {}
+            ExprKind::Number(_)
+            | ExprKind::String(_)
+            | ExprKind::Boolean(_)
+            | ExprKind::Local { .. } => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{InputStream, Parser};
+    use crate::runtime::value::Value;
+    use crate::runtime::Interpreter;
+
+    fn run(code: &str) -> Interpreter {
+        let code = code.to_string();
+        let stmts = Parser::new(InputStream::new("resolve.lf", &code))
+            .parse()
+            .unwrap();
+        let mut interpreter = Interpreter::with_source("resolve.lf", &code);
+        interpreter.eval_program(stmts).unwrap();
+        interpreter
+    }
+
+    fn locals(stmt: &Stmt) -> Vec<(String, usize, usize)> {
+        let json = serde_json::to_value(stmt).unwrap();
+        let mut found = Vec::new();
+        fn walk(value: &serde_json::Value, found: &mut Vec<(String, usize, usize)>) {
+            match value {
+                serde_json::Value::Object(map) => {
+                    if let Some(local) = map.get("Local") {
+                        found.push((
+                            local["name"].as_str().unwrap().to_string(),
+                            local["depth"].as_u64().unwrap() as usize,
+                            local["slot"].as_u64().unwrap() as usize,
+                        ));
+                    }
+                    map.values().for_each(|value| walk(value, found));
+                }
+                serde_json::Value::Array(items) => items.iter().for_each(|v| walk(v, found)),
+                _ => {}
+            }
+        }
+        walk(&json, &mut found);
+        found
+    }
+
+    #[test]
+    fn test_resolves_locals_but_not_globals() {
+        let code = "fn sum(limit: num) -> num {\n    let total = 0;\n    let i = 0;\n    while (i < limit) {\n        total = total + i + offset;\n        i = i + 1;\n    }\n    return total;\n}\n".to_string();
+        let mut stmts = Parser::new(InputStream::new("resolve.lf", &code))
+            .parse()
+            .unwrap();
+        let StmtKind::FunctionDecl { body, .. } = &mut stmts[0].kind else {
+            panic!("expected a function");
+        };
+        resolve_function(["limit"], body);
+
+        // The parameters sit one scope below the body's variables
+        let found = locals(body);
+        assert!(found.contains(&("limit".to_string(), 1, 0)));
+        assert!(found.contains(&("i".to_string(), 0, 1)));
+        assert!(found.contains(&("total".to_string(), 0, 0)));
+        // Inside the loop body, one scope further up
+        assert!(found.contains(&("total".to_string(), 1, 0)));
+        assert!(found.contains(&("i".to_string(), 1, 1)));
+        assert!(!found.iter().any(|(name, ..)| name == "offset"));
+    }
+
+    #[test]
+    fn test_resolved_functions_behave_the_same() {
+        let interpreter = run("let offset = 100;\n\
+             fn sum(limit: num) -> num {\n\
+                 let total = 0;\n\
+                 let i = 0;\n\
+                 while (i < limit) {\n\
+                     total = total + i;\n\
+                     i = i + 1;\n\
+                 }\n\
+                 return total + offset;\n\
+             }\n\
+             fn shadow(flag: bool) -> num {\n\
+                 let x = 1;\n\
+                 if (flag) {\n\
+                     let x = 2;\n\
+                     return x;\n\
+                 }\n\
+                 return x;\n\
+             }\n\
+             fn caught() -> str {\n\
+                 let label = \"none\";\n\
+                 try {\n\
+                     let label = \"inner\";\n\
+                     throw label;\n\
+                 } catch (e) {\n\
+                     return label;\n\
+                 }\n\
+             }\n\
+             fn matched(value: num) -> num {\n\
+                 let n = 10;\n\
+                 return match value {\n\
+                     1 => n,\n\
+                     n => n * 2,\n\
+                 };\n\
+             }\n\
+             fn adder(step: num) -> num {\n\
+                 let add = (x) => x + step;\n\
+                 return add(1);\n\
+             }\n\
+             let total = sum(5);\n\
+             let shadowed = [shadow(true), shadow(false)];\n\
+             let message = caught();\n\
+             let matches = [matched(1), matched(3)];\n\
+             let added = adder(2);\n");
+
+        let num = |n: i64| Value::Number(n.into());
+        assert_eq!(interpreter.env.get("total"), Some(&num(110)));
+        assert_eq!(
+            interpreter.env.get("shadowed"),
+            Some(&Value::Array(vec![num(2), num(1)]))
+        );
+        assert_eq!(
+            interpreter.env.get("message"),
+            Some(&Value::String("none".into()))
+        );
+        assert_eq!(
+            interpreter.env.get("matches"),
+            Some(&Value::Array(vec![num(10), num(6)]))
+        );
+        assert_eq!(interpreter.env.get("added"), Some(&num(3)));
+    }
+}
@@ -0,0 +1,407 @@
+//! Task scheduler backing `async` and `lazy` expressions.
+//!
+//! An `async` expression is queued on a shared pool of worker threads as soon
+//! as it is evaluated, while a `lazy` expression only starts once it is awaited
+//! or handed to `promise.all`/`promise.race`. Awaiting a task that no worker
+//! has picked up yet runs it on the awaiting thread, so waiting on tasks from
+//! inside other tasks cannot starve the pool. Timers such as `time.sleep` are
+//! tasks finished by a single timer thread, so waiting on one ties up no worker.
+
+use super::limits;
+#[cfg(not(target_arch = "wasm32"))]
+use super::limits::Budget;
+#[cfg(not(target_arch = "wasm32"))]
+use super::permission_context;
+#[cfg(not(target_arch = "wasm32"))]
+use super::permissions::PermissionManager;
+use super::value::Value;
+use super::{RuntimeError, RuntimeResult};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+type Job = Box<dyn FnOnce() -> RuntimeResult<Value> + Send>;
+
+/// Work that has not started yet, along with the permissions and the budget
+/// of the thread that created it, for the pool thread that runs it. Without
+/// threads a job runs on the thread that created it, which already has both
+struct PendingJob {
+    job: Job,
+    #[cfg(not(target_arch = "wasm32"))]
+    permissions: Option<PermissionManager>,
+    #[cfg(not(target_arch = "wasm32"))]
+    budget: Option<Arc<Budget>>,
+}
+
+enum TaskState {
+    /// Waiting in the pool queue
+    Queued(PendingJob),
+    /// Not scheduled until something waits on it
+    Lazy(PendingJob),
+    Running,
+    Done(RuntimeResult<Value>),
+}
+
+struct TaskInner {
+    state: Mutex<TaskState>,
+    finished: Condvar,
+}
+
+/// Handle to a unit of work running on the scheduler
+#[derive(Clone)]
+pub struct Task(Arc<TaskInner>);
+
+/// Signalled whenever any task finishes, used by [`race`]
+static COMPLETIONS: (Mutex<u64>, Condvar) = (Mutex::new(0), Condvar::new());
+
+impl Task {
+    fn with_state(state: TaskState) -> Self {
+        Task(Arc::new(TaskInner {
+            state: Mutex::new(state),
+            finished: Condvar::new(),
+        }))
+    }
+
+    fn pending(job: Job) -> PendingJob {
+        PendingJob {
+            job,
+            #[cfg(not(target_arch = "wasm32"))]
+            permissions: permission_context::with_permissions(|pm| pm.clone()),
+            #[cfg(not(target_arch = "wasm32"))]
+            budget: limits::current_budget(),
+        }
+    }
+
+    /// Queue a job to run in the background
+    pub fn spawn(job: impl FnOnce() -> RuntimeResult<Value> + Send + 'static) -> Self {
+        let task = Self::with_state(TaskState::Queued(Self::pending(Box::new(job))));
+        pool::submit(task.clone());
+        task
+    }
+
+    /// Create a task that only runs once it is started or awaited
+    pub fn lazy(job: impl FnOnce() -> RuntimeResult<Value> + Send + 'static) -> Self {
+        Self::with_state(TaskState::Lazy(Self::pending(Box::new(job))))
+    }
+
+    /// Create a task that finishes with unit once `delay` has passed
+    pub fn timer(delay: Duration) -> Self {
+        let task = Self::with_state(TaskState::Running);
+        timers::add(delay, task.clone());
+        task
+    }
+
+    /// Schedule a lazy task on the pool; does nothing for tasks already started
+    pub fn start(&self) {
+        let mut state = self.0.state.lock().unwrap();
+        if matches!(*state, TaskState::Lazy(_)) {
+            let TaskState::Lazy(pending) = std::mem::replace(&mut *state, TaskState::Running)
+            else {
+                unreachable!()
+            };
+            *state = TaskState::Queued(pending);
+            drop(state);
+            pool::submit(self.clone());
+        }
+    }
+
+    /// Take the job out of a task that has not started, marking it as running
+    fn take_pending(&self) -> Option<PendingJob> {
+        let mut state = self.0.state.lock().unwrap();
+        match *state {
+            TaskState::Queued(_) | TaskState::Lazy(_) => {
+                match std::mem::replace(&mut *state, TaskState::Running) {
+                    TaskState::Queued(pending) | TaskState::Lazy(pending) => Some(pending),
+                    _ => unreachable!(),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn finish(&self, result: RuntimeResult<Value>) {
+        *self.0.state.lock().unwrap() = TaskState::Done(result);
+        self.0.finished.notify_all();
+
+        let (count, signal) = &COMPLETIONS;
+        *count.lock().unwrap() += 1;
+        signal.notify_all();
+    }
+
+    /// The result of the task, if it has finished
+    pub fn result(&self) -> Option<RuntimeResult<Value>> {
+        match &*self.0.state.lock().unwrap() {
+            TaskState::Done(result) => Some(result.clone()),
+            _ => None,
+        }
+    }
+
+    /// Block until the task has finished and return its result, or until the
+    /// deadline of the program waiting on it
+    pub fn wait(&self) -> RuntimeResult<Value> {
+        // Nobody picked the task up yet, so run it right here
+        if let Some(pending) = self.take_pending() {
+            self.finish((pending.job)());
+        }
+
+        let mut state = self.0.state.lock().unwrap();
+        loop {
+            if let TaskState::Done(result) = &*state {
+                return result.clone();
+            }
+            state = match limits::blocking_timeout(None) {
+                Some(timeout) => {
+                    let (state, waited) = self.0.finished.wait_timeout(state, timeout).unwrap();
+                    if waited.timed_out() && !matches!(*state, TaskState::Done(_)) {
+                        limits::check_deadline().map_err(RuntimeError::new)?;
+                    }
+                    state
+                }
+                None => self.0.finished.wait(state).unwrap(),
+            };
+        }
+    }
+
+    pub fn ptr_eq(&self, other: &Task) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl std::fmt::Debug for Task {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.result() {
+            Some(_) => write!(f, "Task(done)"),
+            None => write!(f, "Task(pending)"),
+        }
+    }
+}
+
+/// Wait for the first of several tasks to finish and return its result
+pub fn race(tasks: &[Task]) -> RuntimeResult<Value> {
+    let mut tasks = tasks.to_vec();
+    for task in &tasks {
+        task.start();
+    }
+
+    let (count, signal) = &COMPLETIONS;
+    loop {
+        limits::check_deadline().map_err(RuntimeError::new)?;
+        let seen = *count.lock().unwrap();
+
+        // A task that resulted in another promise, like `lazy time.sleep(10)`,
+        // is only done once that promise is
+        for task in tasks.iter_mut() {
+            while let Some(Ok(Value::Task(inner))) = task.result() {
+                inner.start();
+                *task = inner;
+            }
+        }
+        if let Some(result) = tasks.iter().find_map(Task::result) {
+            return result;
+        }
+
+        // Work nobody has picked up yet gets its own thread so a busy pool cannot stall the race
+        for task in &tasks {
+            if let Some(pending) = task.take_pending() {
+                pool::run_detached(task.clone(), pending);
+            }
+        }
+
+        let guard = count.lock().unwrap();
+        if *guard == seen {
+            let _ = signal
+                .wait_timeout(guard, std::time::Duration::from_millis(50))
+                .unwrap();
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod pool {
+    use super::{limits, permission_context, PendingJob, Task};
+    use std::sync::mpsc::{self, Sender};
+    use std::sync::{Arc, Mutex, OnceLock};
+
+    static QUEUE: OnceLock<Mutex<Sender<Task>>> = OnceLock::new();
+
+    /// Hand a task to the worker threads, starting them on first use
+    pub(super) fn submit(task: Task) {
+        let queue = QUEUE.get_or_init(|| {
+            let (sender, receiver) = mpsc::channel::<Task>();
+            let receiver = Arc::new(Mutex::new(receiver));
+            let workers = std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(4)
+                .max(4);
+
+            for i in 0..workers {
+                let receiver = Arc::clone(&receiver);
+                std::thread::Builder::new()
+                    .name(format!("loft-worker-{}", i))
+                    .spawn(move || loop {
+                        let Ok(task) = receiver.lock().unwrap().recv() else {
+                            return;
+                        };
+                        if let Some(pending) = task.take_pending() {
+                            run(&task, pending);
+                        }
+                    })
+                    .expect("failed to start loft worker thread");
+            }
+
+            Mutex::new(sender)
+        });
+
+        let _ = queue.lock().unwrap().send(task);
+    }
+
+    /// Run a task on a dedicated thread outside the pool
+    pub(super) fn run_detached(task: Task, pending: PendingJob) {
+        std::thread::spawn(move || run(&task, pending));
+    }
+
+    fn run(task: &Task, pending: PendingJob) {
+        if let Some(permissions) = pending.permissions {
+            permission_context::init_permissions(permissions);
+        }
+        limits::replace_budget(pending.budget);
+        task.finish((pending.job)());
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod pool {
+    use super::{PendingJob, Task};
+
+    /// Without threads, queued tasks run when they are awaited
+    pub(super) fn submit(_task: Task) {}
+
+    /// Without threads, detached tasks run immediately
+    pub(super) fn run_detached(task: Task, pending: PendingJob) {
+        task.finish((pending.job)());
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod timers {
+    use super::{Task, Value};
+    use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+    use std::sync::{Mutex, OnceLock};
+    use std::time::{Duration, Instant};
+
+    static TIMERS: OnceLock<Mutex<Sender<(Instant, Task)>>> = OnceLock::new();
+
+    /// Finish `task` once `delay` has passed, starting the timer thread on first use
+    pub(super) fn add(delay: Duration, task: Task) {
+        let deadline = Instant::now() + delay;
+        let timers = TIMERS.get_or_init(|| {
+            let (sender, receiver) = mpsc::channel::<(Instant, Task)>();
+            std::thread::Builder::new()
+                .name("loft-timers".to_string())
+                .spawn(move || {
+                    let mut waiting: Vec<(Instant, Task)> = Vec::new();
+                    loop {
+                        let now = Instant::now();
+                        let (due, later) = waiting.into_iter().partition(|(at, _)| *at <= now);
+                        waiting = later;
+                        for (_, task) in due {
+                            task.finish(Ok(Value::Unit));
+                        }
+
+                        let next = match waiting.iter().map(|(at, _)| *at).min() {
+                            Some(at) => receiver.recv_timeout(at - now),
+                            None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                        };
+                        match next {
+                            Ok(timer) => waiting.push(timer),
+                            Err(RecvTimeoutError::Timeout) => {}
+                            Err(RecvTimeoutError::Disconnected) => return,
+                        }
+                    }
+                })
+                .expect("failed to start loft timer thread");
+            Mutex::new(sender)
+        });
+
+        let _ = timers.lock().unwrap().send((deadline, task));
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod timers {
+    use super::{Task, Value};
+    use crate::runtime::{limits, RuntimeError};
+    use chrono::{DateTime, TimeDelta, Utc};
+    use std::time::Duration;
+
+    /// Without threads, waiting for a timer blocks right away. The browser has
+    /// neither `Instant` nor `thread::sleep`, so this watches the clock the way
+    /// [`limits`] does and gives up once the program's time runs out.
+    pub(super) fn add(delay: Duration, task: Task) {
+        let deadline = TimeDelta::from_std(delay)
+            .ok()
+            .and_then(|delay| Utc::now().checked_add_signed(delay))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+        while Utc::now() < deadline {
+            if let Err(message) = limits::check_deadline() {
+                task.finish(Err(RuntimeError::new(message)));
+                return;
+            }
+            std::hint::spin_loop();
+        }
+        task.finish(Ok(Value::Unit));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+    use std::time::{Duration, Instant};
+
+    fn sleepy(ms: u64, n: i64) -> impl FnOnce() -> RuntimeResult<Value> + Send {
+        move || {
+            std::thread::sleep(Duration::from_millis(ms));
+            Ok(Value::Number(Decimal::from(n)))
+        }
+    }
+
+    #[test]
+    fn test_spawned_tasks_run_concurrently() {
+        let start = Instant::now();
+        let tasks: Vec<Task> = (0..4).map(|n| Task::spawn(sleepy(200, n))).collect();
+        let results: Vec<Value> = tasks.iter().map(|t| t.wait().unwrap()).collect();
+
+        assert_eq!(results[3], Value::Number(Decimal::from(3)));
+        assert!(start.elapsed() < Duration::from_millis(700));
+    }
+
+    #[test]
+    fn test_timers_finish_after_their_delay() {
+        let start = Instant::now();
+        let slow = Task::timer(Duration::from_millis(150));
+        let fast = Task::timer(Duration::from_millis(50));
+        assert_eq!(race(&[slow.clone(), fast]).unwrap(), Value::Unit);
+        assert!(slow.result().is_none());
+
+        slow.wait().unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(150));
+    }
+
+    #[test]
+    fn test_lazy_task_runs_when_awaited() {
+        let task = Task::lazy(sleepy(0, 7));
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(task.result().is_none());
+        assert_eq!(task.wait().unwrap(), Value::Number(Decimal::from(7)));
+    }
+
+    #[test]
+    fn test_race_returns_first_result() {
+        let slow = Task::spawn(sleepy(500, 1));
+        let fast = Task::lazy(sleepy(10, 2));
+        assert_eq!(
+            race(&[slow, fast]).unwrap(),
+            Value::Number(Decimal::from(2))
+        );
+    }
+}
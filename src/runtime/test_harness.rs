@@ -0,0 +1,556 @@
+//! Test harness used by `loft test`.
+//!
+//! A test file is any `*_test.lf` file. If the file declares functions marked
+//! with `#[test]`, each of them runs as its own test case; otherwise the whole
+//! file is a single test case. Every test case runs in a fresh interpreter so
+//! that state cannot leak between tests.
+//!
+//! `loft test --doc` runs doc tests instead: the ```loft code blocks in the
+//! `///` comments of every source file. Each block runs after the file's
+//! declarations, with every permission denied.
+
+use super::permission_context;
+use super::permissions::PermissionManager;
+use super::{Interpreter, RuntimeError};
+use crate::analysis::docs::extract_name_from_declaration;
+use crate::docgen::markdown::hidden_line;
+use crate::parser::trivia::doc_line;
+use crate::parser::{InputStream, Parser, Stmt, StmtKind};
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::{Path, PathBuf};
+
+/// Suffix that marks a file as a test file
+pub const TEST_FILE_SUFFIX: &str = "_test.lf";
+
+/// The outcome of a single test case
+#[derive(Debug, Clone)]
+pub enum TestOutcome {
+    Passed,
+    Failed(RuntimeError),
+}
+
+/// A single test case result
+#[derive(Debug, Clone)]
+pub struct TestCaseResult {
+    pub name: String,
+    pub outcome: TestOutcome,
+    pub duration: std::time::Duration,
+}
+
+impl TestCaseResult {
+    pub fn passed(&self) -> bool {
+        matches!(self.outcome, TestOutcome::Passed)
+    }
+}
+
+/// All test case results for one test file
+#[derive(Debug, Clone)]
+pub struct TestFileReport {
+    pub path: String,
+    pub cases: Vec<TestCaseResult>,
+}
+
+impl TestFileReport {
+    pub fn passed_count(&self) -> usize {
+        self.cases.iter().filter(|c| c.passed()).count()
+    }
+
+    pub fn failed_count(&self) -> usize {
+        self.cases.len() - self.passed_count()
+    }
+}
+
+/// Find all `*_test.lf` files below `root`, skipping hidden directories,
+/// installed dependencies and build output. A file path is returned as-is.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn discover_test_files(root: &Path) -> Vec<PathBuf> {
+    discover_files(root, TEST_FILE_SUFFIX)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn discover_files(root: &Path, suffix: &str) -> Vec<PathBuf> {
+    fn walk(dir: &Path, suffix: &str, files: &mut Vec<PathBuf>) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            if path.is_dir() {
+                if !name.starts_with('.') && name != "target" && name != "node_modules" {
+                    walk(&path, suffix, files);
+                }
+            } else if name.ends_with(suffix) {
+                files.push(path);
+            }
+        }
+    }
+
+    if root.is_file() {
+        return vec![root.to_path_buf()];
+    }
+
+    let mut files = Vec::new();
+    walk(root, suffix, &mut files);
+    files.sort();
+    files
+}
+
+/// Names of the functions marked with `#[test]` in a parsed program
+pub fn collect_test_functions(stmts: &[Stmt]) -> Vec<String> {
+    functions_marked(stmts, "test")
+}
+
+/// Names of the functions marked with the attribute `name`, such as `#[bench]`
+pub(crate) fn functions_marked(stmts: &[Stmt], name: &str) -> Vec<String> {
+    stmts
+        .iter()
+        .filter_map(|stmt| match &stmt.kind {
+            StmtKind::AttrStmt { attr, stmt } if attr.name == name => match &stmt.kind {
+                StmtKind::FunctionDecl { name, .. } => Some(name.clone()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// Run every test case in a source file
+///
+/// Only test cases whose name contains `filter` are run. A parse error is
+/// reported as a single failed case named after the file.
+pub fn run_test_source(
+    path: &str,
+    source: &str,
+    features: &[String],
+    filter: Option<&str>,
+) -> TestFileReport {
+    let source_string = source.to_string();
+    let stmts = match Parser::new(InputStream::new(path, &source_string)).parse() {
+        Ok(stmts) => stmts,
+        Err(e) => {
+            return TestFileReport {
+                path: path.to_string(),
+                cases: vec![TestCaseResult {
+                    name: path.to_string(),
+                    outcome: TestOutcome::Failed(RuntimeError::with_source(
+                        e.message,
+                        path.to_string(),
+                        source_string.clone(),
+                        e.position.saturating_sub(e.len.unwrap_or_default()),
+                        e.len.unwrap_or_default(),
+                    )),
+                    duration: std::time::Duration::ZERO,
+                }],
+            };
+        }
+    };
+
+    let new_interpreter =
+        || Interpreter::with_source(path, source).with_features(features.to_vec());
+    let test_functions = collect_test_functions(&stmts);
+    let mut cases = Vec::new();
+
+    if test_functions.is_empty() {
+        // The file itself is the test
+        if filter.is_none_or(|f| path.contains(f)) {
+            let start = std::time::Instant::now();
+            let outcome = match new_interpreter().eval_program(stmts) {
+                Ok(_) => TestOutcome::Passed,
+                Err(e) => TestOutcome::Failed(e),
+            };
+            cases.push(TestCaseResult {
+                name: path.to_string(),
+                outcome,
+                duration: start.elapsed(),
+            });
+        }
+    } else {
+        for name in test_functions {
+            if !filter.is_none_or(|f| name.contains(f)) {
+                continue;
+            }
+
+            let start = std::time::Instant::now();
+            let mut interpreter = new_interpreter();
+            let outcome = match run_test_function(&mut interpreter, &stmts, &name) {
+                Ok(()) => TestOutcome::Passed,
+                Err(e) => TestOutcome::Failed(e),
+            };
+            cases.push(TestCaseResult {
+                name,
+                outcome,
+                duration: start.elapsed(),
+            });
+        }
+    }
+
+    TestFileReport {
+        path: path.to_string(),
+        cases,
+    }
+}
+
+/// Evaluate the top-level declarations of a test file, then call one test function
+fn run_test_function(
+    interpreter: &mut Interpreter,
+    stmts: &[Stmt],
+    name: &str,
+) -> Result<(), RuntimeError> {
+    // Top-level statements are evaluated one by one so that `main` is not invoked
+    for stmt in stmts {
+        interpreter.eval_stmt(stmt.clone())?;
+    }
+
+    let function = interpreter
+        .env
+        .get(name)
+        .cloned()
+        .ok_or_else(|| RuntimeError::new(format!("Test function '{}' not found", name)))?;
+    interpreter.call_value(function, vec![])?;
+    Ok(())
+}
+
+/// A ```loft code block from a doc comment
+#[derive(Debug, Clone)]
+pub struct DocTest {
+    /// The documented declaration and the line the block starts on, e.g. `add (line 3)`
+    pub name: String,
+    pub code: String,
+}
+
+/// The ```loft code blocks in the `///` comments of a source file. Blocks
+/// marked ```loft,ignore are left out, and hidden `# ` lines are included.
+pub fn extract_doc_tests(source: &str) -> Vec<DocTest> {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut tests = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        if !lines[i].trim_start().starts_with("///") {
+            i += 1;
+            continue;
+        }
+
+        // The blocks of one doc comment, with the line each starts on
+        let mut blocks: Vec<(usize, Vec<&str>)> = Vec::new();
+        let mut open: Option<(usize, Vec<&str>, bool)> = None;
+        while i < lines.len() && lines[i].trim_start().starts_with("///") {
+            let text = doc_line(lines[i].trim_start().strip_prefix("///").unwrap());
+            let fence = text.trim_start().strip_prefix("```");
+            match (open.take(), fence) {
+                (Some((line, code, runs)), Some(_)) => {
+                    if runs {
+                        blocks.push((line, code));
+                    }
+                }
+                (Some((line, mut code, runs)), None) => {
+                    code.push(hidden_line(text).unwrap_or(text));
+                    open = Some((line, code, runs));
+                }
+                (None, Some(info)) => {
+                    let mut info = info.split(',').map(str::trim);
+                    let runs = info.next() == Some("loft") && !info.any(|attr| attr == "ignore");
+                    open = Some((i + 1, Vec::new(), runs));
+                }
+                (None, None) => {}
+            }
+            i += 1;
+        }
+
+        let documented = lines[i..]
+            .iter()
+            .find(|line| !line.trim().is_empty())
+            .and_then(|line| extract_name_from_declaration(line))
+            .unwrap_or_else(|| "doc".to_string());
+        for (line, code) in blocks {
+            tests.push(DocTest {
+                name: format!("{} (line {})", documented, line),
+                code: code.join("\n"),
+            });
+        }
+    }
+
+    tests
+}
+
+/// Run the doc tests of a source file whose name contains `filter`
+pub fn run_doc_tests_source(
+    path: &str,
+    source: &str,
+    features: &[String],
+    filter: Option<&str>,
+) -> TestFileReport {
+    let tests: Vec<DocTest> = extract_doc_tests(source)
+        .into_iter()
+        .filter(|test| filter.is_none_or(|f| test.name.contains(f)))
+        .collect();
+    let mut cases = Vec::new();
+    if tests.is_empty() {
+        return TestFileReport {
+            path: path.to_string(),
+            cases,
+        };
+    }
+
+    let source_string = source.to_string();
+    let parsed = Parser::new(InputStream::new(path, &source_string)).parse();
+    let declarations: Vec<Stmt> = match parsed {
+        Ok(stmts) => stmts.into_iter().filter(is_declaration).collect(),
+        Err(e) => {
+            return TestFileReport {
+                path: path.to_string(),
+                cases: vec![TestCaseResult {
+                    name: path.to_string(),
+                    outcome: TestOutcome::Failed(RuntimeError::with_source(
+                        e.message,
+                        path.to_string(),
+                        source_string.clone(),
+                        e.position.saturating_sub(e.len.unwrap_or_default()),
+                        e.len.unwrap_or_default(),
+                    )),
+                    duration: std::time::Duration::ZERO,
+                }],
+            };
+        }
+    };
+
+    for test in tests {
+        let start = std::time::Instant::now();
+        // Doc tests run sandboxed: anything needing a permission fails instead of prompting
+        let previous = permission_context::replace_permissions(Some(PermissionManager::deny_all()));
+        let result = run_doc_test(path, source, &declarations, &test, features);
+        permission_context::replace_permissions(previous);
+
+        cases.push(TestCaseResult {
+            name: test.name,
+            outcome: match result {
+                Ok(()) => TestOutcome::Passed,
+                Err(e) => TestOutcome::Failed(e),
+            },
+            duration: start.elapsed(),
+        });
+    }
+
+    TestFileReport {
+        path: path.to_string(),
+        cases,
+    }
+}
+
+/// Statements a doc test can use: everything declared in the file except `main`
+fn is_declaration(stmt: &Stmt) -> bool {
+    match &stmt.kind {
+        StmtKind::FunctionDecl { name, .. } => name != "main",
+        StmtKind::AttrStmt { stmt, .. } => is_declaration(stmt),
+        StmtKind::ImportDecl { .. }
+        | StmtKind::ConstDecl { .. }
+        | StmtKind::StructDecl { .. }
+        | StmtKind::EnumDecl { .. }
+        | StmtKind::TraitDecl { .. }
+        | StmtKind::ImplBlock { .. } => true,
+        _ => false,
+    }
+}
+
+fn run_doc_test(
+    path: &str,
+    source: &str,
+    declarations: &[Stmt],
+    test: &DocTest,
+    features: &[String],
+) -> Result<(), RuntimeError> {
+    let mut interpreter = Interpreter::with_source(path, source).with_features(features.to_vec());
+    for stmt in declarations {
+        interpreter.eval_stmt(stmt.clone())?;
+    }
+
+    // Errors point into the code block rather than the file
+    let test_path = format!("{} [{}]", path, test.name);
+    interpreter.set_source(test_path.clone(), test.code.clone());
+    let stmts = Parser::new(InputStream::new(&test_path, &test.code))
+        .parse()
+        .map_err(|e| {
+            RuntimeError::with_source(
+                e.message,
+                test_path.clone(),
+                test.code.clone(),
+                e.position.saturating_sub(e.len.unwrap_or_default()),
+                e.len.unwrap_or_default(),
+            )
+        })?;
+    interpreter.eval_program(stmts)?;
+    Ok(())
+}
+
+/// Read and run a test file from disk
+#[cfg(not(target_arch = "wasm32"))]
+pub fn run_test_file(path: &Path, features: &[String], filter: Option<&str>) -> TestFileReport {
+    let display_path = path.display().to_string();
+    match std::fs::read_to_string(path) {
+        Ok(source) => run_test_source(&display_path, &source, features, filter),
+        Err(e) => TestFileReport {
+            path: display_path.clone(),
+            cases: vec![TestCaseResult {
+                name: display_path,
+                outcome: TestOutcome::Failed(RuntimeError::new(format!(
+                    "Failed to read test file: {}",
+                    e
+                ))),
+                duration: std::time::Duration::ZERO,
+            }],
+        },
+    }
+}
+
+/// Read a source file from disk and run its doc tests
+#[cfg(not(target_arch = "wasm32"))]
+pub fn run_doc_test_file(path: &Path, features: &[String], filter: Option<&str>) -> TestFileReport {
+    let display_path = path.display().to_string();
+    match std::fs::read_to_string(path) {
+        Ok(source) => run_doc_tests_source(&display_path, &source, features, filter),
+        Err(e) => TestFileReport {
+            path: display_path.clone(),
+            cases: vec![TestCaseResult {
+                name: display_path,
+                outcome: TestOutcome::Failed(RuntimeError::new(format!(
+                    "Failed to read source file: {}",
+                    e
+                ))),
+                duration: std::time::Duration::ZERO,
+            }],
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runs_each_test_function_in_isolation() {
+        let source = r#"
+            let mut counter = 0;
+
+            #[test]
+            fn first() {
+                counter = counter + 1;
+                assert.eq(counter, 1);
+            }
+
+            #[test]
+            fn second() {
+                counter = counter + 1;
+                assert.eq(counter, 1);
+            }
+
+            #[test]
+            fn failing() {
+                assert.eq(1, 2);
+            }
+
+            fn helper() {
+                assert.fail("helpers are not tests");
+            }
+        "#;
+
+        let report = run_test_source("sample_test.lf", source, &[], None);
+        let names: Vec<&str> = report.cases.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["first", "second", "failing"]);
+        assert_eq!(report.passed_count(), 2);
+        assert_eq!(report.failed_count(), 1);
+    }
+
+    #[test]
+    fn test_whole_file_is_a_test_without_test_functions() {
+        let report = run_test_source("plain_test.lf", "assert.ok(1 == 1);", &[], None);
+        assert_eq!(report.cases.len(), 1);
+        assert!(report.cases[0].passed());
+
+        let report = run_test_source("plain_test.lf", "assert.ok(1 == 2);", &[], None);
+        assert_eq!(report.failed_count(), 1);
+    }
+
+    #[test]
+    fn test_filter_selects_test_functions() {
+        let source = r#"
+            #[test]
+            fn parses_numbers() { assert.ok(true); }
+
+            #[test]
+            fn parses_strings() { assert.ok(true); }
+        "#;
+
+        let report = run_test_source("filter_test.lf", source, &[], Some("strings"));
+        assert_eq!(report.cases.len(), 1);
+        assert_eq!(report.cases[0].name, "parses_strings");
+    }
+
+    #[test]
+    fn test_assert_throws() {
+        let source = r#"
+            #[test]
+            fn throws_when_error() {
+                assert.throws(() => assert.fail("kaboom"), "kaboom");
+            }
+
+            #[test]
+            fn fails_when_no_error() {
+                assert.throws(() => 1);
+            }
+        "#;
+
+        let report = run_test_source("throws_test.lf", source, &[], None);
+        assert!(report.cases[0].passed());
+        assert!(!report.cases[1].passed());
+    }
+
+    #[test]
+    fn test_doc_tests() {
+        let source = r#"
+/// Adds two numbers.
+///
+/// ```loft
+/// # let two = 2;
+/// assert.eq(add(two, 3), 5);
+/// ```
+///
+/// ```loft
+/// assert.eq(add(1, 1), 3);
+/// ```
+///
+/// ```loft,ignore
+/// not even loft
+/// ```
+fn add(a: num, b: num) -> num {
+    return a + b;
+}
+
+/// ```loft
+/// fs.read("secrets.txt");
+/// ```
+fn main() {
+    term.println("not run by doc tests");
+}
+"#;
+
+        let tests = extract_doc_tests(source);
+        assert_eq!(tests.len(), 3);
+        assert_eq!(tests[0].name, "add (line 4)");
+        assert_eq!(tests[0].code, "let two = 2;\nassert.eq(add(two, 3), 5);");
+
+        let features = ["io".to_string()];
+        let report = run_doc_tests_source("math.lf", source, &features, None);
+        let outcomes: Vec<bool> = report.cases.iter().map(|c| c.passed()).collect();
+        assert_eq!(outcomes, vec![true, false, false]);
+
+        // Doc tests have no permissions
+        let TestOutcome::Failed(error) = &report.cases[2].outcome else {
+            unreachable!()
+        };
+        assert!(error.message.contains("denied"), "{}", error.message);
+
+        let report = run_doc_tests_source("math.lf", source, &[], Some("line 4"));
+        assert_eq!(report.cases.len(), 1);
+    }
+}
@@ -1,5 +1,7 @@
+use super::builtins::collections::array::compare_values;
 use super::value::Value;
 use super::{RuntimeError, RuntimeResult};
+use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::Decimal;
 
 /// Trait for addition operation.
@@ -30,6 +32,13 @@ pub trait Div {
     fn div(&self, other: &Value) -> RuntimeResult<Value>;
 }
 
+/// Trait for remainder operation.
+/// Allows values to be divided with the remainder kept using the `%` operator.
+/// The remainder has the sign of the left operand, so `-7 % 2` is `-1`.
+pub trait Rem {
+    fn rem(&self, other: &Value) -> RuntimeResult<Value>;
+}
+
 /// Trait for bitwise AND operation.
 pub trait BitAnd {
     fn bit_and(&self, other: &Value) -> RuntimeResult<Value>;
@@ -86,10 +95,17 @@ pub trait ToString {
 impl Add for Value {
     fn add(&self, other: &Value) -> RuntimeResult<Value> {
         match (self, other) {
-            (Value::Number(l), Value::Number(r)) => Ok(Value::Number(*l + *r)),
-            (Value::String(l), Value::String(r)) => Ok(Value::String(format!("{}{}", l, r))),
+            (Value::Number(l), Value::Number(r)) => checked(l.checked_add(*r)),
+            (Value::String(l), Value::String(r)) => Ok(Value::String(format!("{}{}", l, r).into())),
+            (Value::Bytes(l), Value::Bytes(r)) => {
+                Ok(Value::Bytes([&l[..], &r[..]].concat().into()))
+            }
+            // DateTime + milliseconds
+            (Value::DateTime(dt), Value::Number(ms)) => shift_datetime(dt, *ms),
             // Allow string + any by coercing the right-hand side to its string representation
-            (Value::String(l), _) => Ok(Value::String(format!("{}{}", l, other.to_string()))),
+            (Value::String(l), _) => {
+                Ok(Value::String(format!("{}{}", l, other.to_string()).into()))
+            }
             _ => Err(RuntimeError::new(format!(
                 "Cannot add {:?} and {:?}",
                 self, other
@@ -98,10 +114,61 @@ impl Add for Value {
     }
 }
 
+/// The result of checked arithmetic, which is `None` when it doesn't fit a number
+fn checked(result: Option<Decimal>) -> RuntimeResult<Value> {
+    result
+        .map(Value::Number)
+        .ok_or_else(|| RuntimeError::new("Number overflow"))
+}
+
+/// An operand of a bitwise operator, which must be an integer that fits in 64 bits
+fn integer_operand(n: &Decimal, operator: &str) -> RuntimeResult<i64> {
+    if !n.fract().is_zero() {
+        return Err(RuntimeError::new(format!(
+            "{} requires integer operands, got {}",
+            operator, n
+        )));
+    }
+    n.to_i64().ok_or_else(|| {
+        RuntimeError::new(format!(
+            "{} requires operands that fit in 64 bits, got {}",
+            operator, n
+        ))
+    })
+}
+
+/// The right operand of a shift, a bit count below 64
+fn shift_amount(n: &Decimal) -> RuntimeResult<u32> {
+    n.fract()
+        .is_zero()
+        .then(|| n.to_u32())
+        .flatten()
+        .filter(|bits| *bits < 64)
+        .ok_or_else(|| {
+            RuntimeError::new(format!(
+                "Right operand of shift must be an integer from 0 to 63, got {}",
+                n
+            ))
+        })
+}
+
+/// Move a date by a signed number of milliseconds
+fn shift_datetime(dt: &chrono::DateTime<chrono::FixedOffset>, ms: Decimal) -> RuntimeResult<Value> {
+    ms.to_i64()
+        .and_then(|ms| dt.checked_add_signed(chrono::Duration::milliseconds(ms)))
+        .map(Value::DateTime)
+        .ok_or_else(|| RuntimeError::new("DateTime arithmetic overflowed"))
+}
+
 impl Sub for Value {
     fn sub(&self, other: &Value) -> RuntimeResult<Value> {
         match (self, other) {
-            (Value::Number(l), Value::Number(r)) => Ok(Value::Number(*l - *r)),
+            (Value::Number(l), Value::Number(r)) => checked(l.checked_sub(*r)),
+            (Value::DateTime(dt), Value::Number(ms)) => shift_datetime(dt, -*ms),
+            // The difference between two dates is in milliseconds
+            (Value::DateTime(l), Value::DateTime(r)) => Ok(Value::Number(Decimal::from(
+                l.signed_duration_since(*r).num_milliseconds(),
+            ))),
             _ => Err(RuntimeError::new(format!(
                 "Cannot subtract {:?} and {:?}",
                 self, other
@@ -113,7 +180,7 @@ impl Sub for Value {
 impl Mul for Value {
     fn mul(&self, other: &Value) -> RuntimeResult<Value> {
         match (self, other) {
-            (Value::Number(l), Value::Number(r)) => Ok(Value::Number(*l * *r)),
+            (Value::Number(l), Value::Number(r)) => checked(l.checked_mul(*r)),
             _ => Err(RuntimeError::new(format!(
                 "Cannot multiply {:?} and {:?}",
                 self, other
@@ -129,7 +196,7 @@ impl Div for Value {
                 if *r == Decimal::ZERO {
                     Err(RuntimeError::new("Division by zero"))
                 } else {
-                    Ok(Value::Number(*l / *r))
+                    checked(l.checked_div(*r))
                 }
             }
             _ => Err(RuntimeError::new(format!(
@@ -140,25 +207,31 @@ impl Div for Value {
     }
 }
 
+impl Rem for Value {
+    fn rem(&self, other: &Value) -> RuntimeResult<Value> {
+        match (self, other) {
+            (Value::Number(l), Value::Number(r)) => {
+                if *r == Decimal::ZERO {
+                    Err(RuntimeError::new("Division by zero"))
+                } else {
+                    checked(l.checked_rem(*r))
+                }
+            }
+            _ => Err(RuntimeError::new(format!(
+                "Cannot take the remainder of {:?} by {:?}",
+                self, other
+            ))),
+        }
+    }
+}
+
 impl BitAnd for Value {
     fn bit_and(&self, other: &Value) -> RuntimeResult<Value> {
         match (self, other) {
             (Value::Number(l), Value::Number(r)) => {
-                let l_i64 = l
-                    .to_string()
-                    .split('.')
-                    .next()
-                    .unwrap()
-                    .parse::<i64>()
-                    .map_err(|_| RuntimeError::new("Bitwise AND requires integer operands"))?;
-                let r_i64 = r
-                    .to_string()
-                    .split('.')
-                    .next()
-                    .unwrap()
-                    .parse::<i64>()
-                    .map_err(|_| RuntimeError::new("Bitwise AND requires integer operands"))?;
-                Ok(Value::Number(Decimal::from(l_i64 & r_i64)))
+                let l = integer_operand(l, "Bitwise AND")?;
+                let r = integer_operand(r, "Bitwise AND")?;
+                Ok(Value::Number(Decimal::from(l & r)))
             }
             _ => Err(RuntimeError::new(format!(
                 "Cannot perform bitwise AND on {:?} and {:?}",
@@ -172,21 +245,9 @@ impl BitOr for Value {
     fn bit_or(&self, other: &Value) -> RuntimeResult<Value> {
         match (self, other) {
             (Value::Number(l), Value::Number(r)) => {
-                let l_i64 = l
-                    .to_string()
-                    .split('.')
-                    .next()
-                    .unwrap()
-                    .parse::<i64>()
-                    .map_err(|_| RuntimeError::new("Bitwise OR requires integer operands"))?;
-                let r_i64 = r
-                    .to_string()
-                    .split('.')
-                    .next()
-                    .unwrap()
-                    .parse::<i64>()
-                    .map_err(|_| RuntimeError::new("Bitwise OR requires integer operands"))?;
-                Ok(Value::Number(Decimal::from(l_i64 | r_i64)))
+                let l = integer_operand(l, "Bitwise OR")?;
+                let r = integer_operand(r, "Bitwise OR")?;
+                Ok(Value::Number(Decimal::from(l | r)))
             }
             _ => Err(RuntimeError::new(format!(
                 "Cannot perform bitwise OR on {:?} and {:?}",
@@ -200,21 +261,9 @@ impl BitXor for Value {
     fn bit_xor(&self, other: &Value) -> RuntimeResult<Value> {
         match (self, other) {
             (Value::Number(l), Value::Number(r)) => {
-                let l_i64 = l
-                    .to_string()
-                    .split('.')
-                    .next()
-                    .unwrap()
-                    .parse::<i64>()
-                    .map_err(|_| RuntimeError::new("Bitwise XOR requires integer operands"))?;
-                let r_i64 = r
-                    .to_string()
-                    .split('.')
-                    .next()
-                    .unwrap()
-                    .parse::<i64>()
-                    .map_err(|_| RuntimeError::new("Bitwise XOR requires integer operands"))?;
-                Ok(Value::Number(Decimal::from(l_i64 ^ r_i64)))
+                let l = integer_operand(l, "Bitwise XOR")?;
+                let r = integer_operand(r, "Bitwise XOR")?;
+                Ok(Value::Number(Decimal::from(l ^ r)))
             }
             _ => Err(RuntimeError::new(format!(
                 "Cannot perform bitwise XOR on {:?} and {:?}",
@@ -228,23 +277,12 @@ impl Shl for Value {
     fn shl(&self, other: &Value) -> RuntimeResult<Value> {
         match (self, other) {
             (Value::Number(l), Value::Number(r)) => {
-                let l_i64 = l
-                    .to_string()
-                    .split('.')
-                    .next()
-                    .unwrap()
-                    .parse::<i64>()
-                    .map_err(|_| RuntimeError::new("Left shift requires integer operands"))?;
-                let r_u32 = r
-                    .to_string()
-                    .split('.')
-                    .next()
-                    .unwrap()
-                    .parse::<u32>()
-                    .map_err(|_| {
-                        RuntimeError::new("Right operand of shift must be a non-negative integer")
-                    })?;
-                Ok(Value::Number(Decimal::from(l_i64 << r_u32)))
+                let l = integer_operand(l, "Left shift")?;
+                let bits = shift_amount(r)?;
+                // Bits shifted past the sign bit overflow
+                i64::try_from(i128::from(l) << bits)
+                    .map(|shifted| Value::Number(Decimal::from(shifted)))
+                    .map_err(|_| RuntimeError::new("Number overflow in left shift"))
             }
             _ => Err(RuntimeError::new(format!(
                 "Cannot perform left shift on {:?} and {:?}",
@@ -258,23 +296,9 @@ impl Shr for Value {
     fn shr(&self, other: &Value) -> RuntimeResult<Value> {
         match (self, other) {
             (Value::Number(l), Value::Number(r)) => {
-                let l_i64 = l
-                    .to_string()
-                    .split('.')
-                    .next()
-                    .unwrap()
-                    .parse::<i64>()
-                    .map_err(|_| RuntimeError::new("Right shift requires integer operands"))?;
-                let r_u32 = r
-                    .to_string()
-                    .split('.')
-                    .next()
-                    .unwrap()
-                    .parse::<u32>()
-                    .map_err(|_| {
-                        RuntimeError::new("Right operand of shift must be a non-negative integer")
-                    })?;
-                Ok(Value::Number(Decimal::from(l_i64 >> r_u32)))
+                let l = integer_operand(l, "Right shift")?;
+                let bits = shift_amount(r)?;
+                Ok(Value::Number(Decimal::from(l >> bits)))
             }
             _ => Err(RuntimeError::new(format!(
                 "Cannot perform right shift on {:?} and {:?}",
@@ -288,34 +312,33 @@ impl Index for Value {
     fn index(&self, index: &Value) -> RuntimeResult<Value> {
         match (self, index) {
             (Value::Array(arr), Value::Number(idx)) => {
-                let idx_usize = idx
-                    .to_string()
-                    .parse::<usize>()
-                    .map_err(|_| RuntimeError::new("Array index must be a non-negative integer"))?;
-
-                arr.get(idx_usize).cloned().ok_or_else(|| {
-                    RuntimeError::new(format!("Array index {} out of bounds", idx_usize))
-                })
+                let idx = position(idx, arr.len(), "Array index", false)?;
+                Ok(arr[idx].clone())
             }
             (Value::String(s), Value::Number(idx)) => {
-                let idx_usize = idx.to_string().parse::<usize>().map_err(|_| {
-                    RuntimeError::new("String index must be a non-negative integer")
-                })?;
-
-                s.chars()
-                    .nth(idx_usize)
-                    .map(|c| Value::String(c.to_string()))
-                    .ok_or_else(|| {
-                        RuntimeError::new(format!("String index {} out of bounds", idx_usize))
-                    })
+                let idx = position(idx, s.chars().count(), "String index", false)?;
+                let c = s.chars().nth(idx).unwrap_or_default();
+                Ok(Value::String(c.to_string().into()))
+            }
+            (Value::Bytes(b), Value::Number(idx)) => {
+                let idx = position(idx, b.len(), "Bytes index", false)?;
+                Ok(Value::Number(Decimal::from(b[idx])))
             }
             (Value::Struct { fields, .. }, Value::String(key)) => {
-                fields.get(key).cloned().ok_or_else(|| {
+                fields.get(&**key).cloned().ok_or_else(|| {
                     RuntimeError::new(format!("Object does not have property '{}'", key))
                 })
             }
+            (Value::Map(entries), key) => entries
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.clone())
+                .ok_or_else(|| {
+                    RuntimeError::new(format!("Map does not contain key {}", key.to_string()))
+                }),
             (Value::Array(_), _) => Err(RuntimeError::new("Array index must be a number")),
             (Value::String(_), _) => Err(RuntimeError::new("String index must be a number")),
+            (Value::Bytes(_), _) => Err(RuntimeError::new("Bytes index must be a number")),
             (Value::Struct { .. }, _) => Err(RuntimeError::new("Object index must be a string")),
             _ => Err(RuntimeError::new(format!(
                 "Cannot index value of type {:?}",
@@ -325,45 +348,103 @@ impl Index for Value {
     }
 }
 
+/// Where `index` points in a sequence of `len` items. Negative indices count
+/// from the end, so -1 is the last item. `allow_end` lets it point just past
+/// the last item, as the end of a slice can.
+fn position(index: &Decimal, len: usize, what: &str, allow_end: bool) -> RuntimeResult<usize> {
+    let index = index
+        .to_i64()
+        .filter(|_| index.fract().is_zero())
+        .ok_or_else(|| RuntimeError::new(format!("{} must be an integer, got {}", what, index)))?;
+    let from_start = if index < 0 { len as i64 + index } else { index };
+    let limit = if allow_end {
+        len as i64
+    } else {
+        len as i64 - 1
+    };
+    if from_start < 0 || from_start > limit {
+        return Err(RuntimeError::new(format!(
+            "{} {} out of bounds for length {}",
+            what, index, len
+        )));
+    }
+    Ok(from_start as usize)
+}
+
+/// The items of an array, string or bytes value from `start` up to but not
+/// including `end`, as in `items[1..3]`. Missing bounds mean the start and
+/// the end; negative ones count from the end.
+pub fn slice_value(
+    value: &Value,
+    start: Option<&Value>,
+    end: Option<&Value>,
+) -> RuntimeResult<Value> {
+    let (kind, len) = match value {
+        Value::Array(items) => ("Array", items.len()),
+        Value::String(s) => ("String", s.chars().count()),
+        Value::Bytes(b) => ("Bytes", b.len()),
+        _ => {
+            return Err(RuntimeError::new(format!(
+                "Cannot slice a value of type {}",
+                value.type_name()
+            )))
+        }
+    };
+    let bound = |bound: Option<&Value>, which: &str, default: usize| match bound {
+        None => Ok(default),
+        Some(Value::Number(n)) => position(n, len, &format!("{} slice {}", kind, which), true),
+        Some(other) => Err(RuntimeError::new(format!(
+            "{} slice {} must be a number, got {}",
+            kind,
+            which,
+            other.type_name()
+        ))),
+    };
+    let start = bound(start, "start", 0)?;
+    let end = bound(end, "end", len)?;
+    if start > end {
+        return Err(RuntimeError::new(format!(
+            "{} slice start {} is after its end {}",
+            kind, start, end
+        )));
+    }
+
+    Ok(match value {
+        Value::Array(items) => Value::Array(items[start..end].to_vec()),
+        Value::String(s) => Value::String(
+            s.chars()
+                .skip(start)
+                .take(end - start)
+                .collect::<String>()
+                .into(),
+        ),
+        Value::Bytes(b) => Value::Bytes(b[start..end].into()),
+        _ => unreachable!(),
+    })
+}
+
+/// The order of two values for a comparison operator like `<`: numbers,
+/// strings, booleans and date-times by value, arrays element by element
+fn ordering(left: &Value, op: &str, right: &Value) -> RuntimeResult<std::cmp::Ordering> {
+    compare_values(left, right)
+        .map_err(|_| RuntimeError::new(format!("Cannot compare {:?} {} {:?}", left, op, right)))
+}
+
 impl Ord for Value {
     fn gt(&self, other: &Value) -> RuntimeResult<Value> {
-        match (self, other) {
-            (Value::Number(l), Value::Number(r)) => Ok(Value::Boolean(l > r)),
-            _ => Err(RuntimeError::new(format!(
-                "Cannot compare {:?} > {:?}",
-                self, other
-            ))),
-        }
+        Ok(Value::Boolean(ordering(self, ">", other)?.is_gt()))
     }
 
     fn ge(&self, other: &Value) -> RuntimeResult<Value> {
-        match (self, other) {
-            (Value::Number(l), Value::Number(r)) => Ok(Value::Boolean(l >= r)),
-            _ => Err(RuntimeError::new(format!(
-                "Cannot compare {:?} >= {:?}",
-                self, other
-            ))),
-        }
+        Ok(Value::Boolean(ordering(self, ">=", other)?.is_ge()))
     }
 
     fn lt(&self, other: &Value) -> RuntimeResult<Value> {
-        match (self, other) {
-            (Value::Number(l), Value::Number(r)) => Ok(Value::Boolean(l < r)),
-            _ => Err(RuntimeError::new(format!(
-                "Cannot compare {:?} < {:?}",
-                self, other
-            ))),
-        }
+        Ok(Value::Boolean(ordering(self, "<", other)?.is_lt()))
     }
 
     fn le(&self, other: &Value) -> RuntimeResult<Value> {
-        match (self, other) {
-            (Value::Number(l), Value::Number(r)) => Ok(Value::Boolean(l <= r)),
-            _ => Err(RuntimeError::new(format!(
-                "Cannot compare {:?} <= {:?}",
-                self, other
-            ))),
-        }
+        Ok(Value::Boolean(ordering(self, "<=", other)?.is_le()))
     }
 
     fn eq(&self, other: &Value) -> RuntimeResult<Value> {
@@ -378,7 +459,9 @@ impl Ord for Value {
 impl ToString for Value {
     fn to_string(&self) -> String {
         match self {
-            Value::String(s) => s.clone(),
+            Value::String(s) => s.to_string(),
+            Value::Bytes(b) => Value::bytes_preview(b),
+            Value::DateTime(dt) => dt.to_rfc3339(),
             Value::Number(n) => n.to_string(),
             Value::Boolean(b) => b.to_string(),
             Value::Unit => "()".to_string(),
@@ -386,6 +469,17 @@ impl ToString for Value {
                 let items: Vec<String> = arr.iter().map(|v| v.to_string()).collect();
                 format!("[{}]", items.join(", "))
             }
+            Value::Map(entries) => {
+                let items: Vec<String> = entries
+                    .iter()
+                    .map(|(k, v)| format!("{}: {}", k.to_string(), v.to_string()))
+                    .collect();
+                format!("#{{{}}}", items.join(", "))
+            }
+            Value::Set(items) => {
+                let items: Vec<String> = items.iter().map(|v| v.value().to_string()).collect();
+                format!("Set{{{}}}", items.join(", "))
+            }
             Value::Function { name, .. } => format!("<function {}>", name),
             Value::Struct { name, .. } => format!("<struct {}>", name),
             Value::Builtin(b) => format!("<builtin {}>", b.name),
@@ -394,6 +488,11 @@ impl ToString for Value {
             Value::UserMethod { method_name, .. } => format!("<method {}>", method_name),
             Value::Closure { params, .. } => format!("<closure with {} params>", params.len()),
             Value::Promise(value) => format!("<promise {}>", value.to_string()),
+            Value::Task(task) => match task.result() {
+                Some(Ok(value)) => format!("<promise {}>", value.to_string()),
+                Some(Err(_)) => "<promise rejected>".to_string(),
+                None => "<promise pending>".to_string(),
+            },
             Value::EnumVariant {
                 enum_name,
                 variant_name,
@@ -444,6 +543,7 @@ pub fn call_binop_trait(op: &str, left: &Value, right: &Value) -> RuntimeResult<
         "-" => left.sub(right),
         "*" => left.mul(right),
         "/" => left.div(right),
+        "%" => left.rem(right),
         "&" => left.bit_and(right),
         "|" => left.bit_or(right),
         "^" => left.bit_xor(right),
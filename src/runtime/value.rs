@@ -1,20 +1,29 @@
 use super::builtin::{BuiltinFunction, BuiltinMethod, BuiltinStruct};
+use super::scheduler::Task;
 use crate::parser::{Expr, Stmt, Type};
+use chrono::{DateTime, FixedOffset};
 use rust_decimal::Decimal;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub enum Value {
     Unit,
     Number(Decimal),
-    String(String),
+    String(Arc<str>), // Shared so copying a string value never copies its contents
+    Bytes(Arc<[u8]>), // Binary data, shared like strings
+    DateTime(DateTime<FixedOffset>), // A point in time with its UTC offset
     Boolean(bool),
     Array(Vec<Value>),
+    Map(Vec<(Value, Value)>), // Key-value pairs in insertion order
+    Set(BTreeSet<SetItem>),   // Unique values, iterated in sorted order
     Function {
         name: String,
         params: Vec<(String, String)>, // (name, type)
+        variadic: bool,                // The last parameter collects the remaining arguments
         body: Box<Stmt>,
         is_async: bool,
+        source: Option<Arc<super::SourceFile>>, // File the function was declared in
     },
     Closure {
         params: Vec<(String, Option<Type>)>,
@@ -41,6 +50,7 @@ pub enum Value {
         body: Box<Stmt>,
     },
     Promise(Box<Value>), // A promise that holds a resolved value
+    Task(Task),          // A promise backed by work on the scheduler
     EnumVariant {
         enum_name: String,
         variant_name: String,
@@ -64,8 +74,12 @@ impl std::fmt::Debug for Value {
             Value::Unit => write!(f, "Unit"),
             Value::Number(n) => write!(f, "Number({:?})", n),
             Value::String(s) => write!(f, "String({:?})", s),
+            Value::Bytes(b) => write!(f, "Bytes({:?})", b),
+            Value::DateTime(dt) => write!(f, "DateTime({})", dt.to_rfc3339()),
             Value::Boolean(b) => write!(f, "Boolean({:?})", b),
             Value::Array(arr) => write!(f, "Array({:?})", arr),
+            Value::Map(entries) => write!(f, "Map({:?})", entries),
+            Value::Set(items) => write!(f, "Set({:?})", items),
             Value::Function {
                 name,
                 params,
@@ -109,6 +123,7 @@ impl std::fmt::Debug for Value {
                 )
             }
             Value::Promise(value) => write!(f, "Promise({:?})", value),
+            Value::Task(task) => write!(f, "{:?}", task),
             Value::EnumVariant {
                 enum_name,
                 variant_name,
@@ -138,6 +153,94 @@ impl std::fmt::Debug for Value {
     }
 }
 
+/// A value stored in a `Set`
+///
+/// Only values with a total order can be set members: void, booleans,
+/// numbers, strings, bytes, dates and arrays of these. Members of different
+/// types are ordered by type first.
+#[derive(Clone, Debug)]
+pub struct SetItem(Value);
+
+impl SetItem {
+    pub fn new(value: Value) -> Result<Self, String> {
+        if SetItem::is_orderable(&value) {
+            Ok(SetItem(value))
+        } else {
+            Err(format!("{:?} cannot be stored in a Set", value))
+        }
+    }
+
+    fn is_orderable(value: &Value) -> bool {
+        match value {
+            Value::Unit
+            | Value::Boolean(_)
+            | Value::Number(_)
+            | Value::String(_)
+            | Value::Bytes(_)
+            | Value::DateTime(_) => true,
+            Value::Array(items) => items.iter().all(SetItem::is_orderable),
+            _ => false,
+        }
+    }
+
+    pub fn value(&self) -> &Value {
+        &self.0
+    }
+
+    pub fn into_value(self) -> Value {
+        self.0
+    }
+
+    fn rank(value: &Value) -> u8 {
+        match value {
+            Value::Unit => 0,
+            Value::Boolean(_) => 1,
+            Value::Number(_) => 2,
+            Value::String(_) => 3,
+            Value::Bytes(_) => 4,
+            Value::DateTime(_) => 5,
+            _ => 6,
+        }
+    }
+
+    fn compare(a: &Value, b: &Value) -> std::cmp::Ordering {
+        match (a, b) {
+            (Value::Boolean(l), Value::Boolean(r)) => l.cmp(r),
+            (Value::Number(l), Value::Number(r)) => l.cmp(r),
+            (Value::String(l), Value::String(r)) => l.cmp(r),
+            (Value::Bytes(l), Value::Bytes(r)) => l.cmp(r),
+            (Value::DateTime(l), Value::DateTime(r)) => l.cmp(r),
+            (Value::Array(l), Value::Array(r)) => l
+                .iter()
+                .zip(r)
+                .map(|(x, y)| SetItem::compare(x, y))
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or_else(|| l.len().cmp(&r.len())),
+            _ => SetItem::rank(a).cmp(&SetItem::rank(b)),
+        }
+    }
+}
+
+impl PartialEq for SetItem {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for SetItem {}
+
+impl PartialOrd for SetItem {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SetItem {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        SetItem::compare(&self.0, &other.0)
+    }
+}
+
 // Manual PartialEq implementation
 impl PartialEq for Value {
     fn eq(&self, other: &Self) -> bool {
@@ -145,27 +248,49 @@ impl PartialEq for Value {
             (Value::Unit, Value::Unit) => true,
             (Value::Number(a), Value::Number(b)) => a == b,
             (Value::String(a), Value::String(b)) => a == b,
+            (Value::Bytes(a), Value::Bytes(b)) => a == b,
+            (Value::DateTime(a), Value::DateTime(b)) => a == b,
             (Value::Boolean(a), Value::Boolean(b)) => a == b,
             (Value::Array(a), Value::Array(b)) => a == b,
+            (Value::Map(a), Value::Map(b)) => {
+                // Maps compare equal regardless of insertion order
+                a.len() == b.len()
+                    && a.iter()
+                        .all(|(k, v)| b.iter().any(|(k2, v2)| k == k2 && v == v2))
+            }
+            (Value::Set(a), Value::Set(b)) => a == b,
+            // Functions and closures are equal when they come from the same
+            // declaration (bodies carry their spans) and capture the same values
             (
                 Value::Function {
                     name: n1,
                     params: p1,
+                    body: b1,
                     is_async: a1,
                     ..
                 },
                 Value::Function {
                     name: n2,
                     params: p2,
+                    body: b2,
                     is_async: a2,
                     ..
                 },
-            ) => n1 == n2 && p1 == p2 && a1 == a2,
-            (Value::Closure { params: p1, .. }, Value::Closure { params: p2, .. }) => {
-                // Closures are compared by their parameter signatures
-                // (We can't easily compare captured environments or bodies)
-                p1 == p2
-            }
+            ) => n1 == n2 && p1 == p2 && a1 == a2 && b1 == b2,
+            (
+                Value::Closure {
+                    params: p1,
+                    body: b1,
+                    captured_env: c1,
+                    ..
+                },
+                Value::Closure {
+                    params: p2,
+                    body: b2,
+                    captured_env: c2,
+                    ..
+                },
+            ) => p1 == p2 && b1 == b2 && c1 == c2,
             (
                 Value::Struct {
                     name: n1,
@@ -177,6 +302,7 @@ impl PartialEq for Value {
                 },
             ) => n1 == n2 && f1 == f2,
             (Value::Builtin(a), Value::Builtin(b)) => a.name == b.name,
+            (Value::BuiltinFn(a), Value::BuiltinFn(b)) => std::ptr::fn_addr_eq(*a, *b),
             (
                 Value::BoundMethod {
                     object: o1,
@@ -202,6 +328,7 @@ impl PartialEq for Value {
                 },
             ) => o1 == o2 && m1 == m2,
             (Value::Promise(a), Value::Promise(b)) => a == b,
+            (Value::Task(a), Value::Task(b)) => a.ptr_eq(b),
             (
                 Value::EnumVariant {
                     enum_name: e1,
@@ -233,13 +360,89 @@ impl PartialEq for Value {
 }
 
 impl Value {
+    /// The runtime type name of the value, as reported by `type_of`
+    pub fn type_name(&self) -> String {
+        match self {
+            Value::Unit => "unit".to_string(),
+            Value::Number(_) => "num".to_string(),
+            Value::String(_) => "str".to_string(),
+            Value::Bytes(_) => "Bytes".to_string(),
+            Value::DateTime(_) => "DateTime".to_string(),
+            Value::Boolean(_) => "bool".to_string(),
+            Value::Array(_) => "array".to_string(),
+            Value::Map(_) => "map".to_string(),
+            Value::Set(_) => "Set".to_string(),
+            Value::Function { .. } => "function".to_string(),
+            Value::Closure { .. } => "closure".to_string(),
+            Value::Struct { name, .. } => name.clone(),
+            Value::Builtin(b) => b.name.clone(),
+            Value::BuiltinFn(_) => "builtin_function".to_string(),
+            Value::BoundMethod { .. } => "method".to_string(),
+            Value::UserMethod { .. } => "method".to_string(),
+            Value::Promise(_) | Value::Task(_) => "promise".to_string(),
+            Value::EnumVariant { enum_name, .. } => enum_name.clone(),
+            Value::EnumConstructor { enum_name, .. } => format!("{}_constructor", enum_name),
+            Value::Module { name, .. } => format!("module_{}", name),
+        }
+    }
+
+    /// Rough number of bytes the value takes up, for memory limits. Shared
+    /// strings count once per copy, and a closure's captured variables are
+    /// left out since they are mostly copies of variables counted elsewhere.
+    pub fn approx_size(&self) -> usize {
+        let own = std::mem::size_of::<Value>();
+        own + match self {
+            Value::String(s) => s.len(),
+            Value::Bytes(b) => b.len(),
+            Value::Array(items) => items.iter().map(Value::approx_size).sum(),
+            Value::Map(entries) => entries
+                .iter()
+                .map(|(key, value)| key.approx_size() + value.approx_size())
+                .sum(),
+            Value::Set(items) => items.iter().map(|item| item.0.approx_size()).sum(),
+            Value::Struct { name, fields } => {
+                name.len()
+                    + fields
+                        .iter()
+                        .map(|(field, value)| field.len() + value.approx_size())
+                        .sum::<usize>()
+            }
+            Value::EnumVariant { values, .. } => values.iter().map(Value::approx_size).sum(),
+            Value::Promise(value) => value.approx_size(),
+            Value::BoundMethod { object, .. } | Value::UserMethod { object, .. } => {
+                object.approx_size()
+            }
+            Value::Module { exports, .. } => exports.values().map(Value::approx_size).sum(),
+            _ => 0,
+        }
+    }
+
+    /// Short hex preview of binary data, used when printing `Bytes`
+    pub fn bytes_preview(bytes: &[u8]) -> String {
+        const PREVIEW_LEN: usize = 32;
+        let hex: String = bytes
+            .iter()
+            .take(PREVIEW_LEN)
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        if bytes.len() > PREVIEW_LEN {
+            format!("<bytes {}... ({} bytes)>", hex, bytes.len())
+        } else {
+            format!("<bytes {}>", hex)
+        }
+    }
+
     pub fn is_truthy(&self) -> bool {
         match self {
             Value::Boolean(b) => *b,
             Value::Unit => false,
             Value::Number(n) => *n != Decimal::ZERO,
             Value::String(s) => !s.is_empty(),
+            Value::Bytes(b) => !b.is_empty(),
+            Value::DateTime(_) => true,
             Value::Array(a) => !a.is_empty(),
+            Value::Map(m) => !m.is_empty(),
+            Value::Set(s) => !s.is_empty(),
             Value::Function { .. } => true,
             Value::Closure { .. } => true,
             Value::Struct { .. } => true,
@@ -247,7 +450,7 @@ impl Value {
             Value::BuiltinFn(_) => true,
             Value::BoundMethod { .. } => true,
             Value::UserMethod { .. } => true,
-            Value::Promise(_) => true,
+            Value::Promise(_) | Value::Task(_) => true,
             Value::EnumVariant { .. } => true,
             Value::EnumConstructor { .. } => true,
             Value::Module { .. } => true,
@@ -0,0 +1,336 @@
+//! An in-memory file system that replaces the disk once mounted, for hosts
+//! that don't have one, like the web playground.
+//!
+//! The functions here mirror the `std::fs` calls the module loader and the
+//! `fs` builtin make, and go to the disk while nothing is mounted. Paths are
+//! normalized, so `./lib/util.lf`, `lib/util.lf` and `/lib/util.lf` all name
+//! the same file. Directories exist as long as a file is in them or they were
+//! created with [`create_dir_all`].
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, ErrorKind};
+use std::path::{Component, Path};
+
+#[derive(Debug, Default)]
+struct VirtualFs {
+    files: BTreeMap<String, Vec<u8>>,
+    dirs: BTreeSet<String>,
+}
+
+impl VirtualFs {
+    fn is_dir(&self, path: &str) -> bool {
+        path.is_empty() || self.dirs.contains(path) || self.entries_under(path).next().is_some()
+    }
+
+    /// Files and directories below `dir`, at any depth
+    fn entries_under<'a>(&'a self, dir: &'a str) -> impl Iterator<Item = &'a str> {
+        self.files
+            .keys()
+            .chain(self.dirs.iter())
+            .map(String::as_str)
+            .filter(move |path| dir.is_empty() || child_of(path, dir).is_some())
+    }
+}
+
+thread_local! {
+    static VFS: RefCell<Option<VirtualFs>> = const { RefCell::new(None) };
+}
+
+/// `path` relative to `dir`, if it is below it
+fn child_of<'a>(path: &'a str, dir: &str) -> Option<&'a str> {
+    if dir.is_empty() {
+        return Some(path);
+    }
+    path.strip_prefix(dir)?.strip_prefix('/')
+}
+
+/// The key a path is stored under: `/`-separated, without `.`, `..` or a leading `/`
+fn normalize(path: &Path) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().to_string()),
+            Component::ParentDir => {
+                parts.pop();
+            }
+            _ => {}
+        }
+    }
+    parts.join("/")
+}
+
+fn not_found(path: &str) -> io::Error {
+    io::Error::new(ErrorKind::NotFound, format!("No such file: {}", path))
+}
+
+/// Replace the disk with an empty in-memory file system for the current
+/// thread, or keep the one already mounted
+pub fn mount() {
+    VFS.with(|vfs| {
+        vfs.borrow_mut().get_or_insert_with(VirtualFs::default);
+    });
+}
+
+/// Go back to the disk, dropping every in-memory file
+pub fn unmount() {
+    VFS.with(|vfs| *vfs.borrow_mut() = None);
+}
+
+pub fn is_mounted() -> bool {
+    VFS.with(|vfs| vfs.borrow().is_some())
+}
+
+/// Run `f` on the mounted file system, or return `None` to use the disk
+fn with_vfs<R>(f: impl FnOnce(&mut VirtualFs) -> R) -> Option<R> {
+    VFS.with(|vfs| vfs.borrow_mut().as_mut().map(f))
+}
+
+pub fn read(path: impl AsRef<Path>) -> io::Result<Vec<u8>> {
+    let path = path.as_ref();
+    with_vfs(|vfs| {
+        let key = normalize(path);
+        vfs.files.get(&key).cloned().ok_or_else(|| not_found(&key))
+    })
+    .unwrap_or_else(|| std::fs::read(path))
+}
+
+pub fn read_to_string(path: impl AsRef<Path>) -> io::Result<String> {
+    String::from_utf8(read(path)?).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+}
+
+pub fn write(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let (path, contents) = (path.as_ref(), contents.as_ref());
+    with_vfs(|vfs| {
+        let key = normalize(path);
+        if key.is_empty() || vfs.is_dir(&key) {
+            return Err(io::Error::new(
+                ErrorKind::IsADirectory,
+                format!("Is a directory: {}", key),
+            ));
+        }
+        vfs.files.insert(key, contents.to_vec());
+        Ok(())
+    })
+    .unwrap_or_else(|| std::fs::write(path, contents))
+}
+
+/// Add to the end of a file, creating it if needed
+pub fn append(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let (path, contents) = (path.as_ref(), contents.as_ref());
+    if is_mounted() {
+        let mut data = read(path).or_else(|e| match e.kind() {
+            ErrorKind::NotFound => Ok(Vec::new()),
+            _ => Err(e),
+        })?;
+        data.extend_from_slice(contents);
+        return write(path, data);
+    }
+
+    use std::io::Write;
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(contents))
+}
+
+pub fn exists(path: impl AsRef<Path>) -> bool {
+    let path = path.as_ref();
+    with_vfs(|vfs| {
+        let key = normalize(path);
+        vfs.files.contains_key(&key) || vfs.is_dir(&key)
+    })
+    .unwrap_or_else(|| path.exists())
+}
+
+pub fn is_file(path: impl AsRef<Path>) -> bool {
+    let path = path.as_ref();
+    with_vfs(|vfs| vfs.files.contains_key(&normalize(path))).unwrap_or_else(|| path.is_file())
+}
+
+pub fn is_dir(path: impl AsRef<Path>) -> bool {
+    let path = path.as_ref();
+    with_vfs(|vfs| vfs.is_dir(&normalize(path))).unwrap_or_else(|| path.is_dir())
+}
+
+pub fn create_dir_all(path: impl AsRef<Path>) -> io::Result<()> {
+    let path = path.as_ref();
+    with_vfs(|vfs| {
+        let key = normalize(path);
+        if vfs.files.contains_key(&key) {
+            return Err(io::Error::new(
+                ErrorKind::AlreadyExists,
+                format!("File exists: {}", key),
+            ));
+        }
+        if !key.is_empty() {
+            vfs.dirs.insert(key);
+        }
+        Ok(())
+    })
+    .unwrap_or_else(|| std::fs::create_dir_all(path))
+}
+
+pub fn remove_file(path: impl AsRef<Path>) -> io::Result<()> {
+    let path = path.as_ref();
+    with_vfs(|vfs| {
+        let key = normalize(path);
+        vfs.files
+            .remove(&key)
+            .map(|_| ())
+            .ok_or_else(|| not_found(&key))
+    })
+    .unwrap_or_else(|| std::fs::remove_file(path))
+}
+
+/// Remove a directory and everything in it
+pub fn remove_dir_all(path: impl AsRef<Path>) -> io::Result<()> {
+    let path = path.as_ref();
+    with_vfs(|vfs| {
+        let key = normalize(path);
+        if !vfs.is_dir(&key) {
+            return Err(not_found(&key));
+        }
+        let inside = |p: &String| *p == key || child_of(p, &key).is_some();
+        vfs.files.retain(|p, _| !inside(p));
+        vfs.dirs.retain(|p| !inside(p));
+        Ok(())
+    })
+    .unwrap_or_else(|| std::fs::remove_dir_all(path))
+}
+
+/// Names of the entries of a directory, each with whether it is a directory
+pub fn read_dir(path: impl AsRef<Path>) -> io::Result<Vec<(String, bool)>> {
+    let path = path.as_ref();
+    with_vfs(|vfs| {
+        let key = normalize(path);
+        if !vfs.is_dir(&key) {
+            return Err(not_found(&key));
+        }
+        let mut entries: BTreeMap<String, bool> = BTreeMap::new();
+        for entry in vfs.entries_under(&key) {
+            let rest = child_of(entry, &key).unwrap_or(entry);
+            let (name, is_dir) = match rest.split_once('/') {
+                Some((dir, _)) => (dir, true),
+                None => (rest, vfs.dirs.contains(entry)),
+            };
+            *entries.entry(name.to_string()).or_default() |= is_dir;
+        }
+        Ok(entries.into_iter().collect())
+    })
+    .unwrap_or_else(|| {
+        std::fs::read_dir(path)?
+            .map(|entry| {
+                let entry = entry?;
+                let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+                Ok((entry.file_name().to_string_lossy().to_string(), is_dir))
+            })
+            .collect()
+    })
+}
+
+pub fn copy(from: impl AsRef<Path>, to: impl AsRef<Path>) -> io::Result<()> {
+    let (from, to) = (from.as_ref(), to.as_ref());
+    if is_mounted() {
+        return write(to, read(from)?);
+    }
+    std::fs::copy(from, to).map(|_| ())
+}
+
+pub fn rename(from: impl AsRef<Path>, to: impl AsRef<Path>) -> io::Result<()> {
+    let (from, to) = (from.as_ref(), to.as_ref());
+    if is_mounted() {
+        copy(from, to)?;
+        return remove_file(from);
+    }
+    std::fs::rename(from, to)
+}
+
+/// Size of a file, and whether the path is a file or a directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Metadata {
+    pub len: u64,
+    pub is_file: bool,
+    pub is_dir: bool,
+}
+
+pub fn metadata(path: impl AsRef<Path>) -> io::Result<Metadata> {
+    let path = path.as_ref();
+    with_vfs(|vfs| {
+        let key = normalize(path);
+        match vfs.files.get(&key) {
+            Some(data) => Ok(Metadata {
+                len: data.len() as u64,
+                is_file: true,
+                is_dir: false,
+            }),
+            None if vfs.is_dir(&key) => Ok(Metadata {
+                len: 0,
+                is_file: false,
+                is_dir: true,
+            }),
+            None => Err(not_found(&key)),
+        }
+    })
+    .unwrap_or_else(|| {
+        std::fs::metadata(path).map(|metadata| Metadata {
+            len: metadata.len(),
+            is_file: metadata.is_file(),
+            is_dir: metadata.is_dir(),
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::parser::{InputStream, Parser};
+    use crate::runtime::value::Value;
+    use crate::runtime::Interpreter;
+
+    #[test]
+    fn test_program_on_virtual_fs() {
+        mount();
+        write(
+            "lib/shapes.lf",
+            "teach fn area(w: num, h: num) -> num {\n    return w * h;\n}\n",
+        )
+        .unwrap();
+        write("./data.txt", "3").unwrap();
+
+        let input = "learn \"./lib/shapes\";\nfs.write(\"out/area.txt\", `${shapes.area(2, 3)}`);\nlet listed = fs.list_dir(\"out\");\nlet data = fs.read(\"/data.txt\");\nfs.remove_dir(\"out\");\n"
+            .to_string();
+        let stmts = Parser::new(InputStream::new("main.lf", &input))
+            .parse()
+            .unwrap();
+        let mut interpreter = Interpreter::with_source("main.lf", &input);
+        interpreter.eval_program(stmts).unwrap();
+
+        assert_eq!(
+            interpreter.env.get("listed"),
+            Some(&Value::Array(vec![Value::String("area.txt".into())]))
+        );
+        assert_eq!(
+            interpreter.env.get("data"),
+            Some(&Value::String("3".into()))
+        );
+        assert!(!exists("out"));
+        assert_eq!(
+            read_dir("").unwrap(),
+            [("data.txt".to_string(), false), ("lib".to_string(), true)]
+        );
+
+        // Nothing touched the disk
+        unmount();
+        assert!(!exists("lib/shapes.lf"));
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize(Path::new("./lib/../lib/util.lf")), "lib/util.lf");
+        assert_eq!(normalize(Path::new("/data.txt")), "data.txt");
+        assert_eq!(normalize(Path::new(".")), "");
+    }
+}
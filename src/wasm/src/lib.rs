@@ -1,75 +1,189 @@
 use wasm_bindgen::prelude::*;
+use loft::analysis::{self, completion, stdlib::StdlibTypes};
 use loft::parser::{InputStream, Parser};
-// Use `loft::runtime::value::Value` fully qualified to avoid ambiguity if any, 
-// and import ToString trait to use `to_string()` method.
-use loft::runtime::value::Value;
-use loft::runtime::Interpreter;
-use loft::runtime::traits::ToString; 
+use loft::{Engine, EngineError};
 use loft::formatter::TokenFormatter; // Import TokenFormatter
+use loft::runtime::limits::ExecutionLimits;
+use loft::runtime::builtins::term::{self, Stream};
+use loft::runtime::vfs;
 use std::cell::RefCell;
+use std::time::Duration;
 
 thread_local! {
     static OUTPUT_BUFFER: RefCell<String> = RefCell::new(String::new());
+    static STDLIB_TYPES: StdlibTypes = analysis::stdlib::load();
+    static SESSION: RefCell<Option<Engine>> = const { RefCell::new(None) };
+    static OUTPUT_CALLBACK: RefCell<Option<js_sys::Function>> = const { RefCell::new(None) };
 }
 
+/// Path the playground's program runs as, so `learn "./util"` finds `util.lf`
+const MAIN_FILE: &str = "main.lf";
+
 #[wasm_bindgen(start)]
 pub fn main() {
     console_error_panic_hook::set_once();
+    // There is no disk in the browser
+    vfs::mount();
+    // Nor a terminal, so everything the term builtin writes goes to `emit`
+    term::set_output_sink(Some(write_output));
 }
 
-fn custom_print(_this: &Value, args: &[Value]) -> loft::runtime::RuntimeResult<Value> {
-    OUTPUT_BUFFER.with(|b| {
-        let mut buf = b.borrow_mut();
-        for (i, arg) in args.iter().enumerate() {
-            if i > 0 {
-                buf.push(' ');
-            }
-            buf.push_str(&arg.to_string());
+/// Text printed by the program goes to the streaming callback when one is
+/// set, and into `OUTPUT_BUFFER` otherwise
+fn emit(text: &str, stream: &str) {
+    let streamed = OUTPUT_CALLBACK.with(|callback| match &*callback.borrow() {
+        Some(callback) => {
+            let _ = callback.call2(
+                &JsValue::NULL,
+                &JsValue::from_str(text),
+                &JsValue::from_str(stream),
+            );
+            true
         }
+        None => false,
     });
-    Ok(Value::Unit)
+    if !streamed {
+        OUTPUT_BUFFER.with(|b| b.borrow_mut().push_str(text));
+    }
 }
 
-fn custom_println(this: &Value, args: &[Value]) -> loft::runtime::RuntimeResult<Value> {
-    custom_print(this, args)?;
-    OUTPUT_BUFFER.with(|b| b.borrow_mut().push('\n'));
-    Ok(Value::Unit)
+fn write_output(text: &str, stream: Stream) {
+    let stream = match stream {
+        Stream::Stdout => "stdout",
+        Stream::Stderr => "stderr",
+    };
+    emit(text, stream);
 }
 
-#[wasm_bindgen]
-pub fn run_code(source: &str) -> String {
+/// Limits for `run_code`, so a program that never ends can't hang the tab
+const DEFAULT_MAX_STEPS: u32 = 50_000_000;
+const DEFAULT_TIME_LIMIT_MS: u32 = 10_000;
+
+/// The message shown for a program that failed
+fn error_message(error: &EngineError) -> String {
+    match error {
+        EngineError::Parse(e) => format!("Parse Error: {}", e),
+        e => format!("Runtime Error: {}", e),
+    }
+}
+
+fn limits(max_steps: u32, max_millis: u32) -> ExecutionLimits {
+    ExecutionLimits {
+        max_steps: (max_steps > 0).then_some(max_steps as u64),
+        max_duration: (max_millis > 0).then(|| Duration::from_millis(max_millis as u64)),
+        ..Default::default()
+    }
+}
+
+fn run(engine: &mut Engine, source: &str) -> String {
     // Reset buffer
     OUTPUT_BUFFER.with(|b| b.borrow_mut().clear());
 
-    let source = source.to_string();
-    let input = InputStream::new("playground", &source);
-    let mut parser = Parser::new(input);
-
-    match parser.parse() {
-        Ok(stmts) => {
-            let mut interpreter = Interpreter::new();
-
-            // Override term.print and term.println
-            if let Some(Value::Builtin(mut term)) = interpreter.env.get("term").cloned() {
-                term.methods.insert("print".to_string(), custom_print);
-                term.methods.insert("println".to_string(), custom_println);
-                let _ = interpreter.env.update("term", Value::Builtin(term));
-            }
-
-            match interpreter.eval_program(stmts) {
-                Ok(_) => OUTPUT_BUFFER.with(|b| b.borrow().clone()),
-                Err(e) => {
-                    let output = OUTPUT_BUFFER.with(|b| b.borrow().clone());
-                    format!("{}\nRuntime Error: {}", output, e)
-                }
-            }
+    match engine.eval_named(MAIN_FILE, source) {
+        Ok(_) => OUTPUT_BUFFER.with(|b| b.borrow().clone()),
+        Err(e @ EngineError::Parse(_)) => error_message(&e),
+        Err(e) => {
+            let output = OUTPUT_BUFFER.with(|b| b.borrow().clone());
+            format!("{}\n{}", output, error_message(&e))
         }
-        Err(e) => format!("Parse Error: {}", e),
     }
 }
 
+/// Run a program on a fresh interpreter, stopping it after
+/// `DEFAULT_MAX_STEPS` steps or `DEFAULT_TIME_LIMIT_MS` milliseconds
+#[wasm_bindgen]
+pub fn run_code(source: &str) -> String {
+    let mut engine = Engine::new().with_limits(limits(DEFAULT_MAX_STEPS, DEFAULT_TIME_LIMIT_MS));
+    run(&mut engine, source)
+}
+
+/// Run a program like `run_code`, but instead of returning the output call
+/// `callback(text, stream)` every time the program writes, with `stream` set
+/// to `"stdout"` or, for `term.error` and `term.warn`, `"stderr"`. A runtime or
+/// parse error is passed the same way on `"stderr"`. Progress bars send each
+/// redraw as `\r` followed by the bar. Returns whether the program ran without
+/// errors.
+#[wasm_bindgen]
+pub fn run_code_streaming(source: &str, callback: js_sys::Function) -> bool {
+    OUTPUT_CALLBACK.with(|c| *c.borrow_mut() = Some(callback));
+
+    let mut engine = Engine::new().with_limits(limits(DEFAULT_MAX_STEPS, DEFAULT_TIME_LIMIT_MS));
+    let result = engine.eval_named(MAIN_FILE, source);
+    if let Err(error) = &result {
+        emit(&(error_message(error) + "\n"), "stderr");
+    }
+
+    OUTPUT_CALLBACK.with(|c| *c.borrow_mut() = None);
+    result.is_ok()
+}
+
+/// Run code on the playground's session interpreter, which keeps what earlier
+/// runs defined until `reset_interpreter` is called. The program is stopped
+/// with an "Execution limit exceeded" error after `max_steps` statements and
+/// expressions or `max_millis` milliseconds; 0 means no limit.
+#[wasm_bindgen]
+pub fn run_code_with_limits(source: &str, max_steps: u32, max_millis: u32) -> String {
+    SESSION.with(|session| {
+        let mut session = session.borrow_mut();
+        let engine = session.get_or_insert_with(Engine::new);
+        engine
+            .interpreter_mut()
+            .set_limits(limits(max_steps, max_millis));
+        run(engine, source)
+    })
+}
+
+/// Forget everything the session interpreter has defined
+#[wasm_bindgen]
+pub fn reset_interpreter() {
+    SESSION.with(|session| {
+        if let Some(engine) = session.borrow_mut().as_mut() {
+            engine.interpreter_mut().reset();
+        }
+    });
+    OUTPUT_BUFFER.with(|b| b.borrow_mut().clear());
+}
+
+/// Add or replace a file of the in-memory file system, which other files can
+/// `learn` and the `fs` builtin can read
+#[wasm_bindgen]
+pub fn write_file(path: &str, contents: &str) -> Result<(), String> {
+    vfs::write(path, contents).map_err(|e| e.to_string())
+}
+
+#[wasm_bindgen]
+pub fn remove_file(path: &str) -> Result<(), String> {
+    vfs::remove_file(path).map_err(|e| e.to_string())
+}
+
 #[wasm_bindgen]
 pub fn format_code(source: &str) -> Result<String, String> {
     let formatter = TokenFormatter::new();
     formatter.format(source)
 }
+
+/// Syntax errors and semantic diagnostics as a JSON array, with the same
+/// checks the language server runs. Lines and columns are zero-based.
+#[wasm_bindgen]
+pub fn check_code(source: &str) -> String {
+    let diagnostics =
+        STDLIB_TYPES.with(|types| analysis::check_source("playground", source, types));
+    serde_json::to_string(&diagnostics).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// The parsed program as a JSON array of statements, or the parse error
+#[wasm_bindgen]
+pub fn parse_ast(source: &str) -> Result<String, String> {
+    let source = source.to_string();
+    let stmts = Parser::new(InputStream::new("playground", &source))
+        .parse()
+        .map_err(|e| format!("Parse Error: {}", e))?;
+    serde_json::to_string(&stmts).map_err(|e| e.to_string())
+}
+
+/// Completions at a zero-based line and column as a JSON array
+#[wasm_bindgen]
+pub fn completions(source: &str, line: usize, col: usize) -> String {
+    let items = STDLIB_TYPES.with(|types| completion::completions(source, line, col, types));
+    serde_json::to_string(&items).unwrap_or_else(|_| "[]".to_string())
+}
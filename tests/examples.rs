@@ -98,6 +98,11 @@ fn example_trait_example() {
     run_example("examples/trait_example.lf", None);
 }
 
+#[test]
+fn example_try_catch() {
+    run_example("examples/try_catch.lf", None);
+}
+
 // ---------------------------------------------------------------------------
 // Examples that require external resources (run with --ignored)
 // ---------------------------------------------------------------------------
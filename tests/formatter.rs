@@ -0,0 +1,67 @@
+//! Formatting must be stable: formatting already formatted code changes nothing.
+
+use loft::formatter::config::{BraceStyle, TrailingCommas};
+use loft::formatter::{FormatConfig, TokenFormatter};
+use std::path::{Path, PathBuf};
+
+fn loft_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in std::fs::read_dir(dir).unwrap().flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            loft_files(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "lf") {
+            files.push(path);
+        }
+    }
+}
+
+fn configs() -> Vec<FormatConfig> {
+    vec![
+        FormatConfig::default(),
+        FormatConfig {
+            max_width: 40,
+            ..FormatConfig::default()
+        },
+        FormatConfig {
+            indent_width: 2,
+            max_width: 60,
+            trailing_commas: TrailingCommas::Always,
+            brace_style: BraceStyle::NextLine,
+            ..FormatConfig::default()
+        },
+        FormatConfig {
+            use_tabs: true,
+            max_width: 50,
+            trailing_commas: TrailingCommas::Never,
+            ..FormatConfig::default()
+        },
+    ]
+}
+
+#[test]
+fn test_format_examples_is_idempotent() {
+    let mut files = Vec::new();
+    loft_files(
+        &Path::new(env!("CARGO_MANIFEST_DIR")).join("examples"),
+        &mut files,
+    );
+    assert!(!files.is_empty());
+
+    for config in configs() {
+        let formatter = TokenFormatter::with_config(config.clone());
+        for file in &files {
+            let source = std::fs::read_to_string(file).unwrap();
+            let once = formatter
+                .format(&source)
+                .unwrap_or_else(|e| panic!("{}: {}", file.display(), e));
+            let twice = formatter.format(&once).unwrap();
+            assert_eq!(
+                once,
+                twice,
+                "formatting {} twice with {:?} changed it",
+                file.display(),
+                config
+            );
+        }
+    }
+}
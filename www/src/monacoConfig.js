@@ -5,6 +5,7 @@ export const loftLanguage = {
 
   keywords: [
     'if', 'else', 'while', 'for', 'in', 'return', 'break', 'continue', 'match',
+    'try', 'catch', 'finally', 'throw', 'defer',
     'let', 'const', 'mut', 'fn', 'def', 'struct', 'enum', 'impl', 'trait', 
     'async', 'await', 'lazy', 'learn', 'teach', 'print', 'println'
   ],
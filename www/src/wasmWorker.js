@@ -1,17 +1,47 @@
-import init, { run_code, format_code } from './wasm/loft_wasm';
+import init, {
+  run_code,
+  run_code_streaming,
+  run_code_with_limits,
+  reset_interpreter,
+  write_file,
+  remove_file,
+  format_code,
+  check_code,
+  parse_ast,
+  completions,
+} from './wasm/loft_wasm';
 
 init()
   .then(() => self.postMessage({ id: '__ready' }))
   .catch(err => self.postMessage({ id: '__ready', error: String(err) }));
 
 self.onmessage = ({ data }) => {
-  const { id, type, code } = data;
+  const { id, type, code, path, line, col, maxSteps = 0, maxMillis = 0 } = data;
   try {
     let result;
     if (type === 'run') {
       result = run_code(code);
+    } else if (type === 'runStreaming') {
+      // Output is posted as it is printed, before the final result
+      result = run_code_streaming(code, (chunk, stream) =>
+        self.postMessage({ id, chunk, stream })
+      );
+    } else if (type === 'runSession') {
+      result = run_code_with_limits(code, maxSteps, maxMillis);
+    } else if (type === 'reset') {
+      result = reset_interpreter();
+    } else if (type === 'writeFile') {
+      result = write_file(path, code);
+    } else if (type === 'removeFile') {
+      result = remove_file(path);
     } else if (type === 'format') {
       result = format_code(code);
+    } else if (type === 'check') {
+      result = JSON.parse(check_code(code));
+    } else if (type === 'ast') {
+      result = JSON.parse(parse_ast(code));
+    } else if (type === 'complete') {
+      result = JSON.parse(completions(code, line, col));
     }
     self.postMessage({ id, result });
   } catch (err) {